@@ -0,0 +1,241 @@
+use async_runtimes_cmp::combinators::buffer;
+use async_runtimes_cmp::combinators::chain;
+use async_runtimes_cmp::combinators::dispatch::{self, Merge};
+use async_runtimes_cmp::combinators::join;
+use async_runtimes_cmp::combinators::join_all::{self, Join};
+use async_runtimes_cmp::combinators::join_set;
+use async_runtimes_cmp::combinators::merge;
+use async_runtimes_cmp::combinators::select::{self, Sources};
+use async_runtimes_cmp::combinators::try_join::{self, Work};
+use async_runtimes_cmp::combinators::unordered;
+use async_runtimes_cmp::config::{env_list, env_or};
+use async_runtimes_cmp::harness::Runner;
+use std::time::Duration;
+
+/// Number of selects per run.
+const SELECT_ROUNDS: usize = 10_000_000;
+/// Numbers of channels one dispatcher receives from.
+const DISPATCH_CHANNELS: &[usize] = &[16, 64];
+/// Messages dispatched per run, one at a time.
+const DISPATCH_MESSAGES: usize = 100_000;
+/// Numbers of futures joined at once: 2, 4, 8 or 16.
+const JOIN_ARITIES: &[usize] = &[2, 4, 8, 16];
+/// Number of joins per run.
+const JOIN_ROUNDS: usize = 1_000_000;
+/// Numbers of futures joined at once by `join_all` and `FuturesUnordered`.
+const JOIN_ALL_FUTURES: &[usize] = &[10, 100, 1_000, 10_000, 100_000];
+/// Most futures joined by polling all of them on every wakeup, which takes
+/// quadratic time.
+const JOIN_ALL_POLL_ALL_MAX: usize = 10_000;
+/// Futures driven by one `FuturesUnordered`.
+const UNORDERED_FUTURES: usize = 50_000;
+/// Longest sleep of the sleeping futures.
+const UNORDERED_SLEEP_MS: u64 = 10;
+/// Tasks spawned, drained and cancelled per run.
+const SPAWN_SET_TASKS: usize = 100_000;
+/// Fallible futures joined per run, of which one fails.
+const TRY_JOIN_FUTURES: usize = 16;
+/// Units of work each future does unless stopped.
+const TRY_JOIN_UNITS: usize = 1_000;
+/// Time spun per unit of work.
+const TRY_JOIN_UNIT_US: u64 = 10;
+/// Units done by the failing future before it fails.
+const TRY_JOIN_FAIL_AFTER: usize = 10;
+/// Joins per scenario.
+const TRY_JOIN_RUNS: usize = 200;
+/// Concurrency limits of `buffer_unordered`.
+const BUFFER_LIMITS: &[usize] = &[1, 8, 64, 512];
+/// Items processed per run.
+const BUFFER_ITEMS: usize = 5_000;
+/// Time each item waits.
+const BUFFER_LATENCY_MS: u64 = 1;
+/// Items pushed through the stream combinator chain.
+const CHAIN_ITEMS: usize = 10_000_000;
+/// Numbers of busy streams merged into one.
+const MERGE_STREAMS: &[usize] = &[2, 8, 64, 512];
+/// Events sent over all streams per run.
+const MERGE_EVENTS: usize = 1_000_000;
+
+fn bench_select(runner: &Runner) {
+    let rounds = env_or("SELECT_ROUNDS", SELECT_ROUNDS);
+
+    for &(name, sources) in &[
+        ("both_ready", Sources::BothReady),
+        ("pending_ready", Sources::PendingReady),
+    ] {
+        runner.run(&format!("select_tokio/{}", name), || {
+            select::tokio(sources, rounds)
+        });
+        runner.run(&format!("select_futures/{}", name), || {
+            select::futures(sources, rounds)
+        });
+        runner.run(&format!("select_poll_fn/{}", name), || {
+            select::poll_fn(sources, rounds)
+        });
+    }
+}
+
+fn bench_dispatch(runner: &Runner) {
+    let messages = env_or("DISPATCH_MESSAGES", DISPATCH_MESSAGES);
+
+    for channels in env_list("DISPATCH_CHANNELS", DISPATCH_CHANNELS) {
+        for &(name, merge) in &[
+            ("stream_map", Merge::StreamMap),
+            ("select_all", Merge::SelectAll),
+            ("poll_fn", Merge::PollFn),
+        ] {
+            runner.run(&format!("dispatch_{}_tokio/{}", name, channels), || {
+                dispatch::on_tokio(merge, channels, messages)
+            });
+            runner.run(&format!("dispatch_{}_async_std/{}", name, channels), || {
+                dispatch::on_async_std(merge, channels, messages)
+            });
+        }
+    }
+}
+
+fn bench_join(runner: &Runner) {
+    let rounds = env_or("JOIN_ROUNDS", JOIN_ROUNDS);
+
+    for arity in env_list("JOIN_ARITIES", JOIN_ARITIES) {
+        runner.run(&format!("join_tokio/{}", arity), || {
+            join::tokio(arity, rounds)
+        });
+        runner.run(&format!("join_futures/{}", arity), || {
+            join::futures(arity, rounds)
+        });
+        runner.run(&format!("join_sequential/{}", arity), || {
+            join::sequential(arity, rounds)
+        });
+    }
+}
+
+fn bench_join_all(runner: &Runner) {
+    let poll_all_max = env_or("JOIN_ALL_POLL_ALL_MAX", JOIN_ALL_POLL_ALL_MAX);
+
+    for futures in env_list("JOIN_ALL_FUTURES", JOIN_ALL_FUTURES) {
+        for &(name, join) in &[
+            ("futures", Join::JoinAll),
+            ("unordered", Join::Unordered),
+            ("poll_all", Join::PollAll),
+        ] {
+            if let Join::PollAll = join {
+                if futures > poll_all_max {
+                    continue;
+                }
+            }
+            runner.run(&format!("join_all_{}_tokio/{}", name, futures), || {
+                join_all::on_tokio(join, futures)
+            });
+            runner.run(&format!("join_all_{}_async_std/{}", name, futures), || {
+                join_all::on_async_std(join, futures)
+            });
+        }
+    }
+}
+
+fn bench_unordered(runner: &Runner) {
+    let futures = env_or("UNORDERED_FUTURES", UNORDERED_FUTURES);
+    let sleep = Duration::from_millis(env_or("UNORDERED_SLEEP_MS", UNORDERED_SLEEP_MS));
+
+    runner.run("unordered_tokio", || unordered::on_tokio(futures, sleep));
+    runner.run("unordered_async_std", || {
+        unordered::on_async_std(futures, sleep)
+    });
+}
+
+fn bench_join_set(runner: &Runner) {
+    let tasks = env_or("SPAWN_SET_TASKS", SPAWN_SET_TASKS);
+
+    runner.run("spawn_set_join_set_tokio", || join_set::join_set(tasks));
+    runner.run("spawn_set_unordered_tokio", || {
+        join_set::tokio_unordered(tasks)
+    });
+    runner.run("spawn_set_collect_async_std", || {
+        join_set::async_std_collect(tasks)
+    });
+    runner.run("spawn_set_unordered_async_std", || {
+        join_set::async_std_unordered(tasks)
+    });
+}
+
+fn bench_try_join(runner: &Runner) {
+    let work = Work {
+        futures: env_or("TRY_JOIN_FUTURES", TRY_JOIN_FUTURES),
+        units: env_or("TRY_JOIN_UNITS", TRY_JOIN_UNITS),
+        unit: Duration::from_micros(env_or("TRY_JOIN_UNIT_US", TRY_JOIN_UNIT_US)),
+        fail_after: env_or("TRY_JOIN_FAIL_AFTER", TRY_JOIN_FAIL_AFTER),
+    };
+    let runs = env_or("TRY_JOIN_RUNS", TRY_JOIN_RUNS);
+
+    for &(name, join) in &[
+        ("try_join_all", try_join::Join::TryJoinAll),
+        ("unordered", try_join::Join::Unordered),
+        ("spawned", try_join::Join::Spawned),
+        ("detached", try_join::Join::Detached),
+    ] {
+        runner.run(&format!("try_join_{}_tokio", name), || {
+            try_join::on_tokio(join, work, runs)
+        });
+        runner.run(&format!("try_join_{}_async_std", name), || {
+            try_join::on_async_std(join, work, runs)
+        });
+    }
+}
+
+fn bench_buffer(runner: &Runner) {
+    let items = env_or("BUFFER_ITEMS", BUFFER_ITEMS);
+    let latency = Duration::from_millis(env_or("BUFFER_LATENCY_MS", BUFFER_LATENCY_MS));
+
+    for limit in env_list("BUFFER_LIMITS", BUFFER_LIMITS) {
+        runner.run(&format!("buffer_unordered_tokio/{}", limit), || {
+            buffer::on_tokio(limit, items, latency)
+        });
+        runner.run(&format!("buffer_unordered_async_std/{}", limit), || {
+            buffer::on_async_std(limit, items, latency)
+        });
+    }
+}
+
+fn bench_chain(runner: &Runner) {
+    let items = env_or("CHAIN_ITEMS", CHAIN_ITEMS);
+
+    runner.run("chain_futures", || chain::futures(items));
+    runner.run("chain_async_std", || chain::async_std(items));
+    runner.run("chain_tokio_stream", || chain::tokio_stream(items));
+    runner.run("chain_iterator", || chain::iterator(items));
+}
+
+fn bench_merge(runner: &Runner) {
+    let events = env_or("MERGE_EVENTS", MERGE_EVENTS);
+
+    for streams in env_list("MERGE_STREAMS", MERGE_STREAMS) {
+        for &(name, how) in &[
+            ("select_all", merge::Merge::SelectAll),
+            ("stream_map", merge::Merge::StreamMap),
+            ("select_tree", merge::Merge::SelectTree),
+        ] {
+            runner.run(&format!("merge_{}_tokio/{}", name, streams), || {
+                merge::on_tokio(how, streams, events)
+            });
+            runner.run(&format!("merge_{}_async_std/{}", name, streams), || {
+                merge::on_async_std(how, streams, events)
+            });
+        }
+    }
+}
+
+fn main() {
+    let runner = Runner::from_args();
+
+    bench_select(&runner);
+    bench_dispatch(&runner);
+    bench_join(&runner);
+    bench_join_all(&runner);
+    bench_unordered(&runner);
+    bench_join_set(&runner);
+    bench_try_join(&runner);
+    bench_buffer(&runner);
+    bench_chain(&runner);
+    bench_merge(&runner);
+}
@@ -0,0 +1,27 @@
+use async_runtimes_cmp::footprint::{self, VARIANTS};
+use async_runtimes_cmp::harness::Runner;
+
+fn bench_binary_size(runner: &Runner) {
+    for variant in VARIANTS.iter().copied() {
+        runner.run(&format!("binary_size_{}", variant.name), || {
+            footprint::binary_size(variant)
+                .unwrap_or_else(|err| panic!("cannot build {}: {}", variant.name, err))
+        });
+    }
+}
+
+fn bench_build_times(runner: &Runner) {
+    for variant in VARIANTS.iter().copied() {
+        runner.run(&format!("build_time_{}", variant.name), || {
+            footprint::build_times(variant)
+                .unwrap_or_else(|err| panic!("cannot build {}: {}", variant.name, err))
+        });
+    }
+}
+
+fn main() {
+    let runner = Runner::from_args();
+
+    bench_binary_size(&runner);
+    bench_build_times(&runner);
+}
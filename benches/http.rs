@@ -0,0 +1,195 @@
+use async_runtimes_cmp::config::{env_list, env_or};
+use async_runtimes_cmp::harness::{LoadReport, Runner};
+use async_runtimes_cmp::http::server::{self, Server};
+use async_runtimes_cmp::http::{client, crawl, load, multiplex, open_loop, upload};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Number of requests kept in flight at the same time.
+const CONCURRENCY: usize = 32;
+
+/// Number of requests sent to each server in a measured run.
+const REQUESTS: usize = 20_000;
+
+/// Number of HTTP/2 streams in flight at the same time on the single connection.
+const STREAMS: usize = 100;
+
+/// Number of pages the crawler fetches at the same time, and of threads in the
+/// thread pool baseline.
+const CRAWL_CONCURRENCY: usize = 64;
+
+/// Number of distinct pages fetched per crawl.
+const CRAWL_PAGES: usize = 5_000;
+
+/// Time the crawled site takes to answer each request, in milliseconds.
+const CRAWL_DELAY_MS: u64 = 2;
+
+/// Request rates offered by the open-loop clients, each swept in its own run.
+const OPEN_LOOP_RATES: &[u64] = &[5_000, 10_000, 20_000];
+
+/// How long requests are offered for in each open-loop run, in milliseconds.
+const OPEN_LOOP_DURATION_MS: u64 = 2_000;
+
+/// Request rate offered in the sustained runs.
+const SUSTAINED_RATE: u64 = 5_000;
+
+/// How long requests are offered for in each sustained run, in seconds.
+const SUSTAINED_DURATION_S: u64 = 300;
+
+/// Length of the windows the latencies of a sustained run are reported over, in seconds.
+const SUSTAINED_WINDOW_S: u64 = 10;
+
+/// Size of each uploaded object in bytes.
+const UPLOAD_BYTES: usize = 64 * 1024 * 1024;
+
+/// Size of each part of a multipart upload in bytes.
+const UPLOAD_PART: usize = 1024 * 1024;
+
+/// Number of parts of an upload sent at the same time.
+const UPLOAD_CONCURRENCY: usize = 8;
+
+/// Number of multipart uploads per run.
+const UPLOAD_COUNT: usize = 8;
+
+/// Starts `start`, warms it up and hammers it with `requests` requests.
+fn bench_server(start: fn() -> Server, concurrency: usize, requests: usize) -> LoadReport {
+    let server = start();
+    load::hammer(server.addr(), concurrency, requests / 10);
+    load::hammer(server.addr(), concurrency, requests)
+}
+
+/// Issues `requests` requests through `client` against a warmed up hyper server.
+fn bench_client(
+    client: fn(SocketAddr, usize, usize) -> LoadReport,
+    concurrency: usize,
+    requests: usize,
+) -> LoadReport {
+    let server = server::hyper();
+    load::hammer(server.addr(), concurrency, requests / 10);
+    client(server.addr(), concurrency, requests)
+}
+
+/// Crawls a slow site whose pages take `CRAWL_DELAY_MS` to arrive.
+fn bench_crawl(runner: &Runner) {
+    let concurrency = env_or("CRAWL_CONCURRENCY", CRAWL_CONCURRENCY);
+    let pages = env_or("CRAWL_PAGES", CRAWL_PAGES);
+    let delay = Duration::from_millis(env_or("CRAWL_DELAY_MS", CRAWL_DELAY_MS));
+    let site = crawl::site(delay);
+
+    runner.run("http_crawl_buffer_unordered_tokio", || {
+        crawl::tokio_buffer_unordered(site.addr(), concurrency, pages)
+    });
+    runner.run("http_crawl_semaphore_tokio", || {
+        crawl::tokio_semaphore(site.addr(), concurrency, pages)
+    });
+    runner.run("http_crawl_buffer_unordered_async_std", || {
+        crawl::async_std_buffer_unordered(site.addr(), concurrency, pages)
+    });
+    runner.run("http_crawl_semaphore_async_std", || {
+        crawl::async_std_semaphore(site.addr(), concurrency, pages)
+    });
+    runner.run("http_crawl_thread_pool", || {
+        crawl::thread_pool(site.addr(), concurrency, pages)
+    });
+}
+
+/// Offers requests at each rate to a warmed up hyper server, regardless of
+/// how many are still outstanding.
+fn bench_open_loop(runner: &Runner) {
+    let duration = env_or("OPEN_LOOP_DURATION_MS", OPEN_LOOP_DURATION_MS);
+
+    for rate in env_list("OPEN_LOOP_RATES", OPEN_LOOP_RATES) {
+        let requests = (rate * duration / 1_000) as usize;
+        for &(name, client) in &[
+            ("hyper", open_loop::hyper as fn(_, _, _) -> _),
+            ("surf", open_loop::surf),
+        ] {
+            runner.run(&format!("http_open_loop_{}/{}", name, rate), || {
+                let server = server::hyper();
+                load::hammer(server.addr(), CONCURRENCY, requests / 10);
+                client(server.addr(), rate as f64, requests)
+            });
+        }
+    }
+}
+
+/// Offers requests at a fixed rate to a warmed up hyper server for minutes,
+/// and prints the latencies of every window of the run before its report.
+/// Only runs with `SUSTAINED` set, as it takes that long.
+fn bench_sustained(runner: &Runner) {
+    if std::env::var_os("SUSTAINED").is_none() {
+        return;
+    }
+    let rate = env_or("SUSTAINED_RATE", SUSTAINED_RATE);
+    let duration = env_or("SUSTAINED_DURATION_S", SUSTAINED_DURATION_S);
+    let window = Duration::from_secs(env_or("SUSTAINED_WINDOW_S", SUSTAINED_WINDOW_S));
+    let requests = (rate * duration) as usize;
+
+    for &(name, client) in &[
+        ("hyper", open_loop::hyper_sustained as fn(_, _, _, _) -> _),
+        ("surf", open_loop::surf_sustained),
+    ] {
+        let scenario = format!("http_sustained_{}/{}", name, rate);
+        runner.run(&scenario, || {
+            let server = server::hyper();
+            load::hammer(server.addr(), CONCURRENCY, requests / 100);
+            let report = client(server.addr(), rate as f64, requests, window);
+            for over in &report.windows {
+                let at = format!("{}@{}s", scenario, over.start.as_secs());
+                println!("{:<48} {}", at, over);
+            }
+            report
+        });
+    }
+}
+
+fn bench_upload(runner: &Runner) {
+    let size = env_or("UPLOAD_BYTES", UPLOAD_BYTES);
+    let part = env_or("UPLOAD_PART", UPLOAD_PART);
+    let concurrency = env_or("UPLOAD_CONCURRENCY", UPLOAD_CONCURRENCY);
+    let uploads = env_or("UPLOAD_COUNT", UPLOAD_COUNT);
+    let store = upload::store();
+
+    runner.run("http_upload_multipart_reqwest", || {
+        upload::reqwest(store.addr(), size, part, concurrency, uploads)
+    });
+    runner.run("http_upload_multipart_surf", || {
+        upload::surf(store.addr(), size, part, concurrency, uploads)
+    });
+}
+
+fn main() {
+    let runner = Runner::from_args();
+    let concurrency = env_or("HTTP_CONCURRENCY", CONCURRENCY);
+    let requests = env_or("HTTP_REQUESTS", REQUESTS);
+    let streams = env_or("HTTP2_STREAMS", STREAMS);
+
+    runner.run("http_server_hyper", || {
+        bench_server(server::hyper, concurrency, requests)
+    });
+    runner.run("http_server_tide", || {
+        bench_server(server::tide, concurrency, requests)
+    });
+    runner.run("http_server_actix_web", || {
+        bench_server(server::actix_web, concurrency, requests)
+    });
+    runner.run("http_client_reqwest", || {
+        bench_client(client::reqwest, concurrency, requests)
+    });
+    runner.run("http_client_surf", || {
+        bench_client(client::surf, concurrency, requests)
+    });
+    runner.run("http_client_hyper", || {
+        bench_client(client::hyper, concurrency, requests)
+    });
+    runner.run("http2_multiplex_tokio", || {
+        multiplex::tokio(streams, requests)
+    });
+    runner.run("http2_multiplex_async_std", || {
+        multiplex::async_std(streams, requests)
+    });
+    bench_crawl(&runner);
+    bench_open_loop(&runner);
+    bench_sustained(&runner);
+    bench_upload(&runner);
+}
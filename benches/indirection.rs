@@ -0,0 +1,94 @@
+use async_runtimes_cmp::config::{env_list, env_or};
+use async_runtimes_cmp::harness::Runner;
+use async_runtimes_cmp::indirection::boxed::{self, Erasure};
+use async_runtimes_cmp::indirection::recursion::{self, Descent};
+use async_runtimes_cmp::indirection::sizes;
+use async_runtimes_cmp::indirection::traits::{self, Dispatch};
+
+/// Depths of the descents, each swept in its own run.
+const RECURSION_DEPTHS: &[u32] = &[1, 10, 100, 1_000];
+
+/// Number of levels descended per run, over as many descents as it takes.
+const RECURSION_LEVELS: usize = 10_000_000;
+
+/// Number of futures awaited per run.
+const BOXED_FUTURES: usize = 1_000_000;
+
+/// Number of calls of the async method per run.
+const TRAIT_CALLS: usize = 10_000_000;
+
+fn bench_recursion(runner: &Runner) {
+    let levels = env_or("RECURSION_LEVELS", RECURSION_LEVELS);
+
+    for depth in env_list("RECURSION_DEPTHS", RECURSION_DEPTHS) {
+        for &(name, descent) in &[
+            ("recursive", Descent::Recursive),
+            ("iterative", Descent::Iterative),
+        ] {
+            runner.run(&format!("recursion_{}_{}_tokio", name, depth), || {
+                recursion::tokio(descent, depth, levels)
+            });
+            runner.run(&format!("recursion_{}_{}_async_std", name, depth), || {
+                recursion::async_std(descent, depth, levels)
+            });
+            runner.run(&format!("recursion_{}_{}_smol", name, depth), || {
+                recursion::smol(descent, depth, levels)
+            });
+        }
+    }
+}
+
+fn bench_boxed(runner: &Runner) {
+    let futures = env_or("BOXED_FUTURES", BOXED_FUTURES);
+
+    for &(name, erasure) in &[
+        ("concrete", Erasure::Concrete),
+        ("boxed", Erasure::Boxed),
+        ("channel", Erasure::Channel),
+    ] {
+        runner.run(&format!("boxed_{}_tokio", name), || {
+            boxed::tokio(erasure, futures)
+        });
+        runner.run(&format!("boxed_{}_async_std", name), || {
+            boxed::async_std(erasure, futures)
+        });
+        runner.run(&format!("boxed_{}_smol", name), || {
+            boxed::smol(erasure, futures)
+        });
+    }
+}
+
+fn bench_traits(runner: &Runner) {
+    let calls = env_or("TRAIT_CALLS", TRAIT_CALLS);
+
+    for &(name, dispatch) in &[
+        ("concrete", Dispatch::Concrete),
+        ("native", Dispatch::Native),
+        ("async_trait", Dispatch::AsyncTrait),
+    ] {
+        runner.run(&format!("trait_{}_tokio", name), || {
+            traits::tokio(dispatch, calls)
+        });
+        runner.run(&format!("trait_{}_async_std", name), || {
+            traits::async_std(dispatch, calls)
+        });
+        runner.run(&format!("trait_{}_smol", name), || {
+            traits::smol(dispatch, calls)
+        });
+    }
+}
+
+fn bench_sizes(runner: &Runner) {
+    for (name, size) in sizes::all() {
+        runner.run(&format!("size_{}", name), || size);
+    }
+}
+
+fn main() {
+    let runner = Runner::from_args();
+
+    bench_recursion(&runner);
+    bench_boxed(&runner);
+    bench_traits(&runner);
+    bench_sizes(&runner);
+}
@@ -0,0 +1,551 @@
+use async_runtimes_cmp::chat;
+use async_runtimes_cmp::codec;
+use async_runtimes_cmp::compat::{self, Host};
+use async_runtimes_cmp::compress;
+use async_runtimes_cmp::config::{env_list, env_or};
+use async_runtimes_cmp::dns;
+use async_runtimes_cmp::fanout::{self, Latency};
+use async_runtimes_cmp::harness::{Runner, ServerProcess};
+use async_runtimes_cmp::hedge;
+use async_runtimes_cmp::idle;
+use async_runtimes_cmp::pool;
+use async_runtimes_cmp::proxy;
+use async_runtimes_cmp::quic;
+use async_runtimes_cmp::rpc::{grpc, length_prefixed};
+use async_runtimes_cmp::rtt;
+use async_runtimes_cmp::sim::{self, Faults, Simulation};
+use async_runtimes_cmp::tls::{bulk, handshake};
+use async_runtimes_cmp::wan::{self, Link};
+use async_runtimes_cmp::ws;
+use std::env;
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+
+/// Number of QUIC streams in flight at the same time on the single connection.
+const QUIC_STREAMS: usize = 32;
+
+/// Number of messages echoed per QUIC run.
+const QUIC_ROUNDS: usize = 5_000;
+
+/// Size of each echoed QUIC message in bytes.
+const QUIC_PAYLOAD: usize = 16 * 1024;
+
+/// Number of RPCs in flight at the same time.
+const RPC_CONCURRENCY: usize = 32;
+
+/// Number of RPCs per run.
+const RPC_REQUESTS: usize = 20_000;
+
+/// Size of each RPC message payload in bytes.
+const RPC_PAYLOAD: usize = 128;
+
+/// Number of WebSocket connections echoing frames at the same time.
+const WS_CONNECTIONS: usize = 32;
+
+/// Number of WebSocket frames echoed per run.
+const WS_FRAMES: usize = 20_000;
+
+/// Size of each WebSocket frame in bytes.
+const WS_FRAME_SIZE: usize = 1024;
+
+/// Number of clients performing TLS handshakes at the same time.
+const TLS_CONCURRENCY: usize = 8;
+
+/// Number of TLS handshakes per run.
+const TLS_HANDSHAKES: usize = 2_000;
+
+/// Number of TLS sessions uploading at the same time in the bulk transfer.
+const TLS_BULK_CONNECTIONS: usize = 4;
+
+/// Number of bytes uploaded over TLS per bulk transfer run.
+const TLS_BULK_BYTES: usize = 256 * 1024 * 1024;
+
+/// Size of each write in the TLS bulk transfer.
+const TLS_BULK_CHUNK: usize = 64 * 1024;
+
+/// Number of DNS lookups in flight at the same time.
+const DNS_CONCURRENCY: usize = 32;
+
+/// Number of distinct names resolved per DNS run.
+const DNS_NAMES: usize = 10_000;
+
+/// Number of client connections relayed at the same time.
+const PROXY_CONNECTIONS: usize = 16;
+
+/// Number of bytes sent through the relay per run, echoed back as many.
+const PROXY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Size of each client write to the relay.
+const PROXY_CHUNK: usize = 16 * 1024;
+
+/// Numbers of connected chat clients, each swept in its own run.
+const CHAT_CLIENTS: &[usize] = &[10, 50, 200];
+
+/// Number of rounds in which every chat client sends one message.
+const CHAT_ROUNDS: usize = 20;
+
+/// Number of tasks sharing the connection pool.
+const POOL_TASKS: usize = 256;
+
+/// Maximum number of connections in the pool.
+const POOL_SIZE: usize = 16;
+
+/// Number of checkouts per pool run.
+const POOL_REQUESTS: usize = 100_000;
+
+/// Number of idle connections held open, each needing two file descriptors.
+const IDLE_CONNECTIONS: usize = 10_000;
+
+/// Number of keepalive pings each idle connection sends.
+const IDLE_ROUNDS: usize = 5;
+
+/// Time between two keepalive pings on the same connection, in milliseconds.
+const IDLE_INTERVAL_MS: u64 = 1_000;
+
+/// Number of payload bytes streamed as frames per codec run.
+const CODEC_BYTES: usize = 64 * 1024 * 1024;
+
+/// Size of each frame in the small-frame codec runs.
+const CODEC_SMALL_FRAME: usize = 64;
+
+/// Size of each frame in the large-frame codec runs.
+const CODEC_LARGE_FRAME: usize = 64 * 1024;
+
+/// Number of one-byte pings per round-trip run.
+const RTT_PINGS: usize = 100_000;
+
+/// One-way delay of the simulated wide-area link, in milliseconds.
+const WAN_DELAY_MS: u64 = 20;
+
+/// Largest deviation from the link delay, in milliseconds.
+const WAN_JITTER_MS: u64 = 5;
+
+/// Number of connections with a request in flight across the simulated link.
+const WAN_CONCURRENCY: usize = 256;
+
+/// Number of requests sent across the simulated link per run.
+const WAN_REQUESTS: usize = 10_000;
+
+/// Seed of the deterministic network simulation.
+const SIM_SEED: u64 = 1;
+
+/// Largest latency of a message on a simulated link, in milliseconds.
+const SIM_MAX_LATENCY_MS: u64 = 10;
+
+/// Period of the network stalls injected into the simulation, in milliseconds.
+const SIM_FAULT_EVERY_MS: u64 = 1_000;
+
+/// Length of each injected network stall, in milliseconds.
+const SIM_FAULT_LENGTH_MS: u64 = 100;
+
+/// Numbers of backends every scatter-gather request fans out to.
+const FANOUT_WIDTHS: &[usize] = &[1, 4, 16, 64];
+
+/// Number of scatter-gather requests in flight at the same time.
+const FANOUT_CONCURRENCY: usize = 32;
+
+/// Number of scatter-gather requests per run.
+const FANOUT_REQUESTS: usize = 5_000;
+
+/// Time a backend usually takes to answer, in milliseconds.
+const FANOUT_LATENCY_MS: u64 = 1;
+
+/// Time a slow backend call takes, in milliseconds.
+const FANOUT_SLOW_MS: u64 = 10;
+
+/// One backend call in this many is slow, none if 0.
+const FANOUT_SLOW_ONE_IN: u64 = 100;
+
+/// Number of hedged callers running at the same time.
+const HEDGE_CONCURRENCY: usize = 32;
+
+/// Number of hedged calls per run.
+const HEDGE_REQUESTS: usize = 10_000;
+
+/// Time the hedging backend usually takes to answer, in milliseconds.
+const HEDGE_LATENCY_MS: u64 = 1;
+
+/// Time a slow call to the hedging backend takes, in milliseconds.
+const HEDGE_SLOW_MS: u64 = 20;
+
+/// One call in this many to the hedging backend is slow, none if 0.
+const HEDGE_SLOW_ONE_IN: u64 = 20;
+
+/// Time after which an unanswered call is sent again, in milliseconds.
+const HEDGE_AFTER_MS: u64 = 3;
+
+/// Size of the file served compressed, in bytes.
+const COMPRESS_BYTES: usize = 16 * 1024 * 1024;
+
+/// Number of compressed transfers in flight at the same time.
+const COMPRESS_CONCURRENCY: usize = 4;
+
+/// Number of compressed transfers per run.
+const COMPRESS_TRANSFERS: usize = 8;
+
+/// Size of the chunks the served file is read and compressed in.
+const COMPRESS_CHUNK: usize = 64 * 1024;
+
+/// Number of round trips of the ping-pong across ecosystems per run.
+const COMPAT_PINGS: usize = 100_000;
+
+/// Size of the file read across ecosystems, in bytes.
+const COMPAT_FILE_BYTES: usize = 64 * 1024;
+
+/// Number of reads of the file per run.
+const COMPAT_READS: usize = 10_000;
+
+fn bench_quic(runner: &Runner) {
+    let streams = env_or("QUIC_STREAMS", QUIC_STREAMS);
+    let rounds = env_or("QUIC_ROUNDS", QUIC_ROUNDS);
+    let payload = env_or("QUIC_PAYLOAD", QUIC_PAYLOAD);
+
+    runner.run("quic_echo_tokio", || quic::tokio(streams, rounds, payload));
+    runner.run("quic_echo_async_std", || {
+        quic::async_std(streams, rounds, payload)
+    });
+}
+
+fn bench_rpc(runner: &Runner) {
+    let concurrency = env_or("RPC_CONCURRENCY", RPC_CONCURRENCY);
+    let requests = env_or("RPC_REQUESTS", RPC_REQUESTS);
+    let payload = env_or("RPC_PAYLOAD", RPC_PAYLOAD);
+
+    runner.run("rpc_grpc_unary_tokio", || {
+        grpc::unary(concurrency, requests, payload)
+    });
+    runner.run("rpc_grpc_streaming_tokio", || {
+        grpc::streaming(concurrency, requests, payload)
+    });
+    runner.run("rpc_length_prefixed_tokio", || {
+        length_prefixed::tokio(concurrency, requests, payload)
+    });
+    runner.run("rpc_length_prefixed_async_std", || {
+        length_prefixed::async_std(concurrency, requests, payload)
+    });
+
+    // The same protocol with the server in its own process, on either runtime.
+    for &server in &["tokio", "async_std"] {
+        let start = || ServerProcess::spawn(env!("CARGO_BIN_EXE_echo_server"), &[server]);
+        runner.run(
+            &format!("rpc_process_{}_server_tokio_client", server),
+            || {
+                let server = start();
+                length_prefixed::tokio_client(server.addr(), concurrency, requests, payload)
+            },
+        );
+        runner.run(
+            &format!("rpc_process_{}_server_async_std_client", server),
+            || {
+                let server = start();
+                length_prefixed::async_std_client(server.addr(), concurrency, requests, payload)
+            },
+        );
+    }
+
+    // The same protocol against a server on another machine, if there is one.
+    if let Ok(server) = env::var("RPC_SERVER") {
+        let addr = server
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .unwrap_or_else(|| panic!("cannot resolve RPC_SERVER {}", server));
+        let runtime = env::var("RPC_SERVER_RUNTIME").unwrap_or_else(|_| "unknown".to_owned());
+        runner.run(
+            &format!("rpc_remote_{}_server_tokio_client", runtime),
+            || length_prefixed::tokio_client(addr, concurrency, requests, payload),
+        );
+        runner.run(
+            &format!("rpc_remote_{}_server_async_std_client", runtime),
+            || length_prefixed::async_std_client(addr, concurrency, requests, payload),
+        );
+    }
+}
+
+fn bench_ws(runner: &Runner) {
+    let connections = env_or("WS_CONNECTIONS", WS_CONNECTIONS);
+    let frames = env_or("WS_FRAMES", WS_FRAMES);
+    let frame_size = env_or("WS_FRAME_SIZE", WS_FRAME_SIZE);
+
+    runner.run("ws_echo_tokio_tungstenite", || {
+        ws::tokio(connections, frames, frame_size)
+    });
+    runner.run("ws_echo_async_tungstenite", || {
+        ws::async_std(connections, frames, frame_size)
+    });
+}
+
+fn bench_tls(runner: &Runner) {
+    let concurrency = env_or("TLS_CONCURRENCY", TLS_CONCURRENCY);
+    let handshakes = env_or("TLS_HANDSHAKES", TLS_HANDSHAKES);
+
+    runner.run("tls_handshake_tokio_rustls", || {
+        handshake::tokio(concurrency, handshakes)
+    });
+    runner.run("tls_handshake_futures_rustls", || {
+        handshake::async_std(concurrency, handshakes)
+    });
+
+    let connections = env_or("TLS_BULK_CONNECTIONS", TLS_BULK_CONNECTIONS);
+    let total = env_or("TLS_BULK_BYTES", TLS_BULK_BYTES);
+    let chunk = env_or("TLS_BULK_CHUNK", TLS_BULK_CHUNK);
+
+    runner.run("tls_bulk_tokio_rustls", || {
+        bulk::tokio(connections, total, chunk)
+    });
+    runner.run("tls_bulk_futures_rustls", || {
+        bulk::async_std(connections, total, chunk)
+    });
+}
+
+fn bench_dns(runner: &Runner) {
+    let concurrency = env_or("DNS_CONCURRENCY", DNS_CONCURRENCY);
+    let names = env_or("DNS_NAMES", DNS_NAMES);
+
+    runner.run("dns_hickory_tokio", || dns::tokio(concurrency, names));
+    runner.run("dns_hickory_async_std", || {
+        dns::async_std(concurrency, names)
+    });
+    runner.run("dns_getaddrinfo_spawn_blocking", || {
+        dns::getaddrinfo(concurrency, names)
+    });
+}
+
+fn bench_proxy(runner: &Runner) {
+    let connections = env_or("PROXY_CONNECTIONS", PROXY_CONNECTIONS);
+    let total = env_or("PROXY_BYTES", PROXY_BYTES);
+    let chunk = env_or("PROXY_CHUNK", PROXY_CHUNK);
+
+    runner.run("proxy_copy_bidirectional_tokio", || {
+        proxy::tokio_copy_bidirectional(connections, total, chunk)
+    });
+    runner.run("proxy_joined_copies_tokio", || {
+        proxy::tokio_joined_copies(connections, total, chunk)
+    });
+    runner.run("proxy_joined_copies_async_std", || {
+        proxy::async_std_joined_copies(connections, total, chunk)
+    });
+}
+
+fn bench_chat(runner: &Runner) {
+    let rounds = env_or("CHAT_ROUNDS", CHAT_ROUNDS);
+
+    for clients in env_list("CHAT_CLIENTS", CHAT_CLIENTS) {
+        runner.run(&format!("chat_broadcast_tokio/{}", clients), || {
+            chat::tokio(clients, rounds)
+        });
+        runner.run(&format!("chat_broadcast_async_std/{}", clients), || {
+            chat::async_std(clients, rounds)
+        });
+    }
+}
+
+fn bench_pool(runner: &Runner) {
+    let tasks = env_or("POOL_TASKS", POOL_TASKS);
+    let size = env_or("POOL_SIZE", POOL_SIZE);
+    let requests = env_or("POOL_REQUESTS", POOL_REQUESTS);
+
+    runner.run("pool_bb8_tokio", || pool::bb8_tokio(tasks, size, requests));
+    runner.run("pool_deadpool_tokio", || {
+        pool::deadpool_tokio(tasks, size, requests)
+    });
+    runner.run("pool_deadpool_async_std", || {
+        pool::deadpool_async_std(tasks, size, requests)
+    });
+}
+
+fn bench_idle(runner: &Runner) {
+    let connections = env_or("IDLE_CONNECTIONS", IDLE_CONNECTIONS);
+    let rounds = env_or("IDLE_ROUNDS", IDLE_ROUNDS);
+    let interval = Duration::from_millis(env_or("IDLE_INTERVAL_MS", IDLE_INTERVAL_MS));
+
+    runner.run("idle_keepalive_tokio", || {
+        idle::tokio(connections, rounds, interval)
+    });
+    runner.run("idle_keepalive_async_std", || {
+        idle::async_std(connections, rounds, interval)
+    });
+}
+
+fn bench_codec(runner: &Runner) {
+    let total = env_or("CODEC_BYTES", CODEC_BYTES);
+    let small = env_or("CODEC_SMALL_FRAME", CODEC_SMALL_FRAME);
+    let large = env_or("CODEC_LARGE_FRAME", CODEC_LARGE_FRAME);
+
+    for &size in &[small, large] {
+        let frames = total / size;
+        runner.run(&format!("codec_framed_tokio/{}", size), || {
+            codec::tokio_framed(frames, size)
+        });
+        runner.run(&format!("codec_hand_rolled_tokio/{}", size), || {
+            codec::tokio_hand_rolled(frames, size)
+        });
+        runner.run(&format!("codec_hand_rolled_async_std/{}", size), || {
+            codec::async_std_hand_rolled(frames, size)
+        });
+    }
+}
+
+fn bench_rtt(runner: &Runner) {
+    let pings = env_or("RTT_PINGS", RTT_PINGS);
+
+    runner.run("rtt_ping_pong_tokio", || rtt::tokio(pings));
+    runner.run("rtt_ping_pong_async_std", || rtt::async_std(pings));
+    runner.run("rtt_ping_pong_std_threads", || rtt::std_threads(pings));
+}
+
+fn bench_wan(runner: &Runner) {
+    let link = Link {
+        delay: Duration::from_millis(env_or("WAN_DELAY_MS", WAN_DELAY_MS)),
+        jitter: Duration::from_millis(env_or("WAN_JITTER_MS", WAN_JITTER_MS)),
+    };
+    let concurrency = env_or("WAN_CONCURRENCY", WAN_CONCURRENCY);
+    let requests = env_or("WAN_REQUESTS", WAN_REQUESTS);
+    let payload = env_or("RPC_PAYLOAD", RPC_PAYLOAD);
+
+    runner.run("wan_length_prefixed_tokio", || {
+        wan::tokio(link, concurrency, requests, payload)
+    });
+    runner.run("wan_length_prefixed_async_std", || {
+        wan::async_std(link, concurrency, requests, payload)
+    });
+}
+
+fn bench_sim(runner: &Runner) {
+    let concurrency = env_or("RPC_CONCURRENCY", RPC_CONCURRENCY);
+    let requests = env_or("RPC_REQUESTS", RPC_REQUESTS);
+    let payload = env_or("RPC_PAYLOAD", RPC_PAYLOAD);
+    let simulation = Simulation {
+        seed: env_or("SIM_SEED", SIM_SEED),
+        min_latency: Duration::from_millis(0),
+        max_latency: Duration::from_millis(env_or("SIM_MAX_LATENCY_MS", SIM_MAX_LATENCY_MS)),
+        faults: None,
+    };
+    let faults = Faults::new(
+        Duration::from_millis(env_or("SIM_FAULT_EVERY_MS", SIM_FAULT_EVERY_MS)),
+        Duration::from_millis(env_or("SIM_FAULT_LENGTH_MS", SIM_FAULT_LENGTH_MS)),
+    );
+
+    runner.run("sim_length_prefixed_turmoil", || {
+        sim::length_prefixed(simulation, concurrency, requests, payload)
+    });
+    runner.run("sim_length_prefixed_turmoil_faults", || {
+        let simulation = Simulation {
+            faults: Some(faults),
+            ..simulation
+        };
+        sim::length_prefixed(simulation, concurrency, requests, payload)
+    });
+}
+
+fn bench_fanout(runner: &Runner) {
+    let latency = Latency {
+        typical: Duration::from_millis(env_or("FANOUT_LATENCY_MS", FANOUT_LATENCY_MS)),
+        slow: Duration::from_millis(env_or("FANOUT_SLOW_MS", FANOUT_SLOW_MS)),
+        slow_one_in: env_or("FANOUT_SLOW_ONE_IN", FANOUT_SLOW_ONE_IN),
+    };
+    let concurrency = env_or("FANOUT_CONCURRENCY", FANOUT_CONCURRENCY);
+    let requests = env_or("FANOUT_REQUESTS", FANOUT_REQUESTS);
+    let payload = env_or("RPC_PAYLOAD", RPC_PAYLOAD);
+
+    for width in env_list("FANOUT_WIDTHS", FANOUT_WIDTHS) {
+        runner.run(&format!("fanout_tokio/{}", width), || {
+            fanout::tokio(width, latency, concurrency, requests, payload)
+        });
+        runner.run(&format!("fanout_async_std/{}", width), || {
+            fanout::async_std(width, latency, concurrency, requests, payload)
+        });
+    }
+}
+
+fn bench_hedge(runner: &Runner) {
+    let latency = Latency {
+        typical: Duration::from_millis(env_or("HEDGE_LATENCY_MS", HEDGE_LATENCY_MS)),
+        slow: Duration::from_millis(env_or("HEDGE_SLOW_MS", HEDGE_SLOW_MS)),
+        slow_one_in: env_or("HEDGE_SLOW_ONE_IN", HEDGE_SLOW_ONE_IN),
+    };
+    let hedge_after = Duration::from_millis(env_or("HEDGE_AFTER_MS", HEDGE_AFTER_MS));
+    let concurrency = env_or("HEDGE_CONCURRENCY", HEDGE_CONCURRENCY);
+    let requests = env_or("HEDGE_REQUESTS", HEDGE_REQUESTS);
+    let payload = env_or("RPC_PAYLOAD", RPC_PAYLOAD);
+
+    for &(label, hedge_after) in &[("off", None), ("on", Some(hedge_after))] {
+        runner.run(&format!("hedge_tokio/{}", label), || {
+            hedge::tokio(latency, hedge_after, concurrency, requests, payload)
+        });
+        runner.run(&format!("hedge_async_std/{}", label), || {
+            hedge::async_std(latency, hedge_after, concurrency, requests, payload)
+        });
+    }
+}
+
+fn bench_compress(runner: &Runner) {
+    let concurrency = env_or("COMPRESS_CONCURRENCY", COMPRESS_CONCURRENCY);
+    let transfers = env_or("COMPRESS_TRANSFERS", COMPRESS_TRANSFERS);
+    let chunk = env_or("COMPRESS_CHUNK", COMPRESS_CHUNK);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("served");
+    compress::write_sample(&path, env_or("COMPRESS_BYTES", COMPRESS_BYTES));
+
+    runner.run("compress_gzip_tokio", || {
+        compress::tokio(&path, concurrency, transfers, chunk)
+    });
+    runner.run("compress_gzip_async_std", || {
+        compress::async_std(&path, concurrency, transfers, chunk)
+    });
+    runner.run("compress_gzip_std_threads", || {
+        compress::std_threads(&path, concurrency, transfers, chunk)
+    });
+}
+
+fn bench_compat(runner: &Runner) {
+    let pings = env_or("COMPAT_PINGS", COMPAT_PINGS);
+    let reads = env_or("COMPAT_READS", COMPAT_READS);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("read");
+    std::fs::write(
+        &path,
+        vec![7; env_or("COMPAT_FILE_BYTES", COMPAT_FILE_BYTES)],
+    )
+    .unwrap();
+
+    for &(host_name, host) in &[
+        ("tokio", Host::Tokio),
+        ("async_std", Host::AsyncStd),
+        ("smol", Host::Smol),
+    ] {
+        runner.run(&format!("compat_tokio_net_on_{}", host_name), || {
+            compat::tokio_pings(host, pings)
+        });
+        runner.run(&format!("compat_async_std_net_on_{}", host_name), || {
+            compat::async_std_pings(host, pings)
+        });
+        runner.run(&format!("compat_tokio_fs_on_{}", host_name), || {
+            compat::tokio_reads(host, &path, reads)
+        });
+        runner.run(&format!("compat_async_std_fs_on_{}", host_name), || {
+            compat::async_std_reads(host, &path, reads)
+        });
+    }
+}
+
+fn main() {
+    let runner = Runner::from_args();
+    bench_quic(&runner);
+    bench_rpc(&runner);
+    bench_ws(&runner);
+    bench_tls(&runner);
+    bench_dns(&runner);
+    bench_proxy(&runner);
+    bench_chat(&runner);
+    bench_pool(&runner);
+    bench_idle(&runner);
+    bench_codec(&runner);
+    bench_rtt(&runner);
+    bench_wan(&runner);
+    bench_sim(&runner);
+    bench_fanout(&runner);
+    bench_hedge(&runner);
+    bench_compress(&runner);
+    bench_compat(&runner);
+}
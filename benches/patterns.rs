@@ -0,0 +1,338 @@
+use async_runtimes_cmp::backpressure::{self, Pipe};
+use async_runtimes_cmp::breaker::{self, Flaky, Policy};
+use async_runtimes_cmp::config::{env_list, env_or};
+use async_runtimes_cmp::faults::{self, Faults, Load, Target};
+use async_runtimes_cmp::graph::{self, Graph};
+use async_runtimes_cmp::harness::Runner;
+use async_runtimes_cmp::pipeline::{self, Shape};
+use async_runtimes_cmp::pubsub;
+use async_runtimes_cmp::ratelimit;
+use async_runtimes_cmp::retry::{self, Backoff};
+use std::time::Duration;
+
+/// Number of tasks publishing at the same time.
+const PUBSUB_PUBLISHERS: usize = 4;
+
+/// Numbers of subscribers, spread over the topics, each swept in its own run.
+const PUBSUB_SUBSCRIBERS: &[usize] = &[10, 100, 1_000];
+
+/// Number of topics, each routed through a channel of its own.
+const PUBSUB_TOPICS: usize = 10;
+
+/// Number of messages every publisher sends to each topic.
+const PUBSUB_ROUNDS: usize = 1_000;
+
+/// Calls per second the rate limiters let through.
+const RATE_LIMIT: u32 = 10_000;
+
+/// Number of tasks competing for the rate limiter.
+const RATE_TASKS: usize = 1_000;
+
+/// How long calls are made through the rate limiter, in milliseconds at the target rate.
+const RATE_DURATION_MS: u64 = 2_000;
+
+/// Number of tasks calling the flaky backend.
+const BREAKER_TASKS: usize = 64;
+
+/// Calls allowed in flight at the same time by the bulkhead.
+const BREAKER_BULKHEAD: usize = 32;
+
+/// Time the flaky backend takes to answer while it is up, in milliseconds.
+const BREAKER_LATENCY_MS: u64 = 1;
+
+/// Time after which a call to the flaky backend fails, in milliseconds.
+const BREAKER_TIMEOUT_MS: u64 = 10;
+
+/// Consecutive failed calls that open the breaker.
+const BREAKER_THRESHOLD: u32 = 5;
+
+/// Time the breaker stays open before probing, in milliseconds.
+const BREAKER_COOLDOWN_MS: u64 = 50;
+
+/// Time from the start of the run until the backend goes down, in milliseconds.
+const BREAKER_HEALTHY_MS: u64 = 300;
+
+/// Time the backend stays down, in milliseconds.
+const BREAKER_OUTAGE_MS: u64 = 300;
+
+/// Length of each circuit breaker run, in milliseconds.
+const BREAKER_DURATION_MS: u64 = 1_000;
+
+/// Number of tasks retrying operations at the same time.
+const RETRY_TASKS: usize = 10_000;
+
+/// Number of operations per retry run.
+const RETRY_OPERATIONS: usize = 100_000;
+
+/// Probability that an attempt fails, in percent.
+const RETRY_FAILURE_PERCENT: u32 = 30;
+
+/// Largest backoff after the first failure, in milliseconds.
+const RETRY_BASE_MS: u64 = 1;
+
+/// Largest backoff after any failure, in milliseconds.
+const RETRY_CAP_MS: u64 = 100;
+
+/// Failed attempts after which an operation is given up.
+const RETRY_ATTEMPTS: u32 = 8;
+
+/// Number of tasks sending to the slow consumer.
+const BACKPRESSURE_PRODUCERS: usize = 8;
+
+/// Messages the channel to the slow consumer holds.
+const BACKPRESSURE_CAPACITY: usize = 64;
+
+/// Time the consumer spins for every message, in microseconds.
+const BACKPRESSURE_WORK_US: u64 = 10;
+
+/// Length of each backpressure run, in milliseconds.
+const BACKPRESSURE_DURATION_MS: u64 = 1_000;
+
+/// Numbers of worker stages in the pipeline, each swept in its own run.
+const PIPELINE_STAGES: &[usize] = &[1, 2, 4];
+
+/// Number of workers in every stage of the pipeline.
+const PIPELINE_WORKERS: usize = 4;
+
+/// Messages every mailbox of the pipeline holds.
+const PIPELINE_CAPACITY: usize = 64;
+
+/// Time every worker spins for on every message, in microseconds.
+const PIPELINE_WORK_US: u64 = 1;
+
+/// Number of messages sent through the pipeline per run.
+const PIPELINE_MESSAGES: usize = 200_000;
+
+/// Seeds of the random task graphs, each run on every runtime.
+const GRAPH_SEEDS: &[u64] = &[1, 2, 3];
+
+/// Tasks in every random graph.
+const GRAPH_TASKS: usize = 1_000;
+
+/// Most edges into a task of a random graph.
+const GRAPH_FAN_IN: usize = 4;
+
+/// Longest a task of a random graph sleeps for, in microseconds.
+const GRAPH_SLEEP_US: u64 = 1_000;
+
+/// Most bytes a task of a random graph writes and reads back.
+const GRAPH_IO_BYTES: usize = 16 * 1024;
+
+/// Most messages sent over an edge of a random graph.
+const GRAPH_MESSAGES: usize = 100;
+
+/// Number of times every random graph runs per scenario.
+const GRAPH_RUNS: usize = 10;
+
+/// Percentages of failed polls and dropped messages, each swept in its own
+/// run.
+const FAULT_PERCENTS: &[u32] = &[0, 1, 10];
+
+/// Percentage of polls and messages held back for a random delay.
+const FAULT_DELAY_PERCENT: u32 = 5;
+
+/// Longest a poll or message is held back, in microseconds.
+const FAULT_DELAY_US: u64 = 1_000;
+
+/// Seed the faults are drawn with.
+const FAULT_SEED: u64 = 1;
+
+/// Number of tasks running operations under faults.
+const FAULT_TASKS: usize = 64;
+
+/// Number of operations every task runs under faults.
+const FAULT_OPERATIONS: usize = 200;
+
+/// Size of every file, frame and message written under faults.
+const FAULT_BYTES: usize = 16 * 1024;
+
+/// Longest a request over a faulty channel waits for its reply, in milliseconds.
+const FAULT_TIMEOUT_MS: u64 = 10;
+
+fn bench_pubsub(runner: &Runner) {
+    let publishers = env_or("PUBSUB_PUBLISHERS", PUBSUB_PUBLISHERS);
+    let topics = env_or("PUBSUB_TOPICS", PUBSUB_TOPICS);
+    let rounds = env_or("PUBSUB_ROUNDS", PUBSUB_ROUNDS);
+
+    for subscribers in env_list("PUBSUB_SUBSCRIBERS", PUBSUB_SUBSCRIBERS) {
+        runner.run(&format!("pubsub_tokio/{}", subscribers), || {
+            pubsub::tokio(publishers, subscribers, topics, rounds)
+        });
+        runner.run(&format!("pubsub_async_std/{}", subscribers), || {
+            pubsub::async_std(publishers, subscribers, topics, rounds)
+        });
+    }
+}
+
+fn bench_ratelimit(runner: &Runner) {
+    let rate = env_or("RATE_LIMIT", RATE_LIMIT);
+    let tasks = env_or("RATE_TASKS", RATE_TASKS);
+    let duration = env_or("RATE_DURATION_MS", RATE_DURATION_MS);
+    let calls = (u64::from(rate) * duration / 1_000) as usize;
+
+    runner.run("ratelimit_governor_tokio", || {
+        ratelimit::governor_tokio(rate, tasks, calls)
+    });
+    runner.run("ratelimit_governor_async_std", || {
+        ratelimit::governor_async_std(rate, tasks, calls)
+    });
+    runner.run("ratelimit_semaphore_tokio", || {
+        ratelimit::semaphore_tokio(rate, tasks, calls)
+    });
+    runner.run("ratelimit_semaphore_async_std", || {
+        ratelimit::semaphore_async_std(rate, tasks, calls)
+    });
+}
+
+fn bench_breaker(runner: &Runner) {
+    let backend = Flaky {
+        latency: Duration::from_millis(env_or("BREAKER_LATENCY_MS", BREAKER_LATENCY_MS)),
+        healthy_for: Duration::from_millis(env_or("BREAKER_HEALTHY_MS", BREAKER_HEALTHY_MS)),
+        outage: Duration::from_millis(env_or("BREAKER_OUTAGE_MS", BREAKER_OUTAGE_MS)),
+    };
+    let policy = Policy {
+        bulkhead: env_or("BREAKER_BULKHEAD", BREAKER_BULKHEAD),
+        timeout: Duration::from_millis(env_or("BREAKER_TIMEOUT_MS", BREAKER_TIMEOUT_MS)),
+        threshold: env_or("BREAKER_THRESHOLD", BREAKER_THRESHOLD),
+        cooldown: Duration::from_millis(env_or("BREAKER_COOLDOWN_MS", BREAKER_COOLDOWN_MS)),
+    };
+    let tasks = env_or("BREAKER_TASKS", BREAKER_TASKS);
+    let duration = Duration::from_millis(env_or("BREAKER_DURATION_MS", BREAKER_DURATION_MS));
+
+    runner.run("breaker_tokio", || {
+        breaker::tokio(backend, policy, tasks, duration)
+    });
+    runner.run("breaker_async_std", || {
+        breaker::async_std(backend, policy, tasks, duration)
+    });
+}
+
+fn bench_retry(runner: &Runner) {
+    let backoff = Backoff {
+        failure: f64::from(env_or("RETRY_FAILURE_PERCENT", RETRY_FAILURE_PERCENT)) / 100.0,
+        base: Duration::from_millis(env_or("RETRY_BASE_MS", RETRY_BASE_MS)),
+        cap: Duration::from_millis(env_or("RETRY_CAP_MS", RETRY_CAP_MS)),
+        attempts: env_or("RETRY_ATTEMPTS", RETRY_ATTEMPTS),
+    };
+    let tasks = env_or("RETRY_TASKS", RETRY_TASKS);
+    let operations = env_or("RETRY_OPERATIONS", RETRY_OPERATIONS);
+
+    runner.run("retry_backoff_tokio", || {
+        retry::tokio(backoff, tasks, operations)
+    });
+    runner.run("retry_backoff_async_std", || {
+        retry::async_std(backoff, tasks, operations)
+    });
+}
+
+fn bench_backpressure(runner: &Runner) {
+    let pipe = Pipe {
+        producers: env_or("BACKPRESSURE_PRODUCERS", BACKPRESSURE_PRODUCERS),
+        capacity: env_or("BACKPRESSURE_CAPACITY", BACKPRESSURE_CAPACITY),
+        work: Duration::from_micros(env_or("BACKPRESSURE_WORK_US", BACKPRESSURE_WORK_US)),
+        duration: Duration::from_millis(env_or(
+            "BACKPRESSURE_DURATION_MS",
+            BACKPRESSURE_DURATION_MS,
+        )),
+    };
+
+    runner.run("backpressure_tokio", || backpressure::tokio(pipe));
+    runner.run("backpressure_async_std", || backpressure::async_std(pipe));
+}
+
+fn bench_pipeline(runner: &Runner) {
+    let workers = env_or("PIPELINE_WORKERS", PIPELINE_WORKERS);
+    let capacity = env_or("PIPELINE_CAPACITY", PIPELINE_CAPACITY);
+    let work = Duration::from_micros(env_or("PIPELINE_WORK_US", PIPELINE_WORK_US));
+    let messages = env_or("PIPELINE_MESSAGES", PIPELINE_MESSAGES);
+
+    for stages in env_list("PIPELINE_STAGES", PIPELINE_STAGES) {
+        let shape = Shape {
+            stages,
+            workers,
+            capacity,
+            work,
+        };
+        runner.run(&format!("pipeline_tokio/{}", stages), || {
+            pipeline::tokio(shape, messages)
+        });
+        runner.run(&format!("pipeline_async_std/{}", stages), || {
+            pipeline::async_std(shape, messages)
+        });
+    }
+}
+
+fn bench_graph(runner: &Runner) {
+    let shape = graph::Shape {
+        tasks: env_or("GRAPH_TASKS", GRAPH_TASKS),
+        fan_in: env_or("GRAPH_FAN_IN", GRAPH_FAN_IN),
+        sleep: Duration::from_micros(env_or("GRAPH_SLEEP_US", GRAPH_SLEEP_US)),
+        io: env_or("GRAPH_IO_BYTES", GRAPH_IO_BYTES),
+        messages: env_or("GRAPH_MESSAGES", GRAPH_MESSAGES),
+    };
+    let runs = env_or("GRAPH_RUNS", GRAPH_RUNS);
+    let dir = tempfile::tempdir().unwrap();
+
+    for seed in env_list("GRAPH_SEEDS", GRAPH_SEEDS) {
+        let graph = Graph::generate(seed, shape);
+        eprintln!("graph {}: {}", seed, graph);
+        runner.run(&format!("graph_tokio/{}", seed), || {
+            graph::tokio(&graph, dir.path(), runs)
+        });
+        runner.run(&format!("graph_async_std/{}", seed), || {
+            graph::async_std(&graph, dir.path(), runs)
+        });
+        runner.run(&format!("graph_smol/{}", seed), || {
+            graph::smol(&graph, dir.path(), runs)
+        });
+    }
+}
+
+fn bench_faults(runner: &Runner) {
+    let load = Load {
+        tasks: env_or("FAULT_TASKS", FAULT_TASKS),
+        operations: env_or("FAULT_OPERATIONS", FAULT_OPERATIONS),
+        size: env_or("FAULT_BYTES", FAULT_BYTES),
+        timeout: Duration::from_millis(env_or("FAULT_TIMEOUT_MS", FAULT_TIMEOUT_MS)),
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let targets = [
+        ("fs", Target::Fs(dir.path())),
+        ("net", Target::Net),
+        ("channel", Target::Channel),
+    ];
+
+    for percent in env_list("FAULT_PERCENTS", FAULT_PERCENTS) {
+        let faults = Faults {
+            error_percent: percent,
+            delay_percent: env_or("FAULT_DELAY_PERCENT", FAULT_DELAY_PERCENT),
+            delay: Duration::from_micros(env_or("FAULT_DELAY_US", FAULT_DELAY_US)),
+            drop_percent: percent,
+            seed: env_or("FAULT_SEED", FAULT_SEED),
+        };
+        for &(name, target) in &targets {
+            runner.run(&format!("faults_{}_tokio/{}", name, percent), || {
+                faults::tokio(target, faults, load)
+            });
+            runner.run(&format!("faults_{}_async_std/{}", name, percent), || {
+                faults::async_std(target, faults, load)
+            });
+            runner.run(&format!("faults_{}_smol/{}", name, percent), || {
+                faults::smol(target, faults, load)
+            });
+        }
+    }
+}
+
+fn main() {
+    let runner = Runner::from_args();
+
+    bench_pubsub(&runner);
+    bench_ratelimit(&runner);
+    bench_breaker(&runner);
+    bench_retry(&runner);
+    bench_backpressure(&runner);
+    bench_pipeline(&runner);
+    bench_graph(&runner);
+    bench_faults(&runner);
+}
@@ -0,0 +1,464 @@
+use async_runtimes_cmp::channels::broadcast::{
+    self, AsyncBroadcast, AsyncBroadcastOverflow, TokioBroadcast,
+};
+use async_runtimes_cmp::channels::mpmc::{self, CrossbeamBridge};
+use async_runtimes_cmp::channels::mpsc::{
+    self, AsyncChannel, AsyncChannelUnbounded, Channel, Flume, FlumeUnbounded, FuturesMpsc,
+    FuturesUnbounded, TokioMpsc, TokioUnbounded,
+};
+use async_runtimes_cmp::channels::oneshot::{
+    self, AsyncOneshot, FuturesOneshot, Mode, TokioOneshot,
+};
+use async_runtimes_cmp::channels::stream::{AsyncChannelStream, TokioReceiverStream};
+use async_runtimes_cmp::channels::watch::{self, CondvarWatch, TokioWatch};
+use async_runtimes_cmp::config::{env_list, env_or};
+use async_runtimes_cmp::harness::Runner;
+use async_runtimes_cmp::sync::barrier::{self, Barrier};
+use async_runtimes_cmp::sync::mutex::{self, Lock};
+use async_runtimes_cmp::sync::notify::{self, EventSignal, Signal};
+use async_runtimes_cmp::sync::once_cell::{self, Once};
+use async_runtimes_cmp::sync::rwlock::{self, RwLock};
+use async_runtimes_cmp::sync::semaphore::{self, Semaphore};
+use std::time::Duration;
+
+/// Number of messages sent through each channel.
+const CHANNEL_MESSAGES: usize = 1_000_000;
+
+/// Number of tasks sending at the same time in the multi-producer runs.
+const CHANNEL_PRODUCERS: usize = 4;
+
+/// Numbers of messages a bounded channel holds, each swept in its own run next
+/// to an unbounded one.
+const CHANNEL_CAPACITIES: &[usize] = &[1, 16, 1_024];
+
+/// Number of tasks sending to the work queue.
+const MPMC_PRODUCERS: usize = 4;
+
+/// Number of tasks taking messages off the work queue.
+const MPMC_CONSUMERS: usize = 4;
+
+/// Number of messages sent through each work queue.
+const MPMC_MESSAGES: usize = 1_000_000;
+
+/// Number of messages a work queue holds.
+const MPMC_CAPACITY: usize = 1_024;
+
+/// Number of oneshot channels used one after another.
+const ONESHOT_ROUNDS: usize = 100_000;
+
+/// Numbers of receivers of the broadcast channels, each swept in its own run.
+const BROADCAST_RECEIVERS: &[usize] = &[1, 10, 100, 1_000];
+
+/// Number of messages broadcast per run.
+const BROADCAST_MESSAGES: usize = 10_000;
+
+/// Number of messages a broadcast channel holds.
+const BROADCAST_CAPACITY: usize = 128;
+
+/// Messages broadcast between two yields; above the capacity receivers fall behind.
+const BROADCAST_BURST: usize = 256;
+
+/// Numbers of tasks watching the value, each swept in its own run.
+const WATCH_WATCHERS: &[usize] = &[1, 100, 1_000];
+
+/// Number of times the watched value is updated per run.
+const WATCH_UPDATES: usize = 100_000;
+
+/// Numbers of tasks contending for the mutex, each swept in its own run.
+const MUTEX_TASKS: &[usize] = &[1, 10, 100, 1_000];
+
+/// Time the mutex is held on every turn, in microseconds.
+const MUTEX_HOLD_US: u64 = 1;
+
+/// Length of each mutex run, in milliseconds.
+const MUTEX_DURATION_MS: u64 = 500;
+
+/// Number of tasks reading and writing through the lock.
+const RWLOCK_TASKS: usize = 64;
+
+/// Share of the turns that write, in percent.
+const RWLOCK_WRITE_PERCENT: u32 = 5;
+
+/// Length of each readers-writer lock run, in milliseconds.
+const RWLOCK_DURATION_MS: u64 = 500;
+
+/// Number of tasks sharing the semaphore's permits.
+const SEMAPHORE_TASKS: usize = 1_000;
+
+/// Number of permits of the semaphore.
+const SEMAPHORE_PERMITS: usize = 8;
+
+/// Length of each semaphore run, in milliseconds.
+const SEMAPHORE_DURATION_MS: u64 = 500;
+
+/// Number of ping-pong rounds of each wakeup run.
+const NOTIFY_ROUNDS: usize = 100_000;
+
+/// Numbers of tasks meeting at the barrier, each swept in its own run.
+const BARRIER_TASKS: &[usize] = &[2, 10, 100, 1_000];
+
+/// Number of phases of each barrier run.
+const BARRIER_PHASES: usize = 1_000;
+
+/// Number of tasks getting the lazily initialized value.
+const ONCE_TASKS: usize = 10_000;
+
+/// Time the initializer takes, in milliseconds.
+const ONCE_INIT_MS: u64 = 1;
+
+/// Number of times every task reads the initialized value in the warm runs.
+const ONCE_READS: usize = 1_000;
+
+fn bench_mpsc(runner: &Runner) {
+    let messages = env_or("CHANNEL_MESSAGES", CHANNEL_MESSAGES);
+    // CHANNEL_CAPACITY, from before the capacities were swept, is a list of one.
+    let capacity = env_list("CHANNEL_CAPACITY", CHANNEL_CAPACITIES);
+    let capacities = env_list("CHANNEL_CAPACITIES", &capacity);
+
+    for &(shape, producers) in &[
+        ("spsc", 1),
+        ("mpsc", env_or("CHANNEL_PRODUCERS", CHANNEL_PRODUCERS)),
+    ] {
+        for &capacity in &capacities {
+            let case = format!("{}/{}", shape, capacity);
+            runner.run(&format!("mpsc_tokio_tokio/{}", case), || {
+                mpsc::on_tokio::<TokioMpsc>(producers, messages, capacity)
+            });
+            mpsc_on_both::<FuturesMpsc>(runner, "futures", &case, producers, messages, capacity);
+            mpsc_on_both::<Flume>(runner, "flume", &case, producers, messages, capacity);
+            mpsc_on_both::<AsyncChannel>(
+                runner,
+                "async_channel",
+                &case,
+                producers,
+                messages,
+                capacity,
+            );
+            runner.run(&format!("mpsc_tokio_stream_tokio/{}", case), || {
+                mpsc::on_tokio::<TokioReceiverStream>(producers, messages, capacity)
+            });
+            mpsc_on_both::<AsyncChannelStream>(
+                runner,
+                "async_channel_stream",
+                &case,
+                producers,
+                messages,
+                capacity,
+            );
+        }
+        let case = format!("{}/unbounded", shape);
+        runner.run(&format!("mpsc_tokio_tokio/{}", case), || {
+            mpsc::on_tokio::<TokioUnbounded>(producers, messages, 0)
+        });
+        mpsc_on_both::<FuturesUnbounded>(runner, "futures", &case, producers, messages, 0);
+        mpsc_on_both::<FlumeUnbounded>(runner, "flume", &case, producers, messages, 0);
+        mpsc_on_both::<AsyncChannelUnbounded>(
+            runner,
+            "async_channel",
+            &case,
+            producers,
+            messages,
+            0,
+        );
+    }
+}
+
+/// Runs the scenario of the runtime-independent `C` channel on both runtimes.
+fn mpsc_on_both<C: Channel>(
+    runner: &Runner,
+    channel: &str,
+    case: &str,
+    producers: usize,
+    messages: usize,
+    capacity: usize,
+) {
+    runner.run(&format!("mpsc_{}_tokio/{}", channel, case), || {
+        mpsc::on_tokio::<C>(producers, messages, capacity)
+    });
+    runner.run(&format!("mpsc_{}_async_std/{}", channel, case), || {
+        mpsc::on_async_std::<C>(producers, messages, capacity)
+    });
+}
+
+fn bench_mpmc(runner: &Runner) {
+    let producers = env_or("MPMC_PRODUCERS", MPMC_PRODUCERS);
+    let consumers = env_or("MPMC_CONSUMERS", MPMC_CONSUMERS);
+    let messages = env_or("MPMC_MESSAGES", MPMC_MESSAGES);
+    let capacity = env_or("MPMC_CAPACITY", MPMC_CAPACITY);
+
+    runner.run("mpmc_async_channel_tokio", || {
+        mpmc::on_tokio::<AsyncChannel>(producers, consumers, messages, capacity)
+    });
+    runner.run("mpmc_async_channel_async_std", || {
+        mpmc::on_async_std::<AsyncChannel>(producers, consumers, messages, capacity)
+    });
+    runner.run("mpmc_flume_tokio", || {
+        mpmc::on_tokio::<Flume>(producers, consumers, messages, capacity)
+    });
+    runner.run("mpmc_flume_async_std", || {
+        mpmc::on_async_std::<Flume>(producers, consumers, messages, capacity)
+    });
+    runner.run("mpmc_crossbeam_tokio", || {
+        mpmc::on_tokio::<CrossbeamBridge>(producers, consumers, messages, capacity)
+    });
+    runner.run("mpmc_crossbeam_async_std", || {
+        mpmc::on_async_std::<CrossbeamBridge>(producers, consumers, messages, capacity)
+    });
+}
+
+fn bench_oneshot(runner: &Runner) {
+    let rounds = env_or("ONESHOT_ROUNDS", ONESHOT_ROUNDS);
+
+    for &(name, mode) in &[
+        ("same_task", Mode::SameTask),
+        ("cross_task", Mode::CrossTask),
+    ] {
+        runner.run(&format!("oneshot_tokio_tokio/{}", name), || {
+            oneshot::on_tokio::<TokioOneshot>(mode, rounds)
+        });
+        runner.run(&format!("oneshot_tokio_async_std/{}", name), || {
+            oneshot::on_async_std::<TokioOneshot>(mode, rounds)
+        });
+        runner.run(&format!("oneshot_futures_tokio/{}", name), || {
+            oneshot::on_tokio::<FuturesOneshot>(mode, rounds)
+        });
+        runner.run(&format!("oneshot_futures_async_std/{}", name), || {
+            oneshot::on_async_std::<FuturesOneshot>(mode, rounds)
+        });
+        runner.run(&format!("oneshot_async_oneshot_tokio/{}", name), || {
+            oneshot::on_tokio::<AsyncOneshot>(mode, rounds)
+        });
+        runner.run(&format!("oneshot_async_oneshot_async_std/{}", name), || {
+            oneshot::on_async_std::<AsyncOneshot>(mode, rounds)
+        });
+    }
+}
+
+fn bench_broadcast(runner: &Runner) {
+    let messages = env_or("BROADCAST_MESSAGES", BROADCAST_MESSAGES);
+    let capacity = env_or("BROADCAST_CAPACITY", BROADCAST_CAPACITY);
+    let burst = env_or("BROADCAST_BURST", BROADCAST_BURST);
+
+    for receivers in env_list("BROADCAST_RECEIVERS", BROADCAST_RECEIVERS) {
+        runner.run(&format!("broadcast_tokio_tokio/{}", receivers), || {
+            broadcast::on_tokio::<TokioBroadcast>(receivers, messages, capacity, burst)
+        });
+        runner.run(&format!("broadcast_tokio_async_std/{}", receivers), || {
+            broadcast::on_async_std::<TokioBroadcast>(receivers, messages, capacity, burst)
+        });
+        runner.run(
+            &format!("broadcast_async_broadcast_tokio/{}", receivers),
+            || broadcast::on_tokio::<AsyncBroadcast>(receivers, messages, capacity, burst),
+        );
+        runner.run(
+            &format!("broadcast_async_broadcast_async_std/{}", receivers),
+            || broadcast::on_async_std::<AsyncBroadcast>(receivers, messages, capacity, burst),
+        );
+        runner.run(&format!("broadcast_overflow_tokio/{}", receivers), || {
+            broadcast::on_tokio::<AsyncBroadcastOverflow>(receivers, messages, capacity, burst)
+        });
+        runner.run(
+            &format!("broadcast_overflow_async_std/{}", receivers),
+            || {
+                broadcast::on_async_std::<AsyncBroadcastOverflow>(
+                    receivers, messages, capacity, burst,
+                )
+            },
+        );
+    }
+}
+
+fn bench_watch(runner: &Runner) {
+    let updates = env_or("WATCH_UPDATES", WATCH_UPDATES);
+
+    for watchers in env_list("WATCH_WATCHERS", WATCH_WATCHERS) {
+        runner.run(&format!("watch_tokio_tokio/{}", watchers), || {
+            watch::on_tokio::<TokioWatch>(watchers, updates)
+        });
+        runner.run(&format!("watch_tokio_async_std/{}", watchers), || {
+            watch::on_async_std::<TokioWatch>(watchers, updates)
+        });
+        runner.run(&format!("watch_condvar_tokio/{}", watchers), || {
+            watch::on_tokio::<CondvarWatch>(watchers, updates)
+        });
+        runner.run(&format!("watch_condvar_async_std/{}", watchers), || {
+            watch::on_async_std::<CondvarWatch>(watchers, updates)
+        });
+    }
+}
+
+fn bench_mutex(runner: &Runner) {
+    let hold = Duration::from_micros(env_or("MUTEX_HOLD_US", MUTEX_HOLD_US));
+    let duration = Duration::from_millis(env_or("MUTEX_DURATION_MS", MUTEX_DURATION_MS));
+
+    for tasks in env_list("MUTEX_TASKS", MUTEX_TASKS) {
+        mutex_on_both::<tokio::sync::Mutex<u64>>(runner, "tokio", tasks, hold, duration);
+        mutex_on_both::<async_std::sync::Mutex<u64>>(runner, "async_std", tasks, hold, duration);
+        mutex_on_both::<futures::lock::Mutex<u64>>(runner, "futures", tasks, hold, duration);
+        mutex_on_both::<std::sync::Mutex<u64>>(runner, "std", tasks, hold, duration);
+    }
+}
+
+/// Runs the scenario of the `L` mutex on both runtimes.
+fn mutex_on_both<L: Lock>(
+    runner: &Runner,
+    name: &str,
+    tasks: usize,
+    hold: Duration,
+    duration: Duration,
+) {
+    runner.run(&format!("mutex_{}_tokio/{}", name, tasks), || {
+        mutex::on_tokio::<L>(tasks, hold, duration)
+    });
+    runner.run(&format!("mutex_{}_async_std/{}", name, tasks), || {
+        mutex::on_async_std::<L>(tasks, hold, duration)
+    });
+}
+
+fn bench_rwlock(runner: &Runner) {
+    let tasks = env_or("RWLOCK_TASKS", RWLOCK_TASKS);
+    let writes = f64::from(env_or("RWLOCK_WRITE_PERCENT", RWLOCK_WRITE_PERCENT)) / 100.0;
+    let duration = Duration::from_millis(env_or("RWLOCK_DURATION_MS", RWLOCK_DURATION_MS));
+
+    rwlock_on_both::<tokio::sync::RwLock<u64>>(runner, "tokio", tasks, writes, duration);
+    rwlock_on_both::<async_std::sync::RwLock<u64>>(runner, "async_std", tasks, writes, duration);
+    runner.run("rwlock_parking_lot_threads", || {
+        rwlock::parking_lot_threads(tasks, writes, duration)
+    });
+}
+
+/// Runs the scenario of the `L` readers-writer lock on both runtimes.
+fn rwlock_on_both<L: RwLock>(
+    runner: &Runner,
+    name: &str,
+    tasks: usize,
+    writes: f64,
+    duration: Duration,
+) {
+    runner.run(&format!("rwlock_{}_tokio", name), || {
+        rwlock::on_tokio::<L>(tasks, writes, duration)
+    });
+    runner.run(&format!("rwlock_{}_async_std", name), || {
+        rwlock::on_async_std::<L>(tasks, writes, duration)
+    });
+}
+
+fn bench_semaphore(runner: &Runner) {
+    let tasks = env_or("SEMAPHORE_TASKS", SEMAPHORE_TASKS);
+    let permits = env_or("SEMAPHORE_PERMITS", SEMAPHORE_PERMITS);
+    let duration = Duration::from_millis(env_or("SEMAPHORE_DURATION_MS", SEMAPHORE_DURATION_MS));
+
+    semaphore_on_both::<tokio::sync::Semaphore>(runner, "tokio", tasks, permits, duration);
+    semaphore_on_both::<async_lock::Semaphore>(runner, "async_lock", tasks, permits, duration);
+}
+
+/// Runs the scenario of the `S` semaphore on both runtimes.
+fn semaphore_on_both<S: Semaphore>(
+    runner: &Runner,
+    name: &str,
+    tasks: usize,
+    permits: usize,
+    duration: Duration,
+) {
+    runner.run(&format!("semaphore_{}_tokio", name), || {
+        semaphore::on_tokio::<S>(tasks, permits, duration)
+    });
+    runner.run(&format!("semaphore_{}_async_std", name), || {
+        semaphore::on_async_std::<S>(tasks, permits, duration)
+    });
+}
+
+fn bench_notify(runner: &Runner) {
+    let rounds = env_or("NOTIFY_ROUNDS", NOTIFY_ROUNDS);
+
+    notify_on_both::<tokio::sync::Notify>(runner, "tokio", rounds);
+    notify_on_both::<EventSignal>(runner, "event_listener", rounds);
+    runner.run("notify_condvar_threads", || notify::condvar_threads(rounds));
+}
+
+/// Runs the wakeup scenario of the `S` signal on both runtimes.
+fn notify_on_both<S: Signal>(runner: &Runner, name: &str, rounds: usize) {
+    runner.run(&format!("notify_{}_tokio", name), || {
+        notify::on_tokio::<S>(rounds)
+    });
+    runner.run(&format!("notify_{}_async_std", name), || {
+        notify::on_async_std::<S>(rounds)
+    });
+}
+
+fn bench_barrier(runner: &Runner) {
+    let phases = env_or("BARRIER_PHASES", BARRIER_PHASES);
+
+    for tasks in env_list("BARRIER_TASKS", BARRIER_TASKS) {
+        barrier_on_both::<tokio::sync::Barrier>(runner, "tokio", tasks, phases);
+        barrier_on_both::<async_std::sync::Barrier>(runner, "async_std", tasks, phases);
+    }
+}
+
+/// Runs the scenario of the `B` barrier on both runtimes.
+fn barrier_on_both<B: Barrier>(runner: &Runner, name: &str, tasks: usize, phases: usize) {
+    runner.run(&format!("barrier_{}_tokio/{}", name, tasks), || {
+        barrier::on_tokio::<B>(tasks, phases)
+    });
+    runner.run(&format!("barrier_{}_async_std/{}", name, tasks), || {
+        barrier::on_async_std::<B>(tasks, phases)
+    });
+}
+
+fn bench_once_cell(runner: &Runner) {
+    let tasks = env_or("ONCE_TASKS", ONCE_TASKS);
+    let init = Duration::from_millis(env_or("ONCE_INIT_MS", ONCE_INIT_MS));
+    let reads = env_or("ONCE_READS", ONCE_READS);
+
+    for &(name, mode) in &[
+        ("cold", once_cell::Mode::Cold),
+        ("warm", once_cell::Mode::Warm),
+    ] {
+        once_cell_on_both::<tokio::sync::OnceCell<u64>>(
+            runner, "tokio", name, mode, tasks, init, reads,
+        );
+        once_cell_on_both::<async_once_cell::OnceCell<u64>>(
+            runner,
+            "async_once_cell",
+            name,
+            mode,
+            tasks,
+            init,
+            reads,
+        );
+    }
+}
+
+/// Runs the scenario of the `C` cell on both runtimes.
+fn once_cell_on_both<C: Once>(
+    runner: &Runner,
+    cell: &str,
+    name: &str,
+    mode: once_cell::Mode,
+    tasks: usize,
+    init: Duration,
+    reads: usize,
+) {
+    runner.run(&format!("once_cell_{}_tokio/{}", cell, name), || {
+        once_cell::on_tokio::<C>(mode, tasks, init, reads)
+    });
+    runner.run(&format!("once_cell_{}_async_std/{}", cell, name), || {
+        once_cell::on_async_std::<C>(mode, tasks, init, reads)
+    });
+}
+
+fn main() {
+    let runner = Runner::from_args();
+
+    bench_mpsc(&runner);
+    bench_mpmc(&runner);
+    bench_oneshot(&runner);
+    bench_broadcast(&runner);
+    bench_watch(&runner);
+    bench_mutex(&runner);
+    bench_rwlock(&runner);
+    bench_semaphore(&runner);
+    bench_notify(&runner);
+    bench_barrier(&runner);
+    bench_once_cell(&runner);
+}
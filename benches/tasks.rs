@@ -0,0 +1,702 @@
+use async_runtimes_cmp::config::{env_list, env_or};
+use async_runtimes_cmp::harness::Runner;
+use async_runtimes_cmp::tasks::abort::{self, Storm};
+use async_runtimes_cmp::tasks::blocking::{self, Blocking, Calls};
+use async_runtimes_cmp::tasks::breakeven::{self, Batches, Running};
+use async_runtimes_cmp::tasks::cancel::{self, Churn};
+use async_runtimes_cmp::tasks::cancel_safety::{self, Stress, Workload};
+use async_runtimes_cmp::tasks::drivers::{self, Drivers, Flavor, Yielders};
+use async_runtimes_cmp::tasks::flood;
+use async_runtimes_cmp::tasks::footprint::{self, Body};
+use async_runtimes_cmp::tasks::local;
+use async_runtimes_cmp::tasks::nested;
+use async_runtimes_cmp::tasks::ping_pong::{self, Placement};
+use async_runtimes_cmp::tasks::scoped::{self, Scoping, Split};
+use async_runtimes_cmp::tasks::shutdown::{self, Signal};
+#[cfg(unix)]
+use async_runtimes_cmp::tasks::signal;
+use async_runtimes_cmp::tasks::sleepers;
+use async_runtimes_cmp::tasks::spawn::{self, Mode};
+use async_runtimes_cmp::tasks::startup;
+use async_runtimes_cmp::tasks::steal::{self, Bursts};
+use async_runtimes_cmp::tasks::submit;
+use async_runtimes_cmp::tasks::teardown::{self, Outstanding, Teardown};
+use async_runtimes_cmp::tasks::tuning::Tuning;
+use async_runtimes_cmp::tasks::wake::{self, Wake};
+use async_runtimes_cmp::tasks::waker::{self, Op, Side};
+use async_runtimes_cmp::tasks::yield_now::{self, Yield};
+use std::time::Duration;
+
+/// Number of tasks spawned per run.
+const SPAWN_TASKS: usize = 100_000;
+
+/// Number of threads spawned per run, fewer than tasks as threads are slower.
+const SPAWN_THREADS: usize = 10_000;
+
+/// Numbers of busy tasks running while the parked task is woken, 0 for an
+/// idle executor.
+const WAKE_BUSY_TASKS: &[usize] = &[0, 16];
+
+/// Number of wakeups per run.
+const WAKE_ROUNDS: usize = 20_000;
+
+/// Numbers of other tasks yielding next to the measured one.
+const YIELD_OTHERS: &[usize] = &[0, 10, 100];
+
+/// Number of times the measured task yields per run.
+const YIELD_COUNT: usize = 100_000;
+
+/// Number of tasks cancelled per run.
+const CANCEL_TASKS: usize = 100_000;
+
+/// Number of tasks waiting at the same time before they are cancelled.
+const CANCEL_BATCH: usize = 1_000;
+
+/// Bytes of the buffer every cancelled task holds.
+const CANCEL_BUFFER: usize = 16 * 1024;
+
+/// Number of tasks running next to the cancelled ones.
+const CANCEL_NEIGHBORS: usize = 4;
+
+/// Number of in-flight tasks aborted at once.
+const STORM_TASKS: usize = 10_000;
+
+/// Number of storms per run.
+const STORM_ROUNDS: usize = 10;
+
+/// Number of tasks running next to the aborted ones.
+const STORM_NEIGHBORS: usize = 4;
+
+/// Number of operations cancelled at random points per run.
+const CANCEL_SAFETY_OPS: usize = 10_000;
+
+/// Latest an operation is cancelled after it started, in microseconds.
+const CANCEL_SAFETY_WITHIN_US: u64 = 100;
+
+/// Bytes of every record the cancelled writes append.
+const CANCEL_SAFETY_RECORD: usize = 64 * 1024;
+
+/// Seed the cancellation points are drawn from.
+const CANCEL_SAFETY_SEED: u64 = 1;
+
+/// Number of tasks sharing a counter, local or `Send`.
+const LOCAL_TASKS: usize = 10_000;
+
+/// Number of times every task bumps the counter and yields.
+const LOCAL_STEPS: usize = 100;
+
+/// Number of threads outside the runtime submitting tasks.
+const SUBMIT_THREADS: usize = 4;
+
+/// Number of tasks submitted per run.
+const SUBMIT_TASKS: usize = 200_000;
+
+/// Depths of the task trees, each swept in its own run.
+const NESTED_DEPTHS: &[u32] = &[4, 8, 12, 16];
+
+/// Number of children every task of a tree spawns.
+const NESTED_FANOUT: usize = 2;
+
+/// Number of tasks spawned at once per run.
+const FLOOD_TASKS: usize = 1_000_000;
+
+/// Numbers of idle tasks asleep on long timers, each swept in its own run.
+const SLEEPERS: &[usize] = &[0, 10_000, 100_000, 1_000_000];
+
+/// Number of ping round trips measured next to the sleepers.
+const SLEEPERS_ROUNDS: usize = 20_000;
+
+/// Number of idle tasks held per run, when they hold nothing or a buffer.
+const FOOTPRINT_TASKS: usize = 100_000;
+
+/// Number of idle tasks held per run when they hold a socket, limited by the
+/// file descriptors a process may open.
+const FOOTPRINT_SOCKETS: usize = 10_000;
+
+/// Bytes of the buffer every task holds.
+const FOOTPRINT_BUFFER: usize = 4096;
+
+/// Number of bursts of tasks spawned from one task per run.
+const STEAL_BURSTS: usize = 100;
+
+/// Number of tasks per burst.
+const STEAL_TASKS: usize = 1_000;
+
+/// Microseconds every task of a burst spins for.
+const STEAL_WORK_US: u64 = 10;
+
+/// Number of hops the message makes between the two tasks per run.
+const PING_PONG_HOPS: usize = 1_000_000;
+
+/// Number of busy tasks shut down per run.
+const SHUTDOWN_TASKS: usize = 10_000;
+
+/// Time every unit of work of the tasks sleeps for, in milliseconds.
+const SHUTDOWN_UNIT_MS: u64 = 10;
+
+/// Time the tasks work before they are shut down, in milliseconds.
+const SHUTDOWN_AFTER_MS: u64 = 100;
+
+/// Number of tasks making blocking calls.
+const BLOCKING_TASKS: usize = 16;
+
+/// Number of blocking calls made per run.
+const BLOCKING_CALLS: usize = 1_000;
+
+/// Time every blocking call blocks for, in microseconds.
+const BLOCKING_US: u64 = 1_000;
+
+/// Number of tasks yielding next to the callers.
+const BLOCKING_NEIGHBORS: usize = 4;
+
+/// Number of tasks the numbers are split between.
+const SCOPED_TASKS: usize = 64;
+
+/// Number of numbers summed per round.
+const SCOPED_ITEMS: usize = 65_536;
+
+/// Number of rounds per run.
+const SCOPED_ROUNDS: usize = 1_000;
+
+/// Number of times the waker is cloned or woken per run.
+const WAKER_OPS: usize = 1_000_000;
+
+/// Number of futures in every batch of the spawn vs join breakeven.
+const BREAKEVEN_FUTURES: usize = 1_000;
+
+/// Nanoseconds each future of the breakeven spins for, each swept in its own
+/// run.
+const BREAKEVEN_WORK_NS: &[u64] = &[0, 100, 1_000, 10_000, 100_000];
+
+/// Number of batches of futures in every run of the breakeven.
+const BREAKEVEN_BATCHES: usize = 20;
+
+/// Event intervals of tokio's scheduler, each tried in its own run of the
+/// tuned scenarios; tokio's default is 61.
+const TUNING_EVENT_INTERVALS: &[u32] = &[7, 255];
+
+/// Global queue intervals of tokio's scheduler, each tried in its own run of
+/// the tuned scenarios; tokio adapts it by default.
+const TUNING_GLOBAL_QUEUE_INTERVALS: &[u32] = &[7, 61, 255];
+
+/// Depth of the trees of tasks in the tuned runs.
+const TUNING_NESTED_DEPTH: u32 = 12;
+
+/// Number of tasks yielding on runtimes with and without drivers.
+const DRIVERS_TASKS: usize = 100;
+
+/// Number of times each of those tasks yields.
+const DRIVERS_YIELDS: usize = 1_000;
+
+/// Number of runtimes started per run.
+const STARTUP_ROUNDS: usize = 100;
+
+/// Number of signals raised per run.
+const SIGNAL_ROUNDS: usize = 10_000;
+
+/// Number of runtimes dropped per run.
+const TEARDOWN_ROUNDS: usize = 10;
+
+/// Number of never-finishing tasks outstanding when a runtime is dropped.
+const TEARDOWN_TASKS: usize = 10_000;
+
+/// Number of blocking calls running when a runtime is dropped.
+const TEARDOWN_BLOCKING: usize = 16;
+
+/// Milliseconds every blocking call outstanding at the drop sleeps for.
+const TEARDOWN_BLOCKING_MS: u64 = 100;
+
+/// Milliseconds tokio's `shutdown_timeout` waits for blocking calls.
+const TEARDOWN_TIMEOUT_MS: u64 = 10;
+
+fn bench_spawn(runner: &Runner) {
+    let tasks = env_or("SPAWN_TASKS", SPAWN_TASKS);
+    let threads = env_or("SPAWN_THREADS", SPAWN_THREADS);
+
+    for &(name, mode) in &[("awaited", Mode::Awaited), ("detached", Mode::Detached)] {
+        runner.run(&format!("spawn_tokio/{}", name), || {
+            spawn::tokio(mode, tasks)
+        });
+        runner.run(&format!("spawn_async_std/{}", name), || {
+            spawn::async_std(mode, tasks)
+        });
+        runner.run(&format!("spawn_smol/{}", name), || spawn::smol(mode, tasks));
+        runner.run(&format!("spawn_threads/{}", name), || {
+            spawn::threads(mode, threads)
+        });
+    }
+}
+
+fn bench_wake(runner: &Runner) {
+    let rounds = env_or("WAKE_ROUNDS", WAKE_ROUNDS);
+
+    for busy in env_list("WAKE_BUSY_TASKS", WAKE_BUSY_TASKS) {
+        for &(name, how) in &[("waker", Wake::Waker), ("channel", Wake::Channel)] {
+            runner.run(&format!("wake_{}_tokio/{}", name, busy), || {
+                wake::on_tokio(how, busy, rounds)
+            });
+            runner.run(&format!("wake_{}_async_std/{}", name, busy), || {
+                wake::on_async_std(how, busy, rounds)
+            });
+        }
+    }
+}
+
+fn bench_yield(runner: &Runner) {
+    let yields = env_or("YIELD_COUNT", YIELD_COUNT);
+
+    for others in env_list("YIELD_OTHERS", YIELD_OTHERS) {
+        for &(name, how) in &[
+            ("yield_now", Yield::Runtime),
+            ("pending_once", Yield::PendingOnce),
+        ] {
+            runner.run(&format!("yield_{}_tokio/{}", name, others), || {
+                yield_now::on_tokio(how, others, yields)
+            });
+            runner.run(&format!("yield_{}_async_std/{}", name, others), || {
+                yield_now::on_async_std(how, others, yields)
+            });
+        }
+    }
+}
+
+fn bench_cancel(runner: &Runner) {
+    let churn = Churn {
+        tasks: env_or("CANCEL_TASKS", CANCEL_TASKS),
+        batch: env_or("CANCEL_BATCH", CANCEL_BATCH),
+        buffer: env_or("CANCEL_BUFFER", CANCEL_BUFFER),
+        neighbors: env_or("CANCEL_NEIGHBORS", CANCEL_NEIGHBORS),
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("held");
+    std::fs::write(&path, b"held open by cancelled tasks").unwrap();
+
+    runner.run("cancel_tokio", || cancel::on_tokio(&path, churn));
+    runner.run("cancel_async_std", || cancel::on_async_std(&path, churn));
+}
+
+fn bench_abort(runner: &Runner) {
+    let storm = Storm {
+        tasks: env_or("STORM_TASKS", STORM_TASKS),
+        rounds: env_or("STORM_ROUNDS", STORM_ROUNDS),
+        neighbors: env_or("STORM_NEIGHBORS", STORM_NEIGHBORS),
+    };
+
+    runner.run("abort_storm_tokio", || abort::on_tokio(storm));
+    runner.run("abort_storm_async_std", || abort::on_async_std(storm));
+    runner.run("abort_storm_smol", || abort::on_smol(storm));
+}
+
+fn bench_cancel_safety(runner: &Runner) {
+    let stress = Stress {
+        operations: env_or("CANCEL_SAFETY_OPS", CANCEL_SAFETY_OPS),
+        within: Duration::from_micros(env_or("CANCEL_SAFETY_WITHIN_US", CANCEL_SAFETY_WITHIN_US)),
+        seed: env_or("CANCEL_SAFETY_SEED", CANCEL_SAFETY_SEED),
+    };
+    let record = env_or("CANCEL_SAFETY_RECORD", CANCEL_SAFETY_RECORD);
+
+    for (name, workload) in [
+        ("channel", Workload::Channel),
+        ("write", Workload::Write(record)),
+        ("transfer", Workload::Transfer),
+    ] {
+        // A directory per run, so that the records of one are not read by the next.
+        runner.run(&format!("cancel_safety_tokio/{}", name), || {
+            cancel_safety::on_tokio(workload, stress, tempfile::tempdir().unwrap().path())
+        });
+        runner.run(&format!("cancel_safety_async_std/{}", name), || {
+            cancel_safety::on_async_std(workload, stress, tempfile::tempdir().unwrap().path())
+        });
+        runner.run(&format!("cancel_safety_smol/{}", name), || {
+            cancel_safety::on_smol(workload, stress, tempfile::tempdir().unwrap().path())
+        });
+    }
+}
+
+fn bench_local(runner: &Runner) {
+    let tasks = env_or("LOCAL_TASKS", LOCAL_TASKS);
+    let steps = env_or("LOCAL_STEPS", LOCAL_STEPS);
+
+    runner.run("local_tokio_local_set", || local::tokio_local(tasks, steps));
+    runner.run("local_tokio_current_thread", || {
+        local::tokio_current_thread(tasks, steps)
+    });
+    runner.run("local_tokio_multi_thread", || {
+        local::tokio_multi_thread(tasks, steps)
+    });
+    runner.run("local_async_std_spawn_local", || {
+        local::async_std_local(tasks, steps)
+    });
+    runner.run("local_async_std_spawn", || local::async_std(tasks, steps));
+    runner.run("local_smol_local_executor", || {
+        local::smol_local(tasks, steps)
+    });
+    runner.run("local_smol_executor", || local::smol(tasks, steps));
+}
+
+fn bench_submit(runner: &Runner) {
+    let threads = env_or("SUBMIT_THREADS", SUBMIT_THREADS);
+    let tasks = env_or("SUBMIT_TASKS", SUBMIT_TASKS);
+
+    runner.run("submit_tokio_handle", || submit::tokio(threads, tasks));
+    runner.run("submit_async_std_spawn", || {
+        submit::async_std(threads, tasks)
+    });
+}
+
+fn bench_nested(runner: &Runner) {
+    let fanout = env_or("NESTED_FANOUT", NESTED_FANOUT);
+
+    for depth in env_list("NESTED_DEPTHS", NESTED_DEPTHS) {
+        runner.run(&format!("nested_tokio/{}", depth), || {
+            nested::on_tokio(depth, fanout)
+        });
+        runner.run(&format!("nested_async_std/{}", depth), || {
+            nested::on_async_std(depth, fanout)
+        });
+    }
+}
+
+fn bench_flood(runner: &Runner) {
+    let tasks = env_or("FLOOD_TASKS", FLOOD_TASKS);
+
+    runner.run("flood_tokio", || flood::tokio(tasks));
+    runner.run("flood_async_std", || flood::async_std(tasks));
+    runner.run("flood_smol", || flood::smol(tasks));
+}
+
+fn bench_sleepers(runner: &Runner) {
+    let rounds = env_or("SLEEPERS_ROUNDS", SLEEPERS_ROUNDS);
+
+    for sleepers in env_list("SLEEPERS", SLEEPERS) {
+        runner.run(&format!("sleepers_tokio/{}", sleepers), || {
+            sleepers::on_tokio(sleepers, rounds)
+        });
+        runner.run(&format!("sleepers_async_std/{}", sleepers), || {
+            sleepers::on_async_std(sleepers, rounds)
+        });
+    }
+}
+
+fn bench_footprint(runner: &Runner) {
+    let tasks = env_or("FOOTPRINT_TASKS", FOOTPRINT_TASKS);
+    let sockets = env_or("FOOTPRINT_SOCKETS", FOOTPRINT_SOCKETS);
+    let buffer = env_or("FOOTPRINT_BUFFER", FOOTPRINT_BUFFER);
+
+    for &(name, body, tasks) in &[
+        ("empty", Body::Empty, tasks),
+        ("buffer", Body::Buffer(buffer), tasks),
+        ("socket", Body::Socket, sockets),
+    ] {
+        runner.run(&format!("footprint_{}_tokio", name), || {
+            footprint::tokio(body, tasks)
+        });
+        runner.run(&format!("footprint_{}_async_std", name), || {
+            footprint::async_std(body, tasks)
+        });
+        runner.run(&format!("footprint_{}_smol", name), || {
+            footprint::smol(body, tasks)
+        });
+    }
+}
+
+fn bench_steal(runner: &Runner) {
+    let bursts = Bursts {
+        bursts: env_or("STEAL_BURSTS", STEAL_BURSTS),
+        tasks: env_or("STEAL_TASKS", STEAL_TASKS),
+        work: Duration::from_micros(env_or("STEAL_WORK_US", STEAL_WORK_US)),
+    };
+
+    runner.run("steal_tokio", || steal::tokio(bursts));
+    runner.run("steal_async_std", || steal::async_std(bursts));
+    runner.run("steal_smol", || steal::smol(bursts));
+}
+
+fn bench_ping_pong(runner: &Runner) {
+    let hops = env_or("PING_PONG_HOPS", PING_PONG_HOPS);
+
+    for &(name, placement) in &[
+        ("same_worker", Placement::SameWorker),
+        ("cross_worker", Placement::CrossWorker),
+    ] {
+        runner.run(&format!("ping_pong_tokio/{}", name), || {
+            ping_pong::tokio(placement, hops)
+        });
+        runner.run(&format!("ping_pong_async_std/{}", name), || {
+            ping_pong::async_std(placement, hops)
+        });
+        runner.run(&format!("ping_pong_smol/{}", name), || {
+            ping_pong::smol(placement, hops)
+        });
+    }
+}
+
+fn bench_shutdown(runner: &Runner) {
+    let work = shutdown::Work {
+        tasks: env_or("SHUTDOWN_TASKS", SHUTDOWN_TASKS),
+        unit: Duration::from_millis(env_or("SHUTDOWN_UNIT_MS", SHUTDOWN_UNIT_MS)),
+        after: Duration::from_millis(env_or("SHUTDOWN_AFTER_MS", SHUTDOWN_AFTER_MS)),
+    };
+
+    for &(name, how) in &[
+        ("token", Signal::Token),
+        ("channel_close", Signal::ChannelClose),
+        ("stop_flag", Signal::StopFlag),
+    ] {
+        runner.run(&format!("shutdown_{}_tokio", name), || {
+            shutdown::tokio(how, work)
+        });
+        runner.run(&format!("shutdown_{}_async_std", name), || {
+            shutdown::async_std(how, work)
+        });
+    }
+}
+
+fn bench_blocking(runner: &Runner) {
+    let calls = Calls {
+        tasks: env_or("BLOCKING_TASKS", BLOCKING_TASKS),
+        calls: env_or("BLOCKING_CALLS", BLOCKING_CALLS),
+        block: Duration::from_micros(env_or("BLOCKING_US", BLOCKING_US)),
+        neighbors: env_or("BLOCKING_NEIGHBORS", BLOCKING_NEIGHBORS),
+    };
+
+    for &(name, how) in &[
+        ("inline", Blocking::Inline),
+        ("block_in_place", Blocking::BlockInPlace),
+        ("spawn_blocking", Blocking::SpawnBlocking),
+    ] {
+        runner.run(&format!("blocking_{}_tokio", name), || {
+            blocking::tokio(how, calls)
+        });
+    }
+    for &(name, how) in &[
+        ("inline", Blocking::Inline),
+        ("spawn_blocking", Blocking::SpawnBlocking),
+    ] {
+        runner.run(&format!("blocking_{}_async_std", name), || {
+            blocking::async_std(how, calls)
+        });
+    }
+}
+
+fn bench_scoped(runner: &Runner) {
+    let split = Split {
+        tasks: env_or("SCOPED_TASKS", SCOPED_TASKS),
+        items: env_or("SCOPED_ITEMS", SCOPED_ITEMS),
+        rounds: env_or("SCOPED_ROUNDS", SCOPED_ROUNDS),
+    };
+
+    for &(name, scoping) in &[
+        ("static", Scoping::Static),
+        ("join_set", Scoping::JoinSet),
+        ("async_scoped", Scoping::AsyncScoped),
+        ("moro", Scoping::Moro),
+    ] {
+        runner.run(&format!("scoped_{}_tokio", name), || {
+            scoped::tokio(scoping, split)
+        });
+    }
+    for &(name, scoping) in &[
+        ("static", Scoping::Static),
+        ("async_scoped", Scoping::AsyncScoped),
+        ("moro", Scoping::Moro),
+    ] {
+        runner.run(&format!("scoped_{}_async_std", name), || {
+            scoped::async_std(scoping, split)
+        });
+    }
+}
+
+fn bench_waker(runner: &Runner) {
+    let ops = env_or("WAKER_OPS", WAKER_OPS);
+
+    for &(op_name, op) in &[
+        ("clone", Op::Clone),
+        ("wake_by_ref", Op::WakeByRef),
+        ("wake", Op::Wake),
+    ] {
+        for &(side_name, side) in &[("inside", Side::Inside), ("outside", Side::Outside)] {
+            let name = format!("waker_{}_{}", op_name, side_name);
+            runner.run(&format!("{}_tokio", name), || waker::tokio(op, side, ops));
+            runner.run(&format!("{}_async_std", name), || {
+                waker::async_std(op, side, ops)
+            });
+            runner.run(&format!("{}_smol", name), || waker::smol(op, side, ops));
+        }
+    }
+}
+
+fn bench_breakeven(runner: &Runner) {
+    let futures = env_or("BREAKEVEN_FUTURES", BREAKEVEN_FUTURES);
+    let rounds = env_or("BREAKEVEN_BATCHES", BREAKEVEN_BATCHES);
+
+    for work in env_list("BREAKEVEN_WORK_NS", BREAKEVEN_WORK_NS) {
+        let batches = Batches {
+            futures,
+            work: Duration::from_nanos(work),
+            batches: rounds,
+        };
+        for &(name, running) in &[("spawned", Running::Spawned), ("joined", Running::Joined)] {
+            runner.run(&format!("breakeven_{}_tokio/{}", name, work), || {
+                breakeven::tokio(running, batches)
+            });
+            runner.run(&format!("breakeven_{}_async_std/{}", name, work), || {
+                breakeven::async_std(running, batches)
+            });
+            runner.run(&format!("breakeven_{}_smol/{}", name, work), || {
+                breakeven::smol(running, batches)
+            });
+        }
+    }
+}
+
+fn bench_tuning(runner: &Runner) {
+    let mut tunings = vec![Tuning::default()];
+    for interval in env_list("TUNING_EVENT_INTERVALS", TUNING_EVENT_INTERVALS) {
+        tunings.push(Tuning {
+            event_interval: Some(interval),
+            ..Tuning::default()
+        });
+    }
+    for interval in env_list(
+        "TUNING_GLOBAL_QUEUE_INTERVALS",
+        TUNING_GLOBAL_QUEUE_INTERVALS,
+    ) {
+        tunings.push(Tuning {
+            global_queue_interval: Some(interval),
+            ..Tuning::default()
+        });
+    }
+    if Tuning::LIFO_SLOT_KNOB {
+        tunings.push(Tuning {
+            disable_lifo_slot: true,
+            ..Tuning::default()
+        });
+    }
+    let tasks = env_or("SPAWN_TASKS", SPAWN_TASKS);
+    let depth = env_or("TUNING_NESTED_DEPTH", TUNING_NESTED_DEPTH);
+    let fanout = env_or("NESTED_FANOUT", NESTED_FANOUT);
+    let bursts = Bursts {
+        bursts: env_or("STEAL_BURSTS", STEAL_BURSTS),
+        tasks: env_or("STEAL_TASKS", STEAL_TASKS),
+        work: Duration::from_micros(env_or("STEAL_WORK_US", STEAL_WORK_US)),
+    };
+
+    for tuning in tunings {
+        runner.run(&format!("tuned_spawn_tokio/{}", tuning), || {
+            spawn::tokio_tuned(Mode::Detached, tasks, tuning)
+        });
+        runner.run(&format!("tuned_nested_tokio/{}", tuning), || {
+            nested::on_tokio_tuned(depth, fanout, tuning)
+        });
+        runner.run(&format!("tuned_steal_tokio/{}", tuning), || {
+            steal::tokio_tuned(bursts, tuning)
+        });
+    }
+}
+
+fn bench_drivers(runner: &Runner) {
+    let yielders = Yielders {
+        tasks: env_or("DRIVERS_TASKS", DRIVERS_TASKS),
+        yields: env_or("DRIVERS_YIELDS", DRIVERS_YIELDS),
+    };
+
+    for &(flavor_name, flavor) in &[
+        ("current_thread", Flavor::CurrentThread),
+        ("multi_thread", Flavor::MultiThread),
+    ] {
+        for &(name, drivers) in &[
+            ("none", Drivers::None),
+            ("time", Drivers::Time),
+            ("io", Drivers::Io),
+            ("all", Drivers::All),
+        ] {
+            runner.run(&format!("drivers_{}_tokio/{}", flavor_name, name), || {
+                drivers::run(flavor, drivers, yielders)
+            });
+        }
+    }
+}
+
+fn bench_startup(runner: &Runner) {
+    let rounds = env_or("STARTUP_ROUNDS", STARTUP_ROUNDS);
+
+    runner.run("startup_tokio/current_thread", || {
+        startup::tokio(Flavor::CurrentThread, rounds)
+    });
+    runner.run("startup_tokio/multi_thread", || {
+        startup::tokio(Flavor::MultiThread, rounds)
+    });
+    runner.run("startup_async_std", || startup::async_std(rounds));
+    runner.run("startup_smol", || startup::smol(rounds));
+}
+
+fn bench_teardown(runner: &Runner) {
+    let rounds = env_or("TEARDOWN_ROUNDS", TEARDOWN_ROUNDS);
+    let blocking = Outstanding::Blocking {
+        calls: env_or("TEARDOWN_BLOCKING", TEARDOWN_BLOCKING),
+        each: Duration::from_millis(env_or("TEARDOWN_BLOCKING_MS", TEARDOWN_BLOCKING_MS)),
+    };
+    let timeout = Duration::from_millis(env_or("TEARDOWN_TIMEOUT_MS", TEARDOWN_TIMEOUT_MS));
+
+    for &(name, outstanding) in &[
+        ("idle", Outstanding::Idle),
+        (
+            "pending",
+            Outstanding::Pending(env_or("TEARDOWN_TASKS", TEARDOWN_TASKS)),
+        ),
+        ("blocking", blocking),
+    ] {
+        runner.run(&format!("teardown_drop_tokio/{}", name), || {
+            teardown::tokio(outstanding, Teardown::Drop, rounds)
+        });
+        runner.run(&format!("teardown_shutdown_timeout_tokio/{}", name), || {
+            teardown::tokio(outstanding, Teardown::ShutdownTimeout(timeout), rounds)
+        });
+        runner.run(&format!("teardown_drop_smol/{}", name), || {
+            teardown::smol(outstanding, rounds)
+        });
+    }
+}
+
+#[cfg(unix)]
+fn bench_signal(runner: &Runner) {
+    let rounds = env_or("SIGNAL_ROUNDS", SIGNAL_ROUNDS);
+
+    runner.run("signal_tokio", || signal::tokio(rounds));
+    runner.run("signal_smol", || signal::smol(rounds));
+    runner.run("signal_signal_hook_thread", || {
+        signal::signal_hook_thread(rounds)
+    });
+}
+
+fn main() {
+    let runner = Runner::from_args();
+
+    // First, so that the global runtimes of async_std and smol start cold.
+    bench_startup(&runner);
+    bench_spawn(&runner);
+    bench_wake(&runner);
+    bench_yield(&runner);
+    bench_cancel(&runner);
+    bench_abort(&runner);
+    bench_cancel_safety(&runner);
+    bench_local(&runner);
+    bench_submit(&runner);
+    bench_nested(&runner);
+    bench_flood(&runner);
+    bench_sleepers(&runner);
+    bench_footprint(&runner);
+    bench_steal(&runner);
+    bench_ping_pong(&runner);
+    bench_shutdown(&runner);
+    bench_blocking(&runner);
+    bench_scoped(&runner);
+    bench_waker(&runner);
+    bench_breakeven(&runner);
+    bench_tuning(&runner);
+    bench_drivers(&runner);
+    bench_teardown(&runner);
+    #[cfg(unix)]
+    bench_signal(&runner);
+}
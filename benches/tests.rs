@@ -1,7 +1,11 @@
 #[macro_use]
 extern crate bencher;
 
+use async_runtimes_cmp::phases;
 use bencher::Bencher;
+use std::future::Future;
+use std::time::Duration;
+use tracing::{info_span, Instrument};
 
 /// Number of bytes in generated test file.
 const N_BYTES: u32 = 100000;
@@ -9,73 +13,281 @@ const N_BYTES: u32 = 100000;
 /// Number of nanoseconds to sleep for in lengthy computation.
 const COMPUTE_NANOS: u64 = 2_000_000;
 
+/// Number of times the payload is hashed in SHA-256 computation.
+const SHA256_ROUNDS: usize = 16;
+
+/// Bound below which primes are counted in sieve computation.
+const SIEVE_LIMIT: usize = 500_000;
+
+/// Number of times the payload is hashed in one unit of computation of ratio benchmarks,
+/// taking about as long as writing one file, which is one unit of IO.
+const RATIO_UNIT_ROUNDS: usize = 5;
+
+/// Bytes written by `files` files of gen_bytes, for bencher to report MB/s next to ns/iter.
+fn written(files: usize) -> u64 {
+    files as u64 * u64::from(N_BYTES - 1)
+}
+
 /// Generates random vector of N_BYTES bytes.
 fn gen_bytes() -> Vec<u8> {
     use rand::prelude::*;
+    let _phase = info_span!("generate").entered();
     let mut rng = rand::thread_rng();
     (1..N_BYTES).map(|_| rng.gen::<u8>()).collect()
 }
 
-/// Simulates random lengthy computation.
-async fn compute() {
-    use async_std::task;
-    use std::time::Duration;
+/// Payload of the SHA-256 computation, generated once, on first use.
+fn sha256_payload() -> &'static [u8] {
+    use std::sync::OnceLock;
 
-    task::sleep(Duration::from_nanos(COMPUTE_NANOS)).await;
+    static PAYLOAD: OnceLock<Vec<u8>> = OnceLock::new();
+    PAYLOAD.get_or_init(gen_bytes)
 }
 
-/// Computes and writes file synchronously.
-fn compute_write() {
-    use async_std::task;
+/// Lengthy computation done alongside writing the file.
+#[derive(Clone, Copy)]
+enum Compute {
+    /// Sleeps for the given nanoseconds, which measures the timer rather than computation.
+    Sleep(u64),
+    /// Hashes a payload of N_BYTES bytes with SHA-256 the given number of times.
+    Sha256(usize),
+    /// Counts the primes below SIEVE_LIMIT with a sieve of Eratosthenes.
+    Sieve,
+}
 
-    write_file();
-    task::block_on(compute());
+/// Runs the lengthy computation on runtime R, sleeping on its own timer; the CPU-bound ones hold
+/// the thread polling the future until done.
+async fn compute<R: Runtime>(kind: Compute) {
+    match kind {
+        Compute::Sleep(nanos) => {
+            R::sleep(Duration::from_nanos(nanos))
+                .instrument(info_span!("compute"))
+                .await
+        }
+        Compute::Sha256(_) | Compute::Sieve => compute_blocking(kind),
+    }
 }
 
-/// Computes and writes file asynchronously with the use of async_std::task and async_std::fs.
-async fn compute_write_async_std() {
-    use async_std::task;
-    let write_handle = task::spawn(write_file_async_std());
-    let compute_handle = task::spawn(compute());
-    write_handle.await;
-    compute_handle.await;
+/// Runs the lengthy computation blocking the current thread.
+fn compute_blocking(kind: Compute) {
+    use std::hint::black_box;
+
+    let _phase = info_span!("compute").entered();
+    match kind {
+        Compute::Sleep(nanos) => std::thread::sleep(Duration::from_nanos(nanos)),
+        Compute::Sha256(rounds) => {
+            black_box(sha256(sha256_payload(), rounds));
+        }
+        Compute::Sieve => {
+            black_box(count_primes(SIEVE_LIMIT));
+        }
+    }
 }
 
-/// Computes and writes file asynchronously with the use of futures::join and async_std::fs.
-async fn compute_write_async_std_futures() {
-    use futures::join;
-    let write_future = write_file_async_std();
-    let compute_future = compute();
-    join!(write_future, compute_future);
+/// Hashes payload rounds times, feeding every digest into the next round.
+fn sha256(payload: &[u8], rounds: usize) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+
+    let mut digest = [0; 32];
+    for _ in 0..rounds {
+        let mut hasher = Sha256::new();
+        hasher.update(digest);
+        hasher.update(payload);
+        digest = hasher.finalize().into();
+    }
+    digest
 }
 
-/// Computes and writes file asynchronously with the use of tokio::join and tokio::fs.
-async fn compute_write_tokio() {
-    let write_future = write_file_async_std();
-    let compute_future = compute();
-    tokio::join!(write_future, compute_future);
+/// Counts the primes below limit with a sieve of Eratosthenes.
+fn count_primes(limit: usize) -> usize {
+    let mut composite = vec![false; limit];
+    let mut primes = 0;
+    for n in 2..limit {
+        if !composite[n] {
+            primes += 1;
+            for multiple in (n * n..limit).step_by(n) {
+                composite[multiple] = true;
+            }
+        }
+    }
+    primes
 }
 
-/// Writes file asynchronously in a temporary directory with the use of async_std::fs.
-async fn write_file_async_std() {
-    use tempfile::tempdir;
-    use async_std::fs::File;
-    use async_std::prelude::*;
+/// Computes and writes files synchronously, one after another.
+fn compute_write(kind: Compute, files: usize) {
+    for _ in 0..files {
+        write_file();
+    }
+    compute_blocking(kind);
+}
 
-    let dir = tempdir().unwrap();
-    let mut file = File::create(dir.path().join("temp_file")).await.unwrap();
-    file.write_all(&gen_bytes()).await.unwrap()
+/// The executor, files, timer and blocking pool of one runtime. Workloads and benchmarks are generic over
+/// it and only reach the runtime they are given, so a benchmark of one runtime cannot end up
+/// calling the file or timer APIs of another.
+trait Runtime: 'static {
+    /// Starts the runtime.
+    fn start() -> Self;
+
+    /// Runs future to completion, blocking the current thread.
+    fn block_on<F: Future>(&self, future: F) -> F::Output;
+
+    /// Writes file asynchronously in a temporary directory.
+    fn write_file() -> impl Future<Output = ()> + Send;
+
+    /// Sleeps for duration on the runtime's timer.
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send;
+
+    /// Runs both futures to completion alongside each other.
+    fn join<A, B>(a: A, b: B) -> impl Future<Output = ()> + Send
+    where
+        A: Future<Output = ()> + Send + 'static,
+        B: Future<Output = ()> + Send + 'static;
+
+    /// Runs f on the blocking pool, off the executor's workers.
+    fn spawn_blocking<F: FnOnce() + Send + 'static>(f: F) -> impl Future<Output = ()> + Send;
 }
 
-/// Writes file asynchronously in a temporary directory with the use of tokio::fs.
-async fn write_file_tokio() {
-    use tempfile::tempdir;
-    use tokio::fs::File;
-    use tokio::prelude::*;
+/// async_std::task and async_std::fs, joining futures by spawning them as tasks.
+struct AsyncStd;
+
+impl Runtime for AsyncStd {
+    fn start() -> Self {
+        AsyncStd
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        async_std::task::block_on(future)
+    }
+
+    async fn write_file() {
+        use tempfile::tempdir;
+        use async_std::fs::File;
+        use async_std::prelude::*;
+
+        let dir = tempdir().unwrap();
+        let mut file = File::create(dir.path().join("temp_file")).await.unwrap();
+        file.write_all(&gen_bytes()).await.unwrap()
+    }
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+        async_std::task::sleep(duration)
+    }
+
+    async fn join<A, B>(a: A, b: B)
+    where
+        A: Future<Output = ()> + Send + 'static,
+        B: Future<Output = ()> + Send + 'static,
+    {
+        use async_std::task;
+
+        let (a, b) = {
+            let _phase = info_span!("spawn").entered();
+            (task::spawn(a), task::spawn(b))
+        };
+        a.await;
+        b.await;
+    }
+
+    fn spawn_blocking<F: FnOnce() + Send + 'static>(f: F) -> impl Future<Output = ()> + Send {
+        info_span!("spawn").in_scope(|| async_std::task::spawn_blocking(f))
+    }
+}
 
-    let dir = tempdir().unwrap();
-    let mut file = File::create(dir.path().join("temp_file")).await.unwrap();
-    file.write_all(&gen_bytes()).await.unwrap()
+/// async_std::fs polled by futures::executor, joining futures with futures::join and sleeping on
+/// async-io's Timer, which is what async_std's sleep is built on; futures has no files or timer of
+/// its own.
+struct AsyncStdFutures;
+
+impl Runtime for AsyncStdFutures {
+    fn start() -> Self {
+        AsyncStdFutures
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        futures::executor::block_on(future)
+    }
+
+    fn write_file() -> impl Future<Output = ()> + Send {
+        AsyncStd::write_file()
+    }
+
+    async fn sleep(duration: Duration) {
+        smol::Timer::after(duration).await;
+    }
+
+    async fn join<A, B>(a: A, b: B)
+    where
+        A: Future<Output = ()> + Send + 'static,
+        B: Future<Output = ()> + Send + 'static,
+    {
+        futures::join!(a, b);
+    }
+
+    fn spawn_blocking<F: FnOnce() + Send + 'static>(f: F) -> impl Future<Output = ()> + Send {
+        AsyncStd::spawn_blocking(f)
+    }
+}
+
+/// tokio's multi-threaded runtime and tokio::fs, joining futures with tokio::join.
+struct Tokio(tokio::runtime::Runtime);
+
+impl Runtime for Tokio {
+    fn start() -> Self {
+        Tokio(tokio::runtime::Runtime::new().unwrap())
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.0.block_on(future)
+    }
+
+    async fn write_file() {
+        use tempfile::tempdir;
+        use tokio::fs::File;
+        use tokio::io::AsyncWriteExt;
+
+        let dir = tempdir().unwrap();
+        let mut file = File::create(dir.path().join("temp_file")).await.unwrap();
+        file.write_all(&gen_bytes()).await.unwrap()
+    }
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(duration)
+    }
+
+    async fn join<A, B>(a: A, b: B)
+    where
+        A: Future<Output = ()> + Send + 'static,
+        B: Future<Output = ()> + Send + 'static,
+    {
+        tokio::join!(a, b);
+    }
+
+    async fn spawn_blocking<F: FnOnce() + Send + 'static>(f: F) {
+        let task = info_span!("spawn").in_scope(|| tokio::task::spawn_blocking(f));
+        task.await.unwrap()
+    }
+}
+
+/// Computes and writes files asynchronously on runtime R.
+async fn compute_write_on<R: Runtime>(kind: Compute, files: usize) {
+    R::join(write_files::<R>(files), compute::<R>(kind))
+        .instrument(info_span!("join"))
+        .await;
+}
+
+/// Writes files asynchronously on runtime R while computing in its spawn_blocking, off the
+/// executor's workers.
+async fn compute_blocking_write_on<R: Runtime>(kind: Compute, files: usize) {
+    R::join(write_files::<R>(files), R::spawn_blocking(move || compute_blocking(kind)))
+        .instrument(info_span!("join"))
+        .await;
+}
+
+/// Writes files one after another on runtime R.
+async fn write_files<R: Runtime>(files: usize) {
+    for _ in 0..files {
+        R::write_file().instrument(info_span!("write")).await;
+    }
 }
 
 /// Writes file synchronously in temporary directory with the use of std::fs.
@@ -84,84 +296,346 @@ fn write_file() {
     use std::fs::File;
     use std::io::prelude::*;
 
+    let _phase = info_span!("write").entered();
     let dir = tempdir().unwrap();
     let mut file = File::create(dir.path().join("temp_file")).unwrap();
     file.write_all(&gen_bytes()).unwrap()
 }
 
+/// Awaits tokio::fs inside async_std, which fails as tokio's file operations need a tokio runtime
+/// to run on; returns the panic message if it does.
+fn write_file_tokio_in_async_std() -> Result<(), String> {
+    use std::panic;
+
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let written = panic::catch_unwind(|| AsyncStd.block_on(Tokio::write_file()));
+    panic::set_hook(hook);
+    written.map_err(|payload| match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or("unknown panic", |m| *m).to_owned(),
+    })
+}
+
 // Benchmarks
 
 fn bench_write_file(bench: &mut Bencher) {
+    phases::scenario("bench_write_file");
+    bench.bytes = written(1);
     bench.iter(|| {
         write_file();
     });
 }
 
-fn bench_write_file_async_std(bench: &mut Bencher) {
-    use async_std::task;
+fn bench_write_file_on<R: Runtime>(bench: &mut Bencher) {
+    let rt = R::start();
+    bench.bytes = written(1);
 
     bench.iter(|| {
-        task::block_on(async {
-            write_file_async_std().await;
-        });
+        rt.block_on(R::write_file());
     });
 }
 
+fn bench_write_file_async_std(bench: &mut Bencher) {
+    phases::scenario("bench_write_file_async_std");
+    bench_write_file_on::<AsyncStd>(bench);
+}
+
 fn bench_write_file_tokio(bench: &mut Bencher) {
-    use tokio::runtime::Runtime;
+    phases::scenario("bench_write_file_tokio");
+    bench_write_file_on::<Tokio>(bench);
+}
 
-    let mut rt = Runtime::new().unwrap();
+// Benchmarks of accidentally mixed runtimes: futures of one runtime's IO awaited inside another,
+// which the Runtime trait only allows by naming both.
+
+/// Awaits async_std::fs inside tokio, which works as async_std hands file operations to its own
+/// blocking pool wherever its futures are polled.
+fn bench_write_file_async_std_in_tokio(bench: &mut Bencher) {
+    phases::scenario("bench_write_file_async_std_in_tokio");
+    let rt = Tokio::start();
+    bench.bytes = written(1);
 
     bench.iter(|| {
-        rt.block_on(async {
-            write_file_async_std().await;
-        });
+        rt.block_on(AsyncStd::write_file());
     });
 }
 
-fn bench_compute_write(bench: &mut Bencher) {
+/// Awaits tokio::fs inside async_std; reports the failure and measures nothing if it fails,
+/// which it does unless a tokio runtime is entered around it.
+fn bench_write_file_tokio_in_async_std(bench: &mut Bencher) {
+    use std::sync::Once;
+
+    // Bencher calls the benchmark over and over to find the number of iterations.
+    static REPORTED: Once = Once::new();
+
+    phases::scenario("bench_write_file_tokio_in_async_std");
+    if let Err(message) = write_file_tokio_in_async_std() {
+        REPORTED.call_once(|| {
+            eprintln!("bench_write_file_tokio_in_async_std failed, not measured: {}", message);
+        });
+        return;
+    }
+
+    bench.bytes = written(1);
     bench.iter(|| {
-        compute_write();
+        write_file_tokio_in_async_std().unwrap();
     });
 }
 
-fn bench_compute_write_async_std(bench: &mut Bencher) {
-    use async_std::task;
-
+fn bench_compute_write_kind(bench: &mut Bencher, kind: Compute, files: usize) {
+    // The SHA-256 payload, generated before measuring so that it is not counted as hashing.
+    sha256_payload();
+    bench.bytes = written(files);
     bench.iter(|| {
-        task::block_on(async {
-            compute_write_async_std().await;
-        });
+        compute_write(kind, files);
     });
 }
 
-fn bench_compute_write_async_std_futures(bench: &mut Bencher) {
-    use futures::executor::block_on;
+fn bench_compute_write_on<R: Runtime>(bench: &mut Bencher, kind: Compute, files: usize) {
+    let rt = R::start();
+    sha256_payload();
+    bench.bytes = written(files);
 
     bench.iter(|| {
-        block_on(async {
-            compute_write_async_std_futures().await;
-        });
+        rt.block_on(compute_write_on::<R>(kind, files));
     });
 }
 
-fn bench_compute_write_tokio(bench: &mut Bencher) {
-    use tokio::runtime::Runtime;
-
-    let mut rt = Runtime::new().unwrap();
+fn bench_compute_blocking_write_on<R: Runtime>(bench: &mut Bencher, kind: Compute, files: usize) {
+    let rt = R::start();
+    sha256_payload();
+    bench.bytes = written(files);
 
     bench.iter(|| {
-        rt.block_on(async {
-            compute_write_async_std_futures().await;
-        });
+        rt.block_on(compute_blocking_write_on::<R>(kind, files));
     });
 }
 
+/// Defines the compute and write benchmarks of every runtime for one kind of computation and
+/// number of files.
+macro_rules! compute_write_benches {
+    ($kind:expr, $files:expr, $sync:ident, $async_std:ident, $async_std_futures:ident, $tokio:ident) => {
+        fn $sync(bench: &mut Bencher) {
+            phases::scenario(stringify!($sync));
+            bench_compute_write_kind(bench, $kind, $files);
+        }
+
+        fn $async_std(bench: &mut Bencher) {
+            phases::scenario(stringify!($async_std));
+            bench_compute_write_on::<AsyncStd>(bench, $kind, $files);
+        }
+
+        fn $async_std_futures(bench: &mut Bencher) {
+            phases::scenario(stringify!($async_std_futures));
+            bench_compute_write_on::<AsyncStdFutures>(bench, $kind, $files);
+        }
+
+        fn $tokio(bench: &mut Bencher) {
+            phases::scenario(stringify!($tokio));
+            bench_compute_write_on::<Tokio>(bench, $kind, $files);
+        }
+    };
+}
+
+compute_write_benches!(Compute::Sleep(COMPUTE_NANOS), 1,
+ bench_compute_write,
+ bench_compute_write_async_std,
+ bench_compute_write_async_std_futures,
+ bench_compute_write_tokio);
+
+// Benchmarks named bench_sleep_<duration>_write sweep the sleeping computation next to one file
+// write from much shorter than the write to much longer, bench_compute_write being the 2 ms point,
+// so where overlapping the sleep with the write starts to pay off shows up between points.
+
+compute_write_benches!(Compute::Sleep(10_000), 1,
+ bench_sleep_10us_write,
+ bench_sleep_10us_write_async_std,
+ bench_sleep_10us_write_async_std_futures,
+ bench_sleep_10us_write_tokio);
+
+compute_write_benches!(Compute::Sleep(100_000), 1,
+ bench_sleep_100us_write,
+ bench_sleep_100us_write_async_std,
+ bench_sleep_100us_write_async_std_futures,
+ bench_sleep_100us_write_tokio);
+
+compute_write_benches!(Compute::Sleep(50_000_000), 1,
+ bench_sleep_50ms_write,
+ bench_sleep_50ms_write_async_std,
+ bench_sleep_50ms_write_async_std_futures,
+ bench_sleep_50ms_write_tokio);
+
+/// Defines the benchmarks of every runtime computing in spawn_blocking for one kind of
+/// computation and number of files.
+macro_rules! compute_blocking_write_benches {
+    ($kind:expr, $files:expr, $async_std:ident, $tokio:ident) => {
+        fn $async_std(bench: &mut Bencher) {
+            phases::scenario(stringify!($async_std));
+            bench_compute_blocking_write_on::<AsyncStd>(bench, $kind, $files);
+        }
+
+        fn $tokio(bench: &mut Bencher) {
+            phases::scenario(stringify!($tokio));
+            bench_compute_blocking_write_on::<Tokio>(bench, $kind, $files);
+        }
+    };
+}
+
+compute_write_benches!(Compute::Sha256(SHA256_ROUNDS), 1,
+ bench_sha256_write,
+ bench_sha256_write_async_std,
+ bench_sha256_write_async_std_futures,
+ bench_sha256_write_tokio);
+
+compute_write_benches!(Compute::Sieve, 1,
+ bench_sieve_write,
+ bench_sieve_write_async_std,
+ bench_sieve_write_async_std_futures,
+ bench_sieve_write_tokio);
+
 benchmark_group!(compute_write_group, bench_compute_write,
  bench_compute_write_async_std,
   bench_compute_write_async_std_futures,
    bench_compute_write_tokio);
 
+benchmark_group!(sleep_sweep_group, bench_sleep_10us_write,
+ bench_sleep_10us_write_async_std,
+ bench_sleep_10us_write_async_std_futures,
+ bench_sleep_10us_write_tokio,
+ bench_sleep_100us_write,
+ bench_sleep_100us_write_async_std,
+ bench_sleep_100us_write_async_std_futures,
+ bench_sleep_100us_write_tokio,
+ bench_sleep_50ms_write,
+ bench_sleep_50ms_write_async_std,
+ bench_sleep_50ms_write_async_std_futures,
+ bench_sleep_50ms_write_tokio);
+
+compute_blocking_write_benches!(Compute::Sha256(SHA256_ROUNDS), 1,
+ bench_sha256_blocking_write_async_std,
+ bench_sha256_blocking_write_tokio);
+
+compute_blocking_write_benches!(Compute::Sieve, 1,
+ bench_sieve_blocking_write_async_std,
+ bench_sieve_blocking_write_tokio);
+
+// Benchmarks named bench_ratio_<compute>_<io> do compute units of hashing next to io file writes,
+// so where the async versions stop beating the synchronous one shows where overlap stops paying off.
+
+compute_write_benches!(Compute::Sha256(10 * RATIO_UNIT_ROUNDS), 1,
+ bench_ratio_10_1,
+ bench_ratio_10_1_async_std,
+ bench_ratio_10_1_async_std_futures,
+ bench_ratio_10_1_tokio);
+
+compute_write_benches!(Compute::Sha256(5 * RATIO_UNIT_ROUNDS), 1,
+ bench_ratio_5_1,
+ bench_ratio_5_1_async_std,
+ bench_ratio_5_1_async_std_futures,
+ bench_ratio_5_1_tokio);
+
+compute_write_benches!(Compute::Sha256(2 * RATIO_UNIT_ROUNDS), 1,
+ bench_ratio_2_1,
+ bench_ratio_2_1_async_std,
+ bench_ratio_2_1_async_std_futures,
+ bench_ratio_2_1_tokio);
+
+compute_write_benches!(Compute::Sha256(RATIO_UNIT_ROUNDS), 1,
+ bench_ratio_1_1,
+ bench_ratio_1_1_async_std,
+ bench_ratio_1_1_async_std_futures,
+ bench_ratio_1_1_tokio);
+
+compute_write_benches!(Compute::Sha256(RATIO_UNIT_ROUNDS), 2,
+ bench_ratio_1_2,
+ bench_ratio_1_2_async_std,
+ bench_ratio_1_2_async_std_futures,
+ bench_ratio_1_2_tokio);
+
+compute_write_benches!(Compute::Sha256(RATIO_UNIT_ROUNDS), 5,
+ bench_ratio_1_5,
+ bench_ratio_1_5_async_std,
+ bench_ratio_1_5_async_std_futures,
+ bench_ratio_1_5_tokio);
+
+compute_write_benches!(Compute::Sha256(RATIO_UNIT_ROUNDS), 10,
+ bench_ratio_1_10,
+ bench_ratio_1_10_async_std,
+ bench_ratio_1_10_async_std_futures,
+ bench_ratio_1_10_tokio);
+
+benchmark_group!(sha256_write_group, bench_sha256_write,
+ bench_sha256_write_async_std,
+  bench_sha256_write_async_std_futures,
+   bench_sha256_write_tokio,
+    bench_sha256_blocking_write_async_std,
+    bench_sha256_blocking_write_tokio);
+
+benchmark_group!(sieve_write_group, bench_sieve_write,
+ bench_sieve_write_async_std,
+  bench_sieve_write_async_std_futures,
+   bench_sieve_write_tokio,
+    bench_sieve_blocking_write_async_std,
+    bench_sieve_blocking_write_tokio);
+
+benchmark_group!(ratio_group, bench_ratio_10_1,
+ bench_ratio_10_1_async_std,
+ bench_ratio_10_1_async_std_futures,
+ bench_ratio_10_1_tokio,
+ bench_ratio_5_1,
+ bench_ratio_5_1_async_std,
+ bench_ratio_5_1_async_std_futures,
+ bench_ratio_5_1_tokio,
+ bench_ratio_2_1,
+ bench_ratio_2_1_async_std,
+ bench_ratio_2_1_async_std_futures,
+ bench_ratio_2_1_tokio,
+ bench_ratio_1_1,
+ bench_ratio_1_1_async_std,
+ bench_ratio_1_1_async_std_futures,
+ bench_ratio_1_1_tokio,
+ bench_ratio_1_2,
+ bench_ratio_1_2_async_std,
+ bench_ratio_1_2_async_std_futures,
+ bench_ratio_1_2_tokio,
+ bench_ratio_1_5,
+ bench_ratio_1_5_async_std,
+ bench_ratio_1_5_async_std_futures,
+ bench_ratio_1_5_tokio,
+ bench_ratio_1_10,
+ bench_ratio_1_10_async_std,
+ bench_ratio_1_10_async_std_futures,
+ bench_ratio_1_10_tokio);
+
 benchmark_group!(write_files_group, bench_write_file_async_std, bench_write_file, bench_write_file_tokio);
 
-benchmark_main!(write_files_group, compute_write_group);
\ No newline at end of file
+benchmark_group!(mixed_group, bench_write_file_async_std_in_tokio, bench_write_file_tokio_in_async_std);
+
+/// What benchmark_main! does, but with the phase timings installed when PHASES is set and printed
+/// after the benchmarks.
+fn main() {
+    use bencher::{run_tests_console, TestOpts};
+
+    let timed = std::env::var_os("PHASES").is_some();
+    if timed {
+        phases::install();
+    }
+
+    let mut test_opts = TestOpts::default();
+    if let Some(arg) = std::env::args().skip(1).find(|arg| *arg != "--bench") {
+        test_opts.filter = Some(arg);
+    }
+    let mut benches = Vec::new();
+    for group in [write_files_group, mixed_group, compute_write_group, sleep_sweep_group,
+                  sha256_write_group, sieve_write_group, ratio_group] {
+        benches.extend(group());
+    }
+    run_tests_console(&test_opts, benches).unwrap();
+
+    if timed {
+        for phase in phases::take() {
+            println!("{}", phase);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,180 @@
+use async_runtimes_cmp::config::{env_list, env_or};
+use async_runtimes_cmp::harness::Runner;
+use async_runtimes_cmp::timers::interval;
+use async_runtimes_cmp::timers::many;
+use async_runtimes_cmp::timers::paused;
+use async_runtimes_cmp::timers::saturated::{self, Load};
+use async_runtimes_cmp::timers::sleep;
+use async_runtimes_cmp::timers::timeout::{self, Outcome, Wrap};
+use std::time::Duration;
+
+/// Lengths of the sleeps in nanoseconds, each swept in its own run.
+const SLEEP_NANOS: &[u64] = &[100, 1_000, 1_000_000, 100_000_000];
+
+/// Most sleeps per run.
+const SLEEP_COUNT: usize = 1_000;
+
+/// Time the sleeps of a run may take together at most, in milliseconds, which
+/// limits the number of long sleeps.
+const SLEEP_BUDGET_MS: u64 = 2_000;
+
+/// Numbers of timers pending at once, each swept in its own run.
+const TIMERS: &[usize] = &[100_000, 1_000_000];
+
+/// Time over which the timers' deadlines are spread, in milliseconds.
+const TIMERS_SPREAD_MS: u64 = 2_000;
+
+/// Steps the paused clock is advanced by in the deterministic timer runs, in
+/// microseconds, each swept in its own run.
+const TIMERS_PAUSED_STEPS_US: &[u64] = &[100, 1_000, 10_000];
+
+/// Period of the interval, in microseconds.
+const INTERVAL_PERIOD_US: u64 = 1_000;
+
+/// Numbers of busy tasks next to the interval, each swept in its own run.
+const INTERVAL_BUSY_TASKS: &[usize] = &[0, 16];
+
+/// Number of ticks measured per run.
+const INTERVAL_TICKS: usize = 2_000;
+
+/// Number of awaits per timeout run when the timeout never fires.
+const TIMEOUT_ROUNDS: usize = 200_000;
+
+/// Number of awaits per timeout run when the timeout fires, fewer as tokio
+/// takes a millisecond to fire even a zero timeout.
+const TIMEOUT_FIRED_ROUNDS: usize = 2_000;
+
+/// Numbers of CPU-bound tasks next to the sleeper, each swept in its own run.
+const SATURATED_TASKS: &[usize] = &[0, 16, 64];
+
+/// Time every CPU-bound task spins for between yields, in microseconds.
+const SATURATED_CHUNK_US: u64 = 1_000;
+
+/// Length of the sleeps, in milliseconds.
+const SATURATED_SLEEP_MS: u64 = 10;
+
+/// Number of sleeps per run.
+const SATURATED_SLEEPS: usize = 100;
+
+fn bench_sleep(runner: &Runner) {
+    let count = env_or("SLEEP_COUNT", SLEEP_COUNT);
+    let budget = Duration::from_millis(env_or("SLEEP_BUDGET_MS", SLEEP_BUDGET_MS));
+
+    for nanos in env_list("SLEEP_NANOS", SLEEP_NANOS) {
+        let requested = Duration::from_nanos(nanos);
+        let sleeps = count
+            .min((budget.as_nanos() / requested.as_nanos()) as usize)
+            .max(1);
+        runner.run(&format!("sleep_tokio/{}", nanos), || {
+            sleep::tokio(requested, sleeps)
+        });
+        runner.run(&format!("sleep_async_std/{}", nanos), || {
+            sleep::async_std(requested, sleeps)
+        });
+        runner.run(&format!("sleep_smol/{}", nanos), || {
+            sleep::smol(requested, sleeps)
+        });
+        runner.run(&format!("sleep_thread/{}", nanos), || {
+            sleep::thread(requested, sleeps)
+        });
+    }
+}
+
+fn bench_many(runner: &Runner) {
+    let spread = Duration::from_millis(env_or("TIMERS_SPREAD_MS", TIMERS_SPREAD_MS));
+
+    for timers in env_list("TIMERS", TIMERS) {
+        runner.run(&format!("timers_tokio/{}", timers), || {
+            many::tokio(timers, spread)
+        });
+        runner.run(&format!("timers_async_std/{}", timers), || {
+            many::async_std(timers, spread)
+        });
+        runner.run(&format!("timers_smol/{}", timers), || {
+            many::smol(timers, spread)
+        });
+        for step in env_list("TIMERS_PAUSED_STEPS_US", TIMERS_PAUSED_STEPS_US) {
+            runner.run(&format!("timers_tokio_paused/{}/{}", timers, step), || {
+                paused::tokio(timers, spread, Duration::from_micros(step))
+            });
+        }
+    }
+}
+
+fn bench_interval(runner: &Runner) {
+    let period = Duration::from_micros(env_or("INTERVAL_PERIOD_US", INTERVAL_PERIOD_US));
+    let ticks = env_or("INTERVAL_TICKS", INTERVAL_TICKS);
+
+    for busy in env_list("INTERVAL_BUSY_TASKS", INTERVAL_BUSY_TASKS) {
+        runner.run(&format!("interval_tokio/{}", busy), || {
+            interval::tokio(period, busy, ticks)
+        });
+        runner.run(&format!("interval_async_std/{}", busy), || {
+            interval::async_std(period, busy, ticks)
+        });
+        runner.run(&format!("interval_smol/{}", busy), || {
+            interval::smol(period, busy, ticks)
+        });
+    }
+}
+
+fn bench_timeout(runner: &Runner) {
+    let completed = env_or("TIMEOUT_ROUNDS", TIMEOUT_ROUNDS);
+    let fired = env_or("TIMEOUT_FIRED_ROUNDS", TIMEOUT_FIRED_ROUNDS);
+
+    for &(name, wrap, rounds) in &[
+        ("plain", Wrap::Plain, completed),
+        (
+            "runtime_completes",
+            Wrap::Runtime(Outcome::Completes),
+            completed,
+        ),
+        ("runtime_fires", Wrap::Runtime(Outcome::Fires), fired),
+        (
+            "futures_timer_completes",
+            Wrap::FuturesTimer(Outcome::Completes),
+            completed,
+        ),
+        (
+            "futures_timer_fires",
+            Wrap::FuturesTimer(Outcome::Fires),
+            fired,
+        ),
+    ] {
+        runner.run(&format!("timeout_tokio/{}", name), || {
+            timeout::tokio(wrap, rounds)
+        });
+        runner.run(&format!("timeout_async_std/{}", name), || {
+            timeout::async_std(wrap, rounds)
+        });
+    }
+}
+
+fn bench_saturated(runner: &Runner) {
+    let chunk = Duration::from_micros(env_or("SATURATED_CHUNK_US", SATURATED_CHUNK_US));
+    let requested = Duration::from_millis(env_or("SATURATED_SLEEP_MS", SATURATED_SLEEP_MS));
+    let sleeps = env_or("SATURATED_SLEEPS", SATURATED_SLEEPS);
+
+    for tasks in env_list("SATURATED_TASKS", SATURATED_TASKS) {
+        let load = Load { tasks, chunk };
+        runner.run(&format!("saturated_sleep_tokio/{}", tasks), || {
+            saturated::tokio(load, requested, sleeps)
+        });
+        runner.run(&format!("saturated_sleep_async_std/{}", tasks), || {
+            saturated::async_std(load, requested, sleeps)
+        });
+        runner.run(&format!("saturated_sleep_smol/{}", tasks), || {
+            saturated::smol(load, requested, sleeps)
+        });
+    }
+}
+
+fn main() {
+    let runner = Runner::from_args();
+
+    bench_sleep(&runner);
+    bench_many(&runner);
+    bench_interval(&runner);
+    bench_timeout(&runner);
+    bench_saturated(&runner);
+}
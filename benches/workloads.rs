@@ -0,0 +1,236 @@
+use async_runtimes_cmp::config::{env_list, env_or};
+use async_runtimes_cmp::harness::Runner;
+use async_runtimes_cmp::workloads::cache::{self, Actor, Cache, Mix};
+use async_runtimes_cmp::workloads::ledger::{self, Ledger, Owned, Shared, Transfers};
+use async_runtimes_cmp::workloads::logs;
+use async_runtimes_cmp::workloads::priority::{self, Flood, Queue};
+use async_runtimes_cmp::workloads::replay::{self, Trace};
+use async_runtimes_cmp::workloads::sqlite;
+use async_runtimes_cmp::workloads::thumbnails::{self, Images};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Size of the generated log file, in bytes.
+const LOG_BYTES: u64 = 256 << 20;
+
+/// Number of tasks sharing the cache.
+const CACHE_TASKS: usize = 256;
+
+/// Number of keys in the cache.
+const CACHE_KEYS: u64 = 100_000;
+
+/// Percentage of cache operations that are writes.
+const CACHE_WRITE_PERCENT: u32 = 10;
+
+/// Number of cache operations per run.
+const CACHE_OPERATIONS: usize = 1_000_000;
+
+/// Number of tasks making transfers through the ledger.
+const LEDGER_TASKS: usize = 256;
+
+/// Number of accounts in the ledger.
+const LEDGER_ACCOUNTS: usize = 1_000;
+
+/// Number of transfers per run.
+const LEDGER_TRANSFERS: usize = 1_000_000;
+
+/// Number of tasks running transactions against the database, each with
+/// its own connection.
+const SQLITE_TASKS: usize = 8;
+
+/// Number of rows in the table before every run.
+const SQLITE_ROWS: u64 = 100_000;
+
+/// Percentage of transactions that are inserts.
+const SQLITE_WRITE_PERCENT: u32 = 20;
+
+/// Number of transactions per run.
+const SQLITE_TRANSACTIONS: usize = 50_000;
+
+/// Number of images to make thumbnails of per run.
+const THUMBNAIL_IMAGES: usize = 64;
+
+/// Width of the images in pixels.
+const THUMBNAIL_SOURCE_WIDTH: u32 = 1_920;
+
+/// Height of the images in pixels.
+const THUMBNAIL_SOURCE_HEIGHT: u32 = 1_080;
+
+/// Number of images in flight at the same time, and threads of the rayon
+/// pool.
+const THUMBNAIL_CONCURRENCY: usize = 4;
+
+/// Most threads tokio's blocking pool may have, each swept in its own run;
+/// 512 is tokio's default.
+const THUMBNAIL_BLOCKING_THREADS: &[usize] = &[1, 2, 4, 16, 512];
+
+/// Most threads the blocking crate's pool, used by async_std, has when
+/// `BLOCKING_MAX_THREADS` is not set.
+const ASYNC_STD_BLOCKING_THREADS: usize = 500;
+
+/// Number of workers taking items off the priority queue.
+const PRIORITY_WORKERS: usize = 4;
+
+/// Number of low-priority items queued at all times.
+const PRIORITY_BACKLOG: usize = 1_024;
+
+/// Time a worker spins for on every item, in microseconds.
+const PRIORITY_WORK_US: u64 = 10;
+
+/// Number of high-priority items per run.
+const PRIORITY_HIGH: usize = 1_000;
+
+/// Time between two high-priority items, in microseconds.
+const PRIORITY_INTERVAL_US: u64 = 1_000;
+
+/// Factor the recorded trace is sped up by.
+const REPLAY_SPEED: f64 = 1.0;
+
+fn bench_logs(runner: &Runner) {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("log");
+    let summary = dir.path().join("summary");
+    logs::write_log(&log, env_or("LOG_BYTES", LOG_BYTES));
+
+    runner.run("logs_tokio", || logs::tokio(&log, &summary));
+    runner.run("logs_async_std", || logs::async_std(&log, &summary));
+    runner.run("logs_smol", || logs::smol(&log, &summary));
+    runner.run("logs_std_io", || logs::std_io(&log, &summary));
+}
+
+fn bench_cache(runner: &Runner) {
+    let mix = Mix {
+        tasks: env_or("CACHE_TASKS", CACHE_TASKS),
+        keys: env_or("CACHE_KEYS", CACHE_KEYS),
+        writes: f64::from(env_or("CACHE_WRITE_PERCENT", CACHE_WRITE_PERCENT)) / 100.0,
+        operations: env_or("CACHE_OPERATIONS", CACHE_OPERATIONS),
+    };
+
+    cache_on_both::<parking_lot::RwLock<HashMap<u64, u64>>>(runner, "rwlock", mix);
+    cache_on_both::<dashmap::DashMap<u64, u64>>(runner, "dashmap", mix);
+    cache_on_both::<Actor>(runner, "actor", mix);
+}
+
+/// Runs the cache scenario of `C` on both runtimes.
+fn cache_on_both<C: Cache>(runner: &Runner, name: &str, mix: Mix) {
+    runner.run(&format!("cache_{}_tokio", name), || {
+        cache::on_tokio::<C>(mix)
+    });
+    runner.run(&format!("cache_{}_async_std", name), || {
+        cache::on_async_std::<C>(mix)
+    });
+}
+
+fn bench_ledger(runner: &Runner) {
+    let transfers = Transfers {
+        tasks: env_or("LEDGER_TASKS", LEDGER_TASKS),
+        accounts: env_or("LEDGER_ACCOUNTS", LEDGER_ACCOUNTS),
+        transfers: env_or("LEDGER_TRANSFERS", LEDGER_TRANSFERS),
+    };
+
+    ledger_on_both::<Shared>(runner, "shared", transfers);
+    ledger_on_both::<Owned>(runner, "owned", transfers);
+}
+
+/// Runs the ledger scenario of `L` on both runtimes.
+fn ledger_on_both<L: Ledger>(runner: &Runner, name: &str, transfers: Transfers) {
+    runner.run(&format!("ledger_{}_tokio", name), || {
+        ledger::on_tokio::<L>(transfers)
+    });
+    runner.run(&format!("ledger_{}_async_std", name), || {
+        ledger::on_async_std::<L>(transfers)
+    });
+}
+
+fn bench_sqlite(runner: &Runner) {
+    let mix = sqlite::Mix {
+        tasks: env_or("SQLITE_TASKS", SQLITE_TASKS),
+        rows: env_or("SQLITE_ROWS", SQLITE_ROWS),
+        writes: f64::from(env_or("SQLITE_WRITE_PERCENT", SQLITE_WRITE_PERCENT)) / 100.0,
+        transactions: env_or("SQLITE_TRANSACTIONS", SQLITE_TRANSACTIONS),
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db");
+
+    runner.run("sqlite_sqlx_tokio", || sqlite::tokio(&path, mix));
+    runner.run("sqlite_sqlx_async_std", || sqlite::async_std(&path, mix));
+    runner.run("sqlite_rusqlite_threads", || {
+        sqlite::rusqlite_threads(&path, mix)
+    });
+}
+
+fn bench_thumbnails(runner: &Runner) {
+    let images = Images {
+        count: env_or("THUMBNAIL_IMAGES", THUMBNAIL_IMAGES),
+        width: env_or("THUMBNAIL_SOURCE_WIDTH", THUMBNAIL_SOURCE_WIDTH),
+        height: env_or("THUMBNAIL_SOURCE_HEIGHT", THUMBNAIL_SOURCE_HEIGHT),
+    };
+    let concurrency = env_or("THUMBNAIL_CONCURRENCY", THUMBNAIL_CONCURRENCY);
+    let dir = tempfile::tempdir().unwrap();
+    thumbnails::write_images(dir.path(), images);
+
+    for threads in env_list("THUMBNAIL_BLOCKING_THREADS", THUMBNAIL_BLOCKING_THREADS) {
+        runner.run(&format!("thumbnails_tokio/{}", threads), || {
+            thumbnails::tokio(dir.path(), images, concurrency, threads)
+        });
+    }
+    // The blocking crate reads this once, when its pool first starts.
+    let threads = env_or("BLOCKING_MAX_THREADS", ASYNC_STD_BLOCKING_THREADS);
+    runner.run(&format!("thumbnails_async_std/{}", threads), || {
+        thumbnails::async_std(dir.path(), images, concurrency)
+    });
+    runner.run("thumbnails_rayon", || {
+        thumbnails::rayon(dir.path(), images, concurrency)
+    });
+}
+
+fn bench_priority(runner: &Runner) {
+    let flood = Flood {
+        workers: env_or("PRIORITY_WORKERS", PRIORITY_WORKERS),
+        backlog: env_or("PRIORITY_BACKLOG", PRIORITY_BACKLOG),
+        work: Duration::from_micros(env_or("PRIORITY_WORK_US", PRIORITY_WORK_US)),
+        high: env_or("PRIORITY_HIGH", PRIORITY_HIGH),
+        interval: Duration::from_micros(env_or("PRIORITY_INTERVAL_US", PRIORITY_INTERVAL_US)),
+    };
+
+    for &(name, queue) in &[("fifo", Queue::Fifo), ("prioritized", Queue::Prioritized)] {
+        runner.run(&format!("priority_{}_tokio", name), || {
+            priority::tokio(queue, flood)
+        });
+        runner.run(&format!("priority_{}_async_std", name), || {
+            priority::async_std(queue, flood)
+        });
+    }
+}
+
+/// Replays the trace named by `REPLAY_TRACE`, if it is set.
+fn bench_replay(runner: &Runner) {
+    let path = match std::env::var("REPLAY_TRACE") {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+    let jsonl = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("cannot read {}: {}", path, err));
+    let trace = Trace::parse(&jsonl).unwrap_or_else(|err| panic!("{}: {}", path, err));
+    let speed = env_or("REPLAY_SPEED", REPLAY_SPEED);
+    let dir = tempfile::tempdir().unwrap();
+    replay::prepare(&trace, dir.path());
+
+    runner.run("replay_tokio", || replay::tokio(&trace, dir.path(), speed));
+    runner.run("replay_async_std", || {
+        replay::async_std(&trace, dir.path(), speed)
+    });
+    runner.run("replay_smol", || replay::smol(&trace, dir.path(), speed));
+}
+
+fn main() {
+    let runner = Runner::from_args();
+
+    bench_logs(&runner);
+    bench_cache(&runner);
+    bench_ledger(&runner);
+    bench_sqlite(&runner);
+    bench_thumbnails(&runner);
+    bench_priority(&runner);
+    bench_replay(&runner);
+}
@@ -0,0 +1,10 @@
+fn main() {
+    // Use the vendored protoc so that building does not depend on a system install.
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+    // Clients are built from an explicit `Channel`, so the generated `connect`
+    // helper is not needed.
+    tonic_prost_build::configure()
+        .build_transport(false)
+        .compile_protos(&["proto/bench.proto"], &["proto"])
+        .unwrap();
+}
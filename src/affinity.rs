@@ -0,0 +1,86 @@
+//! Pinning the benchmark to a fixed set of cores, so that runs do not vary
+//! with where the scheduler happens to put the threads, and tokio's workers
+//! to a core each.
+//!
+//! With `PIN_CORES` set to a comma-separated list of core ids, the
+//! [`Runner`](crate::harness::Runner) confines the whole process to those
+//! cores on Linux before any scenario starts, and every thread started later,
+//! the workers of every runtime included, inherits the set. With
+//! `PIN_WORKERS` set too, every thread a runtime built with
+//! [`runtime`](crate::metrics::runtime) starts is pinned to one core of the
+//! set, round-robin, so that with as many workers as cores each worker has a
+//! core of its own. The threads of tokio's blocking pool are threads of the
+//! runtime too and are pinned the same way. async_std's and smol's global
+//! executors start their threads themselves, so they are only confined.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// Cores the threads of every tokio runtime are pinned to, if any.
+static WORKER_CORES: OnceLock<Vec<usize>> = OnceLock::new();
+
+/// Confines the process to `cores`, or to the current thread and the threads
+/// it starts from now on, which is all of them if called first thing.
+#[cfg(target_os = "linux")]
+pub(crate) fn confine(cores: &[usize]) {
+    // SAFETY: `set` is a valid, zeroed `cpu_set_t` the calls only touch
+    // through the pointers the libc functions expect.
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for &core in cores {
+            libc::CPU_SET(core, &mut set);
+        }
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    assert_eq!(
+        result,
+        0,
+        "cannot pin to cores {:?}: {}",
+        cores,
+        std::io::Error::last_os_error()
+    );
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn confine(_cores: &[usize]) {
+    panic!("PIN_CORES needs Linux");
+}
+
+/// Pins the threads of tokio runtimes built from now on to `cores`.
+pub(crate) fn pin_workers(cores: Vec<usize>) {
+    let _ = WORKER_CORES.set(cores);
+}
+
+/// A hook for a runtime to call on every thread it starts, pinning the
+/// thread to the next core, or `None` if the workers are not pinned. Exits
+/// if a thread cannot be pinned, rather than report unpinned runs as pinned.
+pub(crate) fn pinning() -> Option<impl Fn() + Send + Sync + 'static> {
+    let cores = WORKER_CORES.get()?;
+    let next = Arc::new(AtomicUsize::new(0));
+    Some(move || {
+        let core = cores[next.fetch_add(1, Ordering::Relaxed) % cores.len()];
+        if !pin(core) {
+            eprintln!(
+                "cannot pin a worker to core {}: is it one of the cores the process may run on?",
+                core
+            );
+            std::process::exit(1);
+        }
+    })
+}
+
+/// Pins the current thread to `core`, and returns whether it could.
+fn pin(core: usize) -> bool {
+    core_affinity::set_for_current(core_affinity::CoreId { id: core })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_a_thread_to_a_core_it_has() {
+        let core = core_affinity::get_core_ids().unwrap()[0].id;
+        assert!(std::thread::spawn(move || pin(core)).join().unwrap());
+    }
+}
@@ -0,0 +1,133 @@
+//! Counting every allocation the process makes, with a global allocator that
+//! wraps the system one, behind the `count-allocations` feature.
+//!
+//! With the feature on, [`Runner`](crate::harness::Runner) prints how many
+//! allocations each scenario made and how many bytes they asked for, next to
+//! its report. A reallocation counts as an allocation of its new size. The
+//! counters are shared by all threads and cost an atomic add per allocation,
+//! so timings taken with the feature on are a little slower than without.
+//! Without it the counters stay at zero.
+//!
+//! The `heap-profile` feature installs dhat's allocator instead, for
+//! `--heap-profile` runs; only one of the two can be on at a time.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting what is allocated through it.
+pub struct Counting;
+
+impl Counting {
+    fn count(size: usize) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Counting::count(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Counting::count(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Counting::count(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(all(feature = "count-allocations", feature = "heap-profile"))]
+compile_error!("count-allocations and heap-profile both need the global allocator");
+
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[cfg(feature = "heap-profile")]
+#[global_allocator]
+static GLOBAL: dhat::Alloc = dhat::Alloc;
+
+/// Allocations made and bytes asked for, since the process started or
+/// between two counts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Allocations {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+impl Allocations {
+    /// The allocations made so far.
+    pub fn now() -> Self {
+        Allocations {
+            count: ALLOCATIONS.load(Ordering::Relaxed),
+            bytes: BYTES.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The allocations made between `earlier` and these.
+    pub fn since(self, earlier: Allocations) -> Self {
+        Allocations {
+            count: self.count - earlier.count,
+            bytes: self.bytes - earlier.bytes,
+        }
+    }
+}
+
+impl fmt::Display for Allocations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "allocs {:>10}  {:>9.1} MiB",
+            self.count,
+            self.bytes as f64 / (1 << 20) as f64
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "count-allocations")]
+    fn counts_allocations() {
+        let before = Allocations::now();
+        let buffer = std::hint::black_box(vec![0u8; 4096]);
+        let made = Allocations::now().since(before);
+        drop(buffer);
+        assert!(made.count >= 1);
+        assert!(made.bytes >= 4096);
+    }
+
+    #[test]
+    fn since_subtracts() {
+        let earlier = Allocations {
+            count: 3,
+            bytes: 100,
+        };
+        let later = Allocations {
+            count: 10,
+            bytes: 612,
+        };
+        assert_eq!(
+            later.since(earlier),
+            Allocations {
+                count: 7,
+                bytes: 512
+            }
+        );
+    }
+}
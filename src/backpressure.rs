@@ -0,0 +1,170 @@
+//! Fast producers feeding a slow consumer through a bounded channel, which
+//! holds them back.
+//!
+//! Every producer sends as fast as the channel takes its messages; the
+//! consumer spins for a fixed time on every message it receives, so the
+//! channel is full nearly all the time and the producers spend most of the
+//! run waiting for room. After the run's duration the consumer drops its end
+//! of the channel, which stops the producers.
+//!
+//! The throughput is the consumer's and should be close to one message per
+//! unit of its work. Latencies are the stalls, the time every send waited for
+//! room, and the fairness index is over the messages each producer got in.
+//! The channels are tokio's mpsc on tokio and async-channel, which is
+//! `async_std::channel`, on async_std.
+
+use crate::harness::{fairness, latency_histogram, record, LoadReport};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// What the producers and the consumer do.
+#[derive(Clone, Copy, Debug)]
+pub struct Pipe {
+    pub producers: usize,
+    /// Messages the channel holds.
+    pub capacity: usize,
+    /// Time the consumer spins for every message.
+    pub work: Duration,
+    pub duration: Duration,
+}
+
+/// The consumer's throughput, the producers' stalls and how evenly the
+/// producers got their messages in.
+pub struct BackpressureReport {
+    /// Messages consumed, with the stalls of the sends as latencies.
+    pub load: LoadReport,
+    /// Jain's fairness index over the messages sent by every producer.
+    pub fairness: f64,
+}
+
+impl fmt::Display for BackpressureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  fairness {:.3}", self.load, self.fairness)
+    }
+}
+
+impl BackpressureReport {
+    fn new(start: Instant, consumed: usize, producers: Vec<Vec<Duration>>) -> Self {
+        let mut stalls = latency_histogram();
+        let sent: Vec<_> = producers.iter().map(Vec::len).collect();
+        for stall in producers.into_iter().flatten() {
+            record(&mut stalls, stall);
+        }
+        BackpressureReport {
+            load: LoadReport::since(start, consumed, stalls),
+            fairness: fairness(&sent),
+        }
+    }
+}
+
+/// Spins for `work`.
+fn spin(work: Duration) {
+    let start = Instant::now();
+    while start.elapsed() < work {
+        std::hint::spin_loop();
+    }
+}
+
+/// Runs `pipe` on tokio with a `tokio::sync::mpsc` channel.
+pub fn tokio(pipe: Pipe) -> BackpressureReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(pipe.capacity);
+        let start = Instant::now();
+        let producers: Vec<_> = (0..pipe.producers)
+            .map(|_| {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let mut stalls = Vec::new();
+                    loop {
+                        let asked = Instant::now();
+                        if sender.send(asked).await.is_err() {
+                            return stalls;
+                        }
+                        stalls.push(asked.elapsed());
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        let consumed = tokio::spawn(async move {
+            let mut consumed = 0;
+            while start.elapsed() < pipe.duration {
+                receiver.recv().await.unwrap();
+                spin(pipe.work);
+                consumed += 1;
+            }
+            consumed
+        })
+        .await
+        .unwrap();
+        let mut stalls = Vec::with_capacity(pipe.producers);
+        for producer in producers {
+            stalls.push(producer.await.unwrap());
+        }
+        BackpressureReport::new(start, consumed, stalls)
+    })
+}
+
+/// Runs `pipe` on async_std with an async-channel channel.
+pub fn async_std(pipe: Pipe) -> BackpressureReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (sender, receiver) = async_channel::bounded(pipe.capacity);
+        let start = Instant::now();
+        let producers: Vec<_> = (0..pipe.producers)
+            .map(|_| {
+                let sender = sender.clone();
+                task::spawn(async move {
+                    let mut stalls = Vec::new();
+                    loop {
+                        let asked = Instant::now();
+                        if sender.send(asked).await.is_err() {
+                            return stalls;
+                        }
+                        stalls.push(asked.elapsed());
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+        let consumed = task::spawn(async move {
+            let mut consumed = 0;
+            while start.elapsed() < pipe.duration {
+                receiver.recv().await.unwrap();
+                spin(pipe.work);
+                consumed += 1;
+            }
+            consumed
+        })
+        .await;
+        let mut stalls = Vec::with_capacity(pipe.producers);
+        for producer in producers {
+            stalls.push(producer.await);
+        }
+        BackpressureReport::new(start, consumed, stalls)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_consumer_sets_the_pace() {
+        let pipe = Pipe {
+            producers: 4,
+            capacity: 8,
+            work: Duration::from_micros(100),
+            duration: Duration::from_millis(50),
+        };
+        for run in [tokio, async_std] {
+            let report = run(pipe);
+            // At most one message per unit of work, with some to spare for
+            // the last one that started before the deadline.
+            assert!(report.load.requests <= 501);
+            assert!(report.load.latencies.len() >= report.load.requests);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Runs benchmarks built with every combination of toolchains, opt-levels
+//! and LTO settings and prints their reports side by side, see
+//! `async_runtimes_cmp::builds`.
+//!
+//! Usage: `builds [--toolchain <name>]... [--opt-level <level>]...
+//! [--lto <off|thin|fat>]... [--matrix] <bench>... [-- <args>...]`. The
+//! toolchain defaults to `stable`, the opt-level to 3 and LTO to off. The
+//! results of all builds are written to `builds.txt` in the current
+//! directory. Arguments after `--`, like a scenario filter, are passed to
+//! every benchmark.
+
+use async_runtimes_cmp::builds::Build;
+use async_runtimes_cmp::results;
+use std::env;
+use std::fs;
+use std::process;
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: builds [--toolchain <name>]... [--opt-level <level>]... \
+         [--lto <off|thin|fat>]... [--matrix] <bench>... [-- <args>...]"
+    );
+    process::exit(2);
+}
+
+/// `values`, or `default` alone if there are none.
+fn or_default(values: Vec<String>, default: &str) -> Vec<String> {
+    if values.is_empty() {
+        vec![default.to_owned()]
+    } else {
+        values
+    }
+}
+
+fn main() {
+    let mut toolchains = Vec::new();
+    let mut opt_levels = Vec::new();
+    let mut ltos = Vec::new();
+    let mut matrix = false;
+    let mut benches = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--toolchain" => toolchains.push(args.next().unwrap_or_else(|| usage())),
+            "--opt-level" => opt_levels.push(args.next().unwrap_or_else(|| usage())),
+            "--lto" => ltos.push(args.next().unwrap_or_else(|| usage())),
+            "--matrix" => matrix = true,
+            "--" => break,
+            _ => benches.push(arg),
+        }
+    }
+    let passed: Vec<_> = args.collect();
+    if benches.is_empty() {
+        usage();
+    }
+    let builds = Build::combinations(
+        &or_default(toolchains, "stable"),
+        &or_default(opt_levels, "3"),
+        &or_default(ltos, "off"),
+    );
+
+    // Absolute, since cargo runs the benchmarks in the package directory.
+    let path = env::current_dir().unwrap().join("builds.txt");
+    let _ = fs::remove_file(&path);
+    for build in &builds {
+        for bench in &benches {
+            eprintln!("running {} built with {}", bench, build);
+            build.bench(bench, &passed, &path).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                process::exit(1);
+            });
+        }
+    }
+    let contents = fs::read_to_string(&path).unwrap();
+    let runs = results::parse(&contents);
+    if matrix {
+        print!("{}", results::Matrix::new(&runs));
+        return;
+    }
+    for (name, reports) in results::merge(&runs) {
+        for (label, report) in reports {
+            println!("{:<48} {:<40} {}", name, label, report);
+        }
+    }
+}
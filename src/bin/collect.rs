@@ -0,0 +1,78 @@
+//! Runs cross-compiled benchmark binaries on other machines over SSH and
+//! merges their results, see `async_runtimes_cmp::remote`.
+//!
+//! Usage: `collect --host <user@host>... [--env NAME=VALUE]... [--matrix]
+//! <binary>... [-- <args>...]`. Saves the result file of every host as
+//! `<host>.txt` in the current directory, then prints the reports of every
+//! scenario on every host like `merge_results`, or with `--matrix` the
+//! matrix of runtimes against systems. Arguments after `--`, like a
+//! scenario filter, are passed to every binary.
+
+use async_runtimes_cmp::remote::{self, Host};
+use async_runtimes_cmp::results;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: collect --host <user@host>... [--env NAME=VALUE]... [--matrix] \
+         <binary>... [-- <args>...]"
+    );
+    process::exit(2);
+}
+
+fn main() {
+    let mut hosts = Vec::new();
+    let mut vars = Vec::new();
+    let mut matrix = false;
+    let mut binaries = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => hosts.push(Host {
+                address: args.next().unwrap_or_else(|| usage()),
+            }),
+            "--env" => {
+                let var = args.next().unwrap_or_else(|| usage());
+                let (name, value) = var.split_once('=').unwrap_or_else(|| usage());
+                vars.push((name.to_owned(), value.to_owned()));
+            }
+            "--matrix" => matrix = true,
+            "--" => break,
+            _ => binaries.push(arg),
+        }
+    }
+    let passed: Vec<_> = args.collect();
+    if hosts.is_empty() || binaries.is_empty() {
+        usage();
+    }
+    let binaries: Vec<_> = binaries.iter().map(Path::new).collect();
+
+    let mut runs = Vec::new();
+    for host in &hosts {
+        let collected = remote::collect(host, &binaries, &vars, &passed).unwrap_or_else(|err| {
+            eprintln!("cannot collect from {}: {}", host.address, err);
+            process::exit(1);
+        });
+        let file = format!(
+            "{}.txt",
+            host.address.replace(|c: char| c == '/' || c == ':', "-")
+        );
+        fs::write(&file, &collected).unwrap_or_else(|err| {
+            eprintln!("cannot write {}: {}", file, err);
+            process::exit(1);
+        });
+        runs.extend(results::parse(&collected));
+    }
+    if matrix {
+        print!("{}", results::Matrix::new(&runs));
+        return;
+    }
+    for (name, reports) in results::merge(&runs) {
+        for (label, report) in reports {
+            println!("{:<48} {:<24} {}", name, label, report);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Runs the RPC scenarios of the `net` benchmark over a real network: the
+//! echo server on one machine and the clients on others, all over SSH, see
+//! `async_runtimes_cmp::remote`.
+//!
+//! Usage: `coordinate --server <user@host> --client <user@host>...
+//! --echo-server <binary> [--port <port>] [--matrix] <net binary>`. For
+//! every runtime of the server, tokio and async_std, starts `echo_server`
+//! on the server machine, listening on the port, 7000 by default, runs the
+//! `rpc_remote_*` scenarios of the `net` benchmark on every client machine
+//! at once against it, then stops it. Saves the result file of every client
+//! as `<host>.txt` in the current directory and prints the reports of every
+//! scenario on every client, or with `--matrix` the matrix of runtimes
+//! against clients.
+
+use async_runtimes_cmp::remote::{self, Host};
+use async_runtimes_cmp::results;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+
+/// Runtimes the server runs on in turn.
+const SERVERS: [&str; 2] = ["tokio", "async_std"];
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: coordinate --server <user@host> --client <user@host>... \
+         --echo-server <binary> [--port <port>] [--matrix] <net binary>"
+    );
+    process::exit(2);
+}
+
+fn fail(what: &str, err: impl std::fmt::Display) -> ! {
+    eprintln!("{}: {}", what, err);
+    process::exit(1);
+}
+
+fn main() {
+    let mut server = None;
+    let mut clients = Vec::new();
+    let mut echo_server = None;
+    let mut port = 7000;
+    let mut matrix = false;
+    let mut net = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--server" => server = Some(Host { address: value() }),
+            "--client" => clients.push(Host { address: value() }),
+            "--echo-server" => echo_server = Some(PathBuf::from(value())),
+            "--port" => port = value().parse().unwrap_or_else(|_| usage()),
+            "--matrix" => matrix = true,
+            _ => net = Some(PathBuf::from(arg)),
+        }
+    }
+    let (server, echo_server, net) = match (server, echo_server, net) {
+        (Some(server), Some(echo_server), Some(net)) if !clients.is_empty() => {
+            (server, echo_server, net)
+        }
+        _ => usage(),
+    };
+
+    let mut collected = vec![String::new(); clients.len()];
+    for runtime in SERVERS.iter().copied() {
+        let listen = format!("0.0.0.0:{}", port);
+        let (mut running, _) = server
+            .start(&echo_server, &[runtime, &listen])
+            .unwrap_or_else(|err| {
+                fail(
+                    &format!("cannot start the server on {}", server.address),
+                    err,
+                )
+            });
+        let env = [
+            (
+                "RPC_SERVER".to_owned(),
+                format!("{}:{}", server.name(), port),
+            ),
+            ("RPC_SERVER_RUNTIME".to_owned(), runtime.to_owned()),
+        ];
+        let filter = ["rpc_remote_".to_owned()];
+        thread::scope(|scope| {
+            let runs: Vec<_> = clients
+                .iter()
+                .map(|client| {
+                    let (net, env, filter) = (&net, &env, &filter);
+                    scope.spawn(move || remote::collect(client, &[net.as_path()], env, filter))
+                })
+                .collect();
+            for ((run, client), results) in runs.into_iter().zip(&clients).zip(&mut collected) {
+                match run.join().unwrap() {
+                    Ok(run) => results.push_str(&run),
+                    Err(err) => fail(&format!("cannot collect from {}", client.address), err),
+                }
+            }
+        });
+        // Closing its input stops the server.
+        drop(running.stdin.take());
+        let _ = running.wait();
+    }
+
+    let mut runs = Vec::new();
+    for (client, collected) in clients.iter().zip(&collected) {
+        let file = format!(
+            "{}.txt",
+            client.address.replace(|c: char| c == '/' || c == ':', "-")
+        );
+        fs::write(&file, collected)
+            .unwrap_or_else(|err| fail(&format!("cannot write {}", file), err));
+        for mut run in results::parse(collected) {
+            // Clients on the same system would share a label otherwise.
+            run.system.build = client.name().to_owned();
+            runs.push(run);
+        }
+    }
+    if matrix {
+        print!("{}", results::Matrix::new(&runs));
+        return;
+    }
+    for (name, reports) in results::merge(&runs) {
+        for (label, report) in reports {
+            println!("{:<48} {:<40} {}", name, label, report);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Serves the result files written with `RESULTS` as comparison pages, see
+//! `async_runtimes_cmp::dashboard`.
+//!
+//! Usage: `dashboard <file>...`. Listens on `DASHBOARD_ADDR`,
+//! `127.0.0.1:8080` by default, and reads the files again for every page,
+//! so runs appended to them meanwhile show up on reload. A page takes the
+//! workload to show and the runtime to list as the `workload` and `runtime`
+//! query parameters, which its pickers set.
+
+use async_runtimes_cmp::dashboard::{self, History};
+use std::env;
+use std::process;
+use std::sync::Arc;
+use tide::http::mime;
+use tide::{Request, Response};
+
+async fn show(req: Request<Arc<Vec<String>>>) -> tide::Result {
+    let history = History::load(req.state().as_slice())?;
+    let (mut workload, mut runtime) = (None, None);
+    for (key, value) in req.url().query_pairs() {
+        match &*key {
+            "workload" if !value.is_empty() => workload = Some(value.into_owned()),
+            "runtime" if !value.is_empty() => runtime = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+    let page = dashboard::page(&history, workload.as_deref(), runtime.as_deref());
+    Ok(Response::builder(200)
+        .body(page)
+        .content_type(mime::HTML)
+        .build())
+}
+
+#[async_std::main]
+async fn main() -> std::io::Result<()> {
+    let paths: Vec<_> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: dashboard <file>...");
+        process::exit(2);
+    }
+    let addr = env::var("DASHBOARD_ADDR").unwrap_or_else(|_| "127.0.0.1:8080".to_owned());
+    let mut app = tide::with_state(Arc::new(paths));
+    app.at("/").get(show);
+    println!("serving on http://{}", addr);
+    app.listen(addr).await
+}
@@ -0,0 +1,42 @@
+//! Length-prefixed echo server for running the server side of a benchmark in
+//! its own process, see `harness::ServerProcess`.
+//!
+//! Usage: `echo_server <tokio|async_std> [<address>]`. Listens on a free
+//! loopback port, or on `address` for clients on other machines, prints the
+//! address it listens on and serves until its standard input is closed.
+
+use async_runtimes_cmp::harness::{listener, loopback_listener};
+use async_runtimes_cmp::rpc::length_prefixed;
+use std::env;
+use std::io::{self, Read};
+use std::process;
+use std::thread;
+
+fn main() {
+    let runtime = env::args().nth(1).unwrap_or_else(|| {
+        eprintln!("usage: echo_server <tokio|async_std> [<address>]");
+        process::exit(2);
+    });
+    let serve = match runtime.as_str() {
+        "tokio" => length_prefixed::tokio_server,
+        "async_std" => length_prefixed::async_std_server,
+        other => {
+            eprintln!("unknown runtime {:?}, expected tokio or async_std", other);
+            process::exit(2);
+        }
+    };
+
+    let listener = match env::args().nth(2) {
+        Some(addr) => listener(addr.parse().unwrap_or_else(|err| {
+            eprintln!("invalid address {:?}: {}", addr, err);
+            process::exit(2);
+        })),
+        None => loopback_listener(),
+    };
+    println!("{}", listener.local_addr().unwrap());
+    thread::spawn(|| {
+        let _ = io::stdin().read_to_end(&mut Vec::new());
+        process::exit(0);
+    });
+    serve(listener);
+}
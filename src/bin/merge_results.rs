@@ -0,0 +1,57 @@
+//! Merges result files written with `RESULTS` on different systems into one
+//! comparison, see `async_runtimes_cmp::results`.
+//!
+//! Usage: `merge_results [--matrix] <file>...`. Prints the systems the runs
+//! were on with their notes, then the report of every scenario on every
+//! system, one after another, or with `--matrix` the headline figure of
+//! every workload by runtime against the systems.
+
+use async_runtimes_cmp::results;
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+    let matrix = env::args().any(|arg| arg == "--matrix");
+    let paths: Vec<_> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+    if paths.is_empty() {
+        eprintln!("usage: merge_results [--matrix] <file>...");
+        process::exit(2);
+    }
+    let runs: Vec<_> = paths
+        .iter()
+        .flat_map(|path| {
+            let contents = fs::read_to_string(path).unwrap_or_else(|err| {
+                eprintln!("cannot read {}: {}", path, err);
+                process::exit(1);
+            });
+            results::parse(&contents)
+        })
+        .collect();
+
+    let mut labels = Vec::new();
+    for run in &runs {
+        let label = run.system.label();
+        if labels.contains(&label) {
+            continue;
+        }
+        println!("{:<24} {}", label, run.system);
+        for note in &run.system.notes {
+            println!("{:<24}   {}", "", note);
+        }
+        labels.push(label);
+    }
+    println!();
+    if matrix {
+        print!("{}", results::Matrix::new(&runs));
+        return;
+    }
+    for (name, reports) in results::merge(&runs) {
+        for (label, report) in reports {
+            println!("{:<48} {:<24} {}", name, label, report);
+        }
+    }
+}
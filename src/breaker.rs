@@ -0,0 +1,339 @@
+//! Calls to a flaky backend through a bulkhead and a circuit breaker.
+//!
+//! The backend is in-process and answers after a fixed latency, except during
+//! an outage in the middle of the run, when its calls never complete. Calls go
+//! through a bulkhead first, a semaphore that rejects calls beyond a fixed
+//! number in flight instead of queueing them, then through a circuit breaker
+//! that opens after a number of consecutive failed calls, rejects everything
+//! for a cooldown and then lets a single probe call decide whether to close
+//! again. A call fails when the runtime's timer cuts it off after a timeout.
+//!
+//! The breaker and the bulkhead are the same code on both runtimes; only the
+//! timers that end the calls, time their timeouts and pace the callers while
+//! the breaker is open are the runtime's. How long after the outage starts the
+//! breaker opens, and how long after it ends the breaker closes again, then
+//! shows how quickly those timers and the wakeups behind them let the breaker
+//! react.
+
+use crate::harness::{latency_histogram, record, LoadReport, Millis};
+use futures::future::{self, Either};
+use futures::pin_mut;
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Time callers wait before asking again while the breaker is open.
+const BACKOFF: Duration = Duration::from_millis(1);
+
+/// A backend that answers after `latency` and stops answering for `outage`
+/// once `healthy_for` has passed since the start of the run.
+#[derive(Clone, Copy, Debug)]
+pub struct Flaky {
+    pub latency: Duration,
+    pub healthy_for: Duration,
+    pub outage: Duration,
+}
+
+impl Flaky {
+    /// Makes one call, `since` the start of the run, waiting with `sleep`.
+    async fn call<F, Fut>(&self, since: Duration, sleep: &F)
+    where
+        F: Fn(Duration) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        if since >= self.healthy_for && since < self.healthy_for + self.outage {
+            future::pending::<()>().await;
+        }
+        sleep(self.latency).await;
+    }
+}
+
+/// How calls are protected from the backend.
+#[derive(Clone, Copy, Debug)]
+pub struct Policy {
+    /// Calls allowed in flight at the same time; the bulkhead rejects any more.
+    pub bulkhead: usize,
+    /// Time after which a call counts as failed.
+    pub timeout: Duration,
+    /// Consecutive failed calls that open the breaker.
+    pub threshold: u32,
+    /// Time the breaker stays open before letting a probe call through.
+    pub cooldown: Duration,
+}
+
+/// Outcome of a run: the successful calls, the rejected ones and when the
+/// breaker reacted to the outage.
+pub struct BreakerReport {
+    /// Throughput and latencies of the successful calls.
+    pub load: LoadReport,
+    /// Calls the bulkhead turned away.
+    pub rejected: u64,
+    /// Calls the open breaker turned away.
+    pub short_circuited: u64,
+    /// Time from the start of the outage until the breaker opened.
+    pub opened_after: Option<Duration>,
+    /// Time from the end of the outage until the breaker closed again.
+    pub closed_after: Option<Duration>,
+}
+
+impl fmt::Display for BreakerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.load)?;
+        for &(label, delay) in &[("opened", self.opened_after), ("closed", self.closed_after)] {
+            match delay {
+                Some(delay) => write!(f, "  {} after {}", label, Millis(delay.as_nanos() as u64))?,
+                None => write!(f, "  never {}", label)?,
+            }
+        }
+        write!(
+            f,
+            "  rejected {}  short-circuited {}",
+            self.rejected, self.short_circuited
+        )
+    }
+}
+
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen,
+}
+
+/// A circuit breaker that remembers when it opened and closed.
+struct Breaker {
+    policy: Policy,
+    state: Mutex<(State, Vec<(Instant, bool)>)>,
+}
+
+impl Breaker {
+    fn new(policy: Policy) -> Self {
+        Breaker {
+            policy,
+            state: Mutex::new((State::Closed { failures: 0 }, Vec::new())),
+        }
+    }
+
+    /// Whether a call may go through now; an expired cooldown lets this one probe.
+    fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.0 {
+            State::Closed { .. } => true,
+            State::Open { until } if Instant::now() >= until => {
+                state.0 = State::HalfOpen;
+                true
+            }
+            State::Open { .. } | State::HalfOpen => false,
+        }
+    }
+
+    /// Records how an allowed call went.
+    fn record(&self, succeeded: bool) {
+        let mut guard = self.state.lock().unwrap();
+        let (state, transitions) = &mut *guard;
+        let now = Instant::now();
+        match (&mut *state, succeeded) {
+            (State::Closed { failures }, true) => *failures = 0,
+            (State::Closed { failures }, false) => {
+                *failures += 1;
+                if *failures >= self.policy.threshold {
+                    *state = State::Open {
+                        until: now + self.policy.cooldown,
+                    };
+                    transitions.push((now, true));
+                }
+            }
+            (State::HalfOpen, true) => {
+                *state = State::Closed { failures: 0 };
+                transitions.push((now, false));
+            }
+            (State::HalfOpen, false) => {
+                *state = State::Open {
+                    until: now + self.policy.cooldown,
+                }
+            }
+            // Calls that were in flight when the breaker opened.
+            (State::Open { .. }, _) => {}
+        }
+    }
+}
+
+/// What one caller saw.
+struct Calls {
+    latencies: Histogram<u64>,
+    rejected: u64,
+    short_circuited: u64,
+}
+
+/// Runs `tasks` tokio tasks calling `backend` under `policy` for `duration`.
+pub fn tokio(backend: Flaky, policy: Policy, tasks: usize, duration: Duration) -> BreakerReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let run = Arc::new(Run::new(backend, policy));
+        let callers: Vec<_> = (0..tasks)
+            .map(|_| tokio::spawn(run.clone().caller(duration, tokio::time::sleep)))
+            .collect();
+        let mut calls = Vec::with_capacity(tasks);
+        for caller in callers {
+            calls.push(caller.await.unwrap());
+        }
+        run.report(calls)
+    })
+}
+
+/// Runs `tasks` async_std tasks calling `backend` under `policy` for `duration`.
+pub fn async_std(
+    backend: Flaky,
+    policy: Policy,
+    tasks: usize,
+    duration: Duration,
+) -> BreakerReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let run = Arc::new(Run::new(backend, policy));
+        let callers: Vec<_> = (0..tasks)
+            .map(|_| task::spawn(run.clone().caller(duration, task::sleep)))
+            .collect();
+        let mut calls = Vec::with_capacity(tasks);
+        for caller in callers {
+            calls.push(caller.await);
+        }
+        run.report(calls)
+    })
+}
+
+/// State shared by the callers of one run.
+struct Run {
+    start: Instant,
+    backend: Flaky,
+    breaker: Breaker,
+    bulkhead: async_lock::Semaphore,
+}
+
+impl Run {
+    fn new(backend: Flaky, policy: Policy) -> Self {
+        Run {
+            start: Instant::now(),
+            backend,
+            breaker: Breaker::new(policy),
+            bulkhead: async_lock::Semaphore::new(policy.bulkhead),
+        }
+    }
+
+    /// Calls the backend until `duration` has passed, waiting with `sleep`.
+    async fn caller<F, Fut>(self: Arc<Self>, duration: Duration, sleep: F) -> Calls
+    where
+        F: Fn(Duration) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let mut calls = Calls {
+            latencies: latency_histogram(),
+            rejected: 0,
+            short_circuited: 0,
+        };
+        while self.start.elapsed() < duration {
+            let permit = match self.bulkhead.try_acquire() {
+                Some(permit) => permit,
+                None => {
+                    calls.rejected += 1;
+                    sleep(BACKOFF).await;
+                    continue;
+                }
+            };
+            if !self.breaker.allow() {
+                drop(permit);
+                calls.short_circuited += 1;
+                sleep(BACKOFF).await;
+                continue;
+            }
+            let started = Instant::now();
+            let call = self.backend.call(started - self.start, &sleep);
+            pin_mut!(call);
+            let timeout = sleep(self.breaker.policy.timeout);
+            pin_mut!(timeout);
+            let succeeded = match future::select(call, timeout).await {
+                Either::Left(_) => true,
+                Either::Right(_) => false,
+            };
+            drop(permit);
+            self.breaker.record(succeeded);
+            if succeeded {
+                record(&mut calls.latencies, started.elapsed());
+            }
+        }
+        calls
+    }
+
+    fn report(&self, calls: Vec<Calls>) -> BreakerReport {
+        let mut latencies = latency_histogram();
+        let mut rejected = 0;
+        let mut short_circuited = 0;
+        for caller in calls {
+            latencies.add(caller.latencies).unwrap();
+            rejected += caller.rejected;
+            short_circuited += caller.short_circuited;
+        }
+        let outage_start = self.start + self.backend.healthy_for;
+        let outage_end = outage_start + self.backend.outage;
+        let transitions = &self.breaker.state.lock().unwrap().1;
+        let first = |open: bool, after: Instant| {
+            transitions
+                .iter()
+                .find(|&&(at, opened)| opened == open && at >= after)
+                .map(|&(at, _)| at - after)
+        };
+        let successes = latencies.len() as usize;
+        BreakerReport {
+            load: LoadReport::since(self.start, successes, latencies),
+            rejected,
+            short_circuited,
+            opened_after: first(true, outage_start),
+            closed_after: first(false, outage_end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> Policy {
+        Policy {
+            bulkhead: 4,
+            timeout: Duration::from_millis(10),
+            threshold: 3,
+            cooldown: Duration::from_millis(20),
+        }
+    }
+
+    #[test]
+    fn breaker_opens_after_consecutive_failures() {
+        let breaker = Breaker::new(policy());
+        breaker.record(false);
+        breaker.record(false);
+        breaker.record(true);
+        breaker.record(false);
+        breaker.record(false);
+        assert!(breaker.allow());
+        breaker.record(false);
+        assert!(!breaker.allow());
+    }
+
+    #[test]
+    fn breaker_reacts_to_the_outage() {
+        let backend = Flaky {
+            latency: Duration::from_millis(1),
+            healthy_for: Duration::from_millis(50),
+            outage: Duration::from_millis(50),
+        };
+        for run in &[tokio, async_std] {
+            let report = run(backend, policy(), 8, Duration::from_millis(200));
+            let opened = report.opened_after.unwrap();
+            assert!(opened >= Duration::from_millis(10) && opened < Duration::from_millis(50));
+            assert!(report.closed_after.unwrap() < Duration::from_millis(50));
+            assert!(report.short_circuited > 0);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Running the same benchmarks built with other toolchains and profiles, to
+//! tell what differs between runtimes from what differs between compilers.
+//!
+//! Every [`Build`] is a rustc toolchain, as rustup names it, an opt-level
+//! and an LTO setting. [`Build::bench`] runs `cargo bench` through rustup's
+//! cargo with the toolchain and with the bench profile overridden through
+//! `CARGO_PROFILE_BENCH_*`, in a target directory of the build's own, so
+//! that switching between builds does not throw away the artifacts of the
+//! others. The runs append to a result file with `RESULTS_BUILD` naming the
+//! build, see [`results`](crate::results), so that the reports of every
+//! scenario under every build line up; the `builds` binary prints them.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A toolchain and profile to build the benchmarks with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Build {
+    /// The rustup toolchain, like `stable`, `nightly` or `1.80`.
+    pub toolchain: String,
+    pub opt_level: String,
+    /// `off`, `thin` or `fat`.
+    pub lto: String,
+}
+
+impl Build {
+    /// Every combination of `toolchains`, `opt_levels` and `ltos`.
+    pub fn combinations(
+        toolchains: &[String],
+        opt_levels: &[String],
+        ltos: &[String],
+    ) -> Vec<Self> {
+        let mut builds = Vec::new();
+        for toolchain in toolchains {
+            for opt_level in opt_levels {
+                for lto in ltos {
+                    builds.push(Build {
+                        toolchain: toolchain.clone(),
+                        opt_level: opt_level.clone(),
+                        lto: lto.clone(),
+                    });
+                }
+            }
+        }
+        builds
+    }
+
+    /// The target directory of this build, under `target`.
+    fn target_dir(&self, target: &Path) -> PathBuf {
+        let name = self
+            .to_string()
+            .replace(|c: char| !c.is_ascii_alphanumeric() && c != '.', "-");
+        target.join("builds").join(name)
+    }
+
+    /// Builds `bench` and runs it with `args`, appending its results to
+    /// `results`.
+    pub fn bench(&self, bench: &str, args: &[String], results: &Path) -> io::Result<()> {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let status = self
+            .command(manifest, bench, args)
+            .env("RESULTS", results)
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("{} of {} failed: {}", bench, self, status),
+            ));
+        }
+        Ok(())
+    }
+
+    fn command(&self, manifest: &Path, bench: &str, args: &[String]) -> Command {
+        // Not `$CARGO`, which is a toolchain's own cargo rather than
+        // rustup's proxy that understands `+toolchain`.
+        let mut command = Command::new("cargo");
+        command
+            .arg(format!("+{}", self.toolchain))
+            .args(["bench", "--bench", bench, "--manifest-path"])
+            .arg(manifest.join("Cargo.toml"))
+            .arg("--")
+            .args(args)
+            .env("CARGO_PROFILE_BENCH_OPT_LEVEL", &self.opt_level)
+            .env("CARGO_PROFILE_BENCH_LTO", &self.lto)
+            .env(
+                "CARGO_TARGET_DIR",
+                self.target_dir(&manifest.join("target")),
+            )
+            .env("RESULTS_BUILD", self.to_string());
+        command
+    }
+}
+
+impl fmt::Display for Build {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} O{} lto-{}", self.toolchain, self.opt_level, self.lto)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_every_combination_apart() {
+        let strings = |items: &[&str]| {
+            items
+                .iter()
+                .map(|&item| item.to_owned())
+                .collect::<Vec<_>>()
+        };
+        let builds = Build::combinations(
+            &strings(&["stable", "1.80"]),
+            &strings(&["2", "3"]),
+            &strings(&["off"]),
+        );
+        assert_eq!(builds.len(), 4);
+        assert_eq!(builds[3].to_string(), "1.80 O3 lto-off");
+        let command = builds[3].command(Path::new("/repo"), "tasks", &[]);
+        let target = command
+            .get_envs()
+            .find(|(name, _)| *name == "CARGO_TARGET_DIR")
+            .and_then(|(_, value)| value)
+            .unwrap();
+        assert_eq!(target, Path::new("/repo/target/builds/1.80-O3-lto-off"));
+    }
+}
@@ -0,0 +1,86 @@
+//! Running the benchmark under a CPU quota, on Linux, the way a container
+//! limited to a few cores runs it.
+//!
+//! The benchmark is run again in a cgroup of its own, created under the root
+//! of the cgroup v2 hierarchy with its `cpu.max` set to the quota, and the
+//! cgroup is removed once the run is over. The kernel throttles the threads
+//! of the cgroup for the rest of a period once they have used up the quota
+//! in it, however many cores are idle. The standard library reads the quota
+//! back in `available_parallelism`, so runtimes that size their worker pools
+//! from it, like tokio, start as many workers as the quota has cores.
+//! Creating a cgroup under the root usually needs root.
+
+use std::env;
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Where the cgroup v2 hierarchy is mounted.
+const HIERARCHY: &str = "/sys/fs/cgroup";
+
+/// Length of a period of the quota, in microseconds, the kernel's default.
+const PERIOD: u64 = 100_000;
+
+/// The `cpu.max` of a quota of `cores` cores per period.
+fn cpu_max(cores: f64) -> String {
+    format!("{} {}", (cores * PERIOD as f64).round() as u64, PERIOD)
+}
+
+/// Reruns the benchmark in a cgroup allowed `cores` cores' worth of CPU
+/// time and exits with its status.
+pub(crate) fn rerun_limited(cores: f64) -> ! {
+    assert!(cores > 0.0, "CPU_QUOTA must be a positive number of cores");
+    let name = format!("async-runtimes-cmp-{}", std::process::id());
+    let cgroup = PathBuf::from(HIERARCHY).join(name);
+    // The cpu controller must be on for the children of the root; it usually
+    // already is, and the quota cannot be set below if this fails.
+    let _ = fs::write(
+        PathBuf::from(HIERARCHY).join("cgroup.subtree_control"),
+        "+cpu",
+    );
+    fs::create_dir(&cgroup)
+        .unwrap_or_else(|err| panic!("cannot create the cgroup {}: {}", cgroup.display(), err));
+    let status = fs::write(cgroup.join("cpu.max"), cpu_max(cores))
+        .map_err(|err| format!("cannot set the quota: {}", err))
+        .and_then(|()| {
+            let procs = cgroup.join("cgroup.procs");
+            let procs = CString::new(procs.as_os_str().as_bytes()).unwrap();
+            let mut command = Command::new(env::current_exe().unwrap());
+            command.args(env::args_os().skip(1)).env_remove("CPU_QUOTA");
+            // SAFETY: the child only opens, writes and closes a file with a
+            // path prepared beforehand, which is safe between fork and exec.
+            unsafe {
+                command.pre_exec(move || {
+                    // Writing 0 moves the writing process into the cgroup.
+                    let fd = libc::open(procs.as_ptr(), libc::O_WRONLY);
+                    if fd < 0 || libc::write(fd, b"0".as_ptr().cast(), 1) != 1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    libc::close(fd);
+                    Ok(())
+                });
+            }
+            command
+                .status()
+                .map_err(|err| format!("cannot start the limited run: {}", err))
+        });
+    let _ = fs::remove_dir(&cgroup);
+    match status {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(message) => panic!("{}", message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quota_is_per_period() {
+        assert_eq!(cpu_max(2.0), "200000 100000");
+        assert_eq!(cpu_max(0.5), "50000 100000");
+    }
+}
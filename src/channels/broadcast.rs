@@ -0,0 +1,307 @@
+//! One sender broadcasting to a growing number of receivers.
+//!
+//! The sender sends its messages in bursts of `burst` and yields to the
+//! runtime between bursts, so with bursts larger than the channel's capacity
+//! the receivers cannot keep up. What happens then depends on the channel:
+//! `tokio::sync::broadcast` overwrites the oldest messages and the receivers
+//! that fall behind lose them, async-broadcast makes the sender wait for the
+//! slowest receiver, and async-broadcast in overflow mode overwrites like tokio.
+//! None of them depends on a runtime, so all are measured on both.
+//!
+//! Latencies are from the send until each delivery. The report also counts
+//! the lost messages, the receivers that lost any and the p99 of the receiver
+//! that fared worst.
+
+use crate::harness::{latency_histogram, record, LoadReport, Millis};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::time::Instant;
+
+/// What a receiver got from the channel.
+pub enum Received {
+    Message(Instant),
+    /// The receiver fell behind and this many messages were overwritten.
+    Lagged(u64),
+    Closed,
+}
+
+/// A broadcast channel of send times.
+pub trait Broadcast: 'static {
+    type Sender: Send + 'static;
+    type Receiver: Send + 'static;
+
+    /// Creates a channel of `capacity` with its first receiver.
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver);
+
+    /// Adds another receiver.
+    fn subscribe(sender: &Self::Sender) -> Self::Receiver;
+
+    fn send(sender: &Self::Sender, sent: Instant) -> impl Future<Output = ()> + Send + '_;
+
+    fn recv(receiver: &mut Self::Receiver) -> impl Future<Output = Received> + Send + '_;
+}
+
+/// `tokio::sync::broadcast`, which overwrites the oldest messages when full.
+pub struct TokioBroadcast;
+
+impl Broadcast for TokioBroadcast {
+    type Sender = tokio::sync::broadcast::Sender<Instant>;
+    type Receiver = tokio::sync::broadcast::Receiver<Instant>;
+
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver) {
+        tokio::sync::broadcast::channel(capacity)
+    }
+
+    fn subscribe(sender: &Self::Sender) -> Self::Receiver {
+        sender.subscribe()
+    }
+
+    async fn send(sender: &Self::Sender, sent: Instant) {
+        sender.send(sent).unwrap();
+    }
+
+    async fn recv(receiver: &mut Self::Receiver) -> Received {
+        use tokio::sync::broadcast::error::RecvError;
+
+        match receiver.recv().await {
+            Ok(sent) => Received::Message(sent),
+            Err(RecvError::Lagged(skipped)) => Received::Lagged(skipped),
+            Err(RecvError::Closed) => Received::Closed,
+        }
+    }
+}
+
+/// async-broadcast, whose sender waits while the channel is full.
+pub struct AsyncBroadcast;
+
+impl Broadcast for AsyncBroadcast {
+    type Sender = async_broadcast::Sender<Instant>;
+    type Receiver = async_broadcast::Receiver<Instant>;
+
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver) {
+        async_broadcast::broadcast(capacity)
+    }
+
+    fn subscribe(sender: &Self::Sender) -> Self::Receiver {
+        sender.new_receiver()
+    }
+
+    async fn send(sender: &Self::Sender, sent: Instant) {
+        sender.broadcast_direct(sent).await.unwrap();
+    }
+
+    async fn recv(receiver: &mut Self::Receiver) -> Received {
+        async_receive(receiver).await
+    }
+}
+
+/// async-broadcast in overflow mode, which overwrites the oldest messages
+/// when full.
+pub struct AsyncBroadcastOverflow;
+
+impl Broadcast for AsyncBroadcastOverflow {
+    type Sender = async_broadcast::Sender<Instant>;
+    type Receiver = async_broadcast::Receiver<Instant>;
+
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver) {
+        let (mut sender, receiver) = async_broadcast::broadcast(capacity);
+        sender.set_overflow(true);
+        (sender, receiver)
+    }
+
+    fn subscribe(sender: &Self::Sender) -> Self::Receiver {
+        sender.new_receiver()
+    }
+
+    async fn send(sender: &Self::Sender, sent: Instant) {
+        sender.broadcast_direct(sent).await.unwrap();
+    }
+
+    async fn recv(receiver: &mut Self::Receiver) -> Received {
+        async_receive(receiver).await
+    }
+}
+
+async fn async_receive(receiver: &mut async_broadcast::Receiver<Instant>) -> Received {
+    use async_broadcast::RecvError;
+
+    match receiver.recv_direct().await {
+        Ok(sent) => Received::Message(sent),
+        Err(RecvError::Overflowed(skipped)) => Received::Lagged(skipped),
+        Err(RecvError::Closed) => Received::Closed,
+    }
+}
+
+/// A load report over deliveries, with what the receivers lost.
+pub struct BroadcastReport {
+    pub load: LoadReport,
+    /// Messages sent that some receiver never got.
+    pub lost: u64,
+    /// Receivers that lost at least one message.
+    pub lagging: usize,
+    /// p99 latency of the receiver with the highest one.
+    pub worst_p99: u64,
+}
+
+impl BroadcastReport {
+    fn new(start: Instant, messages: usize, receivers: Vec<Deliveries>) -> Self {
+        let mut latencies = latency_histogram();
+        let mut lost = 0;
+        let mut lagging = 0;
+        let mut worst_p99 = 0;
+        for receiver in &receivers {
+            latencies.add(&receiver.latencies).unwrap();
+            lost += receiver.lost;
+            lagging += usize::from(receiver.lost > 0);
+            worst_p99 = worst_p99.max(receiver.latencies.value_at_quantile(0.99));
+        }
+        let delivered = latencies.len() as usize;
+        assert_eq!(delivered as u64 + lost, (messages * receivers.len()) as u64);
+        BroadcastReport {
+            load: LoadReport::since(start, delivered, latencies),
+            lost,
+            lagging,
+            worst_p99,
+        }
+    }
+}
+
+impl fmt::Display for BroadcastReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  lost {}  lagging {}  worst p99 {}",
+            self.load,
+            self.lost,
+            self.lagging,
+            Millis(self.worst_p99)
+        )
+    }
+}
+
+/// What one receiver got.
+struct Deliveries {
+    latencies: Histogram<u64>,
+    lost: u64,
+}
+
+/// Broadcasts `messages` messages to `receivers` tokio tasks over a `C`
+/// channel of `capacity`, in bursts of `burst`; `receivers` must be at least one.
+pub fn on_tokio<C: Broadcast>(
+    receivers: usize,
+    messages: usize,
+    capacity: usize,
+    burst: usize,
+) -> BroadcastReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (sender, first) = C::channel(capacity);
+        let others = (1..receivers).map(|_| C::subscribe(&sender));
+        let handles: Vec<_> = std::iter::once(first)
+            .chain(others)
+            .map(|receiver| tokio::spawn(receive::<C>(receiver)))
+            .collect();
+        let start = Instant::now();
+        tokio::spawn(send::<C, _, _>(
+            sender,
+            messages,
+            burst,
+            tokio::task::yield_now,
+        ));
+        let mut deliveries = Vec::with_capacity(receivers);
+        for handle in handles {
+            deliveries.push(handle.await.unwrap());
+        }
+        BroadcastReport::new(start, messages, deliveries)
+    })
+}
+
+/// Broadcasts `messages` messages to `receivers` async_std tasks over a `C`
+/// channel of `capacity`, in bursts of `burst`; `receivers` must be at least one.
+pub fn on_async_std<C: Broadcast>(
+    receivers: usize,
+    messages: usize,
+    capacity: usize,
+    burst: usize,
+) -> BroadcastReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (sender, first) = C::channel(capacity);
+        let others = (1..receivers).map(|_| C::subscribe(&sender));
+        let handles: Vec<_> = std::iter::once(first)
+            .chain(others)
+            .map(|receiver| task::spawn(receive::<C>(receiver)))
+            .collect();
+        let start = Instant::now();
+        task::spawn(send::<C, _, _>(sender, messages, burst, task::yield_now));
+        let mut deliveries = Vec::with_capacity(receivers);
+        for handle in handles {
+            deliveries.push(handle.await);
+        }
+        BroadcastReport::new(start, messages, deliveries)
+    })
+}
+
+/// Sends `messages` messages in bursts of `burst`, calling `yield_now` between
+/// bursts, and closes the channel.
+///
+/// It runs in a task of its own rather than in `block_on`, so that yielding
+/// lets the receivers on the same worker run.
+async fn send<C, F, Fut>(sender: C::Sender, messages: usize, burst: usize, yield_now: F)
+where
+    C: Broadcast,
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    for i in 1..=messages {
+        C::send(&sender, Instant::now()).await;
+        if i.is_multiple_of(burst) {
+            yield_now().await;
+        }
+    }
+}
+
+async fn receive<C: Broadcast>(mut receiver: C::Receiver) -> Deliveries {
+    let mut deliveries = Deliveries {
+        latencies: latency_histogram(),
+        lost: 0,
+    };
+    loop {
+        match C::recv(&mut receiver).await {
+            Received::Message(sent) => record(&mut deliveries.latencies, sent.elapsed()),
+            Received::Lagged(skipped) => deliveries.lost += skipped,
+            Received::Closed => break,
+        }
+    }
+    deliveries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_bursts_reach_every_receiver() {
+        for report in [
+            on_tokio::<TokioBroadcast>(10, 500, 64, 4),
+            on_async_std::<TokioBroadcast>(10, 500, 64, 4),
+            on_tokio::<AsyncBroadcast>(10, 500, 64, 4),
+            on_async_std::<AsyncBroadcastOverflow>(10, 500, 64, 4),
+        ] {
+            assert_eq!(report.load.requests, 5_000);
+            assert_eq!(report.lost, 0, "{}", report);
+        }
+    }
+
+    #[test]
+    fn large_bursts_overwrite_unless_the_sender_waits() {
+        let report = on_tokio::<TokioBroadcast>(10, 500, 16, 100);
+        assert!(report.lost > 0 && report.lagging > 0);
+        let report = on_async_std::<AsyncBroadcastOverflow>(10, 500, 16, 100);
+        assert!(report.lost > 0 && report.lagging > 0);
+        let report = on_async_std::<AsyncBroadcast>(10, 500, 16, 100);
+        assert_eq!(report.load.requests, 5_000);
+    }
+}
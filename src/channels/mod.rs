@@ -0,0 +1,10 @@
+//! Channels of the runtimes and of runtime-independent crates, measured as
+//! load scenarios: every message carries the time it was sent, and latencies
+//! are the time until it was received.
+
+pub mod broadcast;
+pub mod mpmc;
+pub mod mpsc;
+pub mod oneshot;
+pub mod stream;
+pub mod watch;
@@ -0,0 +1,258 @@
+//! Multi-producer multi-consumer channels used as work queues: `producers`
+//! tasks send their share of the messages and `consumers` tasks take them off
+//! the shared channel, each message going to exactly one of them.
+//!
+//! async-channel and flume are MPMC themselves and use the [`mpsc`] scenarios'
+//! implementations. crossbeam-channel is synchronous, so it is bridged to
+//! async as [`CrossbeamBridge`]: senders and receivers try the channel and,
+//! when it is full or empty, wait for an event-listener `Event` that the other
+//! side notifies after each operation, which is also how async-channel waits.
+//! None of them depends on a runtime, so all are measured on both.
+//!
+//! Latencies are from the send until a consumer received the message, and the
+//! report includes Jain's fairness index over the messages each consumer took.
+//!
+//! [`mpsc`]: super::mpsc
+
+use super::mpsc::Channel;
+use crate::harness::{fairness, latency_histogram, record, split, LoadReport};
+use crossbeam_channel::{TryRecvError, TrySendError};
+use event_listener::Event;
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A bounded crossbeam channel with event-listener wakeups for async tasks.
+pub struct CrossbeamBridge;
+
+/// Wakeups shared by both sides of a [`CrossbeamBridge`].
+#[derive(Default)]
+struct Events {
+    not_full: Event,
+    not_empty: Event,
+}
+
+/// The sending side of a [`CrossbeamBridge`].
+pub struct BridgeSender {
+    /// Only taken on drop, to wake the receivers once it is disconnected.
+    sender: Option<crossbeam_channel::Sender<Instant>>,
+    events: Arc<Events>,
+}
+
+impl Clone for BridgeSender {
+    fn clone(&self) -> Self {
+        BridgeSender {
+            sender: self.sender.clone(),
+            events: self.events.clone(),
+        }
+    }
+}
+
+impl Drop for BridgeSender {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        self.events.not_empty.notify(usize::MAX);
+    }
+}
+
+/// The receiving side of a [`CrossbeamBridge`].
+#[derive(Clone)]
+pub struct BridgeReceiver {
+    receiver: crossbeam_channel::Receiver<Instant>,
+    events: Arc<Events>,
+}
+
+impl Channel for CrossbeamBridge {
+    type Sender = BridgeSender;
+    type Receiver = BridgeReceiver;
+
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver) {
+        let (sender, receiver) = crossbeam_channel::bounded(capacity);
+        let events = Arc::new(Events::default());
+        let sender = BridgeSender {
+            sender: Some(sender),
+            events: events.clone(),
+        };
+        (sender, BridgeReceiver { receiver, events })
+    }
+
+    async fn send(sender: &mut Self::Sender, sent: Instant) {
+        let channel = sender.sender.as_ref().unwrap();
+        let mut message = sent;
+        let mut listener = None;
+        loop {
+            match channel.try_send(message) {
+                Ok(()) => {
+                    sender.events.not_empty.notify_additional(1);
+                    return;
+                }
+                Err(TrySendError::Full(back)) => message = back,
+                Err(TrySendError::Disconnected(_)) => panic!("all receivers are gone"),
+            }
+            // Listen before trying again, so that a receiver taking a message
+            // in between is not missed.
+            match listener.take() {
+                None => listener = Some(sender.events.not_full.listen()),
+                Some(listener) => listener.await,
+            }
+        }
+    }
+
+    async fn recv(receiver: &mut Self::Receiver) -> Option<Instant> {
+        let mut listener = None;
+        loop {
+            match receiver.receiver.try_recv() {
+                Ok(sent) => {
+                    receiver.events.not_full.notify_additional(1);
+                    return Some(sent);
+                }
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {}
+            }
+            match listener.take() {
+                None => listener = Some(receiver.events.not_empty.listen()),
+                Some(listener) => listener.await,
+            }
+        }
+    }
+}
+
+/// A load report over messages, with how evenly the consumers shared them.
+pub struct MpmcReport {
+    pub load: LoadReport,
+    /// Jain's fairness index over the number of messages each consumer received.
+    pub fairness: f64,
+}
+
+impl MpmcReport {
+    fn new(start: Instant, messages: usize, consumers: Vec<Histogram<u64>>) -> Self {
+        let counts: Vec<_> = consumers.iter().map(|c| c.len() as usize).collect();
+        let mut latencies = latency_histogram();
+        for consumer in &consumers {
+            latencies.add(consumer).unwrap();
+        }
+        assert_eq!(counts.iter().sum::<usize>(), messages);
+        MpmcReport {
+            load: LoadReport::since(start, messages, latencies),
+            fairness: fairness(&counts),
+        }
+    }
+}
+
+impl fmt::Display for MpmcReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  fairness {:.3}", self.load, self.fairness)
+    }
+}
+
+/// Sends `messages` messages from `producers` tokio tasks to `consumers`
+/// others over a `C` channel of `capacity`.
+pub fn on_tokio<C>(
+    producers: usize,
+    consumers: usize,
+    messages: usize,
+    capacity: usize,
+) -> MpmcReport
+where
+    C: Channel,
+    C::Receiver: Clone,
+{
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (sender, receiver) = C::channel(capacity);
+        let start = Instant::now();
+        let handles: Vec<_> = (0..consumers)
+            .map(|_| tokio::spawn(consume::<C>(receiver.clone())))
+            .collect();
+        drop(receiver);
+        for share in split(messages, producers) {
+            tokio::spawn(produce::<C>(sender.clone(), share));
+        }
+        drop(sender);
+        let mut received = Vec::with_capacity(consumers);
+        for handle in handles {
+            received.push(handle.await.unwrap());
+        }
+        MpmcReport::new(start, messages, received)
+    })
+}
+
+/// Sends `messages` messages from `producers` async_std tasks to `consumers`
+/// others over a `C` channel of `capacity`.
+pub fn on_async_std<C>(
+    producers: usize,
+    consumers: usize,
+    messages: usize,
+    capacity: usize,
+) -> MpmcReport
+where
+    C: Channel,
+    C::Receiver: Clone,
+{
+    use async_std::task;
+
+    task::block_on(async {
+        let (sender, receiver) = C::channel(capacity);
+        let start = Instant::now();
+        let handles: Vec<_> = (0..consumers)
+            .map(|_| task::spawn(consume::<C>(receiver.clone())))
+            .collect();
+        drop(receiver);
+        for share in split(messages, producers) {
+            task::spawn(produce::<C>(sender.clone(), share));
+        }
+        drop(sender);
+        let mut received = Vec::with_capacity(consumers);
+        for handle in handles {
+            received.push(handle.await);
+        }
+        MpmcReport::new(start, messages, received)
+    })
+}
+
+async fn produce<C: Channel>(mut sender: C::Sender, messages: usize) {
+    for _ in 0..messages {
+        C::send(&mut sender, Instant::now()).await;
+    }
+}
+
+async fn consume<C: Channel>(mut receiver: C::Receiver) -> Histogram<u64> {
+    let mut latencies = latency_histogram();
+    while let Some(sent) = C::recv(&mut receiver).await {
+        record(&mut latencies, sent.elapsed());
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mpsc::{AsyncChannel, Flume};
+    use super::*;
+
+    fn delivers_everything<C>()
+    where
+        C: Channel,
+        C::Receiver: Clone,
+    {
+        for &(producers, consumers) in &[(1, 3), (3, 3)] {
+            assert_eq!(
+                on_tokio::<C>(producers, consumers, 1_000, 4).load.requests,
+                1_000
+            );
+            assert_eq!(
+                on_async_std::<C>(producers, consumers, 1_000, 4)
+                    .load
+                    .requests,
+                1_000
+            );
+        }
+    }
+
+    #[test]
+    fn channels_deliver_every_message_once() {
+        delivers_everything::<AsyncChannel>();
+        delivers_everything::<Flume>();
+        delivers_everything::<CrossbeamBridge>();
+    }
+}
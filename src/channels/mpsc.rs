@@ -0,0 +1,275 @@
+//! Multi-producer single-consumer channels: `producers` tasks send their share
+//! of the messages as fast as the channel takes them, and one task receives
+//! them all.
+//!
+//! Every channel is measured bounded, with senders waiting while it is full,
+//! and unbounded, so that the cost of the bookkeeping behind backpressure
+//! shows. Sending to an unbounded channel never waits, so its senders only
+//! give way to the receiver when the runtime preempts them or they finish.
+//!
+//! tokio's channel is only measured on tokio. The others do not depend on a
+//! runtime and are measured on both; `async_std::channel` is a re-export of
+//! async-channel, so it is covered by [`AsyncChannel`] on async_std.
+
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use hdrhistogram::Histogram;
+use std::future::Future;
+use std::time::Instant;
+
+/// A multi-producer channel of send times.
+pub trait Channel: 'static {
+    type Sender: Clone + Send + 'static;
+    type Receiver: Send + 'static;
+
+    /// Creates a channel that holds at most about `capacity` messages, if it
+    /// is bounded at all.
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver);
+
+    /// Sends `sent`, waiting while a bounded channel is full.
+    fn send(sender: &mut Self::Sender, sent: Instant) -> impl Future<Output = ()> + Send + '_;
+
+    /// Receives the next message, or `None` once all senders are gone.
+    fn recv(receiver: &mut Self::Receiver) -> impl Future<Output = Option<Instant>> + Send + '_;
+}
+
+/// `tokio::sync::mpsc`.
+pub struct TokioMpsc;
+
+impl Channel for TokioMpsc {
+    type Sender = tokio::sync::mpsc::Sender<Instant>;
+    type Receiver = tokio::sync::mpsc::Receiver<Instant>;
+
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver) {
+        tokio::sync::mpsc::channel(capacity)
+    }
+
+    async fn send(sender: &mut Self::Sender, sent: Instant) {
+        sender.send(sent).await.unwrap()
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> impl Future<Output = Option<Instant>> + Send + '_ {
+        receiver.recv()
+    }
+}
+
+/// `futures::channel::mpsc`, whose capacity grows by one slot per sender.
+pub struct FuturesMpsc;
+
+impl Channel for FuturesMpsc {
+    type Sender = futures::channel::mpsc::Sender<Instant>;
+    type Receiver = futures::channel::mpsc::Receiver<Instant>;
+
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver) {
+        futures::channel::mpsc::channel(capacity)
+    }
+
+    async fn send(sender: &mut Self::Sender, sent: Instant) {
+        use futures::SinkExt;
+
+        sender.send(sent).await.unwrap()
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> impl Future<Output = Option<Instant>> + Send + '_ {
+        use futures::StreamExt;
+
+        receiver.next()
+    }
+}
+
+/// flume, which also has a blocking interface on the same channel.
+pub struct Flume;
+
+impl Channel for Flume {
+    type Sender = flume::Sender<Instant>;
+    type Receiver = flume::Receiver<Instant>;
+
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver) {
+        flume::bounded(capacity)
+    }
+
+    async fn send(sender: &mut Self::Sender, sent: Instant) {
+        sender.send_async(sent).await.unwrap()
+    }
+
+    async fn recv(receiver: &mut Self::Receiver) -> Option<Instant> {
+        receiver.recv_async().await.ok()
+    }
+}
+
+/// async-channel, which is also `async_std::channel`.
+pub struct AsyncChannel;
+
+impl Channel for AsyncChannel {
+    type Sender = async_channel::Sender<Instant>;
+    type Receiver = async_channel::Receiver<Instant>;
+
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver) {
+        async_channel::bounded(capacity)
+    }
+
+    async fn send(sender: &mut Self::Sender, sent: Instant) {
+        sender.send(sent).await.unwrap()
+    }
+
+    async fn recv(receiver: &mut Self::Receiver) -> Option<Instant> {
+        receiver.recv().await.ok()
+    }
+}
+
+/// Unbounded `tokio::sync::mpsc`.
+pub struct TokioUnbounded;
+
+impl Channel for TokioUnbounded {
+    type Sender = tokio::sync::mpsc::UnboundedSender<Instant>;
+    type Receiver = tokio::sync::mpsc::UnboundedReceiver<Instant>;
+
+    fn channel(_: usize) -> (Self::Sender, Self::Receiver) {
+        tokio::sync::mpsc::unbounded_channel()
+    }
+
+    async fn send(sender: &mut Self::Sender, sent: Instant) {
+        sender.send(sent).unwrap()
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> impl Future<Output = Option<Instant>> + Send + '_ {
+        receiver.recv()
+    }
+}
+
+/// Unbounded `futures::channel::mpsc`.
+pub struct FuturesUnbounded;
+
+impl Channel for FuturesUnbounded {
+    type Sender = futures::channel::mpsc::UnboundedSender<Instant>;
+    type Receiver = futures::channel::mpsc::UnboundedReceiver<Instant>;
+
+    fn channel(_: usize) -> (Self::Sender, Self::Receiver) {
+        futures::channel::mpsc::unbounded()
+    }
+
+    async fn send(sender: &mut Self::Sender, sent: Instant) {
+        sender.unbounded_send(sent).unwrap()
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> impl Future<Output = Option<Instant>> + Send + '_ {
+        use futures::StreamExt;
+
+        receiver.next()
+    }
+}
+
+/// Unbounded flume.
+pub struct FlumeUnbounded;
+
+impl Channel for FlumeUnbounded {
+    type Sender = flume::Sender<Instant>;
+    type Receiver = flume::Receiver<Instant>;
+
+    fn channel(_: usize) -> (Self::Sender, Self::Receiver) {
+        flume::unbounded()
+    }
+
+    async fn send(sender: &mut Self::Sender, sent: Instant) {
+        sender.send(sent).unwrap()
+    }
+
+    async fn recv(receiver: &mut Self::Receiver) -> Option<Instant> {
+        receiver.recv_async().await.ok()
+    }
+}
+
+/// Unbounded async-channel.
+pub struct AsyncChannelUnbounded;
+
+impl Channel for AsyncChannelUnbounded {
+    type Sender = async_channel::Sender<Instant>;
+    type Receiver = async_channel::Receiver<Instant>;
+
+    fn channel(_: usize) -> (Self::Sender, Self::Receiver) {
+        async_channel::unbounded()
+    }
+
+    async fn send(sender: &mut Self::Sender, sent: Instant) {
+        sender.try_send(sent).unwrap()
+    }
+
+    async fn recv(receiver: &mut Self::Receiver) -> Option<Instant> {
+        receiver.recv().await.ok()
+    }
+}
+
+/// Sends `messages` messages from `producers` tokio tasks to one over a `C`
+/// channel of `capacity`, which unbounded channels ignore.
+pub fn on_tokio<C: Channel>(producers: usize, messages: usize, capacity: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (sender, receiver) = C::channel(capacity);
+        let start = Instant::now();
+        let consumer = tokio::spawn(consume::<C>(receiver));
+        for share in split(messages, producers) {
+            tokio::spawn(produce::<C>(sender.clone(), share));
+        }
+        drop(sender);
+        let latencies = consumer.await.unwrap();
+        LoadReport::since(start, messages, latencies)
+    })
+}
+
+/// Sends `messages` messages from `producers` async_std tasks to one over a
+/// `C` channel of `capacity`, which unbounded channels ignore.
+pub fn on_async_std<C: Channel>(producers: usize, messages: usize, capacity: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (sender, receiver) = C::channel(capacity);
+        let start = Instant::now();
+        let consumer = task::spawn(consume::<C>(receiver));
+        for share in split(messages, producers) {
+            task::spawn(produce::<C>(sender.clone(), share));
+        }
+        drop(sender);
+        let latencies = consumer.await;
+        LoadReport::since(start, messages, latencies)
+    })
+}
+
+async fn produce<C: Channel>(mut sender: C::Sender, messages: usize) {
+    for _ in 0..messages {
+        C::send(&mut sender, Instant::now()).await;
+    }
+}
+
+async fn consume<C: Channel>(mut receiver: C::Receiver) -> Histogram<u64> {
+    let mut latencies = latency_histogram();
+    while let Some(sent) = C::recv(&mut receiver).await {
+        record(&mut latencies, sent.elapsed());
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivers_everything<C: Channel>() {
+        for &producers in &[1, 3] {
+            assert_eq!(on_tokio::<C>(producers, 1_000, 16).latencies.len(), 1_000);
+            assert_eq!(
+                on_async_std::<C>(producers, 1_000, 16).latencies.len(),
+                1_000
+            );
+        }
+    }
+
+    #[test]
+    fn channels_deliver_every_message() {
+        delivers_everything::<TokioMpsc>();
+        delivers_everything::<FuturesMpsc>();
+        delivers_everything::<Flume>();
+        delivers_everything::<AsyncChannel>();
+        delivers_everything::<TokioUnbounded>();
+        delivers_everything::<FuturesUnbounded>();
+        delivers_everything::<FlumeUnbounded>();
+        delivers_everything::<AsyncChannelUnbounded>();
+    }
+}
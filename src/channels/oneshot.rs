@@ -0,0 +1,177 @@
+//! Oneshot channels, created, used once and dropped, the way a request hands
+//! the task that serves it a channel for the reply.
+//!
+//! In the same-task mode one task creates a channel, sends and receives on it,
+//! so the value is always there when the receiver is polled and latencies are
+//! the whole create-send-receive round trip. In the cross-task mode a task is
+//! spawned to wait on the receiver, the sender yields once so that it has
+//! usually started waiting, and then sends; latencies are from the send until
+//! the woken receiver runs, which goes through the runtime's scheduler.
+//!
+//! None of the channels depends on a runtime, so all of them are measured on
+//! both.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use std::future::Future;
+use std::time::Instant;
+
+/// Whether the value is received by the task that sent it.
+#[derive(Clone, Copy, Debug)]
+pub enum Mode {
+    SameTask,
+    CrossTask,
+}
+
+/// A oneshot channel of send times.
+pub trait Oneshot: 'static {
+    type Sender: Send + 'static;
+    type Receiver: Send + 'static;
+
+    fn channel() -> (Self::Sender, Self::Receiver);
+
+    fn send(sender: Self::Sender, sent: Instant);
+
+    fn recv(receiver: Self::Receiver) -> impl Future<Output = Instant> + Send;
+}
+
+/// `tokio::sync::oneshot`.
+pub struct TokioOneshot;
+
+impl Oneshot for TokioOneshot {
+    type Sender = tokio::sync::oneshot::Sender<Instant>;
+    type Receiver = tokio::sync::oneshot::Receiver<Instant>;
+
+    fn channel() -> (Self::Sender, Self::Receiver) {
+        tokio::sync::oneshot::channel()
+    }
+
+    fn send(sender: Self::Sender, sent: Instant) {
+        sender.send(sent).unwrap()
+    }
+
+    async fn recv(receiver: Self::Receiver) -> Instant {
+        receiver.await.unwrap()
+    }
+}
+
+/// `futures::channel::oneshot`.
+pub struct FuturesOneshot;
+
+impl Oneshot for FuturesOneshot {
+    type Sender = futures::channel::oneshot::Sender<Instant>;
+    type Receiver = futures::channel::oneshot::Receiver<Instant>;
+
+    fn channel() -> (Self::Sender, Self::Receiver) {
+        futures::channel::oneshot::channel()
+    }
+
+    fn send(sender: Self::Sender, sent: Instant) {
+        sender.send(sent).unwrap()
+    }
+
+    async fn recv(receiver: Self::Receiver) -> Instant {
+        receiver.await.unwrap()
+    }
+}
+
+/// async-oneshot.
+pub struct AsyncOneshot;
+
+impl Oneshot for AsyncOneshot {
+    type Sender = async_oneshot::Sender<Instant>;
+    type Receiver = async_oneshot::Receiver<Instant>;
+
+    fn channel() -> (Self::Sender, Self::Receiver) {
+        async_oneshot::oneshot()
+    }
+
+    fn send(mut sender: Self::Sender, sent: Instant) {
+        sender.send(sent).unwrap()
+    }
+
+    async fn recv(receiver: Self::Receiver) -> Instant {
+        receiver.await.unwrap()
+    }
+}
+
+/// Uses `rounds` `C` channels one after another on tokio.
+pub fn on_tokio<C: Oneshot>(mode: Mode, rounds: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let start = Instant::now();
+        let mut latencies = latency_histogram();
+        for _ in 0..rounds {
+            let created = Instant::now();
+            let (sender, receiver) = C::channel();
+            let latency = match mode {
+                Mode::SameTask => {
+                    C::send(sender, Instant::now());
+                    C::recv(receiver).await;
+                    created.elapsed()
+                }
+                Mode::CrossTask => {
+                    let woken = tokio::spawn(async move {
+                        let sent = C::recv(receiver).await;
+                        sent.elapsed()
+                    });
+                    tokio::task::yield_now().await;
+                    C::send(sender, Instant::now());
+                    woken.await.unwrap()
+                }
+            };
+            record(&mut latencies, latency);
+        }
+        LoadReport::since(start, rounds, latencies)
+    })
+}
+
+/// Uses `rounds` `C` channels one after another on async_std.
+pub fn on_async_std<C: Oneshot>(mode: Mode, rounds: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let start = Instant::now();
+        let mut latencies = latency_histogram();
+        for _ in 0..rounds {
+            let created = Instant::now();
+            let (sender, receiver) = C::channel();
+            let latency = match mode {
+                Mode::SameTask => {
+                    C::send(sender, Instant::now());
+                    C::recv(receiver).await;
+                    created.elapsed()
+                }
+                Mode::CrossTask => {
+                    let woken = task::spawn(async move {
+                        let sent = C::recv(receiver).await;
+                        sent.elapsed()
+                    });
+                    task::yield_now().await;
+                    C::send(sender, Instant::now());
+                    woken.await
+                }
+            };
+            record(&mut latencies, latency);
+        }
+        LoadReport::since(start, rounds, latencies)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivers_every_round<C: Oneshot>() {
+        for &mode in &[Mode::SameTask, Mode::CrossTask] {
+            assert_eq!(on_tokio::<C>(mode, 100).latencies.len(), 100);
+            assert_eq!(on_async_std::<C>(mode, 100).latencies.len(), 100);
+        }
+    }
+
+    #[test]
+    fn oneshots_deliver_every_round() {
+        delivers_every_round::<TokioOneshot>();
+        delivers_every_round::<FuturesOneshot>();
+        delivers_every_round::<AsyncOneshot>();
+    }
+}
@@ -0,0 +1,80 @@
+//! Receiving from a channel through its `Stream` adapter instead of a
+//! `recv` loop.
+//!
+//! The adapters are [`mpsc::Channel`]s whose receiver is consumed with
+//! `StreamExt::next`, so they run the same scenarios as the channels they
+//! wrap: tokio's receiver wrapped in tokio-stream's `ReceiverStream` and
+//! async-channel's receiver, which is a `Stream` itself but has to be pinned
+//! to be polled as one. Senders are the plain ones.
+
+use super::mpsc::{self, Channel};
+use futures::StreamExt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Instant;
+
+/// `tokio::sync::mpsc` received through `ReceiverStream`.
+pub struct TokioReceiverStream;
+
+impl Channel for TokioReceiverStream {
+    type Sender = <mpsc::TokioMpsc as Channel>::Sender;
+    type Receiver = tokio_stream::wrappers::ReceiverStream<Instant>;
+
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver) {
+        let (sender, receiver) = mpsc::TokioMpsc::channel(capacity);
+        (
+            sender,
+            tokio_stream::wrappers::ReceiverStream::new(receiver),
+        )
+    }
+
+    fn send(sender: &mut Self::Sender, sent: Instant) -> impl Future<Output = ()> + Send + '_ {
+        mpsc::TokioMpsc::send(sender, sent)
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> impl Future<Output = Option<Instant>> + Send + '_ {
+        receiver.next()
+    }
+}
+
+/// async-channel received as a `Stream`.
+pub struct AsyncChannelStream;
+
+impl Channel for AsyncChannelStream {
+    type Sender = <mpsc::AsyncChannel as Channel>::Sender;
+    type Receiver = Pin<Box<async_channel::Receiver<Instant>>>;
+
+    fn channel(capacity: usize) -> (Self::Sender, Self::Receiver) {
+        let (sender, receiver) = mpsc::AsyncChannel::channel(capacity);
+        (sender, Box::pin(receiver))
+    }
+
+    fn send(sender: &mut Self::Sender, sent: Instant) -> impl Future<Output = ()> + Send + '_ {
+        mpsc::AsyncChannel::send(sender, sent)
+    }
+
+    fn recv(receiver: &mut Self::Receiver) -> impl Future<Output = Option<Instant>> + Send + '_ {
+        receiver.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streams_deliver_every_message() {
+        assert_eq!(
+            mpsc::on_tokio::<TokioReceiverStream>(2, 1_000, 16)
+                .latencies
+                .len(),
+            1_000
+        );
+        for report in [
+            mpsc::on_tokio::<AsyncChannelStream>(2, 1_000, 16),
+            mpsc::on_async_std::<AsyncChannelStream>(2, 1_000, 16),
+        ] {
+            assert_eq!(report.latencies.len(), 1_000);
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! A value updated at high frequency and watched by many tasks.
+//!
+//! One task keeps replacing the value, yielding to the runtime after every
+//! update, while the watchers wait for it to change and read the latest one.
+//! Watchers only ever see the newest value, so updates they are too slow for
+//! are skipped rather than queued. `tokio::sync::watch` is compared with a
+//! watch built from async-std's `Mutex` and `Condvar`, which wakes every
+//! watcher on every update. Neither depends on a runtime, so both are measured
+//! on both.
+//!
+//! Latencies are from an update until a watcher reads it. Wasted wakeups are
+//! the times a waiting watcher was polled again without getting a new value,
+//! counted the same way for both watches by wrapping their futures.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use futures::future::poll_fn;
+use futures::pin_mut;
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// An update: its number, counting from one, and when it was made.
+pub type Update = (u64, Instant);
+
+/// A watched value, updated by one sender.
+pub trait Watch: 'static {
+    type Sender: Send + 'static;
+    type Receiver: Send + 'static;
+
+    /// Creates a watch of a value that has not been updated yet.
+    fn channel() -> (Self::Sender, Self::Receiver);
+
+    fn subscribe(receiver: &Self::Receiver) -> Self::Receiver;
+
+    fn update(sender: &Self::Sender, update: Update) -> impl Future<Output = ()> + Send + '_;
+
+    /// Lets the watchers know that no more updates will come.
+    fn close(sender: Self::Sender) -> impl Future<Output = ()> + Send;
+
+    /// Waits for an update the receiver has not seen, or `None` once closed.
+    fn changed(receiver: &mut Self::Receiver) -> impl Future<Output = Option<Update>> + Send + '_;
+}
+
+/// `tokio::sync::watch`.
+pub struct TokioWatch;
+
+impl Watch for TokioWatch {
+    type Sender = tokio::sync::watch::Sender<Update>;
+    type Receiver = tokio::sync::watch::Receiver<Update>;
+
+    fn channel() -> (Self::Sender, Self::Receiver) {
+        tokio::sync::watch::channel((0, Instant::now()))
+    }
+
+    fn subscribe(receiver: &Self::Receiver) -> Self::Receiver {
+        receiver.clone()
+    }
+
+    async fn update(sender: &Self::Sender, update: Update) {
+        sender.send_replace(update);
+    }
+
+    async fn close(sender: Self::Sender) {
+        drop(sender);
+    }
+
+    async fn changed(receiver: &mut Self::Receiver) -> Option<Update> {
+        receiver.changed().await.ok()?;
+        Some(*receiver.borrow_and_update())
+    }
+}
+
+/// A watch built from `async_std::sync::Mutex` and `Condvar`.
+pub struct CondvarWatch;
+
+struct Shared {
+    latest: async_std::sync::Mutex<(Update, bool)>,
+    changed: async_std::sync::Condvar,
+}
+
+/// The sending side of a [`CondvarWatch`].
+pub struct CondvarSender(Arc<Shared>);
+
+/// The receiving side of a [`CondvarWatch`] with the last update it saw.
+pub struct CondvarReceiver {
+    shared: Arc<Shared>,
+    seen: u64,
+}
+
+impl Watch for CondvarWatch {
+    type Sender = CondvarSender;
+    type Receiver = CondvarReceiver;
+
+    fn channel() -> (Self::Sender, Self::Receiver) {
+        let shared = Arc::new(Shared {
+            latest: async_std::sync::Mutex::new(((0, Instant::now()), false)),
+            changed: async_std::sync::Condvar::new(),
+        });
+        let receiver = CondvarReceiver {
+            shared: shared.clone(),
+            seen: 0,
+        };
+        (CondvarSender(shared), receiver)
+    }
+
+    fn subscribe(receiver: &Self::Receiver) -> Self::Receiver {
+        CondvarReceiver {
+            shared: receiver.shared.clone(),
+            seen: 0,
+        }
+    }
+
+    async fn update(sender: &Self::Sender, update: Update) {
+        sender.0.latest.lock().await.0 = update;
+        sender.0.changed.notify_all();
+    }
+
+    async fn close(sender: Self::Sender) {
+        sender.0.latest.lock().await.1 = true;
+        sender.0.changed.notify_all();
+    }
+
+    async fn changed(receiver: &mut Self::Receiver) -> Option<Update> {
+        let mut latest = receiver.shared.latest.lock().await;
+        loop {
+            let ((number, at), closed) = *latest;
+            if number != receiver.seen {
+                receiver.seen = number;
+                return Some((number, at));
+            }
+            if closed {
+                return None;
+            }
+            latest = receiver.shared.changed.wait(latest).await;
+        }
+    }
+}
+
+/// A load report over the updates watchers read, with the ones they skipped
+/// and the wakeups that brought them nothing.
+pub struct WatchReport {
+    pub load: LoadReport,
+    /// Updates some watcher never read because a newer one replaced them first.
+    pub skipped: u64,
+    /// Times a waiting watcher was polled again without getting an update.
+    pub wasted_wakeups: u64,
+}
+
+impl fmt::Display for WatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  skipped {}  wasted wakeups {}",
+            self.load, self.skipped, self.wasted_wakeups
+        )
+    }
+}
+
+impl WatchReport {
+    fn new(start: Instant, updates: usize, watchers: Vec<Observed>) -> Self {
+        let mut latencies = latency_histogram();
+        let mut wasted_wakeups = 0;
+        for watcher in &watchers {
+            latencies.add(&watcher.latencies).unwrap();
+            wasted_wakeups += watcher.wasted_wakeups;
+        }
+        let read = latencies.len() as usize;
+        WatchReport {
+            load: LoadReport::since(start, read, latencies),
+            skipped: (updates * watchers.len() - read) as u64,
+            wasted_wakeups,
+        }
+    }
+}
+
+/// What one watcher saw.
+struct Observed {
+    latencies: Histogram<u64>,
+    wasted_wakeups: u64,
+}
+
+/// Updates a `C` watch `updates` times while `watchers` tokio tasks watch it;
+/// `watchers` must be at least one.
+pub fn on_tokio<C: Watch>(watchers: usize, updates: usize) -> WatchReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (sender, first) = C::channel();
+        let others: Vec<_> = (1..watchers).map(|_| C::subscribe(&first)).collect();
+        let handles: Vec<_> = std::iter::once(first)
+            .chain(others)
+            .map(|receiver| tokio::spawn(watch::<C>(receiver)))
+            .collect();
+        let start = Instant::now();
+        tokio::spawn(update::<C, _, _>(sender, updates, tokio::task::yield_now));
+        let mut observed = Vec::with_capacity(watchers);
+        for handle in handles {
+            observed.push(handle.await.unwrap());
+        }
+        WatchReport::new(start, updates, observed)
+    })
+}
+
+/// Updates a `C` watch `updates` times while `watchers` async_std tasks watch
+/// it; `watchers` must be at least one.
+pub fn on_async_std<C: Watch>(watchers: usize, updates: usize) -> WatchReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (sender, first) = C::channel();
+        let others: Vec<_> = (1..watchers).map(|_| C::subscribe(&first)).collect();
+        let handles: Vec<_> = std::iter::once(first)
+            .chain(others)
+            .map(|receiver| task::spawn(watch::<C>(receiver)))
+            .collect();
+        let start = Instant::now();
+        task::spawn(update::<C, _, _>(sender, updates, task::yield_now));
+        let mut observed = Vec::with_capacity(watchers);
+        for handle in handles {
+            observed.push(handle.await);
+        }
+        WatchReport::new(start, updates, observed)
+    })
+}
+
+/// Makes `updates` updates, calling `yield_now` after each, and closes the watch.
+async fn update<C, F, Fut>(sender: C::Sender, updates: usize, yield_now: F)
+where
+    C: Watch,
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    for number in 1..=updates as u64 {
+        C::update(&sender, (number, Instant::now())).await;
+        yield_now().await;
+    }
+    C::close(sender).await;
+}
+
+async fn watch<C: Watch>(mut receiver: C::Receiver) -> Observed {
+    let mut observed = Observed {
+        latencies: latency_histogram(),
+        wasted_wakeups: 0,
+    };
+    loop {
+        let changed = C::changed(&mut receiver);
+        pin_mut!(changed);
+        let mut polls = 0;
+        let update = poll_fn(|cx| {
+            polls += 1;
+            changed.as_mut().poll(cx)
+        })
+        .await;
+        // The first poll is not a wakeup, and the last one of a wait is useful.
+        observed.wasted_wakeups += polls.max(2) - 2;
+        match update {
+            Some((_, at)) => record(&mut observed.latencies, at.elapsed()),
+            None => break,
+        }
+    }
+    observed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watchers_read_the_last_update() {
+        for report in [
+            on_tokio::<TokioWatch>(8, 1_000),
+            on_async_std::<TokioWatch>(8, 1_000),
+            on_tokio::<CondvarWatch>(8, 1_000),
+            on_async_std::<CondvarWatch>(8, 1_000),
+        ] {
+            assert!(report.load.requests >= 8);
+            assert_eq!(report.load.requests + report.skipped, 8 * 1_000);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! A chat server that relays every message from one client to all the others.
+//!
+//! Each server connection has a reader task that publishes incoming messages
+//! into a broadcast channel and a writer task that forwards everything from
+//! the channel, except the connection's own messages, back to its client. On
+//! tokio the channel is `tokio::sync::broadcast`; async_std has no broadcast
+//! channel of its own, so async-broadcast is used there. Note that tokio's
+//! channel drops messages for lagging receivers while async-broadcast makes
+//! the sender wait, so a lagging run panics on tokio, as soon as a
+//! subscriber falls behind, instead of slowing down.
+//!
+//! Clients wait until the server has subscribed their connection, then run in
+//! rounds: every client sends one message and then reads one message from each
+//! of the others before sending the next. Messages carry the time they were
+//! sent, and latencies are the time until each delivery.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use futures::future::Either;
+use hdrhistogram::Histogram;
+use std::convert::TryInto;
+use std::time::{Duration, Instant};
+
+/// Size of a message on the wire: the nanoseconds since the run started.
+const MESSAGE: usize = 8;
+
+/// Sent by the server once a connection is subscribed, so that no client
+/// sends before every other client can receive.
+const READY: &[u8] = b"!";
+
+/// Runs `rounds` rounds with `clients` clients on tokio.
+pub fn tokio(clients: usize, rounds: usize) -> LoadReport {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::broadcast;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (publish, _) = broadcast::channel::<(usize, [u8; MESSAGE])>(4 * clients);
+        // The clients of a lagging subscriber would wait for it forever, so
+        // it is reported to the run instead of panicking in its own task.
+        let (lagged, mut fell_behind) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for id in 0.. {
+                let (stream, _) = listener.accept().await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                let (mut read, mut write) = stream.into_split();
+                let mut subscription = publish.subscribe();
+                write.write_all(READY).await.unwrap();
+                let lagged = lagged.clone();
+                tokio::spawn(async move {
+                    loop {
+                        match subscription.recv().await {
+                            Ok((from, message)) if from != id => {
+                                if write.write_all(&message).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Ok(_) => {}
+                            Err(broadcast::error::RecvError::Closed) => break,
+                            Err(err) => {
+                                let _ = lagged.send(err);
+                                break;
+                            }
+                        }
+                    }
+                });
+                let publish = publish.clone();
+                tokio::spawn(async move {
+                    let mut message = [0u8; MESSAGE];
+                    while read.read_exact(&mut message).await.is_ok() {
+                        let _ = publish.send((id, message));
+                    }
+                });
+            }
+        });
+
+        let streams = futures::future::join_all((0..clients).map(|_| async {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            stream.read_exact(&mut [0]).await.unwrap();
+            stream
+        }))
+        .await;
+        let epoch = Instant::now();
+        let tasks: Vec<_> = streams
+            .into_iter()
+            .map(|mut stream| {
+                tokio::spawn(async move {
+                    let mut latencies = latency_histogram();
+                    let mut message = [0u8; MESSAGE];
+                    for _ in 0..rounds {
+                        stream.write_all(&stamp(epoch)).await.unwrap();
+                        for _ in 1..clients {
+                            stream.read_exact(&mut message).await.unwrap();
+                            record(&mut latencies, age(epoch, message));
+                        }
+                    }
+                    latencies
+                })
+            })
+            .collect();
+        let all = Box::pin(futures::future::join_all(tasks));
+        let done = match futures::future::select(all, Box::pin(fell_behind.recv())).await {
+            Either::Left((done, _)) => done,
+            Either::Right((err, _)) => panic!("chat subscriber fell behind: {:?}", err),
+        };
+        let mut latencies = latency_histogram();
+        for task in done {
+            latencies.add(task.unwrap()).unwrap();
+        }
+        report(epoch, latencies)
+    })
+}
+
+/// Runs `rounds` rounds with `clients` clients on async_std.
+pub fn async_std(clients: usize, rounds: usize) -> LoadReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (publish, subscribe) =
+            async_broadcast::broadcast::<(usize, [u8; MESSAGE])>(4 * clients);
+        // An inactive receiver keeps the channel open without holding messages back.
+        let subscribe = subscribe.deactivate();
+        let accept = task::spawn(async move {
+            for id in 0.. {
+                let (stream, _) = listener.accept().await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                let mut subscription = subscribe.activate_cloned();
+                let mut write = stream.clone();
+                write.write_all(READY).await.unwrap();
+                task::spawn(async move {
+                    while let Ok((from, message)) = subscription.recv().await {
+                        if from != id && write.write_all(&message).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                let publish = publish.clone();
+                let mut read = stream;
+                task::spawn(async move {
+                    let mut message = [0u8; MESSAGE];
+                    while read.read_exact(&mut message).await.is_ok() {
+                        let _ = publish.broadcast((id, message)).await;
+                    }
+                });
+            }
+        });
+
+        let streams = futures::future::join_all((0..clients).map(|_| async {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            stream.read_exact(&mut [0]).await.unwrap();
+            stream
+        }))
+        .await;
+
+        let epoch = Instant::now();
+        let tasks: Vec<_> = streams
+            .into_iter()
+            .map(|mut stream| {
+                task::spawn(async move {
+                    let mut latencies = latency_histogram();
+                    let mut message = [0u8; MESSAGE];
+                    for _ in 0..rounds {
+                        stream.write_all(&stamp(epoch)).await.unwrap();
+                        for _ in 1..clients {
+                            stream.read_exact(&mut message).await.unwrap();
+                            record(&mut latencies, age(epoch, message));
+                        }
+                    }
+                    latencies
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for task in tasks {
+            latencies.add(task.await).unwrap();
+        }
+        let report = report(epoch, latencies);
+        accept.cancel().await;
+        report
+    })
+}
+
+/// A message holding the time elapsed since `epoch`.
+fn stamp(epoch: Instant) -> [u8; MESSAGE] {
+    (epoch.elapsed().as_nanos() as u64).to_be_bytes()
+}
+
+/// How long ago `message` was stamped.
+fn age(epoch: Instant, message: [u8; MESSAGE]) -> Duration {
+    let sent = Duration::from_nanos(u64::from_be_bytes(message));
+    epoch.elapsed() - sent
+}
+
+/// Builds the report of a run that started at `epoch`, with one request per delivery.
+fn report(epoch: Instant, latencies: Histogram<u64>) -> LoadReport {
+    let deliveries = latencies.len().try_into().unwrap();
+    LoadReport::since(epoch, deliveries, latencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokio_delivers_to_everyone_else() {
+        assert_eq!(tokio(4, 5).requests, 4 * 3 * 5);
+    }
+
+    #[test]
+    fn async_std_delivers_to_everyone_else() {
+        assert_eq!(async_std(4, 5).requests, 4 * 3 * 5);
+    }
+}
@@ -0,0 +1,140 @@
+//! One-way streams of length-delimited frames over a loopback connection.
+//!
+//! tokio_util's `Framed` with a `LengthDelimitedCodec` is compared with the
+//! hand-rolled loop from [`crate::framing`] over buffered `futures` IO, on
+//! async_std and, through `tokio_util::compat`, on tokio, so the codec and the
+//! runtime can be told apart. Both put the same bytes on the wire: a
+//! big-endian `u32` length followed by the payload. Senders only flush once
+//! all frames are written and leave the batching to the codec or the buffer.
+//!
+//! Latencies are the time the receiver waited for each frame.
+
+use crate::framing::{feed_frame, read_frame};
+use crate::harness::{latency_histogram, record, LoadReport};
+use futures::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use hdrhistogram::Histogram;
+use std::time::Instant;
+
+/// Sends `frames` frames of `size` bytes through `Framed` on tokio.
+pub fn tokio_framed(frames: usize, size: usize) -> LoadReport {
+    use bytes::Bytes;
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let receiver = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = Framed::new(stream, LengthDelimitedCodec::new());
+            let mut latencies = latency_histogram();
+            for _ in 0..frames {
+                let start = Instant::now();
+                let frame = stream.next().await.unwrap().unwrap();
+                assert_eq!(frame.len(), size);
+                record(&mut latencies, start.elapsed());
+            }
+            latencies
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut sink = Framed::new(stream, LengthDelimitedCodec::new());
+        let payload = Bytes::from(vec![0; size]);
+        let start = Instant::now();
+        for _ in 0..frames {
+            sink.feed(payload.clone()).await.unwrap();
+        }
+        SinkExt::<Bytes>::flush(&mut sink).await.unwrap();
+        let latencies = receiver.await.unwrap();
+        LoadReport::since(start, frames, latencies).with_bytes((frames * size) as u64)
+    })
+}
+
+/// Sends `frames` frames of `size` bytes with the hand-rolled loop on tokio.
+pub fn tokio_hand_rolled(frames: usize, size: usize) -> LoadReport {
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let receiver = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            receive(stream.compat(), frames, size).await
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let start = Instant::now();
+        send(stream.compat(), frames, size).await;
+        let latencies = receiver.await.unwrap();
+        LoadReport::since(start, frames, latencies).with_bytes((frames * size) as u64)
+    })
+}
+
+/// Sends `frames` frames of `size` bytes with the hand-rolled loop on async_std.
+pub fn async_std_hand_rolled(frames: usize, size: usize) -> LoadReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let receiver = task::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            receive(stream, frames, size).await
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let start = Instant::now();
+        send(stream, frames, size).await;
+        let latencies = receiver.await;
+        LoadReport::since(start, frames, latencies).with_bytes((frames * size) as u64)
+    })
+}
+
+/// Writes `frames` frames of `size` bytes through a buffer, flushing once at the end.
+async fn send<W>(writer: W, frames: usize, size: usize)
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut writer = BufWriter::new(writer);
+    let payload = vec![0; size];
+    for _ in 0..frames {
+        feed_frame(&mut writer, &payload).await.unwrap();
+    }
+    writer.flush().await.unwrap();
+}
+
+/// Reads `frames` frames of `size` bytes through a buffer.
+async fn receive<R>(reader: R, frames: usize, size: usize) -> Histogram<u64>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    let mut latencies = latency_histogram();
+    for _ in 0..frames {
+        let start = Instant::now();
+        assert!(read_frame(&mut reader, &mut buf).await.unwrap());
+        assert_eq!(buf.len(), size);
+        record(&mut latencies, start.elapsed());
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_frame_arrives() {
+        for stream in &[tokio_framed, tokio_hand_rolled, async_std_hand_rolled] {
+            let report = stream(100, 1000);
+            assert_eq!(report.latencies.len(), 100);
+            assert_eq!(report.bytes, 100_000);
+        }
+    }
+}
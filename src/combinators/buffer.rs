@@ -0,0 +1,44 @@
+//! A stream of items each waiting for a while, processed with
+//! `buffer_unordered` at growing concurrency limits.
+//!
+//! Every item sleeps for `latency` on the runtime's timer, the way a request
+//! to a backend waits for its answer, and all of them are polled from one
+//! task by [`drive`]. Throughput should grow with the limit until the timer
+//! and the single polling task become the bottleneck.
+
+use crate::harness::{drive, LoadReport};
+use std::time::Duration;
+
+/// Processes `items` items of `latency`, `limit` at a time, on tokio.
+pub fn on_tokio(limit: usize, items: usize, latency: Duration) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let processing = drive(limit, items, move || tokio::time::sleep(latency));
+        tokio::spawn(processing).await.unwrap()
+    })
+}
+
+/// Processes `items` items of `latency`, `limit` at a time, on async_std.
+pub fn on_async_std(limit: usize, items: usize, latency: Duration) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(drive(limit, items, move || {
+        task::sleep(latency)
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn more_concurrency_takes_less_time() {
+        let latency = Duration::from_millis(2);
+        for run in [on_tokio, on_async_std] {
+            let serial = run(1, 20, latency);
+            let concurrent = run(20, 20, latency);
+            assert_eq!(concurrent.latencies.len(), 20);
+            assert!(concurrent.elapsed < serial.elapsed / 4);
+        }
+    }
+}
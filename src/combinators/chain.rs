@@ -0,0 +1,138 @@
+//! Millions of items through a chain of stream combinators, with the streams
+//! of futures, async-std and tokio-stream, against the same chain on an
+//! iterator.
+//!
+//! The chain multiplies every item by three, keeps the even ones, adds one
+//! with an async step, groups them in chunks of 16 and sums the chunks. Not
+//! every library has every combinator: async-std has no `then` and no chunks,
+//! so its async step is a `map` and the chunks are gathered in the `fold`,
+//! and tokio-stream only chunks with a timeout, which is set long enough never
+//! to fire. Nothing in the chain waits, so the time is all combinator
+//! overhead; each chain runs on the executor of its library. async-std's
+//! `filter` returns pending and wakes the task for every item it drops
+//! rather than polling on, so half of its items cost a trip through the
+//! executor.
+
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Items per chunk.
+const CHUNK: usize = 16;
+
+/// How long one pass over the items took.
+pub struct ChainReport {
+    pub items: u64,
+    pub elapsed: Duration,
+    /// Sum of all chunks, the same for every library.
+    pub sum: u64,
+}
+
+impl ChainReport {
+    fn run(items: usize, chain: impl FnOnce() -> u64) -> Self {
+        let start = Instant::now();
+        let sum = chain();
+        ChainReport {
+            items: items as u64,
+            elapsed: start.elapsed(),
+            sum,
+        }
+    }
+}
+
+impl fmt::Display for ChainReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nanos = self.elapsed.as_nanos() as f64 / self.items as f64;
+        write!(f, "{:>8.2} ns/item", nanos)
+    }
+}
+
+/// Runs the chain over `items` items with `futures::stream`.
+pub fn futures(items: usize) -> ChainReport {
+    use futures::stream::{self, StreamExt};
+
+    ChainReport::run(items, || {
+        futures::executor::block_on(
+            stream::iter(0..black_box(items as u64))
+                .map(|item| item * 3)
+                .filter(|item| std::future::ready(item % 2 == 0))
+                .then(|item| async move { item + 1 })
+                .chunks(CHUNK)
+                .fold(
+                    0,
+                    |sum, chunk| async move { sum + chunk.iter().sum::<u64>() },
+                ),
+        )
+    })
+}
+
+/// Runs the chain over `items` items with `async_std::stream`.
+pub fn async_std(items: usize) -> ChainReport {
+    use async_std::stream::{self, StreamExt};
+
+    ChainReport::run(items, || {
+        let (sum, rest) = async_std::task::block_on(
+            stream::from_iter(0..black_box(items as u64))
+                .map(|item| item * 3)
+                .filter(|item| item % 2 == 0)
+                .map(|item| item + 1)
+                .fold((0, Vec::with_capacity(CHUNK)), |(sum, mut chunk), item| {
+                    chunk.push(item);
+                    if chunk.len() < CHUNK {
+                        return (sum, chunk);
+                    }
+                    let sum = sum + chunk.iter().sum::<u64>();
+                    chunk.clear();
+                    (sum, chunk)
+                }),
+        );
+        sum + rest.iter().sum::<u64>()
+    })
+}
+
+/// Runs the chain over `items` items with `tokio_stream`.
+pub fn tokio_stream(items: usize) -> ChainReport {
+    use tokio_stream::{self as stream, StreamExt};
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    ChainReport::run(items, || {
+        rt.block_on(
+            stream::iter(0..black_box(items as u64))
+                .map(|item| item * 3)
+                .filter(|item| item % 2 == 0)
+                .then(|item| async move { item + 1 })
+                .chunks_timeout(CHUNK, Duration::from_secs(3600))
+                .fold(0, |sum, chunk| sum + chunk.iter().sum::<u64>()),
+        )
+    })
+}
+
+/// Runs the chain over `items` items as an iterator.
+pub fn iterator(items: usize) -> ChainReport {
+    ChainReport::run(items, || {
+        let kept: Vec<_> = (0..black_box(items as u64))
+            .map(|item| item * 3)
+            .filter(|item| item % 2 == 0)
+            .map(|item| item + 1)
+            .collect();
+        kept.chunks(CHUNK)
+            .map(|chunk| chunk.iter().sum::<u64>())
+            .sum()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_chain_sums_the_same() {
+        let expected = iterator(1_000).sum;
+        for chain in [futures, async_std, tokio_stream] {
+            assert_eq!(chain(1_000).sum, expected);
+        }
+    }
+}
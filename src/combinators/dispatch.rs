@@ -0,0 +1,140 @@
+//! One dispatcher task receiving from dozens of channels, of which only one
+//! has a message at any time, the shape of a server task that multiplexes
+//! many connections or subsystems.
+//!
+//! A producer sends each message to a channel drawn at random and waits for
+//! the dispatcher to acknowledge it before sending the next one, so every
+//! wakeup of the dispatcher finds exactly one ready channel among many idle
+//! ones. The channels are async-channel's, which do not depend on a runtime,
+//! and the dispatcher merges them with tokio-stream's `StreamMap`, futures'
+//! `select_all` or a `poll_fn` that polls every receiver in turn, starting
+//! after the one that was ready last. Latencies are from send to dispatch.
+
+use crate::harness::{latency_histogram, record, xorshift, LoadReport};
+use futures::stream::{self, Stream, StreamExt};
+use hdrhistogram::Histogram;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Instant;
+
+/// How the dispatcher merges its channels.
+#[derive(Clone, Copy, Debug)]
+pub enum Merge {
+    StreamMap,
+    SelectAll,
+    PollFn,
+}
+
+/// Dispatches `messages` messages arriving one at a time over `channels`
+/// channels on tokio.
+pub fn on_tokio(merge: Merge, channels: usize, messages: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (senders, receivers) = open(channels);
+        let (ack, acked) = async_channel::bounded(1);
+        let start = Instant::now();
+        let dispatcher = tokio::spawn(dispatch(merge, receivers, messages, ack));
+        tokio::spawn(produce(senders, messages, acked));
+        let latencies = dispatcher.await.unwrap();
+        LoadReport::since(start, messages, latencies)
+    })
+}
+
+/// Dispatches `messages` messages arriving one at a time over `channels`
+/// channels on async_std.
+pub fn on_async_std(merge: Merge, channels: usize, messages: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (senders, receivers) = open(channels);
+        let (ack, acked) = async_channel::bounded(1);
+        let start = Instant::now();
+        let dispatcher = task::spawn(dispatch(merge, receivers, messages, ack));
+        task::spawn(produce(senders, messages, acked));
+        let latencies = dispatcher.await;
+        LoadReport::since(start, messages, latencies)
+    })
+}
+
+/// async-channel's receiver is not `Unpin`, which all three merges need.
+type Receiver = Pin<Box<async_channel::Receiver<Instant>>>;
+
+/// Opens `channels` channels that hold one message each.
+fn open(channels: usize) -> (Vec<async_channel::Sender<Instant>>, Vec<Receiver>) {
+    (0..channels)
+        .map(|_| {
+            let (sender, receiver) = async_channel::bounded(1);
+            (sender, Box::pin(receiver))
+        })
+        .unzip()
+}
+
+async fn produce(
+    senders: Vec<async_channel::Sender<Instant>>,
+    messages: usize,
+    acked: async_channel::Receiver<()>,
+) {
+    let mut rng = 1;
+    for _ in 0..messages {
+        let channel = xorshift(&mut rng) as usize % senders.len();
+        senders[channel].send(Instant::now()).await.unwrap();
+        acked.recv().await.unwrap();
+    }
+}
+
+async fn dispatch(
+    merge: Merge,
+    receivers: Vec<Receiver>,
+    messages: usize,
+    ack: async_channel::Sender<()>,
+) -> Histogram<u64> {
+    match merge {
+        Merge::StreamMap => {
+            let merged: tokio_stream::StreamMap<_, _> = receivers.into_iter().enumerate().collect();
+            drain(merged.map(|(_, sent)| sent), messages, ack).await
+        }
+        Merge::SelectAll => drain(stream::select_all(receivers), messages, ack).await,
+        Merge::PollFn => {
+            let mut receivers = receivers;
+            let mut next = 0;
+            let merged = stream::poll_fn(move |cx| {
+                for i in 0..receivers.len() {
+                    let channel = (next + i) % receivers.len();
+                    if let Poll::Ready(sent) = receivers[channel].poll_next_unpin(cx) {
+                        next = channel + 1;
+                        return Poll::Ready(sent);
+                    }
+                }
+                Poll::Pending
+            });
+            drain(merged, messages, ack).await
+        }
+    }
+}
+
+async fn drain(
+    merged: impl Stream<Item = Instant> + Unpin,
+    messages: usize,
+    ack: async_channel::Sender<()>,
+) -> Histogram<u64> {
+    let mut latencies = latency_histogram();
+    let mut merged = merged.take(messages);
+    while let Some(sent) = merged.next().await {
+        record(&mut latencies, sent.elapsed());
+        ack.send(()).await.unwrap();
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_merge_dispatches_every_message() {
+        for &merge in &[Merge::StreamMap, Merge::SelectAll, Merge::PollFn] {
+            assert_eq!(on_tokio(merge, 30, 1_000).latencies.len(), 1_000);
+            assert_eq!(on_async_std(merge, 30, 1_000).latencies.len(), 1_000);
+        }
+    }
+}
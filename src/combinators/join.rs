@@ -0,0 +1,138 @@
+//! The cost of joining a handful of futures with `tokio::join!` and
+//! `futures::join!`, against awaiting them one after another.
+//!
+//! Every joined future returns pending once, waking itself, and is ready on
+//! its second poll, so a join cannot finish in a single pass and both macros
+//! have to poll all of their branches again when woken. Awaiting in sequence
+//! polls the same futures the same number of times, but goes back to the
+//! executor once per future where a join goes back once for all of them. As
+//! with the selects, nothing waits, so the joins run on futures' executor.
+
+use std::fmt;
+use std::future::Future;
+use std::hint::black_box;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// How long the joins took.
+pub struct JoinReport {
+    pub joins: u64,
+    pub arity: usize,
+    pub elapsed: Duration,
+}
+
+impl JoinReport {
+    fn run(arity: usize, rounds: usize, join: impl Future<Output = ()>) -> Self {
+        let start = Instant::now();
+        futures::executor::block_on(join);
+        JoinReport {
+            joins: rounds as u64,
+            arity,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+impl fmt::Display for JoinReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nanos = self.elapsed.as_nanos() as f64 / self.joins as f64;
+        write!(
+            f,
+            "{:>8.1} ns/join  {:>6.1} ns/future",
+            nanos,
+            nanos / self.arity as f64
+        )
+    }
+}
+
+/// A future that is ready on its second poll.
+struct Step(bool);
+
+fn step() -> Step {
+    Step(false)
+}
+
+impl Future for Step {
+    type Output = u64;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u64> {
+        if self.0 {
+            return Poll::Ready(black_box(1));
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Expands to `$join` over one `step()` per token after it, or to awaiting
+/// them in sequence if `$join` is `sequential`.
+macro_rules! joined {
+    (sequential; $($branch:tt)*) => {{
+        $(
+            let _ = $branch;
+            black_box(step().await);
+        )*
+    }};
+    ($($join:ident)::+; $($branch:tt)*) => {{
+        black_box($($join)::+!($({
+            let _ = $branch;
+            step()
+        }),*));
+    }};
+}
+
+/// Expands to a join of `arity` futures, which must be 2, 4, 8 or 16.
+macro_rules! arities {
+    ($arity:expr, $($join:tt)+) => {
+        match $arity {
+            2 => joined!($($join)+; 0 1),
+            4 => joined!($($join)+; 0 1 2 3),
+            8 => joined!($($join)+; 0 1 2 3 4 5 6 7),
+            16 => joined!($($join)+; 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15),
+            arity => panic!("joins of {} futures are not measured", arity),
+        }
+    };
+}
+
+/// Joins `arity` futures `rounds` times with `tokio::join!`.
+pub fn tokio(arity: usize, rounds: usize) -> JoinReport {
+    JoinReport::run(arity, rounds, async {
+        for _ in 0..rounds {
+            arities!(arity, tokio::join);
+        }
+    })
+}
+
+/// Joins `arity` futures `rounds` times with `futures::join!`.
+pub fn futures(arity: usize, rounds: usize) -> JoinReport {
+    JoinReport::run(arity, rounds, async {
+        for _ in 0..rounds {
+            arities!(arity, futures::join);
+        }
+    })
+}
+
+/// Awaits `arity` futures one after another, `rounds` times.
+pub fn sequential(arity: usize, rounds: usize) -> JoinReport {
+    JoinReport::run(arity, rounds, async {
+        for _ in 0..rounds {
+            arities!(arity, sequential);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_arity_joins() {
+        for join in [tokio, futures, sequential] {
+            for arity in [2, 4, 8, 16] {
+                assert_eq!(join(arity, 10).joins, 10);
+            }
+        }
+    }
+}
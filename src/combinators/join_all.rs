@@ -0,0 +1,144 @@
+//! Waiting for a large number of futures at once with `join_all` and
+//! `FuturesUnordered`, as their number grows.
+//!
+//! A driver task completes the futures one at a time in random order, and
+//! waits for each to report back before completing the next, so that every
+//! completion wakes the joining task once. A join that polls all of its
+//! futures on every wakeup takes quadratic time this way; this is what
+//! `join_all` used to do and still does for up to 30 futures, beyond which it
+//! is a `FuturesOrdered`, which like `FuturesUnordered` only polls the futures
+//! that were woken. The old behavior is measured as `PollAll`, a `poll_fn`
+//! over `MaybeDone`s. Latencies are from completing a future to it reporting
+//! back through the joining task.
+
+use crate::harness::{latency_histogram, record, xorshift, LoadReport};
+use futures::channel::oneshot;
+use futures::future;
+use futures::stream::{FuturesUnordered, StreamExt};
+use hdrhistogram::Histogram;
+use std::future::Future;
+use std::task::Poll;
+use std::time::Instant;
+
+/// How the futures are joined.
+#[derive(Clone, Copy, Debug)]
+pub enum Join {
+    JoinAll,
+    Unordered,
+    PollAll,
+}
+
+/// Completes `futures` futures joined with `join` on tokio.
+pub fn on_tokio(join: Join, futures: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (acks, acked) = async_channel::unbounded();
+        let (senders, waiting) = waiting(futures, acks);
+        let start = Instant::now();
+        let joiner = tokio::spawn(joined(join, waiting));
+        let latencies = tokio::spawn(drive(senders, acked)).await.unwrap();
+        joiner.await.unwrap();
+        LoadReport::since(start, futures, latencies)
+    })
+}
+
+/// Completes `futures` futures joined with `join` on async_std.
+pub fn on_async_std(join: Join, futures: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (acks, acked) = async_channel::unbounded();
+        let (senders, waiting) = waiting(futures, acks);
+        let start = Instant::now();
+        let joiner = task::spawn(joined(join, waiting));
+        let latencies = task::spawn(drive(senders, acked)).await;
+        joiner.await;
+        LoadReport::since(start, futures, latencies)
+    })
+}
+
+/// Makes `futures` futures that each wait to be completed and then report
+/// back on `acks`, with the senders that complete them.
+fn waiting(
+    futures: usize,
+    acks: async_channel::Sender<()>,
+) -> (
+    Vec<oneshot::Sender<()>>,
+    Vec<impl Future<Output = ()> + Send>,
+) {
+    (0..futures)
+        .map(|_| {
+            let (sender, receiver) = oneshot::channel();
+            let acks = acks.clone();
+            let waiting = async move {
+                receiver.await.unwrap();
+                acks.try_send(()).unwrap();
+            };
+            (sender, waiting)
+        })
+        .unzip()
+}
+
+async fn joined<F: Future<Output = ()>>(join: Join, futures: Vec<F>) {
+    match join {
+        Join::JoinAll => {
+            future::join_all(futures).await;
+        }
+        Join::Unordered => {
+            let mut unordered: FuturesUnordered<_> = futures.into_iter().collect();
+            while unordered.next().await.is_some() {}
+        }
+        Join::PollAll => {
+            let mut futures: Vec<_> = futures
+                .into_iter()
+                .map(|future| Box::pin(future::maybe_done(future)))
+                .collect();
+            future::poll_fn(|cx| {
+                let mut done = true;
+                for future in &mut futures {
+                    done &= future.as_mut().poll(cx).is_ready();
+                }
+                if done {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await
+        }
+    }
+}
+
+/// Completes the futures of `senders` in random order, one after another.
+async fn drive(
+    mut senders: Vec<oneshot::Sender<()>>,
+    acked: async_channel::Receiver<()>,
+) -> Histogram<u64> {
+    let mut rng = 1;
+    for i in (1..senders.len()).rev() {
+        senders.swap(i, xorshift(&mut rng) as usize % (i + 1));
+    }
+    let mut latencies = latency_histogram();
+    for sender in senders {
+        let sent = Instant::now();
+        sender.send(()).unwrap();
+        acked.recv().await.unwrap();
+        record(&mut latencies, sent.elapsed());
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_join_completes_every_future() {
+        for &join in &[Join::JoinAll, Join::Unordered, Join::PollAll] {
+            for &futures in &[10, 100] {
+                assert_eq!(on_tokio(join, futures).latencies.len(), futures as u64);
+                assert_eq!(on_async_std(join, futures).latencies.len(), futures as u64);
+            }
+        }
+    }
+}
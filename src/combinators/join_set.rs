@@ -0,0 +1,220 @@
+//! Spawning a batch of tasks and draining their results with tokio's
+//! `JoinSet`, with the join handles in a `FuturesUnordered`, and on async_std
+//! with the handles collected and awaited in order.
+//!
+//! Each run spawns `tasks` tasks that yield once and return their index, and
+//! times the spawning and the draining separately. It then spawns as many
+//! tasks that never finish and cancels them the way each set does: dropping
+//! a `JoinSet` aborts its tasks, while dropping a join handle only detaches
+//! the task, so the others abort or cancel every handle. Cancellation is
+//! timed until every task is gone.
+
+use crate::harness::{Alive, Guard};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// How long the phases of a run took.
+pub struct SpawnReport {
+    pub tasks: usize,
+    /// Time to spawn all tasks.
+    pub spawn: Duration,
+    /// Time from the last spawn to the last result.
+    pub drain: Duration,
+    /// Time from starting to cancel the pending tasks to all being dropped.
+    pub cancel: Duration,
+}
+
+impl fmt::Display for SpawnReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let per_task = |phase: Duration| phase.as_nanos() as f64 / self.tasks as f64;
+        write!(
+            f,
+            "spawn {:>7.1} ns/task  drain {:>7.1} ns/task  cancel {:>7.1} ns/task",
+            per_task(self.spawn),
+            per_task(self.drain),
+            per_task(self.cancel)
+        )
+    }
+}
+
+/// Spawns and drains `tasks` tasks with a `JoinSet`.
+pub fn join_set(tasks: usize) -> SpawnReport {
+    use tokio::task::{yield_now, JoinSet};
+
+    on_tokio(async move {
+        let start = Instant::now();
+        let mut set = JoinSet::new();
+        for i in 0..tasks {
+            set.spawn(finishing(i, yield_now));
+        }
+        let spawned = Instant::now();
+        let mut sum = 0;
+        while let Some(i) = set.join_next().await {
+            sum += i.unwrap();
+        }
+        check_sum(tasks, sum);
+        let drained = Instant::now();
+
+        let alive = Alive::default();
+        let mut set = JoinSet::new();
+        for _ in 0..tasks {
+            set.spawn(pending(alive.guard()));
+        }
+        let cancelling = Instant::now();
+        drop(set);
+        alive.gone(yield_now).await;
+        SpawnReport::new(tasks, start, spawned, drained, cancelling)
+    })
+}
+
+/// Spawns `tasks` tokio tasks and drains their handles from a `FuturesUnordered`.
+pub fn tokio_unordered(tasks: usize) -> SpawnReport {
+    use tokio::task::yield_now;
+
+    on_tokio(async move {
+        let start = Instant::now();
+        let mut handles: FuturesUnordered<_> = (0..tasks)
+            .map(|i| tokio::spawn(finishing(i, yield_now)))
+            .collect();
+        let spawned = Instant::now();
+        let mut sum = 0;
+        while let Some(i) = handles.next().await {
+            sum += i.unwrap();
+        }
+        check_sum(tasks, sum);
+        let drained = Instant::now();
+
+        let alive = Alive::default();
+        let handles: FuturesUnordered<_> = (0..tasks)
+            .map(|_| tokio::spawn(pending(alive.guard())))
+            .collect();
+        let cancelling = Instant::now();
+        for handle in &handles {
+            handle.abort();
+        }
+        drop(handles);
+        alive.gone(yield_now).await;
+        SpawnReport::new(tasks, start, spawned, drained, cancelling)
+    })
+}
+
+/// Spawns `tasks` async_std tasks and awaits their handles in order.
+pub fn async_std_collect(tasks: usize) -> SpawnReport {
+    use async_std::task::{self, yield_now};
+
+    task::block_on(task::spawn(async move {
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|i| task::spawn(finishing(i, yield_now)))
+            .collect();
+        let spawned = Instant::now();
+        let mut sum = 0;
+        for handle in handles {
+            sum += handle.await;
+        }
+        check_sum(tasks, sum);
+        let drained = Instant::now();
+
+        let alive = Alive::default();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| task::spawn(pending(alive.guard())))
+            .collect();
+        let cancelling = Instant::now();
+        for handle in handles {
+            handle.cancel().await;
+        }
+        alive.gone(yield_now).await;
+        SpawnReport::new(tasks, start, spawned, drained, cancelling)
+    }))
+}
+
+/// Spawns `tasks` async_std tasks and drains their handles from a
+/// `FuturesUnordered`.
+pub fn async_std_unordered(tasks: usize) -> SpawnReport {
+    use async_std::task::{self, yield_now};
+
+    task::block_on(task::spawn(async move {
+        let start = Instant::now();
+        let mut handles: FuturesUnordered<_> = (0..tasks)
+            .map(|i| task::spawn(finishing(i, yield_now)))
+            .collect();
+        let spawned = Instant::now();
+        let mut sum = 0;
+        while let Some(i) = handles.next().await {
+            sum += i;
+        }
+        check_sum(tasks, sum);
+        let drained = Instant::now();
+
+        let alive = Alive::default();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| task::spawn(pending(alive.guard())))
+            .collect();
+        let cancelling = Instant::now();
+        let mut cancelled: FuturesUnordered<_> =
+            handles.into_iter().map(|handle| handle.cancel()).collect();
+        while cancelled.next().await.is_some() {}
+        alive.gone(yield_now).await;
+        SpawnReport::new(tasks, start, spawned, drained, cancelling)
+    }))
+}
+
+impl SpawnReport {
+    fn new(
+        tasks: usize,
+        start: Instant,
+        spawned: Instant,
+        drained: Instant,
+        cancelling: Instant,
+    ) -> Self {
+        SpawnReport {
+            tasks,
+            spawn: spawned - start,
+            drain: drained - spawned,
+            cancel: cancelling.elapsed(),
+        }
+    }
+}
+
+/// Runs `run` in a task of a multi-threaded tokio runtime.
+fn on_tokio(run: impl Future<Output = SpawnReport> + Send + 'static) -> SpawnReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async { tokio::spawn(run).await.unwrap() })
+}
+
+async fn finishing<F, Fut>(i: usize, yield_now: F) -> usize
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    yield_now().await;
+    i
+}
+
+async fn pending(guard: Guard) {
+    let _guard = guard;
+    std::future::pending::<()>().await
+}
+
+fn check_sum(tasks: usize, sum: usize) {
+    assert_eq!(sum, tasks * tasks.saturating_sub(1) / 2, "lost results");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_set_drains_and_cancels() {
+        for run in [
+            join_set,
+            tokio_unordered,
+            async_std_collect,
+            async_std_unordered,
+        ] {
+            assert_eq!(run(1_000).tasks, 1_000);
+        }
+    }
+}
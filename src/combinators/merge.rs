@@ -0,0 +1,116 @@
+//! A growing number of busy event streams merged into one consumer.
+//!
+//! Unlike the dispatcher in [`super::dispatch`], every input here is busy:
+//! a task per stream sends its share of the events into an async-channel as
+//! fast as it is drained, and one task consumes them all from the merged
+//! stream. The streams are merged with futures' `select_all`, tokio-stream's
+//! `StreamMap`, or a balanced tree of futures' binary `stream::select`, which
+//! is how `merge` from tokio-stream and async-std combines more than two.
+//! Latencies are from send to consumption.
+
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use futures::stream::{self, BoxStream, StreamExt};
+use hdrhistogram::Histogram;
+use std::time::Instant;
+
+/// Events each input channel holds.
+const CAPACITY: usize = 64;
+
+/// How the inputs are merged.
+#[derive(Clone, Copy, Debug)]
+pub enum Merge {
+    SelectAll,
+    StreamMap,
+    SelectTree,
+}
+
+/// Sends `events` events over `streams` streams merged with `merge` on tokio.
+pub fn on_tokio(merge: Merge, streams: usize, events: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (senders, receivers) = open(streams);
+        let start = Instant::now();
+        let consumer = tokio::spawn(consume(merged(merge, receivers)));
+        for (sender, share) in senders.into_iter().zip(split(events, streams)) {
+            tokio::spawn(produce(sender, share));
+        }
+        let latencies = consumer.await.unwrap();
+        LoadReport::since(start, events, latencies)
+    })
+}
+
+/// Sends `events` events over `streams` streams merged with `merge` on async_std.
+pub fn on_async_std(merge: Merge, streams: usize, events: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (senders, receivers) = open(streams);
+        let start = Instant::now();
+        let consumer = task::spawn(consume(merged(merge, receivers)));
+        for (sender, share) in senders.into_iter().zip(split(events, streams)) {
+            task::spawn(produce(sender, share));
+        }
+        let latencies = consumer.await;
+        LoadReport::since(start, events, latencies)
+    })
+}
+
+type Input = BoxStream<'static, Instant>;
+
+fn open(streams: usize) -> (Vec<async_channel::Sender<Instant>>, Vec<Input>) {
+    (0..streams)
+        .map(|_| {
+            let (sender, receiver) = async_channel::bounded(CAPACITY);
+            (sender, receiver.boxed())
+        })
+        .unzip()
+}
+
+fn merged(merge: Merge, inputs: Vec<Input>) -> Input {
+    match merge {
+        Merge::SelectAll => stream::select_all(inputs).boxed(),
+        Merge::StreamMap => {
+            let map: tokio_stream::StreamMap<_, _> = inputs.into_iter().enumerate().collect();
+            map.map(|(_, sent)| sent).boxed()
+        }
+        Merge::SelectTree => tree(inputs),
+    }
+}
+
+/// Merges `inputs` two at a time, halving them at every level.
+fn tree(mut inputs: Vec<Input>) -> Input {
+    if inputs.len() == 1 {
+        return inputs.pop().unwrap();
+    }
+    let right = inputs.split_off(inputs.len() / 2);
+    stream::select(tree(inputs), tree(right)).boxed()
+}
+
+async fn produce(sender: async_channel::Sender<Instant>, events: usize) {
+    for _ in 0..events {
+        sender.send(Instant::now()).await.unwrap();
+    }
+}
+
+async fn consume(mut merged: Input) -> Histogram<u64> {
+    let mut latencies = latency_histogram();
+    while let Some(sent) = merged.next().await {
+        record(&mut latencies, sent.elapsed());
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_merge_delivers_every_event() {
+        for &merge in &[Merge::SelectAll, Merge::StreamMap, Merge::SelectTree] {
+            for &streams in &[1, 7] {
+                assert_eq!(on_tokio(merge, streams, 1_000).latencies.len(), 1_000);
+                assert_eq!(on_async_std(merge, streams, 1_000).latencies.len(), 1_000);
+            }
+        }
+    }
+}
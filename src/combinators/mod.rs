@@ -0,0 +1,13 @@
+//! Combinators that wait for several futures at once: selecting, joining and
+//! draining collections of futures and of spawned tasks.
+
+pub mod buffer;
+pub mod chain;
+pub mod dispatch;
+pub mod join;
+pub mod join_all;
+pub mod join_set;
+pub mod merge;
+pub mod select;
+pub mod try_join;
+pub mod unordered;
@@ -0,0 +1,149 @@
+//! The cost of one select over two futures that are ready at once, and which
+//! branch it picks.
+//!
+//! `tokio::select!` and `futures::select!` both start polling at a random
+//! branch, so that a branch that is always ready cannot starve the others; a
+//! hand-written `poll_fn` select polls in order and always picks the first
+//! ready one. The sources are either both ready, which shows how evenly each
+//! select picks, or a pending one followed by a ready one, which adds the
+//! cost of polling a branch that is not ready. Nothing ever waits, so the
+//! selects run on futures' single-threaded executor, away from any runtime.
+
+use futures::future::FusedFuture;
+use std::fmt;
+use std::future::Future;
+use std::hint::black_box;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// What the two branches of every select are.
+#[derive(Clone, Copy, Debug)]
+pub enum Sources {
+    BothReady,
+    PendingReady,
+}
+
+impl Sources {
+    fn make(self) -> (Source, Source) {
+        match self {
+            Sources::BothReady => (Source(black_box(true)), Source(black_box(true))),
+            Sources::PendingReady => (Source(black_box(false)), Source(black_box(true))),
+        }
+    }
+}
+
+/// A future that is either ready right away or never.
+struct Source(bool);
+
+impl Future for Source {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl FusedFuture for Source {
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
+
+/// How long the selects took and how often they picked the first branch.
+pub struct SelectReport {
+    pub selects: u64,
+    pub elapsed: Duration,
+    pub first: u64,
+}
+
+impl SelectReport {
+    fn run(rounds: usize, select: impl Future<Output = u64>) -> Self {
+        let start = Instant::now();
+        let first = futures::executor::block_on(select);
+        SelectReport {
+            selects: rounds as u64,
+            elapsed: start.elapsed(),
+            first,
+        }
+    }
+}
+
+impl fmt::Display for SelectReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nanos = self.elapsed.as_nanos() as f64 / self.selects as f64;
+        let first = 100.0 * self.first as f64 / self.selects as f64;
+        write!(f, "{:>8.1} ns/select  first branch {:>5.1}%", nanos, first)
+    }
+}
+
+/// Selects `rounds` times between `sources` with `tokio::select!`.
+pub fn tokio(sources: Sources, rounds: usize) -> SelectReport {
+    SelectReport::run(rounds, async {
+        let mut first = 0;
+        for _ in 0..rounds {
+            let (a, b) = sources.make();
+            first += tokio::select! {
+                _ = a => 1,
+                _ = b => 0,
+            };
+        }
+        first
+    })
+}
+
+/// Selects `rounds` times between `sources` with `futures::select!`.
+pub fn futures(sources: Sources, rounds: usize) -> SelectReport {
+    SelectReport::run(rounds, async {
+        let mut first = 0;
+        for _ in 0..rounds {
+            let (mut a, mut b) = sources.make();
+            first += futures::select! {
+                _ = a => 1,
+                _ = b => 0,
+            };
+        }
+        first
+    })
+}
+
+/// Selects `rounds` times between `sources` with a `poll_fn` polling them in order.
+pub fn poll_fn(sources: Sources, rounds: usize) -> SelectReport {
+    SelectReport::run(rounds, async {
+        let mut first = 0;
+        for _ in 0..rounds {
+            let (mut a, mut b) = sources.make();
+            first += std::future::poll_fn(|cx| {
+                if Pin::new(&mut a).poll(cx).is_ready() {
+                    return Poll::Ready(1);
+                }
+                if Pin::new(&mut b).poll(cx).is_ready() {
+                    return Poll::Ready(0);
+                }
+                Poll::Pending
+            })
+            .await;
+        }
+        first
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_hand_written_select_is_biased() {
+        for select in [tokio, futures] {
+            let report = select(Sources::BothReady, 10_000);
+            assert!(report.first > 4_000 && report.first < 6_000);
+            assert_eq!(select(Sources::PendingReady, 100).first, 0);
+        }
+        assert_eq!(poll_fn(Sources::BothReady, 100).first, 100);
+        assert_eq!(poll_fn(Sources::PendingReady, 100).first, 0);
+    }
+}
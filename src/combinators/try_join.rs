@@ -0,0 +1,228 @@
+//! Joining fallible work where one of the futures fails early, and how much
+//! of the rest keeps running after the error.
+//!
+//! Every future works in units, spinning for `unit` and yielding after each,
+//! and the first one fails after `fail_after` units. Joins within one task,
+//! `try_join_all` and a `FuturesUnordered` returning at the first error, drop
+//! the other futures as they return. Spawned tasks are cancelled: a `JoinSet`
+//! on tokio aborts its tasks when dropped and async_std's handles are
+//! cancelled one by one, but a task that is running on another worker thread
+//! only stops at its next yield. Detached tasks, whose handles are simply
+//! dropped, run to completion.
+//!
+//! Teardown is the time from the failure until every other future has been
+//! dropped, and wasted work are the units done after the failure.
+
+use crate::harness::{latency_histogram, record, Alive, Guard, Millis};
+use futures::stream::{FuturesUnordered, StreamExt};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How the fallible futures are joined.
+#[derive(Clone, Copy, Debug)]
+pub enum Join {
+    TryJoinAll,
+    Unordered,
+    /// Spawned into a `JoinSet` on tokio and cancelled on async_std.
+    Spawned,
+    /// Spawned, with the handles dropped.
+    Detached,
+}
+
+/// The work joined in every run.
+#[derive(Clone, Copy, Debug)]
+pub struct Work {
+    /// Futures joined, of which one fails.
+    pub futures: usize,
+    /// Units of work a future does unless it fails or is dropped.
+    pub units: usize,
+    /// Time spun per unit.
+    pub unit: Duration,
+    /// Units done by the failing future before it fails.
+    pub fail_after: usize,
+}
+
+/// How long teardowns took and how much work was wasted.
+pub struct TryJoinReport {
+    pub runs: usize,
+    pub teardown: Histogram<u64>,
+    pub wasted: u64,
+}
+
+impl fmt::Display for TryJoinReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "teardown p50 {}  p99 {}  max {}  wasted units/run {:.1}",
+            Millis(self.teardown.value_at_quantile(0.5)),
+            Millis(self.teardown.value_at_quantile(0.99)),
+            Millis(self.teardown.max()),
+            self.wasted as f64 / self.runs as f64
+        )
+    }
+}
+
+/// The failure of the failing future.
+#[derive(Debug)]
+struct Failed;
+
+/// What the futures of one run share.
+#[derive(Default)]
+struct Run {
+    failed: OnceLock<Instant>,
+    wasted: AtomicU64,
+    alive: Alive,
+}
+
+/// Joins `work` `runs` times with `join` on tokio.
+pub fn on_tokio(join: Join, work: Work, runs: usize) -> TryJoinReport {
+    use tokio::task::{yield_now, JoinSet};
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        tokio::spawn(async move {
+            let mut report = TryJoinReport::new(runs);
+            for _ in 0..runs {
+                let run = Arc::new(Run::default());
+                let futures = (0..work.futures).map(|i| working(i, work, run.clone(), yield_now));
+                match join {
+                    Join::TryJoinAll => drop(futures::future::try_join_all(futures).await),
+                    Join::Unordered => drop(first_error(futures.collect()).await),
+                    Join::Spawned => {
+                        let mut set = JoinSet::new();
+                        for future in futures {
+                            set.spawn(future);
+                        }
+                        while let Some(result) = set.join_next().await {
+                            if result.unwrap().is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Join::Detached => {
+                        let handles = futures
+                            .map(|future| {
+                                let handle = tokio::spawn(future);
+                                async move { handle.await.unwrap() }
+                            })
+                            .collect();
+                        drop(first_error(handles).await);
+                    }
+                }
+                run.alive.gone(yield_now).await;
+                report.add(&run);
+            }
+            report
+        })
+        .await
+        .unwrap()
+    })
+}
+
+/// Joins `work` `runs` times with `join` on async_std.
+pub fn on_async_std(join: Join, work: Work, runs: usize) -> TryJoinReport {
+    use async_std::task::{self, yield_now};
+
+    task::block_on(task::spawn(async move {
+        let mut report = TryJoinReport::new(runs);
+        for _ in 0..runs {
+            let run = Arc::new(Run::default());
+            let futures = (0..work.futures).map(|i| working(i, work, run.clone(), yield_now));
+            match join {
+                Join::TryJoinAll => drop(futures::future::try_join_all(futures).await),
+                Join::Unordered => drop(first_error(futures.collect()).await),
+                Join::Spawned => {
+                    let mut handles: FuturesUnordered<_> = futures.map(task::spawn).collect();
+                    while let Some(result) = handles.next().await {
+                        if result.is_err() {
+                            break;
+                        }
+                    }
+                    let cancelled: FuturesUnordered<_> =
+                        handles.into_iter().map(|handle| handle.cancel()).collect();
+                    cancelled.collect::<Vec<_>>().await;
+                }
+                Join::Detached => {
+                    let handles = futures.map(task::spawn).collect();
+                    drop(first_error(handles).await);
+                }
+            }
+            run.alive.gone(yield_now).await;
+            report.add(&run);
+        }
+        report
+    }))
+}
+
+impl TryJoinReport {
+    fn new(runs: usize) -> Self {
+        TryJoinReport {
+            runs,
+            teardown: latency_histogram(),
+            wasted: 0,
+        }
+    }
+
+    fn add(&mut self, run: &Run) {
+        record(&mut self.teardown, run.failed.get().unwrap().elapsed());
+        self.wasted += run.wasted.load(Ordering::Relaxed);
+    }
+}
+
+/// Drains `futures` up to the first error, or all of them.
+async fn first_error<F, E>(mut futures: FuturesUnordered<F>) -> Result<(), E>
+where
+    F: Future<Output = Result<(), E>>,
+{
+    while let Some(result) = futures.next().await {
+        result?;
+    }
+    Ok(())
+}
+
+/// Does the work of the `i`-th future, of which the first fails.
+async fn working<F, Fut>(i: usize, work: Work, run: Arc<Run>, yield_now: F) -> Result<(), Failed>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let _guard: Guard = run.alive.guard();
+    for unit in 0..work.units {
+        if i == 0 && unit == work.fail_after {
+            run.failed.set(Instant::now()).unwrap();
+            return Err(Failed);
+        }
+        let spun = Instant::now();
+        while spun.elapsed() < work.unit {}
+        if run.failed.get().is_some() {
+            run.wasted.fetch_add(1, Ordering::Relaxed);
+        }
+        yield_now().await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_detached_tasks_finish_their_work() {
+        let work = Work {
+            futures: 8,
+            units: 50,
+            unit: Duration::from_micros(1),
+            fail_after: 5,
+        };
+        for run in [on_tokio, on_async_std] {
+            for join in [Join::TryJoinAll, Join::Unordered, Join::Spawned] {
+                assert!(run(join, work, 10).wasted < 10 * 7 * 45);
+            }
+            assert!(run(Join::Detached, work, 10).wasted >= 10 * 7 * 44);
+        }
+    }
+}
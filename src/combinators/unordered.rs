@@ -0,0 +1,151 @@
+//! Tens of thousands of futures that behave differently driven by one
+//! `FuturesUnordered`.
+//!
+//! A third of the futures are ready on their first poll, a third wake
+//! themselves and return pending between one and eight times before they are
+//! ready, and a third sleep on the runtime's timer for up to `sleep`. The
+//! self-waking ones make a poll storm: `FuturesUnordered` polls what was
+//! woken in a round and gives way to the executor every 32 polls, so the
+//! report counts both the polls of the futures and the polls of the
+//! `FuturesUnordered` itself. Latencies are from the start of the run to each
+//! future's completion.
+
+use crate::harness::{latency_histogram, record, xorshift, LoadReport};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::fmt;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+/// A load report over the completed futures with how often they were polled.
+pub struct UnorderedReport {
+    pub load: LoadReport,
+    /// Polls of the futures.
+    pub polls: u64,
+    /// Polls of the `FuturesUnordered`.
+    pub rounds: u64,
+}
+
+impl fmt::Display for UnorderedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  polls/future {:.2}  rounds {}",
+            self.load,
+            self.polls as f64 / self.load.requests as f64,
+            self.rounds
+        )
+    }
+}
+
+/// Drives `futures` mixed futures, sleeping for up to `sleep`, on tokio.
+pub fn on_tokio(futures: usize, sleep: Duration) -> UnorderedReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        tokio::spawn(drive(futures, sleep, tokio::time::sleep))
+            .await
+            .unwrap()
+    })
+}
+
+/// Drives `futures` mixed futures, sleeping for up to `sleep`, on async_std.
+pub fn on_async_std(futures: usize, sleep: Duration) -> UnorderedReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(drive(futures, sleep, task::sleep)))
+}
+
+async fn drive<F, Fut>(futures: usize, sleep: Duration, sleeper: F) -> UnorderedReport
+where
+    F: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let polls = AtomicU64::new(0);
+    let mut rng = 1;
+    let mut unordered: FuturesUnordered<_> = (0..futures)
+        .map(|_| {
+            let draw = xorshift(&mut rng);
+            let behavior = mixed(draw, sleep, &sleeper);
+            let polls = &polls;
+            async move {
+                let mut behavior = pin!(behavior);
+                std::future::poll_fn(|cx| {
+                    polls.fetch_add(1, Ordering::Relaxed);
+                    behavior.as_mut().poll(cx)
+                })
+                .await
+            }
+        })
+        .collect();
+
+    let start = Instant::now();
+    let mut rounds = 0;
+    let mut latencies = latency_histogram();
+    loop {
+        let next = std::future::poll_fn(|cx| {
+            rounds += 1;
+            unordered.poll_next_unpin(cx)
+        })
+        .await;
+        match next {
+            Some(()) => record(&mut latencies, start.elapsed()),
+            None => break,
+        }
+    }
+    UnorderedReport {
+        load: LoadReport::since(start, futures, latencies),
+        polls: polls.load(Ordering::Relaxed),
+        rounds,
+    }
+}
+
+/// What a future does, chosen by the random `draw`.
+async fn mixed<F, Fut>(draw: u64, sleep: Duration, sleeper: &F)
+where
+    F: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    match draw % 3 {
+        0 => {}
+        1 => {
+            for _ in 0..=draw / 3 % 8 {
+                yield_once().await;
+            }
+        }
+        _ => {
+            let nanos = sleep.as_nanos() as u64;
+            sleeper(Duration::from_nanos(draw / 3 % (nanos + 1))).await
+        }
+    }
+}
+
+/// Returns pending once, waking the task right away.
+async fn yield_once() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_future_completes() {
+        for run in [on_tokio, on_async_std] {
+            let report = run(3_000, Duration::from_millis(5));
+            assert_eq!(report.load.requests, 3_000);
+            assert!(report.polls > 3_000 * 2);
+            assert!(report.rounds < report.polls);
+        }
+    }
+}
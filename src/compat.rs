@@ -0,0 +1,176 @@
+//! tokio's sockets and files used from async_std and smol through
+//! async-compat, and async_std's used from tokio, against each used on its
+//! own runtime.
+//!
+//! tokio's IO types only work inside a tokio runtime, as they register with
+//! its reactor and run file operations on its blocking pool. async-compat
+//! wraps a future so that a hidden tokio runtime, started on a thread of its
+//! own the first time it is needed, is entered around every poll; the
+//! reactor and the blocking pool are those of that runtime, and its thread
+//! drives them, so every readiness event crosses from that thread to the
+//! host executor. async_std's types need no shim on tokio: async-io runs its
+//! reactor on a thread of its own wherever the futures are polled, and
+//! async_std's file operations go to its own blocking pool.
+//!
+//! Every run is one task: either a one-byte ping-pong on a loopback
+//! connection, the client and the echoing server joined in that task, or
+//! reads of a whole file, one after another. Latencies are those of the
+//! round trips or the reads.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use std::future::Future;
+use std::path::Path;
+use std::time::Instant;
+
+/// The executor a future runs on.
+#[derive(Clone, Copy, Debug)]
+pub enum Host {
+    Tokio,
+    AsyncStd,
+    Smol,
+}
+
+/// Runs `future` in a task on `host`.
+fn hosted<F>(host: Host, future: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match host {
+        Host::Tokio => {
+            let rt = crate::metrics::runtime();
+            rt.block_on(async { tokio::spawn(future).await.unwrap() })
+        }
+        Host::AsyncStd => async_std::task::block_on(async_std::task::spawn(future)),
+        Host::Smol => smol::block_on(smol::spawn(future)),
+    }
+}
+
+/// Runs `future`, which needs a tokio runtime, in a task on `host`, through
+/// async-compat unless `host` is tokio.
+fn shimmed<F>(host: Host, future: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match host {
+        Host::Tokio => hosted(host, future),
+        Host::AsyncStd | Host::Smol => hosted(host, async_compat::Compat::new(future)),
+    }
+}
+
+/// Sends `pings` one-byte pings over tokio sockets on `host`.
+pub fn tokio_pings(host: Host, pings: usize) -> LoadReport {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    shimmed(host, async move {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = async {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            let mut byte = [0];
+            while stream.read_exact(&mut byte).await.is_ok() {
+                stream.write_all(&byte).await.unwrap();
+            }
+        };
+        let client = async {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            let mut latencies = latency_histogram();
+            let start = Instant::now();
+            for _ in 0..pings {
+                let sent = Instant::now();
+                stream.write_all(b"!").await.unwrap();
+                stream.read_exact(&mut [0]).await.unwrap();
+                record(&mut latencies, sent.elapsed());
+            }
+            LoadReport::since(start, pings, latencies)
+        };
+        futures::join!(server, client).1
+    })
+}
+
+/// Sends `pings` one-byte pings over async_std sockets on `host`.
+pub fn async_std_pings(host: Host, pings: usize) -> LoadReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    hosted(host, async move {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = async {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            let mut byte = [0];
+            while stream.read_exact(&mut byte).await.is_ok() {
+                stream.write_all(&byte).await.unwrap();
+            }
+        };
+        let client = async {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            let mut latencies = latency_histogram();
+            let start = Instant::now();
+            for _ in 0..pings {
+                let sent = Instant::now();
+                stream.write_all(b"!").await.unwrap();
+                stream.read_exact(&mut [0]).await.unwrap();
+                record(&mut latencies, sent.elapsed());
+            }
+            LoadReport::since(start, pings, latencies)
+        };
+        futures::join!(server, client).1
+    })
+}
+
+/// Reads the file at `path` `reads` times with `tokio::fs` on `host`.
+pub fn tokio_reads(host: Host, path: &Path, reads: usize) -> LoadReport {
+    let path = path.to_owned();
+    shimmed(host, async move {
+        let mut latencies = latency_histogram();
+        let mut bytes = 0;
+        let start = Instant::now();
+        for _ in 0..reads {
+            let read = Instant::now();
+            bytes += tokio::fs::read(&path).await.unwrap().len() as u64;
+            record(&mut latencies, read.elapsed());
+        }
+        LoadReport::since(start, reads, latencies).with_bytes(bytes)
+    })
+}
+
+/// Reads the file at `path` `reads` times with `async_std::fs` on `host`.
+pub fn async_std_reads(host: Host, path: &Path, reads: usize) -> LoadReport {
+    let path = path.to_owned();
+    hosted(host, async move {
+        let mut latencies = latency_histogram();
+        let mut bytes = 0;
+        let start = Instant::now();
+        for _ in 0..reads {
+            let read = Instant::now();
+            bytes += async_std::fs::read(&path).await.unwrap().len() as u64;
+            record(&mut latencies, read.elapsed());
+        }
+        LoadReport::since(start, reads, latencies).with_bytes(bytes)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_host_runs_both_ecosystems() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("read");
+        std::fs::write(&path, [7; 4096]).unwrap();
+        for host in [Host::Tokio, Host::AsyncStd, Host::Smol] {
+            assert_eq!(tokio_pings(host, 100).requests, 100);
+            assert_eq!(async_std_pings(host, 100).requests, 100);
+            assert_eq!(tokio_reads(host, &path, 10).bytes, 40_960);
+            assert_eq!(async_std_reads(host, &path, 10).bytes, 40_960);
+        }
+    }
+}
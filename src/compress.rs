@@ -0,0 +1,280 @@
+//! Serving a large file gzip-compressed over a socket, the way a server sends
+//! big compressed responses.
+//!
+//! For every connection the server opens the file, reads it in chunks,
+//! compresses them as they come and writes the compressed stream to the
+//! socket, so that neither the file nor its compressed form is ever held in
+//! memory as a whole. On the runtimes the encoder is async-compression's
+//! streaming gzip encoder over the runtime's own file and socket types; the
+//! synchronous baseline uses flate2 with a thread per connection. Clients only
+//! count the compressed bytes they receive.
+//!
+//! Bandwidth is reported for the uncompressed file, as the amount of content
+//! served.
+
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use std::fmt;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A load report over transfers together with how much was sent over the wire.
+pub struct CompressReport {
+    pub load: LoadReport,
+    /// Compressed bytes received by the clients.
+    pub compressed: u64,
+}
+
+impl fmt::Display for CompressReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ratio = self.compressed as f64 / self.load.bytes as f64;
+        write!(f, "{}  ratio {:.3}", self.load, ratio)
+    }
+}
+
+impl CompressReport {
+    fn new(start: Instant, size: u64, transfers: Vec<(Duration, u64)>) -> Self {
+        let mut latencies = latency_histogram();
+        let mut compressed = 0;
+        for &(latency, bytes) in &transfers {
+            record(&mut latencies, latency);
+            compressed += bytes;
+        }
+        let load = LoadReport::since(start, transfers.len(), latencies);
+        CompressReport {
+            load: load.with_bytes(size * transfers.len() as u64),
+            compressed,
+        }
+    }
+}
+
+/// Writes `bytes` bytes of log-like text, which compresses about as well as
+/// typical text responses, to `path`.
+pub fn write_sample(path: &Path, bytes: usize) {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    let mut written = 0;
+    for line in 0.. {
+        let text = format!(
+            "{:010} INFO request {} served from cache shard {} in {}us\n",
+            line,
+            line * 7919 % 100_003,
+            line % 17,
+            line * 31 % 977
+        );
+        let text = &text.as_bytes()[..text.len().min(bytes - written)];
+        file.write_all(text).unwrap();
+        written += text.len();
+        if written == bytes {
+            break;
+        }
+    }
+    file.flush().unwrap();
+}
+
+/// Sends the compressed file at `path` `transfers` times from a tokio server,
+/// `concurrency` connections at a time, reading it in chunks of `chunk` bytes.
+pub fn tokio(path: &Path, concurrency: usize, transfers: usize, chunk: usize) -> CompressReport {
+    use async_compression::tokio::bufread::GzipEncoder;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    let size = std::fs::metadata(path).unwrap().len();
+    let path = path.to_path_buf();
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let path = path.clone();
+                tokio::spawn(async move {
+                    let file = tokio::fs::File::open(path).await.unwrap();
+                    let mut gzip = GzipEncoder::new(BufReader::with_capacity(chunk, file));
+                    tokio::io::copy(&mut gzip, &mut stream).await.unwrap();
+                    stream.shutdown().await.unwrap();
+                });
+            }
+        });
+
+        let start = Instant::now();
+        let clients: Vec<_> = split(transfers, concurrency)
+            .map(|share| {
+                tokio::spawn(async move {
+                    let mut buf = vec![0; chunk];
+                    let mut timings = Vec::with_capacity(share);
+                    for _ in 0..share {
+                        let started = Instant::now();
+                        let mut stream = TcpStream::connect(addr).await.unwrap();
+                        let mut received = 0;
+                        loop {
+                            match stream.read(&mut buf).await.unwrap() {
+                                0 => break,
+                                len => received += len as u64,
+                            }
+                        }
+                        timings.push((started.elapsed(), received));
+                    }
+                    timings
+                })
+            })
+            .collect();
+        let mut timings = Vec::with_capacity(transfers);
+        for client in clients {
+            timings.extend(client.await.unwrap());
+        }
+        CompressReport::new(start, size, timings)
+    })
+}
+
+/// Sends the compressed file at `path` `transfers` times from an async_std
+/// server, `concurrency` connections at a time, reading it in chunks of
+/// `chunk` bytes.
+pub fn async_std(
+    path: &Path,
+    concurrency: usize,
+    transfers: usize,
+    chunk: usize,
+) -> CompressReport {
+    use async_compression::futures::bufread::GzipEncoder;
+    use async_std::io::{BufReader, ReadExt};
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+
+    let size = std::fs::metadata(path).unwrap().len();
+    let path = path.to_path_buf();
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept = task::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let path = path.clone();
+                task::spawn(async move {
+                    let file = async_std::fs::File::open(path).await.unwrap();
+                    let mut gzip = GzipEncoder::new(BufReader::with_capacity(chunk, file));
+                    async_std::io::copy(&mut gzip, &mut stream).await.unwrap();
+                    stream.shutdown(std::net::Shutdown::Write).unwrap();
+                });
+            }
+        });
+
+        let start = Instant::now();
+        let clients: Vec<_> = split(transfers, concurrency)
+            .map(|share| {
+                task::spawn(async move {
+                    let mut buf = vec![0; chunk];
+                    let mut timings = Vec::with_capacity(share);
+                    for _ in 0..share {
+                        let started = Instant::now();
+                        let mut stream = TcpStream::connect(addr).await.unwrap();
+                        let mut received = 0;
+                        loop {
+                            match stream.read(&mut buf).await.unwrap() {
+                                0 => break,
+                                len => received += len as u64,
+                            }
+                        }
+                        timings.push((started.elapsed(), received));
+                    }
+                    timings
+                })
+            })
+            .collect();
+        let mut timings = Vec::with_capacity(transfers);
+        for client in clients {
+            timings.extend(client.await);
+        }
+        let report = CompressReport::new(start, size, timings);
+        accept.cancel().await;
+        report
+    })
+}
+
+/// Sends the compressed file at `path` `transfers` times from a thread per
+/// connection with flate2, `concurrency` connections at a time, reading it in
+/// chunks of `chunk` bytes.
+pub fn std_threads(
+    path: &Path,
+    concurrency: usize,
+    transfers: usize,
+    chunk: usize,
+) -> CompressReport {
+    use flate2::read::GzEncoder;
+    use flate2::Compression;
+    use std::io::{BufReader, Read};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    let size = std::fs::metadata(path).unwrap().len();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr: SocketAddr = listener.local_addr().unwrap();
+    let start = Instant::now();
+    let timings = thread::scope(|scope| {
+        scope.spawn(move || {
+            for _ in 0..transfers {
+                let (mut stream, _) = listener.accept().unwrap();
+                scope.spawn(move || {
+                    let file = std::fs::File::open(path).unwrap();
+                    let reader = BufReader::with_capacity(chunk, file);
+                    let mut gzip = GzEncoder::new(reader, Compression::default());
+                    std::io::copy(&mut gzip, &mut stream).unwrap();
+                });
+            }
+        });
+        let clients: Vec<_> = split(transfers, concurrency)
+            .map(|share| {
+                scope.spawn(move || {
+                    let mut buf = vec![0; chunk];
+                    let mut timings = Vec::with_capacity(share);
+                    for _ in 0..share {
+                        let started = Instant::now();
+                        let mut stream = TcpStream::connect(addr).unwrap();
+                        let mut received = 0;
+                        loop {
+                            match stream.read(&mut buf).unwrap() {
+                                0 => break,
+                                len => received += len as u64,
+                            }
+                        }
+                        timings.push((started.elapsed(), received));
+                    }
+                    timings
+                })
+            })
+            .collect();
+        let mut timings = Vec::with_capacity(transfers);
+        for client in clients {
+            timings.extend(client.join().unwrap());
+        }
+        timings
+    });
+    CompressReport::new(start, size, timings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_has_the_requested_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sample");
+        write_sample(&path, 12_345);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 12_345);
+    }
+
+    #[test]
+    fn transfers_arrive_compressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sample");
+        write_sample(&path, 1 << 20);
+        for server in &[tokio, async_std, std_threads] {
+            let report = server(&path, 2, 5, 16 * 1024);
+            assert_eq!(report.load.requests, 5);
+            assert_eq!(report.load.bytes, 5 << 20);
+            assert!(report.compressed > 0 && report.compressed < report.load.bytes / 4);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Benchmark parameters that can be overridden through environment variables.
+
+use std::env;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+/// Reads `name` from the environment, falling back to `default` when it is not set.
+///
+/// Panics if the variable is set but cannot be parsed, so that a typo does not
+/// silently run the benchmark with the default value.
+pub fn env_or<T>(name: &str, default: T) -> T
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|err| panic!("invalid value {:?} for {}: {:?}", value, name, err)),
+        Err(_) => default,
+    }
+}
+
+/// Reads a comma-separated list from `name`, falling back to `default` when it is not set.
+///
+/// Used for parameters that are swept, so that every value in the list gets its
+/// own run.
+pub fn env_list<T>(name: &str, default: &[T]) -> Vec<T>
+where
+    T: FromStr + Clone,
+    T::Err: Debug,
+{
+    match env::var(name) {
+        Ok(value) => value
+            .split(',')
+            .map(|item| {
+                item.trim()
+                    .parse()
+                    .unwrap_or_else(|err| panic!("invalid value {:?} in {}: {:?}", item, name, err))
+            })
+            .collect(),
+        Err(_) => default.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_default() {
+        assert_eq!(env_or("ASYNC_RUNTIMES_CMP_UNSET", 42u32), 42);
+    }
+
+    #[test]
+    fn parses_set_value() {
+        env::set_var("ASYNC_RUNTIMES_CMP_SET", "7");
+        assert_eq!(env_or("ASYNC_RUNTIMES_CMP_SET", 42u32), 7);
+    }
+
+    #[test]
+    fn parses_list() {
+        env::set_var("ASYNC_RUNTIMES_CMP_LIST", "1, 2,3");
+        assert_eq!(env_list("ASYNC_RUNTIMES_CMP_LIST", &[9u32]), vec![1, 2, 3]);
+        assert_eq!(env_list("ASYNC_RUNTIMES_CMP_UNSET_LIST", &[9u32]), vec![9]);
+    }
+}
@@ -0,0 +1,224 @@
+//! Pages of the results dashboard, which the `dashboard` binary serves.
+//!
+//! The history the dashboard shows is the result files written with
+//! `RESULTS`, see [`results`](crate::results), read again for every page so
+//! that runs appended meanwhile show up. Every run in them, in the order of
+//! the files and then of the runs in each, is a point in time. A page shows
+//! one workload, as the scenario names split it, picked from all of them:
+//! the figure every scenario of it is judged by, its first rate or else its
+//! first time, plotted over the runs, one line per runtime, and every report
+//! below, optionally only those of one runtime.
+
+use crate::repeat;
+use crate::results::{self, Run};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Every run of the result files, with the file it came from.
+pub struct History {
+    runs: Vec<(String, Run)>,
+}
+
+impl History {
+    /// Reads the runs of every file of `paths`, in order.
+    pub fn load(paths: &[impl AsRef<Path>]) -> io::Result<Self> {
+        let mut runs = Vec::new();
+        for path in paths {
+            let source = path.as_ref().display().to_string();
+            for run in results::parse(&fs::read_to_string(path)?) {
+                runs.push((source.clone(), run));
+            }
+        }
+        Ok(History { runs })
+    }
+
+    /// Every workload of the history, in the order they first ran.
+    fn workloads(&self) -> Vec<String> {
+        let mut workloads = Vec::new();
+        for (_, run) in &self.runs {
+            for (name, _) in &run.reports {
+                let (workload, _) = results::split_runtime(name);
+                if !workloads.contains(&workload) {
+                    workloads.push(workload);
+                }
+            }
+        }
+        workloads
+    }
+
+    /// The reports of `workload`, with the index of the run, the runtime
+    /// and the system, in the order they ran.
+    fn reports(&self, workload: &str) -> Vec<(usize, &'static str, String, &str)> {
+        let mut reports = Vec::new();
+        for (i, (_, run)) in self.runs.iter().enumerate() {
+            for (name, report) in &run.reports {
+                let (of, runtime) = results::split_runtime(name);
+                if of == workload {
+                    reports.push((i, runtime, run.system.label(), report.as_str()));
+                }
+            }
+        }
+        reports
+    }
+}
+
+/// Escapes `text` for HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Colours the runtimes are drawn in, in turn.
+const COLOURS: [&str; 6] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b",
+];
+
+/// Plots the figure of every runtime of `reports` over the `runs`.
+fn trend(runs: usize, reports: &[(usize, &'static str, String, &str)]) -> String {
+    const WIDTH: f64 = 800.0;
+    const HEIGHT: f64 = 300.0;
+    const MARGIN: f64 = 40.0;
+    let mut runtimes: Vec<&str> = Vec::new();
+    let mut points: Vec<(usize, &str, f64)> = Vec::new();
+    for &(run, runtime, _, report) in reports {
+        if let Some(figure) = repeat::figure(report) {
+            points.push((run, runtime, figure));
+            if !runtimes.contains(&runtime) {
+                runtimes.push(runtime);
+            }
+        }
+    }
+    let max = points
+        .iter()
+        .map(|&(_, _, figure)| figure)
+        .fold(0.0, f64::max)
+        .max(1e-9);
+    let x = |run: usize| {
+        MARGIN + run as f64 / runs.saturating_sub(1).max(1) as f64 * (WIDTH - 2.0 * MARGIN)
+    };
+    let y = |figure: f64| HEIGHT - MARGIN - figure / max * (HEIGHT - 2.0 * MARGIN);
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-size="12">"#,
+        WIDTH, HEIGHT
+    );
+    write!(
+        svg,
+        r#"<path d="M{m},{m} V{b} H{r}" fill="none" stroke="black"/><text x="{m}" y="{t}">max {max:.1}</text>"#,
+        m = MARGIN,
+        b = HEIGHT - MARGIN,
+        r = WIDTH - MARGIN,
+        t = MARGIN / 2.0,
+        max = max
+    )
+    .unwrap();
+    for (i, runtime) in runtimes.iter().enumerate() {
+        let colour = COLOURS[i % COLOURS.len()];
+        let line: Vec<_> = points
+            .iter()
+            .filter(|(_, of, _)| of == runtime)
+            .map(|&(run, _, figure)| format!("{:.1},{:.1}", x(run), y(figure)))
+            .collect();
+        write!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/><text x="{}" y="{}" fill="{}">{}</text>"#,
+            line.join(" "),
+            colour,
+            WIDTH - MARGIN - 150.0,
+            MARGIN + 16.0 * i as f64,
+            colour,
+            escape(runtime)
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// The page of `workload`, or of the first workload if it is `None`, with
+/// only the reports of `runtime` listed if it is given.
+pub fn page(history: &History, workload: Option<&str>, runtime: Option<&str>) -> String {
+    let workloads = history.workloads();
+    let workload = workload
+        .map(str::to_owned)
+        .or_else(|| workloads.first().cloned())
+        .unwrap_or_default();
+    let reports = history.reports(&workload);
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>async runtimes</title></head><body>\n\
+         <form method=\"get\"><select name=\"workload\" onchange=\"this.form.submit()\">",
+    );
+    for known in &workloads {
+        let selected = if *known == workload { " selected" } else { "" };
+        write!(html, "<option{}>{}</option>", selected, escape(known)).unwrap();
+    }
+    html.push_str("</select> <select name=\"runtime\" onchange=\"this.form.submit()\"><option value=\"\">every runtime</option>");
+    let mut runtimes: Vec<&str> = reports.iter().map(|&(_, runtime, _, _)| runtime).collect();
+    runtimes.sort_unstable();
+    runtimes.dedup();
+    for known in &runtimes {
+        let selected = if Some(*known) == runtime {
+            " selected"
+        } else {
+            ""
+        };
+        write!(html, "<option{}>{}</option>", selected, escape(known)).unwrap();
+    }
+    html.push_str("</select></form>\n");
+    let shown: Vec<_> = reports
+        .into_iter()
+        .filter(|&(_, of, _, _)| runtime.map_or(true, |runtime| of == runtime))
+        .collect();
+    writeln!(
+        html,
+        "<h1>{}</h1>\n{}",
+        escape(&workload),
+        trend(history.runs.len(), &shown)
+    )
+    .unwrap();
+    html.push_str(
+        "<table><tr><th>run</th><th>file</th><th>system</th><th>runtime</th><th>report</th></tr>\n",
+    );
+    for (run, runtime, system, report) in &shown {
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><pre>{}</pre></td></tr>",
+            run + 1,
+            escape(&history.runs[*run].0),
+            escape(system),
+            escape(runtime),
+            escape(report)
+        )
+        .unwrap();
+    }
+    html.push_str("</table></body></html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_one_workload_over_the_runs() {
+        let runs = results::parse(
+            "# os linux\n# io epoll\nspawn_tokio 1000 ops/s\nspawn_smol 900 ops/s\nsleep_tokio 1.0ms\n\
+             # os linux\n# io epoll\nspawn_tokio 1100 ops/s\n",
+        );
+        let history = History {
+            runs: runs
+                .into_iter()
+                .map(|run| ("a.txt".to_owned(), run))
+                .collect(),
+        };
+        assert_eq!(history.workloads(), ["spawn", "sleep"]);
+        let page = page(&history, None, Some("tokio"));
+        assert!(page.contains("<h1>spawn</h1>"));
+        assert!(page.contains("1100 ops/s"));
+        assert!(!page.contains("900 ops/s"));
+        assert!(page.contains("<option selected>tokio</option>"));
+    }
+}
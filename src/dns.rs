@@ -0,0 +1,177 @@
+//! Name resolution against an in-process mock DNS server.
+//!
+//! hickory-resolver runs on tokio and, through async-std-resolver, on
+//! async_std. The getaddrinfo baseline resolves `localhost` on tokio's
+//! blocking pool instead, since the system resolver cannot be pointed at the
+//! mock server; it measures the cost of handing lookups to threads rather than
+//! of talking to a name server.
+
+use crate::harness::{drive, LoadReport};
+use hickory_proto::op::{Message, MessageType};
+use hickory_proto::rr::rdata::A;
+use hickory_proto::rr::{RData, Record, RecordType};
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// A DNS server on its own thread that answers every A query with `127.0.0.1`.
+///
+/// The server is stopped when the handle is dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Starts the server on a free loopback port.
+    pub fn start() -> Self {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        let addr = socket.local_addr().unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            while !stopped.load(Ordering::Relaxed) {
+                if let Ok((len, peer)) = socket.recv_from(&mut buf) {
+                    if let Some(response) = answer(&buf[..len]) {
+                        let _ = socket.send_to(&response, peer);
+                    }
+                }
+            }
+        });
+        MockServer {
+            addr,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// A resolver configuration that only asks this server, without caching.
+    fn resolver_config(&self) -> (ResolverConfig, ResolverOpts) {
+        let name_servers =
+            NameServerConfigGroup::from_ips_clear(&[self.addr.ip()], self.addr.port(), true);
+        let config = ResolverConfig::from_parts(None, vec![], name_servers);
+        let mut opts = ResolverOpts::default();
+        opts.cache_size = 0;
+        (config, opts)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap();
+        }
+    }
+}
+
+/// Builds the response to an encoded query, or `None` if it cannot be parsed.
+fn answer(query: &[u8]) -> Option<Vec<u8>> {
+    let query = Message::from_vec(query).ok()?;
+    let mut response = Message::new();
+    response
+        .set_id(query.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(query.op_code())
+        .set_recursion_desired(query.recursion_desired())
+        .set_recursion_available(true);
+    for question in query.queries() {
+        response.add_query(question.clone());
+        if question.query_type() == RecordType::A {
+            let localhost = RData::A(A::new(127, 0, 0, 1));
+            response.add_answer(Record::from_rdata(question.name().clone(), 60, localhost));
+        }
+    }
+    response.to_vec().ok()
+}
+
+/// Fully qualified name of the `i`-th host in a batch.
+fn host(i: usize) -> String {
+    format!("host-{}.bench.test.", i)
+}
+
+/// Resolves `names` distinct names, `concurrency` at a time, with hickory-resolver on tokio.
+pub fn tokio(concurrency: usize, names: usize) -> LoadReport {
+    use hickory_resolver::TokioAsyncResolver;
+
+    let server = MockServer::start();
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (config, opts) = server.resolver_config();
+        let resolver = TokioAsyncResolver::tokio(config, opts);
+        let mut next = 0..names;
+        drive(concurrency, names, || {
+            let name = host(next.next().unwrap());
+            let resolver = &resolver;
+            async move {
+                let lookup = resolver.ipv4_lookup(name).await.unwrap();
+                assert_eq!(lookup.iter().count(), 1);
+            }
+        })
+        .await
+    })
+}
+
+/// Resolves `names` distinct names, `concurrency` at a time, with async-std-resolver.
+pub fn async_std(concurrency: usize, names: usize) -> LoadReport {
+    let server = MockServer::start();
+    async_std::task::block_on(async {
+        let (config, opts) = server.resolver_config();
+        let resolver = async_std_resolver::resolver(config, opts).await;
+        let mut next = 0..names;
+        drive(concurrency, names, || {
+            let name = host(next.next().unwrap());
+            let resolver = &resolver;
+            async move {
+                let lookup = resolver.ipv4_lookup(name).await.unwrap();
+                assert_eq!(lookup.iter().count(), 1);
+            }
+        })
+        .await
+    })
+}
+
+/// Resolves `localhost` `lookups` times with getaddrinfo on tokio's blocking pool.
+pub fn getaddrinfo(concurrency: usize, lookups: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(drive(concurrency, lookups, || async {
+        let addrs = tokio::task::spawn_blocking(|| ("localhost", 0).to_socket_addrs())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(addrs.count() > 0);
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokio_resolves_names() {
+        assert_eq!(tokio(4, 10).latencies.len(), 10);
+    }
+
+    #[test]
+    fn async_std_resolves_names() {
+        assert_eq!(async_std(4, 10).latencies.len(), 10);
+    }
+
+    #[test]
+    fn getaddrinfo_resolves_localhost() {
+        assert_eq!(getaddrinfo(2, 4).latencies.len(), 4);
+    }
+}
@@ -0,0 +1,117 @@
+//! Energy the CPU packages used during a scenario, read from the RAPL
+//! counters Linux exposes under `/sys/class/powercap`.
+//!
+//! Every package has a counter of the microjoules it used, `energy_uj` of its
+//! `intel-rapl:<package>` zone, which AMD processors expose under the same
+//! name; its cores, uncore and memory are zones below it and already counted
+//! in it. The counters cover the whole machine, not the process, so anything
+//! else running at the time is counted too. Newer kernels only let root read
+//! them; where they cannot be read, nothing is reported.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Joules used by a scenario, and per operation of its load.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Energy {
+    pub(crate) joules: f64,
+    /// Requests completed, for scenarios reporting them.
+    pub(crate) operations: Option<u64>,
+}
+
+impl fmt::Display for Energy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "energy {:>9.3} J", self.joules)?;
+        if let Some(operations) = self.operations.filter(|&operations| operations > 0) {
+            write!(f, "  {:>10.2} uJ/op", self.joules * 1e6 / operations as f64)?;
+        }
+        Ok(())
+    }
+}
+
+/// A package counter and where it stood when the scenario started.
+struct Zone {
+    energy: PathBuf,
+    /// The counter wraps around to zero past this.
+    range: u64,
+    start: u64,
+}
+
+/// The package counters, counting until stopped.
+pub(crate) struct Rapl {
+    zones: Vec<Zone>,
+}
+
+fn read(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Microjoules counted from `start` to `end` by a counter wrapping at `range`.
+fn counted(start: u64, end: u64, range: u64) -> u64 {
+    if end >= start {
+        end - start
+    } else {
+        range - start + end
+    }
+}
+
+impl Rapl {
+    /// Reads every package counter, or returns `None` if there is none that
+    /// can be read.
+    pub(crate) fn start() -> Option<Self> {
+        let mut zones = Vec::new();
+        for entry in fs::read_dir("/sys/class/powercap").ok()?.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            // Packages are `intel-rapl:0`, their subzones `intel-rapl:0:0`.
+            match name.strip_prefix("intel-rapl:") {
+                Some(package) if !package.contains(':') => {}
+                _ => continue,
+            }
+            let energy = entry.path().join("energy_uj");
+            zones.push(Zone {
+                range: read(&entry.path().join("max_energy_range_uj"))?,
+                start: read(&energy)?,
+                energy,
+            });
+        }
+        if zones.is_empty() {
+            return None;
+        }
+        Some(Rapl { zones })
+    }
+
+    /// Reads the counters again and sums what they counted since starting,
+    /// over `operations` if the scenario reported any.
+    pub(crate) fn stop(self, operations: Option<u64>) -> Option<Energy> {
+        let mut microjoules = 0;
+        for zone in &self.zones {
+            microjoules += counted(zone.start, read(&zone.energy)?, zone.range);
+        }
+        Some(Energy {
+            joules: microjoules as f64 / 1e6,
+            operations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_wrap_around() {
+        assert_eq!(counted(100, 250, 1_000), 150);
+        assert_eq!(counted(900, 50, 1_000), 150);
+    }
+
+    #[test]
+    fn energy_per_operation() {
+        let energy = Energy {
+            joules: 2.0,
+            operations: Some(1_000),
+        };
+        assert_eq!(energy.to_string(), "energy     2.000 J     2000.00 uJ/op");
+    }
+}
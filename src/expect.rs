@@ -0,0 +1,244 @@
+//! Expectations about the reports of scenarios, checked pass or fail after
+//! a benchmark ran, which turn its numbers into claims that can break.
+//!
+//! The file named by `EXPECT` holds one expectation per line, with `#`
+//! starting a comment:
+//!
+//! ```text
+//! spawn_tokio/awaited under 2us/op
+//! http_tokio over 50000 req/s
+//! logs_tokio within 20% of logs_async_std
+//! ```
+//!
+//! A bound, `under` or `over`, is checked against the first figure of the
+//! report in the unit given, a time converted between `s`, `ms`, `us` and
+//! `ns`, with the same `/op` or the like after it, if any. `within` checks
+//! the figure a scenario is judged by, its first rate or else its first
+//! time, against that of another scenario.
+
+use crate::repeat;
+use std::fmt;
+use std::fs;
+
+/// What a scenario's report is expected to show.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Claim {
+    Under(f64, String),
+    Over(f64, String),
+    /// Within a share of the figure of another scenario.
+    Within(f64, String),
+}
+
+/// One expectation about one scenario.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Expectation {
+    pub(crate) scenario: String,
+    pub(crate) claim: Claim,
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.claim {
+            Claim::Under(bound, unit) => write!(f, "{} under {} {}", self.scenario, bound, unit),
+            Claim::Over(bound, unit) => write!(f, "{} over {} {}", self.scenario, bound, unit),
+            Claim::Within(share, other) => {
+                write!(
+                    f,
+                    "{} within {}% of {}",
+                    self.scenario,
+                    share * 100.0,
+                    other
+                )
+            }
+        }
+    }
+}
+
+/// Parses an expectation file, panicking on a line it cannot read so that a
+/// typo does not pass silently.
+pub(crate) fn parse(config: &str) -> Vec<Expectation> {
+    config
+        .lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            expectation(line).unwrap_or_else(|| panic!("cannot read expectation {:?}", line))
+        })
+        .collect()
+}
+
+/// Reads the expectations in the file named by `EXPECT`, if it is set.
+pub(crate) fn load() -> Option<Vec<Expectation>> {
+    let path = std::env::var("EXPECT").ok()?;
+    let config =
+        fs::read_to_string(&path).unwrap_or_else(|err| panic!("cannot read {}: {}", path, err));
+    Some(parse(&config))
+}
+
+fn expectation(line: &str) -> Option<Expectation> {
+    let words: Vec<_> = line.split_whitespace().collect();
+    let claim = match words[1..] {
+        ["under", ref bound @ ..] => {
+            let (figure, unit) = bound_of(bound)?;
+            Claim::Under(figure, unit)
+        }
+        ["over", ref bound @ ..] => {
+            let (figure, unit) = bound_of(bound)?;
+            Claim::Over(figure, unit)
+        }
+        ["within", share, "of", other] => Claim::Within(
+            share.strip_suffix('%')?.parse::<f64>().ok()? / 100.0,
+            other.to_owned(),
+        ),
+        _ => return None,
+    };
+    Some(Expectation {
+        scenario: words[0].to_owned(),
+        claim,
+    })
+}
+
+/// A figure and its unit, written together, like `2us/op`, or apart.
+fn bound_of(words: &[&str]) -> Option<(f64, String)> {
+    match *words {
+        [figure, unit] => Some((figure.parse().ok()?, unit.to_owned())),
+        [joined] => split_figure(joined),
+        _ => None,
+    }
+}
+
+/// Splits a word like `1.500ms` into its figure and unit.
+fn split_figure(word: &str) -> Option<(f64, String)> {
+    let at = word.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))?;
+    Some((word[..at].parse().ok()?, word[at..].to_owned()))
+}
+
+/// Seconds in a time unit.
+fn seconds(unit: &str) -> Option<f64> {
+    match unit {
+        "s" => Some(1.0),
+        "ms" => Some(1e-3),
+        "us" | "µs" => Some(1e-6),
+        "ns" => Some(1e-9),
+        _ => None,
+    }
+}
+
+/// What a figure in `from` is worth in `to`, if they are the same unit or
+/// times per the same thing.
+fn conversion(from: &str, to: &str) -> Option<f64> {
+    if from == to {
+        return Some(1.0);
+    }
+    let (from, from_per) = from.split_once('/').unwrap_or((from, ""));
+    let (to, to_per) = to.split_once('/').unwrap_or((to, ""));
+    if from_per != to_per {
+        return None;
+    }
+    Some(seconds(from)? / seconds(to)?)
+}
+
+/// The first figure of `report` that can be expressed in `unit`, in it.
+fn figure_in(report: &str, unit: &str) -> Option<f64> {
+    let words: Vec<_> = report.split_whitespace().collect();
+    words.iter().enumerate().find_map(|(i, word)| {
+        let (figure, of) = match split_figure(word) {
+            Some((figure, of)) => (figure, of),
+            None => (word.parse().ok()?, (*words.get(i + 1)?).to_owned()),
+        };
+        Some(figure * conversion(&of, unit)?)
+    })
+}
+
+impl Expectation {
+    /// Checks the expectation against the `reports` of the scenarios that
+    /// ran, returning whether it held and what was found, or `None` if a
+    /// scenario it names did not run.
+    pub(crate) fn check(&self, reports: &[(String, String)]) -> Option<(bool, String)> {
+        let report = |name: &str| {
+            reports
+                .iter()
+                .find(|(scenario, _)| scenario == name)
+                .map(|(_, report)| report.as_str())
+        };
+        let found = report(self.scenario.as_str())?;
+        let unreadable = || (false, format!("no figure in {:?}", found.trim()));
+        Some(match &self.claim {
+            Claim::Under(bound, unit) | Claim::Over(bound, unit) => match figure_in(found, unit) {
+                Some(figure) => {
+                    let held = match self.claim {
+                        Claim::Under(..) => figure < *bound,
+                        _ => figure > *bound,
+                    };
+                    (held, format!("{:.3} {}", figure, unit))
+                }
+                None => unreadable(),
+            },
+            Claim::Within(share, other) => {
+                let other = report(other.as_str())?;
+                match repeat::figure(found).zip(repeat::figure(other)) {
+                    Some((figure, theirs)) => {
+                        let off = (figure - theirs).abs() / theirs.abs();
+                        (off <= *share, format!("{:.1}% off", off * 100.0))
+                    }
+                    None => unreadable(),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_bounds_and_comparisons() {
+        let expectations = parse(
+            "# spawning\nspawn_tokio/awaited under 2us/op\n\
+             http_tokio over 50000 req/s  # loopback\nlogs_tokio within 20% of logs_async_std\n",
+        );
+        assert_eq!(
+            expectations,
+            [
+                Expectation {
+                    scenario: "spawn_tokio/awaited".to_owned(),
+                    claim: Claim::Under(2.0, "us/op".to_owned()),
+                },
+                Expectation {
+                    scenario: "http_tokio".to_owned(),
+                    claim: Claim::Over(50000.0, "req/s".to_owned()),
+                },
+                Expectation {
+                    scenario: "logs_tokio".to_owned(),
+                    claim: Claim::Within(0.2, "logs_async_std".to_owned()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_figures_in_the_unit_given() {
+        let reports = vec![
+            (
+                "spawn_tokio/awaited".to_owned(),
+                "  1500.0 ns/op     666667 ops/s".to_owned(),
+            ),
+            (
+                "logs_tokio".to_owned(),
+                "   1000 lines/s  p50    2.000ms".to_owned(),
+            ),
+            (
+                "logs_async_std".to_owned(),
+                "   1300 lines/s  p50    1.000ms".to_owned(),
+            ),
+        ];
+        let check = |line: &str| expectation(line).unwrap().check(&reports);
+        assert!(check("spawn_tokio/awaited under 2us/op").unwrap().0);
+        assert!(!check("spawn_tokio/awaited under 1000 ns/op").unwrap().0);
+        assert!(check("logs_tokio under 3ms").unwrap().0);
+        assert!(!check("logs_tokio within 20% of logs_async_std").unwrap().0);
+        assert!(check("logs_tokio within 25% of logs_async_std").unwrap().0);
+        assert_eq!(check("http_tokio over 1 req/s"), None);
+    }
+}
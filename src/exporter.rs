@@ -0,0 +1,223 @@
+//! A Prometheus endpoint for a scenario running over and over, so that a
+//! dashboard can watch a long soak of each runtime side by side.
+//!
+//! With `METRICS_ADDR` set, a [`Runner`](crate::harness::Runner) in its
+//! profile mode serves `GET /metrics` on that address from a thread of its
+//! own. It exposes the runs of the scenario completed and how long the last
+//! one took, and, for scenarios whose load goes through
+//! [`drive`](crate::harness::drive), the requests completed, the requests in
+//! flight and a histogram of their latencies. Throughput is the rate of the
+//! counters, e.g. `rate(scenario_requests_total[1m])`. Nothing is recorded
+//! unless the endpoint is served.
+
+use std::fmt::{self, Write};
+use std::io::{BufRead, BufReader, Write as _};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Upper bounds of the latency buckets, in seconds.
+const BUCKETS: [f64; 13] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+/// Whether the endpoint is served, and so whether anything is recorded.
+static SERVING: AtomicBool = AtomicBool::new(false);
+
+static METRICS: Mutex<Metrics> = Mutex::new(Metrics::new());
+
+/// What the scenario did since the endpoint started.
+#[derive(Debug)]
+struct Metrics {
+    runs: u64,
+    last_run: Duration,
+    requests: u64,
+    in_flight: u64,
+    /// Requests that took at most each bound of [`BUCKETS`], not cumulative.
+    buckets: [u64; BUCKETS.len()],
+    /// Total latency of the completed requests.
+    latency: Duration,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Metrics {
+            runs: 0,
+            last_run: Duration::ZERO,
+            requests: 0,
+            in_flight: 0,
+            buckets: [0; BUCKETS.len()],
+            latency: Duration::ZERO,
+        }
+    }
+
+    fn finished(&mut self, latency: Duration) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+        self.requests += 1;
+        self.latency += latency;
+        let seconds = latency.as_secs_f64();
+        if let Some(bucket) = BUCKETS.iter().position(|&bound| seconds <= bound) {
+            self.buckets[bucket] += 1;
+        }
+    }
+
+    /// The metrics in Prometheus' text format, labelled with `scenario`.
+    fn render(&self, scenario: &str) -> String {
+        let label = format!(
+            "scenario=\"{}\"",
+            scenario.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+        let mut text = String::new();
+        let runs = "scenario_runs_total";
+        describe(
+            &mut text,
+            runs,
+            "counter",
+            "Runs of the scenario completed.",
+        );
+        sample(&mut text, runs, &label, self.runs);
+        let last_run = "scenario_last_run_seconds";
+        describe(&mut text, last_run, "gauge", "Wall time of the last run.");
+        sample(&mut text, last_run, &label, self.last_run.as_secs_f64());
+        let requests = "scenario_requests_total";
+        describe(&mut text, requests, "counter", "Requests completed.");
+        sample(&mut text, requests, &label, self.requests);
+        let in_flight = "scenario_requests_in_flight";
+        describe(
+            &mut text,
+            in_flight,
+            "gauge",
+            "Requests started but not completed.",
+        );
+        sample(&mut text, in_flight, &label, self.in_flight);
+        let latency = "scenario_request_duration_seconds";
+        describe(&mut text, latency, "histogram", "Latency of the requests.");
+        let bucket = format!("{}_bucket", latency);
+        let mut below = 0;
+        for (bound, count) in BUCKETS.iter().zip(self.buckets) {
+            below += count;
+            sample(
+                &mut text,
+                &bucket,
+                &format!("{},le=\"{}\"", label, bound),
+                below,
+            );
+        }
+        sample(
+            &mut text,
+            &bucket,
+            &format!("{},le=\"+Inf\"", label),
+            self.requests,
+        );
+        let sum = format!("{}_sum", latency);
+        sample(&mut text, &sum, &label, self.latency.as_secs_f64());
+        sample(
+            &mut text,
+            &format!("{}_count", latency),
+            &label,
+            self.requests,
+        );
+        text
+    }
+}
+
+fn describe(text: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(text, "# HELP {} {}", name, help).unwrap();
+    writeln!(text, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn sample(text: &mut String, name: &str, labels: &str, value: impl fmt::Display) {
+    writeln!(text, "{}{{{}}} {}", name, labels, value).unwrap();
+}
+
+fn record(update: impl FnOnce(&mut Metrics)) {
+    if SERVING.load(Ordering::Relaxed) {
+        update(&mut METRICS.lock().unwrap());
+    }
+}
+
+/// Counts a request as in flight.
+pub(crate) fn started() {
+    record(|metrics| metrics.in_flight += 1);
+}
+
+/// Counts a request in flight as completed after `latency`.
+pub(crate) fn finished(latency: Duration) {
+    record(|metrics| metrics.finished(latency));
+}
+
+/// Counts a run of the scenario that took `wall`.
+pub(crate) fn run_finished(wall: Duration) {
+    record(|metrics| {
+        metrics.runs += 1;
+        metrics.last_run = wall;
+    });
+}
+
+/// Serves the metrics of `scenario` on `addr` from a thread of its own.
+pub(crate) fn serve(addr: SocketAddr, scenario: &str) {
+    let listener = TcpListener::bind(addr)
+        .unwrap_or_else(|err| panic!("cannot serve metrics on {}: {}", addr, err));
+    SERVING.store(true, Ordering::Relaxed);
+    let scenario = scenario.to_owned();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A scrape that fails only loses that scrape.
+            let _ = respond(stream, &scenario);
+        }
+    });
+}
+
+fn respond(mut stream: TcpStream, scenario: &str) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Skip the headers, which say nothing the response depends on.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let (status, body) = match request.split_whitespace().nth(1) {
+        Some("/metrics") => ("200 OK", METRICS.lock().unwrap().render(scenario)),
+        _ => ("404 Not Found", String::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_cumulative_buckets() {
+        let mut metrics = Metrics::new();
+        metrics.in_flight = 3;
+        metrics.finished(Duration::from_micros(50));
+        metrics.finished(Duration::from_millis(3));
+        metrics.finished(Duration::from_secs(2));
+        let text = metrics.render("spawn_tokio");
+        assert!(text.contains("scenario_requests_in_flight{scenario=\"spawn_tokio\"} 0\n"));
+        assert!(text.contains("scenario_requests_total{scenario=\"spawn_tokio\"} 3\n"));
+        assert!(text.contains(
+            "scenario_request_duration_seconds_bucket{scenario=\"spawn_tokio\",le=\"0.0001\"} 1\n"
+        ));
+        assert!(text.contains(
+            "scenario_request_duration_seconds_bucket{scenario=\"spawn_tokio\",le=\"0.005\"} 2\n"
+        ));
+        assert!(text.contains(
+            "scenario_request_duration_seconds_bucket{scenario=\"spawn_tokio\",le=\"1\"} 2\n"
+        ));
+        assert!(text.contains(
+            "scenario_request_duration_seconds_bucket{scenario=\"spawn_tokio\",le=\"+Inf\"} 3\n"
+        ));
+    }
+}
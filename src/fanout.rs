@@ -0,0 +1,221 @@
+//! Scatter-gather: every request fans out to `width` backends and completes
+//! when the slowest of them has answered.
+//!
+//! The backends are in-process length-prefixed servers, one listener each,
+//! that wait for a delay drawn from a [`Latency`] distribution before echoing
+//! a call. Each client task keeps a connection to every backend and sends one
+//! call to all of them per request, joining the answers with `join_all`. With
+//! a small share of slow calls the end-to-end tail grows quickly with the
+//! width, since a request is slow as soon as any one of its calls is.
+
+use crate::framing::{read_frame, write_frame};
+use crate::harness::{latency_histogram, record, split, xorshift, LoadReport};
+use futures::future::join_all;
+use futures::io::{AsyncRead, AsyncWrite};
+use hdrhistogram::Histogram;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// How long a backend takes to answer: usually `typical`, but one call in
+/// `slow_one_in` takes `slow` instead, or none if it is 0.
+#[derive(Clone, Copy, Debug)]
+pub struct Latency {
+    pub typical: Duration,
+    pub slow: Duration,
+    pub slow_one_in: u64,
+}
+
+impl Latency {
+    /// Delay of the next call, drawn with the xorshift state `rng`.
+    fn sample(&self, rng: &mut u64) -> Duration {
+        if self.slow_one_in != 0 && xorshift(rng).is_multiple_of(self.slow_one_in) {
+            self.slow
+        } else {
+            self.typical
+        }
+    }
+}
+
+/// Runs `requests` scatter-gather requests to `width` backends from
+/// `concurrency` client tasks, with clients and backends on tokio.
+pub fn tokio(
+    width: usize,
+    latency: Latency,
+    concurrency: usize,
+    requests: usize,
+    payload: usize,
+) -> LoadReport {
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let mut addrs = Vec::with_capacity(width);
+        for _ in 0..width {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            addrs.push(listener.local_addr().unwrap());
+            tokio::spawn(async move {
+                for seed in 1.. {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    stream.set_nodelay(true).unwrap();
+                    let backend = backend(stream.compat(), latency, seed, tokio::time::sleep);
+                    tokio::spawn(backend);
+                }
+            });
+        }
+
+        let start = Instant::now();
+        let clients: Vec<_> = split(requests, concurrency)
+            .map(|share| {
+                let addrs = addrs.clone();
+                tokio::spawn(async move {
+                    let mut backends = Vec::with_capacity(addrs.len());
+                    for addr in addrs {
+                        let stream = TcpStream::connect(addr).await.unwrap();
+                        stream.set_nodelay(true).unwrap();
+                        backends.push(stream.compat());
+                    }
+                    scatter_gather(backends, share, payload).await
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for client in clients {
+            latencies.add(client.await.unwrap()).unwrap();
+        }
+        LoadReport::since(start, requests, latencies)
+    })
+}
+
+/// Runs `requests` scatter-gather requests to `width` backends from
+/// `concurrency` client tasks, with clients and backends on async_std.
+pub fn async_std(
+    width: usize,
+    latency: Latency,
+    concurrency: usize,
+    requests: usize,
+    payload: usize,
+) -> LoadReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+
+    task::block_on(async {
+        let mut addrs = Vec::with_capacity(width);
+        let mut accepts = Vec::with_capacity(width);
+        for _ in 0..width {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            addrs.push(listener.local_addr().unwrap());
+            accepts.push(task::spawn(async move {
+                for seed in 1.. {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    stream.set_nodelay(true).unwrap();
+                    task::spawn(backend(stream, latency, seed, task::sleep));
+                }
+            }));
+        }
+
+        let start = Instant::now();
+        let clients: Vec<_> = split(requests, concurrency)
+            .map(|share| {
+                let addrs = addrs.clone();
+                task::spawn(async move {
+                    let mut backends = Vec::with_capacity(addrs.len());
+                    for addr in addrs {
+                        let stream = TcpStream::connect(addr).await.unwrap();
+                        stream.set_nodelay(true).unwrap();
+                        backends.push(stream);
+                    }
+                    scatter_gather(backends, share, payload).await
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for client in clients {
+            latencies.add(client.await).unwrap();
+        }
+        let report = LoadReport::since(start, requests, latencies);
+        for accept in accepts {
+            accept.cancel().await;
+        }
+        report
+    })
+}
+
+/// Echoes every call after a delay drawn from `latency`, waiting with `sleep`.
+pub(crate) async fn backend<S, F, Fut>(mut stream: S, latency: Latency, seed: u64, sleep: F)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    F: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut rng = seed;
+    let mut buf = Vec::new();
+    while let Ok(true) = read_frame(&mut stream, &mut buf).await {
+        sleep(latency.sample(&mut rng)).await;
+        if write_frame(&mut stream, &buf).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Sends `rounds` requests, each a call of `payload` bytes to every backend at
+/// once, and times how long it takes until all of them have answered.
+async fn scatter_gather<S>(mut backends: Vec<S>, rounds: usize, payload: usize) -> Histogram<u64>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let message = vec![0; payload];
+    let mut bufs = vec![Vec::new(); backends.len()];
+    let mut latencies = latency_histogram();
+    for _ in 0..rounds {
+        let start = Instant::now();
+        let calls = backends.iter_mut().zip(&mut bufs).map(|(stream, buf)| {
+            let message = &message;
+            async move {
+                write_frame(stream, message).await.unwrap();
+                assert!(read_frame(stream, buf).await.unwrap());
+            }
+        });
+        join_all(calls).await;
+        record(&mut latencies, start.elapsed());
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_calls_are_one_in_n() {
+        let latency = Latency {
+            typical: Duration::from_millis(1),
+            slow: Duration::from_millis(10),
+            slow_one_in: 10,
+        };
+        let mut rng = 1;
+        let slow = (0..10_000)
+            .filter(|_| latency.sample(&mut rng) == latency.slow)
+            .count();
+        assert!(slow > 800 && slow < 1_200, "{} slow calls", slow);
+        let never = Latency {
+            slow_one_in: 0,
+            ..latency
+        };
+        assert!((0..100).all(|_| never.sample(&mut rng) == never.typical));
+    }
+
+    #[test]
+    fn requests_wait_for_every_backend() {
+        let latency = Latency {
+            typical: Duration::from_millis(0),
+            slow: Duration::from_millis(5),
+            slow_one_in: 1,
+        };
+        for run in &[tokio, async_std] {
+            let report = run(3, latency, 2, 10, 16);
+            assert_eq!(report.latencies.len(), 10);
+            assert!(report.latencies.min() >= Duration::from_millis(5).as_nanos() as u64);
+        }
+    }
+}
@@ -0,0 +1,550 @@
+//! Faults injected into file writes, connections and channels, on tokio,
+//! async_std and smol, to see what throughput each keeps under them and what
+//! failing costs.
+//!
+//! [`Faulty`] wraps a reader or writer and, on every poll of a read or a
+//! write, fails it with an injected error or holds it back for a random
+//! delay first, each with a probability of its own. The delays run on
+//! futures-timer's thread, so that the timers of the runtime being measured
+//! only serve the scenario itself. Channel messages are dropped, delayed or
+//! answered with an error by the scenario instead, since they have no polls
+//! of their own to wrap.
+//!
+//! - `fs`: tasks write files in chunks; a failed write leaves a partial file,
+//!   which is removed before the next one is started.
+//! - `net`: tasks echo length-prefixed frames with a server on a thread of
+//!   its own; a failed frame leaves the connection in an unknown state, so it
+//!   is dropped and a new one is made for the next frame.
+//! - `channel`: tasks send requests to a responder task and wait for the
+//!   reply up to a timeout; a dropped request costs the whole
+//!   timeout and cancels the receive that waited for it.
+//!
+//! Operations that succeed report their latency. Those that fail report how
+//! long it took for the failure to reach the task, error propagation and
+//! cleanup included, which is what the runtimes differ in most here.
+
+use crate::framing::{read_frame, write_frame};
+use crate::harness::{latency_histogram, loopback_listener, record, xorshift, Millis};
+use crate::rpc::length_prefixed::EchoServer;
+use futures::future::{self, BoxFuture, Either};
+use futures::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use futures::FutureExt;
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Bytes written to a file at a time.
+const CHUNK: usize = 4096;
+
+/// Files every task writes over and over, in turn.
+const FILES_PER_TASK: usize = 4;
+
+/// The faults to inject, and the seed they are drawn with.
+#[derive(Clone, Copy, Debug)]
+pub struct Faults {
+    /// Percentage of polls, or messages, failed with an error.
+    pub error_percent: u32,
+    /// Percentage of polls, or messages, held back for a delay.
+    pub delay_percent: u32,
+    /// Longest a poll or message is held back.
+    pub delay: Duration,
+    /// Percentage of channel messages dropped.
+    pub drop_percent: u32,
+    pub seed: u64,
+}
+
+/// Whether a draw from `rng` falls within `percent`.
+fn roll(rng: &mut u64, percent: u32) -> bool {
+    xorshift(rng) % 100 < u64::from(percent)
+}
+
+/// A random delay of at most `faults.delay`, if one is due.
+fn draw_delay(rng: &mut u64, faults: &Faults) -> Option<Duration> {
+    if !roll(rng, faults.delay_percent) {
+        return None;
+    }
+    let nanos = faults.delay.as_nanos() as u64;
+    Some(Duration::from_nanos(xorshift(rng) % (nanos + 1)))
+}
+
+fn injected() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "injected fault")
+}
+
+/// A reader or writer that fails or delays its polls as `faults` say.
+pub struct Faulty<T> {
+    inner: T,
+    faults: Faults,
+    rng: u64,
+    delay: Option<futures_timer::Delay>,
+}
+
+impl<T> Faulty<T> {
+    /// Wraps `inner`, drawing its faults with `seed` mixed into the seed of
+    /// `faults`.
+    pub fn new(inner: T, faults: Faults, seed: u64) -> Self {
+        Faulty {
+            inner,
+            faults,
+            // xorshift never leaves zero.
+            rng: (faults.seed ^ seed.wrapping_mul(0x9e37_79b9_7f4a_7c15)).max(1),
+            delay: None,
+        }
+    }
+
+    /// Decides whether the poll about to be made fails, waits or goes ahead.
+    fn inject(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(delay) = &mut self.delay {
+            futures::ready!(Pin::new(delay).poll(cx));
+            self.delay = None;
+            return Poll::Ready(Ok(()));
+        }
+        if roll(&mut self.rng, self.faults.error_percent) {
+            return Poll::Ready(Err(injected()));
+        }
+        if let Some(delay) = draw_delay(&mut self.rng, &self.faults) {
+            let mut delay = futures_timer::Delay::new(delay);
+            if Pin::new(&mut delay).poll(cx).is_pending() {
+                self.delay = Some(delay);
+                return Poll::Pending;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Faulty<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        futures::ready!(this.inject(cx))?;
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Faulty<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        futures::ready!(this.inject(cx))?;
+        Pin::new(&mut this.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+/// What faults are injected into.
+#[derive(Clone, Copy, Debug)]
+pub enum Target<'a> {
+    /// File writes, to files in the directory.
+    Fs(&'a Path),
+    Net,
+    Channel,
+}
+
+/// How much every scenario does.
+#[derive(Clone, Copy, Debug)]
+pub struct Load {
+    /// Tasks running operations at the same time.
+    pub tasks: usize,
+    /// Operations every task runs, one after another.
+    pub operations: usize,
+    /// Bytes of every file, frame or message.
+    pub size: usize,
+    /// Longest a request over a channel waits for its reply.
+    pub timeout: Duration,
+}
+
+/// Throughput under faults, and the latencies of the operations that
+/// succeeded and of the failures.
+pub struct FaultReport {
+    pub elapsed: Duration,
+    pub latencies: Histogram<u64>,
+    /// Time from the start of every failed operation until its task saw it
+    /// fail.
+    pub failures: Histogram<u64>,
+}
+
+impl fmt::Display for FaultReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (ok, failed) = (self.latencies.len(), self.failures.len());
+        write!(
+            f,
+            "{:>10.0} ok/s  {:>5.1}% failed  p50 {}  p99 {}  failure p50 {}  p99 {}",
+            ok as f64 / self.elapsed.as_secs_f64(),
+            failed as f64 * 100.0 / (ok + failed).max(1) as f64,
+            Millis(self.latencies.value_at_quantile(0.5)),
+            Millis(self.latencies.value_at_quantile(0.99)),
+            Millis(self.failures.value_at_quantile(0.5)),
+            Millis(self.failures.value_at_quantile(0.99))
+        )
+    }
+}
+
+trait Stream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Stream for T {}
+
+type Outcomes = Vec<(bool, Duration)>;
+
+/// A request over a channel and where its reply goes.
+type Request = (Vec<u8>, async_channel::Sender<Result<Vec<u8>, ()>>);
+
+/// What the scenarios need of a runtime.
+#[derive(Clone, Copy)]
+struct Runtime {
+    /// Spawns a task and waits for what it returns.
+    spawn: fn(BoxFuture<'static, Outcomes>) -> BoxFuture<'static, Outcomes>,
+    sleep: fn(Duration) -> BoxFuture<'static, ()>,
+    create: fn(PathBuf) -> BoxFuture<'static, io::Result<Box<dyn AsyncWrite + Send + Unpin>>>,
+    remove: fn(PathBuf) -> BoxFuture<'static, ()>,
+    connect: fn(SocketAddr) -> BoxFuture<'static, io::Result<Box<dyn Stream>>>,
+}
+
+/// Writes a file of `size` bytes through a faulty writer.
+async fn write_file(
+    runtime: &Runtime,
+    path: PathBuf,
+    size: usize,
+    faults: Faults,
+    seed: u64,
+) -> bool {
+    let written = async {
+        let mut file = Faulty::new((runtime.create)(path.clone()).await?, faults, seed);
+        let data = vec![1; size];
+        for chunk in data.chunks(CHUNK) {
+            file.write_all(chunk).await?;
+        }
+        file.close().await
+    }
+    .await;
+    if written.is_err() {
+        (runtime.remove)(path).await;
+    }
+    written.is_ok()
+}
+
+/// Echoes one frame of `size` bytes over `connection`, connecting first if
+/// there is none, and drops the connection if the frame failed.
+async fn echo(
+    runtime: &Runtime,
+    connection: &mut Option<Faulty<Box<dyn Stream>>>,
+    addr: SocketAddr,
+    size: usize,
+    faults: Faults,
+    seed: u64,
+) -> bool {
+    if connection.is_none() {
+        match (runtime.connect)(addr).await {
+            Ok(stream) => *connection = Some(Faulty::new(stream, faults, seed)),
+            Err(_) => return false,
+        }
+    }
+    let stream = connection.as_mut().unwrap();
+    let mut buf = Vec::new();
+    let echoed = async {
+        write_frame(stream, &vec![0; size]).await?;
+        read_frame(stream, &mut buf).await
+    }
+    .await;
+    let ok = matches!(echoed, Ok(true)) && buf.len() == size;
+    if !ok {
+        *connection = None;
+    }
+    ok
+}
+
+/// Sends a request of `size` bytes to the responder and waits for its reply
+/// up to `timeout`.
+async fn request(
+    runtime: &Runtime,
+    requests: &async_channel::Sender<Request>,
+    size: usize,
+    timeout: Duration,
+) -> bool {
+    let (reply, replies) = async_channel::bounded(1);
+    if requests.send((vec![0; size], reply)).await.is_err() {
+        return false;
+    }
+    let received = replies.recv();
+    let expired = (runtime.sleep)(timeout);
+    match future::select(Box::pin(received), expired).await {
+        Either::Left((Ok(Ok(message)), _)) => message.len() == size,
+        _ => false,
+    }
+}
+
+/// Answers requests, dropping, delaying or failing their replies as
+/// `faults` say, until every sender is gone. The reply channels of dropped
+/// requests are kept open, the way a lost message leaves its sender waiting.
+async fn respond(runtime: Runtime, requests: async_channel::Receiver<Request>, faults: Faults) {
+    let mut rng = faults.seed.max(1);
+    let mut lost = Vec::new();
+    while let Ok((message, reply)) = requests.recv().await {
+        if roll(&mut rng, faults.drop_percent) {
+            lost.push(reply);
+            continue;
+        }
+        if let Some(delay) = draw_delay(&mut rng, &faults) {
+            (runtime.sleep)(delay).await;
+        }
+        let answer = if roll(&mut rng, faults.error_percent) {
+            Err(())
+        } else {
+            Ok(message)
+        };
+        let _ = reply.send(answer).await;
+    }
+}
+
+async fn run(target: Target<'_>, faults: Faults, load: Load, runtime: Runtime) -> FaultReport {
+    let (server, addr) = match target {
+        Target::Net => {
+            let listener = loopback_listener();
+            let addr = listener.local_addr().unwrap();
+            (Some(EchoServer::start(listener)), Some(addr))
+        }
+        _ => (None, None),
+    };
+    let (requests, incoming) = async_channel::unbounded::<Request>();
+    let responder = match target {
+        Target::Channel => Some((runtime.spawn)(
+            async move {
+                respond(runtime, incoming, faults).await;
+                Vec::new()
+            }
+            .boxed(),
+        )),
+        _ => None,
+    };
+    let start = Instant::now();
+    let tasks: Vec<_> = (0..load.tasks)
+        .map(|task| {
+            let dir = match target {
+                Target::Fs(dir) => Some(dir.to_path_buf()),
+                _ => None,
+            };
+            let requests = requests.clone();
+            (runtime.spawn)(
+                async move {
+                    let mut outcomes = Vec::with_capacity(load.operations);
+                    let mut connection = None;
+                    for operation in 0..load.operations {
+                        let seed = (task * load.operations + operation) as u64;
+                        let started = Instant::now();
+                        let ok = match (&dir, addr) {
+                            (Some(dir), _) => {
+                                let file = operation % FILES_PER_TASK;
+                                let path = dir.join(format!("faults-{}-{}", task, file));
+                                write_file(&runtime, path, load.size, faults, seed).await
+                            }
+                            (None, Some(addr)) => {
+                                echo(&runtime, &mut connection, addr, load.size, faults, seed).await
+                            }
+                            (None, None) => {
+                                request(&runtime, &requests, load.size, load.timeout).await
+                            }
+                        };
+                        outcomes.push((ok, started.elapsed()));
+                    }
+                    outcomes
+                }
+                .boxed(),
+            )
+        })
+        .collect();
+    drop(requests);
+    let mut latencies = latency_histogram();
+    let mut failures = latency_histogram();
+    for outcomes in future::join_all(tasks).await {
+        for (ok, elapsed) in outcomes {
+            record(if ok { &mut latencies } else { &mut failures }, elapsed);
+        }
+    }
+    let elapsed = start.elapsed();
+    if let Some(responder) = responder {
+        responder.await;
+    }
+    drop(server);
+    FaultReport {
+        elapsed,
+        latencies,
+        failures,
+    }
+}
+
+/// Runs the operations of `load` on `target` with `faults` injected, on tokio.
+pub fn tokio(target: Target<'_>, faults: Faults, load: Load) -> FaultReport {
+    use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(run(
+        target,
+        faults,
+        load,
+        Runtime {
+            spawn: |task| tokio::spawn(task).map(Result::unwrap).boxed(),
+            sleep: |duration| tokio::time::sleep(duration).boxed(),
+            create: |path| {
+                async move {
+                    let file = tokio::fs::File::create(path).await?;
+                    Ok(Box::new(file.compat_write()) as Box<dyn AsyncWrite + Send + Unpin>)
+                }
+                .boxed()
+            },
+            remove: |path| {
+                async move {
+                    let _ = tokio::fs::remove_file(path).await;
+                }
+                .boxed()
+            },
+            connect: |addr| {
+                async move {
+                    let stream = tokio::net::TcpStream::connect(addr).await?;
+                    stream.set_nodelay(true)?;
+                    Ok(Box::new(stream.compat()) as Box<dyn Stream>)
+                }
+                .boxed()
+            },
+        },
+    ))
+}
+
+/// Runs the operations of `load` on `target` with `faults` injected, on
+/// async_std.
+pub fn async_std(target: Target<'_>, faults: Faults, load: Load) -> FaultReport {
+    async_std::task::block_on(run(
+        target,
+        faults,
+        load,
+        Runtime {
+            spawn: |task| async_std::task::spawn(task).boxed(),
+            sleep: |duration| async_std::task::sleep(duration).boxed(),
+            create: |path| {
+                async move {
+                    let file = async_std::fs::File::create(path).await?;
+                    Ok(Box::new(file) as Box<dyn AsyncWrite + Send + Unpin>)
+                }
+                .boxed()
+            },
+            remove: |path| {
+                async move {
+                    let _ = async_std::fs::remove_file(path).await;
+                }
+                .boxed()
+            },
+            connect: |addr| {
+                async move {
+                    let stream = async_std::net::TcpStream::connect(addr).await?;
+                    stream.set_nodelay(true)?;
+                    Ok(Box::new(stream) as Box<dyn Stream>)
+                }
+                .boxed()
+            },
+        },
+    ))
+}
+
+/// Runs the operations of `load` on `target` with `faults` injected, on smol.
+pub fn smol(target: Target<'_>, faults: Faults, load: Load) -> FaultReport {
+    smol::block_on(run(
+        target,
+        faults,
+        load,
+        Runtime {
+            spawn: |task| smol::spawn(task).boxed(),
+            sleep: |duration| {
+                async move {
+                    smol::Timer::after(duration).await;
+                }
+                .boxed()
+            },
+            create: |path| {
+                async move {
+                    let file = smol::fs::File::create(path).await?;
+                    Ok(Box::new(file) as Box<dyn AsyncWrite + Send + Unpin>)
+                }
+                .boxed()
+            },
+            remove: |path| {
+                async move {
+                    let _ = smol::fs::remove_file(path).await;
+                }
+                .boxed()
+            },
+            connect: |addr| {
+                async move {
+                    let stream = smol::net::TcpStream::connect(addr).await?;
+                    stream.set_nodelay(true)?;
+                    Ok(Box::new(stream) as Box<dyn Stream>)
+                }
+                .boxed()
+            },
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOAD: Load = Load {
+        tasks: 4,
+        operations: 50,
+        size: 10_000,
+        timeout: Duration::from_millis(20),
+    };
+
+    fn faults(percent: u32) -> Faults {
+        Faults {
+            error_percent: percent,
+            delay_percent: percent,
+            delay: Duration::from_micros(100),
+            drop_percent: percent,
+            seed: 1,
+        }
+    }
+
+    #[test]
+    fn nothing_fails_without_faults() {
+        let dir = tempfile::tempdir().unwrap();
+        for target in [Target::Fs(dir.path()), Target::Net, Target::Channel] {
+            for report in [
+                tokio(target, faults(0), LOAD),
+                async_std(target, faults(0), LOAD),
+                smol(target, faults(0), LOAD),
+            ] {
+                assert_eq!(report.latencies.len(), 200);
+                assert_eq!(report.failures.len(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn faults_fail_some_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        for target in [Target::Fs(dir.path()), Target::Net, Target::Channel] {
+            let report = tokio(target, faults(10), LOAD);
+            assert_eq!(report.latencies.len() + report.failures.len(), 200);
+            assert!(!report.failures.is_empty());
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! What a runtime costs before it runs anything: the size of a small program
+//! built on it, and how long the program takes to build.
+//!
+//! Every variant is the same program, spawning a task that prints a line and
+//! waiting for it, written for one runtime with one set of features, plus a
+//! baseline on std alone. Each is generated as a cargo project of its own in
+//! a temporary directory and built in release mode with the same profile,
+//! stripped of symbols, into a target directory shared by all of them, so
+//! that dependencies common to several variants are only compiled once.
+//! Building fetches the runtimes from the registry unless cargo has them
+//! already.
+//!
+//! Build times are those of the developer loop instead: a debug build from
+//! scratch, in a target directory of the variant's own, dependencies and
+//! all, and then a debug build again after a change to the program, which
+//! only recompiles the program, incrementally. The dependencies are fetched
+//! before the clock starts.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// One runtime, with one set of features, and the program written for it.
+#[derive(Clone, Copy, Debug)]
+pub struct Variant {
+    pub name: &'static str,
+    /// The `[dependencies]` of the program.
+    dependencies: &'static str,
+    main: &'static str,
+}
+
+/// The variants, baseline first.
+pub const VARIANTS: [Variant; 5] = [
+    Variant {
+        name: "std",
+        dependencies: "",
+        main: r#"fn main() {
+    std::thread::spawn(|| println!("hello")).join().unwrap();
+}
+"#,
+    },
+    Variant {
+        name: "tokio_full",
+        dependencies: r#"tokio = { version = "1", features = ["full"] }"#,
+        main: r#"#[tokio::main]
+async fn main() {
+    tokio::spawn(async { println!("hello") }).await.unwrap();
+}
+"#,
+    },
+    Variant {
+        name: "tokio_minimal",
+        dependencies: r#"tokio = { version = "1", features = ["rt"] }"#,
+        main: r#"fn main() {
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    rt.block_on(async { tokio::spawn(async { println!("hello") }).await.unwrap() });
+}
+"#,
+    },
+    Variant {
+        name: "async_std",
+        dependencies: r#"async-std = "1""#,
+        main: r#"fn main() {
+    async_std::task::block_on(async_std::task::spawn(async { println!("hello") }));
+}
+"#,
+    },
+    Variant {
+        name: "smol",
+        dependencies: r#"smol = "2""#,
+        main: r#"fn main() {
+    smol::block_on(smol::spawn(async { println!("hello") }));
+}
+"#,
+    },
+];
+
+/// The profile every variant is built with.
+const PROFILE: &str = r#"[profile.release]
+opt-level = 3
+strip = true
+"#;
+
+/// A variant generated as a cargo project, removed when dropped.
+pub struct Project {
+    variant: Variant,
+    dir: PathBuf,
+    target: PathBuf,
+}
+
+impl Project {
+    /// Generates `variant` in a temporary directory, to build in the target
+    /// directory shared by all variants.
+    pub fn generate(variant: Variant) -> io::Result<Self> {
+        let target = env::temp_dir().join("async-runtimes-cmp-footprint-target");
+        Self::generate_in(variant, |_| target)
+    }
+
+    /// Generates `variant` in a temporary directory, to build in a target
+    /// directory of its own, removed with the project.
+    pub fn isolated(variant: Variant) -> io::Result<Self> {
+        Self::generate_in(variant, |dir| dir.join("target"))
+    }
+
+    fn generate_in(variant: Variant, target: impl FnOnce(&Path) -> PathBuf) -> io::Result<Self> {
+        // Projects generated at the same time each need a directory.
+        static GENERATED: AtomicUsize = AtomicUsize::new(0);
+        let dir = env::temp_dir().join(format!(
+            "async-runtimes-cmp-footprint-{}-{}-{}",
+            variant.name,
+            std::process::id(),
+            GENERATED.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(dir.join("src"))?;
+        fs::write(
+            dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                 [dependencies]\n{}\n\n{}\n[workspace]\n",
+                package(variant.name),
+                variant.dependencies,
+                PROFILE
+            ),
+        )?;
+        fs::write(dir.join("src/main.rs"), variant.main)?;
+        Ok(Project {
+            variant,
+            target: target(&dir),
+            dir,
+        })
+    }
+
+    /// Builds the program, in release mode or in debug mode.
+    pub fn build(&self, release: bool) -> io::Result<()> {
+        let mut command = self.cargo("build");
+        if release {
+            command.arg("--release");
+        }
+        self.succeed(command.arg("--target-dir").arg(&self.target))
+    }
+
+    /// Downloads the dependencies of the program, unless cargo has them.
+    pub fn fetch(&self) -> io::Result<()> {
+        self.succeed(&mut self.cargo("fetch"))
+    }
+
+    /// Changes the line the program prints, so that it has to be compiled
+    /// again.
+    pub fn edit(&self) -> io::Result<()> {
+        let edited = self.variant.main.replace("hello", "hello again");
+        fs::write(self.dir.join("src/main.rs"), edited)
+    }
+
+    fn cargo(&self, subcommand: &str) -> Command {
+        let mut command = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()));
+        command
+            .args([subcommand, "--quiet", "--manifest-path"])
+            .arg(self.dir.join("Cargo.toml"));
+        command
+    }
+
+    fn succeed(&self, command: &mut Command) -> io::Result<()> {
+        let status = command.status()?;
+        if !status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("cargo failed on {}: {}", self.variant.name, status),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The program as last built in release mode.
+    pub fn binary(&self) -> PathBuf {
+        let name = package(self.variant.name) + env::consts::EXE_SUFFIX;
+        self.target.join("release").join(name)
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// The package, and binary, name of a variant.
+fn package(variant: &str) -> String {
+    format!("footprint-{}", variant.replace('_', "-"))
+}
+
+/// Size of a built program.
+pub struct SizeReport {
+    pub bytes: u64,
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>10.1} KiB stripped", self.bytes as f64 / 1024.0)
+    }
+}
+
+/// Builds `variant` and measures the size of the stripped program.
+pub fn binary_size(variant: Variant) -> io::Result<SizeReport> {
+    let project = Project::generate(variant)?;
+    project.build(true)?;
+    Ok(SizeReport {
+        bytes: fs::metadata(project.binary())?.len(),
+    })
+}
+
+/// How long a program took to build from scratch and again after a change.
+pub struct BuildReport {
+    pub clean: Duration,
+    pub incremental: Duration,
+}
+
+impl fmt::Display for BuildReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "clean {:>8.2}s  incremental {:>8.2}s",
+            self.clean.as_secs_f64(),
+            self.incremental.as_secs_f64()
+        )
+    }
+}
+
+/// Builds `variant` in debug mode from scratch, then changes it and builds
+/// it again.
+pub fn build_times(variant: Variant) -> io::Result<BuildReport> {
+    let project = Project::isolated(variant)?;
+    project.fetch()?;
+    let started = Instant::now();
+    project.build(false)?;
+    let clean = started.elapsed();
+    project.edit()?;
+    let started = Instant::now();
+    project.build(false)?;
+    Ok(BuildReport {
+        clean,
+        incremental: started.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_a_project_per_variant() {
+        for variant in VARIANTS.iter().copied() {
+            let project = Project::generate(variant).unwrap();
+            let manifest = fs::read_to_string(project.dir.join("Cargo.toml")).unwrap();
+            assert!(manifest.contains(variant.dependencies));
+            assert!(manifest.contains("strip = true"));
+            assert!(project
+                .binary()
+                .ends_with(package(variant.name) + env::consts::EXE_SUFFIX));
+        }
+    }
+
+    #[test]
+    fn edits_what_the_program_prints() {
+        let project = Project::isolated(VARIANTS[0]).unwrap();
+        project.edit().unwrap();
+        let main = fs::read_to_string(project.dir.join("src/main.rs")).unwrap();
+        assert!(main.contains("hello again"));
+        assert!(project.target.starts_with(&project.dir));
+    }
+}
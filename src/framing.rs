@@ -0,0 +1,76 @@
+//! Hand-rolled length-prefixed framing: a big-endian `u32` length followed by the payload.
+
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use std::io;
+
+/// Writes `payload` as a single frame and flushes the writer.
+pub async fn write_frame<W>(writer: &mut W, payload: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+/// Writes `payload` as a single frame without flushing, for buffered writers
+/// that batch many frames into one write.
+pub async fn feed_frame<W>(writer: &mut W, payload: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer
+        .write_all(&(payload.len() as u32).to_be_bytes())
+        .await?;
+    writer.write_all(payload).await
+}
+
+/// Reads the next frame into `buf`, replacing its contents.
+///
+/// Returns `Ok(false)` if the stream ended cleanly before a new frame started.
+pub async fn read_frame<R>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<bool>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len).await {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+        Err(err) => return Err(err),
+    }
+    buf.resize(u32::from_be_bytes(len) as usize, 0);
+    reader.read_exact(buf).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+
+    #[test]
+    fn frames_round_trip() {
+        block_on(async {
+            let mut wire = Cursor::new(Vec::new());
+            write_frame(&mut wire, b"hello").await.unwrap();
+            write_frame(&mut wire, b"").await.unwrap();
+            feed_frame(&mut wire, b"fed").await.unwrap();
+            feed_frame(&mut wire, b"").await.unwrap();
+            wire.set_position(0);
+
+            let mut buf = Vec::new();
+            assert!(read_frame(&mut wire, &mut buf).await.unwrap());
+            assert_eq!(buf, b"hello");
+            assert!(read_frame(&mut wire, &mut buf).await.unwrap());
+            assert!(buf.is_empty());
+            assert!(read_frame(&mut wire, &mut buf).await.unwrap());
+            assert_eq!(buf, b"fed");
+            assert!(read_frame(&mut wire, &mut buf).await.unwrap());
+            assert!(buf.is_empty());
+            assert!(!read_frame(&mut wire, &mut buf).await.unwrap());
+        });
+    }
+}
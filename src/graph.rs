@@ -0,0 +1,315 @@
+//! Random graphs of tasks, generated from a seed, run the same on every
+//! runtime, so that comparisons are not limited to the shapes of the
+//! hand-written scenarios.
+//!
+//! A graph is a DAG of tasks. Every task waits for the messages of the edges
+//! into it, then either sleeps or writes a file of its own and reads it back,
+//! then sends the messages of the edges out of it. Edges are unbounded
+//! async-channel channels, which do not depend on a runtime, and only ever
+//! lead from a task to later ones, which keeps the graph acyclic. The same
+//! seed and shape give the same graph, so a schedule that one runtime copes
+//! badly with can be run again on its own, and sweeping seeds looks for
+//! such schedules.
+//!
+//! The makespan of a run is set against its critical path, the longest
+//! chain of sleeps through the graph, which no scheduler can beat; IO and
+//! messages are left out of it, so it is a lower bound.
+
+use crate::harness::{xorshift, Millis};
+use futures::future::{self, BoxFuture};
+use futures::FutureExt;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// The parameters graphs are drawn with.
+#[derive(Clone, Copy, Debug)]
+pub struct Shape {
+    /// Tasks in the graph.
+    pub tasks: usize,
+    /// Most edges into a task, each from a random earlier task.
+    pub fan_in: usize,
+    /// Longest a task sleeps for.
+    pub sleep: Duration,
+    /// Most bytes a task writes and reads back.
+    pub io: usize,
+    /// Most messages sent over an edge.
+    pub messages: usize,
+}
+
+/// What a task does once the messages into it arrived.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Work {
+    Sleep(Duration),
+    /// Writes that many bytes to a file and reads them back.
+    Io(usize),
+}
+
+/// A task of a graph and the edges into it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Task {
+    pub work: Work,
+    /// The earlier tasks the edges come from, with the messages sent over
+    /// each.
+    pub inputs: Vec<(usize, usize)>,
+}
+
+/// A DAG of tasks, every edge leading to a later task.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Graph {
+    pub tasks: Vec<Task>,
+}
+
+impl Graph {
+    /// Draws a graph of `shape` with the seed `seed`.
+    pub fn generate(seed: u64, shape: Shape) -> Self {
+        // xorshift never leaves zero.
+        let mut rng = seed.max(1);
+        let mut draw = |below: u64| xorshift(&mut rng) % below.max(1);
+        let tasks = (0..shape.tasks)
+            .map(|i| {
+                let work = if draw(2) == 0 {
+                    Work::Sleep(Duration::from_nanos(draw(
+                        shape.sleep.as_nanos() as u64 + 1,
+                    )))
+                } else {
+                    Work::Io(draw(shape.io as u64 + 1) as usize)
+                };
+                let mut inputs: Vec<(usize, usize)> = Vec::new();
+                for _ in 0..draw(shape.fan_in.min(i) as u64 + 1) {
+                    let from = draw(i as u64) as usize;
+                    if inputs.iter().all(|&(earlier, _)| earlier != from) {
+                        inputs.push((from, 1 + draw(shape.messages as u64) as usize));
+                    }
+                }
+                Task { work, inputs }
+            })
+            .collect();
+        Graph { tasks }
+    }
+
+    /// Edges in the graph.
+    pub fn edges(&self) -> usize {
+        self.tasks.iter().map(|task| task.inputs.len()).sum()
+    }
+
+    /// The longest chain of sleeps through the graph.
+    pub fn critical_path(&self) -> Duration {
+        let mut finish: Vec<Duration> = Vec::with_capacity(self.tasks.len());
+        for task in &self.tasks {
+            let ready = task.inputs.iter().map(|&(from, _)| finish[from]).max();
+            let work = match task.work {
+                Work::Sleep(duration) => duration,
+                Work::Io(_) => Duration::ZERO,
+            };
+            finish.push(ready.unwrap_or_default() + work);
+        }
+        finish.into_iter().max().unwrap_or_default()
+    }
+
+    /// The tasks of the graph, sleeping with `sleep` and doing IO with `io`
+    /// on files in `dir`.
+    fn build(
+        &self,
+        dir: &Path,
+        sleep: fn(Duration) -> BoxFuture<'static, ()>,
+        io: fn(PathBuf, usize) -> BoxFuture<'static, ()>,
+    ) -> Vec<BoxFuture<'static, ()>> {
+        let mut outputs: Vec<Vec<(async_channel::Sender<()>, usize)>> =
+            vec![Vec::new(); self.tasks.len()];
+        let mut inputs = Vec::new();
+        for (to, task) in self.tasks.iter().enumerate() {
+            let mut receivers = Vec::new();
+            for &(from, messages) in &task.inputs {
+                let (sender, receiver) = async_channel::unbounded();
+                outputs[from].push((sender, messages));
+                receivers.push(receiver);
+            }
+            inputs.push((to, task.work, receivers));
+        }
+        inputs
+            .into_iter()
+            .zip(outputs)
+            .map(|((i, work, receivers), senders)| {
+                let path = dir.join(format!("task-{}", i));
+                async move {
+                    for receiver in receivers {
+                        while receiver.recv().await.is_ok() {}
+                    }
+                    match work {
+                        Work::Sleep(duration) => sleep(duration).await,
+                        Work::Io(bytes) => io(path, bytes).await,
+                    }
+                    for (sender, messages) in senders {
+                        for _ in 0..messages {
+                            sender.send(()).await.unwrap();
+                        }
+                    }
+                }
+                .boxed()
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sleeps = self
+            .tasks
+            .iter()
+            .filter(|task| matches!(task.work, Work::Sleep(_)))
+            .count();
+        write!(
+            f,
+            "{} tasks, {} sleeping, {} edges, critical path {}",
+            self.tasks.len(),
+            sleeps,
+            self.edges(),
+            Millis(self.critical_path().as_nanos() as u64)
+        )
+    }
+}
+
+/// How long the runs of a graph took, against its critical path.
+pub struct GraphReport {
+    pub tasks: usize,
+    pub runs: usize,
+    pub elapsed: Duration,
+    pub critical_path: Duration,
+}
+
+impl fmt::Display for GraphReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let makespan = self.elapsed / self.runs as u32;
+        write!(
+            f,
+            "{:>10.0} tasks/s  makespan {}  critical path {}  {:>5.2}x",
+            (self.tasks * self.runs) as f64 / self.elapsed.as_secs_f64(),
+            Millis(makespan.as_nanos() as u64),
+            Millis(self.critical_path.as_nanos() as u64),
+            makespan.as_secs_f64() / self.critical_path.as_secs_f64()
+        )
+    }
+}
+
+/// Runs `graph` `runs` times, one run after the other, on a tokio runtime.
+pub fn tokio(graph: &Graph, dir: &Path, runs: usize) -> GraphReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let start = Instant::now();
+        for _ in 0..runs {
+            let tasks = graph.build(
+                dir,
+                |duration| tokio::time::sleep(duration).boxed(),
+                |path, bytes| {
+                    async move {
+                        tokio::fs::write(&path, vec![1; bytes]).await.unwrap();
+                        tokio::fs::read(&path).await.unwrap();
+                    }
+                    .boxed()
+                },
+            );
+            let handles: Vec<_> = tasks.into_iter().map(tokio::spawn).collect();
+            for handle in handles {
+                handle.await.unwrap();
+            }
+        }
+        report(graph, runs, start)
+    })
+}
+
+/// Runs `graph` `runs` times, one run after the other, on async_std.
+pub fn async_std(graph: &Graph, dir: &Path, runs: usize) -> GraphReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let start = Instant::now();
+        for _ in 0..runs {
+            let tasks = graph.build(
+                dir,
+                |duration| task::sleep(duration).boxed(),
+                |path, bytes| {
+                    async move {
+                        async_std::fs::write(&path, vec![1; bytes]).await.unwrap();
+                        async_std::fs::read(&path).await.unwrap();
+                    }
+                    .boxed()
+                },
+            );
+            future::join_all(tasks.into_iter().map(task::spawn)).await;
+        }
+        report(graph, runs, start)
+    })
+}
+
+/// Runs `graph` `runs` times, one run after the other, on smol.
+pub fn smol(graph: &Graph, dir: &Path, runs: usize) -> GraphReport {
+    smol::block_on(async {
+        let start = Instant::now();
+        for _ in 0..runs {
+            let tasks = graph.build(
+                dir,
+                |duration| {
+                    async move {
+                        smol::Timer::after(duration).await;
+                    }
+                    .boxed()
+                },
+                |path, bytes| {
+                    async move {
+                        smol::fs::write(&path, vec![1; bytes]).await.unwrap();
+                        smol::fs::read(&path).await.unwrap();
+                    }
+                    .boxed()
+                },
+            );
+            future::join_all(tasks.into_iter().map(smol::spawn)).await;
+        }
+        report(graph, runs, start)
+    })
+}
+
+fn report(graph: &Graph, runs: usize, start: Instant) -> GraphReport {
+    GraphReport {
+        tasks: graph.tasks.len(),
+        runs,
+        elapsed: start.elapsed(),
+        critical_path: graph.critical_path(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPE: Shape = Shape {
+        tasks: 50,
+        fan_in: 3,
+        sleep: Duration::from_millis(2),
+        io: 4096,
+        messages: 10,
+    };
+
+    #[test]
+    fn the_same_seed_draws_the_same_graph() {
+        let graph = Graph::generate(7, SHAPE);
+        assert_eq!(graph, Graph::generate(7, SHAPE));
+        assert_ne!(graph, Graph::generate(8, SHAPE));
+        for (to, task) in graph.tasks.iter().enumerate() {
+            assert!(task.inputs.iter().all(|&(from, _)| from < to));
+        }
+    }
+
+    #[test]
+    fn every_runtime_finishes_the_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let graph = Graph::generate(1, SHAPE);
+        for report in [
+            tokio(&graph, dir.path(), 2),
+            async_std(&graph, dir.path(), 2),
+            smol(&graph, dir.path(), 2),
+        ] {
+            assert!(report.elapsed >= report.critical_path * 2);
+        }
+    }
+}
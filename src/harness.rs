@@ -0,0 +1,1151 @@
+//! Minimal runner for scenarios that report more than `ns/iter`.
+//!
+//! `bencher` only reports the mean time per iteration, which is not enough for
+//! load-style scenarios where throughput and latency percentiles matter. Bench
+//! targets for such scenarios use `harness = false` and drive a [`Runner`]
+//! from their `main` instead.
+//!
+//! With `WORKER_THREADS` set to a comma-separated list of thread counts, the
+//! runner reruns the whole benchmark once per count in a child process, with
+//! tokio's, async_std's and smol's worker threads set through
+//! `TOKIO_WORKER_THREADS`, `ASYNC_STD_THREAD_COUNT` and `SMOL_THREADS`, and
+//! prints every scenario's reports as a curve over the counts instead. A
+//! process of its own per count is what lets async_std take part: its global
+//! runtime reads the variable once, when it first starts. Runtimes built with
+//! an explicit number of workers keep it. The curves are followed by how
+//! well every scenario reporting a throughput scales, its throughput at every
+//! count against perfect scaling from the fewest threads, which is plotted to
+//! the SVG file named by `SCALING_PLOT` if it is set.
+//!
+//! With `CONTENTION` set to a percentage of the cores, the runner keeps that
+//! share of them busy with spinning threads of its own while the scenarios
+//! run, the way other work on a shared machine would. Set to a
+//! comma-separated list, it reruns the benchmark once per percentage and
+//! prints the curves the same way, so that how each runtime's latency
+//! degrades as the machine gets busier shows next to its idle numbers.
+//!
+//! With `NUMA` set on a machine with more than one NUMA node, the runner
+//! reruns the benchmark twice with the process pinned to the same number of
+//! cores, all on one node and then spread over every node, and prints both
+//! reports of every scenario as a curve over the nodes used, so that the
+//! penalty each runtime pays for crossing between nodes shows.
+//!
+//! With `REPEAT` set to a number of runs, the runner reruns the whole
+//! benchmark that many times, prints every scenario's reports as a curve
+//! over the runs and then how repeatable each is: the mean of the figure it
+//! is judged by and its coefficient of variation, flagged where it is within
+//! the noise of another runtime's on the same workload. The runs follow one
+//! another, so a machine that drifts over the whole run shows as noise.
+//!
+//! With `NICE` set to a niceness, the runner runs the scenarios at it, and
+//! with `SCHED_POLICY` set to `batch` or `idle`, under that Linux scheduling
+//! policy, while the spinning threads of `CONTENTION` keep theirs. Set to a
+//! comma-separated list, `NICE` reruns the benchmark once per niceness and
+//! prints the curves.
+//!
+//! With `SOAK` set to a number of minutes, the runner keeps running every
+//! scenario for that long after its first run, printing the resident set,
+//! open file descriptors and threads of the process every `SOAK_INTERVAL`
+//! seconds with the scenario's figure over the runs in between, then how
+//! the end of the soak compares with its start, to show slow leaks and
+//! latency or throughput drifting over hours.
+//!
+//! With `EXPECT` set to a path, the runner checks the expectations in that
+//! file against the reports of the scenarios once they all ran, printing
+//! whether each held, and exits with a failure if any did not.
+//!
+//! With `RESULTS` set to a path, the line printed for every scenario is
+//! appended to that file too, after a header naming the system, see
+//! [`results`](crate::results). The runs of a sweep do not append theirs.
+
+use crate::affinity;
+use crate::alloc::Allocations;
+use crate::config::{env_list, env_or};
+use crate::energy::Rapl;
+use crate::expect::{self, Expectation};
+use crate::exporter;
+use crate::metrics::Sampler;
+use crate::numa;
+use crate::perf::Counters;
+use crate::polls::{self, Polls};
+use crate::preflight;
+use crate::priority;
+use crate::repeat;
+use crate::results;
+use crate::scaling;
+use crate::soak::{self, Soak};
+use crate::syscalls;
+use futures::stream::{self, StreamExt};
+use hdrhistogram::Histogram;
+use std::any::Any;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufRead, BufReader, Write};
+use std::net::SocketAddr;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs named scenarios, honouring the name filter passed on the command line,
+/// and prints the CPU time and memory each took next to its report.
+pub struct Runner {
+    filter: Option<String>,
+    /// Whether every scenario runs under a dhat heap profiler of its own.
+    heap_profile: bool,
+    /// Whether the start and end of every scenario are marked for strace.
+    syscall_marks: bool,
+    /// Whether the first scenario that passes the filter runs over and over.
+    profile: bool,
+    /// Whether the energy every scenario used is read from RAPL.
+    energy: bool,
+    /// The file the line of every scenario is appended to, if any.
+    results: Option<File>,
+    /// What the reports are expected to show, if anything.
+    expectations: Option<Vec<Expectation>>,
+    /// The report of every scenario that ran, to check the expectations on.
+    reported: Mutex<Vec<(String, String)>>,
+    /// How long every scenario runs over and over while it is sampled, if
+    /// it is soaked.
+    soak: Option<Soak>,
+}
+
+impl Runner {
+    /// Creates a runner that filters scenarios the same way `bencher` does:
+    /// by the first command line argument that is not a flag.
+    ///
+    /// With `--heap-profile`, which needs the `heap-profile` feature, every
+    /// scenario that passes the filter runs under dhat and its heap profile is
+    /// written to `dhat-heap-<name>.json`, with any `/` in the name replaced
+    /// by `-`.
+    ///
+    /// With `--profile`, the first scenario that passes the filter runs over
+    /// and over until the process is stopped, printing its report after every
+    /// run, for a profiler to watch. `--console`, which needs the `console`
+    /// feature and `--cfg tokio_unstable`, does the same with a
+    /// console-subscriber serving tokio-console, so that the polls and wakeups
+    /// of the tasks of tokio scenarios can be inspected live. Either serves
+    /// Prometheus metrics of the runs, and of the requests of scenarios
+    /// driven with [`drive`], on `METRICS_ADDR` if it is set.
+    ///
+    /// Warns about the machine being set up in a way that skews comparisons,
+    /// see [`preflight`](crate::preflight), and with `--strict` exits
+    /// instead of running with it set up that way.
+    ///
+    /// With `EXPECT` set to a path, reads the expectations in that file, to
+    /// check once the runner is dropped.
+    ///
+    /// With `SOAK` set to a number of minutes, runs every scenario that
+    /// passes the filter over and over for that long after its first run,
+    /// sampling the process every `SOAK_INTERVAL` seconds.
+    ///
+    /// With `--energy`, on Linux, the energy the CPU packages used during
+    /// every scenario is read from their RAPL counters and printed, per
+    /// request too for scenarios reporting a [`LoadReport`].
+    ///
+    /// With `CPU_QUOTA` set to a number of cores, on Linux, reruns the
+    /// benchmark in a cgroup limited to that much CPU time and exits, see
+    /// the README.
+    ///
+    /// With `PIN_CORES` set to a list of core ids, confines the process to
+    /// those cores, on Linux, and with `PIN_WORKERS` set too, pins every
+    /// thread of the tokio runtimes the scenarios build to one of them.
+    ///
+    /// Runs the worker thread sweep and exits instead if `WORKER_THREADS` is
+    /// set, runs the NUMA comparison and exits if `NUMA` is, repeats it and
+    /// exits if `REPEAT` is, runs the
+    /// contention sweep and exits if `CONTENTION` lists more
+    /// than one percentage, runs the niceness sweep and exits if `NICE` lists
+    /// more than one niceness, and counts the system calls of every scenario
+    /// under strace and exits if `COUNT_SYSCALLS` is set.
+    pub fn from_args() -> Self {
+        if env::var_os("CPU_QUOTA").is_some() {
+            limit_cpu(env_or("CPU_QUOTA", 0.0));
+        }
+        let cores = env_list::<usize>("PIN_CORES", &[]);
+        if !cores.is_empty() {
+            affinity::confine(&cores);
+            if env::var_os("PIN_WORKERS").is_some() {
+                affinity::pin_workers(cores);
+            }
+        }
+        if env::var_os("WORKER_THREADS").is_some() {
+            sweep_workers(&env_list::<usize>("WORKER_THREADS", &[]));
+            std::process::exit(0);
+        }
+        if env::var_os("NUMA").is_some() {
+            compare_numa();
+            std::process::exit(0);
+        }
+        if env::var_os("REPEAT").is_some() {
+            sweep_repeats(env_or("REPEAT", 1));
+            std::process::exit(0);
+        }
+        let percents = env_list::<usize>("CONTENTION", &[]);
+        if percents.len() > 1 {
+            sweep_contention(&percents);
+            std::process::exit(0);
+        }
+        // Before any spinning thread starts, as every run of the sweep starts
+        // its own.
+        let levels = env_list::<i32>("NICE", &[]);
+        if levels.len() > 1 {
+            sweep_nice(&levels);
+            std::process::exit(0);
+        }
+        if let [percent] = percents[..] {
+            occupy(percent);
+        }
+        // After the spinning threads started, so that they keep the priority.
+        if let [nice] = levels[..] {
+            priority::renice(nice);
+        }
+        if let Ok(policy) = env::var("SCHED_POLICY") {
+            priority::schedule(&policy);
+        }
+        if env::var_os("COUNT_SYSCALLS").is_some() {
+            assert!(
+                cfg!(target_os = "linux"),
+                "COUNT_SYSCALLS needs Linux and strace"
+            );
+            syscalls::count();
+            std::process::exit(0);
+        }
+        preflight::run(env::args().any(|arg| arg == "--strict"));
+        let heap_profile = env::args().any(|arg| arg == "--heap-profile");
+        assert!(
+            !heap_profile || cfg!(feature = "heap-profile"),
+            "--heap-profile needs the heap-profile feature"
+        );
+        let console = env::args().any(|arg| arg == "--console");
+        if console {
+            serve_console();
+        }
+        let filter = env::args().skip(1).find(|arg| !arg.starts_with("--"));
+        Runner {
+            filter,
+            heap_profile,
+            syscall_marks: env::var_os("SYSCALL_MARKS").is_some(),
+            profile: console || env::args().any(|arg| arg == "--profile"),
+            energy: env::args().any(|arg| arg == "--energy"),
+            results: results::create(),
+            expectations: expect::load(),
+            reported: Mutex::new(Vec::new()),
+            soak: soak::load(),
+        }
+    }
+
+    /// Runs `scenario` and prints its report, unless `name` is filtered out.
+    pub fn run<R, F>(&self, name: &str, mut scenario: F)
+    where
+        R: fmt::Display + 'static,
+        F: FnMut() -> R,
+    {
+        if let Some(filter) = &self.filter {
+            if !name.contains(filter.as_str()) {
+                return;
+            }
+        }
+        if self.profile {
+            if let Ok(addr) = env::var("METRICS_ADDR") {
+                exporter::serve(addr.parse().expect("METRICS_ADDR is not an address"), name);
+            }
+            loop {
+                let started = Instant::now();
+                self.measure(name, &mut scenario);
+                exporter::run_finished(started.elapsed());
+            }
+        }
+        if let Some(soak) = &self.soak {
+            self.measure(name, &mut scenario);
+            if let Some(drift) = soak.run(name, &mut scenario) {
+                println!("{:<48} {}", name, drift);
+            }
+            return;
+        }
+        self.measure(name, scenario);
+    }
+
+    /// Runs `scenario` once and prints its report with what it used.
+    fn measure<R, F>(&self, name: &str, scenario: F)
+    where
+        R: fmt::Display + 'static,
+        F: FnOnce() -> R,
+    {
+        let before = Allocations::now();
+        let polled = Polls::start();
+        let started = Instant::now();
+        let usage = Usage::now();
+        let resident = resident_memory();
+        let threads = thread_count();
+        let sampler = cfg!(tokio_unstable).then(Sampler::start);
+        let peak_threads = PeakThreads::start(usize::from(sampler.is_some()));
+        let peak = PeakMemory::start();
+        let counters = Counters::start();
+        let rapl = self.energy.then(Rapl::start).flatten();
+        if self.syscall_marks {
+            syscalls::mark("start", name);
+        }
+        let report = if self.heap_profile {
+            profiled(name, scenario)
+        } else {
+            scenario()
+        };
+        if self.syscall_marks {
+            syscalls::mark("end", name);
+        }
+        let counts = counters.and_then(Counters::stop);
+        let energy = rapl.and_then(|rapl| rapl.stop(requests(&report)));
+        let memory_peak = peak.stop();
+        let tokio = sampler.and_then(Sampler::stop);
+        let threads = Threads {
+            before: threads,
+            peak: peak_threads.stop(),
+            after: thread_count(),
+        };
+        let timing = Timing {
+            wall: started.elapsed(),
+            used: usage
+                .zip(Usage::now())
+                .map(|(before, after)| after.since(before)),
+        };
+        let memory = Memory {
+            peak: memory_peak,
+            delta: resident
+                .zip(resident_memory())
+                .map(|(before, after)| after as i64 - before as i64),
+        };
+        if self.expectations.is_some() {
+            let reported = (name.to_owned(), report.to_string());
+            self.reported.lock().unwrap().push(reported);
+        }
+        let mut line = format!(
+            "{:<48} {}  {}  {}  {}",
+            name, report, timing, memory, threads
+        );
+        if cfg!(feature = "count-allocations") {
+            let made = Allocations::now().since(before);
+            line += &format!("  {}", made);
+        }
+        let polled = polled.stop();
+        if polled.count > 0 {
+            line += &format!("  {}", polled);
+        }
+        if let Some(counts) = counts {
+            line += &format!("  {}", counts);
+        }
+        if let Some(tokio) = tokio {
+            line += &format!("  {}", tokio);
+        }
+        if let Some(energy) = energy {
+            line += &format!("  {}", energy);
+        } else if self.energy {
+            line += "  energy n/a";
+        }
+        println!("{}", line);
+        if let Some(mut file) = self.results.as_ref() {
+            writeln!(file, "{}", line).expect("cannot append to RESULTS");
+        }
+    }
+}
+
+impl Drop for Runner {
+    /// Checks the expectations, once every scenario ran.
+    fn drop(&mut self) {
+        let expectations = match &self.expectations {
+            Some(expectations) => expectations,
+            None => return,
+        };
+        let reported = self.reported.get_mut().unwrap();
+        let mut failed = 0;
+        println!();
+        for expectation in expectations {
+            match expectation.check(reported) {
+                Some((true, found)) => println!("pass  {}  ({})", expectation, found),
+                Some((false, found)) => {
+                    failed += 1;
+                    println!("FAIL  {}  ({})", expectation, found);
+                }
+                None => println!("skip  {}  (did not run)", expectation),
+            }
+        }
+        if failed > 0 {
+            eprintln!("{} of {} expectations failed", failed, expectations.len());
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The requests `report` counts, if it is a [`LoadReport`].
+fn requests(report: &dyn Any) -> Option<u64> {
+    report
+        .downcast_ref::<LoadReport>()
+        .map(|report| report.requests)
+}
+
+/// Reruns the benchmark limited to `cores` cores' worth of CPU time.
+#[cfg(target_os = "linux")]
+fn limit_cpu(cores: f64) -> ! {
+    crate::cgroup::rerun_limited(cores)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn limit_cpu(_cores: f64) -> ! {
+    panic!("CPU_QUOTA needs Linux cgroups");
+}
+
+/// Starts serving tokio-console from this process.
+#[cfg(feature = "console")]
+fn serve_console() {
+    console_subscriber::init();
+}
+
+#[cfg(not(feature = "console"))]
+fn serve_console() {
+    panic!("--console needs the console feature");
+}
+
+/// Runs `scenario` under a dhat heap profiler that writes its profile to a
+/// file named after `name` once the scenario is done.
+#[cfg(feature = "heap-profile")]
+fn profiled<R>(name: &str, scenario: impl FnOnce() -> R) -> R {
+    let _profiler = dhat::Profiler::builder()
+        .file_name(format!("dhat-heap-{}.json", name.replace('/', "-")))
+        .build();
+    scenario()
+}
+
+#[cfg(not(feature = "heap-profile"))]
+fn profiled<R>(_name: &str, scenario: impl FnOnce() -> R) -> R {
+    scenario()
+}
+
+/// CPU time used by all threads of the process so far, in user and kernel
+/// mode, the context switches they went through and the page faults they
+/// took, from `getrusage`.
+///
+/// A voluntary switch is a thread giving up its core to wait, say in
+/// `epoll_wait` or on a blocking pool's queue; an involuntary one is the
+/// kernel taking the core away from a thread that could have gone on.
+///
+/// A minor fault is the first touch of a page the process mapped, which the
+/// kernel backs with memory then, so fresh stacks, arenas and `mmap`s of an
+/// allocator show up as minor faults; a major one had to be read from disk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Usage {
+    user: Duration,
+    system: Duration,
+    voluntary: u64,
+    involuntary: u64,
+    minor_faults: u64,
+    major_faults: u64,
+}
+
+impl Usage {
+    /// The usage so far, or `None` where `getrusage` is not available.
+    #[cfg(unix)]
+    fn now() -> Option<Self> {
+        let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+        // SAFETY: `getrusage` fills in `usage` when it returns 0.
+        if unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        let usage = unsafe { usage.assume_init() };
+        let time =
+            |time: libc::timeval| Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1_000);
+        Some(Usage {
+            user: time(usage.ru_utime),
+            system: time(usage.ru_stime),
+            voluntary: usage.ru_nvcsw as u64,
+            involuntary: usage.ru_nivcsw as u64,
+            minor_faults: usage.ru_minflt as u64,
+            major_faults: usage.ru_majflt as u64,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn now() -> Option<Self> {
+        None
+    }
+
+    /// The usage between `earlier` and this.
+    fn since(self, earlier: Usage) -> Self {
+        Usage {
+            user: self.user.saturating_sub(earlier.user),
+            system: self.system.saturating_sub(earlier.system),
+            voluntary: self.voluntary.saturating_sub(earlier.voluntary),
+            involuntary: self.involuntary.saturating_sub(earlier.involuntary),
+            minor_faults: self.minor_faults.saturating_sub(earlier.minor_faults),
+            major_faults: self.major_faults.saturating_sub(earlier.major_faults),
+        }
+    }
+}
+
+/// How long a scenario took by the wall clock, and the CPU time, context
+/// switches and page faults it used, or `None` where those are not available.
+///
+/// CPU time above the wall time means the scenario kept more than one core
+/// busy; well below it, that it spent the time waiting.
+struct Timing {
+    wall: Duration,
+    used: Option<Usage>,
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wall {:>9.1}ms", self.wall.as_secs_f64() * 1e3)?;
+        match self.used {
+            Some(used) => write!(
+                f,
+                "  user {:>9.1}ms  sys {:>9.1}ms  csw {:>8} vol {:>8} invol  \
+                 faults {:>8} minor {:>6} major",
+                used.user.as_secs_f64() * 1e3,
+                used.system.as_secs_f64() * 1e3,
+                used.voluntary,
+                used.involuntary,
+                used.minor_faults,
+                used.major_faults
+            ),
+            None => write!(f, "  cpu n/a"),
+        }
+    }
+}
+
+/// How much the resident set of the process grew while a scenario ran, at
+/// its peak and by the end, or `None` where that is not available.
+struct Memory {
+    peak: Option<u64>,
+    delta: Option<i64>,
+}
+
+impl fmt::Display for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MIB: f64 = (1 << 20) as f64;
+        match (self.peak, self.delta) {
+            (Some(peak), Some(delta)) => write!(
+                f,
+                "rss peak +{:>7.1} MiB  end {:>+7.1} MiB",
+                peak as f64 / MIB,
+                delta as f64 / MIB
+            ),
+            _ => write!(f, "rss n/a"),
+        }
+    }
+}
+
+/// How many threads the process had before a scenario, at most while it ran
+/// and after it, or `None` where that is not available, not counting the
+/// threads measuring it.
+///
+/// The workers and blocking pool threads a scenario's runtimes start show up
+/// in the peak, and the ones a global runtime keeps, in what is left after.
+struct Threads {
+    before: Option<usize>,
+    peak: Option<usize>,
+    after: Option<usize>,
+}
+
+impl fmt::Display for Threads {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.before, self.peak, self.after) {
+            (Some(before), Some(peak), Some(after)) => write!(
+                f,
+                "threads {:>4} peak {:>4} after {:>4}",
+                before, peak, after
+            ),
+            _ => write!(f, "threads n/a"),
+        }
+    }
+}
+
+/// Keeps the largest number of threads of the process seen until stopped,
+/// sampled every millisecond by a thread of its own, less that thread and
+/// the other measuring threads.
+struct PeakThreads {
+    stop: Arc<AtomicBool>,
+    sampler: thread::JoinHandle<Option<usize>>,
+}
+
+impl PeakThreads {
+    /// Starts sampling, with `others` measuring threads running next to this
+    /// one's while it does, besides the peak memory sampler.
+    fn start(others: usize) -> Self {
+        PeakThreads::counting(others, thread_count)
+    }
+
+    /// Starts sampling the threads `count` counts, at least once.
+    fn counting(others: usize, count: fn() -> Option<usize>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let sampling = stop.clone();
+        let sampler = thread::spawn(move || {
+            let mut peak = 0;
+            loop {
+                let stopping = sampling.load(Ordering::Relaxed);
+                peak = peak.max(count()?);
+                if stopping {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            // This thread and the peak memory sampler, which starts later.
+            Some(peak.saturating_sub(others + 2))
+        });
+        PeakThreads { stop, sampler }
+    }
+
+    fn stop(self) -> Option<usize> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.sampler.join().unwrap()
+    }
+}
+
+/// Number of threads of the benchmark process, read from
+/// `/proc/self/status`, or `None` where that is not available.
+pub(crate) fn thread_count() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("Threads:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Number of file descriptors the benchmark process has open, read from
+/// `/proc/self/fd`, or `None` where that is not available.
+pub(crate) fn open_files() -> Option<usize> {
+    // Less the descriptor of the directory being read.
+    Some(std::fs::read_dir("/proc/self/fd").ok()?.count() - 1)
+}
+
+/// Reruns the benchmark with every count of `threads` worker threads and
+/// prints the reports of each scenario one count after another.
+fn sweep_workers(threads: &[usize]) {
+    let runs = sweep(threads, "threads", |command, count| {
+        command
+            .env_remove("WORKER_THREADS")
+            .env("TOKIO_WORKER_THREADS", count.to_string())
+            .env("ASYNC_STD_THREAD_COUNT", count.to_string())
+            .env("SMOL_THREADS", count.to_string());
+    });
+    let scenarios: Vec<_> = curves(&runs)
+        .into_iter()
+        .filter_map(|(name, curve)| Some((name, scaling::scaling(&curve)?)))
+        .collect();
+    if scenarios.is_empty() {
+        return;
+    }
+    println!();
+    for (name, points) in &scenarios {
+        for point in points {
+            println!(
+                "{:<48} {:>3} threads  {:>12.0}/s  ideal {:>12.0}/s  efficiency {:>5.1}%",
+                name,
+                point.threads,
+                point.throughput,
+                point.ideal,
+                point.efficiency() * 100.0
+            );
+        }
+    }
+    if let Ok(path) = env::var("SCALING_PLOT") {
+        std::fs::write(&path, scaling::svg(&scenarios))
+            .unwrap_or_else(|err| panic!("cannot write {}: {}", path, err));
+    }
+}
+
+/// Reruns the benchmark with every percentage of `percents` of the cores
+/// kept busy and prints the reports of each scenario one after another.
+fn sweep_contention(percents: &[usize]) {
+    sweep(percents, "% busy", |command, percent| {
+        command.env("CONTENTION", percent.to_string());
+    });
+}
+
+/// Reruns the benchmark `times` times, prints the reports of each scenario
+/// one after another and then how repeatable each is.
+fn sweep_repeats(times: usize) {
+    let points: Vec<usize> = (1..=times).collect();
+    let runs = sweep(&points, "run", |command, _| {
+        command.env_remove("REPEAT");
+    });
+    println!();
+    for (name, spread, overlapping) in repeat::repeatability(&curves(&runs)) {
+        let mut line = format!(
+            "{:<48} mean {:>12.3}  cv {:>5.1}%",
+            name,
+            spread.mean,
+            spread.variation() * 100.0
+        );
+        if !overlapping.is_empty() {
+            line += &format!("  noisy: within the noise of {}", overlapping.join(", "));
+        }
+        println!("{}", line);
+    }
+}
+
+/// Reruns the benchmark at every niceness of `levels` and prints the
+/// reports of each scenario one after another.
+fn sweep_nice(levels: &[i32]) {
+    sweep(levels, "nice", |command, nice| {
+        command.env("NICE", nice.to_string());
+    });
+}
+
+/// Reruns the benchmark pinned to cores of one NUMA node and to as many
+/// spread over every node, and prints the reports of each scenario in turn.
+fn compare_numa() {
+    let nodes = numa::nodes();
+    assert!(
+        nodes.len() > 1,
+        "NUMA needs a machine with more than one node"
+    );
+    let (confined, spread) = numa::placements(&nodes);
+    sweep(&[1, nodes.len()], "nodes", |command, used| {
+        let cores = if used == 1 { &confined } else { &spread };
+        let cores: Vec<_> = cores.iter().map(usize::to_string).collect();
+        command.env_remove("NUMA").env("PIN_CORES", cores.join(","));
+    });
+}
+
+/// Reruns the benchmark once per point of `points`, set up for it by
+/// `configure`, and prints the reports of each scenario as a curve over the
+/// points, labelled with `unit`. Returns the output of every run.
+fn sweep<T: Copy + fmt::Display>(
+    points: &[T],
+    unit: &str,
+    configure: impl Fn(&mut Command, T),
+) -> Vec<(T, String)> {
+    let program = env::current_exe().unwrap();
+    let mut runs = Vec::new();
+    for &point in points {
+        let mut command = Command::new(&program);
+        command
+            .args(env::args_os().skip(1))
+            .env_remove("RESULTS")
+            .env_remove("EXPECT")
+            .stderr(Stdio::inherit());
+        configure(&mut command, point);
+        let output = command.output().unwrap();
+        assert!(
+            output.status.success(),
+            "run with {} {} failed",
+            point,
+            unit
+        );
+        runs.push((point, String::from_utf8(output.stdout).unwrap()));
+    }
+    for (name, curve) in curves(&runs) {
+        for (point, report) in curve {
+            println!("{:<48} {:>3} {}  {}", name, point, unit, report);
+        }
+    }
+    runs
+}
+
+/// Keeps `percent` of the cores busy until the process exits, with as many
+/// threads spinning on arithmetic.
+fn occupy(percent: usize) {
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+    for _ in 0..cores * percent / 100 {
+        thread::spawn(|| {
+            let mut state = 1;
+            loop {
+                std::hint::black_box(xorshift(&mut state));
+            }
+        });
+    }
+}
+
+/// Groups the report lines printed by the runs at each point of a sweep by
+/// scenario, in the order the scenarios first ran.
+fn curves<T: Copy>(runs: &[(T, String)]) -> Vec<(&str, Vec<(T, &str)>)> {
+    let mut curves: Vec<(&str, Vec<(T, &str)>)> = Vec::new();
+    for (count, output) in runs {
+        for line in output.lines() {
+            let (name, report) = match line.split_once(' ') {
+                Some((name, report)) if !name.is_empty() => (name, report.trim_start()),
+                _ => continue,
+            };
+            match curves.iter_mut().find(|(scenario, _)| *scenario == name) {
+                Some((_, points)) => points.push((*count, report)),
+                None => curves.push((name, vec![(*count, report)])),
+            }
+        }
+    }
+    curves
+}
+
+/// Creates an empty histogram of latencies recorded in nanoseconds.
+pub fn latency_histogram() -> Histogram<u64> {
+    Histogram::new(3).unwrap()
+}
+
+/// Records `latency` into `histogram`.
+pub fn record(histogram: &mut Histogram<u64>, latency: Duration) {
+    histogram.record(latency.as_nanos() as u64).unwrap();
+}
+
+/// Splits `total` units of work into `parts` shares that differ by at most one.
+pub fn split(total: usize, parts: usize) -> impl Iterator<Item = usize> {
+    (0..parts).map(move |i| total / parts + usize::from(i < total % parts))
+}
+
+/// Outcome of a closed-loop load run: how many requests completed, how long it
+/// took and how long each request took.
+///
+/// `bytes` is the payload moved by the run, or zero if only requests count.
+pub struct LoadReport {
+    pub requests: u64,
+    pub bytes: u64,
+    pub elapsed: Duration,
+    pub latencies: Histogram<u64>,
+}
+
+impl LoadReport {
+    /// Builds the report of a run that started at `start` and has just completed `requests` requests.
+    pub fn since(start: Instant, requests: usize, latencies: Histogram<u64>) -> Self {
+        LoadReport {
+            requests: requests as u64,
+            bytes: 0,
+            elapsed: start.elapsed(),
+            latencies,
+        }
+    }
+
+    /// Sets the payload moved by the run, so that bandwidth is reported too.
+    pub fn with_bytes(self, bytes: u64) -> Self {
+        LoadReport { bytes, ..self }
+    }
+
+    /// Completed requests per second of wall-clock time.
+    pub fn throughput(&self) -> f64 {
+        self.requests as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>10.0} req/s", self.throughput())?;
+        if self.bytes > 0 {
+            let megabytes = self.bytes as f64 / 1e6;
+            write!(f, "  {:>8.1} MB/s", megabytes / self.elapsed.as_secs_f64())?;
+        }
+        for &(label, quantile) in &[("p50", 0.5), ("p90", 0.9), ("p99", 0.99), ("p99.9", 0.999)] {
+            let nanos = self.latencies.value_at_quantile(quantile);
+            write!(f, "  {} {}", label, Millis(nanos))?;
+        }
+        write!(f, "  max {}", Millis(self.latencies.max()))
+    }
+}
+
+/// Binds a listener to a free loopback port with a backlog deep enough for
+/// hundreds of clients connecting at once.
+///
+/// `std::net::TcpListener::bind` asks for a backlog of 128; beyond that the
+/// kernel drops connection attempts, and the clients retry a second later.
+pub fn loopback_listener() -> std::net::TcpListener {
+    listener("127.0.0.1:0".parse().unwrap())
+}
+
+/// Binds a listener to `addr` with the same backlog as
+/// [`loopback_listener`], for servers that other machines connect to.
+pub fn listener(addr: SocketAddr) -> std::net::TcpListener {
+    use socket2::{Domain, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None).unwrap();
+    socket.set_reuse_address(true).unwrap();
+    socket
+        .bind(&addr.into())
+        .unwrap_or_else(|err| panic!("cannot listen on {}: {}", addr, err));
+    socket.listen(4096).unwrap();
+    socket.into()
+}
+
+/// A server running as a separate process, so that it does not share an
+/// executor, or even a scheduler time slice, with the clients measuring it.
+///
+/// The program must print the address it listens on as its first line of
+/// output and should exit once its standard input is closed, so that it does
+/// not outlive a benchmark that crashed. It is killed when the handle is
+/// dropped.
+pub struct ServerProcess {
+    child: Child,
+    addr: SocketAddr,
+}
+
+impl ServerProcess {
+    /// Starts `program` with `args` and waits until it reports its address.
+    pub fn spawn(program: &str, args: &[&str]) -> Self {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|err| panic!("cannot start {}: {}", program, err));
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let addr = line
+            .trim()
+            .parse()
+            .unwrap_or_else(|err| panic!("{} printed {:?}: {}", program, line, err));
+        ServerProcess { child, addr }
+    }
+
+    /// Address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Resident set size of the benchmark process in bytes, read from
+/// `/proc/self/status`, or `None` where that is not available.
+pub fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Keeps the largest resident set size seen until stopped, sampled every
+/// millisecond by a thread of its own.
+pub(crate) struct PeakMemory {
+    stop: Arc<AtomicBool>,
+    sampler: thread::JoinHandle<Option<u64>>,
+}
+
+impl PeakMemory {
+    pub(crate) fn start() -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let sampling = stop.clone();
+        let sampler = thread::spawn(move || {
+            let before = resident_memory()?;
+            let mut peak = before;
+            while !sampling.load(Ordering::Relaxed) {
+                peak = peak.max(resident_memory()?);
+                thread::sleep(Duration::from_millis(1));
+            }
+            Some(peak.saturating_sub(before))
+        });
+        PeakMemory { stop, sampler }
+    }
+
+    /// The largest growth of the resident set since starting.
+    pub(crate) fn stop(self) -> Option<u64> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.sampler.join().unwrap()
+    }
+}
+
+/// CPU time used by all threads of the benchmark process so far, in user and
+/// kernel mode, read from `/proc/self/stat`, or `None` where that is not
+/// available.
+///
+/// The kernel counts it in clock ticks, a hundredth of a second on Linux.
+pub fn cpu_time() -> Option<Duration> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // The command name in parentheses may contain spaces, the fields after it
+    // do not; user and system time are the 14th and 15th fields.
+    let mut fields = stat.rsplit(')').next()?.split_whitespace().skip(11);
+    let user: u64 = fields.next()?.parse().ok()?;
+    let system: u64 = fields.next()?.parse().ok()?;
+    Some(Duration::from_millis((user + system) * 10))
+}
+
+/// Runs `requests` calls of `request`, at most `concurrency` of them at a time,
+/// and records how long each call took.
+///
+/// The calls are polled from the current task with `buffer_unordered`, so the
+/// concurrency limit is the same no matter which runtime drives them. The
+/// polls of that task are counted, see [`polls`](crate::polls).
+pub async fn drive<F, Fut>(concurrency: usize, requests: usize, mut request: F) -> LoadReport
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    polls::counted(async move {
+        let start = Instant::now();
+        let mut latencies = latency_histogram();
+        let mut timings = stream::iter(0..requests)
+            .map(|_| {
+                let request = request();
+                async move {
+                    let start = Instant::now();
+                    exporter::started();
+                    request.await;
+                    let latency = start.elapsed();
+                    exporter::finished(latency);
+                    latency
+                }
+            })
+            .buffer_unordered(concurrency);
+        while let Some(latency) = timings.next().await {
+            record(&mut latencies, latency);
+        }
+        LoadReport::since(start, requests, latencies)
+    })
+    .await
+}
+
+/// Jain's fairness index: `(Σx)² / (n · Σx²)`, between `1/n` and 1.
+pub(crate) fn fairness(counts: &[usize]) -> f64 {
+    let sum: f64 = counts.iter().map(|&count| count as f64).sum();
+    let squares: f64 = counts.iter().map(|&count| (count as f64).powi(2)).sum();
+    if squares == 0.0 {
+        return 1.0;
+    }
+    sum * sum / (counts.len() as f64 * squares)
+}
+
+/// Advances the xorshift state `state`, which must not be zero, and returns it.
+///
+/// Good enough to draw delays from, and cheap and reproducible from a seed.
+pub(crate) fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Formats nanoseconds as fractional milliseconds.
+pub(crate) struct Millis(pub u64);
+
+impl fmt::Display for Millis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>8.3}ms", self.0 as f64 / 1e6)
+    }
+}
+
+/// Futures or tasks that have not been dropped yet.
+#[derive(Clone, Default)]
+pub(crate) struct Alive(Arc<AtomicUsize>);
+
+/// Counts as alive until dropped.
+pub(crate) struct Guard(Alive);
+
+impl Alive {
+    pub(crate) fn guard(&self) -> Guard {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Guard(self.clone())
+    }
+
+    /// Yields with `yield_now` until every guard is dropped.
+    pub(crate) async fn gone<F, Fut>(&self, yield_now: F)
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        while self.0.load(Ordering::Acquire) > 0 {
+            yield_now().await;
+        }
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        (self.0).0.fetch_sub(1, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_spreads_remainder() {
+        assert_eq!(split(10, 4).collect::<Vec<_>>(), vec![3, 3, 2, 2]);
+    }
+
+    #[test]
+    fn curves_group_reports_by_scenario() {
+        let runs = vec![
+            (1, "a    10 req/s\nb    5 req/s\n".to_owned()),
+            (2, "a    19 req/s\nb    9 req/s\n".to_owned()),
+        ];
+        assert_eq!(
+            curves(&runs),
+            vec![
+                ("a", vec![(1, "10 req/s"), (2, "19 req/s")]),
+                ("b", vec![(1, "5 req/s"), (2, "9 req/s")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn usage_grows() {
+        if let Some(before) = Usage::now() {
+            let spun = Instant::now();
+            while spun.elapsed() < Duration::from_millis(50) {
+                std::hint::spin_loop();
+            }
+            thread::sleep(Duration::from_millis(1));
+            let used = Usage::now().unwrap().since(before);
+            assert!(used.user + used.system >= Duration::from_millis(10));
+            assert!(used.voluntary >= 1);
+            let faulting = Usage::now().unwrap();
+            let touched = vec![1u8; 16 << 20];
+            assert_eq!(std::hint::black_box(touched)[(8 << 20) + 1], 1);
+            assert!(Usage::now().unwrap().since(faulting).minor_faults >= 1);
+        }
+    }
+
+    #[test]
+    fn memory_shows_growth() {
+        let memory = Memory {
+            peak: Some(3 << 20),
+            delta: Some(-(1 << 20)),
+        };
+        assert_eq!(memory.to_string(), "rss peak +    3.0 MiB  end    -1.0 MiB");
+        let unknown = Memory {
+            peak: None,
+            delta: None,
+        };
+        assert_eq!(unknown.to_string(), "rss n/a");
+    }
+
+    #[test]
+    fn threads_show_the_peak() {
+        let threads = Threads {
+            before: Some(1),
+            peak: Some(9),
+            after: Some(5),
+        };
+        assert_eq!(threads.to_string(), "threads    1 peak    9 after    5");
+        if let Some(count) = thread_count() {
+            assert!(count >= 1);
+        }
+    }
+
+    #[test]
+    fn peak_threads_leave_out_the_samplers() {
+        // Ten threads of the scenario, next to both samplers and one more
+        // measuring thread, counted apart from the other tests' threads.
+        let peak_threads = PeakThreads::counting(1, || Some(13));
+        assert_eq!(peak_threads.stop(), Some(10));
+    }
+
+    #[test]
+    fn fairness_index() {
+        assert_eq!(fairness(&[5, 5, 5, 5]), 1.0);
+        assert_eq!(fairness(&[20, 0, 0, 0]), 0.25);
+    }
+
+    #[test]
+    fn throughput_is_requests_per_second() {
+        let report = LoadReport {
+            requests: 500,
+            bytes: 0,
+            elapsed: Duration::from_millis(250),
+            latencies: latency_histogram(),
+        };
+        assert_eq!(report.throughput(), 2000.0);
+    }
+}
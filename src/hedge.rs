@@ -0,0 +1,241 @@
+//! Request hedging: when a call has not been answered after a delay, the same
+//! call is sent again over another connection and whichever answer arrives
+//! first is taken, cancelling the other call.
+//!
+//! The backend is the one from [`crate::fanout`], so its answers take a
+//! [`Latency`] drawn per call. Hedging cuts the tail down to roughly the hedge
+//! delay plus a typical call, at the price of sending the duplicates. A
+//! cancelled call leaves its answer unread on its connection, so the client
+//! drops that connection and opens a new spare after the request, outside of
+//! its measured latency; the backend still does the cancelled work.
+
+use crate::fanout::{backend, Latency};
+use crate::framing::{read_frame, write_frame};
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use futures::future::{self, Either};
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::pin_mut;
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// A load report together with how many requests were hedged.
+pub struct HedgeReport {
+    pub load: LoadReport,
+    /// Requests that sent a duplicate call and cancelled one of the two.
+    pub hedged: u64,
+}
+
+impl fmt::Display for HedgeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let share = self.hedged as f64 / self.load.requests as f64;
+        write!(f, "{}  hedged {:>5.1}%", self.load, 100.0 * share)
+    }
+}
+
+/// Runs `requests` calls to a backend answering with `latency` from
+/// `concurrency` client tasks on tokio, hedging each call after `hedge_after`,
+/// or never if it is `None`.
+pub fn tokio(
+    latency: Latency,
+    hedge_after: Option<Duration>,
+    concurrency: usize,
+    requests: usize,
+    payload: usize,
+) -> HedgeReport {
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for seed in 1.. {
+                let (stream, _) = listener.accept().await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                tokio::spawn(backend(stream.compat(), latency, seed, tokio::time::sleep));
+            }
+        });
+
+        let start = Instant::now();
+        let clients: Vec<_> = split(requests, concurrency)
+            .map(|share| {
+                let connect = move || async move {
+                    let stream = TcpStream::connect(addr).await.unwrap();
+                    stream.set_nodelay(true).unwrap();
+                    stream.compat()
+                };
+                let calls = hedged_calls(connect, tokio::time::sleep, hedge_after, share, payload);
+                tokio::spawn(calls)
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        let mut hedged = 0;
+        for client in clients {
+            let (client_latencies, client_hedged) = client.await.unwrap();
+            latencies.add(client_latencies).unwrap();
+            hedged += client_hedged;
+        }
+        HedgeReport {
+            load: LoadReport::since(start, requests, latencies),
+            hedged,
+        }
+    })
+}
+
+/// Runs `requests` calls to a backend answering with `latency` from
+/// `concurrency` client tasks on async_std, hedging each call after
+/// `hedge_after`, or never if it is `None`.
+pub fn async_std(
+    latency: Latency,
+    hedge_after: Option<Duration>,
+    concurrency: usize,
+    requests: usize,
+    payload: usize,
+) -> HedgeReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept = task::spawn(async move {
+            for seed in 1.. {
+                let (stream, _) = listener.accept().await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                task::spawn(backend(stream, latency, seed, task::sleep));
+            }
+        });
+
+        let start = Instant::now();
+        let clients: Vec<_> = split(requests, concurrency)
+            .map(|share| {
+                let connect = move || async move {
+                    let stream = TcpStream::connect(addr).await.unwrap();
+                    stream.set_nodelay(true).unwrap();
+                    stream
+                };
+                task::spawn(hedged_calls(
+                    connect,
+                    task::sleep,
+                    hedge_after,
+                    share,
+                    payload,
+                ))
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        let mut hedged = 0;
+        for client in clients {
+            let (client_latencies, client_hedged) = client.await;
+            latencies.add(client_latencies).unwrap();
+            hedged += client_hedged;
+        }
+        let report = HedgeReport {
+            load: LoadReport::since(start, requests, latencies),
+            hedged,
+        };
+        accept.cancel().await;
+        report
+    })
+}
+
+/// Sends `rounds` calls one after another, hedging each after `hedge_after`,
+/// and returns their latencies and how many of them were hedged.
+async fn hedged_calls<S, C, CFut, F, SFut>(
+    connect: C,
+    sleep: F,
+    hedge_after: Option<Duration>,
+    rounds: usize,
+    payload: usize,
+) -> (Histogram<u64>, u64)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    C: Fn() -> CFut,
+    CFut: Future<Output = S>,
+    F: Fn(Duration) -> SFut,
+    SFut: Future<Output = ()>,
+{
+    let message = vec![0; payload];
+    let mut primary = connect().await;
+    let mut spare = Some(connect().await);
+    let mut latencies = latency_histogram();
+    let mut hedged = 0;
+    for _ in 0..rounds {
+        let start = Instant::now();
+        let first = call(primary, &message);
+        primary = match hedge_after {
+            None => first.await,
+            Some(after) => {
+                pin_mut!(first);
+                let timer = sleep(after);
+                pin_mut!(timer);
+                match future::select(first, timer).await {
+                    Either::Left((stream, _)) => stream,
+                    Either::Right(((), first)) => {
+                        hedged += 1;
+                        let second = call(spare.take().unwrap(), &message);
+                        pin_mut!(second);
+                        match future::select(first, second).await {
+                            Either::Left((stream, _)) => stream,
+                            Either::Right((stream, _)) => stream,
+                        }
+                    }
+                }
+            }
+        };
+        record(&mut latencies, start.elapsed());
+        if spare.is_none() {
+            spare = Some(connect().await);
+        }
+    }
+    (latencies, hedged)
+}
+
+/// Sends one call over `stream`, waits for its answer and hands the stream back.
+async fn call<S>(mut stream: S, message: &[u8]) -> S
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buf = Vec::new();
+    write_frame(&mut stream, message).await.unwrap();
+    assert!(read_frame(&mut stream, &mut buf).await.unwrap());
+    stream
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hedging_cuts_the_slow_calls_short() {
+        let latency = Latency {
+            typical: Duration::from_millis(1),
+            slow: Duration::from_millis(200),
+            slow_one_in: 8,
+        };
+        let hedge_after = Some(Duration::from_millis(20));
+        for run in &[tokio, async_std] {
+            // Both calls of a hedged request are slow only once in 64 requests.
+            let report = run(latency, hedge_after, 2, 80, 16);
+            assert_eq!(report.load.latencies.len(), 80);
+            assert!(report.hedged > 0);
+            let p90 = report.load.latencies.value_at_quantile(0.9);
+            assert!(p90 < Duration::from_millis(200).as_nanos() as u64);
+        }
+    }
+
+    #[test]
+    fn unhedged_calls_wait_for_the_backend() {
+        let latency = Latency {
+            typical: Duration::from_millis(5),
+            slow: Duration::from_millis(5),
+            slow_one_in: 1,
+        };
+        let report = tokio(latency, None, 2, 10, 16);
+        assert_eq!(report.hedged, 0);
+        assert!(report.load.latencies.min() >= Duration::from_millis(5).as_nanos() as u64);
+    }
+}
@@ -0,0 +1,65 @@
+//! The same batch of GET requests issued through different HTTP client libraries.
+//!
+//! Each client uses its own connection pool and the runtime it is built for;
+//! concurrency is limited the same way for all of them, with `buffer_unordered`
+//! inside a single task.
+
+use super::HELLO;
+use crate::harness::{drive, LoadReport};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty};
+use hyper::StatusCode;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use std::net::SocketAddr;
+
+/// Issues `requests` GET requests to `addr` with reqwest on a tokio runtime.
+pub fn reqwest(addr: SocketAddr, concurrency: usize, requests: usize) -> LoadReport {
+    let url = format!("http://{}/", addr);
+    let client = reqwest::Client::new();
+    let rt = crate::metrics::runtime();
+    rt.block_on(drive(concurrency, requests, || async {
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.bytes().await.unwrap(), HELLO.as_bytes());
+    }))
+}
+
+/// Issues `requests` GET requests to `addr` with surf on async_std.
+pub fn surf(addr: SocketAddr, concurrency: usize, requests: usize) -> LoadReport {
+    let url = format!("http://{}/", addr);
+    let client = surf::Client::new();
+    async_std::task::block_on(drive(concurrency, requests, || async {
+        let mut response = client.get(&url).await.unwrap();
+        assert_eq!(response.status(), surf::StatusCode::Ok);
+        assert_eq!(response.body_bytes().await.unwrap(), HELLO.as_bytes());
+    }))
+}
+
+/// Issues `requests` GET requests to `addr` with the pooled hyper client on a tokio runtime.
+pub fn hyper(addr: SocketAddr, concurrency: usize, requests: usize) -> LoadReport {
+    let uri: hyper::Uri = format!("http://{}/", addr).parse().unwrap();
+    let client = Client::builder(TokioExecutor::new()).build_http::<Empty<Bytes>>();
+    let rt = crate::metrics::runtime();
+    rt.block_on(drive(concurrency, requests, || async {
+        let response = client.get(uri.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, HELLO.as_bytes());
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::server;
+
+    #[test]
+    fn clients_complete_requests() {
+        let server = server::hyper();
+        for client in &[reqwest, surf, hyper] {
+            let report = client(server.addr(), 2, 10);
+            assert_eq!(report.latencies.len(), 10);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! A crawler fetching a batch of distinct pages with a concurrency limit.
+//!
+//! The pages come from an in-process site that answers every request after a
+//! fixed delay, standing in for the time a remote server takes to respond, so
+//! the interesting part is how well each approach overlaps the waiting. The
+//! async crawlers either limit concurrency with `buffer_unordered` inside one
+//! task or spawn a task per page and gate the fetches with a semaphore; the
+//! thread pool baseline runs blocking ureq requests on as many threads as the
+//! async crawlers have fetches in flight.
+//!
+//! Latencies are the time each fetch took, excluding any wait for a permit.
+
+use super::server::{bind, Server};
+use crate::harness::{drive, latency_histogram, record, LoadReport};
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Starts a hyper site that echoes the requested path after `delay`.
+pub fn site(delay: Duration) -> Server {
+    let (listener, addr) = bind();
+    let rt = crate::metrics::runtime();
+    rt.spawn(async move {
+        let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            tokio::spawn(async move {
+                let page = service_fn(move |request: Request<Incoming>| async move {
+                    tokio::time::sleep(delay).await;
+                    let path = Bytes::copy_from_slice(request.uri().path().as_bytes());
+                    Ok::<_, Infallible>(Response::new(Full::new(path)))
+                });
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), page)
+                    .await;
+            });
+        }
+    });
+    Server::new(addr, move || rt.shutdown_background())
+}
+
+/// Path of the `i`-th page.
+fn path(i: usize) -> String {
+    format!("/page/{}", i)
+}
+
+/// Fetches `pages` pages from `addr`, `concurrency` at a time, with reqwest
+/// and `buffer_unordered` on tokio.
+pub fn tokio_buffer_unordered(addr: SocketAddr, concurrency: usize, pages: usize) -> LoadReport {
+    let client = reqwest::Client::new();
+    let rt = crate::metrics::runtime();
+    let mut next = 0..pages;
+    rt.block_on(drive(concurrency, pages, || {
+        let path = path(next.next().unwrap());
+        let client = &client;
+        async move { fetch_reqwest(client, addr, &path).await }
+    }))
+}
+
+/// Fetches `pages` pages from `addr` with reqwest on tokio, spawning a task
+/// per page and letting `concurrency` of them fetch at a time.
+pub fn tokio_semaphore(addr: SocketAddr, concurrency: usize, pages: usize) -> LoadReport {
+    use tokio::sync::Semaphore;
+
+    let client = reqwest::Client::new();
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let permits = Arc::new(Semaphore::new(concurrency));
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..pages)
+            .map(|i| {
+                let permits = permits.clone();
+                let client = client.clone();
+                tokio::spawn(async move {
+                    let _permit = permits.acquire().await.unwrap();
+                    let start = Instant::now();
+                    fetch_reqwest(&client, addr, &path(i)).await;
+                    start.elapsed()
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for task in tasks {
+            record(&mut latencies, task.await.unwrap());
+        }
+        LoadReport::since(start, pages, latencies)
+    })
+}
+
+async fn fetch_reqwest(client: &reqwest::Client, addr: SocketAddr, path: &str) {
+    let url = format!("http://{}{}", addr, path);
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.bytes().await.unwrap(), path.as_bytes());
+}
+
+/// Fetches `pages` pages from `addr`, `concurrency` at a time, with surf and
+/// `buffer_unordered` on async_std.
+pub fn async_std_buffer_unordered(
+    addr: SocketAddr,
+    concurrency: usize,
+    pages: usize,
+) -> LoadReport {
+    let client = surf::Client::new();
+    let mut next = 0..pages;
+    async_std::task::block_on(drive(concurrency, pages, || {
+        let path = path(next.next().unwrap());
+        let client = &client;
+        async move { fetch_surf(client, addr, &path).await }
+    }))
+}
+
+/// Fetches `pages` pages from `addr` with surf on async_std, spawning a task
+/// per page and letting `concurrency` of them fetch at a time.
+///
+/// async_std has no semaphore of its own, so the one from async-lock is used.
+pub fn async_std_semaphore(addr: SocketAddr, concurrency: usize, pages: usize) -> LoadReport {
+    use async_lock::Semaphore;
+    use async_std::task;
+
+    let client = surf::Client::new();
+    task::block_on(async {
+        let permits = Arc::new(Semaphore::new(concurrency));
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..pages)
+            .map(|i| {
+                let permits = permits.clone();
+                let client = client.clone();
+                task::spawn(async move {
+                    let _permit = permits.acquire().await;
+                    let start = Instant::now();
+                    fetch_surf(&client, addr, &path(i)).await;
+                    start.elapsed()
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for task in tasks {
+            record(&mut latencies, task.await);
+        }
+        LoadReport::since(start, pages, latencies)
+    })
+}
+
+async fn fetch_surf(client: &surf::Client, addr: SocketAddr, path: &str) {
+    let url = format!("http://{}{}", addr, path);
+    let mut response = client.get(&url).await.unwrap();
+    assert_eq!(response.status(), surf::StatusCode::Ok);
+    assert_eq!(response.body_bytes().await.unwrap(), path.as_bytes());
+}
+
+/// Fetches `pages` pages from `addr` with blocking ureq requests on `threads` threads.
+pub fn thread_pool(addr: SocketAddr, threads: usize, pages: usize) -> LoadReport {
+    let agent = ureq::Agent::new_with_defaults();
+    let next = AtomicUsize::new(0);
+    let start = Instant::now();
+    let latencies = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut latencies = latency_histogram();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= pages {
+                            break latencies;
+                        }
+                        let path = path(i);
+                        let url = format!("http://{}{}", addr, path);
+                        let start = Instant::now();
+                        let mut response = agent.get(&url).call().unwrap();
+                        assert_eq!(response.status(), StatusCode::OK);
+                        assert_eq!(response.body_mut().read_to_string().unwrap(), path);
+                        record(&mut latencies, start.elapsed());
+                    }
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for worker in workers {
+            latencies.add(worker.join().unwrap()).unwrap();
+        }
+        latencies
+    });
+    LoadReport::since(start, pages, latencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crawlers_fetch_every_page() {
+        let site = site(Duration::from_millis(1));
+        for crawl in &[
+            tokio_buffer_unordered,
+            tokio_semaphore,
+            async_std_buffer_unordered,
+            async_std_semaphore,
+            thread_pool,
+        ] {
+            assert_eq!(crawl(site.addr(), 4, 20).latencies.len(), 20);
+        }
+    }
+}
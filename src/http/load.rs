@@ -0,0 +1,58 @@
+//! Closed-loop HTTP/1.1 load generator.
+//!
+//! The client always runs on its own tokio runtime, so every server is measured
+//! against exactly the same client regardless of the runtime it uses itself.
+
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use bytes::Bytes;
+use hdrhistogram::Histogram;
+use http_body_util::{BodyExt, Empty};
+use hyper::client::conn::http1;
+use hyper::header::HOST;
+use hyper::{Request, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::net::SocketAddr;
+use std::time::Instant;
+use tokio::net::TcpStream;
+
+/// Sends `requests` GET requests to `addr` over `concurrency` keep-alive connections.
+///
+/// Every connection sends its next request as soon as the previous response
+/// has been read, so at most `concurrency` requests are in flight at once.
+pub fn hammer(addr: SocketAddr, concurrency: usize, requests: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let start = Instant::now();
+        let connections: Vec<_> = split(requests, concurrency)
+            .map(|share| tokio::spawn(connection(addr, share)))
+            .collect();
+        let mut latencies = latency_histogram();
+        for connection in connections {
+            latencies.add(connection.await.unwrap()).unwrap();
+        }
+        LoadReport::since(start, requests, latencies)
+    })
+}
+
+/// Sends `requests` requests one after another over a single connection.
+async fn connection(addr: SocketAddr, requests: usize) -> Histogram<u64> {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    stream.set_nodelay(true).unwrap();
+    let (mut sender, conn) = http1::handshake(TokioIo::new(stream)).await.unwrap();
+    tokio::spawn(conn);
+
+    let mut latencies = latency_histogram();
+    for _ in 0..requests {
+        let request = Request::get("/")
+            .header(HOST, addr.to_string())
+            .body(Empty::<Bytes>::new())
+            .unwrap();
+        sender.ready().await.unwrap();
+        let start = Instant::now();
+        let response = sender.send_request(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        response.into_body().collect().await.unwrap();
+        record(&mut latencies, start.elapsed());
+    }
+    latencies
+}
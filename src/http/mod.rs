@@ -0,0 +1,12 @@
+//! HTTP servers and clients used by the HTTP scenarios.
+
+pub mod client;
+pub mod crawl;
+pub mod load;
+pub mod multiplex;
+pub mod open_loop;
+pub mod server;
+pub mod upload;
+
+/// Body returned by every benchmark server.
+pub const HELLO: &str = "Hello, world!";
@@ -0,0 +1,116 @@
+//! HTTP/2 with many concurrent streams multiplexed over a single connection.
+//!
+//! h2 is written against tokio's IO traits; on async_std the socket is adapted
+//! with `tokio_util::compat`, so the protocol code is the same on both runtimes.
+
+use super::HELLO;
+use crate::harness::{drive, LoadReport};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use hyper::{Request, Response};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Spawns a background future on the runtime the scenario runs on.
+type Spawn = fn(BoxFuture<'static, ()>);
+
+/// Sends `requests` requests over one h2 connection, `streams` at a time, on tokio.
+pub fn tokio(streams: usize, requests: usize) -> LoadReport {
+    use tokio::net::{TcpListener, TcpStream};
+
+    let spawn: Spawn = |future| {
+        tokio::spawn(future);
+    };
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            socket.set_nodelay(true).unwrap();
+            serve(socket, streams).await;
+        });
+        let socket = TcpStream::connect(addr).await.unwrap();
+        socket.set_nodelay(true).unwrap();
+        request(socket, streams, requests, spawn).await
+    })
+}
+
+/// Sends `requests` requests over one h2 connection, `streams` at a time, on async_std.
+pub fn async_std(streams: usize, requests: usize) -> LoadReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+    use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+    let spawn: Spawn = |future| {
+        task::spawn(future);
+    };
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        task::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            socket.set_nodelay(true).unwrap();
+            serve(socket.compat(), streams).await;
+        });
+        let socket = TcpStream::connect(addr).await.unwrap();
+        socket.set_nodelay(true).unwrap();
+        request(socket.compat(), streams, requests, spawn).await
+    })
+}
+
+/// Answers every stream on the connection with a small body until the client goes away.
+async fn serve<T>(io: T, streams: usize)
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut connection = h2::server::Builder::new()
+        .max_concurrent_streams(streams as u32)
+        .handshake::<_, Bytes>(io)
+        .await
+        .unwrap();
+    while let Some(Ok((_, mut respond))) = connection.accept().await {
+        let mut body = respond.send_response(Response::new(()), false).unwrap();
+        body.send_data(Bytes::from_static(HELLO.as_bytes()), true)
+            .unwrap();
+    }
+}
+
+/// Sends `requests` GET requests as separate streams, at most `streams` at a time.
+async fn request<T>(io: T, streams: usize, requests: usize, spawn: Spawn) -> LoadReport
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (client, connection) = h2::client::handshake(io).await.unwrap();
+    spawn(Box::pin(async move {
+        let _ = connection.await;
+    }));
+    drive(streams, requests, || {
+        let client = client.clone();
+        async move {
+            let mut client = client.ready().await.unwrap();
+            let request = Request::get("http://localhost/").body(()).unwrap();
+            let (response, _) = client.send_request(request, true).unwrap();
+            let mut body = response.await.unwrap().into_body();
+            while let Some(chunk) = body.data().await {
+                let chunk = chunk.unwrap();
+                body.flow_control().release_capacity(chunk.len()).unwrap();
+            }
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokio_completes_streams() {
+        assert_eq!(tokio(4, 20).latencies.len(), 20);
+    }
+
+    #[test]
+    fn async_std_completes_streams() {
+        assert_eq!(async_std(4, 20).latencies.len(), 20);
+    }
+}
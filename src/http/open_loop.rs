@@ -0,0 +1,101 @@
+//! GET requests offered at a fixed rate with the generators from [`crate::open_loop`].
+//!
+//! Each request is its own task, so the clients open as many pooled
+//! connections as there are requests outstanding.
+
+use super::HELLO;
+use crate::open_loop::{self, OpenLoopReport, SustainedReport};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http_body_util::{BodyExt, Empty};
+use hyper::StatusCode;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Offers `requests` GET requests to `addr` at `rate` per second with the hyper client on tokio.
+pub fn hyper(addr: SocketAddr, rate: f64, requests: usize) -> OpenLoopReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(open_loop::tokio(rate, requests, hyper_get(addr)))
+}
+
+/// Offers `requests` GET requests to `addr` at `rate` per second with the
+/// hyper client on tokio, reporting the latencies of every `window` too.
+pub fn hyper_sustained(
+    addr: SocketAddr,
+    rate: f64,
+    requests: usize,
+    window: Duration,
+) -> SustainedReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(open_loop::tokio_sustained(
+        rate,
+        requests,
+        window,
+        hyper_get(addr),
+    ))
+}
+
+/// Makes GET requests to `addr` with one pooled hyper client.
+fn hyper_get(addr: SocketAddr) -> impl FnMut() -> BoxFuture<'static, ()> {
+    let uri: hyper::Uri = format!("http://{}/", addr).parse().unwrap();
+    let client = Client::builder(TokioExecutor::new()).build_http::<Empty<Bytes>>();
+    move || {
+        let client = client.clone();
+        let uri = uri.clone();
+        Box::pin(async move {
+            let response = client.get(uri).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, HELLO.as_bytes());
+        })
+    }
+}
+
+/// Offers `requests` GET requests to `addr` at `rate` per second with surf on async_std.
+pub fn surf(addr: SocketAddr, rate: f64, requests: usize) -> OpenLoopReport {
+    async_std::task::block_on(open_loop::async_std(rate, requests, surf_get(addr)))
+}
+
+/// Offers `requests` GET requests to `addr` at `rate` per second with surf
+/// on async_std, reporting the latencies of every `window` too.
+pub fn surf_sustained(
+    addr: SocketAddr,
+    rate: f64,
+    requests: usize,
+    window: Duration,
+) -> SustainedReport {
+    let run = open_loop::async_std_sustained(rate, requests, window, surf_get(addr));
+    async_std::task::block_on(run)
+}
+
+/// Makes GET requests to `addr` with one surf client.
+fn surf_get(addr: SocketAddr) -> impl FnMut() -> BoxFuture<'static, ()> {
+    let url = format!("http://{}/", addr);
+    let client = surf::Client::new();
+    move || {
+        let client = client.clone();
+        let url = url.clone();
+        Box::pin(async move {
+            let mut response = client.get(&url).await.unwrap();
+            assert_eq!(response.status(), surf::StatusCode::Ok);
+            assert_eq!(response.body_bytes().await.unwrap(), HELLO.as_bytes());
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::server;
+
+    #[test]
+    fn offered_requests_complete() {
+        let server = server::hyper();
+        for client in &[hyper, surf] {
+            let report = client(server.addr(), 1_000.0, 20);
+            assert_eq!(report.load.latencies.len(), 20);
+        }
+    }
+}
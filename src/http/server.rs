@@ -0,0 +1,158 @@
+//! Minimal "hello world" servers, one per framework, each running on its own runtime.
+
+use super::HELLO;
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::mpsc;
+use std::thread;
+
+/// A server running in the background of the benchmark process.
+///
+/// The server is stopped when the handle is dropped.
+pub struct Server {
+    addr: SocketAddr,
+    stop: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl Server {
+    pub(super) fn new(addr: SocketAddr, stop: impl FnOnce() + Send + 'static) -> Self {
+        Server {
+            addr,
+            stop: Some(Box::new(stop)),
+        }
+    }
+
+    /// Address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
+
+/// Binds a non-blocking listener to a free port on the loopback interface.
+pub(super) fn bind() -> (TcpListener, SocketAddr) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let addr = listener.local_addr().unwrap();
+    (listener, addr)
+}
+
+/// Starts a hyper server on a multi-threaded tokio runtime.
+pub fn hyper() -> Server {
+    let (listener, addr) = bind();
+    let rt = crate::metrics::runtime();
+    rt.spawn(async move {
+        let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            tokio::spawn(async move {
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service_fn(hello_hyper))
+                    .await;
+            });
+        }
+    });
+    Server::new(addr, move || rt.shutdown_background())
+}
+
+async fn hello_hyper(_: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    Ok(Response::new(Full::new(Bytes::from_static(
+        HELLO.as_bytes(),
+    ))))
+}
+
+/// Starts a tide server on the global async_std executor.
+///
+/// Connections are accepted by hand rather than with `app.listen` so that
+/// `TCP_NODELAY` can be set like for the other servers.
+pub fn tide() -> Server {
+    use async_std::task;
+
+    let (listener, addr) = bind();
+    let mut app = tide::new();
+    app.at("/").get(|_| async { Ok(HELLO) });
+    let accept = task::spawn(async move {
+        let listener = async_std::net::TcpListener::from(listener);
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            let app = app.clone();
+            task::spawn(async move {
+                let _ = async_h1::accept(stream, |request| {
+                    let app = app.clone();
+                    async move { app.respond(request).await }
+                })
+                .await;
+            });
+        }
+    });
+    Server::new(addr, move || {
+        task::block_on(accept.cancel());
+    })
+}
+
+/// Starts an actix-web server on its own actix system thread.
+pub fn actix_web() -> Server {
+    use actix_web::{web, App, HttpServer};
+
+    let (listener, addr) = bind();
+    let (handle_tx, handle_rx) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        actix_web::rt::System::new().block_on(async move {
+            let server =
+                HttpServer::new(|| App::new().route("/", web::get().to(|| async { HELLO })))
+                    .listen(listener)
+                    .unwrap()
+                    .run();
+            handle_tx.send(server.handle()).unwrap();
+            server.await
+        })
+    });
+    let handle = handle_rx.recv().unwrap();
+    Server::new(addr, move || {
+        futures::executor::block_on(handle.stop(false));
+        thread.join().unwrap().unwrap();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::load;
+
+    fn assert_serves(server: Server) {
+        let report = load::hammer(server.addr(), 2, 10);
+        assert_eq!(report.requests, 10);
+        assert_eq!(report.latencies.len(), 10);
+    }
+
+    #[test]
+    fn hyper_serves_requests() {
+        assert_serves(hyper());
+    }
+
+    #[test]
+    fn tide_serves_requests() {
+        assert_serves(tide());
+    }
+
+    #[test]
+    fn actix_web_serves_requests() {
+        assert_serves(actix_web());
+    }
+}
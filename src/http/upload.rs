@@ -0,0 +1,235 @@
+//! S3-style multipart uploads to an in-process object store.
+//!
+//! An upload is started with `POST /uploads`, which returns its id, its parts
+//! are sent with `PUT /uploads/{id}/{part}`, any number of them at a time, and
+//! it is finished with `POST /uploads/{id}/complete?parts={n}`, which checks
+//! that all parts arrived and answers with the object's size. The store runs
+//! on hyper and only counts the bytes of each part, so that memory does not
+//! limit the payload size. Clients split one payload into parts and send
+//! `concurrency` of them at a time with `buffer_unordered`.
+//!
+//! Latencies are the time each upload took from start to completion.
+
+use super::server::{bind, Server};
+use crate::harness::{latency_histogram, record, LoadReport};
+use bytes::Bytes;
+use futures::stream::{self, StreamExt};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Sizes of the parts received so far, by upload id and part number.
+#[derive(Default)]
+struct Uploads {
+    next_id: u64,
+    parts: HashMap<u64, BTreeMap<usize, usize>>,
+}
+
+/// Starts the object store on a multi-threaded tokio runtime.
+pub fn store() -> Server {
+    let (listener, addr) = bind();
+    let uploads = Arc::new(Mutex::new(Uploads::default()));
+    let rt = crate::metrics::runtime();
+    rt.spawn(async move {
+        let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            let uploads = uploads.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request| handle(uploads.clone(), request));
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+    Server::new(addr, move || rt.shutdown_background())
+}
+
+async fn handle(
+    uploads: Arc<Mutex<Uploads>>,
+    request: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let path: Vec<_> = request.uri().path().split('/').skip(1).collect();
+    let response = match (request.method(), path.as_slice()) {
+        (&Method::POST, ["uploads"]) => {
+            let mut uploads = uploads.lock().unwrap();
+            let id = uploads.next_id;
+            uploads.next_id += 1;
+            uploads.parts.insert(id, BTreeMap::new());
+            Response::new(Full::new(Bytes::from(id.to_string())))
+        }
+        (&Method::PUT, ["uploads", id, part]) => {
+            let (id, part) = (id.parse().unwrap(), part.parse().unwrap());
+            let mut body = request.into_body();
+            let mut size = 0;
+            while let Some(frame) = body.frame().await {
+                if let Some(data) = frame.unwrap().data_ref() {
+                    size += data.len();
+                }
+            }
+            match uploads.lock().unwrap().parts.get_mut(&id) {
+                Some(parts) => {
+                    parts.insert(part, size);
+                    Response::new(Full::default())
+                }
+                None => status(StatusCode::NOT_FOUND),
+            }
+        }
+        (&Method::POST, ["uploads", id, "complete"]) => {
+            let id = id.parse().unwrap();
+            let expected: usize = request
+                .uri()
+                .query()
+                .and_then(|query| query.strip_prefix("parts="))
+                .and_then(|parts| parts.parse().ok())
+                .unwrap_or(0);
+            match uploads.lock().unwrap().parts.remove(&id) {
+                Some(parts) if parts.keys().copied().eq(0..expected) => {
+                    let size: usize = parts.values().sum();
+                    Response::new(Full::new(Bytes::from(size.to_string())))
+                }
+                Some(_) => status(StatusCode::BAD_REQUEST),
+                None => status(StatusCode::NOT_FOUND),
+            }
+        }
+        _ => status(StatusCode::NOT_FOUND),
+    };
+    Ok(response)
+}
+
+fn status(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::default());
+    *response.status_mut() = status;
+    response
+}
+
+/// Uploads a payload of `size` bytes `uploads` times to the store at `addr`
+/// with reqwest on tokio, in parts of `part` bytes, `concurrency` at a time.
+pub fn reqwest(
+    addr: SocketAddr,
+    size: usize,
+    part: usize,
+    concurrency: usize,
+    uploads: usize,
+) -> LoadReport {
+    let client = reqwest::Client::new();
+    let payload = Bytes::from(vec![7; size]);
+    let base = format!("http://{}/uploads", addr);
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let start = Instant::now();
+        let mut latencies = latency_histogram();
+        for _ in 0..uploads {
+            let started = Instant::now();
+            let response = client.post(&base).send().await.unwrap();
+            let id = response.text().await.unwrap();
+            let parts = (0..size).step_by(part).enumerate().map(|(i, offset)| {
+                let url = format!("{}/{}/{}", base, id, i);
+                let body = payload.slice(offset..size.min(offset + part));
+                let client = &client;
+                async move {
+                    let response = client.put(&url).body(body).send().await.unwrap();
+                    assert_eq!(response.status(), StatusCode::OK);
+                }
+            });
+            let count = stream::iter(parts)
+                .buffer_unordered(concurrency)
+                .count()
+                .await;
+            let url = format!("{}/{}/complete?parts={}", base, id, count);
+            let response = client.post(&url).send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.text().await.unwrap(), size.to_string());
+            record(&mut latencies, started.elapsed());
+        }
+        LoadReport::since(start, uploads, latencies).with_bytes((size * uploads) as u64)
+    })
+}
+
+/// Uploads a payload of `size` bytes `uploads` times to the store at `addr`
+/// with surf on async_std, in parts of `part` bytes, `concurrency` at a time.
+pub fn surf(
+    addr: SocketAddr,
+    size: usize,
+    part: usize,
+    concurrency: usize,
+    uploads: usize,
+) -> LoadReport {
+    let client = surf::Client::new();
+    let payload = vec![7; size];
+    let base = format!("http://{}/uploads", addr);
+    async_std::task::block_on(async {
+        let start = Instant::now();
+        let mut latencies = latency_histogram();
+        for _ in 0..uploads {
+            let started = Instant::now();
+            let id = client.post(&base).recv_string().await.unwrap();
+            let parts = payload.chunks(part).enumerate().map(|(i, chunk)| {
+                let url = format!("{}/{}/{}", base, id, i);
+                // surf bodies own their bytes, so every part is copied once.
+                let body = surf::Body::from_bytes(chunk.to_vec());
+                let client = &client;
+                async move {
+                    let response = client.put(&url).body(body).await.unwrap();
+                    assert_eq!(response.status(), surf::StatusCode::Ok);
+                }
+            });
+            let count = stream::iter(parts)
+                .buffer_unordered(concurrency)
+                .count()
+                .await;
+            let url = format!("{}/{}/complete?parts={}", base, id, count);
+            let mut response = client.post(&url).await.unwrap();
+            assert_eq!(response.status(), surf::StatusCode::Ok);
+            assert_eq!(response.body_string().await.unwrap(), size.to_string());
+            record(&mut latencies, started.elapsed());
+        }
+        LoadReport::since(start, uploads, latencies).with_bytes((size * uploads) as u64)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uploads_complete_with_every_part() {
+        let store = store();
+        for client in &[reqwest, surf] {
+            let report = client(store.addr(), 100_000, 30_000, 2, 3);
+            assert_eq!(report.latencies.len(), 3);
+            assert_eq!(report.bytes, 300_000);
+        }
+    }
+
+    #[test]
+    fn missing_parts_fail_the_upload() {
+        let store = store();
+        let base = format!("http://{}/uploads", store.addr());
+        let agent = ureq::Agent::new_with_defaults();
+        let id = agent.post(&base).send_empty().unwrap();
+        let id = id.into_body().read_to_string().unwrap();
+        agent
+            .put(&format!("{}/{}/1", base, id))
+            .send("part")
+            .unwrap();
+        let complete = agent
+            .post(&format!("{}/{}/complete?parts=2", base, id))
+            .config()
+            .http_status_as_error(false)
+            .build()
+            .send_empty()
+            .unwrap();
+        assert_eq!(complete.status(), StatusCode::BAD_REQUEST);
+    }
+}
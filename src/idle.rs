@@ -0,0 +1,239 @@
+//! Many mostly idle connections kept alive with periodic pings, the C10K question.
+//!
+//! Every connection sends a one-byte ping once per interval and waits for the
+//! echo; the first pings are spread evenly over the first interval, so the
+//! server sees a steady trickle rather than bursts. Both ends of every
+//! connection live in the benchmark process, which needs two file descriptors
+//! per connection: raise `ulimit -n` before running with many connections.
+//!
+//! Memory per connection is the growth of the resident set between just
+//! before connecting and the end of the run, while all connections are still
+//! open, divided by the number of connections. It covers a client and a
+//! server task with their sockets and buffers; every client records its pings
+//! into a buffer of its own made before connecting, and the buffers are merged
+//! into one histogram once the clients are done. Memory freed by an earlier
+//! scenario in the same process can be reused without growing the resident
+//! set, so filter the run down to a single scenario for the most reliable
+//! figure.
+
+use crate::harness::{latency_histogram, record, resident_memory, LoadReport};
+use futures::stream::{self, StreamExt};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Number of connections being opened at the same time.
+const CONNECTING: usize = 128;
+
+/// A load report of the pings together with the memory each connection held.
+pub struct IdleReport {
+    pub load: LoadReport,
+    /// Growth of the resident set per connection in bytes, or `None` if the
+    /// resident set size is not available on this platform.
+    pub memory_per_connection: Option<u64>,
+}
+
+impl IdleReport {
+    fn new(load: LoadReport, before: Option<u64>, connections: usize) -> Self {
+        let memory_per_connection = before
+            .zip(resident_memory())
+            .map(|(before, after)| after.saturating_sub(before) / connections as u64);
+        IdleReport {
+            load,
+            memory_per_connection,
+        }
+    }
+}
+
+impl fmt::Display for IdleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.load)?;
+        match self.memory_per_connection {
+            Some(bytes) => write!(f, "  {:>6.1} KiB/conn", bytes as f64 / 1024.0),
+            None => write!(f, "  memory n/a"),
+        }
+    }
+}
+
+/// When the `i`-th of `connections` connections sends its first ping.
+fn first_ping(start: Instant, i: usize, connections: usize, interval: Duration) -> Instant {
+    start + interval.mul_f64(i as f64 / connections as f64)
+}
+
+/// Holds `connections` connections on tokio, each pinging `rounds` times every `interval`.
+pub fn tokio(connections: usize, rounds: usize, interval: Duration) -> IdleReport {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                tokio::spawn(async move {
+                    let mut byte = [0];
+                    while stream.read_exact(&mut byte).await.is_ok() {
+                        if stream.write_all(&byte).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        // Allocated up front, so that the growth only covers connections.
+        let mut latencies = latency_histogram();
+        let pings: Vec<Vec<Duration>> = (0..connections)
+            .map(|_| Vec::with_capacity(rounds))
+            .collect();
+        let before = resident_memory();
+        let streams: Vec<_> = stream::iter(0..connections)
+            .map(|_| async {
+                let stream = TcpStream::connect(addr).await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                stream
+            })
+            .buffer_unordered(CONNECTING)
+            .collect()
+            .await;
+
+        let start = Instant::now();
+        let clients: Vec<_> = streams
+            .into_iter()
+            .zip(pings)
+            .enumerate()
+            .map(|(i, (mut stream, mut pings))| {
+                let mut next = first_ping(start, i, connections, interval);
+                tokio::spawn(async move {
+                    for _ in 0..rounds {
+                        tokio::time::sleep_until(next.into()).await;
+                        let sent = Instant::now();
+                        stream.write_all(b"!").await.unwrap();
+                        stream.read_exact(&mut [0]).await.unwrap();
+                        pings.push(sent.elapsed());
+                        next += interval;
+                    }
+                    (stream, pings)
+                })
+            })
+            .collect();
+        let mut streams = Vec::with_capacity(connections);
+        for client in clients {
+            let (stream, pings) = client.await.unwrap();
+            for latency in pings {
+                record(&mut latencies, latency);
+            }
+            streams.push(stream);
+        }
+        report(start, latencies, before, connections)
+    })
+}
+
+/// Holds `connections` connections on async_std, each pinging `rounds` times every `interval`.
+pub fn async_std(connections: usize, rounds: usize, interval: Duration) -> IdleReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept = task::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                task::spawn(async move {
+                    let mut byte = [0];
+                    while stream.read_exact(&mut byte).await.is_ok() {
+                        if stream.write_all(&byte).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        // Allocated up front, so that the growth only covers connections.
+        let mut latencies = latency_histogram();
+        let pings: Vec<Vec<Duration>> = (0..connections)
+            .map(|_| Vec::with_capacity(rounds))
+            .collect();
+        let before = resident_memory();
+        let streams: Vec<_> = stream::iter(0..connections)
+            .map(|_| async {
+                let stream = TcpStream::connect(addr).await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                stream
+            })
+            .buffer_unordered(CONNECTING)
+            .collect()
+            .await;
+
+        let start = Instant::now();
+        let clients: Vec<_> = streams
+            .into_iter()
+            .zip(pings)
+            .enumerate()
+            .map(|(i, (mut stream, mut pings))| {
+                let mut next = first_ping(start, i, connections, interval);
+                task::spawn(async move {
+                    for _ in 0..rounds {
+                        task::sleep(next.saturating_duration_since(Instant::now())).await;
+                        let sent = Instant::now();
+                        stream.write_all(b"!").await.unwrap();
+                        stream.read_exact(&mut [0]).await.unwrap();
+                        pings.push(sent.elapsed());
+                        next += interval;
+                    }
+                    (stream, pings)
+                })
+            })
+            .collect();
+        let mut streams = Vec::with_capacity(connections);
+        for client in clients {
+            let (stream, pings) = client.await;
+            for latency in pings {
+                record(&mut latencies, latency);
+            }
+            streams.push(stream);
+        }
+        let report = report(start, latencies, before, connections);
+        accept.cancel().await;
+        report
+    })
+}
+
+/// Builds the report while the connections are still open.
+fn report(
+    start: Instant,
+    latencies: Histogram<u64>,
+    before: Option<u64>,
+    connections: usize,
+) -> IdleReport {
+    let pings = latencies.len() as usize;
+    IdleReport::new(
+        LoadReport::since(start, pings, latencies),
+        before,
+        connections,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokio_pings_every_connection() {
+        let report = tokio(50, 2, Duration::from_millis(10));
+        assert_eq!(report.load.requests, 100);
+    }
+
+    #[test]
+    fn async_std_pings_every_connection() {
+        let report = async_std(50, 2, Duration::from_millis(10));
+        assert_eq!(report.load.requests, 100);
+    }
+}
@@ -0,0 +1,135 @@
+//! Polling the same future as its concrete type, erased into a
+//! `Pin<Box<dyn Future>>`, and erased and sent through a channel of boxed
+//! futures to the task that awaits them, on tokio, async_std and smol.
+//!
+//! Every future returns pending once, waking itself, and is ready with its
+//! index on the second poll, so each is polled twice through the runtime's
+//! waker. A concrete future is awaited in place without an allocation and
+//! its polls can be inlined; a boxed one costs an allocation and a call
+//! through its vtable per poll; the channel is the queue of jobs a service
+//! hands to a worker, adding a send and a receive per future, with another
+//! task boxing and sending them. The cost is per future.
+
+use super::StepReport;
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::hint::black_box;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+/// Capacity of the channel of boxed futures.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// How the futures are awaited.
+#[derive(Clone, Copy, Debug)]
+pub enum Erasure {
+    /// As their concrete type.
+    Concrete,
+    /// As a `Pin<Box<dyn Future>>`.
+    Boxed,
+    /// As a `Pin<Box<dyn Future>>` received from a channel.
+    Channel,
+}
+
+type Job = Pin<Box<dyn Future<Output = u64> + Send>>;
+
+/// A future that is ready on its second poll.
+struct Step(bool);
+
+impl Future for Step {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+async fn work(i: u64) -> u64 {
+    Step(false).await;
+    black_box(i)
+}
+
+/// Awaits `futures` futures as `erasure` says, spawning the task that sends
+/// them through the channel with `spawn`.
+pub(crate) async fn polling<S>(erasure: Erasure, futures: usize, spawn: S) -> StepReport
+where
+    S: FnOnce(BoxFuture<'static, ()>),
+{
+    let start = Instant::now();
+    let mut sum = 0;
+    match erasure {
+        Erasure::Concrete => {
+            for i in 0..futures as u64 {
+                sum += work(i).await;
+            }
+        }
+        Erasure::Boxed => {
+            for i in 0..futures as u64 {
+                // Hides the type behind the box from the compiler.
+                let job: Job = black_box(Box::pin(work(i)));
+                sum += job.await;
+            }
+        }
+        Erasure::Channel => {
+            let (jobs, queued) = async_channel::bounded::<Job>(QUEUE_CAPACITY);
+            spawn(Box::pin(async move {
+                for i in 0..futures as u64 {
+                    jobs.send(Box::pin(work(i))).await.unwrap();
+                }
+            }));
+            while let Ok(job) = queued.recv().await {
+                sum += job.await;
+            }
+        }
+    }
+    let futures = futures as u64;
+    assert_eq!(sum, futures * futures.saturating_sub(1) / 2, "lost futures");
+    StepReport::new(futures as usize, start.elapsed())
+}
+
+/// Awaits `futures` futures in a tokio task.
+pub fn tokio(erasure: Erasure, futures: usize) -> StepReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let run = polling(erasure, futures, |producer| {
+            tokio::spawn(producer);
+        });
+        tokio::spawn(run).await.unwrap()
+    })
+}
+
+/// Awaits `futures` futures in an async_std task.
+pub fn async_std(erasure: Erasure, futures: usize) -> StepReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(polling(erasure, futures, |producer| {
+        task::spawn(producer);
+    })))
+}
+
+/// Awaits `futures` futures in a smol task.
+pub fn smol(erasure: Erasure, futures: usize) -> StepReport {
+    smol::block_on(smol::spawn(polling(erasure, futures, |producer| {
+        smol::spawn(producer).detach();
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_future_is_awaited() {
+        for run in [tokio, async_std, smol] {
+            for erasure in [Erasure::Concrete, Erasure::Boxed, Erasure::Channel] {
+                assert_eq!(run(erasure, 5_000).steps, 5_000);
+            }
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Indirection in futures: what boxing, recursion and dynamic dispatch add
+//! to polling them on each runtime.
+
+pub mod boxed;
+pub mod recursion;
+pub mod sizes;
+pub mod traits;
+
+use std::fmt;
+use std::time::Duration;
+
+/// How long a number of steps through a future took.
+pub struct StepReport {
+    pub steps: u64,
+    pub elapsed: Duration,
+}
+
+impl StepReport {
+    fn new(steps: usize, elapsed: Duration) -> Self {
+        StepReport {
+            steps: steps as u64,
+            elapsed,
+        }
+    }
+}
+
+impl fmt::Display for StepReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.elapsed.as_secs_f64();
+        write!(
+            f,
+            "{:>8.2} ns/step  {:>12.0} steps/s",
+            seconds * 1e9 / self.steps as f64,
+            self.steps as f64 / seconds
+        )
+    }
+}
@@ -0,0 +1,116 @@
+//! A recursive async function, boxed at every level, against a loop doing
+//! the same steps in one future, on tokio, async_std and smol.
+//!
+//! Every descent goes `depth` levels down and yields once with the runtime's
+//! `yield_now` at the bottom before counting the levels on the way back up.
+//! A recursive async function must box the future of its call to itself, so
+//! every level allocates a future and every poll of the descent goes through
+//! as many boxed futures as there are levels; the yield makes the runtime
+//! poll that whole chain again. The loop counts the same levels in a single
+//! future that yields once. The cost is per level.
+
+use super::StepReport;
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::hint::black_box;
+use std::time::Instant;
+
+/// How a descent goes down its levels.
+#[derive(Clone, Copy, Debug)]
+pub enum Descent {
+    /// A boxed future per level.
+    Recursive,
+    /// A loop in one future.
+    Iterative,
+}
+
+fn recursive<F, Fut>(depth: u32, yield_now: F) -> BoxFuture<'static, u32>
+where
+    F: Fn() -> Fut + Copy + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    Box::pin(async move {
+        if depth == 0 {
+            yield_now().await;
+            return 0;
+        }
+        recursive(depth - 1, yield_now).await + 1
+    })
+}
+
+async fn iterative<F, Fut>(depth: u32, yield_now: F) -> u32
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut levels = 0;
+    for _ in 0..depth {
+        levels = black_box(levels) + 1;
+    }
+    yield_now().await;
+    levels
+}
+
+/// Makes as many descents of `depth` levels as `levels` levels take,
+/// yielding with `yield_now` at the bottom of each.
+pub(crate) async fn descending<F, Fut>(
+    descent: Descent,
+    depth: u32,
+    levels: usize,
+    yield_now: F,
+) -> StepReport
+where
+    F: Fn() -> Fut + Copy + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let descents = levels / depth as usize;
+    let start = Instant::now();
+    for _ in 0..descents {
+        let reached = match descent {
+            Descent::Recursive => recursive(depth, yield_now).await,
+            Descent::Iterative => iterative(depth, yield_now).await,
+        };
+        assert_eq!(reached, depth);
+    }
+    StepReport::new(descents * depth as usize, start.elapsed())
+}
+
+/// Descends in a tokio task.
+pub fn tokio(descent: Descent, depth: u32, levels: usize) -> StepReport {
+    use tokio::task::yield_now;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        tokio::spawn(descending(descent, depth, levels, yield_now))
+            .await
+            .unwrap()
+    })
+}
+
+/// Descends in an async_std task.
+pub fn async_std(descent: Descent, depth: u32, levels: usize) -> StepReport {
+    use async_std::task::{self, yield_now};
+
+    task::block_on(task::spawn(descending(descent, depth, levels, yield_now)))
+}
+
+/// Descends in a smol task.
+pub fn smol(descent: Descent, depth: u32, levels: usize) -> StepReport {
+    use smol::future::yield_now;
+
+    smol::block_on(smol::spawn(descending(descent, depth, levels, yield_now)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_descent_reaches_the_bottom() {
+        for run in [tokio, async_std, smol] {
+            for descent in [Descent::Recursive, Descent::Iterative] {
+                assert_eq!(run(descent, 10, 1_005).steps, 1_000);
+            }
+        }
+    }
+}
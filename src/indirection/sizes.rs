@@ -0,0 +1,156 @@
+//! The sizes of the futures that scenarios spawn as their tasks, built with
+//! the pieces of each runtime but never polled.
+//!
+//! A future holds everything it keeps across its awaits, including the
+//! futures it awaits, so the `yield_now` and sleep futures of a runtime end
+//! up inside every task built on them, together with whatever they keep
+//! inline rather than in the runtime. A future is moved into its task when
+//! it is spawned, so its size is copied on every spawn and allocated for the
+//! task. Only the tasks of scenarios that are built from runtime generic
+//! functions can be listed here; the others are async blocks inside the
+//! functions that run them.
+
+use crate::indirection::boxed::{self, Erasure};
+use crate::indirection::recursion::{self, Descent};
+use crate::tasks;
+use crate::timers;
+use crate::workloads::cache::{self, Mix};
+use crate::workloads::ledger::{self, Ledger, Shared};
+use crate::workloads::priority::{self, Flood};
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The size of a future, in bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct FutureSize(pub usize);
+
+impl fmt::Display for FutureSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>8} bytes", self.0)
+    }
+}
+
+fn size_of<F: Future>(future: F) -> FutureSize {
+    FutureSize(std::mem::size_of_val(&future))
+}
+
+/// The futures of the tasks on one runtime, named by scenario and task, as
+/// built with `$yield_now`, `$sleep` and `$spawn`.
+macro_rules! sizes_on {
+    ($runtime:literal, $yield_now:expr, $sleep:expr, $spawn:expr) => {{
+        let stop = Arc::new(AtomicBool::new(false));
+        let alive = crate::harness::Alive::default();
+        let (high, high_received) = async_channel::unbounded();
+        let (_, low_received) = async_channel::unbounded();
+        let flood = Flood {
+            workers: 1,
+            backlog: 1,
+            work: Duration::ZERO,
+            high: 1,
+            interval: Duration::ZERO,
+        };
+        let mix = Mix {
+            tasks: 1,
+            keys: 1,
+            writes: 0.0,
+            operations: 1,
+        };
+        let cache = Arc::new(dashmap::DashMap::<u64, u64>::new());
+        let (ledger, _) = Shared::open(1);
+        let sizes = vec![
+            (
+                "tasks_neighbor",
+                size_of(tasks::neighbor(stop.clone(), $yield_now)),
+            ),
+            (
+                "yield_timed",
+                size_of(tasks::yield_now::timed(1, $yield_now)),
+            ),
+            (
+                "abort_in_flight",
+                size_of(tasks::abort::in_flight(alive.guard(), $sleep)),
+            ),
+            (
+                "timers_busy",
+                size_of(timers::busy(stop.clone(), Duration::ZERO, $yield_now)),
+            ),
+            (
+                "priority_working",
+                size_of(priority::working(
+                    high_received,
+                    low_received,
+                    Duration::ZERO,
+                    $yield_now,
+                )),
+            ),
+            (
+                "priority_trickling",
+                size_of(priority::trickling(high, flood, stop, $sleep)),
+            ),
+            (
+                "cache_operate",
+                size_of(cache::operate(cache, mix, 1, 1, $yield_now)),
+            ),
+            (
+                "ledger_transferring",
+                size_of(ledger::transferring(Arc::new(ledger), 1, 1, 1, $yield_now)),
+            ),
+            (
+                "recursion_descending",
+                size_of(recursion::descending(Descent::Recursive, 1, 1, $yield_now)),
+            ),
+            (
+                "boxed_polling",
+                size_of(boxed::polling(Erasure::Channel, 1, $spawn)),
+            ),
+        ];
+        sizes
+            .into_iter()
+            .map(|(task, size)| (format!("{}_{}", task, $runtime), size))
+            .collect::<Vec<_>>()
+    }};
+}
+
+/// The sizes of the tasks on tokio, async_std and smol.
+pub fn all() -> Vec<(String, FutureSize)> {
+    let mut sizes = sizes_on!(
+        "tokio",
+        tokio::task::yield_now,
+        tokio::time::sleep,
+        |producer| {
+            tokio::spawn(producer);
+        }
+    );
+    sizes.extend(sizes_on!(
+        "async_std",
+        async_std::task::yield_now,
+        async_std::task::sleep,
+        |producer| {
+            async_std::task::spawn(producer);
+        }
+    ));
+    sizes.extend(sizes_on!(
+        "smol",
+        smol::future::yield_now,
+        |duration| async move {
+            smol::Timer::after(duration).await;
+        },
+        |producer| smol::spawn(producer).detach()
+    ));
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_task_is_listed_on_every_runtime() {
+        let sizes = all();
+        assert_eq!(sizes.len(), 30);
+        assert!(sizes.iter().all(|(_, size)| size.0 > 0));
+    }
+}
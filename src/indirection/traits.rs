@@ -0,0 +1,135 @@
+//! Calling a small async method through an `#[async_trait]` trait object,
+//! through a native async fn in a trait and directly on the concrete type,
+//! on tokio, async_std and smol.
+//!
+//! The method looks a key up in a table and never waits, so the calls only
+//! differ in how they are dispatched. A direct call can be inlined into the
+//! calling future; a native async fn in a trait is monomorphized for the
+//! type like any generic, so it can be inlined just the same; async-trait
+//! returns a `Pin<Box<dyn Future>>`, so every call allocates a future and
+//! polls it through a vtable, after calling the method itself through the
+//! vtable of the trait object. The cost is per call.
+
+use super::StepReport;
+use std::future::Future;
+use std::hint::black_box;
+use std::time::Instant;
+
+/// Number of entries in the table.
+const TABLE_SIZE: u64 = 1024;
+
+/// How the method is called.
+#[derive(Clone, Copy, Debug)]
+pub enum Dispatch {
+    /// On the concrete type.
+    Concrete,
+    /// Through a generic bound on a trait with a native async fn.
+    Native,
+    /// Through a `&dyn` of an `#[async_trait]` trait.
+    AsyncTrait,
+}
+
+/// A table of values to look keys up in.
+struct Table(Vec<u64>);
+
+impl Table {
+    fn new() -> Self {
+        Table((0..TABLE_SIZE).collect())
+    }
+
+    async fn get(&self, key: u64) -> u64 {
+        self.0[(key % TABLE_SIZE) as usize]
+    }
+}
+
+/// Looking keys up with a native async fn in the trait.
+trait Lookup {
+    fn get(&self, key: u64) -> impl Future<Output = u64> + Send;
+}
+
+impl Lookup for Table {
+    async fn get(&self, key: u64) -> u64 {
+        Table::get(self, key).await
+    }
+}
+
+/// Looking keys up with a boxed future from async-trait.
+#[async_trait::async_trait]
+trait BoxedLookup {
+    async fn get(&self, key: u64) -> u64;
+}
+
+#[async_trait::async_trait]
+impl BoxedLookup for Table {
+    async fn get(&self, key: u64) -> u64 {
+        Table::get(self, key).await
+    }
+}
+
+async fn native<L: Lookup>(table: &L, calls: usize) -> u64 {
+    let mut sum = 0;
+    for key in 0..calls as u64 {
+        sum += table.get(black_box(key)).await;
+    }
+    sum
+}
+
+/// Makes `calls` calls as `dispatch` says.
+async fn calling(dispatch: Dispatch, calls: usize) -> StepReport {
+    let table = Table::new();
+    let start = Instant::now();
+    let sum = match dispatch {
+        Dispatch::Concrete => {
+            let mut sum = 0;
+            for key in 0..calls as u64 {
+                sum += table.get(black_box(key)).await;
+            }
+            sum
+        }
+        Dispatch::Native => native(&table, calls).await,
+        Dispatch::AsyncTrait => {
+            let table: &(dyn BoxedLookup + Sync) = black_box(&table);
+            let mut sum = 0;
+            for key in 0..calls as u64 {
+                sum += table.get(black_box(key)).await;
+            }
+            sum
+        }
+    };
+    let elapsed = start.elapsed();
+    let expected: u64 = (0..calls as u64).map(|key| key % TABLE_SIZE).sum();
+    assert_eq!(sum, expected, "lost calls");
+    StepReport::new(calls, elapsed)
+}
+
+/// Makes the calls in a tokio task.
+pub fn tokio(dispatch: Dispatch, calls: usize) -> StepReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async { tokio::spawn(calling(dispatch, calls)).await.unwrap() })
+}
+
+/// Makes the calls in an async_std task.
+pub fn async_std(dispatch: Dispatch, calls: usize) -> StepReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(calling(dispatch, calls)))
+}
+
+/// Makes the calls in a smol task.
+pub fn smol(dispatch: Dispatch, calls: usize) -> StepReport {
+    smol::block_on(smol::spawn(calling(dispatch, calls)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_dispatch_makes_every_call() {
+        for run in [tokio, async_std, smol] {
+            for dispatch in [Dispatch::Concrete, Dispatch::Native, Dispatch::AsyncTrait] {
+                assert_eq!(run(dispatch, 5_000).steps, 5_000);
+            }
+        }
+    }
+}
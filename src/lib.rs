@@ -1,3 +1,66 @@
+//! Workloads shared by the benchmarks in `benches/`.
+
+mod affinity;
+pub mod alloc;
+pub mod backpressure;
+pub mod breaker;
+pub mod builds;
+#[cfg(target_os = "linux")]
+mod cgroup;
+pub mod channels;
+pub mod chat;
+pub mod codec;
+pub mod combinators;
+pub mod compat;
+pub mod compress;
+pub mod config;
+pub mod dashboard;
+pub mod dns;
+mod energy;
+mod expect;
+mod exporter;
+pub mod fanout;
+pub mod faults;
+pub mod footprint;
+pub mod framing;
+pub mod graph;
+pub mod harness;
+pub mod hedge;
+pub mod http;
+pub mod idle;
+pub mod indirection;
+pub mod metrics;
+mod numa;
+pub mod open_loop;
+mod perf;
+pub mod phases;
+pub mod pipeline;
+pub mod polls;
+pub mod pool;
+pub mod preflight;
+mod priority;
+pub mod proxy;
+pub mod pubsub;
+pub mod quic;
+pub mod ratelimit;
+pub mod remote;
+mod repeat;
+pub mod results;
+pub mod retry;
+pub mod rpc;
+pub mod rtt;
+mod scaling;
+pub mod sim;
+mod soak;
+pub mod sync;
+mod syscalls;
+pub mod tasks;
+pub mod timers;
+pub mod tls;
+pub mod wan;
+pub mod workloads;
+pub mod ws;
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -0,0 +1,164 @@
+//! tokio's runtime metrics, sampled while a scenario runs, to show why tokio
+//! scenarios differ and not just that they do.
+//!
+//! Scenarios build their tokio runtimes with [`runtime`], which registers the
+//! runtime for sampling. While a [`Runner`](crate::harness::Runner) runs a
+//! scenario, a thread samples every registered runtime every millisecond:
+//! the steals and parks of all its workers so far, and the depths of its
+//! global queue, where tasks spawned from outside its workers wait, and of
+//! its blocking pool's queue. Steals, parks and the blocking queue are only
+//! counted by tokio built with `--cfg tokio_unstable`; without it nothing is
+//! registered and nothing is reported.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::{Builder, Handle, Runtime};
+
+/// Runtimes built since the sampling last started.
+static RUNTIMES: Mutex<Vec<Handle>> = Mutex::new(Vec::new());
+
+/// Builds a multi-threaded tokio runtime with every driver, the same as
+/// `Runtime::new`, and registers it for sampling. Its threads are pinned to
+/// cores if `PIN_WORKERS` is set.
+pub fn runtime() -> Runtime {
+    let mut builder = Builder::new_multi_thread();
+    builder.enable_all();
+    build(&mut builder)
+}
+
+/// Builds the runtime `builder` is set up for the way [`runtime`] builds
+/// its own: pinned to cores if `PIN_WORKERS` is set, and registered for
+/// sampling.
+pub(crate) fn build(builder: &mut Builder) -> Runtime {
+    if let Some(pin) = crate::affinity::pinning() {
+        builder.on_thread_start(pin);
+    }
+    let rt = builder.build().unwrap();
+    if cfg!(tokio_unstable) {
+        RUNTIMES.lock().unwrap().push(rt.handle().clone());
+    }
+    rt
+}
+
+/// What the runtimes of a scenario did, summed over the runtimes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Summary {
+    pub(crate) steals: u64,
+    pub(crate) parks: u64,
+    /// Deepest the global queue of a runtime got.
+    pub(crate) global_queue: usize,
+    /// Deepest the blocking pool's queue of a runtime got.
+    pub(crate) blocking_queue: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "steals {:>9}  parks {:>9}  global queue max {:>7}  blocking queue max {:>7}",
+            self.steals, self.parks, self.global_queue, self.blocking_queue
+        )
+    }
+}
+
+#[cfg(tokio_unstable)]
+fn sample(handle: &Handle, seen: &mut Summary) {
+    let metrics = handle.metrics();
+    let workers = 0..metrics.num_workers();
+    seen.steals = workers
+        .clone()
+        .map(|worker| metrics.worker_steal_count(worker))
+        .sum();
+    seen.parks = workers
+        .map(|worker| metrics.worker_park_count(worker))
+        .sum();
+    seen.global_queue = seen.global_queue.max(metrics.global_queue_depth());
+    seen.blocking_queue = seen.blocking_queue.max(metrics.blocking_queue_depth());
+}
+
+#[cfg(not(tokio_unstable))]
+fn sample(_handle: &Handle, _seen: &mut Summary) {}
+
+/// Samples the runtimes registered until stopped, on a thread of its own.
+pub(crate) struct Sampler {
+    stop: Arc<AtomicBool>,
+    sampler: thread::JoinHandle<Option<Summary>>,
+}
+
+impl Sampler {
+    /// Forgets the runtimes registered so far and starts sampling the ones
+    /// registered from now on.
+    pub(crate) fn start() -> Self {
+        RUNTIMES.lock().unwrap().clear();
+        let stop = Arc::new(AtomicBool::new(false));
+        let sampling = stop.clone();
+        let sampler = thread::spawn(move || {
+            let mut seen: Vec<Summary> = Vec::new();
+            loop {
+                let stopping = sampling.load(Ordering::Relaxed);
+                let runtimes = RUNTIMES.lock().unwrap().clone();
+                seen.resize(runtimes.len(), Summary::default());
+                for (handle, seen) in runtimes.iter().zip(&mut seen) {
+                    sample(handle, seen);
+                }
+                if stopping {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            if seen.is_empty() {
+                return None;
+            }
+            Some(seen.iter().fold(Summary::default(), |sum, seen| Summary {
+                steals: sum.steals + seen.steals,
+                parks: sum.parks + seen.parks,
+                global_queue: sum.global_queue.max(seen.global_queue),
+                blocking_queue: sum.blocking_queue.max(seen.blocking_queue),
+            }))
+        });
+        Sampler { stop, sampler }
+    }
+
+    /// Stops sampling and sums up what the runtimes did, or returns `None`
+    /// if no runtime was registered.
+    pub(crate) fn stop(self) -> Option<Summary> {
+        self.stop.store(true, Ordering::Relaxed);
+        let summary = self.sampler.join().unwrap();
+        RUNTIMES.lock().unwrap().clear();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_shows_every_figure() {
+        let summary = Summary {
+            steals: 12,
+            parks: 3,
+            global_queue: 40,
+            blocking_queue: 0,
+        };
+        assert_eq!(
+            summary.to_string(),
+            "steals        12  parks         3  global queue max      40  blocking queue max       0"
+        );
+    }
+
+    #[test]
+    fn samples_registered_runtimes() {
+        // Without tokio_unstable nothing is registered, runtimes or not.
+        if !cfg!(tokio_unstable) {
+            assert_eq!(Sampler::start().stop(), None);
+        }
+        let sampler = Sampler::start();
+        let rt = runtime();
+        rt.block_on(tokio::task::yield_now());
+        assert_eq!(sampler.stop().is_some(), cfg!(tokio_unstable));
+    }
+}
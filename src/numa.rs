@@ -0,0 +1,77 @@
+//! The NUMA nodes of the machine and where to put the workers on them, for
+//! comparing a benchmark confined to one node with the same number of cores
+//! spread over all of them.
+//!
+//! On a machine with more than one node, memory is attached to a node, and
+//! reaching the memory of another node, or a cache line last written there,
+//! costs more than reaching one's own. Workers confined to one node keep
+//! their tasks, their queues and the memory they touch first on it; spread
+//! over the nodes, every steal and wakeup may cross between them.
+
+use std::fs;
+
+/// The cores of every NUMA node, read from sysfs, or none where that is not
+/// available.
+pub(crate) fn nodes() -> Vec<Vec<usize>> {
+    let Ok(entries) = fs::read_dir("/sys/devices/system/node") else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<(usize, Vec<usize>)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let node = name.strip_prefix("node")?.parse().ok()?;
+            let cores = fs::read_to_string(entry.path().join("cpulist")).ok()?;
+            Some((node, cpu_list(&cores)?))
+        })
+        .filter(|(_, cores)| !cores.is_empty())
+        .collect();
+    nodes.sort();
+    nodes.into_iter().map(|(_, cores)| cores).collect()
+}
+
+/// Parses a list of cores in the kernel's format, like `0-3,8-11`.
+fn cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cores = Vec::new();
+    for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+        match range.split_once('-') {
+            Some((first, last)) => {
+                cores.extend(first.parse::<usize>().ok()?..=last.parse().ok()?);
+            }
+            None => cores.push(range.parse().ok()?),
+        }
+    }
+    Some(cores)
+}
+
+/// As many cores as the smallest node has, all on the first node, and the
+/// same number taken from every node in turn.
+pub(crate) fn placements(nodes: &[Vec<usize>]) -> (Vec<usize>, Vec<usize>) {
+    let count = nodes.iter().map(Vec::len).min().unwrap_or(0);
+    let confined = nodes
+        .first()
+        .map_or(Vec::new(), |node| node[..count].to_vec());
+    let spread = (0..count)
+        .flat_map(|i| nodes.iter().map(move |node| node[i]))
+        .take(count)
+        .collect();
+    (confined, spread)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_core_lists() {
+        assert_eq!(cpu_list("0-3,8,10-11\n"), Some(vec![0, 1, 2, 3, 8, 10, 11]));
+        assert_eq!(cpu_list("\n"), Some(vec![]));
+        assert_eq!(cpu_list("a-b"), None);
+    }
+
+    #[test]
+    fn spreads_over_every_node() {
+        let nodes = vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]];
+        assert_eq!(placements(&nodes), (vec![0, 1, 2, 3], vec![0, 4, 1, 5]));
+    }
+}
@@ -0,0 +1,309 @@
+//! Open-loop load generation: requests are issued at a fixed rate no matter
+//! how many are still outstanding.
+//!
+//! [`drive`](crate::harness::drive) and the other closed-loop scenarios only
+//! send a request when an earlier one has completed, so a slow server slows
+//! the load down with it and never sees a queue build up. Here the `i`-th
+//! request is due `i / rate` seconds after the start and is spawned as its
+//! own task once it is due, so the achieved throughput falls behind the
+//! offered rate only when the system cannot keep up.
+//!
+//! Runtime timers are not precise enough to wait for every individual due
+//! time at high rates; requests whose time has already come are issued right
+//! away, so they leave in small bursts on every timer tick.
+//!
+//! Latencies are corrected for coordinated omission: they count from the time
+//! a request was due rather than from when it was actually issued, the way
+//! wrk2 does it. If the generator stalls, because its timer fires late or its
+//! runtime is too busy to poll it, the requests it should have sent meanwhile
+//! still leave late but are charged for the whole wait, instead of looking as
+//! fast as the ones that left on time. Completing late is what the clients of
+//! a stalled server would have seen. The uncorrected latencies, from issue to
+//! completion, are kept as well, and the gap between the two shows how much
+//! of the tail is the generator's own backlog.
+//!
+//! Sustained runs keep the rate up for minutes and report the latencies of
+//! every window of the run as well, by when the requests were due, since a
+//! stall that comes once every few seconds, a timer wheel cascading or a
+//! pool being resized, is lost in a short run and flattened in one overall
+//! histogram.
+
+use crate::harness::{latency_histogram, record, LoadReport, Millis};
+use crate::polls;
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// A load report together with the rate the requests were offered at.
+pub struct OpenLoopReport {
+    /// Requests per second the generator tried to issue.
+    pub offered: f64,
+    /// Throughput and latencies corrected for coordinated omission.
+    pub load: LoadReport,
+    /// Latencies from issuing each request to its completion.
+    pub uncorrected: Histogram<u64>,
+}
+
+impl OpenLoopReport {
+    fn new(start: Instant, rate: f64, timings: Vec<Timing>) -> Self {
+        let mut corrected = latency_histogram();
+        let mut uncorrected = latency_histogram();
+        for timing in &timings {
+            record(&mut corrected, timing.since_due);
+            record(&mut uncorrected, timing.since_issue);
+        }
+        OpenLoopReport {
+            offered: rate,
+            load: LoadReport::since(start, timings.len(), corrected),
+            uncorrected,
+        }
+    }
+}
+
+impl fmt::Display for OpenLoopReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "offered {:>8.0} req/s  {}", self.offered, self.load)?;
+        let p99 = self.uncorrected.value_at_quantile(0.99);
+        write!(f, "  uncorrected p99 {}", Millis(p99))
+    }
+}
+
+/// Corrected latencies of the requests due within one window of a sustained run.
+pub struct Window {
+    /// Time from the start of the run to the start of the window.
+    pub start: Duration,
+    pub latencies: Histogram<u64>,
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>10} req  p99 {}  p99.9 {}  max {}",
+            self.latencies.len(),
+            Millis(self.latencies.value_at_quantile(0.99)),
+            Millis(self.latencies.value_at_quantile(0.999)),
+            Millis(self.latencies.max())
+        )
+    }
+}
+
+/// An open-loop report over a whole sustained run, and over each window of it.
+pub struct SustainedReport {
+    pub run: OpenLoopReport,
+    pub windows: Vec<Window>,
+}
+
+impl SustainedReport {
+    fn new(start: Instant, rate: f64, window: Duration, timings: Vec<Timing>) -> Self {
+        let mut windows: Vec<Window> = Vec::new();
+        for timing in &timings {
+            let index = ((timing.due - start).as_secs_f64() / window.as_secs_f64()) as usize;
+            while windows.len() <= index {
+                windows.push(Window {
+                    start: window * windows.len() as u32,
+                    latencies: latency_histogram(),
+                });
+            }
+            record(&mut windows[index].latencies, timing.since_due);
+        }
+        SustainedReport {
+            run: OpenLoopReport::new(start, rate, timings),
+            windows,
+        }
+    }
+
+    /// The window with the highest p99.9.
+    fn worst(&self) -> Option<&Window> {
+        self.windows
+            .iter()
+            .max_by_key(|window| window.latencies.value_at_quantile(0.999))
+    }
+}
+
+impl fmt::Display for SustainedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.run)?;
+        if let Some(worst) = self.worst() {
+            write!(
+                f,
+                "  worst p99.9 {} at {}s",
+                Millis(worst.latencies.value_at_quantile(0.999)),
+                worst.start.as_secs()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How long one request took, counted from when it was due and from when it was issued.
+struct Timing {
+    due: Instant,
+    since_due: Duration,
+    since_issue: Duration,
+}
+
+/// Runs `request`, which was due at `due`, and times it.
+async fn timed<Fut: Future<Output = ()>>(due: Instant, request: Fut) -> Timing {
+    let issued = Instant::now();
+    request.await;
+    let done = Instant::now();
+    Timing {
+        due,
+        since_due: done - due,
+        since_issue: done - issued,
+    }
+}
+
+/// When the `i`-th request is due, for a generator that started at `start`.
+fn due(start: Instant, rate: f64, i: usize) -> Instant {
+    start + Duration::from_secs_f64(i as f64 / rate)
+}
+
+/// Issues `requests` calls of `request` at `rate` per second, each in its own tokio task.
+pub async fn tokio<F, Fut>(rate: f64, requests: usize, request: F) -> OpenLoopReport
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let (start, timings) = polls::counted(tokio_timings(rate, requests, request)).await;
+    OpenLoopReport::new(start, rate, timings)
+}
+
+/// Issues `requests` calls of `request` at `rate` per second, each in its own
+/// tokio task, and reports their latencies per `window` too.
+pub async fn tokio_sustained<F, Fut>(
+    rate: f64,
+    requests: usize,
+    window: Duration,
+    request: F,
+) -> SustainedReport
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let (start, timings) = polls::counted(tokio_timings(rate, requests, request)).await;
+    SustainedReport::new(start, rate, window, timings)
+}
+
+async fn tokio_timings<F, Fut>(rate: f64, requests: usize, mut request: F) -> (Instant, Vec<Timing>)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let start = Instant::now();
+    let mut tasks = Vec::with_capacity(requests);
+    for i in 0..requests {
+        let due = due(start, rate, i);
+        if due > Instant::now() {
+            tokio::time::sleep_until(due.into()).await;
+        }
+        tasks.push(tokio::spawn(timed(due, request())));
+    }
+    let mut timings = Vec::with_capacity(requests);
+    for task in tasks {
+        timings.push(task.await.unwrap());
+    }
+    (start, timings)
+}
+
+/// Issues `requests` calls of `request` at `rate` per second, each in its own async_std task.
+pub async fn async_std<F, Fut>(rate: f64, requests: usize, request: F) -> OpenLoopReport
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let (start, timings) = polls::counted(async_std_timings(rate, requests, request)).await;
+    OpenLoopReport::new(start, rate, timings)
+}
+
+/// Issues `requests` calls of `request` at `rate` per second, each in its own
+/// async_std task, and reports their latencies per `window` too.
+pub async fn async_std_sustained<F, Fut>(
+    rate: f64,
+    requests: usize,
+    window: Duration,
+    request: F,
+) -> SustainedReport
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let (start, timings) = polls::counted(async_std_timings(rate, requests, request)).await;
+    SustainedReport::new(start, rate, window, timings)
+}
+
+async fn async_std_timings<F, Fut>(
+    rate: f64,
+    requests: usize,
+    mut request: F,
+) -> (Instant, Vec<Timing>)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    use async_std::task;
+
+    let start = Instant::now();
+    let mut tasks = Vec::with_capacity(requests);
+    for i in 0..requests {
+        let due = due(start, rate, i);
+        let wait = due.saturating_duration_since(Instant::now());
+        if wait > Duration::from_secs(0) {
+            task::sleep(wait).await;
+        }
+        tasks.push(task::spawn(timed(due, request())));
+    }
+    let mut timings = Vec::with_capacity(requests);
+    for task in tasks {
+        timings.push(task.await);
+    }
+    (start, timings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_spread_over_the_run() {
+        let rt = crate::metrics::runtime();
+        let report = rt.block_on(tokio(2_000.0, 40, || async {}));
+        assert_eq!(report.load.latencies.len(), 40);
+        assert!(report.load.elapsed >= Duration::from_millis(19));
+
+        let report = async_std::task::block_on(async_std(2_000.0, 40, || async {}));
+        assert_eq!(report.load.latencies.len(), 40);
+        assert!(report.load.elapsed >= Duration::from_millis(19));
+    }
+
+    #[test]
+    fn sustained_runs_are_split_into_windows() {
+        let rt = crate::metrics::runtime();
+        let window = Duration::from_millis(10);
+        let report = rt.block_on(tokio_sustained(2_000.0, 60, window, || async {}));
+        assert_eq!(report.windows.len(), 3);
+        assert_eq!(report.windows[2].start, Duration::from_millis(20));
+        let windowed: u64 = report.windows.iter().map(|w| w.latencies.len()).sum();
+        assert_eq!(windowed, 60);
+    }
+
+    #[test]
+    fn stalls_are_charged_to_the_requests_that_were_due() {
+        // Blocking the runtime's only thread holds back the requests due meanwhile.
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut first = true;
+        let report = rt.block_on(tokio(1_000.0, 20, || {
+            if std::mem::replace(&mut first, false) {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            async {}
+        }));
+        assert!(report.load.latencies.max() >= Duration::from_millis(9).as_nanos() as u64);
+        assert!(report.uncorrected.max() < Duration::from_millis(9).as_nanos() as u64);
+    }
+}
@@ -0,0 +1,114 @@
+//! Hardware performance counters of a scenario, read through
+//! `perf_event_open` on Linux with the `perf-counters` feature.
+//!
+//! Each counter follows the thread running the scenario and every thread it
+//! starts while the counter is on, so the workers of a runtime the scenario
+//! builds are counted, but the threads of a global runtime that was already
+//! running, like async_std's and smol's after their first use, are not. The
+//! counters are opened anew for every scenario; where the kernel refuses them,
+//! say because `perf_event_paranoid` is too strict, nothing is reported.
+
+use std::fmt;
+
+/// Instructions retired, cache misses and mispredicted branches.
+#[cfg_attr(
+    not(all(feature = "perf-counters", target_os = "linux")),
+    allow(dead_code)
+)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Counts {
+    pub(crate) instructions: u64,
+    pub(crate) cache_misses: u64,
+    pub(crate) branch_misses: u64,
+}
+
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "instr {:>14}  cache-miss {:>11}  branch-miss {:>11}",
+            self.instructions, self.cache_misses, self.branch_misses
+        )
+    }
+}
+
+#[cfg(all(feature = "perf-counters", target_os = "linux"))]
+mod counters {
+    use super::Counts;
+    use perf_event::events::Hardware;
+    use perf_event::{Builder, Counter};
+
+    /// Counters that are on until stopped.
+    pub(crate) struct Counters {
+        instructions: Counter,
+        cache_misses: Counter,
+        branch_misses: Counter,
+    }
+
+    fn open(kind: Hardware) -> Option<Counter> {
+        let mut counter = Builder::new().kind(kind).inherit(true).build().ok()?;
+        counter.enable().ok()?;
+        Some(counter)
+    }
+
+    impl Counters {
+        /// Opens and starts the counters, or returns `None` if the kernel
+        /// refuses any of them.
+        pub(crate) fn start() -> Option<Self> {
+            Some(Counters {
+                instructions: open(Hardware::INSTRUCTIONS)?,
+                cache_misses: open(Hardware::CACHE_MISSES)?,
+                branch_misses: open(Hardware::BRANCH_MISSES)?,
+            })
+        }
+
+        /// Stops the counters and reads what they counted.
+        pub(crate) fn stop(mut self) -> Option<Counts> {
+            let mut read = |counter: &mut Counter| {
+                counter.disable().ok()?;
+                counter.read().ok()
+            };
+            Some(Counts {
+                instructions: read(&mut self.instructions)?,
+                cache_misses: read(&mut self.cache_misses)?,
+                branch_misses: read(&mut self.branch_misses)?,
+            })
+        }
+    }
+}
+
+#[cfg(not(all(feature = "perf-counters", target_os = "linux")))]
+mod counters {
+    use super::Counts;
+
+    /// Stands in for the counters where they are not built; there are none
+    /// to start.
+    pub(crate) enum Counters {}
+
+    impl Counters {
+        pub(crate) fn start() -> Option<Self> {
+            None
+        }
+
+        pub(crate) fn stop(self) -> Option<Counts> {
+            match self {}
+        }
+    }
+}
+
+pub(crate) use counters::Counters;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_instructions_where_available() {
+        if let Some(counters) = Counters::start() {
+            let sum: u64 = std::hint::black_box(0..100_000u64).sum();
+            assert!(sum > 0);
+            let counts = counters.stop().unwrap();
+            assert!(counts.instructions >= 100_000);
+        }
+    }
+}
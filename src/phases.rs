@@ -0,0 +1,139 @@
+//! Time spent in the phases of a workload, marked with tracing spans, summed
+//! per scenario by a subscriber layer.
+//!
+//! A phase is a span named after it, such as `generate`, `spawn`, `write`,
+//! `compute` or `join`, and lasts from the span's creation until it closes,
+//! so a phase of an async workload includes the time its future waited, not
+//! only the polls. Phases may nest, a `generate` inside a `write` say, and
+//! then the time counts in both. Spans are counted under the scenario last
+//! named with [`scenario`]. Without [`PhaseTimings`] installed the spans cost
+//! next to nothing and nothing is counted.
+
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Scenario the phases closing now belong to.
+static SCENARIO: Mutex<String> = Mutex::new(String::new());
+
+/// Spans closed so far, by scenario and phase, in the order they first
+/// closed.
+static TIMINGS: Mutex<Vec<Phase>> = Mutex::new(Vec::new());
+
+/// How often a phase of a scenario ran and how long it took in all.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Phase {
+    pub scenario: String,
+    pub name: &'static str,
+    pub count: u64,
+    pub total: Duration,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<48} {:<10} {:>10} spans  mean {:>10.1}us  total {:>9.1}ms",
+            self.scenario,
+            self.name,
+            self.count,
+            self.total.as_secs_f64() * 1e6 / self.count as f64,
+            self.total.as_secs_f64() * 1e3
+        )
+    }
+}
+
+/// Counts the phases that close from now on under `name`.
+pub fn scenario(name: &str) {
+    let mut scenario = SCENARIO.lock().unwrap();
+    scenario.clear();
+    scenario.push_str(name);
+}
+
+/// Takes the phases counted so far.
+pub fn take() -> Vec<Phase> {
+    std::mem::take(&mut *TIMINGS.lock().unwrap())
+}
+
+fn record(name: &'static str, took: Duration) {
+    let scenario = SCENARIO.lock().unwrap();
+    let mut timings = TIMINGS.lock().unwrap();
+    match timings
+        .iter_mut()
+        .find(|phase| phase.name == name && phase.scenario == *scenario)
+    {
+        Some(phase) => {
+            phase.count += 1;
+            phase.total += took;
+        }
+        None => timings.push(Phase {
+            scenario: scenario.clone(),
+            name,
+            count: 1,
+            total: took,
+        }),
+    }
+}
+
+/// When a span was created.
+struct Created(Instant);
+
+/// A layer that times every span from its creation until it closes.
+pub struct PhaseTimings;
+
+impl<S> Layer<S> for PhaseTimings
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Created(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(&id) {
+            if let Some(Created(created)) = span.extensions().get::<Created>() {
+                record(span.name(), created.elapsed());
+            }
+        }
+    }
+}
+
+/// Installs [`PhaseTimings`] as the global subscriber.
+pub fn install() {
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    tracing_subscriber::registry().with(PhaseTimings).init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn sums_phases_per_scenario() {
+        let subscriber = tracing_subscriber::registry().with(PhaseTimings);
+        tracing::subscriber::with_default(subscriber, || {
+            scenario("phases_test");
+            for _ in 0..3 {
+                let _write = tracing::info_span!("write").entered();
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        });
+        let phases: Vec<_> = take()
+            .into_iter()
+            .filter(|phase| phase.scenario == "phases_test")
+            .collect();
+        assert_eq!(phases.len(), 1);
+        assert_eq!(phases[0].name, "write");
+        assert_eq!(phases[0].count, 3);
+        assert!(phases[0].total >= Duration::from_millis(3));
+    }
+}
@@ -0,0 +1,128 @@
+//! A pipeline of actor tasks: a source, stages of workers and an aggregator,
+//! connected by channels.
+//!
+//! The source sends every message stamped with the time it made it. Every
+//! stage is a pool of `workers` tasks sharing one async-channel channel as
+//! their mailbox, each spinning for a fixed time on every message before
+//! passing it on to the next stage's mailbox, and the aggregator receives
+//! them all at the end. All mailboxes are bounded, so a slow stage holds the
+//! ones before it back. The channels do not depend on a runtime, so the
+//! pipelines are the same on both runtimes.
+//!
+//! Latencies are end to end, from the source to the aggregator.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use hdrhistogram::Histogram;
+use std::time::{Duration, Instant};
+
+/// The shape of the pipeline.
+#[derive(Clone, Copy, Debug)]
+pub struct Shape {
+    /// Stages of workers between the source and the aggregator.
+    pub stages: usize,
+    /// Workers in every stage.
+    pub workers: usize,
+    /// Messages every mailbox holds.
+    pub capacity: usize,
+    /// Time every worker spins for on every message.
+    pub work: Duration,
+}
+
+/// The tasks of a pipeline carrying `messages` messages: the source and the
+/// workers, and the aggregator, which ends with the latencies once all
+/// messages arrived.
+fn build(
+    shape: Shape,
+    messages: usize,
+) -> (
+    Vec<BoxFuture<'static, ()>>,
+    BoxFuture<'static, Histogram<u64>>,
+) {
+    let (source, mut mailbox) = async_channel::bounded(shape.capacity);
+    let mut tasks = vec![async move {
+        for _ in 0..messages {
+            source.send(Instant::now()).await.unwrap();
+        }
+    }
+    .boxed()];
+    for _ in 0..shape.stages {
+        let (next, next_mailbox) = async_channel::bounded(shape.capacity);
+        for _ in 0..shape.workers {
+            let (mailbox, next) = (mailbox.clone(), next.clone());
+            tasks.push(
+                async move {
+                    while let Ok(made) = mailbox.recv().await {
+                        let spun = Instant::now();
+                        while spun.elapsed() < shape.work {
+                            std::hint::spin_loop();
+                        }
+                        next.send(made).await.unwrap();
+                    }
+                }
+                .boxed(),
+            );
+        }
+        mailbox = next_mailbox;
+    }
+    let aggregator = async move {
+        let mut latencies = latency_histogram();
+        while let Ok(made) = mailbox.recv().await {
+            record(&mut latencies, made.elapsed());
+        }
+        latencies
+    }
+    .boxed();
+    (tasks, aggregator)
+}
+
+/// Sends `messages` messages through a pipeline of tokio tasks.
+pub fn tokio(shape: Shape, messages: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (tasks, aggregator) = build(shape, messages);
+        let start = Instant::now();
+        let aggregator = tokio::spawn(aggregator);
+        for task in tasks {
+            tokio::spawn(task);
+        }
+        let latencies = aggregator.await.unwrap();
+        LoadReport::since(start, messages, latencies)
+    })
+}
+
+/// Sends `messages` messages through a pipeline of async_std tasks.
+pub fn async_std(shape: Shape, messages: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (tasks, aggregator) = build(shape, messages);
+        let start = Instant::now();
+        let aggregator = task::spawn(aggregator);
+        for task in tasks {
+            task::spawn(task);
+        }
+        let latencies = aggregator.await;
+        LoadReport::since(start, messages, latencies)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_message_reaches_the_aggregator() {
+        for stages in [0, 1, 3] {
+            let shape = Shape {
+                stages,
+                workers: 3,
+                capacity: 4,
+                work: Duration::from_micros(1),
+            };
+            assert_eq!(tokio(shape, 1_000).latencies.len(), 1_000);
+            assert_eq!(async_std(shape, 1_000).latencies.len(), 1_000);
+        }
+    }
+}
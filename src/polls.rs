@@ -0,0 +1,175 @@
+//! How often the root future of a scenario was polled and how long the polls
+//! took, to tell a runtime that polls it often and briefly from one that
+//! polls it seldom and long.
+//!
+//! A root future is one a runtime's `block_on` drives, or the task a
+//! scenario waits on; wrapped in [`counted`], its polls are counted into
+//! totals that a [`Runner`](crate::harness::Runner) reads around every
+//! scenario, like the allocations. [`drive`](crate::harness::drive) and the
+//! open-loop generators count theirs, so every load driven by them is
+//! counted. Polls of the tasks the root future spawns are not its polls.
+//!
+//! The wakeups of a root future are counted too, and so are the ones after
+//! which it was polled only to return `Pending` again, having made no
+//! progress. Such spurious wakeups cost a poll and a trip through the
+//! scheduler for nothing; a waker woken for every item of a channel that
+//! the future drains in batches, or woken by more than one source at once,
+//! produces them, and wall-clock numbers alone do not show them.
+
+use futures::task::AtomicWaker;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
+
+static POLLS: AtomicU64 = AtomicU64::new(0);
+static POLLING_NANOS: AtomicU64 = AtomicU64::new(0);
+static LONGEST_NANOS: AtomicU64 = AtomicU64::new(0);
+static WAKEUPS: AtomicU64 = AtomicU64::new(0);
+static SPURIOUS: AtomicU64 = AtomicU64::new(0);
+
+/// A future whose polls are counted.
+pub struct Counted<F> {
+    future: Pin<Box<F>>,
+    alarm: Arc<Alarm>,
+}
+
+/// Stands in for the waker of the runtime, noting that it was woken.
+struct Alarm {
+    woken: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl Wake for Alarm {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        WAKEUPS.fetch_add(1, Ordering::Relaxed);
+        self.woken.store(true, Ordering::Release);
+        self.waker.wake();
+    }
+}
+
+/// Counts the polls of `future`, the time they take and its wakeups.
+pub fn counted<F: Future>(future: F) -> Counted<F> {
+    Counted {
+        future: Box::pin(future),
+        alarm: Arc::new(Alarm {
+            woken: AtomicBool::new(false),
+            waker: AtomicWaker::new(),
+        }),
+    }
+}
+
+impl<F: Future> Future for Counted<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.get_mut();
+        this.alarm.waker.register(cx.waker());
+        let woken = this.alarm.woken.swap(false, Ordering::Acquire);
+        let alarm = Waker::from(this.alarm.clone());
+        let started = Instant::now();
+        let poll = this.future.as_mut().poll(&mut Context::from_waker(&alarm));
+        let nanos = started.elapsed().as_nanos() as u64;
+        POLLS.fetch_add(1, Ordering::Relaxed);
+        POLLING_NANOS.fetch_add(nanos, Ordering::Relaxed);
+        LONGEST_NANOS.fetch_max(nanos, Ordering::Relaxed);
+        if woken && poll.is_pending() {
+            SPURIOUS.fetch_add(1, Ordering::Relaxed);
+        }
+        poll
+    }
+}
+
+/// Polls of root futures counted since some point.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Polls {
+    pub count: u64,
+    pub polling: Duration,
+    pub longest: Duration,
+    pub wakeups: u64,
+    /// Polls after a wakeup that returned `Pending`.
+    pub spurious: u64,
+}
+
+impl Polls {
+    /// Starts counting from now, forgetting the longest poll so far.
+    pub fn start() -> Self {
+        LONGEST_NANOS.store(0, Ordering::Relaxed);
+        Polls {
+            count: POLLS.load(Ordering::Relaxed),
+            polling: Duration::from_nanos(POLLING_NANOS.load(Ordering::Relaxed)),
+            longest: Duration::ZERO,
+            wakeups: WAKEUPS.load(Ordering::Relaxed),
+            spurious: SPURIOUS.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The polls counted since starting.
+    pub fn stop(self) -> Self {
+        Polls {
+            count: POLLS.load(Ordering::Relaxed) - self.count,
+            polling: Duration::from_nanos(POLLING_NANOS.load(Ordering::Relaxed)) - self.polling,
+            longest: Duration::from_nanos(LONGEST_NANOS.load(Ordering::Relaxed)),
+            wakeups: WAKEUPS.load(Ordering::Relaxed) - self.wakeups,
+            spurious: SPURIOUS.load(Ordering::Relaxed) - self.spurious,
+        }
+    }
+}
+
+impl fmt::Display for Polls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "root polls {:>9}  {:>9.2}us/poll  longest {:>9.1}us  \
+             wakeups {:>9}  spurious {:>9}",
+            self.count,
+            self.polling.as_secs_f64() * 1e6 / self.count.max(1) as f64,
+            self.longest.as_secs_f64() * 1e6,
+            self.wakeups,
+            self.spurious
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_every_poll() {
+        let started = Polls::start();
+        futures::executor::block_on(counted(async {
+            for _ in 0..3 {
+                smol::future::yield_now().await;
+            }
+        }));
+        // Other tests may count polls of their own meanwhile.
+        assert!(started.stop().count >= 4);
+    }
+
+    #[test]
+    fn counts_wakeups_that_make_no_progress() {
+        let started = Polls::start();
+        let mut woken = 0;
+        futures::executor::block_on(counted(futures::future::poll_fn(|cx| {
+            // Woken three times, ready only on the last.
+            woken += 1;
+            if woken < 4 {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })));
+        let polled = started.stop();
+        assert!(polled.wakeups >= 3);
+        assert!(polled.spurious >= 2);
+    }
+}
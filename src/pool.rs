@@ -0,0 +1,331 @@
+//! Many tasks sharing a few pooled connections to an echo server.
+//!
+//! Every task repeatedly checks a connection out, sends one byte over it,
+//! waits for the echo and returns the connection, until the run's requests are
+//! used up. There are more tasks than connections, so most of the time is spent
+//! waiting for the pool. bb8 needs tokio; deadpool does not depend on a runtime
+//! and is measured on both. Both pools open all their connections before the
+//! clock starts, bb8 through `min_idle` and deadpool by checking them all
+//! out once, so that neither pays for connecting during the run.
+//!
+//! Latencies are the time from asking for a connection to returning it. Tasks
+//! take requests from a shared counter, so a pool that hands connections out
+//! unfairly lets some tasks finish many more requests than others; the report
+//! includes Jain's fairness index over the per-task counts, which is 1 when
+//! every task got the same share.
+
+use crate::harness::{fairness, latency_histogram, record, LoadReport};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A load report together with how evenly the requests were spread over the tasks.
+pub struct PoolReport {
+    pub load: LoadReport,
+    /// Jain's fairness index over the number of requests each task completed.
+    pub fairness: f64,
+}
+
+impl PoolReport {
+    fn new(load: LoadReport, counts: &[usize]) -> Self {
+        PoolReport {
+            load,
+            fairness: fairness(counts),
+        }
+    }
+}
+
+impl fmt::Display for PoolReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  fairness {:.3}", self.load, self.fairness)
+    }
+}
+
+/// Takes the next request from `remaining`, or returns `false` once they are used up.
+fn take(remaining: &AtomicUsize) -> bool {
+    remaining
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+            left.checked_sub(1)
+        })
+        .is_ok()
+}
+
+/// Opens tokio connections to an echo server for bb8 and deadpool.
+struct TokioManager {
+    addr: SocketAddr,
+}
+
+impl TokioManager {
+    async fn open(&self) -> io::Result<tokio::net::TcpStream> {
+        let stream = tokio::net::TcpStream::connect(self.addr).await?;
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+}
+
+impl bb8::ManageConnection for TokioManager {
+    type Connection = tokio::net::TcpStream;
+    type Error = io::Error;
+
+    async fn connect(&self) -> io::Result<Self::Connection> {
+        self.open().await
+    }
+
+    async fn is_valid(&self, _: &mut Self::Connection) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn has_broken(&self, _: &mut Self::Connection) -> bool {
+        false
+    }
+}
+
+impl deadpool::managed::Manager for TokioManager {
+    type Type = tokio::net::TcpStream;
+    type Error = io::Error;
+
+    async fn create(&self) -> io::Result<Self::Type> {
+        self.open().await
+    }
+
+    async fn recycle(
+        &self,
+        _: &mut Self::Type,
+        _: &deadpool::managed::Metrics,
+    ) -> deadpool::managed::RecycleResult<io::Error> {
+        Ok(())
+    }
+}
+
+/// Opens async_std connections to an echo server for deadpool.
+struct AsyncStdManager {
+    addr: SocketAddr,
+}
+
+impl deadpool::managed::Manager for AsyncStdManager {
+    type Type = async_std::net::TcpStream;
+    type Error = io::Error;
+
+    async fn create(&self) -> io::Result<Self::Type> {
+        let stream = async_std::net::TcpStream::connect(self.addr).await?;
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+
+    async fn recycle(
+        &self,
+        _: &mut Self::Type,
+        _: &deadpool::managed::Metrics,
+    ) -> deadpool::managed::RecycleResult<io::Error> {
+        Ok(())
+    }
+}
+
+/// Starts a one-byte echo server on tokio.
+async fn tokio_echo() -> SocketAddr {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            tokio::spawn(async move {
+                let mut byte = [0];
+                while stream.read_exact(&mut byte).await.is_ok() {
+                    if stream.write_all(&byte).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    addr
+}
+
+/// Sends one byte over a tokio connection and waits for the echo.
+async fn tokio_ping(stream: &mut tokio::net::TcpStream) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    stream.write_all(b"!").await.unwrap();
+    stream.read_exact(&mut [0]).await.unwrap();
+}
+
+/// Opens all `size` connections of a deadpool pool up front, the way bb8's
+/// `min_idle` does, by checking them out at once and returning them.
+async fn warm_up<M: deadpool::managed::Manager>(pool: &deadpool::managed::Pool<M>, size: usize) {
+    let connections = futures::future::join_all((0..size).map(|_| pool.get())).await;
+    assert!(
+        connections.iter().all(Result::is_ok),
+        "cannot open the pooled connections"
+    );
+}
+
+/// Spawns `tasks` tokio tasks running `worker` and collects their latencies and counts.
+async fn tokio_tasks<F, Fut>(tasks: usize, requests: usize, worker: F) -> PoolReport
+where
+    F: Fn(Arc<AtomicUsize>) -> Fut,
+    Fut: std::future::Future<Output = (Histogram<u64>, usize)> + Send + 'static,
+{
+    let remaining = Arc::new(AtomicUsize::new(requests));
+    let start = Instant::now();
+    let handles: Vec<_> = (0..tasks)
+        .map(|_| tokio::spawn(worker(remaining.clone())))
+        .collect();
+    let mut latencies = latency_histogram();
+    let mut counts = Vec::new();
+    for handle in handles {
+        let (task_latencies, count) = handle.await.unwrap();
+        latencies.add(task_latencies).unwrap();
+        counts.push(count);
+    }
+    PoolReport::new(LoadReport::since(start, requests, latencies), &counts)
+}
+
+/// Runs `requests` checkouts from `tasks` tasks through a bb8 pool of `size` connections.
+pub fn bb8_tokio(tasks: usize, size: usize, requests: usize) -> PoolReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let addr = tokio_echo().await;
+        let pool = bb8::Pool::builder()
+            .max_size(size as u32)
+            .min_idle(Some(size as u32))
+            .test_on_check_out(false)
+            .build(TokioManager { addr })
+            .await
+            .unwrap();
+        tokio_tasks(tasks, requests, |remaining| {
+            let pool = pool.clone();
+            async move {
+                let mut latencies = latency_histogram();
+                let mut count = 0;
+                while take(&remaining) {
+                    let start = Instant::now();
+                    let mut stream = pool.get().await.unwrap();
+                    tokio_ping(&mut stream).await;
+                    drop(stream);
+                    record(&mut latencies, start.elapsed());
+                    count += 1;
+                }
+                (latencies, count)
+            }
+        })
+        .await
+    })
+}
+
+/// Runs `requests` checkouts from `tasks` tasks through a deadpool pool of
+/// `size` connections on tokio.
+pub fn deadpool_tokio(tasks: usize, size: usize, requests: usize) -> PoolReport {
+    use deadpool::managed::Pool;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let addr = tokio_echo().await;
+        let pool: Pool<TokioManager> = Pool::builder(TokioManager { addr })
+            .max_size(size)
+            .build()
+            .unwrap();
+        warm_up(&pool, size).await;
+        tokio_tasks(tasks, requests, |remaining| {
+            let pool = pool.clone();
+            async move {
+                let mut latencies = latency_histogram();
+                let mut count = 0;
+                while take(&remaining) {
+                    let start = Instant::now();
+                    let mut stream = pool.get().await.unwrap();
+                    tokio_ping(&mut stream).await;
+                    drop(stream);
+                    record(&mut latencies, start.elapsed());
+                    count += 1;
+                }
+                (latencies, count)
+            }
+        })
+        .await
+    })
+}
+
+/// Runs `requests` checkouts from `tasks` tasks through a deadpool pool of
+/// `size` connections on async_std.
+pub fn deadpool_async_std(tasks: usize, size: usize, requests: usize) -> PoolReport {
+    use async_std::net::TcpListener;
+    use async_std::task;
+    use deadpool::managed::Pool;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept = task::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                task::spawn(async move {
+                    let mut byte = [0];
+                    while stream.read_exact(&mut byte).await.is_ok() {
+                        if stream.write_all(&byte).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        let pool: Pool<AsyncStdManager> = Pool::builder(AsyncStdManager { addr })
+            .max_size(size)
+            .build()
+            .unwrap();
+        warm_up(&pool, size).await;
+        let remaining = Arc::new(AtomicUsize::new(requests));
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| {
+                let pool = pool.clone();
+                let remaining = remaining.clone();
+                task::spawn(async move {
+                    let mut latencies = latency_histogram();
+                    let mut count = 0;
+                    while take(&remaining) {
+                        let start = Instant::now();
+                        let mut stream = pool.get().await.unwrap();
+                        stream.write_all(b"!").await.unwrap();
+                        stream.read_exact(&mut [0]).await.unwrap();
+                        drop(stream);
+                        record(&mut latencies, start.elapsed());
+                        count += 1;
+                    }
+                    (latencies, count)
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        let mut counts = Vec::new();
+        for handle in handles {
+            let (task_latencies, count) = handle.await;
+            latencies.add(task_latencies).unwrap();
+            counts.push(count);
+        }
+        let report = PoolReport::new(LoadReport::since(start, requests, latencies), &counts);
+        accept.cancel().await;
+        report
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pools_complete_requests() {
+        for pool in &[bb8_tokio, deadpool_tokio, deadpool_async_std] {
+            assert_eq!(pool(8, 2, 100).load.latencies.len(), 100);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Checks of how the machine is set up that make runs hard to compare, done
+//! before any scenario starts.
+//!
+//! A frequency governor other than `performance` clocks the cores up and
+//! down with the load, and turbo boost clocks them up while the package is
+//! cool, so the same work takes longer later in a run, or on a warmer day.
+//! With SMT on, two workers may share a core without knowing it. A CPU that
+//! has been throttling for heat runs slower than its nominal clock, and
+//! memory swapped out makes the page faults of a scenario cost disk reads.
+//!
+//! Every check reads sysfs or procfs, on Linux; elsewhere, or where a file
+//! is missing, the check is left out. The [`Runner`](crate::harness::Runner)
+//! warns about every check that failed, or refuses to run with `--strict`,
+//! and the header of a result file records them all, see
+//! [`results`](crate::results).
+
+use std::fs;
+
+/// What one check found, and why it makes runs hard to compare, if it does.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub value: String,
+    pub warning: Option<String>,
+}
+
+/// The checks that could be made on this machine.
+pub fn checks() -> Vec<Check> {
+    let read = |path: &str| fs::read_to_string(path).ok();
+    let governors: Vec<_> = fs::read_dir("/sys/devices/system/cpu/cpufreq")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|policy| fs::read_to_string(policy.path().join("scaling_governor")).ok())
+        .collect();
+    let throttles: Option<u64> = fs::read_dir("/sys/devices/system/cpu")
+        .ok()
+        .and_then(|cpus| {
+            cpus.flatten()
+                .filter_map(|cpu| {
+                    let count = cpu.path().join("thermal_throttle/package_throttle_count");
+                    fs::read_to_string(count).ok()?.trim().parse::<u64>().ok()
+                })
+                .max()
+        });
+    // intel_pstate reports turbo the other way around.
+    let turbo = read("/sys/devices/system/cpu/cpufreq/boost")
+        .map(|boost| boost.trim() == "1")
+        .or_else(|| {
+            read("/sys/devices/system/cpu/intel_pstate/no_turbo").map(|off| off.trim() == "0")
+        });
+    vec![
+        governor(&governors),
+        turbo.map(|on| flag("turbo", on, "turbo boost clocks up while the CPU is cool")),
+        read("/sys/devices/system/cpu/smt/active")
+            .map(|active| flag("smt", active.trim() == "1", "workers may share cores")),
+        throttles.map(throttled),
+        read("/proc/meminfo").and_then(|meminfo| swapped(&meminfo)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Whether every core is clocked by the `performance` governor.
+fn governor(governors: &[String]) -> Option<Check> {
+    let mut names: Vec<_> = governors.iter().map(|governor| governor.trim()).collect();
+    names.sort_unstable();
+    names.dedup();
+    if names.is_empty() {
+        return None;
+    }
+    let value = names.join(",");
+    Some(Check {
+        name: "governor",
+        warning: (names != ["performance"])
+            .then(|| format!("frequency governor is {}, not performance", value)),
+        value,
+    })
+}
+
+/// A feature that is better off, with why if it is on.
+fn flag(name: &'static str, on: bool, why: &str) -> Check {
+    Check {
+        name,
+        value: if on { "on" } else { "off" }.to_owned(),
+        warning: on.then(|| format!("{} is on: {}", name, why)),
+    }
+}
+
+/// How often the CPU packages have throttled for heat since boot.
+fn throttled(count: u64) -> Check {
+    Check {
+        name: "throttled",
+        value: count.to_string(),
+        warning: (count > 0)
+            .then(|| format!("the CPU has throttled for heat {} times since boot", count)),
+    }
+}
+
+/// How much memory is swapped out, from `/proc/meminfo`.
+fn swapped(meminfo: &str) -> Option<Check> {
+    let kib = |field: &str| -> Option<u64> {
+        let line = meminfo.lines().find(|line| line.starts_with(field))?;
+        line.split_whitespace().nth(1)?.parse().ok()
+    };
+    let used = kib("SwapTotal:")?.saturating_sub(kib("SwapFree:")?);
+    Some(Check {
+        name: "swapped",
+        value: format!("{}KiB", used),
+        warning: (used > 0).then(|| format!("{} KiB of memory is swapped out", used)),
+    })
+}
+
+/// Prints a warning for every failed check, or with `strict`, exits if any
+/// failed.
+pub(crate) fn run(strict: bool) {
+    let failed: Vec<_> = checks()
+        .into_iter()
+        .filter_map(|check| check.warning)
+        .collect();
+    for warning in &failed {
+        eprintln!("warning: {}", warning);
+    }
+    if strict && !failed.is_empty() {
+        eprintln!("refusing to run with --strict");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warns_about_anything_but_performance() {
+        let governors = |names: &[&str]| {
+            names
+                .iter()
+                .map(|&name| name.to_owned() + "\n")
+                .collect::<Vec<_>>()
+        };
+        let performance = governor(&governors(&["performance", "performance"])).unwrap();
+        assert_eq!(performance.warning, None);
+        let mixed = governor(&governors(&["powersave", "performance"])).unwrap();
+        assert_eq!(mixed.value, "performance,powersave");
+        assert!(mixed.warning.is_some());
+        assert_eq!(governor(&[]), None);
+    }
+
+    #[test]
+    fn warns_about_swapped_memory() {
+        let meminfo = "MemTotal: 1000 kB\nSwapTotal: 2048 kB\nSwapFree: 1024 kB\n";
+        assert_eq!(swapped(meminfo).unwrap().value, "1024KiB");
+        assert_eq!(
+            swapped("SwapTotal: 0 kB\nSwapFree: 0 kB\n")
+                .unwrap()
+                .warning,
+            None
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! Running the benchmark at a lower priority than the rest of the machine,
+//! the way a background worker sharing a host with more important work
+//! does.
+//!
+//! With `NICE` set to a niceness, the [`Runner`](crate::harness::Runner)
+//! renices itself before any scenario starts, and with `SCHED_POLICY` set to
+//! `batch` or `idle`, on Linux, moves to that scheduling policy. Both are
+//! properties of a thread on Linux that the threads it starts inherit, so
+//! every runtime's workers run the same way, while the spinning threads of
+//! `CONTENTION`, started before, keep the priority they had. Alone, a lower
+//! priority changes little; next to `CONTENTION` it shows how each runtime
+//! gets by on the time the busier threads leave it.
+
+/// Sets the niceness of the calling thread, and of the threads it starts
+/// from now on.
+#[cfg(unix)]
+pub(crate) fn renice(nice: i32) {
+    // SAFETY: `setpriority` takes no pointers.
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS as _, 0, nice) };
+    assert_eq!(
+        result,
+        0,
+        "cannot renice to {}: {}",
+        nice,
+        std::io::Error::last_os_error()
+    );
+}
+
+#[cfg(not(unix))]
+pub(crate) fn renice(_nice: i32) {
+    panic!("NICE needs Unix");
+}
+
+/// The scheduling policy called `name`, `batch` or `idle`.
+#[cfg(target_os = "linux")]
+fn policy(name: &str) -> libc::c_int {
+    match name {
+        "batch" => libc::SCHED_BATCH,
+        "idle" => libc::SCHED_IDLE,
+        other => panic!("unknown SCHED_POLICY {:?}, expected batch or idle", other),
+    }
+}
+
+/// Moves the calling thread, and the threads it starts from now on, to the
+/// scheduling policy called `name`.
+#[cfg(target_os = "linux")]
+pub(crate) fn schedule(name: &str) {
+    let param = libc::sched_param { sched_priority: 0 };
+    // SAFETY: `param` is a valid `sched_param` for the call to read.
+    let result = unsafe { libc::sched_setscheduler(0, policy(name), &param) };
+    assert_eq!(
+        result,
+        0,
+        "cannot move to SCHED_POLICY {}: {}",
+        name,
+        std::io::Error::last_os_error()
+    );
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn schedule(_name: &str) {
+    panic!("SCHED_POLICY needs Linux");
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_a_thread_to_a_lower_priority() {
+        std::thread::spawn(|| {
+            renice(5);
+            schedule("batch");
+            // SAFETY: `sched_getscheduler` takes no pointers.
+            assert_eq!(unsafe { libc::sched_getscheduler(0) }, libc::SCHED_BATCH);
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+//! A TCP relay between clients and an echo backend, the core loop of every proxy.
+//!
+//! Clients stream their payload through the relay to the backend and read the
+//! echo back at the same time, so the relay copies data in both directions at
+//! once. On tokio the relay either uses `copy_bidirectional` or joins two
+//! `copy` loops; async_std has no `copy_bidirectional`, so only the joined
+//! loops are measured there.
+//!
+//! Latencies are the time each client connection took to send its share and
+//! receive all of it back.
+
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use futures::future::BoxFuture;
+use std::time::{Duration, Instant};
+
+/// Relays `total` bytes over `connections` connections with `tokio::io::copy_bidirectional`.
+pub fn tokio_copy_bidirectional(connections: usize, total: usize, chunk: usize) -> LoadReport {
+    tokio_relay(copy_bidirectional, connections, total, chunk)
+}
+
+/// Relays `total` bytes over `connections` connections with two joined `tokio::io::copy` loops.
+pub fn tokio_joined_copies(connections: usize, total: usize, chunk: usize) -> LoadReport {
+    tokio_relay(joined_copies, connections, total, chunk)
+}
+
+/// Relays one client connection to one backend connection on tokio.
+type TokioRelay = fn(tokio::net::TcpStream, tokio::net::TcpStream) -> BoxFuture<'static, ()>;
+
+/// Relays with a single `copy_bidirectional` call.
+fn copy_bidirectional(
+    mut client: tokio::net::TcpStream,
+    mut backend: tokio::net::TcpStream,
+) -> BoxFuture<'static, ()> {
+    Box::pin(async move {
+        let _ = tokio::io::copy_bidirectional(&mut client, &mut backend).await;
+    })
+}
+
+/// Relays with one `copy` loop per direction, joined in the same task.
+fn joined_copies(
+    mut client: tokio::net::TcpStream,
+    mut backend: tokio::net::TcpStream,
+) -> BoxFuture<'static, ()> {
+    use tokio::io::{self, AsyncWriteExt};
+
+    Box::pin(async move {
+        let (mut client_read, mut client_write) = client.split();
+        let (mut backend_read, mut backend_write) = backend.split();
+        let upstream = async {
+            let _ = io::copy(&mut client_read, &mut backend_write).await;
+            let _ = backend_write.shutdown().await;
+        };
+        let downstream = async {
+            let _ = io::copy(&mut backend_read, &mut client_write).await;
+            let _ = client_write.shutdown().await;
+        };
+        tokio::join!(upstream, downstream);
+    })
+}
+
+/// Runs an echo backend, a relay using `relay` and the clients on one tokio runtime.
+fn tokio_relay(relay: TokioRelay, connections: usize, total: usize, chunk: usize) -> LoadReport {
+    use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backend_addr = backend.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let (mut read, mut write) = stream.split();
+                    let _ = io::copy(&mut read, &mut write).await;
+                    let _ = write.shutdown().await;
+                });
+            }
+        });
+
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (client, _) = proxy.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let backend = TcpStream::connect(backend_addr).await.unwrap();
+                    relay(client, backend).await;
+                });
+            }
+        });
+
+        let transfer = |bytes: usize| async move {
+            let stream = TcpStream::connect(proxy_addr).await.unwrap();
+            let (mut read, mut write) = stream.into_split();
+            let start = Instant::now();
+            let send = async {
+                let buf = vec![0u8; chunk];
+                let mut remaining = bytes;
+                while remaining > 0 {
+                    let len = remaining.min(chunk);
+                    write.write_all(&buf[..len]).await.unwrap();
+                    remaining -= len;
+                }
+                write.shutdown().await.unwrap();
+            };
+            let receive = async {
+                let mut buf = vec![0u8; chunk];
+                let mut received = 0;
+                loop {
+                    match read.read(&mut buf).await.unwrap() {
+                        0 => break received,
+                        n => received += n,
+                    }
+                }
+            };
+            let ((), received) = tokio::join!(send, receive);
+            assert_eq!(received, bytes);
+            start.elapsed()
+        };
+
+        let start = Instant::now();
+        let clients: Vec<_> = split(total, connections)
+            .map(|share| tokio::spawn(transfer(share)))
+            .collect();
+        let mut durations = Vec::new();
+        for client in clients {
+            durations.push(client.await.unwrap());
+        }
+        report(start, durations, total)
+    })
+}
+
+/// Relays `total` bytes over `connections` connections with two joined
+/// `futures::io::copy` loops on async_std.
+pub fn async_std_joined_copies(connections: usize, total: usize, chunk: usize) -> LoadReport {
+    use async_std::net::{Shutdown, TcpListener, TcpStream};
+    use async_std::task;
+    use futures::io::{self, AsyncReadExt, AsyncWriteExt};
+
+    task::block_on(async {
+        let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backend_addr = backend.local_addr().unwrap();
+        let accept_backend = task::spawn(async move {
+            loop {
+                let (stream, _) = backend.accept().await.unwrap();
+                task::spawn(async move {
+                    let _ = io::copy(&stream, &mut &stream).await;
+                    let _ = stream.shutdown(Shutdown::Write);
+                });
+            }
+        });
+
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        let accept_proxy = task::spawn(async move {
+            loop {
+                let (client, _) = proxy.accept().await.unwrap();
+                task::spawn(async move {
+                    let backend = TcpStream::connect(backend_addr).await.unwrap();
+                    let upstream = async {
+                        let _ = io::copy(&client, &mut &backend).await;
+                        let _ = backend.shutdown(Shutdown::Write);
+                    };
+                    let downstream = async {
+                        let _ = io::copy(&backend, &mut &client).await;
+                        let _ = client.shutdown(Shutdown::Write);
+                    };
+                    futures::join!(upstream, downstream);
+                });
+            }
+        });
+
+        let transfer = |bytes: usize| async move {
+            let stream = TcpStream::connect(proxy_addr).await.unwrap();
+            let start = Instant::now();
+            let send = async {
+                let buf = vec![0u8; chunk];
+                let mut remaining = bytes;
+                while remaining > 0 {
+                    let len = remaining.min(chunk);
+                    (&stream).write_all(&buf[..len]).await.unwrap();
+                    remaining -= len;
+                }
+                stream.shutdown(Shutdown::Write).unwrap();
+            };
+            let receive = async {
+                let mut buf = vec![0u8; chunk];
+                let mut received = 0;
+                loop {
+                    match (&stream).read(&mut buf).await.unwrap() {
+                        0 => break received,
+                        n => received += n,
+                    }
+                }
+            };
+            let ((), received) = futures::join!(send, receive);
+            assert_eq!(received, bytes);
+            start.elapsed()
+        };
+
+        let start = Instant::now();
+        let clients: Vec<_> = split(total, connections)
+            .map(|share| task::spawn(transfer(share)))
+            .collect();
+        let mut durations = Vec::new();
+        for client in clients {
+            durations.push(client.await);
+        }
+        let report = report(start, durations, total);
+        accept_proxy.cancel().await;
+        accept_backend.cancel().await;
+        report
+    })
+}
+
+/// Builds the report of a run that started at `start`, where every payload
+/// byte crossed the relay twice.
+fn report(start: Instant, durations: Vec<Duration>, total: usize) -> LoadReport {
+    let mut latencies = latency_histogram();
+    for duration in &durations {
+        record(&mut latencies, *duration);
+    }
+    LoadReport::since(start, durations.len(), latencies).with_bytes(2 * total as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokio_relays_everything() {
+        assert_eq!(tokio_copy_bidirectional(2, 100_000, 4096).requests, 2);
+        assert_eq!(tokio_joined_copies(2, 100_000, 4096).requests, 2);
+    }
+
+    #[test]
+    fn async_std_relays_everything() {
+        assert_eq!(async_std_joined_copies(2, 100_000, 4096).requests, 2);
+    }
+}
@@ -0,0 +1,211 @@
+//! Publish/subscribe with `publishers` publishers, `subscribers` subscribers
+//! and `topics` topics, each topic routed through a broadcast channel of its
+//! own.
+//!
+//! Every subscriber follows one topic, assigned round-robin, and every
+//! publisher sends `rounds` messages to each of the topics, so all subscribers
+//! expect the same number of deliveries. On tokio the channels are
+//! `tokio::sync::broadcast`, which lets a full channel overwrite the oldest
+//! messages, so subscribers that fall behind lose them instead of slowing the
+//! publishers down. async_std has no broadcast channel of its own, so
+//! async-broadcast is used there, which makes publishers wait for the slowest
+//! subscriber of the topic instead. The report counts the lost messages and
+//! includes Jain's fairness index over the deliveries per subscriber.
+//!
+//! Messages carry the time they were published, and latencies are the time
+//! until each delivery.
+
+use crate::harness::{fairness, latency_histogram, record, LoadReport};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::time::Instant;
+
+/// Number of messages a topic's channel holds before it is full.
+const CAPACITY: usize = 1024;
+
+/// A load report over deliveries, with the messages subscribers lost and how
+/// evenly the deliveries were spread over them.
+pub struct PubSubReport {
+    pub load: LoadReport,
+    /// Messages published to a subscriber's topic that it never received.
+    pub lost: u64,
+    /// Jain's fairness index over the number of messages each subscriber received.
+    pub fairness: f64,
+}
+
+impl PubSubReport {
+    fn new(start: Instant, expected: usize, subscribers: Vec<(Histogram<u64>, usize)>) -> Self {
+        let counts: Vec<_> = subscribers.iter().map(|&(_, count)| count).collect();
+        let delivered: usize = counts.iter().sum();
+        let mut latencies = latency_histogram();
+        for (subscriber, _) in &subscribers {
+            latencies.add(subscriber).unwrap();
+        }
+        PubSubReport {
+            load: LoadReport::since(start, delivered, latencies),
+            lost: (expected * counts.len() - delivered) as u64,
+            fairness: fairness(&counts),
+        }
+    }
+}
+
+impl fmt::Display for PubSubReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  lost {}  fairness {:.3}",
+            self.load, self.lost, self.fairness
+        )
+    }
+}
+
+/// Publishes `rounds` messages per topic from each publisher through tokio's
+/// broadcast channels.
+pub fn tokio(publishers: usize, subscribers: usize, topics: usize, rounds: usize) -> PubSubReport {
+    use tokio::sync::broadcast::{self, error::RecvError};
+
+    let expected = publishers * rounds;
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let senders: Vec<_> = (0..topics)
+            .map(|_| broadcast::channel::<Instant>(CAPACITY).0)
+            .collect();
+        let receivers: Vec<_> = (0..subscribers)
+            .map(|i| senders[i % topics].subscribe())
+            .collect();
+
+        let start = Instant::now();
+        let subscribers: Vec<_> = receivers
+            .into_iter()
+            .map(|mut receiver| {
+                tokio::spawn(async move {
+                    let mut latencies = latency_histogram();
+                    let mut received = 0;
+                    loop {
+                        match receiver.recv().await {
+                            Ok(published) => {
+                                record(&mut latencies, published.elapsed());
+                                received += 1;
+                            }
+                            Err(RecvError::Lagged(_)) => {}
+                            Err(RecvError::Closed) => break,
+                        }
+                    }
+                    (latencies, received)
+                })
+            })
+            .collect();
+        let publishers: Vec<_> = (0..publishers)
+            .map(|_| {
+                let senders = senders.clone();
+                tokio::spawn(async move {
+                    for _ in 0..rounds {
+                        for sender in &senders {
+                            let _ = sender.send(Instant::now());
+                        }
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect();
+        drop(senders);
+
+        for publisher in publishers {
+            publisher.await.unwrap();
+        }
+        let mut results = Vec::with_capacity(subscribers.len());
+        for subscriber in subscribers {
+            results.push(subscriber.await.unwrap());
+        }
+        PubSubReport::new(start, expected, results)
+    })
+}
+
+/// Publishes `rounds` messages per topic from each publisher through
+/// async-broadcast channels on async_std.
+pub fn async_std(
+    publishers: usize,
+    subscribers: usize,
+    topics: usize,
+    rounds: usize,
+) -> PubSubReport {
+    use async_broadcast::RecvError;
+    use async_std::task;
+
+    let expected = publishers * rounds;
+    task::block_on(async {
+        let mut senders = Vec::with_capacity(topics);
+        let mut topic_receivers = Vec::with_capacity(topics);
+        for _ in 0..topics {
+            let (mut sender, receiver) = async_broadcast::broadcast::<Instant>(CAPACITY);
+            sender.set_await_active(false);
+            senders.push(sender);
+            topic_receivers.push(receiver);
+        }
+        let receivers: Vec<_> = (0..subscribers)
+            .map(|i| topic_receivers[i % topics].clone())
+            .collect();
+        drop(topic_receivers);
+
+        let start = Instant::now();
+        let subscribers: Vec<_> = receivers
+            .into_iter()
+            .map(|mut receiver| {
+                task::spawn(async move {
+                    let mut latencies = latency_histogram();
+                    let mut received = 0;
+                    loop {
+                        match receiver.recv().await {
+                            Ok(published) => {
+                                record(&mut latencies, published.elapsed());
+                                received += 1;
+                            }
+                            Err(RecvError::Overflowed(_)) => {}
+                            Err(RecvError::Closed) => break,
+                        }
+                    }
+                    (latencies, received)
+                })
+            })
+            .collect();
+        let publishers: Vec<_> = (0..publishers)
+            .map(|_| {
+                let senders = senders.clone();
+                task::spawn(async move {
+                    for _ in 0..rounds {
+                        for sender in &senders {
+                            let _ = sender.broadcast(Instant::now()).await;
+                        }
+                        task::yield_now().await;
+                    }
+                })
+            })
+            .collect();
+        drop(senders);
+
+        for publisher in publishers {
+            publisher.await;
+        }
+        let mut results = Vec::with_capacity(subscribers.len());
+        for subscriber in subscribers {
+            results.push(subscriber.await);
+        }
+        PubSubReport::new(start, expected, results)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_subscriber_hears_its_topic() {
+        let report = async_std(3, 8, 4, 50);
+        assert_eq!(report.load.requests, 3 * 8 * 50);
+        assert_eq!(report.lost, 0);
+        assert_eq!(report.fairness, 1.0);
+
+        let report = tokio(3, 8, 4, 50);
+        assert_eq!(report.load.requests + report.lost, 3 * 8 * 50);
+    }
+}
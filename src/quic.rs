@@ -0,0 +1,118 @@
+//! QUIC echo over loopback with quinn.
+//!
+//! quinn abstracts over the runtime it runs on, so the same echo code is driven
+//! by tokio or by async_std just by choosing the [`quinn::Runtime`].
+
+use crate::harness::{drive, LoadReport};
+use crate::tls::Identity;
+use quinn::{
+    ClientConfig, Endpoint, EndpointConfig, Runtime, ServerConfig, TransportConfig, VarInt,
+};
+use std::net::UdpSocket;
+use std::sync::Arc;
+
+/// Echoes `rounds` messages of `payload` bytes, `streams` at a time, on tokio.
+pub fn tokio(streams: usize, rounds: usize, payload: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(echo(
+        Arc::new(quinn::TokioRuntime),
+        streams,
+        rounds,
+        payload,
+    ))
+}
+
+/// Echoes `rounds` messages of `payload` bytes, `streams` at a time, on async_std.
+pub fn async_std(streams: usize, rounds: usize, payload: usize) -> LoadReport {
+    async_std::task::block_on(echo(
+        Arc::new(quinn::AsyncStdRuntime),
+        streams,
+        rounds,
+        payload,
+    ))
+}
+
+/// Opens one connection to an in-process echo server and sends every message
+/// on its own bidirectional stream.
+async fn echo(
+    runtime: Arc<dyn Runtime>,
+    streams: usize,
+    rounds: usize,
+    payload: usize,
+) -> LoadReport {
+    let identity = Identity::generate();
+
+    let mut transport = TransportConfig::default();
+    transport.max_concurrent_bidi_streams(VarInt::from_u32(streams as u32));
+    let mut server_config =
+        ServerConfig::with_single_cert(vec![identity.cert.clone()], identity.key()).unwrap();
+    server_config.transport_config(Arc::new(transport));
+    let server = endpoint(Some(server_config), runtime.clone());
+    let addr = server.local_addr().unwrap();
+    runtime.spawn(Box::pin(serve(server.clone(), runtime.clone())));
+
+    let mut client = endpoint(None, runtime);
+    client.set_default_client_config(
+        ClientConfig::with_root_certificates(Arc::new(identity.roots())).unwrap(),
+    );
+    let connection = client.connect(addr, "localhost").unwrap().await.unwrap();
+
+    let message = vec![0u8; payload];
+    let report = drive(streams, rounds, || async {
+        let (mut send, mut recv) = connection.open_bi().await.unwrap();
+        send.write_all(&message).await.unwrap();
+        send.finish().unwrap();
+        let echoed = recv.read_to_end(payload).await.unwrap();
+        assert_eq!(echoed.len(), payload);
+    })
+    .await;
+
+    connection.close(VarInt::from_u32(0), b"done");
+    client.wait_idle().await;
+    server.close(VarInt::from_u32(0), b"done");
+    report.with_bytes((rounds * payload) as u64)
+}
+
+/// Creates an endpoint on a free loopback port.
+fn endpoint(server_config: Option<ServerConfig>, runtime: Arc<dyn Runtime>) -> Endpoint {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    Endpoint::new(EndpointConfig::default(), server_config, socket, runtime).unwrap()
+}
+
+/// Accepts connections and echoes every bidirectional stream back to its sender.
+async fn serve(endpoint: Endpoint, runtime: Arc<dyn Runtime>) {
+    while let Some(incoming) = endpoint.accept().await {
+        let connection = match incoming.await {
+            Ok(connection) => connection,
+            Err(_) => continue,
+        };
+        let streams = runtime.clone();
+        runtime.spawn(Box::pin(async move {
+            while let Ok((mut send, mut recv)) = connection.accept_bi().await {
+                streams.spawn(Box::pin(async move {
+                    if let Ok(message) = recv.read_to_end(usize::MAX).await {
+                        let _ = send.write_all(&message).await;
+                        let _ = send.finish();
+                    }
+                }));
+            }
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokio_echoes() {
+        let report = tokio(4, 20, 1024);
+        assert_eq!(report.latencies.len(), 20);
+        assert_eq!(report.bytes, 20 * 1024);
+    }
+
+    #[test]
+    fn async_std_echoes() {
+        assert_eq!(async_std(4, 20, 1024).latencies.len(), 20);
+    }
+}
@@ -0,0 +1,247 @@
+//! Thousands of tasks passing through one token-bucket rate limiter.
+//!
+//! Every task asks for a permit before each of its calls, so the tasks
+//! together always want more than the limit and the limiter alone sets the
+//! pace. The report compares the achieved rate with the target; latencies are
+//! the time each call waited for its permit, which under this load is mostly
+//! the queue of tasks ahead of it.
+//!
+//! governor does not depend on a runtime and sleeps with `futures-timer`, so it
+//! runs on both. The hand-rolled limiter is a semaphore refilled by the
+//! runtime's own interval timer every [`TICK`], up to one tick's worth of
+//! permits, so a late or coalesced tick costs permits and shows up as a lower
+//! achieved rate.
+
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Period at which the hand-rolled limiter hands out new permits.
+pub const TICK: Duration = Duration::from_millis(1);
+
+/// A load report together with the rate the limiter was set to.
+pub struct RateReport {
+    /// Calls per second the limiter should let through.
+    pub target: f64,
+    pub load: LoadReport,
+}
+
+impl RateReport {
+    /// Achieved rate as a share of the target.
+    pub fn accuracy(&self) -> f64 {
+        self.load.throughput() / self.target
+    }
+}
+
+impl fmt::Display for RateReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  accuracy {:>5.1}%",
+            self.load,
+            100.0 * self.accuracy()
+        )
+    }
+}
+
+/// Permits handed out per tick of the hand-rolled limiter at `rate` per second.
+fn per_tick(rate: u32) -> usize {
+    ((f64::from(rate) * TICK.as_secs_f64()).round() as usize).max(1)
+}
+
+/// A governor limiter for `rate` per second that allows bursts of one tick's
+/// worth of calls, like the hand-rolled one.
+fn governor(rate: u32) -> governor::DefaultDirectRateLimiter {
+    use governor::{Quota, RateLimiter};
+
+    let rate = NonZeroU32::new(rate).unwrap();
+    let burst = NonZeroU32::new(per_tick(rate.get()) as u32).unwrap();
+    RateLimiter::direct(Quota::per_second(rate).allow_burst(burst))
+}
+
+/// Makes `calls` calls from `tasks` tokio tasks through governor at `rate` per second.
+pub fn governor_tokio(rate: u32, tasks: usize, calls: usize) -> RateReport {
+    let limiter = Arc::new(governor(rate));
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let start = Instant::now();
+        let handles: Vec<_> = split(calls, tasks)
+            .map(|share| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { wait_each(share, || limiter.until_ready()).await })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for handle in handles {
+            latencies.add(handle.await.unwrap()).unwrap();
+        }
+        RateReport {
+            target: f64::from(rate),
+            load: LoadReport::since(start, calls, latencies),
+        }
+    })
+}
+
+/// Makes `calls` calls from `tasks` async_std tasks through governor at `rate` per second.
+pub fn governor_async_std(rate: u32, tasks: usize, calls: usize) -> RateReport {
+    use async_std::task;
+
+    let limiter = Arc::new(governor(rate));
+    task::block_on(async {
+        let start = Instant::now();
+        let handles: Vec<_> = split(calls, tasks)
+            .map(|share| {
+                let limiter = limiter.clone();
+                task::spawn(async move { wait_each(share, || limiter.until_ready()).await })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for handle in handles {
+            latencies.add(handle.await).unwrap();
+        }
+        RateReport {
+            target: f64::from(rate),
+            load: LoadReport::since(start, calls, latencies),
+        }
+    })
+}
+
+/// Makes `calls` calls from `tasks` tokio tasks through a `tokio::sync::Semaphore`
+/// refilled by `tokio::time::interval` at `rate` per second.
+pub fn semaphore_tokio(rate: u32, tasks: usize, calls: usize) -> RateReport {
+    use tokio::sync::Semaphore;
+
+    let per_tick = per_tick(rate);
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let permits = Arc::new(Semaphore::new(per_tick));
+        let refill = tokio::spawn({
+            let permits = permits.clone();
+            async move {
+                let mut interval = tokio::time::interval(TICK);
+                loop {
+                    interval.tick().await;
+                    permits.add_permits(per_tick.saturating_sub(permits.available_permits()));
+                }
+            }
+        });
+
+        let start = Instant::now();
+        let handles: Vec<_> = split(calls, tasks)
+            .map(|share| {
+                let permits = permits.clone();
+                tokio::spawn(async move {
+                    wait_each(share, || async {
+                        permits.acquire().await.unwrap().forget();
+                    })
+                    .await
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for handle in handles {
+            latencies.add(handle.await.unwrap()).unwrap();
+        }
+        refill.abort();
+        RateReport {
+            target: f64::from(rate),
+            load: LoadReport::since(start, calls, latencies),
+        }
+    })
+}
+
+/// Makes `calls` calls from `tasks` async_std tasks through an `async_lock::Semaphore`
+/// refilled by `async_std::stream::interval` at `rate` per second.
+pub fn semaphore_async_std(rate: u32, tasks: usize, calls: usize) -> RateReport {
+    use async_lock::Semaphore;
+    use async_std::stream::{self, StreamExt};
+    use async_std::task;
+
+    let per_tick = per_tick(rate);
+    task::block_on(async {
+        let permits = Arc::new(Semaphore::new(per_tick));
+        let refill = task::spawn({
+            let permits = permits.clone();
+            async move {
+                let mut interval = stream::interval(TICK);
+                while interval.next().await.is_some() {
+                    // async-lock cannot tell how many permits are left, so the
+                    // unused ones are taken back before the tick's are added.
+                    while let Some(permit) = permits.try_acquire() {
+                        permit.forget();
+                    }
+                    permits.add_permits(per_tick);
+                }
+            }
+        });
+
+        let start = Instant::now();
+        let handles: Vec<_> = split(calls, tasks)
+            .map(|share| {
+                let permits = permits.clone();
+                task::spawn(async move {
+                    wait_each(share, || async {
+                        permits.acquire().await.forget();
+                    })
+                    .await
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for handle in handles {
+            latencies.add(handle.await).unwrap();
+        }
+        let report = RateReport {
+            target: f64::from(rate),
+            load: LoadReport::since(start, calls, latencies),
+        };
+        refill.cancel().await;
+        report
+    })
+}
+
+/// Waits `calls` times for `permit` and records how long each wait took.
+async fn wait_each<F, Fut>(calls: usize, mut permit: F) -> Histogram<u64>
+where
+    F: FnMut() -> Fut,
+    Fut: Future,
+{
+    let mut latencies = latency_histogram();
+    for _ in 0..calls {
+        let asked = Instant::now();
+        permit().await;
+        record(&mut latencies, asked.elapsed());
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_hand_out_at_least_one_permit() {
+        assert_eq!(per_tick(10_000), 10);
+        assert_eq!(per_tick(100), 1);
+    }
+
+    #[test]
+    fn limiters_hold_the_rate() {
+        let limiters: &[fn(u32, usize, usize) -> RateReport] = &[
+            governor_tokio,
+            governor_async_std,
+            semaphore_tokio,
+            semaphore_async_std,
+        ];
+        for limiter in limiters {
+            // 500 calls at 5000/s take at least 90ms after the first burst.
+            let report = limiter(5_000, 50, 500);
+            assert_eq!(report.load.latencies.len(), 500);
+            assert!(report.load.elapsed >= Duration::from_millis(90));
+        }
+    }
+}
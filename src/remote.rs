@@ -0,0 +1,166 @@
+//! Running benchmarks built for another machine on it over SSH and
+//! collecting their results, so that runs on a Raspberry Pi or a Graviton
+//! instance land in the same result files as local ones.
+//!
+//! The benchmark binaries are cross-compiled on the host, e.g. with
+//! `cargo bench --no-run --target aarch64-unknown-linux-gnu`, which prints
+//! their paths. [`collect`] copies them to a directory of the remote
+//! machine with `scp`, runs each there with `ssh` and `RESULTS` set, so that
+//! the usual runner appends to a result file on the remote side, and copies
+//! that file back. Runs on several machines are merged like files from
+//! different systems, see [`results`](crate::results). Nothing is installed
+//! on the remote machine but the binaries, which need no toolchain there.
+//!
+//! [`Host::start`] starts a server the same way and keeps it running while
+//! the benchmarks of other machines connect to it, for comparisons over a
+//! real network that loopback cannot stand in for.
+
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+/// Directory on the remote machine, relative to the home directory, that
+/// the binaries are copied to and run in.
+const DIRECTORY: &str = "async-runtimes-cmp";
+
+/// Name of the result file in that directory.
+const RESULTS: &str = "results.txt";
+
+/// A machine reachable with `ssh` and `scp`, as `user@host` or a host alias.
+pub struct Host {
+    pub address: String,
+}
+
+impl Host {
+    /// The name of the machine for others to connect to, without the user.
+    pub fn name(&self) -> &str {
+        self.address.rsplit('@').next().unwrap()
+    }
+
+    /// Copies `binary` to the machine and starts it there with `args`,
+    /// waiting for the first line it prints. The binary is expected to exit
+    /// once its standard input, which stays connected to the returned child
+    /// through `ssh`, is closed, like `echo_server` does.
+    pub fn start(&self, binary: &Path, args: &[&str]) -> io::Result<(Child, String)> {
+        self.ssh(&format!("mkdir -p {}", DIRECTORY))?;
+        let name = copy(self, binary)?;
+        let args: Vec<_> = args.iter().map(|arg| quote(arg)).collect();
+        let mut child = Command::new("ssh")
+            .arg(&self.address)
+            .arg(format!(
+                "cd {} && ./{} {}",
+                DIRECTORY,
+                quote(&name),
+                args.join(" ")
+            ))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut line)?;
+        Ok((child, line.trim().to_owned()))
+    }
+
+    /// Runs `command` in a shell on the machine.
+    fn ssh(&self, command: &str) -> io::Result<()> {
+        succeed(
+            Command::new("ssh").arg(&self.address).arg(command),
+            &self.address,
+        )
+    }
+
+    /// Copies `from`, on this machine or the remote one as `scp` names them,
+    /// to `to`.
+    fn scp(&self, from: &str, to: &str) -> io::Result<()> {
+        succeed(Command::new("scp").args(["-q", from, to]), &self.address)
+    }
+
+    /// A path in the working directory on the machine, as `scp` names it.
+    fn remote(&self, file: &str) -> String {
+        format!("{}:{}/{}", self.address, DIRECTORY, file)
+    }
+}
+
+fn succeed(command: &mut Command, address: &str) -> io::Result<()> {
+    let status = command.status()?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("{:?} on {} failed: {}", command, address, status),
+        ));
+    }
+    Ok(())
+}
+
+/// Copies `binary` to the working directory on `host` and returns its name
+/// there.
+fn copy(host: &Host, binary: &Path) -> io::Result<String> {
+    let name = binary
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a binary"))?;
+    host.scp(&binary.to_string_lossy(), &host.remote(name))?;
+    Ok(name.to_owned())
+}
+
+/// Quotes `word` for a POSIX shell.
+fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+/// The command running `binary` in the working directory with `env` set,
+/// appending to the result file.
+fn run_command(binary: &str, env: &[(String, String)], args: &[String]) -> String {
+    let mut command = format!("cd {} && env RESULTS={}", DIRECTORY, RESULTS);
+    for (name, value) in env {
+        command += &format!(" {}={}", name, quote(value));
+    }
+    command += &format!(" ./{}", quote(binary));
+    for arg in args {
+        command += &format!(" {}", quote(arg));
+    }
+    command
+}
+
+/// Copies `binaries` to `host`, runs each of them there with `env` set and
+/// `args` passed, and returns their result file.
+pub fn collect(
+    host: &Host,
+    binaries: &[&Path],
+    env: &[(String, String)],
+    args: &[String],
+) -> io::Result<String> {
+    host.ssh(&format!(
+        "mkdir -p {0} && rm -f {0}/{1}",
+        DIRECTORY, RESULTS
+    ))?;
+    for binary in binaries {
+        let name = copy(host, binary)?;
+        host.ssh(&run_command(&name, env, args))?;
+    }
+    let local = std::env::temp_dir().join(format!(
+        "async-runtimes-cmp-{}-{}",
+        host.address
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "-"),
+        std::process::id()
+    ));
+    host.scp(&host.remote(RESULTS), &local.to_string_lossy())?;
+    let results = std::fs::read_to_string(&local);
+    let _ = std::fs::remove_file(&local);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_what_it_passes_to_the_shell() {
+        let env = [("HTTP_CONCURRENCY".to_owned(), "64".to_owned())];
+        assert_eq!(
+            run_command("http-1a2b", &env, &["it's".to_owned()]),
+            "cd async-runtimes-cmp && env RESULTS=results.txt \
+             HTTP_CONCURRENCY='64' ./'http-1a2b' 'it'\\''s'"
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! How repeatable the report of every scenario is over several runs of the
+//! benchmark, and whether its noise drowns the differences between
+//! runtimes.
+//!
+//! The figure a scenario is judged by is its first rate, like `req/s`, or
+//! else its first time, like a p50 in `ms`. Its repeatability is the
+//! coefficient of variation of that figure over the runs, the standard
+//! deviation as a share of the mean. A scenario is flagged as noisy when its
+//! mean and that of another runtime on the same workload, as the scenario
+//! names split it, differ by less than two of their combined standard
+//! deviations, in which case the runs cannot tell the two apart.
+
+use crate::results;
+use crate::scaling;
+
+/// Mean and standard deviation of the figure of a scenario over the runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Spread {
+    pub(crate) mean: f64,
+    pub(crate) stddev: f64,
+}
+
+impl Spread {
+    fn of(figures: &[f64]) -> Self {
+        let n = figures.len() as f64;
+        let mean = figures.iter().sum::<f64>() / n;
+        let variance = figures
+            .iter()
+            .map(|figure| (figure - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0).max(1.0);
+        Spread {
+            mean,
+            stddev: variance.sqrt(),
+        }
+    }
+
+    /// The standard deviation as a share of the mean.
+    pub(crate) fn variation(&self) -> f64 {
+        self.stddev / self.mean.abs()
+    }
+
+    /// Whether the difference to `other` is within the noise of both.
+    fn overlaps(&self, other: &Spread) -> bool {
+        let noise = (self.stddev.powi(2) + other.stddev.powi(2)).sqrt();
+        (self.mean - other.mean).abs() < 2.0 * noise
+    }
+}
+
+/// The figure a report is judged by: its first rate, or else its first
+/// time in milli-, micro- or nanoseconds, in that unit.
+pub(crate) fn figure(report: &str) -> Option<f64> {
+    scaling::throughput(report).or_else(|| {
+        report.split_whitespace().find_map(|word| {
+            ["ms", "us", "ns"]
+                .iter()
+                .find_map(|unit| word.strip_suffix(unit)?.parse().ok())
+        })
+    })
+}
+
+/// The spread of every scenario of `curves` over its runs, with the other
+/// runtimes on its workload that it cannot be told apart from.
+pub(crate) fn repeatability<'a>(
+    curves: &[(&'a str, Vec<(usize, &str)>)],
+) -> Vec<(&'a str, Spread, Vec<&'static str>)> {
+    let spreads: Vec<(&str, Spread)> = curves
+        .iter()
+        .filter_map(|(name, curve)| {
+            let figures: Vec<f64> = curve
+                .iter()
+                .map(|(_, report)| figure(report))
+                .collect::<Option<_>>()?;
+            Some((*name, Spread::of(&figures)))
+        })
+        .collect();
+    spreads
+        .iter()
+        .map(|&(name, spread)| {
+            let (workload, runtime) = results::split_runtime(name);
+            let overlapping = spreads
+                .iter()
+                .filter_map(|&(other, other_spread)| {
+                    let (other_workload, other_runtime) = results::split_runtime(other);
+                    (other_workload == workload
+                        && other_runtime != runtime
+                        && spread.overlaps(&other_spread))
+                    .then_some(other_runtime)
+                })
+                .collect();
+            (name, spread, overlapping)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn judges_by_the_first_rate_or_time() {
+        assert_eq!(figure("   12000 req/s  p50 1.000ms"), Some(12000.0));
+        assert_eq!(
+            figure("     100 sleeps  overshoot p50    1.500ms"),
+            Some(1.5)
+        );
+        assert_eq!(figure("no figures"), None);
+    }
+
+    #[test]
+    fn flags_runtimes_within_the_noise() {
+        let curves = vec![
+            (
+                "spawn_tokio",
+                vec![(1, "100 ops/s"), (2, "110 ops/s"), (3, "90 ops/s")],
+            ),
+            (
+                "spawn_smol",
+                vec![(1, "104 ops/s"), (2, "96 ops/s"), (3, "100 ops/s")],
+            ),
+            (
+                "spawn_async_std",
+                vec![(1, "500 ops/s"), (2, "501 ops/s"), (3, "499 ops/s")],
+            ),
+        ];
+        let scores = repeatability(&curves);
+        assert_eq!(scores[0].1.mean, 100.0);
+        assert!((scores[0].1.variation() - 0.1).abs() < 1e-9);
+        assert_eq!(scores[0].2, ["smol"]);
+        assert_eq!(scores[2].2, Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,438 @@
+//! Result files, for comparing runs of the same scenarios on different
+//! operating systems.
+//!
+//! With `RESULTS` set to a path, the [`Runner`](crate::harness::Runner)
+//! appends the line it prints for every scenario to that file, after a
+//! header naming the system the run is on: the OS, the architecture, the
+//! number of cores, the readiness mechanism the runtimes do their IO on and
+//! notes on what the OS measures differently. Every benchmark appends a
+//! header and lines of its own, so one file can hold a whole `cargo bench`.
+//! The header records what the checks of [`preflight`](crate::preflight)
+//! found too, so that a run on a machine set up to skew them can be told.
+//! With `RESULTS_BUILD` set too, the header names the build the same way,
+//! e.g. the toolchain and profile [`builds`](crate::builds) built it with,
+//! and runs of different builds on one system are told apart by it.
+//!
+//! tokio, through mio, and async_std and smol, through async-io and polling,
+//! all do their IO on epoll on Linux, kqueue on macOS and the BSDs and IOCP
+//! on Windows, so the mechanism is the OS's rather than the runtime's. Files
+//! from runs on different systems are merged by [`merge`], which lines up
+//! the reports of every scenario from every system; the `merge_results`
+//! binary prints them.
+//!
+//! [`Matrix`] arranges the same runs the other way, for teams shipping on
+//! all of the systems: the scenarios of every workload by runtime, as named
+//! by the scenario, against the systems, with the headline figure of each
+//! report in the cells: its first rate, like `req/s`, or else its first
+//! figure. Scenarios naming no
+//! runtime, like the baselines on threads, are a workload of their own.
+
+use crate::preflight;
+use std::env;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+
+/// Start of the header lines of a result file.
+const HEADER: &str = "# ";
+
+/// The system a run is on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct System {
+    pub os: String,
+    pub arch: String,
+    pub cores: usize,
+    /// The readiness mechanism the runtimes do their IO on.
+    pub io: String,
+    /// What the OS measures differently, that a comparison should bear in
+    /// mind.
+    pub notes: Vec<String>,
+    /// The build the run was of, if named.
+    pub build: String,
+    /// What every pre-flight check found, by name.
+    pub checks: Vec<(String, String)>,
+}
+
+impl System {
+    /// The system this process runs on.
+    pub fn current() -> Self {
+        System {
+            os: env::consts::OS.to_owned(),
+            arch: env::consts::ARCH.to_owned(),
+            cores: thread::available_parallelism().map_or(1, |cores| cores.get()),
+            io: io_mechanism(env::consts::OS).to_owned(),
+            notes: notes(env::consts::OS)
+                .iter()
+                .map(|&note| note.to_owned())
+                .collect(),
+            build: env::var("RESULTS_BUILD").unwrap_or_default(),
+            checks: preflight::checks()
+                .into_iter()
+                .map(|check| (check.name.to_owned(), check.value))
+                .collect(),
+        }
+    }
+
+    /// Short label of the system, like `linux-aarch64/epoll`, which tells
+    /// runs on machines of different architectures apart, followed by the
+    /// build if named.
+    pub fn label(&self) -> String {
+        let mut label = if self.arch.is_empty() {
+            format!("{}/{}", self.os, self.io)
+        } else {
+            format!("{}-{}/{}", self.os, self.arch, self.io)
+        };
+        if !self.build.is_empty() {
+            label += &format!(" {}", self.build);
+        }
+        label
+    }
+
+    /// Writes the header of a run on this system.
+    fn write_header(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "{}os {}", HEADER, self.os)?;
+        writeln!(out, "{}arch {}", HEADER, self.arch)?;
+        writeln!(out, "{}cores {}", HEADER, self.cores)?;
+        writeln!(out, "{}io {}", HEADER, self.io)?;
+        for note in &self.notes {
+            writeln!(out, "{}note {}", HEADER, note)?;
+        }
+        if !self.build.is_empty() {
+            writeln!(out, "{}build {}", HEADER, self.build)?;
+        }
+        for (name, value) in &self.checks {
+            writeln!(out, "{}check {} {}", HEADER, name, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for System {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} cores, {} IO", self.arch, self.cores, self.io)
+    }
+}
+
+/// The readiness mechanism of `os` that mio and polling use.
+fn io_mechanism(os: &str) -> &'static str {
+    match os {
+        "linux" | "android" => "epoll",
+        "macos" | "ios" | "freebsd" | "netbsd" | "openbsd" | "dragonfly" => "kqueue",
+        "windows" => "IOCP",
+        _ => "unknown",
+    }
+}
+
+/// What the harness cannot measure on `os`, or measures differently.
+fn notes(os: &str) -> &'static [&'static str] {
+    match os {
+        "linux" => &[],
+        "windows" => &[
+            "sleeps and timeouts wake at the system timer tick, 15.6 ms by default",
+            "no getrusage or /proc: CPU time, context switches, page faults, rss and threads are n/a",
+            "signal scenarios are Unix only and do not run",
+            "CPU_QUOTA, NUMA, COUNT_SYSCALLS and --energy need Linux",
+        ],
+        _ => &[
+            "no /proc: rss and threads are n/a",
+            "CPU_QUOTA, NUMA, COUNT_SYSCALLS and --energy need Linux",
+        ],
+    }
+}
+
+/// A result file being written, or `None` where `RESULTS` is not set.
+pub(crate) fn create() -> Option<File> {
+    let path = env::var_os("RESULTS")?;
+    let path = Path::new(&path);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap_or_else(|err| panic!("cannot open {}: {}", path.display(), err));
+    System::current()
+        .write_header(&mut file)
+        .unwrap_or_else(|err| panic!("cannot write {}: {}", path.display(), err));
+    Some(file)
+}
+
+/// The runtimes a scenario name may contain, as a part of it between `_`s or
+/// `/`s, the executors of the wasm32 scenarios included.
+const RUNTIMES: [&str; 5] = [
+    "tokio",
+    "async_std",
+    "smol",
+    "wasm_bindgen_futures",
+    "local_pool",
+];
+
+/// The scenarios of one run and the system it was on.
+#[derive(Debug, Default, PartialEq)]
+pub struct Run {
+    pub system: System,
+    /// The report of every scenario, in the order they ran.
+    pub reports: Vec<(String, String)>,
+}
+
+/// Parses the runs in a result file.
+pub fn parse(results: &str) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    let mut in_header = false;
+    for line in results.lines() {
+        if let Some(field) = line.strip_prefix(HEADER) {
+            if !in_header {
+                runs.push(Run::default());
+                in_header = true;
+            }
+            let system = &mut runs.last_mut().unwrap().system;
+            let (key, value) = field.split_once(' ').unwrap_or((field, ""));
+            match key {
+                "os" => system.os = value.to_owned(),
+                "arch" => system.arch = value.to_owned(),
+                "cores" => system.cores = value.parse().unwrap_or(0),
+                "io" => system.io = value.to_owned(),
+                "note" => system.notes.push(value.to_owned()),
+                "build" => system.build = value.to_owned(),
+                "check" => {
+                    let (name, value) = value.split_once(' ').unwrap_or((value, ""));
+                    system.checks.push((name.to_owned(), value.to_owned()));
+                }
+                _ => {}
+            }
+            continue;
+        }
+        in_header = false;
+        let (name, report) = match line.split_once(' ') {
+            Some((name, report)) if !name.is_empty() => (name, report.trim_start()),
+            _ => continue,
+        };
+        if runs.is_empty() {
+            runs.push(Run::default());
+        }
+        runs.last_mut()
+            .unwrap()
+            .reports
+            .push((name.to_owned(), report.to_owned()));
+    }
+    runs
+}
+
+/// The report of every scenario on every system it ran on, labelled with
+/// the system, in the order the scenarios first ran.
+pub fn merge(runs: &[Run]) -> Vec<(&str, Vec<(String, &str)>)> {
+    let mut merged: Vec<(&str, Vec<(String, &str)>)> = Vec::new();
+    for run in runs {
+        let label = run.system.label();
+        for (name, report) in &run.reports {
+            let report = (label.clone(), report.as_str());
+            match merged
+                .iter_mut()
+                .find(|(scenario, _)| *scenario == name.as_str())
+            {
+                Some((_, reports)) => reports.push(report),
+                None => merged.push((name.as_str(), vec![report])),
+            }
+        }
+    }
+    merged
+}
+
+/// The workload and the runtime a scenario `name` contains, or the name and
+/// `-` if it names no runtime.
+pub(crate) fn split_runtime(name: &str) -> (String, &'static str) {
+    let separator = |c: Option<char>| c.map_or(true, |c| c == '_' || c == '/');
+    for runtime in RUNTIMES.iter().copied() {
+        for (start, _) in name.match_indices(runtime) {
+            let end = start + runtime.len();
+            let (before, after) = (&name[..start], &name[end..]);
+            if !separator(before.chars().last()) || !separator(after.chars().next()) {
+                continue;
+            }
+            // Drop the runtime with the `_` that joins it to the rest.
+            let workload = match (before.strip_suffix('_'), after.strip_prefix('_')) {
+                (Some(before), _) => format!("{}{}", before, after),
+                (None, Some(after)) => format!("{}{}", before, after),
+                (None, None) => format!("{}{}", before, after),
+            };
+            return (workload, runtime);
+        }
+    }
+    (name.to_owned(), "-")
+}
+
+/// The headline figure of a report: its first rate, a figure in a unit per
+/// second, or else its first two words.
+fn headline(report: &str) -> String {
+    let words: Vec<_> = report.split_whitespace().collect();
+    let pair = words
+        .windows(2)
+        .find(|pair| pair[1].ends_with("/s") && pair[0].parse::<f64>().is_ok())
+        .unwrap_or(&words[..words.len().min(2)]);
+    pair.join(" ")
+}
+
+/// The headline figure of every scenario of every workload by runtime,
+/// against the systems the runs were on.
+#[derive(Debug, PartialEq)]
+pub struct Matrix {
+    /// The labels of the systems, in the order their first runs came.
+    pub systems: Vec<String>,
+    /// Every workload, with a row per runtime and a cell per system.
+    pub workloads: Vec<(String, Vec<(&'static str, Vec<Option<String>>)>)>,
+}
+
+impl Matrix {
+    pub fn new(runs: &[Run]) -> Self {
+        let mut systems: Vec<String> = Vec::new();
+        for run in runs {
+            if !systems.contains(&run.system.label()) {
+                systems.push(run.system.label());
+            }
+        }
+        let mut workloads: Vec<(String, Vec<(&'static str, Vec<Option<String>>)>)> = Vec::new();
+        for (name, reports) in merge(runs) {
+            let (workload, runtime) = split_runtime(name);
+            let rows = match workloads
+                .iter_mut()
+                .position(|(known, _)| *known == workload)
+            {
+                Some(i) => &mut workloads[i].1,
+                None => {
+                    workloads.push((workload, Vec::new()));
+                    &mut workloads.last_mut().unwrap().1
+                }
+            };
+            let mut cells = vec![None; systems.len()];
+            for (label, report) in reports {
+                let system = systems.iter().position(|known| *known == label).unwrap();
+                cells[system] = Some(headline(report));
+            }
+            rows.push((runtime, cells));
+        }
+        Matrix { systems, workloads }
+    }
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Wide enough for every label, as long as builds make them.
+        let width = self
+            .systems
+            .iter()
+            .map(String::len)
+            .max()
+            .unwrap_or(0)
+            .max(20);
+        for (workload, rows) in &self.workloads {
+            write!(f, "{:<40}", workload)?;
+            for system in &self.systems {
+                write!(f, " {:>width$}", system, width = width)?;
+            }
+            writeln!(f)?;
+            for (runtime, cells) in rows {
+                write!(f, "  {:<38}", runtime)?;
+                for cell in cells {
+                    write!(
+                        f,
+                        " {:>width$}",
+                        cell.as_deref().unwrap_or("-"),
+                        width = width
+                    )?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_runs_from_every_system() {
+        let mut linux = Vec::new();
+        let system = System {
+            os: "linux".to_owned(),
+            arch: "x86_64".to_owned(),
+            cores: 8,
+            io: "epoll".to_owned(),
+            notes: Vec::new(),
+            build: "stable O3".to_owned(),
+            checks: vec![("governor".to_owned(), "performance".to_owned())],
+        };
+        system.write_header(&mut linux).unwrap();
+        let mut results = String::from_utf8(linux).unwrap();
+        results += "spawn_tokio      1000 tasks/s\nspawn_smol  900 tasks/s\n";
+        results += "# os windows\n# io IOCP\n# note sleeps round up\n";
+        results += "spawn_tokio      500 tasks/s\n";
+        let runs = parse(&results);
+        assert_eq!(runs[0].system, system);
+        assert_eq!(runs[1].system.notes, ["sleeps round up"]);
+        assert_eq!(
+            merge(&runs),
+            [
+                (
+                    "spawn_tokio",
+                    vec![
+                        ("linux-x86_64/epoll stable O3".to_owned(), "1000 tasks/s"),
+                        ("windows/IOCP".to_owned(), "500 tasks/s")
+                    ]
+                ),
+                (
+                    "spawn_smol",
+                    vec![("linux-x86_64/epoll stable O3".to_owned(), "900 tasks/s")]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_the_runtime_in_a_name() {
+        assert_eq!(split_runtime("spawn_tokio"), ("spawn".to_owned(), "tokio"));
+        assert_eq!(
+            split_runtime("local_async_std_spawn"),
+            ("local_spawn".to_owned(), "async_std")
+        );
+        assert_eq!(
+            split_runtime("spawn_smol/detached"),
+            ("spawn/detached".to_owned(), "smol")
+        );
+        assert_eq!(
+            split_runtime("spawn_threads"),
+            ("spawn_threads".to_owned(), "-")
+        );
+        assert_eq!(
+            split_runtime("signal_tokio_like"),
+            ("signal_like".to_owned(), "tokio")
+        );
+    }
+
+    #[test]
+    fn arranges_runtimes_against_systems() {
+        let runs = parse(
+            "# os linux\n# io epoll\nspawn_tokio spawned 9 in 9ms  1000 tasks/s\nspawn_smol 900 tasks/s\n\
+             # os windows\n# io IOCP\nspawn_tokio 500 tasks/s\n",
+        );
+        let matrix = Matrix::new(&runs);
+        assert_eq!(matrix.systems, ["linux/epoll", "windows/IOCP"]);
+        assert_eq!(
+            matrix.workloads,
+            [(
+                "spawn".to_owned(),
+                vec![
+                    (
+                        "tokio",
+                        vec![
+                            Some("1000 tasks/s".to_owned()),
+                            Some("500 tasks/s".to_owned())
+                        ]
+                    ),
+                    ("smol", vec![Some("900 tasks/s".to_owned()), None]),
+                ]
+            )]
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Operations that fail some of the time and are retried with jittered
+//! exponential backoff.
+//!
+//! Each attempt fails with a fixed probability. After the `n`-th failure of an
+//! operation the task sleeps for a time drawn uniformly between zero and
+//! `base * 2^n`, capped at `cap` ("full jitter"), and gives the operation up
+//! once it has failed `attempts` times. The attempts themselves are instant, so
+//! with many tasks the runtime's timer holds a large number of sleeps at once;
+//! the report shows the goodput, the most sleeps pending at the same time and
+//! how far past their deadlines the sleeps woke up.
+
+use crate::harness::{latency_histogram, record, split, xorshift, LoadReport, Millis};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How operations fail and are retried.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    /// Probability that an attempt fails.
+    pub failure: f64,
+    /// Largest backoff after the first failure.
+    pub base: Duration,
+    /// Largest backoff after any failure.
+    pub cap: Duration,
+    /// Failed attempts after which an operation is given up.
+    pub attempts: u32,
+}
+
+impl Backoff {
+    /// Whether the next attempt fails, drawn with the xorshift state `rng`.
+    fn fails(&self, rng: &mut u64) -> bool {
+        (xorshift(rng) as f64 / u64::MAX as f64) < self.failure
+    }
+
+    /// Time to sleep after the `failures`-th failure, drawn with `rng`.
+    fn delay(&self, failures: u32, rng: &mut u64) -> Duration {
+        let ceiling = self
+            .base
+            .saturating_mul(1 << (failures - 1).min(31_u32))
+            .min(self.cap);
+        Duration::from_nanos(xorshift(rng) % (ceiling.as_nanos() as u64 + 1))
+    }
+}
+
+/// A load report over the operations that eventually succeeded, with the
+/// retries it took and how the runtime's timer coped with the backoff sleeps.
+pub struct RetryReport {
+    /// Goodput and latencies, including backoff, of the successful operations.
+    pub load: LoadReport,
+    /// Attempts made after a failure.
+    pub retries: u64,
+    /// Operations given up after too many failures.
+    pub given_up: u64,
+    /// Most backoff sleeps pending at the same time.
+    pub peak_sleeping: usize,
+    /// Time each backoff sleep overran its deadline.
+    pub oversleep: Histogram<u64>,
+}
+
+impl fmt::Display for RetryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  retries {}  given up {}  peak sleeping {}  oversleep p99 {}",
+            self.load,
+            self.retries,
+            self.given_up,
+            self.peak_sleeping,
+            Millis(self.oversleep.value_at_quantile(0.99))
+        )
+    }
+}
+
+/// Runs `operations` operations from `tasks` tokio tasks, retrying as `backoff` says.
+pub fn tokio(backoff: Backoff, tasks: usize, operations: usize) -> RetryReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let sleeping = Arc::new(Sleeping::default());
+        let start = Instant::now();
+        let handles: Vec<_> = split(operations, tasks)
+            .zip(1..)
+            .map(|(share, seed)| {
+                let run = retrying(backoff, share, seed, sleeping.clone(), tokio::time::sleep);
+                tokio::spawn(run)
+            })
+            .collect();
+        let mut outcomes = Vec::with_capacity(tasks);
+        for handle in handles {
+            outcomes.push(handle.await.unwrap());
+        }
+        RetryReport::new(start, outcomes, &sleeping)
+    })
+}
+
+/// Runs `operations` operations from `tasks` async_std tasks, retrying as `backoff` says.
+pub fn async_std(backoff: Backoff, tasks: usize, operations: usize) -> RetryReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let sleeping = Arc::new(Sleeping::default());
+        let start = Instant::now();
+        let handles: Vec<_> = split(operations, tasks)
+            .zip(1..)
+            .map(|(share, seed)| {
+                task::spawn(retrying(
+                    backoff,
+                    share,
+                    seed,
+                    sleeping.clone(),
+                    task::sleep,
+                ))
+            })
+            .collect();
+        let mut outcomes = Vec::with_capacity(tasks);
+        for handle in handles {
+            outcomes.push(handle.await);
+        }
+        RetryReport::new(start, outcomes, &sleeping)
+    })
+}
+
+/// Backoff sleeps pending now and at most.
+#[derive(Default)]
+struct Sleeping {
+    now: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+/// What one task saw.
+///
+/// Timings are kept in plain vectors rather than a histogram per task, which
+/// would take more memory than everything else together with many tasks.
+struct Outcome {
+    latencies: Vec<Duration>,
+    oversleep: Vec<Duration>,
+    retries: u64,
+    given_up: u64,
+}
+
+impl RetryReport {
+    fn new(start: Instant, outcomes: Vec<Outcome>, sleeping: &Sleeping) -> Self {
+        let mut latencies = latency_histogram();
+        let mut oversleep = latency_histogram();
+        let mut retries = 0;
+        let mut given_up = 0;
+        for outcome in outcomes {
+            for latency in outcome.latencies {
+                record(&mut latencies, latency);
+            }
+            for overrun in outcome.oversleep {
+                record(&mut oversleep, overrun);
+            }
+            retries += outcome.retries;
+            given_up += outcome.given_up;
+        }
+        let successes = latencies.len() as usize;
+        RetryReport {
+            load: LoadReport::since(start, successes, latencies),
+            retries,
+            given_up,
+            peak_sleeping: sleeping.peak.load(Ordering::Relaxed),
+            oversleep,
+        }
+    }
+}
+
+/// Runs `operations` operations one after another, backing off with `sleep`.
+async fn retrying<F, Fut>(
+    backoff: Backoff,
+    operations: usize,
+    seed: u64,
+    sleeping: Arc<Sleeping>,
+    sleep: F,
+) -> Outcome
+where
+    F: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut rng = seed;
+    let mut outcome = Outcome {
+        latencies: Vec::new(),
+        oversleep: Vec::new(),
+        retries: 0,
+        given_up: 0,
+    };
+    for _ in 0..operations {
+        let start = Instant::now();
+        let mut failures = 0;
+        while backoff.fails(&mut rng) {
+            failures += 1;
+            if failures == backoff.attempts {
+                break;
+            }
+            let delay = backoff.delay(failures, &mut rng);
+            let now = sleeping.now.fetch_add(1, Ordering::Relaxed) + 1;
+            sleeping.peak.fetch_max(now, Ordering::Relaxed);
+            let asleep = Instant::now();
+            sleep(delay).await;
+            outcome
+                .oversleep
+                .push(asleep.elapsed().saturating_sub(delay));
+            sleeping.now.fetch_sub(1, Ordering::Relaxed);
+            outcome.retries += 1;
+        }
+        if failures == backoff.attempts {
+            outcome.given_up += 1;
+        } else {
+            outcome.latencies.push(start.elapsed());
+        }
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backoff(failure: f64) -> Backoff {
+        Backoff {
+            failure,
+            base: Duration::from_millis(1),
+            cap: Duration::from_millis(8),
+            attempts: 5,
+        }
+    }
+
+    #[test]
+    fn delays_grow_up_to_the_cap() {
+        let backoff = backoff(0.5);
+        let mut rng = 1;
+        for failures in 1..10 {
+            let ceiling = Duration::from_millis(1 << (failures - 1).min(3));
+            for _ in 0..100 {
+                assert!(backoff.delay(failures, &mut rng) <= ceiling);
+            }
+        }
+    }
+
+    #[test]
+    fn every_operation_succeeds_or_is_given_up() {
+        for run in &[tokio, async_std] {
+            let report = run(backoff(0.5), 20, 200);
+            assert_eq!(report.load.requests + report.given_up, 200);
+            assert!(report.retries > 0);
+            assert!(report.peak_sleeping <= 20);
+        }
+        assert_eq!(tokio(backoff(0.0), 4, 40).retries, 0);
+    }
+}
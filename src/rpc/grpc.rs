@@ -0,0 +1,131 @@
+//! gRPC echo service with tonic, which only runs on tokio.
+//!
+//! All calls share one channel, i.e. one HTTP/2 connection, the way a service
+//! would normally talk to its dependency.
+
+use super::proto::echo_client::EchoClient;
+use super::proto::echo_server::{Echo, EchoServer};
+use super::proto::Message;
+use crate::harness::{drive, latency_histogram, record, split, LoadReport};
+use futures::stream::BoxStream;
+use hdrhistogram::Histogram;
+use std::time::Instant;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::transport::{Channel, Server};
+use tonic::{Request, Response, Status, Streaming};
+
+struct EchoService;
+
+#[tonic::async_trait]
+impl Echo for EchoService {
+    async fn unary(&self, request: Request<Message>) -> Result<Response<Message>, Status> {
+        Ok(Response::new(request.into_inner()))
+    }
+
+    type StreamStream = BoxStream<'static, Result<Message, Status>>;
+
+    async fn stream(
+        &self,
+        request: Request<Streaming<Message>>,
+    ) -> Result<Response<Self::StreamStream>, Status> {
+        Ok(Response::new(Box::pin(request.into_inner())))
+    }
+}
+
+/// Sends `requests` unary calls of `payload` bytes, `concurrency` at a time.
+pub fn unary(concurrency: usize, requests: usize, payload: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let client = EchoClient::new(serve().await);
+        let message = Message {
+            payload: vec![0; payload],
+        };
+        drive(concurrency, requests, || {
+            let mut client = client.clone();
+            let message = message.clone();
+            async move {
+                let echoed = client.unary(message).await.unwrap().into_inner();
+                assert_eq!(echoed.payload.len(), payload);
+            }
+        })
+        .await
+        .with_bytes((requests * payload) as u64)
+    })
+}
+
+/// Opens `concurrency` bidirectional streams and exchanges `requests` messages
+/// of `payload` bytes over them, waiting for each echo before sending the next.
+pub fn streaming(concurrency: usize, requests: usize, payload: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let client = EchoClient::new(serve().await);
+        let start = Instant::now();
+        let streams: Vec<_> = split(requests, concurrency)
+            .map(|share| tokio::spawn(ping_pong(client.clone(), share, payload)))
+            .collect();
+        let mut latencies = latency_histogram();
+        for stream in streams {
+            latencies.add(stream.await.unwrap()).unwrap();
+        }
+        LoadReport::since(start, requests, latencies).with_bytes((requests * payload) as u64)
+    })
+}
+
+/// Exchanges `rounds` messages over a single bidirectional stream.
+async fn ping_pong(
+    mut client: EchoClient<Channel>,
+    rounds: usize,
+    payload: usize,
+) -> Histogram<u64> {
+    let (requests, outgoing) = mpsc::channel(1);
+    let mut responses = client
+        .stream(ReceiverStream::new(outgoing))
+        .await
+        .unwrap()
+        .into_inner();
+    let mut latencies = latency_histogram();
+    for _ in 0..rounds {
+        let message = Message {
+            payload: vec![0; payload],
+        };
+        let start = Instant::now();
+        requests.send(message).await.unwrap();
+        let echoed = responses.message().await.unwrap().unwrap();
+        assert_eq!(echoed.payload.len(), payload);
+        record(&mut latencies, start.elapsed());
+    }
+    latencies
+}
+
+/// Starts the echo server on the current runtime and connects a channel to it.
+async fn serve() -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_service(EchoServer::new(EchoService))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unary_calls_complete() {
+        assert_eq!(unary(4, 20, 64).latencies.len(), 20);
+    }
+
+    #[test]
+    fn streams_complete() {
+        assert_eq!(streaming(4, 20, 64).latencies.len(), 20);
+    }
+}
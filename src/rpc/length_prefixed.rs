@@ -0,0 +1,251 @@
+//! Echo over the hand-rolled length-prefixed protocol from [`crate::framing`].
+//!
+//! Each connection has one request in flight, so `concurrency` is the number of
+//! connections. The protocol code is written against the `futures` IO traits;
+//! on tokio the sockets are adapted with `tokio_util::compat`.
+//!
+//! Besides running both sides on one runtime, the server can run on its own in
+//! another process, see the `echo_server` binary, and be measured with the
+//! `*_client` functions, so that server and clients do not share an executor.
+
+use crate::framing::{read_frame, write_frame};
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use futures::io::{AsyncRead, AsyncWrite};
+use hdrhistogram::Histogram;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+/// Echoes `requests` messages of `payload` bytes over `concurrency` connections on async_std.
+pub fn async_std(concurrency: usize, requests: usize, payload: usize) -> LoadReport {
+    use async_std::net::TcpListener;
+    use async_std::task;
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept = task::spawn(async_std_accept(listener));
+        let report = async_std_clients(addr, concurrency, requests, payload).await;
+        accept.cancel().await;
+        report
+    })
+}
+
+/// Echoes `requests` messages of `payload` bytes over `concurrency` connections
+/// on async_std, against a server at `addr` that runs elsewhere.
+pub fn async_std_client(
+    addr: SocketAddr,
+    concurrency: usize,
+    requests: usize,
+    payload: usize,
+) -> LoadReport {
+    async_std::task::block_on(async_std_clients(addr, concurrency, requests, payload))
+}
+
+/// Serves the echo protocol on `listener` with async_std until the process exits.
+pub fn async_std_server(listener: std::net::TcpListener) {
+    async_std::task::block_on(async_std_accept(listener.into()));
+}
+
+async fn async_std_accept(listener: async_std::net::TcpListener) {
+    loop {
+        let (stream, _) = listener.accept().await.unwrap();
+        stream.set_nodelay(true).unwrap();
+        async_std::task::spawn(serve(stream));
+    }
+}
+
+async fn async_std_clients(
+    addr: SocketAddr,
+    concurrency: usize,
+    requests: usize,
+    payload: usize,
+) -> LoadReport {
+    use async_std::net::TcpStream;
+    use async_std::task;
+
+    let start = Instant::now();
+    let connections: Vec<_> = split(requests, concurrency)
+        .map(|share| {
+            task::spawn(async move {
+                let stream = TcpStream::connect(addr).await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                ping_pong(stream, share, payload).await
+            })
+        })
+        .collect();
+    let mut latencies = latency_histogram();
+    for connection in connections {
+        latencies.add(connection.await).unwrap();
+    }
+    LoadReport::since(start, requests, latencies).with_bytes((requests * payload) as u64)
+}
+
+/// Echoes `requests` messages of `payload` bytes over `concurrency` connections on tokio.
+pub fn tokio(concurrency: usize, requests: usize, payload: usize) -> LoadReport {
+    use tokio::net::TcpListener;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(tokio_accept(listener));
+        tokio_clients(addr, concurrency, requests, payload).await
+    })
+}
+
+/// Echoes `requests` messages of `payload` bytes over `concurrency` connections
+/// on tokio, against a server at `addr` that runs elsewhere.
+pub fn tokio_client(
+    addr: SocketAddr,
+    concurrency: usize,
+    requests: usize,
+    payload: usize,
+) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(tokio_clients(addr, concurrency, requests, payload))
+}
+
+/// Serves the echo protocol on `listener` with tokio until the process exits.
+pub fn tokio_server(listener: std::net::TcpListener) {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        listener.set_nonblocking(true).unwrap();
+        tokio_accept(tokio::net::TcpListener::from_std(listener).unwrap()).await
+    });
+}
+
+/// An echo server on a tokio runtime and a thread of its own, for scenarios
+/// that need a server next to the runtime they measure.
+///
+/// The server is stopped, and its thread joined, when the handle is dropped.
+pub(crate) struct EchoServer {
+    stop: Option<tokio::sync::oneshot::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl EchoServer {
+    /// Starts serving the echo protocol on `listener`.
+    pub(crate) fn start(listener: std::net::TcpListener) -> Self {
+        let (stop, stopped) = tokio::sync::oneshot::channel();
+        let thread = std::thread::spawn(move || {
+            // Not `metrics::runtime()`, so that the server's steals and parks
+            // are not added to those of the scenario it serves.
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                listener.set_nonblocking(true).unwrap();
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                futures::future::select(Box::pin(tokio_accept(listener)), stopped).await;
+            });
+        });
+        EchoServer {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for EchoServer {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap();
+        }
+    }
+}
+
+async fn tokio_accept(listener: tokio::net::TcpListener) {
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    loop {
+        let (stream, _) = listener.accept().await.unwrap();
+        stream.set_nodelay(true).unwrap();
+        tokio::spawn(serve(stream.compat()));
+    }
+}
+
+async fn tokio_clients(
+    addr: SocketAddr,
+    concurrency: usize,
+    requests: usize,
+    payload: usize,
+) -> LoadReport {
+    use tokio::net::TcpStream;
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    let start = Instant::now();
+    let connections: Vec<_> = split(requests, concurrency)
+        .map(|share| {
+            tokio::spawn(async move {
+                let stream = TcpStream::connect(addr).await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                ping_pong(stream.compat(), share, payload).await
+            })
+        })
+        .collect();
+    let mut latencies = latency_histogram();
+    for connection in connections {
+        latencies.add(connection.await.unwrap()).unwrap();
+    }
+    LoadReport::since(start, requests, latencies).with_bytes((requests * payload) as u64)
+}
+
+/// Echoes frames back until the peer closes the connection.
+pub(crate) async fn serve<S>(mut stream: S)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buf = Vec::new();
+    while let Ok(true) = read_frame(&mut stream, &mut buf).await {
+        if write_frame(&mut stream, &buf).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Sends `rounds` frames one after another, waiting for each echo.
+async fn ping_pong<S>(mut stream: S, rounds: usize, payload: usize) -> Histogram<u64>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let message = vec![0; payload];
+    let mut buf = Vec::new();
+    let mut latencies = latency_histogram();
+    for _ in 0..rounds {
+        let start = Instant::now();
+        write_frame(&mut stream, &message).await.unwrap();
+        assert!(read_frame(&mut stream, &mut buf).await.unwrap());
+        assert_eq!(buf.len(), payload);
+        record(&mut latencies, start.elapsed());
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn async_std_echoes() {
+        assert_eq!(async_std(4, 20, 64).latencies.len(), 20);
+    }
+
+    #[test]
+    fn tokio_echoes() {
+        assert_eq!(tokio(4, 20, 64).latencies.len(), 20);
+    }
+
+    #[test]
+    fn clients_reach_servers_on_the_other_runtime() {
+        let tokio_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tokio_addr = tokio_listener.local_addr().unwrap();
+        std::thread::spawn(move || tokio_server(tokio_listener));
+        let async_std_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let async_std_addr = async_std_listener.local_addr().unwrap();
+        std::thread::spawn(move || async_std_server(async_std_listener));
+
+        assert_eq!(async_std_client(tokio_addr, 2, 10, 64).latencies.len(), 10);
+        assert_eq!(tokio_client(async_std_addr, 2, 10, 64).latencies.len(), 10);
+    }
+}
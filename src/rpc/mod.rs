@@ -0,0 +1,10 @@
+//! Request/response RPC over a loopback connection: gRPC with tonic and a
+//! hand-rolled length-prefixed protocol that runs on any runtime.
+
+pub mod grpc;
+pub mod length_prefixed;
+
+/// Messages and services generated from `proto/bench.proto`.
+pub mod proto {
+    tonic::include_proto!("bench");
+}
@@ -0,0 +1,122 @@
+//! One-byte ping-pong over a single loopback connection.
+//!
+//! With one byte in flight at a time there is nothing to batch, so every round
+//! trip pays for two readiness notifications and two task wakeups and little
+//! else. Blocking std sockets on two threads give the floor set by the kernel.
+//!
+//! Latencies are the round-trip times of the individual pings.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use std::time::Instant;
+
+/// Sends `pings` one-byte pings one after another on tokio.
+pub fn tokio(pings: usize) -> LoadReport {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            let mut byte = [0];
+            while stream.read_exact(&mut byte).await.is_ok() {
+                stream.write_all(&byte).await.unwrap();
+            }
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut latencies = latency_histogram();
+        let start = Instant::now();
+        for _ in 0..pings {
+            let sent = Instant::now();
+            stream.write_all(b"!").await.unwrap();
+            stream.read_exact(&mut [0]).await.unwrap();
+            record(&mut latencies, sent.elapsed());
+        }
+        LoadReport::since(start, pings, latencies)
+    })
+}
+
+/// Sends `pings` one-byte pings one after another on async_std.
+pub fn async_std(pings: usize) -> LoadReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = task::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            let mut byte = [0];
+            while stream.read_exact(&mut byte).await.is_ok() {
+                stream.write_all(&byte).await.unwrap();
+            }
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut latencies = latency_histogram();
+        let start = Instant::now();
+        for _ in 0..pings {
+            let sent = Instant::now();
+            stream.write_all(b"!").await.unwrap();
+            stream.read_exact(&mut [0]).await.unwrap();
+            record(&mut latencies, sent.elapsed());
+        }
+        let report = LoadReport::since(start, pings, latencies);
+        drop(stream);
+        server.await;
+        report
+    })
+}
+
+/// Sends `pings` one-byte pings one after another over blocking std sockets.
+pub fn std_threads(pings: usize) -> LoadReport {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut byte = [0];
+        while stream.read_exact(&mut byte).is_ok() {
+            stream.write_all(&byte).unwrap();
+        }
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.set_nodelay(true).unwrap();
+    let mut latencies = latency_histogram();
+    let start = Instant::now();
+    for _ in 0..pings {
+        let sent = Instant::now();
+        stream.write_all(b"!").unwrap();
+        stream.read_exact(&mut [0]).unwrap();
+        record(&mut latencies, sent.elapsed());
+    }
+    let report = LoadReport::since(start, pings, latencies);
+    drop(stream);
+    server.join().unwrap();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_ping_returns() {
+        for ping_pong in &[tokio, async_std, std_threads] {
+            assert_eq!(ping_pong(100).latencies.len(), 100);
+        }
+    }
+}
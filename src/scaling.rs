@@ -0,0 +1,166 @@
+//! How well scenarios scale over the worker thread sweep: the throughput of
+//! every scenario at every thread count, next to what perfect scaling from
+//! its fewest threads would give, as a table and optionally as an SVG plot.
+//!
+//! The throughput of a report is its first figure in a unit per second, such
+//! as `req/s`, `tasks/s` or `msg/s`; scenarios reporting none are left out.
+//! Speedup is the throughput over that at the fewest threads, and efficiency
+//! the speedup over the growth in threads, 100% being perfect scaling.
+
+use std::fmt::Write;
+
+/// Throughput of a scenario at one thread count, against perfect scaling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Point {
+    pub(crate) threads: usize,
+    pub(crate) throughput: f64,
+    /// Throughput perfect scaling from the fewest threads would give.
+    pub(crate) ideal: f64,
+}
+
+impl Point {
+    pub(crate) fn efficiency(&self) -> f64 {
+        self.throughput / self.ideal
+    }
+}
+
+/// The first figure of `report` that is followed by a unit per second.
+pub(crate) fn throughput(report: &str) -> Option<f64> {
+    let words: Vec<_> = report.split_whitespace().collect();
+    words
+        .windows(2)
+        .find(|pair| pair[1].ends_with("/s"))
+        .and_then(|pair| pair[0].parse().ok())
+}
+
+/// The scaling of a scenario whose reports at every thread count are
+/// `curve`, or `None` if they report no throughput.
+pub(crate) fn scaling(curve: &[(usize, &str)]) -> Option<Vec<Point>> {
+    let measured: Vec<(usize, f64)> = curve
+        .iter()
+        .map(|&(threads, report)| Some((threads, throughput(report)?)))
+        .collect::<Option<_>>()?;
+    let &(base_threads, base) = measured.iter().min_by_key(|(threads, _)| *threads)?;
+    Some(
+        measured
+            .into_iter()
+            .map(|(threads, throughput)| Point {
+                threads,
+                throughput,
+                ideal: base * threads as f64 / base_threads as f64,
+            })
+            .collect(),
+    )
+}
+
+/// Colours the scenarios are drawn in, in turn.
+const COLOURS: [&str; 8] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+];
+
+/// Plots the throughput of every scenario over the thread counts as a solid
+/// line, and the perfect scaling from its fewest threads as a dashed one in
+/// the same colour.
+pub(crate) fn svg(scenarios: &[(&str, Vec<Point>)]) -> String {
+    const WIDTH: f64 = 800.0;
+    const HEIGHT: f64 = 500.0;
+    const MARGIN: f64 = 60.0;
+    let points = scenarios.iter().flat_map(|(_, points)| points);
+    let max_threads = points.clone().map(|point| point.threads).max().unwrap_or(1) as f64;
+    let max_throughput = points
+        .map(|point| point.throughput.max(point.ideal))
+        .fold(1.0, f64::max);
+    let x = |threads: usize| MARGIN + threads as f64 / max_threads * (WIDTH - 2.0 * MARGIN);
+    let y =
+        |throughput: f64| HEIGHT - MARGIN - throughput / max_throughput * (HEIGHT - 2.0 * MARGIN);
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+        WIDTH, HEIGHT
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<path d="M{m},{m} V{b} H{r}" fill="none" stroke="black"/>"#,
+        m = MARGIN,
+        b = HEIGHT - MARGIN,
+        r = WIDTH - MARGIN
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="middle">worker threads (max {})</text>"#,
+        WIDTH / 2.0,
+        HEIGHT - MARGIN / 3.0,
+        max_threads
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<text x="{}" y="{}">throughput (max {:.0}/s)</text>"#,
+        MARGIN,
+        MARGIN / 2.0,
+        max_throughput
+    )
+    .unwrap();
+    for (i, (name, points)) in scenarios.iter().enumerate() {
+        let colour = COLOURS[i % COLOURS.len()];
+        let line = |value: fn(&Point) -> f64| {
+            points
+                .iter()
+                .map(|point| format!("{:.1},{:.1}", x(point.threads), y(value(point))))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+            line(|point| point.throughput),
+            colour
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-dasharray="4 4"/>"#,
+            line(|point| point.ideal),
+            colour
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" fill="{}">{}</text>"#,
+            WIDTH - MARGIN - 200.0,
+            MARGIN + 16.0 * i as f64,
+            colour,
+            name.replace('&', "&amp;").replace('<', "&lt;")
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_first_rate() {
+        assert_eq!(throughput("   12000 req/s  p50 1.000ms"), Some(12000.0));
+        assert_eq!(
+            throughput("spawned 10 in 3.0ms  5000 tasks/s"),
+            Some(5000.0)
+        );
+        assert_eq!(throughput("lateness 1.000ms"), None);
+    }
+
+    #[test]
+    fn efficiency_is_against_the_fewest_threads() {
+        let points = scaling(&[(1, "100 req/s"), (2, "180 req/s"), (4, "200 req/s")]).unwrap();
+        assert_eq!(points[1].ideal, 200.0);
+        assert_eq!(points[1].efficiency(), 0.9);
+        assert_eq!(points[2].efficiency(), 0.5);
+        assert_eq!(scaling(&[(1, "100 req/s"), (2, "n/a")]), None);
+    }
+}
@@ -0,0 +1,202 @@
+//! The length-prefixed echo from [`crate::rpc::length_prefixed`] inside a
+//! deterministic turmoil simulation.
+//!
+//! turmoil runs every simulated host on a single thread with simulated time
+//! and a simulated network, all driven by one seeded random number generator,
+//! so the same seed replays the same interleaving of tasks and the same
+//! message latencies on any machine. That makes scheduling-order comparisons
+//! and failure-injection runs reproducible, at the price of saying nothing
+//! about how fast any real runtime is: only the wall-clock throughput depends
+//! on the machine, the latencies are in simulated time.
+//!
+//! turmoil replaces tokio's networking and clock, so only tokio scenarios can
+//! run in it; async_std has no equivalent and its scenarios are not covered.
+
+use crate::framing::{read_frame, write_frame};
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use crate::rpc::length_prefixed;
+use hdrhistogram::Histogram;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Port the simulated server listens on.
+const PORT: u16 = 9000;
+
+/// Parameters of a simulated run besides the load itself.
+#[derive(Clone, Copy, Debug)]
+pub struct Simulation {
+    /// Seed of the simulation; equal seeds give identical runs.
+    pub seed: u64,
+    /// Smallest and largest latency of a message on a simulated link.
+    pub min_latency: Duration,
+    pub max_latency: Duration,
+    /// Holds back all messages between the clients and the server for the
+    /// second part of every period, then delivers them in one go.
+    pub faults: Option<Faults>,
+}
+
+/// A periodic network stall: every `every` of simulated time, all links to
+/// the server hold messages for `length`.
+#[derive(Clone, Copy, Debug)]
+pub struct Faults {
+    every: Duration,
+    length: Duration,
+}
+
+impl Faults {
+    /// Stalls lasting `length` every `every`, which must not be 0 nor shorter
+    /// than `length`.
+    pub fn new(every: Duration, length: Duration) -> Self {
+        assert!(
+            !every.is_zero(),
+            "the period of network stalls must not be 0"
+        );
+        assert!(
+            length <= every,
+            "network stalls of {:?} do not fit in a period of {:?}",
+            length,
+            every
+        );
+        Faults { every, length }
+    }
+
+    /// Whether the links are held at simulated time `elapsed`.
+    fn held_at(&self, elapsed: Duration) -> bool {
+        let into_period = elapsed.as_nanos() % self.every.as_nanos();
+        into_period >= (self.every - self.length).as_nanos()
+    }
+}
+
+/// A load report in which latencies are measured in simulated time.
+pub struct SimReport {
+    /// Throughput over wall-clock time and latencies over simulated time.
+    pub load: LoadReport,
+    /// Simulated time the run took.
+    pub simulated: Duration,
+}
+
+impl fmt::Display for SimReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  simulated {:.3}s",
+            self.load,
+            self.simulated.as_secs_f64()
+        )
+    }
+}
+
+/// Echoes `requests` messages of `payload` bytes over `concurrency` simulated
+/// client hosts talking to one simulated server.
+pub fn length_prefixed(
+    simulation: Simulation,
+    concurrency: usize,
+    requests: usize,
+    payload: usize,
+) -> SimReport {
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+    use turmoil::net::{TcpListener, TcpStream};
+
+    let mut sim = turmoil::Builder::new()
+        .rng_seed(simulation.seed)
+        .min_message_latency(simulation.min_latency)
+        .max_message_latency(simulation.max_latency)
+        .simulation_duration(Duration::from_secs(24 * 60 * 60))
+        .build();
+    sim.host("server", || async {
+        let listener = TcpListener::bind(("0.0.0.0", PORT)).await?;
+        loop {
+            let (stream, _) = listener.accept().await?;
+            tokio::spawn(length_prefixed::serve(stream.compat()));
+        }
+    });
+
+    let latencies = Rc::new(RefCell::new(latency_histogram()));
+    let clients: Vec<_> = (0..concurrency).map(|i| format!("client-{}", i)).collect();
+    for (name, share) in clients.iter().zip(split(requests, concurrency)) {
+        let latencies = latencies.clone();
+        sim.client(name.as_str(), async move {
+            let stream = TcpStream::connect(("server", PORT)).await?;
+            let client_latencies = ping_pong(stream.compat(), share, payload).await;
+            latencies.borrow_mut().add(client_latencies).unwrap();
+            Ok(())
+        });
+    }
+
+    let start = Instant::now();
+    let mut held = false;
+    loop {
+        if let Some(faults) = simulation.faults {
+            if faults.held_at(sim.elapsed()) != held {
+                held = !held;
+                for name in &clients {
+                    if held {
+                        sim.hold(name.as_str(), "server");
+                    } else {
+                        sim.release(name.as_str(), "server");
+                    }
+                }
+            }
+        }
+        if sim.step().unwrap() {
+            break;
+        }
+    }
+    let simulated = sim.elapsed();
+    let latencies = latencies.replace(latency_histogram());
+    SimReport {
+        load: LoadReport::since(start, requests, latencies).with_bytes((requests * payload) as u64),
+        simulated,
+    }
+}
+
+/// Sends `rounds` frames one after another, timing each echo in simulated time.
+async fn ping_pong<S>(mut stream: S, rounds: usize, payload: usize) -> Histogram<u64>
+where
+    S: futures::io::AsyncRead + futures::io::AsyncWrite + Unpin,
+{
+    let message = vec![0; payload];
+    let mut buf = Vec::new();
+    let mut latencies = latency_histogram();
+    for _ in 0..rounds {
+        let start = tokio::time::Instant::now();
+        write_frame(&mut stream, &message).await.unwrap();
+        assert!(read_frame(&mut stream, &mut buf).await.unwrap());
+        record(&mut latencies, start.elapsed());
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulation(seed: u64) -> Simulation {
+        Simulation {
+            seed,
+            min_latency: Duration::from_millis(1),
+            max_latency: Duration::from_millis(10),
+            faults: Some(Faults::new(
+                Duration::from_millis(100),
+                Duration::from_millis(20),
+            )),
+        }
+    }
+
+    #[test]
+    fn equal_seeds_replay_the_same_run() {
+        let first = length_prefixed(simulation(7), 4, 200, 64);
+        let second = length_prefixed(simulation(7), 4, 200, 64);
+        assert_eq!(first.load.latencies.len(), 200);
+        assert_eq!(first.simulated, second.simulated);
+        assert_eq!(first.load.latencies, second.load.latencies);
+    }
+
+    #[test]
+    fn held_links_show_up_in_the_tail() {
+        let run = length_prefixed(simulation(7), 4, 200, 64);
+        assert!(run.load.latencies.max() >= Duration::from_millis(15).as_nanos() as u64);
+    }
+}
@@ -0,0 +1,292 @@
+//! Soaking scenarios: running every one over and over for a long time while
+//! sampling what the process holds, to find the slow leaks and drifts that
+//! single runs are too short to show.
+//!
+//! Every interval, the runner samples the resident set, the open file
+//! descriptors and the threads of the process, and averages the figure the
+//! scenario is judged by over the runs since the last sample: its first
+//! rate, or else its first time, see [`repeat`](crate::repeat). Once the
+//! scenario soaked long enough, the first quarter of the samples is compared
+//! with the last. The resident set is flagged as growing when every sample
+//! of the last quarter is above every sample of the first, which a leak
+//! does and memory that only fragments rarely does, the file descriptors
+//! and threads whenever there are more of them at the end, and the figure as
+//! drifting when its mean moved by more than a share of it.
+
+use crate::config::env_or;
+use crate::harness::{open_files, resident_memory, thread_count};
+use crate::repeat;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+const MIB: f64 = (1 << 20) as f64;
+
+/// What the process held at one point of a soak, and how the scenario did
+/// since the point before.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Sample {
+    pub(crate) at: Duration,
+    pub(crate) runs: usize,
+    pub(crate) resident: Option<u64>,
+    pub(crate) files: Option<usize>,
+    pub(crate) threads: Option<usize>,
+    /// Mean figure of the runs since the sample before.
+    pub(crate) figure: Option<f64>,
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>8.1} min  {:>6} runs",
+            self.at.as_secs_f64() / 60.0,
+            self.runs
+        )?;
+        match self.resident {
+            Some(resident) => write!(f, "  rss {:>8.1} MiB", resident as f64 / MIB)?,
+            None => write!(f, "  rss n/a")?,
+        }
+        match self.files {
+            Some(files) => write!(f, "  fds {:>5}", files)?,
+            None => write!(f, "  fds n/a")?,
+        }
+        match self.threads {
+            Some(threads) => write!(f, "  threads {:>4}", threads)?,
+            None => write!(f, "  threads n/a")?,
+        }
+        if let Some(figure) = self.figure {
+            write!(f, "  figure {:>12.1}", figure)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the samples at the end of a soak compare with those at its start.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Drift {
+    pub(crate) runs: usize,
+    pub(crate) elapsed: Duration,
+    /// Mean resident set at the start and the end, in bytes, its growth per
+    /// hour, and whether it grew throughout.
+    pub(crate) resident: Option<(f64, f64, f64, bool)>,
+    pub(crate) files: Option<(f64, f64)>,
+    pub(crate) threads: Option<(f64, f64)>,
+    /// Mean figure at the start and the end, and whether it drifted.
+    pub(crate) figure: Option<(f64, f64, bool)>,
+}
+
+impl Drift {
+    /// Compares the first quarter of `samples` with the last, flagging the
+    /// figure as drifting when its mean moved by more than `tolerance`, a
+    /// share of it.
+    pub(crate) fn of(samples: &[Sample], tolerance: f64) -> Option<Self> {
+        let last = samples.last()?;
+        let quarter = (samples.len() / 4).max(1);
+        let (start, end) = (&samples[..quarter], &samples[samples.len() - quarter..]);
+        let hours = (middle(end) - middle(start)).as_secs_f64() / 3600.0;
+        let resident = means(start, end, |sample| {
+            sample.resident.map(|bytes| bytes as f64)
+        })
+        .map(|(before, after)| {
+            let growing =
+                lowest(end, |sample| sample.resident) > highest(start, |sample| sample.resident);
+            let per_hour = if hours > 0.0 {
+                (after - before) / hours
+            } else {
+                0.0
+            };
+            (before, after, per_hour, growing)
+        });
+        let figure = means(start, end, |sample| sample.figure).map(|(before, after)| {
+            (
+                before,
+                after,
+                (after - before).abs() > tolerance * before.abs(),
+            )
+        });
+        Some(Drift {
+            runs: samples.iter().map(|sample| sample.runs).sum(),
+            elapsed: last.at,
+            resident,
+            files: means(start, end, |sample| sample.files.map(|files| files as f64)),
+            threads: means(start, end, |sample| {
+                sample.threads.map(|threads| threads as f64)
+            }),
+            figure,
+        })
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "soak {:.1} min  {} runs",
+            self.elapsed.as_secs_f64() / 60.0,
+            self.runs
+        )?;
+        match self.resident {
+            Some((before, after, per_hour, growing)) => write!(
+                f,
+                "  rss {:.1} -> {:.1} MiB ({:+.1} MiB/h{})",
+                before / MIB,
+                after / MIB,
+                per_hour / MIB,
+                if growing { ", growing" } else { "" }
+            )?,
+            None => write!(f, "  rss n/a")?,
+        }
+        for (what, counts) in [("fds", self.files), ("threads", self.threads)] {
+            match counts {
+                Some((before, after)) => write!(
+                    f,
+                    "  {} {:.0} -> {:.0}{}",
+                    what,
+                    before,
+                    after,
+                    if after > before { " (growing)" } else { "" }
+                )?,
+                None => write!(f, "  {} n/a", what)?,
+            }
+        }
+        if let Some((before, after, drifting)) = self.figure {
+            write!(
+                f,
+                "  figure {:.1} -> {:.1} ({:+.1}%{})",
+                before,
+                after,
+                (after - before) * 100.0 / before,
+                if drifting { ", drifting" } else { "" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// When the samples of `part` were taken, on average.
+fn middle(part: &[Sample]) -> Duration {
+    part.iter().map(|sample| sample.at).sum::<Duration>() / part.len() as u32
+}
+
+/// The mean of what `of` reads from the samples of `start` and of `end`, if
+/// every sample has it.
+fn means(
+    start: &[Sample],
+    end: &[Sample],
+    of: impl Fn(&Sample) -> Option<f64>,
+) -> Option<(f64, f64)> {
+    let mean = |part: &[Sample]| {
+        let values = part.iter().map(&of).collect::<Option<Vec<f64>>>()?;
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    };
+    Some((mean(start)?, mean(end)?))
+}
+
+fn lowest(part: &[Sample], of: impl Fn(&Sample) -> Option<u64>) -> Option<u64> {
+    part.iter().filter_map(of).min()
+}
+
+fn highest(part: &[Sample], of: impl Fn(&Sample) -> Option<u64>) -> Option<u64> {
+    part.iter().filter_map(of).max()
+}
+
+/// How long every scenario soaks, and how it is sampled.
+pub(crate) struct Soak {
+    duration: Duration,
+    interval: Duration,
+    /// Share of its figure a scenario may drift by.
+    tolerance: f64,
+}
+
+/// Reads how long to soak every scenario from `SOAK`, in minutes, if it is
+/// set, with the seconds between samples from `SOAK_INTERVAL`, 60 by
+/// default, and the percentage the figure may drift by from `SOAK_DRIFT`, 10
+/// by default.
+pub(crate) fn load() -> Option<Soak> {
+    std::env::var_os("SOAK")?;
+    Some(Soak {
+        duration: Duration::from_secs_f64(env_or("SOAK", 60.0) * 60.0),
+        interval: Duration::from_secs_f64(env_or("SOAK_INTERVAL", 60.0)),
+        tolerance: env_or("SOAK_DRIFT", 10.0) / 100.0,
+    })
+}
+
+impl Soak {
+    /// Runs `scenario` over and over, printing a sample every interval
+    /// under `name`, and returns how the samples drifted.
+    pub(crate) fn run<R: fmt::Display>(
+        &self,
+        name: &str,
+        mut scenario: impl FnMut() -> R,
+    ) -> Option<Drift> {
+        let started = Instant::now();
+        let mut samples = Vec::new();
+        while started.elapsed() < self.duration {
+            let since = Instant::now();
+            let mut runs = 0;
+            let mut figures = Vec::new();
+            while runs == 0 || since.elapsed() < self.interval {
+                figures.extend(repeat::figure(&scenario().to_string()));
+                runs += 1;
+            }
+            let sample = Sample {
+                at: started.elapsed(),
+                runs,
+                resident: resident_memory(),
+                files: open_files(),
+                threads: thread_count(),
+                figure: (figures.len() == runs).then(|| figures.iter().sum::<f64>() / runs as f64),
+            };
+            println!("{:<48} {}", name, sample);
+            samples.push(sample);
+        }
+        Drift::of(&samples, self.tolerance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(minutes: u64, resident: u64, figure: f64) -> Sample {
+        Sample {
+            at: Duration::from_secs(minutes * 60),
+            runs: 10,
+            resident: Some(resident << 20),
+            files: Some(12),
+            threads: Some(4),
+            figure: Some(figure),
+        }
+    }
+
+    #[test]
+    fn flags_growth_and_drift() {
+        let leaking: Vec<_> = (1..=8).map(|i| sample(i * 15, 100 + i, 1000.0)).collect();
+        let drift = Drift::of(&leaking, 0.1).unwrap();
+        assert_eq!(drift.runs, 80);
+        assert_eq!(
+            drift.resident,
+            Some((101.5 * MIB, 107.5 * MIB, 4.0 * MIB, true))
+        );
+        assert_eq!(drift.figure, Some((1000.0, 1000.0, false)));
+        assert_eq!(
+            drift.to_string(),
+            "soak 120.0 min  80 runs  rss 101.5 -> 107.5 MiB (+4.0 MiB/h, growing)  fds 12 -> 12  \
+             threads 4 -> 4  figure 1000.0 -> 1000.0 (+0.0%)"
+        );
+
+        let slowing: Vec<_> = (1..=8)
+            .map(|i| {
+                sample(
+                    i,
+                    if i % 2 == 0 { 100 } else { 101 },
+                    1000.0 - 30.0 * i as f64,
+                )
+            })
+            .collect();
+        let drift = Drift::of(&slowing, 0.1).unwrap();
+        assert_eq!(drift.resident.map(|(.., growing)| growing), Some(false));
+        assert_eq!(drift.figure.map(|(.., drifting)| drifting), Some(true));
+        assert!(Drift::of(&[], 0.1).is_none());
+    }
+}
@@ -0,0 +1,133 @@
+//! Tasks meeting at a barrier phase after phase, the way phase-synchronized
+//! parallel pipelines wait for their slowest stage.
+//!
+//! Every task notes the time it arrives at the barrier and waits there; once
+//! the last one has arrived they are all released and go straight on to the
+//! next phase. tokio's barrier is compared with async_std's, which is
+//! async-lock's. Neither depends on a runtime, so both are measured on both.
+//!
+//! Throughput is in phases per second; latencies are from the last arrival
+//! of a phase until each task resumed.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use hdrhistogram::Histogram;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A barrier for a fixed number of tasks, reusable phase after phase.
+pub trait Barrier: Send + Sync + 'static {
+    fn new(tasks: usize) -> Self;
+
+    fn wait(&self) -> impl Future<Output = ()> + Send + '_;
+}
+
+impl Barrier for tokio::sync::Barrier {
+    fn new(tasks: usize) -> Self {
+        tokio::sync::Barrier::new(tasks)
+    }
+
+    async fn wait(&self) {
+        self.wait().await;
+    }
+}
+
+impl Barrier for async_std::sync::Barrier {
+    fn new(tasks: usize) -> Self {
+        async_std::sync::Barrier::new(tasks)
+    }
+
+    async fn wait(&self) {
+        self.wait().await;
+    }
+}
+
+/// A barrier with the time of the last arrival of the current phase.
+struct Meeting<B> {
+    start: Instant,
+    barrier: B,
+    /// Latest arrival, in nanoseconds from `start`, of even and odd phases.
+    ///
+    /// A slot is only reused two phases later, after every task has read it.
+    arrived: [AtomicU64; 2],
+}
+
+impl<B: Barrier> Meeting<B> {
+    fn new(tasks: usize) -> Self {
+        Meeting {
+            start: Instant::now(),
+            barrier: B::new(tasks),
+            arrived: [AtomicU64::new(0), AtomicU64::new(0)],
+        }
+    }
+
+    /// Waits at the barrier in `phase` and returns how long after the last
+    /// arrival this task resumed.
+    async fn meet(&self, phase: usize) -> Duration {
+        let slot = &self.arrived[phase % 2];
+        slot.fetch_max(self.start.elapsed().as_nanos() as u64, Ordering::AcqRel);
+        self.barrier.wait().await;
+        let last = Duration::from_nanos(slot.load(Ordering::Acquire));
+        self.start.elapsed() - last
+    }
+}
+
+/// Runs `tasks` tokio tasks through `phases` phases at a `B`.
+pub fn on_tokio<B: Barrier>(tasks: usize, phases: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let meeting = Arc::new(Meeting::<B>::new(tasks));
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| tokio::spawn(meet_phases(meeting.clone(), phases)))
+            .collect();
+        let mut latencies = latency_histogram();
+        for handle in handles {
+            latencies.add(handle.await.unwrap()).unwrap();
+        }
+        LoadReport::since(meeting.start, phases, latencies)
+    })
+}
+
+/// Runs `tasks` async_std tasks through `phases` phases at a `B`.
+pub fn on_async_std<B: Barrier>(tasks: usize, phases: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let meeting = Arc::new(Meeting::<B>::new(tasks));
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| task::spawn(meet_phases(meeting.clone(), phases)))
+            .collect();
+        let mut latencies = latency_histogram();
+        for handle in handles {
+            latencies.add(handle.await).unwrap();
+        }
+        LoadReport::since(meeting.start, phases, latencies)
+    })
+}
+
+async fn meet_phases<B: Barrier>(meeting: Arc<Meeting<B>>, phases: usize) -> Histogram<u64> {
+    let mut latencies = latency_histogram();
+    for phase in 0..phases {
+        record(&mut latencies, meeting.meet(phase).await);
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_task_passes_every_phase() {
+        for report in [
+            on_tokio::<tokio::sync::Barrier>(10, 100),
+            on_async_std::<tokio::sync::Barrier>(10, 100),
+            on_tokio::<async_std::sync::Barrier>(10, 100),
+            on_async_std::<async_std::sync::Barrier>(10, 100),
+        ] {
+            assert_eq!(report.requests, 100);
+            assert_eq!(report.latencies.len(), 10 * 100);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! Synchronization primitives other than channels, from the runtimes, from
+//! futures and from std, contended by many tasks.
+
+use crate::harness::{fairness, latency_histogram, record, LoadReport};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+pub mod barrier;
+pub mod mutex;
+pub mod notify;
+pub mod once_cell;
+pub mod rwlock;
+pub mod semaphore;
+
+/// A load report over acquisitions of a primitive, with how evenly the tasks
+/// contending for it got their turns.
+pub struct ContentionReport {
+    /// Acquisitions per second and the time each waited to acquire.
+    pub load: LoadReport,
+    /// Jain's fairness index over the number of acquisitions of each task.
+    pub fairness: f64,
+}
+
+impl ContentionReport {
+    /// Builds the report from the waits of every task, in a vector per task
+    /// rather than a histogram, which would take more memory than everything
+    /// else together with many tasks.
+    fn new(start: Instant, tasks: Vec<Vec<Duration>>) -> Self {
+        let counts: Vec<_> = tasks.iter().map(Vec::len).collect();
+        let mut latencies = latency_histogram();
+        for wait in tasks.into_iter().flatten() {
+            record(&mut latencies, wait);
+        }
+        ContentionReport {
+            load: LoadReport::since(start, counts.iter().sum(), latencies),
+            fairness: fairness(&counts),
+        }
+    }
+}
+
+impl fmt::Display for ContentionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  fairness {:.3}", self.load, self.fairness)
+    }
+}
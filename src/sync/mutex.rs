@@ -0,0 +1,155 @@
+//! Tasks taking turns on one mutex.
+//!
+//! Every task locks the mutex, holds it for `hold` while spinning, unlocks it
+//! and yields to the runtime, standing in for the other work a task does
+//! between two critical sections, until `duration` has passed. tokio's,
+//! futures' and async_std's mutexes (the last being async-lock's) are
+//! compared with `std::sync::Mutex`, which blocks the worker thread while it
+//! waits. None of them depends on a runtime, so all are measured on both.
+//!
+//! Latencies are the time each task waited to acquire the lock.
+
+use super::ContentionReport;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A mutex around a counter.
+pub trait Lock: Default + Send + Sync + 'static {
+    /// Locks the mutex, runs `critical` on the counter and unlocks it.
+    fn with(&self, critical: impl FnOnce(&mut u64) + Send) -> impl Future<Output = ()> + Send;
+}
+
+impl Lock for tokio::sync::Mutex<u64> {
+    async fn with(&self, critical: impl FnOnce(&mut u64) + Send) {
+        critical(&mut *self.lock().await)
+    }
+}
+
+impl Lock for async_std::sync::Mutex<u64> {
+    async fn with(&self, critical: impl FnOnce(&mut u64) + Send) {
+        critical(&mut *self.lock().await)
+    }
+}
+
+impl Lock for futures::lock::Mutex<u64> {
+    async fn with(&self, critical: impl FnOnce(&mut u64) + Send) {
+        critical(&mut *self.lock().await)
+    }
+}
+
+impl Lock for std::sync::Mutex<u64> {
+    async fn with(&self, critical: impl FnOnce(&mut u64) + Send) {
+        critical(&mut self.lock().unwrap())
+    }
+}
+
+/// Runs `tasks` tokio tasks taking turns on an `L` for `duration`.
+pub fn on_tokio<L: Lock>(tasks: usize, hold: Duration, duration: Duration) -> ContentionReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let lock = Arc::new(L::default());
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| {
+                let turns =
+                    take_turns(lock.clone(), hold, start + duration, tokio::task::yield_now);
+                tokio::spawn(turns)
+            })
+            .collect();
+        let mut waits = Vec::with_capacity(tasks);
+        for handle in handles {
+            waits.push(handle.await.unwrap());
+        }
+        check_count(&*lock, &waits).await;
+        ContentionReport::new(start, waits)
+    })
+}
+
+/// Runs `tasks` async_std tasks taking turns on an `L` for `duration`.
+pub fn on_async_std<L: Lock>(tasks: usize, hold: Duration, duration: Duration) -> ContentionReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let lock = Arc::new(L::default());
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| {
+                task::spawn(take_turns(
+                    lock.clone(),
+                    hold,
+                    start + duration,
+                    task::yield_now,
+                ))
+            })
+            .collect();
+        let mut waits = Vec::with_capacity(tasks);
+        for handle in handles {
+            waits.push(handle.await);
+        }
+        check_count(&*lock, &waits).await;
+        ContentionReport::new(start, waits)
+    })
+}
+
+/// Locks `lock` until `deadline`, calling `yield_now` after each turn, and
+/// returns the time each acquisition waited.
+async fn take_turns<L, F, Fut>(
+    lock: Arc<L>,
+    hold: Duration,
+    deadline: Instant,
+    yield_now: F,
+) -> Vec<Duration>
+where
+    L: Lock,
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut waits = Vec::new();
+    while Instant::now() < deadline {
+        let asked = Instant::now();
+        lock.with(|counter| {
+            waits.push(asked.elapsed());
+            *counter += 1;
+            let held = Instant::now();
+            while held.elapsed() < hold {
+                std::hint::spin_loop();
+            }
+        })
+        .await;
+        yield_now().await;
+    }
+    waits
+}
+
+/// Checks that no increment of the counter was lost to a race.
+async fn check_count<L: Lock>(lock: &L, waits: &[Vec<Duration>]) {
+    let turns: usize = waits.iter().map(Vec::len).sum();
+    let mut counted = 0;
+    lock.with(|counter| counted = *counter).await;
+    assert_eq!(counted, turns as u64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts_every_turn<L: Lock>() {
+        let hold = Duration::from_micros(1);
+        let duration = Duration::from_millis(20);
+        for report in [
+            on_tokio::<L>(8, hold, duration),
+            on_async_std::<L>(8, hold, duration),
+        ] {
+            assert!(report.load.requests >= 8);
+        }
+    }
+
+    #[test]
+    fn mutexes_count_every_turn() {
+        counts_every_turn::<tokio::sync::Mutex<u64>>();
+        counts_every_turn::<async_std::sync::Mutex<u64>>();
+        counts_every_turn::<futures::lock::Mutex<u64>>();
+        counts_every_turn::<std::sync::Mutex<u64>>();
+    }
+}
@@ -0,0 +1,201 @@
+//! Waking one waiting task, the step behind every other primitive and every
+//! IO readiness event.
+//!
+//! A notifier and a waiter play ping-pong on two signals: the notifier notes
+//! the time and signals the waiter, which records how long it took to resume
+//! and signals back, so the waiter has usually gone back to waiting by the
+//! next round. `tokio::sync::Notify` is compared with an event-listener
+//! `Event` guarding a flag, which is how async-lock and async-channel wait,
+//! both running as tasks on either runtime, and with a `Condvar` between two
+//! threads as the baseline.
+//!
+//! Latencies are from the notification until the woken side runs.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use event_listener::Event;
+use hdrhistogram::Histogram;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// A signal that wakes the one task waiting for it, or lets the next wait
+/// return at once if nobody waits yet.
+pub trait Signal: Default + Send + Sync + 'static {
+    fn notify(&self);
+
+    fn wait(&self) -> impl Future<Output = ()> + Send + '_;
+}
+
+impl Signal for tokio::sync::Notify {
+    fn notify(&self) {
+        self.notify_one();
+    }
+
+    fn wait(&self) -> impl Future<Output = ()> + Send + '_ {
+        self.notified()
+    }
+}
+
+/// A flag with an event-listener `Event` to wait for it being set.
+#[derive(Default)]
+pub struct EventSignal {
+    set: AtomicBool,
+    event: Event,
+}
+
+impl Signal for EventSignal {
+    fn notify(&self) {
+        self.set.store(true, Ordering::Release);
+        self.event.notify(1);
+    }
+
+    async fn wait(&self) {
+        loop {
+            if self.set.swap(false, Ordering::Acquire) {
+                return;
+            }
+            // Listen before checking again, so that a notification in
+            // between is not missed.
+            let listener = self.event.listen();
+            if self.set.swap(false, Ordering::Acquire) {
+                return;
+            }
+            listener.await;
+        }
+    }
+}
+
+/// The two signals of a run and the time of the last notification.
+struct Rally<S> {
+    start: Instant,
+    ping: S,
+    pong: S,
+    /// Nanoseconds from `start` to the last ping.
+    pinged: AtomicU64,
+}
+
+impl<S: Default> Rally<S> {
+    fn new() -> Self {
+        Rally {
+            start: Instant::now(),
+            ping: S::default(),
+            pong: S::default(),
+            pinged: AtomicU64::new(0),
+        }
+    }
+
+    fn ping_now(&self) {
+        let since = self.start.elapsed().as_nanos() as u64;
+        self.pinged.store(since, Ordering::Release);
+    }
+
+    fn since_ping(&self) -> Duration {
+        let pinged = Duration::from_nanos(self.pinged.load(Ordering::Acquire));
+        self.start.elapsed() - pinged
+    }
+}
+
+/// Plays `rounds` rounds between two tokio tasks over `S` signals.
+pub fn on_tokio<S: Signal>(rounds: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let rally = Arc::new(Rally::<S>::new());
+        let waiter = tokio::spawn(wait_rounds(rally.clone(), rounds));
+        tokio::spawn(notify_rounds(rally.clone(), rounds));
+        let latencies = waiter.await.unwrap();
+        LoadReport::since(rally.start, rounds, latencies)
+    })
+}
+
+/// Plays `rounds` rounds between two async_std tasks over `S` signals.
+pub fn on_async_std<S: Signal>(rounds: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let rally = Arc::new(Rally::<S>::new());
+        let waiter = task::spawn(wait_rounds(rally.clone(), rounds));
+        task::spawn(notify_rounds(rally.clone(), rounds));
+        let latencies = waiter.await;
+        LoadReport::since(rally.start, rounds, latencies)
+    })
+}
+
+async fn wait_rounds<S: Signal>(rally: Arc<Rally<S>>, rounds: usize) -> Histogram<u64> {
+    let mut latencies = latency_histogram();
+    for _ in 0..rounds {
+        rally.ping.wait().await;
+        record(&mut latencies, rally.since_ping());
+        rally.pong.notify();
+    }
+    latencies
+}
+
+async fn notify_rounds<S: Signal>(rally: Arc<Rally<S>>, rounds: usize) {
+    for _ in 0..rounds {
+        rally.ping_now();
+        rally.ping.notify();
+        rally.pong.wait().await;
+    }
+}
+
+/// A flag with a `Condvar` to wait for it being set, for threads.
+#[derive(Default)]
+struct CondvarSignal {
+    set: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl CondvarSignal {
+    fn notify(&self) {
+        *self.set.lock().unwrap() = true;
+        self.changed.notify_one();
+    }
+
+    fn wait(&self) {
+        let set = self.set.lock().unwrap();
+        let mut set = self.changed.wait_while(set, |set| !*set).unwrap();
+        *set = false;
+    }
+}
+
+/// Plays `rounds` rounds between two threads over `Condvar` signals.
+pub fn condvar_threads(rounds: usize) -> LoadReport {
+    let rally = Rally::<CondvarSignal>::new();
+    let latencies = std::thread::scope(|scope| {
+        let waiter = scope.spawn(|| {
+            let mut latencies = latency_histogram();
+            for _ in 0..rounds {
+                rally.ping.wait();
+                record(&mut latencies, rally.since_ping());
+                rally.pong.notify();
+            }
+            latencies
+        });
+        for _ in 0..rounds {
+            rally.ping_now();
+            rally.ping.notify();
+            rally.pong.wait();
+        }
+        waiter.join().unwrap()
+    });
+    LoadReport::since(rally.start, rounds, latencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_round_wakes_the_waiter() {
+        for report in [
+            on_tokio::<tokio::sync::Notify>(1_000),
+            on_async_std::<tokio::sync::Notify>(1_000),
+            on_tokio::<EventSignal>(1_000),
+            on_async_std::<EventSignal>(1_000),
+            condvar_threads(1_000),
+        ] {
+            assert_eq!(report.latencies.len(), 1_000);
+        }
+    }
+}
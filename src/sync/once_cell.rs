@@ -0,0 +1,189 @@
+//! Thousands of tasks getting a lazily initialized value from one cell.
+//!
+//! In the cold mode all tasks are spawned at once on an empty cell and race
+//! to initialize it, with an initializer that takes `init` on the runtime's
+//! timer, so all but the winner wait for the value; latencies are from the
+//! start until each task had it. In the warm mode the cell is already full
+//! and every task reads it `reads` times, which is the overhead every later
+//! access pays; latencies are the time each task took for its reads.
+//! `tokio::sync::OnceCell` is compared with async-once-cell's. Neither
+//! depends on a runtime, so both are measured on both.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use hdrhistogram::Histogram;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Whether the cell is empty when the tasks start.
+#[derive(Clone, Copy, Debug)]
+pub enum Mode {
+    Cold,
+    Warm,
+}
+
+/// A cell initialized at most once by an async initializer.
+pub trait Once: Default + Send + Sync + 'static {
+    /// Returns the value, running `init` first if the cell is still empty and
+    /// no other task is initializing it.
+    fn get_or_init<'a, F>(&'a self, init: F) -> impl Future<Output = u64> + Send + 'a
+    where
+        F: Future<Output = u64> + Send + 'a;
+}
+
+impl Once for tokio::sync::OnceCell<u64> {
+    async fn get_or_init<'a, F>(&'a self, init: F) -> u64
+    where
+        F: Future<Output = u64> + Send + 'a,
+    {
+        *self.get_or_init(|| init).await
+    }
+}
+
+impl Once for async_once_cell::OnceCell<u64> {
+    async fn get_or_init<'a, F>(&'a self, init: F) -> u64
+    where
+        F: Future<Output = u64> + Send + 'a,
+    {
+        *self.get_or_init(init).await
+    }
+}
+
+/// A cell with the number of times an initializer ran.
+#[derive(Default)]
+struct Lazy<C> {
+    cell: C,
+    inits: AtomicUsize,
+}
+
+impl<C: Once> Lazy<C> {
+    /// Gets the value, initializing it in `init` with `sleep`.
+    async fn get<F, Fut>(&self, init: Duration, sleep: &F) -> u64
+    where
+        F: Fn(Duration) -> Fut + Sync,
+        Fut: Future<Output = ()> + Send,
+    {
+        self.cell
+            .get_or_init(async {
+                self.inits.fetch_add(1, Ordering::Relaxed);
+                sleep(init).await;
+                42
+            })
+            .await
+    }
+}
+
+/// Gets the value of a `C` from `tasks` tokio tasks, `reads` times each in
+/// the warm mode.
+pub fn on_tokio<C: Once>(mode: Mode, tasks: usize, init: Duration, reads: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let lazy = Arc::new(Lazy::<C>::default());
+        if let Mode::Warm = mode {
+            lazy.get(init, &tokio::time::sleep).await;
+        }
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| {
+                tokio::spawn(get(
+                    lazy.clone(),
+                    mode,
+                    start,
+                    init,
+                    reads,
+                    tokio::time::sleep,
+                ))
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for handle in handles {
+            record(&mut latencies, handle.await.unwrap());
+        }
+        lazy.report(mode, start, tasks, reads, latencies)
+    })
+}
+
+/// Gets the value of a `C` from `tasks` async_std tasks, `reads` times each
+/// in the warm mode.
+pub fn on_async_std<C: Once>(mode: Mode, tasks: usize, init: Duration, reads: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let lazy = Arc::new(Lazy::<C>::default());
+        if let Mode::Warm = mode {
+            lazy.get(init, &task::sleep).await;
+        }
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| task::spawn(get(lazy.clone(), mode, start, init, reads, task::sleep)))
+            .collect();
+        let mut latencies = latency_histogram();
+        for handle in handles {
+            record(&mut latencies, handle.await);
+        }
+        lazy.report(mode, start, tasks, reads, latencies)
+    })
+}
+
+impl<C> Lazy<C> {
+    fn report(
+        &self,
+        mode: Mode,
+        start: Instant,
+        tasks: usize,
+        reads: usize,
+        latencies: Histogram<u64>,
+    ) -> LoadReport {
+        assert_eq!(self.inits.load(Ordering::Relaxed), 1);
+        let gets = match mode {
+            Mode::Cold => tasks,
+            Mode::Warm => tasks * reads,
+        };
+        LoadReport::since(start, gets, latencies)
+    }
+}
+
+/// Gets the value once in the cold mode or `reads` times in the warm one and
+/// returns how long it took, counting from `start` in the cold mode.
+async fn get<C, F, Fut>(
+    lazy: Arc<Lazy<C>>,
+    mode: Mode,
+    start: Instant,
+    init: Duration,
+    reads: usize,
+    sleep: F,
+) -> Duration
+where
+    C: Once,
+    F: Fn(Duration) -> Fut + Sync,
+    Fut: Future<Output = ()> + Send,
+{
+    let (started, times) = match mode {
+        Mode::Cold => (start, 1),
+        Mode::Warm => (Instant::now(), reads),
+    };
+    for _ in 0..times {
+        assert_eq!(lazy.get(init, &sleep).await, 42);
+    }
+    started.elapsed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn initializes_once<C: Once>() {
+        let init = Duration::from_millis(1);
+        for &mode in &[Mode::Cold, Mode::Warm] {
+            assert_eq!(on_tokio::<C>(mode, 100, init, 10).latencies.len(), 100);
+            assert_eq!(on_async_std::<C>(mode, 100, init, 10).latencies.len(), 100);
+        }
+    }
+
+    #[test]
+    fn cells_initialize_once() {
+        initializes_once::<tokio::sync::OnceCell<u64>>();
+        initializes_once::<async_once_cell::OnceCell<u64>>();
+    }
+}
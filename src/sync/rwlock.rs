@@ -0,0 +1,280 @@
+//! Tasks reading and writing through one readers-writer lock, mostly reading.
+//!
+//! Each turn a task takes the lock for writing with probability `writes`, for
+//! reading otherwise, and yields to the runtime once while holding it, so that
+//! readers overlap and a writer has to wait for all of them to leave. Between
+//! turns it yields again, until `duration` has passed. tokio's and async_std's
+//! locks (the latter being async-lock's) are measured on both runtimes;
+//! parking_lot's lock, whose guards are held by threads, is the baseline with
+//! a thread per task that yields the same way.
+//!
+//! Latencies are the time each acquisition waited; the report also has the
+//! waits of the writers alone, whose tail shows whether readers starve them.
+
+use crate::harness::{latency_histogram, record, xorshift, LoadReport, Millis};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A readers-writer lock around a counter.
+pub trait RwLock: Default + Send + Sync + 'static {
+    /// Locks for reading, awaits `held` and returns the counter with what
+    /// `held` returned.
+    fn read<F>(&self, held: F) -> impl Future<Output = (u64, F::Output)> + Send
+    where
+        F: Future + Send,
+        F::Output: Send;
+
+    /// Locks for writing, awaits `held`, increments the counter and returns
+    /// what `held` returned.
+    fn write<F>(&self, held: F) -> impl Future<Output = F::Output> + Send
+    where
+        F: Future + Send,
+        F::Output: Send;
+}
+
+impl RwLock for tokio::sync::RwLock<u64> {
+    async fn read<F>(&self, held: F) -> (u64, F::Output)
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let counter = self.read().await;
+        let output = held.await;
+        (*counter, output)
+    }
+
+    async fn write<F>(&self, held: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let mut counter = self.write().await;
+        let output = held.await;
+        *counter += 1;
+        output
+    }
+}
+
+impl RwLock for async_std::sync::RwLock<u64> {
+    async fn read<F>(&self, held: F) -> (u64, F::Output)
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let counter = self.read().await;
+        let output = held.await;
+        (*counter, output)
+    }
+
+    async fn write<F>(&self, held: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let mut counter = self.write().await;
+        let output = held.await;
+        *counter += 1;
+        output
+    }
+}
+
+/// A load report over lock acquisitions, with the waits of the writers.
+pub struct RwLockReport {
+    pub load: LoadReport,
+    pub writes: u64,
+    /// The time each write acquisition waited.
+    pub write_waits: Histogram<u64>,
+}
+
+impl fmt::Display for RwLockReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  writes {}  write wait p99 {}  max {}",
+            self.load,
+            self.writes,
+            Millis(self.write_waits.value_at_quantile(0.99)),
+            Millis(self.write_waits.max())
+        )
+    }
+}
+
+/// The waits of one task's reads and writes.
+#[derive(Default)]
+struct Waits {
+    reads: Vec<Duration>,
+    writes: Vec<Duration>,
+}
+
+impl RwLockReport {
+    fn new(start: Instant, tasks: Vec<Waits>, counted: u64) -> Self {
+        let mut latencies = latency_histogram();
+        let mut write_waits = latency_histogram();
+        for task in tasks {
+            for wait in task.reads {
+                record(&mut latencies, wait);
+            }
+            for wait in task.writes {
+                record(&mut latencies, wait);
+                record(&mut write_waits, wait);
+            }
+        }
+        assert_eq!(counted, write_waits.len());
+        let turns = latencies.len() as usize;
+        RwLockReport {
+            load: LoadReport::since(start, turns, latencies),
+            writes: write_waits.len(),
+            write_waits,
+        }
+    }
+}
+
+/// Runs `tasks` tokio tasks through an `L` for `duration`, writing with
+/// probability `writes`.
+pub fn on_tokio<L: RwLock>(tasks: usize, writes: f64, duration: Duration) -> RwLockReport {
+    use tokio::task::yield_now;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let lock = Arc::new(L::default());
+        let start = Instant::now();
+        let handles: Vec<_> = (1..=tasks as u64)
+            .map(|seed| {
+                let turns = take_turns(lock.clone(), writes, seed, start + duration, yield_now);
+                tokio::spawn(turns)
+            })
+            .collect();
+        let mut waits = Vec::with_capacity(tasks);
+        for handle in handles {
+            waits.push(handle.await.unwrap());
+        }
+        let (counted, ()) = lock.read(async {}).await;
+        RwLockReport::new(start, waits, counted)
+    })
+}
+
+/// Runs `tasks` async_std tasks through an `L` for `duration`, writing with
+/// probability `writes`.
+pub fn on_async_std<L: RwLock>(tasks: usize, writes: f64, duration: Duration) -> RwLockReport {
+    use async_std::task::{self, yield_now};
+
+    task::block_on(async {
+        let lock = Arc::new(L::default());
+        let start = Instant::now();
+        let handles: Vec<_> = (1..=tasks as u64)
+            .map(|seed| {
+                let turns = take_turns(lock.clone(), writes, seed, start + duration, yield_now);
+                task::spawn(turns)
+            })
+            .collect();
+        let mut waits = Vec::with_capacity(tasks);
+        for handle in handles {
+            waits.push(handle.await);
+        }
+        let (counted, ()) = lock.read(async {}).await;
+        RwLockReport::new(start, waits, counted)
+    })
+}
+
+/// Runs `tasks` threads through a `parking_lot::RwLock` for `duration`,
+/// writing with probability `writes`.
+pub fn parking_lot_threads(tasks: usize, writes: f64, duration: Duration) -> RwLockReport {
+    use std::thread;
+
+    let lock = parking_lot::RwLock::new(0_u64);
+    let start = Instant::now();
+    let deadline = start + duration;
+    let waits = thread::scope(|scope| {
+        let handles: Vec<_> = (1..=tasks as u64)
+            .map(|seed| {
+                let lock = &lock;
+                scope.spawn(move || {
+                    let mut rng = seed;
+                    let mut waits = Waits::default();
+                    while Instant::now() < deadline {
+                        let asked = Instant::now();
+                        if writes_now(writes, &mut rng) {
+                            let mut counter = lock.write();
+                            waits.writes.push(asked.elapsed());
+                            thread::yield_now();
+                            *counter += 1;
+                        } else {
+                            let _counter = lock.read();
+                            waits.reads.push(asked.elapsed());
+                            thread::yield_now();
+                        }
+                        thread::yield_now();
+                    }
+                    waits
+                })
+            })
+            .collect();
+        let waits: Vec<_> = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        waits
+    });
+    let counted = *lock.read();
+    RwLockReport::new(start, waits, counted)
+}
+
+/// Whether the next turn writes, drawn with the xorshift state `rng`.
+fn writes_now(writes: f64, rng: &mut u64) -> bool {
+    (xorshift(rng) as f64 / u64::MAX as f64) < writes
+}
+
+/// Takes turns on `lock` until `deadline`, yielding with `yield_now` while
+/// holding it and after each turn.
+async fn take_turns<L, F, Fut>(
+    lock: Arc<L>,
+    writes: f64,
+    seed: u64,
+    deadline: Instant,
+    yield_now: F,
+) -> Waits
+where
+    L: RwLock,
+    F: Fn() -> Fut + Sync,
+    Fut: Future<Output = ()> + Send,
+{
+    let mut rng = seed;
+    let mut waits = Waits::default();
+    while Instant::now() < deadline {
+        let asked = Instant::now();
+        let held = async {
+            let wait = asked.elapsed();
+            yield_now().await;
+            wait
+        };
+        if writes_now(writes, &mut rng) {
+            waits.writes.push(lock.write(held).await);
+        } else {
+            waits.reads.push(lock.read(held).await.1);
+        }
+        yield_now().await;
+    }
+    waits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_write_is_counted() {
+        let duration = Duration::from_millis(20);
+        for report in [
+            on_tokio::<tokio::sync::RwLock<u64>>(8, 0.2, duration),
+            on_async_std::<tokio::sync::RwLock<u64>>(8, 0.2, duration),
+            on_tokio::<async_std::sync::RwLock<u64>>(8, 0.2, duration),
+            on_async_std::<async_std::sync::RwLock<u64>>(8, 0.2, duration),
+            parking_lot_threads(8, 0.2, duration),
+        ] {
+            assert!(report.writes > 0 && report.writes < report.load.requests);
+        }
+    }
+}
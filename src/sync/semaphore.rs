@@ -0,0 +1,167 @@
+//! Many tasks sharing a small number of permits, the usual way to bound how
+//! much of something runs at once.
+//!
+//! Each turn a task acquires a permit, yields to the runtime once while
+//! holding it, releases it and yields again, until `duration` has passed.
+//! async_std has no semaphore of its own, so tokio's is compared with
+//! async-lock's, which is what async_std's other locks are built on. Neither
+//! depends on a runtime, so both are measured on both.
+//!
+//! Latencies are the time each acquisition waited for its permit.
+
+use super::ContentionReport;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A counting semaphore.
+pub trait Semaphore: Send + Sync + 'static {
+    fn new(permits: usize) -> Self;
+
+    /// Acquires a permit, awaits `held` and releases the permit.
+    fn with_permit<F>(&self, held: F) -> impl Future<Output = F::Output> + Send
+    where
+        F: Future + Send,
+        F::Output: Send;
+}
+
+impl Semaphore for tokio::sync::Semaphore {
+    fn new(permits: usize) -> Self {
+        tokio::sync::Semaphore::new(permits)
+    }
+
+    async fn with_permit<F>(&self, held: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let _permit = self.acquire().await.unwrap();
+        held.await
+    }
+}
+
+impl Semaphore for async_lock::Semaphore {
+    fn new(permits: usize) -> Self {
+        async_lock::Semaphore::new(permits)
+    }
+
+    async fn with_permit<F>(&self, held: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let _permit = self.acquire().await;
+        held.await
+    }
+}
+
+/// Permits held now and at most.
+#[derive(Default)]
+struct Holders {
+    now: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+/// Runs `tasks` tokio tasks sharing the `permits` of an `S` for `duration`.
+pub fn on_tokio<S: Semaphore>(
+    tasks: usize,
+    permits: usize,
+    duration: Duration,
+) -> ContentionReport {
+    use tokio::task::yield_now;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let semaphore = Arc::new(S::new(permits));
+        let holders = Arc::new(Holders::default());
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| {
+                let (semaphore, holders) = (semaphore.clone(), holders.clone());
+                tokio::spawn(take_turns(semaphore, holders, start + duration, yield_now))
+            })
+            .collect();
+        let mut waits = Vec::with_capacity(tasks);
+        for handle in handles {
+            waits.push(handle.await.unwrap());
+        }
+        assert!(holders.peak.load(Ordering::Relaxed) <= permits);
+        ContentionReport::new(start, waits)
+    })
+}
+
+/// Runs `tasks` async_std tasks sharing the `permits` of an `S` for `duration`.
+pub fn on_async_std<S: Semaphore>(
+    tasks: usize,
+    permits: usize,
+    duration: Duration,
+) -> ContentionReport {
+    use async_std::task::{self, yield_now};
+
+    task::block_on(async {
+        let semaphore = Arc::new(S::new(permits));
+        let holders = Arc::new(Holders::default());
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| {
+                let (semaphore, holders) = (semaphore.clone(), holders.clone());
+                task::spawn(take_turns(semaphore, holders, start + duration, yield_now))
+            })
+            .collect();
+        let mut waits = Vec::with_capacity(tasks);
+        for handle in handles {
+            waits.push(handle.await);
+        }
+        assert!(holders.peak.load(Ordering::Relaxed) <= permits);
+        ContentionReport::new(start, waits)
+    })
+}
+
+/// Takes permits until `deadline`, yielding with `yield_now` while holding
+/// one and after each turn, and returns the time each acquisition waited.
+async fn take_turns<S, F, Fut>(
+    semaphore: Arc<S>,
+    holders: Arc<Holders>,
+    deadline: Instant,
+    yield_now: F,
+) -> Vec<Duration>
+where
+    S: Semaphore,
+    F: Fn() -> Fut + Sync,
+    Fut: Future<Output = ()> + Send,
+{
+    let mut waits = Vec::new();
+    while Instant::now() < deadline {
+        let asked = Instant::now();
+        let held = async {
+            let wait = asked.elapsed();
+            let now = holders.now.fetch_add(1, Ordering::Relaxed) + 1;
+            holders.peak.fetch_max(now, Ordering::Relaxed);
+            yield_now().await;
+            holders.now.fetch_sub(1, Ordering::Relaxed);
+            wait
+        };
+        waits.push(semaphore.with_permit(held).await);
+        yield_now().await;
+    }
+    waits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permits_bound_the_holders() {
+        let duration = Duration::from_millis(20);
+        for report in [
+            on_tokio::<tokio::sync::Semaphore>(50, 4, duration),
+            on_async_std::<tokio::sync::Semaphore>(50, 4, duration),
+            on_tokio::<async_lock::Semaphore>(50, 4, duration),
+            on_async_std::<async_lock::Semaphore>(50, 4, duration),
+        ] {
+            assert!(report.load.requests >= 50);
+        }
+    }
+}
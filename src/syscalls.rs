@@ -0,0 +1,119 @@
+//! Counting the system calls every scenario makes, on Linux, by running the
+//! benchmark again under `strace -f`.
+//!
+//! The benchmark in the child marks where every scenario starts and ends by
+//! looking up a path that does not exist and names the scenario, which strace
+//! logs like any other call; the parent then counts the calls between the
+//! marks of each scenario, on all threads, by name. Only calls that were
+//! entered between the marks count, and the marks themselves do not. Tracing
+//! slows every call down a lot, so the reports of the traced run are not
+//! worth comparing with untraced ones; the counts are what it is for.
+
+use std::collections::HashMap;
+use std::env;
+use std::process::Command;
+
+/// Start of the paths that mark the scenarios.
+const MARK: &str = "/async-runtimes-cmp-scenario/";
+
+/// Number of the most frequent calls printed for every scenario.
+const TOP: usize = 8;
+
+/// Marks the `edge`, `start` or `end`, of the scenario `name` for the tracer.
+pub(crate) fn mark(edge: &str, name: &str) {
+    let _ = std::fs::metadata(format!("{}{}/{}", MARK, edge, name));
+}
+
+/// Reruns the benchmark under strace with the scenarios marked and prints
+/// the calls each made, most frequent first.
+pub(crate) fn count() {
+    let log = env::temp_dir().join(format!("syscalls-{}.log", std::process::id()));
+    let status = Command::new("strace")
+        .arg("-f")
+        .args(["-s", "256"])
+        .arg("-o")
+        .arg(&log)
+        .arg(env::current_exe().unwrap())
+        .args(env::args_os().skip(1))
+        .env_remove("COUNT_SYSCALLS")
+        .env_remove("RESULTS")
+        .env_remove("EXPECT")
+        .env("SYSCALL_MARKS", "1")
+        .status()
+        .unwrap_or_else(|err| panic!("cannot start strace: {}", err));
+    assert!(status.success(), "traced run failed");
+    let traced = std::fs::read_to_string(&log).unwrap();
+    let _ = std::fs::remove_file(&log);
+    for (name, calls) in tally(&traced) {
+        let total: u64 = calls.iter().map(|(_, count)| count).sum();
+        let top: Vec<_> = calls
+            .iter()
+            .take(TOP)
+            .map(|(call, count)| format!("{} {}", call, count))
+            .collect();
+        println!("{:<48} {:>10} syscalls  {}", name, total, top.join("  "));
+    }
+}
+
+/// Counts the calls strace logged between the marks of every scenario, in
+/// the order the scenarios ran, most frequent call first.
+fn tally(traced: &str) -> Vec<(String, Vec<(String, u64)>)> {
+    let mut scenarios = Vec::new();
+    let mut current: Option<(String, HashMap<String, u64>)> = None;
+    for line in traced.lines() {
+        if let Some(at) = line.find(MARK) {
+            let marked = &line[at + MARK.len()..];
+            let marked = &marked[..marked.find('"').unwrap_or(marked.len())];
+            match marked.split_once('/') {
+                Some(("start", name)) => current = Some((name.to_owned(), HashMap::new())),
+                Some(("end", _)) => {
+                    if let Some((name, calls)) = current.take() {
+                        let mut calls: Vec<_> = calls.into_iter().collect();
+                        calls.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                        scenarios.push((name, calls));
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        // Lines are `<pid> <call>(<args>...`, or `<pid> <... <call> resumed>`
+        // for the rest of a call that was entered earlier.
+        let call = line
+            .split_once(' ')
+            .map(|(_, rest)| rest.trim_start())
+            .and_then(|rest| rest.split_once('('))
+            .map(|(call, _)| call)
+            .filter(|call| call.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        if let (Some((_, calls)), Some(call)) = (current.as_mut(), call) {
+            *calls.entry(call.to_owned()).or_default() += 1;
+        }
+    }
+    scenarios
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tallies_calls_between_marks() {
+        let traced = "\
+12 write(1, \"x\", 1) = 1
+12 statx(AT_FDCWD, \"/async-runtimes-cmp-scenario/start/a/1\", 0, 0, 0x1) = -1 ENOENT
+13 epoll_wait(3,  <unfinished ...>
+12 write(4, \"\\1\", 1) = 1
+13 <... epoll_wait resumed>[], 1024, -1) = 1
+13 epoll_wait(3, [], 1024, 0) = 0
+12 statx(AT_FDCWD, \"/async-runtimes-cmp-scenario/end/a/1\", 0, 0, 0x1) = -1 ENOENT
+12 write(1, \"y\", 1) = 1
+";
+        assert_eq!(
+            tally(traced),
+            vec![(
+                "a/1".to_owned(),
+                vec![("epoll_wait".to_owned(), 2), ("write".to_owned(), 1)]
+            )]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Thousands of in-flight tasks aborted at once, next to a few neighbor tasks
+//! that keep running.
+//!
+//! The tasks stand for requests in flight: each sleeps on its runtime's timer
+//! in a loop. After they have all started they are cancelled together, round
+//! after round: aborted through their handles on tokio, cancelled on
+//! async_std and dropped on smol, whose tasks are cancelled when their handle
+//! is dropped. Teardown is timed until every task is gone, and the gaps of
+//! the neighbors show how long the storm kept them from running.
+
+use super::{neighbor, CancelReport};
+use crate::harness::{Alive, Guard};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long every in-flight task sleeps at a time.
+const IN_FLIGHT: Duration = Duration::from_millis(10);
+
+/// How long the tasks run before they are aborted.
+const WARMUP: Duration = Duration::from_millis(20);
+
+/// How many tasks are aborted, and how often.
+#[derive(Clone, Copy, Debug)]
+pub struct Storm {
+    /// Tasks aborted at once.
+    pub tasks: usize,
+    /// Storms per run.
+    pub rounds: usize,
+    /// Tasks running next to the aborted ones.
+    pub neighbors: usize,
+}
+
+/// Aborts storms of tokio tasks.
+pub fn on_tokio(storm: Storm) -> CancelReport {
+    use tokio::task::yield_now;
+    use tokio::time::sleep;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        tokio::spawn(async move {
+            let stop = Arc::new(AtomicBool::new(false));
+            let neighbors: Vec<_> = (0..storm.neighbors)
+                .map(|_| tokio::spawn(neighbor(stop.clone(), yield_now)))
+                .collect();
+            let alive = Alive::default();
+            let mut teardown = Duration::ZERO;
+            for _ in 0..storm.rounds {
+                let handles: Vec<_> = (0..storm.tasks)
+                    .map(|_| tokio::spawn(in_flight(alive.guard(), sleep)))
+                    .collect();
+                sleep(WARMUP).await;
+                let start = Instant::now();
+                for handle in &handles {
+                    handle.abort();
+                }
+                alive.gone(yield_now).await;
+                teardown += start.elapsed();
+            }
+            stop.store(true, Ordering::Relaxed);
+            let mut gaps = Vec::with_capacity(storm.neighbors);
+            for neighbor in neighbors {
+                gaps.push(neighbor.await.unwrap());
+            }
+            CancelReport::new(storm.tasks * storm.rounds, teardown, gaps)
+        })
+        .await
+        .unwrap()
+    })
+}
+
+/// Cancels storms of async_std tasks.
+pub fn on_async_std(storm: Storm) -> CancelReport {
+    use async_std::task::{self, sleep, yield_now};
+
+    task::block_on(task::spawn(async move {
+        let stop = Arc::new(AtomicBool::new(false));
+        let neighbors: Vec<_> = (0..storm.neighbors)
+            .map(|_| task::spawn(neighbor(stop.clone(), yield_now)))
+            .collect();
+        let alive = Alive::default();
+        let mut teardown = Duration::ZERO;
+        for _ in 0..storm.rounds {
+            let handles: Vec<_> = (0..storm.tasks)
+                .map(|_| task::spawn(in_flight(alive.guard(), sleep)))
+                .collect();
+            sleep(WARMUP).await;
+            let start = Instant::now();
+            let mut cancelled: FuturesUnordered<_> =
+                handles.into_iter().map(|handle| handle.cancel()).collect();
+            while cancelled.next().await.is_some() {}
+            alive.gone(yield_now).await;
+            teardown += start.elapsed();
+        }
+        stop.store(true, Ordering::Relaxed);
+        let mut gaps = Vec::with_capacity(storm.neighbors);
+        for neighbor in neighbors {
+            gaps.push(neighbor.await);
+        }
+        CancelReport::new(storm.tasks * storm.rounds, teardown, gaps)
+    }))
+}
+
+/// Drops storms of smol tasks.
+pub fn on_smol(storm: Storm) -> CancelReport {
+    use smol::future::yield_now;
+
+    let sleep = |duration| async move {
+        smol::Timer::after(duration).await;
+    };
+    smol::block_on(smol::spawn(async move {
+        let stop = Arc::new(AtomicBool::new(false));
+        let neighbors: Vec<_> = (0..storm.neighbors)
+            .map(|_| smol::spawn(neighbor(stop.clone(), yield_now)))
+            .collect();
+        let alive = Alive::default();
+        let mut teardown = Duration::ZERO;
+        for _ in 0..storm.rounds {
+            let handles: Vec<_> = (0..storm.tasks)
+                .map(|_| smol::spawn(in_flight(alive.guard(), sleep)))
+                .collect();
+            sleep(WARMUP).await;
+            let start = Instant::now();
+            drop(handles);
+            alive.gone(yield_now).await;
+            teardown += start.elapsed();
+        }
+        stop.store(true, Ordering::Relaxed);
+        let mut gaps = Vec::with_capacity(storm.neighbors);
+        for neighbor in neighbors {
+            gaps.push(neighbor.await);
+        }
+        CancelReport::new(storm.tasks * storm.rounds, teardown, gaps)
+    }))
+}
+
+/// Sleeps with `sleep` in a loop until dropped.
+pub(crate) async fn in_flight<F, Fut>(guard: Guard, sleep: F)
+where
+    F: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let _guard = guard;
+    loop {
+        sleep(IN_FLIGHT).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_storm_tears_down_every_task() {
+        let storm = Storm {
+            tasks: 500,
+            rounds: 2,
+            neighbors: 2,
+        };
+        for run in [on_tokio, on_async_std, on_smol] {
+            assert_eq!(run(storm).cancelled, 1_000);
+        }
+    }
+}
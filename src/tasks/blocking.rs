@@ -0,0 +1,200 @@
+//! Blocking calls made from tasks: inline on the worker, through tokio's
+//! `block_in_place` and moved to the blocking pools of `spawn_blocking` on
+//! tokio and async_std, next to a few neighbor tasks that keep yielding.
+//!
+//! Every call is a `std::thread::sleep`, the way a task waits on a blocking
+//! API; `tasks` tasks make them one after another. Inline calls hold their
+//! worker for the whole call, so the neighbors and the other callers on that
+//! worker wait; `block_in_place` hands the worker's other tasks to a new
+//! thread first, and `spawn_blocking` leaves the worker free at the price of
+//! moving every call to another thread and back. async_std has no
+//! `block_in_place`, though its executor hands the queue of a worker that is
+//! stuck for long to another thread by itself.
+//!
+//! Throughput is that of the calls, and the gaps of the neighbors show how
+//! long the calls kept them from running.
+
+use super::neighbor;
+use crate::harness::{latency_histogram, record, split, LoadReport, Millis};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Where the blocking calls run.
+#[derive(Clone, Copy, Debug)]
+pub enum Blocking {
+    Inline,
+    /// In `tokio::task::block_in_place`, only on tokio.
+    BlockInPlace,
+    SpawnBlocking,
+}
+
+/// The blocking calls to make.
+#[derive(Clone, Copy, Debug)]
+pub struct Calls {
+    /// Tasks making the calls.
+    pub tasks: usize,
+    pub calls: usize,
+    /// Time every call blocks for.
+    pub block: Duration,
+    /// Tasks running next to the callers.
+    pub neighbors: usize,
+}
+
+/// A load report over the calls, with the gaps of the neighbors.
+pub struct BlockingReport {
+    /// Throughput and latencies of the calls.
+    pub load: LoadReport,
+    /// Time between the polls of every yield of the neighbors.
+    pub gaps: Histogram<u64>,
+}
+
+impl fmt::Display for BlockingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  gap p99 {}  max {}",
+            self.load,
+            Millis(self.gaps.value_at_quantile(0.99)),
+            Millis(self.gaps.max())
+        )
+    }
+}
+
+impl BlockingReport {
+    fn new(start: Instant, calls: Vec<Vec<Duration>>, neighbors: Vec<Vec<Duration>>) -> Self {
+        let mut latencies = latency_histogram();
+        let mut count = 0;
+        for latency in calls.into_iter().flatten() {
+            record(&mut latencies, latency);
+            count += 1;
+        }
+        let mut gaps = latency_histogram();
+        for gap in neighbors.into_iter().flatten() {
+            record(&mut gaps, gap);
+        }
+        BlockingReport {
+            load: LoadReport::since(start, count, latencies),
+            gaps,
+        }
+    }
+}
+
+/// Makes `calls` blocking calls from tokio tasks, running them as `how` says.
+pub fn tokio(how: Blocking, calls: Calls) -> BlockingReport {
+    use tokio::task::{self, yield_now};
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        let neighbors: Vec<_> = (0..calls.neighbors)
+            .map(|_| tokio::spawn(neighbor(stop.clone(), yield_now)))
+            .collect();
+        let block = calls.block;
+        let start = Instant::now();
+        let callers: Vec<_> = split(calls.calls, calls.tasks)
+            .map(|share| {
+                tokio::spawn(async move {
+                    let mut latencies = Vec::with_capacity(share);
+                    for _ in 0..share {
+                        let called = Instant::now();
+                        match how {
+                            Blocking::Inline => std::thread::sleep(block),
+                            Blocking::BlockInPlace => {
+                                task::block_in_place(|| std::thread::sleep(block))
+                            }
+                            Blocking::SpawnBlocking => {
+                                task::spawn_blocking(move || std::thread::sleep(block))
+                                    .await
+                                    .unwrap()
+                            }
+                        }
+                        latencies.push(called.elapsed());
+                    }
+                    latencies
+                })
+            })
+            .collect();
+        let mut latencies = Vec::with_capacity(calls.tasks);
+        for caller in callers {
+            latencies.push(caller.await.unwrap());
+        }
+        stop.store(true, Ordering::Relaxed);
+        let mut gaps = Vec::with_capacity(calls.neighbors);
+        for neighbor in neighbors {
+            gaps.push(neighbor.await.unwrap());
+        }
+        BlockingReport::new(start, latencies, gaps)
+    })
+}
+
+/// Makes `calls` blocking calls from async_std tasks, running them as `how`
+/// says; async_std has no `block_in_place`, so `how` must not ask for it.
+pub fn async_std(how: Blocking, calls: Calls) -> BlockingReport {
+    use async_std::task::{self, yield_now};
+
+    task::block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        let neighbors: Vec<_> = (0..calls.neighbors)
+            .map(|_| task::spawn(neighbor(stop.clone(), yield_now)))
+            .collect();
+        let block = calls.block;
+        let start = Instant::now();
+        let callers: Vec<_> = split(calls.calls, calls.tasks)
+            .map(|share| {
+                task::spawn(async move {
+                    let mut latencies = Vec::with_capacity(share);
+                    for _ in 0..share {
+                        let called = Instant::now();
+                        match how {
+                            Blocking::Inline => std::thread::sleep(block),
+                            Blocking::BlockInPlace => panic!("async_std has no block_in_place"),
+                            Blocking::SpawnBlocking => {
+                                task::spawn_blocking(move || std::thread::sleep(block)).await
+                            }
+                        }
+                        latencies.push(called.elapsed());
+                    }
+                    latencies
+                })
+            })
+            .collect();
+        let mut latencies = Vec::with_capacity(calls.tasks);
+        for caller in callers {
+            latencies.push(caller.await);
+        }
+        stop.store(true, Ordering::Relaxed);
+        let mut gaps = Vec::with_capacity(calls.neighbors);
+        for neighbor in neighbors {
+            gaps.push(neighbor.await);
+        }
+        BlockingReport::new(start, latencies, gaps)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_call_is_made() {
+        let calls = Calls {
+            tasks: 4,
+            calls: 20,
+            block: Duration::from_micros(100),
+            neighbors: 2,
+        };
+        for how in [
+            Blocking::Inline,
+            Blocking::BlockInPlace,
+            Blocking::SpawnBlocking,
+        ] {
+            assert_eq!(tokio(how, calls).load.requests, 20);
+        }
+        for how in [Blocking::Inline, Blocking::SpawnBlocking] {
+            assert_eq!(async_std(how, calls).load.requests, 20);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Batches of independent futures, each spinning for the same work, either
+//! spawned as tasks and awaited or joined inline in the task that makes them,
+//! on tokio, async_std and smol, as the work grows.
+//!
+//! A spawned future costs allocating and scheduling a task and waking the
+//! spawner when it is done, but the runtime can run it on any of its workers;
+//! joined with `join_all`, the futures run one after another on the thread
+//! polling the joining task, at the cost of a poll each. Below some work per
+//! future spawning only adds its cost, and above it spreading the work over
+//! the workers pays for it; where that is depends on the number of workers, so
+//! with one core it never pays. smol runs on its global executor, which has one
+//! thread unless `SMOL_THREADS` says otherwise. The cost is per future.
+
+use super::CostReport;
+use futures::future::{join_all, BoxFuture};
+use std::future::Future;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// How the futures of a batch are run.
+#[derive(Clone, Copy, Debug)]
+pub enum Running {
+    /// Spawned as tasks whose handles are joined.
+    Spawned,
+    /// Joined in the task that makes them.
+    Joined,
+}
+
+/// Batches of futures, each spinning for `work`.
+#[derive(Clone, Copy, Debug)]
+pub struct Batches {
+    pub futures: usize,
+    pub work: Duration,
+    pub batches: usize,
+}
+
+async fn spinning(i: usize, work: Duration) -> usize {
+    let start = Instant::now();
+    while start.elapsed() < work {
+        std::hint::spin_loop();
+    }
+    black_box(i)
+}
+
+/// Runs `batches` with `running`, spawning futures with `spawn`, which
+/// returns a future of the spawned future's output.
+async fn batching<S, H>(running: Running, batches: Batches, spawn: S) -> CostReport
+where
+    S: Fn(BoxFuture<'static, usize>) -> H,
+    H: Future<Output = usize>,
+{
+    let Batches {
+        futures,
+        work,
+        batches: rounds,
+    } = batches;
+    let start = Instant::now();
+    for _ in 0..rounds {
+        let sum: usize = match running {
+            Running::Spawned => join_all((0..futures).map(|i| spawn(Box::pin(spinning(i, work)))))
+                .await
+                .into_iter()
+                .sum(),
+            Running::Joined => join_all((0..futures).map(|i| spinning(i, work)))
+                .await
+                .into_iter()
+                .sum(),
+        };
+        assert_eq!(sum, futures * futures.saturating_sub(1) / 2, "lost futures");
+    }
+    CostReport::new(futures * rounds, start.elapsed())
+}
+
+/// Runs `batches` in a tokio task.
+pub fn tokio(running: Running, batches: Batches) -> CostReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let run = batching(running, batches, |future| {
+            let handle = tokio::spawn(future);
+            async { handle.await.unwrap() }
+        });
+        tokio::spawn(run).await.unwrap()
+    })
+}
+
+/// Runs `batches` in an async_std task.
+pub fn async_std(running: Running, batches: Batches) -> CostReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(batching(running, batches, task::spawn)))
+}
+
+/// Runs `batches` in a smol task.
+pub fn smol(running: Running, batches: Batches) -> CostReport {
+    smol::block_on(smol::spawn(batching(running, batches, smol::spawn)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_future_of_every_batch_runs() {
+        let batches = Batches {
+            futures: 50,
+            work: Duration::from_micros(1),
+            batches: 4,
+        };
+        for run in [tokio, async_std, smol] {
+            for running in [Running::Spawned, Running::Joined] {
+                assert_eq!(run(running, batches).operations, 200);
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Tasks cancelled while they hold a buffer and an open file, batch after
+//! batch, next to a few neighbor tasks that keep running.
+//!
+//! Every task allocates and fills a buffer, opens a file with its runtime's
+//! file API and then waits forever. Once all tasks of a batch are waiting they
+//! are cancelled: aborted through their handles on tokio and with `cancel` on
+//! async_std, where dropping a handle would only detach the task. Dropping a
+//! task frees its buffer and closes its file on the thread that drops it,
+//! which is time the neighbors cannot run; their gaps show it.
+
+use super::{neighbor, CancelReport};
+use crate::harness::{split, Alive, Guard};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How much is cancelled.
+#[derive(Clone, Copy, Debug)]
+pub struct Churn {
+    /// Tasks cancelled in all.
+    pub tasks: usize,
+    /// Tasks waiting at the same time before being cancelled together.
+    pub batch: usize,
+    /// Bytes of the buffer each task holds.
+    pub buffer: usize,
+    /// Tasks running next to the cancelled ones.
+    pub neighbors: usize,
+}
+
+/// Cancels tokio tasks holding `path` open as `churn` says.
+pub fn on_tokio(path: &Path, churn: Churn) -> CancelReport {
+    use tokio::task::yield_now;
+
+    let path = path.to_path_buf();
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        tokio::spawn(async move {
+            let stop = Arc::new(AtomicBool::new(false));
+            let neighbors: Vec<_> = (0..churn.neighbors)
+                .map(|_| tokio::spawn(neighbor(stop.clone(), yield_now)))
+                .collect();
+            let alive = Alive::default();
+            let mut cancelling = Duration::ZERO;
+            for batch in split(churn.tasks, churn.tasks.div_ceil(churn.batch)) {
+                let (ready, readies) = async_channel::bounded(batch);
+                let handles: Vec<_> = (0..batch)
+                    .map(|_| {
+                        let (guard, path, ready) = (alive.guard(), path.clone(), ready.clone());
+                        tokio::spawn(async move {
+                            let buffer = vec![1_u8; churn.buffer];
+                            let file = tokio::fs::File::open(path).await.unwrap();
+                            held(ready, (guard, buffer, file)).await
+                        })
+                    })
+                    .collect();
+                for _ in 0..batch {
+                    readies.recv().await.unwrap();
+                }
+                let start = Instant::now();
+                for handle in &handles {
+                    handle.abort();
+                }
+                alive.gone(yield_now).await;
+                cancelling += start.elapsed();
+            }
+            stop.store(true, Ordering::Relaxed);
+            let mut gaps = Vec::with_capacity(churn.neighbors);
+            for neighbor in neighbors {
+                gaps.push(neighbor.await.unwrap());
+            }
+            CancelReport::new(churn.tasks, cancelling, gaps)
+        })
+        .await
+        .unwrap()
+    })
+}
+
+/// Cancels async_std tasks holding `path` open as `churn` says.
+pub fn on_async_std(path: &Path, churn: Churn) -> CancelReport {
+    use async_std::task::{self, yield_now};
+
+    let path = path.to_path_buf();
+    task::block_on(task::spawn(async move {
+        let stop = Arc::new(AtomicBool::new(false));
+        let neighbors: Vec<_> = (0..churn.neighbors)
+            .map(|_| task::spawn(neighbor(stop.clone(), yield_now)))
+            .collect();
+        let alive = Alive::default();
+        let mut cancelling = Duration::ZERO;
+        for batch in split(churn.tasks, churn.tasks.div_ceil(churn.batch)) {
+            let (ready, readies) = async_channel::bounded(batch);
+            let handles: Vec<_> = (0..batch)
+                .map(|_| {
+                    let (guard, path, ready) = (alive.guard(), path.clone(), ready.clone());
+                    task::spawn(async move {
+                        let buffer = vec![1_u8; churn.buffer];
+                        let file = async_std::fs::File::open(path).await.unwrap();
+                        held(ready, (guard, buffer, file)).await
+                    })
+                })
+                .collect();
+            for _ in 0..batch {
+                readies.recv().await.unwrap();
+            }
+            let start = Instant::now();
+            let mut cancelled: FuturesUnordered<_> =
+                handles.into_iter().map(|handle| handle.cancel()).collect();
+            while cancelled.next().await.is_some() {}
+            alive.gone(yield_now).await;
+            cancelling += start.elapsed();
+        }
+        stop.store(true, Ordering::Relaxed);
+        let mut gaps = Vec::with_capacity(churn.neighbors);
+        for neighbor in neighbors {
+            gaps.push(neighbor.await);
+        }
+        CancelReport::new(churn.tasks, cancelling, gaps)
+    }))
+}
+
+/// Says it is ready on `ready` and then holds on to `held` until dropped.
+async fn held<T>(ready: async_channel::Sender<()>, held: (Guard, Vec<u8>, T)) {
+    let _held = held;
+    ready.send(()).await.unwrap();
+    std::future::pending::<()>().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_task_is_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("held");
+        std::fs::write(&path, b"held").unwrap();
+        let churn = Churn {
+            tasks: 300,
+            batch: 100,
+            buffer: 1024,
+            neighbors: 2,
+        };
+        for run in [on_tokio, on_async_std] {
+            let report = run(&path, churn);
+            assert_eq!(report.cancelled, 300);
+            assert!(!report.gaps.is_empty());
+        }
+    }
+}
@@ -0,0 +1,531 @@
+//! Operations cancelled at random points, over and over, with the state they
+//! touch checked afterwards for what the cancellations broke.
+//!
+//! Every operation is raced against a cancellation at a random time up to a
+//! limit: half of them run under the runtime's timeout, the others are
+//! spawned as tasks of their own and aborted, or cancelled on async_std and
+//! smol, while they may be in the middle of a poll of another thread. The
+//! operations run one after another, so only cancellation breaks them:
+//!
+//! - `channel`: receives a few items from the runtime's channel, which a
+//!   producer task keeps feeding, into a list. Afterwards every item must be
+//!   either in the list or still in the channel, exactly once; every item
+//!   missing or received twice is a violation.
+//! - `write`: appends a record to a file with the runtime's file API. Every
+//!   record that is not whole afterwards is a violation, the kind of garbage
+//!   a cancelled write leaves behind. Writes handed to a blocking pool may
+//!   still land after the cancellation, so the file is read once they had
+//!   time to settle.
+//! - `transfer`: moves a unit from one counter to another under the
+//!   runtime's mutex, yielding in between. Every unit missing from the total
+//!   afterwards is a violation: the guard was dropped with the transfer half
+//!   done.
+//!
+//! Afterwards the mutex and the channel must still be usable: if they cannot
+//! be taken within a second, the run counts as deadlocked.
+
+use crate::harness::xorshift;
+use futures::future::{select, BoxFuture, Either};
+use futures::FutureExt;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Items every operation of the channel workload receives.
+const ITEMS: usize = 8;
+
+/// How long the mutex or the channel may take to be usable again.
+const DEADLOCK: Duration = Duration::from_secs(1);
+
+/// How long writes cancelled on a blocking pool are given to land.
+const SETTLE: Duration = Duration::from_millis(100);
+
+/// The operations cancelled.
+#[derive(Clone, Copy, Debug)]
+pub enum Workload {
+    Channel,
+    /// Records of that many bytes.
+    Write(usize),
+    Transfer,
+}
+
+/// How many operations are cancelled, and when.
+#[derive(Clone, Copy, Debug)]
+pub struct Stress {
+    pub operations: usize,
+    /// Latest an operation is cancelled after it started.
+    pub within: Duration,
+    pub seed: u64,
+}
+
+/// How many operations were cancelled, and what the cancellations broke.
+pub struct SafetyReport {
+    pub operations: usize,
+    pub cancelled: usize,
+    pub violations: usize,
+    pub deadlocked: bool,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for SafetyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>10.0} ops/s  {:>5.1}% cancelled  {:>6} violations  {}",
+            self.operations as f64 / self.elapsed.as_secs_f64(),
+            self.cancelled as f64 * 100.0 / self.operations.max(1) as f64,
+            self.violations,
+            if self.deadlocked {
+                "DEADLOCKED"
+            } else {
+                "no deadlock"
+            }
+        )
+    }
+}
+
+/// A mutex of the runtime's, running every future handed to it while
+/// holding the mutex.
+type Locked = Arc<dyn Fn(BoxFuture<'static, ()>) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// The ends of an unbounded channel of the runtime's.
+struct Channel {
+    send: Box<dyn Fn(u64) -> BoxFuture<'static, ()> + Send>,
+    /// Receives the next item, or `None` once the channel is closed.
+    recv: Arc<dyn Fn() -> BoxFuture<'static, Option<u64>> + Send + Sync>,
+    /// Receives an item already in the channel, if there is one.
+    try_recv: Box<dyn Fn() -> Option<u64> + Send>,
+}
+
+/// What the workloads need of a runtime.
+#[derive(Clone, Copy)]
+struct Runtime {
+    /// Runs an operation under the runtime's timeout, returning whether it
+    /// completed in time.
+    timeout: fn(Duration, BoxFuture<'static, ()>) -> BoxFuture<'static, bool>,
+    /// Spawns an operation and aborts it after a time, returning whether it
+    /// completed first.
+    abort: fn(Duration, BoxFuture<'static, ()>) -> BoxFuture<'static, bool>,
+    spawn: fn(BoxFuture<'static, ()>) -> BoxFuture<'static, ()>,
+    sleep: fn(Duration) -> BoxFuture<'static, ()>,
+    yield_now: fn() -> BoxFuture<'static, ()>,
+    mutex: fn() -> Locked,
+    channel: fn() -> Channel,
+    /// Appends a record to the file at a path, creating it if needed.
+    append: fn(PathBuf, Vec<u8>) -> BoxFuture<'static, ()>,
+}
+
+/// Runs the operations `operation` makes one after another, each cancelled
+/// at a random point, and returns how many were cancelled and how long it
+/// all took.
+async fn race(
+    runtime: Runtime,
+    stress: Stress,
+    operation: impl Fn(usize) -> BoxFuture<'static, ()>,
+) -> (usize, Duration) {
+    let mut rng = stress.seed.max(1);
+    let within = stress.within.as_nanos() as u64;
+    let mut cancelled = 0;
+    let start = Instant::now();
+    for i in 0..stress.operations {
+        let at = Duration::from_nanos(xorshift(&mut rng) % (within + 1));
+        let cancel = if xorshift(&mut rng) % 2 == 0 {
+            runtime.timeout
+        } else {
+            runtime.abort
+        };
+        if !cancel(at, operation(i)).await {
+            cancelled += 1;
+        }
+    }
+    (cancelled, start.elapsed())
+}
+
+/// Whether `locked` can be taken again within [`DEADLOCK`].
+async fn usable(runtime: Runtime, locked: &Locked) -> bool {
+    (runtime.timeout)(DEADLOCK, locked(async {}.boxed())).await
+}
+
+/// Items of `0..sent` missing from `received`, or in it more than once.
+fn lost_or_duplicated(sent: usize, mut received: Vec<u64>) -> usize {
+    received.sort_unstable();
+    let before = received.len();
+    received.dedup();
+    let duplicated = before - received.len();
+    duplicated + sent
+        - received
+            .iter()
+            .filter(|&&item| (item as usize) < sent)
+            .count()
+}
+
+/// Records in `contents` that are not whole: runs of one byte that are not
+/// `size` bytes long. Records are told apart by their byte, so a partial one
+/// does not shift the records after it.
+fn torn(contents: &[u8], size: usize) -> usize {
+    let runs = contents.chunk_by(|a, b| a == b);
+    runs.filter(|run| run.len() != size).count()
+}
+
+/// The byte record `i` is filled with, never the same as the one before.
+fn fill(i: usize) -> u8 {
+    (i % 251) as u8
+}
+
+fn report(
+    stress: Stress,
+    (cancelled, elapsed): (usize, Duration),
+    violations: usize,
+    deadlocked: bool,
+) -> SafetyReport {
+    SafetyReport {
+        operations: stress.operations,
+        cancelled,
+        violations,
+        deadlocked,
+        elapsed,
+    }
+}
+
+/// Cancels the operations of `workload` on `runtime`, appending records to
+/// the file at `path`.
+async fn run(workload: Workload, stress: Stress, path: PathBuf, runtime: Runtime) -> SafetyReport {
+    let locked = (runtime.mutex)();
+    match workload {
+        Workload::Channel => {
+            let sent = stress.operations * ITEMS;
+            let Channel {
+                send,
+                recv,
+                try_recv,
+            } = (runtime.channel)();
+            let producer = (runtime.spawn)(
+                async move {
+                    for item in 0..sent as u64 {
+                        send(item).await;
+                        (runtime.yield_now)().await;
+                    }
+                }
+                .boxed(),
+            );
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let raced = race(runtime, stress, |_| {
+                let (recv, received) = (recv.clone(), received.clone());
+                locked(
+                    async move {
+                        for _ in 0..ITEMS {
+                            match recv().await {
+                                Some(item) => received.lock().unwrap().push(item),
+                                None => break,
+                            }
+                        }
+                    }
+                    .boxed(),
+                )
+            })
+            .await;
+            producer.await;
+            if !usable(runtime, &locked).await {
+                return report(stress, raced, 0, true);
+            }
+            let mut received = std::mem::take(&mut *received.lock().unwrap());
+            while let Some(item) = try_recv() {
+                received.push(item);
+            }
+            report(stress, raced, lost_or_duplicated(sent, received), false)
+        }
+        Workload::Write(size) => {
+            let raced = race(runtime, stress, |i| {
+                (runtime.append)(path.clone(), vec![fill(i); size])
+            })
+            .await;
+            (runtime.sleep)(SETTLE).await;
+            let contents = std::fs::read(&path).unwrap_or_default();
+            report(stress, raced, torn(&contents, size), false)
+        }
+        Workload::Transfer => {
+            let total = stress.operations as u64;
+            let accounts = Arc::new((AtomicU64::new(total), AtomicU64::new(0)));
+            let raced = race(runtime, stress, |_| {
+                let accounts = accounts.clone();
+                locked(
+                    async move {
+                        accounts.0.fetch_sub(1, Ordering::Relaxed);
+                        (runtime.yield_now)().await;
+                        accounts.1.fetch_add(1, Ordering::Relaxed);
+                    }
+                    .boxed(),
+                )
+            })
+            .await;
+            if !usable(runtime, &locked).await {
+                return report(stress, raced, 0, true);
+            }
+            let left = accounts.0.load(Ordering::Relaxed) + accounts.1.load(Ordering::Relaxed);
+            report(stress, raced, (total - left) as usize, false)
+        }
+    }
+}
+
+/// Cancels the operations of `workload` on tokio, with its files in `dir`.
+pub fn on_tokio(workload: Workload, stress: Stress, dir: &Path) -> SafetyReport {
+    use tokio::time::timeout;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(run(
+        workload,
+        stress,
+        dir.join("records-tokio"),
+        Runtime {
+            timeout: |at, operation| async move { timeout(at, operation).await.is_ok() }.boxed(),
+            abort: |at, operation| {
+                async move {
+                    let mut handle = tokio::spawn(operation);
+                    if timeout(at, &mut handle).await.is_ok() {
+                        return true;
+                    }
+                    handle.abort();
+                    handle.await.is_ok()
+                }
+                .boxed()
+            },
+            spawn: |task| tokio::spawn(task).map(Result::unwrap).boxed(),
+            sleep: |duration| tokio::time::sleep(duration).boxed(),
+            yield_now: || tokio::task::yield_now().boxed(),
+            mutex: || {
+                let mutex = Arc::new(tokio::sync::Mutex::new(()));
+                Arc::new(move |section: BoxFuture<'static, ()>| {
+                    let mutex = mutex.clone();
+                    async move {
+                        let _guard = mutex.lock().await;
+                        section.await;
+                    }
+                    .boxed()
+                })
+            },
+            channel: || {
+                let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+                // Behind a mutex, as receiving takes the receiver mutably.
+                let receiver = Arc::new(tokio::sync::Mutex::new(receiver));
+                let receiving = receiver.clone();
+                Channel {
+                    send: Box::new(move |item: u64| {
+                        sender.send(item).unwrap();
+                        async {}.boxed()
+                    }),
+                    recv: Arc::new(move || {
+                        let receiver = receiving.clone();
+                        async move {
+                            let mut receiver = receiver.lock().await;
+                            receiver.recv().await
+                        }
+                        .boxed()
+                    }),
+                    try_recv: Box::new(move || receiver.try_lock().ok()?.try_recv().ok()),
+                }
+            },
+            append: |path, record| {
+                async move {
+                    use tokio::io::AsyncWriteExt;
+                    let mut file = tokio::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .await
+                        .unwrap();
+                    file.write_all(&record).await.unwrap();
+                    file.flush().await.unwrap();
+                }
+                .boxed()
+            },
+        },
+    ))
+}
+
+/// Cancels the operations of `workload` on async_std, with its files in
+/// `dir`.
+pub fn on_async_std(workload: Workload, stress: Stress, dir: &Path) -> SafetyReport {
+    use async_std::future::timeout;
+    use async_std::task::{self, sleep};
+
+    task::block_on(run(
+        workload,
+        stress,
+        dir.join("records-async-std"),
+        Runtime {
+            timeout: |at, operation| async move { timeout(at, operation).await.is_ok() }.boxed(),
+            abort: |at, operation| {
+                async move {
+                    match select(task::spawn(operation), sleep(at).boxed()).await {
+                        Either::Left(_) => true,
+                        Either::Right((_, handle)) => handle.cancel().await.is_some(),
+                    }
+                }
+                .boxed()
+            },
+            spawn: |task| task::spawn(task).boxed(),
+            sleep: |duration| sleep(duration).boxed(),
+            yield_now: || task::yield_now().boxed(),
+            mutex: || {
+                let mutex = Arc::new(async_std::sync::Mutex::new(()));
+                Arc::new(move |section: BoxFuture<'static, ()>| {
+                    let mutex = mutex.clone();
+                    async move {
+                        let _guard = mutex.lock().await;
+                        section.await;
+                    }
+                    .boxed()
+                })
+            },
+            channel: || {
+                let (sender, receiver) = async_std::channel::unbounded();
+                let receiving = receiver.clone();
+                Channel {
+                    send: Box::new(move |item: u64| {
+                        let sender = sender.clone();
+                        async move { sender.send(item).await.unwrap() }.boxed()
+                    }),
+                    recv: Arc::new(move || {
+                        let receiver = receiving.clone();
+                        async move { receiver.recv().await.ok() }.boxed()
+                    }),
+                    try_recv: Box::new(move || receiver.try_recv().ok()),
+                }
+            },
+            append: |path, record| {
+                async move {
+                    use async_std::io::WriteExt;
+                    let mut file = async_std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .await
+                        .unwrap();
+                    file.write_all(&record).await.unwrap();
+                    file.flush().await.unwrap();
+                }
+                .boxed()
+            },
+        },
+    ))
+}
+
+/// Cancels the operations of `workload` on smol, with its files in `dir`.
+pub fn on_smol(workload: Workload, stress: Stress, dir: &Path) -> SafetyReport {
+    use smol::Timer;
+
+    smol::block_on(run(
+        workload,
+        stress,
+        dir.join("records-smol"),
+        Runtime {
+            timeout: |at, operation| {
+                let expired = async move {
+                    Timer::after(at).await;
+                    false
+                };
+                smol::future::or(operation.map(|()| true), expired).boxed()
+            },
+            abort: |at, operation| {
+                async move {
+                    match select(smol::spawn(operation), Timer::after(at)).await {
+                        Either::Left(_) => true,
+                        Either::Right((_, task)) => task.cancel().await.is_some(),
+                    }
+                }
+                .boxed()
+            },
+            spawn: |task| smol::spawn(task).boxed(),
+            sleep: |duration| {
+                async move {
+                    Timer::after(duration).await;
+                }
+                .boxed()
+            },
+            yield_now: || smol::future::yield_now().boxed(),
+            mutex: || {
+                let mutex = Arc::new(smol::lock::Mutex::new(()));
+                Arc::new(move |section: BoxFuture<'static, ()>| {
+                    let mutex = mutex.clone();
+                    async move {
+                        let _guard = mutex.lock().await;
+                        section.await;
+                    }
+                    .boxed()
+                })
+            },
+            channel: || {
+                let (sender, receiver) = smol::channel::unbounded();
+                let receiving = receiver.clone();
+                Channel {
+                    send: Box::new(move |item: u64| {
+                        let sender = sender.clone();
+                        async move { sender.send(item).await.unwrap() }.boxed()
+                    }),
+                    recv: Arc::new(move || {
+                        let receiver = receiving.clone();
+                        async move { receiver.recv().await.ok() }.boxed()
+                    }),
+                    try_recv: Box::new(move || receiver.try_recv().ok()),
+                }
+            },
+            append: |path, record| {
+                async move {
+                    use smol::io::AsyncWriteExt;
+                    let mut file = smol::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .await
+                        .unwrap();
+                    file.write_all(&record).await.unwrap();
+                    file.flush().await.unwrap();
+                }
+                .boxed()
+            },
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_lost_and_torn_state() {
+        assert_eq!(lost_or_duplicated(5, vec![4, 0, 1, 3, 2]), 0);
+        assert_eq!(lost_or_duplicated(5, vec![4, 0, 1, 1, 3]), 2);
+        assert_eq!(torn(&[1, 1, 2, 2, 3], 2), 1);
+        assert_eq!(torn(&[1, 2, 2, 2], 2), 2);
+        let mut appended = vec![1; 4];
+        appended.extend([2; 3]);
+        for fill in 3..100 {
+            appended.extend([fill; 4]);
+        }
+        assert_eq!(torn(&appended, 4), 1);
+    }
+
+    #[test]
+    fn receiving_survives_cancellation_everywhere() {
+        let dir = tempfile::tempdir().unwrap();
+        let stress = Stress {
+            operations: 200,
+            within: Duration::from_micros(200),
+            seed: 1,
+        };
+        for workload in [Workload::Channel, Workload::Write(1024), Workload::Transfer] {
+            for report in [
+                on_tokio(workload, stress, dir.path()),
+                on_async_std(workload, stress, dir.path()),
+                on_smol(workload, stress, dir.path()),
+            ] {
+                assert!(!report.deadlocked);
+                if let Workload::Channel = workload {
+                    assert_eq!(report.violations, 0);
+                }
+            }
+        }
+    }
+}
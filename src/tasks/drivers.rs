@@ -0,0 +1,88 @@
+//! Tokio runtimes with and without their IO and timer drivers, on one thread
+//! and on many, running tasks that touch neither, to see whether drivers that
+//! are enabled but unused cost anything.
+//!
+//! The features of tokio are unified across the whole build, so every variant
+//! has the same code compiled in; what differs is which drivers the builder
+//! enables, and a driver that is not enabled is never built or polled, which
+//! is all that leaving out its feature would save at run time. Every task
+//! yields with tokio's `yield_now`, which defers the wakeup until the worker
+//! has polled its drivers, so a yield pays for the enabled drivers each time.
+
+use super::CostReport;
+use std::time::Instant;
+use tokio::runtime::Builder;
+
+/// Which scheduler the runtime has.
+#[derive(Clone, Copy, Debug)]
+pub enum Flavor {
+    CurrentThread,
+    MultiThread,
+}
+
+/// Which drivers the runtime enables.
+#[derive(Clone, Copy, Debug)]
+pub enum Drivers {
+    None,
+    Time,
+    Io,
+    All,
+}
+
+/// Tasks that yield, and how many.
+#[derive(Clone, Copy, Debug)]
+pub struct Yielders {
+    pub tasks: usize,
+    /// Times every task yields.
+    pub yields: usize,
+}
+
+/// Runs `yielders` on a runtime of `flavor` with `drivers` enabled, timing
+/// them from the first spawn until all are done.
+pub fn run(flavor: Flavor, drivers: Drivers, yielders: Yielders) -> CostReport {
+    let mut builder = match flavor {
+        Flavor::CurrentThread => Builder::new_current_thread(),
+        Flavor::MultiThread => Builder::new_multi_thread(),
+    };
+    match drivers {
+        Drivers::None => &mut builder,
+        Drivers::Time => builder.enable_time(),
+        Drivers::Io => builder.enable_io(),
+        Drivers::All => builder.enable_all(),
+    };
+    let rt = builder.build().unwrap();
+    rt.block_on(async {
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..yielders.tasks)
+            .map(|_| {
+                tokio::spawn(async move {
+                    for _ in 0..yielders.yields {
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        CostReport::new(yielders.tasks * yielders.yields, start.elapsed())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_variant_runs_the_tasks() {
+        let yielders = Yielders {
+            tasks: 10,
+            yields: 100,
+        };
+        for flavor in [Flavor::CurrentThread, Flavor::MultiThread] {
+            for drivers in [Drivers::None, Drivers::Time, Drivers::Io, Drivers::All] {
+                assert_eq!(run(flavor, drivers, yielders).operations, 1_000);
+            }
+        }
+    }
+}
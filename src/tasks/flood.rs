@@ -0,0 +1,111 @@
+//! A million trivial tasks spawned as fast as one task can spawn them, on
+//! tokio, async_std and smol: how many tasks a runtime takes in, and what
+//! holding them costs.
+//!
+//! The spawning time ends with the last spawn and the completion time with the
+//! last task having run, which the tasks find out by counting. Tasks start
+//! running on the other worker threads while they are still being spawned, so
+//! how many are queued at once, and with them the peak memory, depends on how
+//! far the spawner outpaces the workers.
+//!
+//! Peak memory is the largest growth of the resident set over its size before
+//! the run, sampled every millisecond by a thread of its own. Memory freed by
+//! an earlier scenario in the same process can be reused without growing the
+//! resident set, so filter the run down to a single scenario for the most
+//! reliable figure.
+
+use super::Countdown;
+use crate::harness::PeakMemory;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long spawning and running the tasks took, and the memory it needed.
+pub struct FloodReport {
+    pub tasks: u64,
+    /// Time from the first spawn to the last.
+    pub spawning: Duration,
+    /// Time from the first spawn until every task had run.
+    pub completion: Duration,
+    /// Largest growth of the resident set in bytes, or `None` if the resident
+    /// set size is not available on this platform.
+    pub peak_memory: Option<u64>,
+}
+
+impl fmt::Display for FloodReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>10.0} spawns/s  done in {:>8.1}ms",
+            self.tasks as f64 / self.spawning.as_secs_f64(),
+            self.completion.as_secs_f64() * 1e3
+        )?;
+        match self.peak_memory {
+            Some(bytes) => write!(f, "  peak {:>7.1} MiB", bytes as f64 / (1 << 20) as f64),
+            None => write!(f, "  memory n/a"),
+        }
+    }
+}
+
+/// Times spawning `tasks` with `spawn` and waiting for all of them to run.
+async fn flood<F>(tasks: usize, mut spawn: F) -> FloodReport
+where
+    F: FnMut(Arc<Countdown>),
+{
+    let (countdown, all_done) = Countdown::new(tasks);
+    let peak = PeakMemory::start();
+    let start = Instant::now();
+    for _ in 0..tasks {
+        spawn(countdown.clone());
+    }
+    let spawning = start.elapsed();
+    all_done.recv().await.unwrap();
+    let completion = start.elapsed();
+    FloodReport {
+        tasks: tasks as u64,
+        spawning,
+        completion,
+        peak_memory: peak.stop(),
+    }
+}
+
+/// Spawns `tasks` trivial tokio tasks from a tokio task.
+pub fn tokio(tasks: usize) -> FloodReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let run = flood(tasks, |countdown| {
+            tokio::spawn(async move { countdown.count() });
+        });
+        tokio::spawn(run).await.unwrap()
+    })
+}
+
+/// Spawns `tasks` trivial async_std tasks from an async_std task.
+pub fn async_std(tasks: usize) -> FloodReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(flood(tasks, |countdown| {
+        task::spawn(async move { countdown.count() });
+    })))
+}
+
+/// Spawns `tasks` trivial smol tasks from a smol task.
+pub fn smol(tasks: usize) -> FloodReport {
+    smol::block_on(smol::spawn(flood(tasks, |countdown| {
+        smol::spawn(async move { countdown.count() }).detach();
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_task_runs() {
+        for run in [tokio, async_std, smol] {
+            let report = run(1_000);
+            assert_eq!(report.tasks, 1_000);
+            assert!(report.spawning <= report.completion);
+        }
+    }
+}
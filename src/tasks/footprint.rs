@@ -0,0 +1,179 @@
+//! Memory held by idle tasks on tokio, async_std and smol, for tasks holding
+//! nothing, a buffer or a socket.
+//!
+//! All tasks are spawned, run until they wait on a channel that stays open
+//! for the whole run, and are counted once they do; when all of them have,
+//! the growth of the resident set since before the first spawn is divided by
+//! the number of tasks. Buffers are filled so that their pages are resident,
+//! and sockets are UDP sockets of the runtime's own type bound on localhost,
+//! registered with its reactor. The tasks need a file descriptor each with
+//! sockets: raise `ulimit -n` before running with many of them.
+//!
+//! Memory freed by an earlier scenario in the same process can be reused
+//! without growing the resident set, so filter the run down to a single
+//! scenario for the most reliable figure.
+
+use super::Countdown;
+use crate::harness::{resident_memory, Alive, Guard};
+use std::fmt;
+use std::sync::Arc;
+
+/// What every idle task holds.
+#[derive(Clone, Copy, Debug)]
+pub enum Body {
+    Empty,
+    /// A buffer of that many bytes.
+    Buffer(usize),
+    Socket,
+}
+
+/// How much memory the tasks held.
+pub struct FootprintReport {
+    pub tasks: u64,
+    /// Growth of the resident set per task in bytes, or `None` if the
+    /// resident set size is not available on this platform.
+    pub memory_per_task: Option<u64>,
+}
+
+impl FootprintReport {
+    fn new(before: Option<u64>, tasks: usize) -> Self {
+        let memory_per_task = before
+            .zip(resident_memory())
+            .map(|(before, after)| after.saturating_sub(before) / tasks as u64);
+        FootprintReport {
+            tasks: tasks as u64,
+            memory_per_task,
+        }
+    }
+}
+
+impl fmt::Display for FootprintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.memory_per_task {
+            Some(bytes) => write!(f, "{:>10} B/task  {:>8} tasks", bytes, self.tasks),
+            None => write!(f, "memory n/a"),
+        }
+    }
+}
+
+/// Holds `held` until `stopped` closes, once `waiting` knows it is waiting.
+async fn hold<T>(
+    held: T,
+    waiting: Arc<Countdown>,
+    stopped: async_channel::Receiver<()>,
+    _alive: Guard,
+) {
+    waiting.count();
+    let _ = stopped.recv().await;
+    drop(held);
+}
+
+/// Measures `tasks` idle tokio tasks holding `body`.
+pub fn tokio(body: Body, tasks: usize) -> FootprintReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (waiting, all_waiting) = Countdown::new(tasks);
+        let (stop, stopped) = async_channel::bounded(1);
+        let alive = Alive::default();
+        let before = resident_memory();
+        for _ in 0..tasks {
+            let (waiting, stopped, guard) = (waiting.clone(), stopped.clone(), alive.guard());
+            match body {
+                Body::Empty => tokio::spawn(hold((), waiting, stopped, guard)),
+                Body::Buffer(size) => {
+                    tokio::spawn(
+                        async move { hold(vec![1_u8; size], waiting, stopped, guard).await },
+                    )
+                }
+                Body::Socket => tokio::spawn(async move {
+                    let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+                    hold(socket, waiting, stopped, guard).await
+                }),
+            };
+        }
+        all_waiting.recv().await.unwrap();
+        let report = FootprintReport::new(before, tasks);
+        drop(stop);
+        alive.gone(tokio::task::yield_now).await;
+        report
+    })
+}
+
+/// Measures `tasks` idle async_std tasks holding `body`.
+pub fn async_std(body: Body, tasks: usize) -> FootprintReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (waiting, all_waiting) = Countdown::new(tasks);
+        let (stop, stopped) = async_channel::bounded(1);
+        let alive = Alive::default();
+        let before = resident_memory();
+        for _ in 0..tasks {
+            let (waiting, stopped, guard) = (waiting.clone(), stopped.clone(), alive.guard());
+            match body {
+                Body::Empty => task::spawn(hold((), waiting, stopped, guard)),
+                Body::Buffer(size) => {
+                    task::spawn(
+                        async move { hold(vec![1_u8; size], waiting, stopped, guard).await },
+                    )
+                }
+                Body::Socket => task::spawn(async move {
+                    let socket = async_std::net::UdpSocket::bind("127.0.0.1:0")
+                        .await
+                        .unwrap();
+                    hold(socket, waiting, stopped, guard).await
+                }),
+            };
+        }
+        all_waiting.recv().await.unwrap();
+        let report = FootprintReport::new(before, tasks);
+        drop(stop);
+        alive.gone(task::yield_now).await;
+        report
+    })
+}
+
+/// Measures `tasks` idle smol tasks holding `body`.
+pub fn smol(body: Body, tasks: usize) -> FootprintReport {
+    smol::block_on(async {
+        let (waiting, all_waiting) = Countdown::new(tasks);
+        let (stop, stopped) = async_channel::bounded(1);
+        let alive = Alive::default();
+        let before = resident_memory();
+        for _ in 0..tasks {
+            let (waiting, stopped, guard) = (waiting.clone(), stopped.clone(), alive.guard());
+            match body {
+                Body::Empty => smol::spawn(hold((), waiting, stopped, guard)),
+                Body::Buffer(size) => {
+                    smol::spawn(
+                        async move { hold(vec![1_u8; size], waiting, stopped, guard).await },
+                    )
+                }
+                Body::Socket => smol::spawn(async move {
+                    let socket = smol::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+                    hold(socket, waiting, stopped, guard).await
+                }),
+            }
+            .detach();
+        }
+        all_waiting.recv().await.unwrap();
+        let report = FootprintReport::new(before, tasks);
+        drop(stop);
+        alive.gone(smol::future::yield_now).await;
+        report
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_task_waits_and_ends() {
+        for run in [tokio, async_std, smol] {
+            for body in [Body::Empty, Body::Buffer(64), Body::Socket] {
+                assert_eq!(run(body, 100).tasks, 100);
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Many tasks that cannot leave their thread, holding `Rc`s, against `Send`
+//! tasks doing the same work.
+//!
+//! Every task bumps a shared counter and yields, `steps` times. Local tasks
+//! share an `Rc<Cell<u64>>` and run on tokio's `LocalSet`, with async_std's
+//! `spawn_local` and on a smol `LocalExecutor`; `Send` tasks share an
+//! `Arc<AtomicU64>` and run on a current-thread tokio runtime, which does the
+//! work on one thread as well, on the multi-threaded one, on async_std's pool
+//! and on a smol `Executor` driven by one thread.
+
+use super::CostReport;
+use std::cell::Cell;
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Runs the local tasks on a `LocalSet` of a current-thread tokio runtime.
+pub fn tokio_local(tasks: usize, steps: usize) -> CostReport {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let local = tokio::task::LocalSet::new();
+    local.block_on(&rt, async {
+        let counter = Rc::new(Cell::new(0));
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| {
+                tokio::task::spawn_local(bumping_local(
+                    counter.clone(),
+                    steps,
+                    tokio::task::yield_now,
+                ))
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        report(tasks, steps, start, counter.get())
+    })
+}
+
+/// Runs the `Send` tasks on a current-thread tokio runtime.
+pub fn tokio_current_thread(tasks: usize, steps: usize) -> CostReport {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    rt.block_on(on_tokio(tasks, steps))
+}
+
+/// Runs the `Send` tasks on a multi-threaded tokio runtime.
+pub fn tokio_multi_thread(tasks: usize, steps: usize) -> CostReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(on_tokio(tasks, steps))
+}
+
+async fn on_tokio(tasks: usize, steps: usize) -> CostReport {
+    let counter = Arc::new(AtomicU64::new(0));
+    let start = Instant::now();
+    let handles: Vec<_> = (0..tasks)
+        .map(|_| tokio::spawn(bumping(counter.clone(), steps, tokio::task::yield_now)))
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    report(tasks, steps, start, counter.load(Ordering::Relaxed))
+}
+
+/// Runs the local tasks with async_std's `spawn_local`.
+pub fn async_std_local(tasks: usize, steps: usize) -> CostReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let counter = Rc::new(Cell::new(0));
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| task::spawn_local(bumping_local(counter.clone(), steps, task::yield_now)))
+            .collect();
+        for handle in handles {
+            handle.await;
+        }
+        report(tasks, steps, start, counter.get())
+    })
+}
+
+/// Runs the `Send` tasks on async_std's pool.
+pub fn async_std(tasks: usize, steps: usize) -> CostReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let counter = Arc::new(AtomicU64::new(0));
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| task::spawn(bumping(counter.clone(), steps, task::yield_now)))
+            .collect();
+        for handle in handles {
+            handle.await;
+        }
+        report(tasks, steps, start, counter.load(Ordering::Relaxed))
+    })
+}
+
+/// Runs the local tasks on a smol `LocalExecutor`.
+pub fn smol_local(tasks: usize, steps: usize) -> CostReport {
+    let executor = smol::LocalExecutor::new();
+    smol::block_on(executor.run(async {
+        let counter = Rc::new(Cell::new(0));
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| {
+                executor.spawn(bumping_local(
+                    counter.clone(),
+                    steps,
+                    smol::future::yield_now,
+                ))
+            })
+            .collect();
+        for handle in handles {
+            handle.await;
+        }
+        report(tasks, steps, start, counter.get())
+    }))
+}
+
+/// Runs the `Send` tasks on a smol `Executor` driven by the calling thread.
+pub fn smol(tasks: usize, steps: usize) -> CostReport {
+    let executor = smol::Executor::new();
+    smol::block_on(executor.run(async {
+        let counter = Arc::new(AtomicU64::new(0));
+        let start = Instant::now();
+        let handles: Vec<_> = (0..tasks)
+            .map(|_| executor.spawn(bumping(counter.clone(), steps, smol::future::yield_now)))
+            .collect();
+        for handle in handles {
+            handle.await;
+        }
+        report(tasks, steps, start, counter.load(Ordering::Relaxed))
+    }))
+}
+
+fn report(tasks: usize, steps: usize, start: Instant, counted: u64) -> CostReport {
+    assert_eq!(counted, (tasks * steps) as u64, "lost steps");
+    CostReport::new(tasks * steps, start.elapsed())
+}
+
+async fn bumping_local<F, Fut>(counter: Rc<Cell<u64>>, steps: usize, yield_now: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    for _ in 0..steps {
+        counter.set(counter.get() + 1);
+        yield_now().await;
+    }
+}
+
+async fn bumping<F, Fut>(counter: Arc<AtomicU64>, steps: usize, yield_now: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    for _ in 0..steps {
+        counter.fetch_add(1, Ordering::Relaxed);
+        yield_now().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_step_is_counted() {
+        for run in [
+            tokio_local,
+            tokio_current_thread,
+            tokio_multi_thread,
+            async_std_local,
+            async_std,
+            smol_local,
+            smol,
+        ] {
+            assert_eq!(run(100, 10).operations, 1_000);
+        }
+    }
+}
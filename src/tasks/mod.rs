@@ -0,0 +1,136 @@
+//! Tasks themselves: what spawning, waking, yielding and cancelling them
+//! costs on each runtime.
+
+pub mod abort;
+pub mod blocking;
+pub mod breakeven;
+pub mod cancel;
+pub mod cancel_safety;
+pub mod drivers;
+pub mod flood;
+pub mod footprint;
+pub mod local;
+pub mod nested;
+pub mod ping_pong;
+pub mod scoped;
+pub mod shutdown;
+#[cfg(unix)]
+pub mod signal;
+pub mod sleepers;
+pub mod spawn;
+pub mod startup;
+pub mod steal;
+pub mod submit;
+pub mod teardown;
+pub mod tuning;
+pub mod wake;
+pub mod waker;
+pub mod yield_now;
+
+use crate::harness::{latency_histogram, record, Millis};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a number of operations on tasks took.
+pub struct CostReport {
+    pub operations: u64,
+    pub elapsed: Duration,
+}
+
+impl CostReport {
+    fn new(operations: usize, elapsed: Duration) -> Self {
+        CostReport {
+            operations: operations as u64,
+            elapsed,
+        }
+    }
+}
+
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.elapsed.as_secs_f64();
+        write!(
+            f,
+            "{:>8.1} ns/op  {:>10.0} ops/s",
+            seconds * 1e9 / self.operations as f64,
+            self.operations as f64 / seconds
+        )
+    }
+}
+
+/// How fast tasks were cancelled, and how long tasks running next to them
+/// waited for their turns meanwhile.
+pub struct CancelReport {
+    pub cancelled: u64,
+    /// Time from starting to cancel until the cancelled tasks were gone,
+    /// summed over all cancellations.
+    pub cancelling: Duration,
+    /// Time between the polls of every yield of the neighbors.
+    pub gaps: Histogram<u64>,
+}
+
+impl fmt::Display for CancelReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>10.0} cancelled/s  gap p50 {}  p99 {}  max {}",
+            self.cancelled as f64 / self.cancelling.as_secs_f64(),
+            Millis(self.gaps.value_at_quantile(0.5)),
+            Millis(self.gaps.value_at_quantile(0.99)),
+            Millis(self.gaps.max())
+        )
+    }
+}
+
+impl CancelReport {
+    fn new(cancelled: usize, cancelling: Duration, neighbors: Vec<Vec<Duration>>) -> Self {
+        let mut gaps = latency_histogram();
+        for gap in neighbors.into_iter().flatten() {
+            record(&mut gaps, gap);
+        }
+        CancelReport {
+            cancelled: cancelled as u64,
+            cancelling,
+            gaps,
+        }
+    }
+}
+
+/// Yields with `yield_now` until `stop` is set, timing every yield.
+pub(crate) async fn neighbor<F, Fut>(stop: Arc<AtomicBool>, yield_now: F) -> Vec<Duration>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut gaps = Vec::new();
+    while !stop.load(Ordering::Relaxed) {
+        let yielded = Instant::now();
+        yield_now().await;
+        gaps.push(yielded.elapsed());
+    }
+    gaps
+}
+
+/// Tasks that ran, and who to tell once all of them did.
+struct Countdown {
+    left: AtomicUsize,
+    done: async_channel::Sender<()>,
+}
+
+impl Countdown {
+    fn new(tasks: usize) -> (Arc<Self>, async_channel::Receiver<()>) {
+        let (done, all_done) = async_channel::bounded(1);
+        let left = AtomicUsize::new(tasks);
+        (Arc::new(Countdown { left, done }), all_done)
+    }
+
+    fn count(&self) {
+        if self.left.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.done.try_send(()).unwrap();
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Trees of tasks: every task spawns `fanout` children, which do the same
+//! until the tree is `depth` levels deep, and waits for all of them.
+//!
+//! The leaves return one and every task returns the sum of its children, so
+//! a run checks that every leaf ran. Only the leaves do anything but wait, so
+//! the time is spent on spawning, on the scheduler's queues, which fill with
+//! the newest children first, and on waking every parent once its last child
+//! is done. The cost is reported per task of the tree.
+
+use super::tuning::Tuning;
+use super::CostReport;
+use futures::future::BoxFuture;
+use std::time::Instant;
+
+/// Number of tasks in a tree of `depth` levels below the root.
+fn size(depth: u32, fanout: usize) -> usize {
+    (0..=depth).map(|level| fanout.pow(level)).sum()
+}
+
+/// Runs a tree of tokio tasks.
+pub fn on_tokio(depth: u32, fanout: usize) -> CostReport {
+    on_tokio_tuned(depth, fanout, Tuning::default())
+}
+
+/// Runs a tree of tokio tasks on a runtime with the knobs of `tuning` set.
+pub fn on_tokio_tuned(depth: u32, fanout: usize, tuning: Tuning) -> CostReport {
+    let rt = tuning.runtime();
+    rt.block_on(async {
+        let start = Instant::now();
+        let leaves = tokio::spawn(tokio_tree(depth, fanout)).await.unwrap();
+        assert_eq!(leaves, fanout.pow(depth));
+        CostReport::new(size(depth, fanout), start.elapsed())
+    })
+}
+
+fn tokio_tree(depth: u32, fanout: usize) -> BoxFuture<'static, usize> {
+    Box::pin(async move {
+        if depth == 0 {
+            return 1;
+        }
+        let children: Vec<_> = (0..fanout)
+            .map(|_| tokio::spawn(tokio_tree(depth - 1, fanout)))
+            .collect();
+        let mut leaves = 0;
+        for child in children {
+            leaves += child.await.unwrap();
+        }
+        leaves
+    })
+}
+
+/// Runs a tree of async_std tasks.
+pub fn on_async_std(depth: u32, fanout: usize) -> CostReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let start = Instant::now();
+        let leaves = task::spawn(async_std_tree(depth, fanout)).await;
+        assert_eq!(leaves, fanout.pow(depth));
+        CostReport::new(size(depth, fanout), start.elapsed())
+    })
+}
+
+fn async_std_tree(depth: u32, fanout: usize) -> BoxFuture<'static, usize> {
+    Box::pin(async move {
+        if depth == 0 {
+            return 1;
+        }
+        let children: Vec<_> = (0..fanout)
+            .map(|_| async_std::task::spawn(async_std_tree(depth - 1, fanout)))
+            .collect();
+        let mut leaves = 0;
+        for child in children {
+            leaves += child.await;
+        }
+        leaves
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_leaf_runs() {
+        assert_eq!(size(3, 2), 15);
+        for run in [on_tokio, on_async_std] {
+            assert_eq!(run(6, 3).operations, size(6, 3) as u64);
+            assert_eq!(run(200, 1).operations, 201);
+        }
+    }
+}
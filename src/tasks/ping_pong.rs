@@ -0,0 +1,158 @@
+//! Two tasks bouncing one message back and forth over a pair of async-channel
+//! channels, on the same worker thread and on two, on tokio, async_std and
+//! smol.
+//!
+//! On the same worker both tasks run on one single-threaded executor: a
+//! current-thread tokio runtime, async_std's `spawn_local` and a smol
+//! `LocalExecutor`. Across workers every task gets an executor on a thread of
+//! its own, so that every hop wakes a task on the other thread; the
+//! work-stealing schedulers give no way to keep two tasks on different
+//! workers. The channels do not depend on a runtime, so the runtimes only
+//! differ in how they wake and schedule the receiving task.
+//!
+//! Latencies are single hops, from sending the message until the other task
+//! received it.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use hdrhistogram::Histogram;
+use std::thread;
+use std::time::Instant;
+
+/// Where the two tasks run.
+#[derive(Clone, Copy, Debug)]
+pub enum Placement {
+    SameWorker,
+    CrossWorker,
+}
+
+/// A message with the number of hops it has left to make.
+type Ball = (Instant, usize);
+
+/// One player's ends of the channels.
+struct Player {
+    inbox: async_channel::Receiver<Ball>,
+    outbox: async_channel::Sender<Ball>,
+}
+
+/// Two connected players, with the message for `hops` hops already sent to
+/// the first.
+fn players(hops: usize) -> (Player, Player) {
+    let (to_first, first_inbox) = async_channel::bounded(1);
+    let (to_second, second_inbox) = async_channel::bounded(1);
+    to_first.try_send((Instant::now(), hops - 1)).unwrap();
+    let first = Player {
+        inbox: first_inbox,
+        outbox: to_second,
+    };
+    let second = Player {
+        inbox: second_inbox,
+        outbox: to_first,
+    };
+    (first, second)
+}
+
+impl Player {
+    /// Sends every message back until none are left, recording every hop.
+    async fn play(self) -> Histogram<u64> {
+        let mut latencies = latency_histogram();
+        while let Ok((sent, left)) = self.inbox.recv().await {
+            record(&mut latencies, sent.elapsed());
+            if left == 0 {
+                break;
+            }
+            self.outbox.send((Instant::now(), left - 1)).await.unwrap();
+        }
+        latencies
+    }
+}
+
+fn report(
+    start: Instant,
+    hops: usize,
+    mut first: Histogram<u64>,
+    second: Histogram<u64>,
+) -> LoadReport {
+    first.add(second).unwrap();
+    LoadReport::since(start, hops, first)
+}
+
+/// Makes `hops` hops on a tokio runtime per worker.
+pub fn tokio(placement: Placement, hops: usize) -> LoadReport {
+    let runtime = || {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+    };
+    let (first, second) = players(hops);
+    let start = Instant::now();
+    let (first, second) = match placement {
+        Placement::SameWorker => runtime().block_on(async {
+            let first = tokio::spawn(first.play());
+            let second = tokio::spawn(second.play());
+            (first.await.unwrap(), second.await.unwrap())
+        }),
+        Placement::CrossWorker => {
+            let second = thread::spawn(move || runtime().block_on(second.play()));
+            let first = runtime().block_on(first.play());
+            (first, second.join().unwrap())
+        }
+    };
+    report(start, hops, first, second)
+}
+
+/// Makes `hops` hops on async_std, with one thread per worker.
+pub fn async_std(placement: Placement, hops: usize) -> LoadReport {
+    use async_std::task;
+
+    let (first, second) = players(hops);
+    let start = Instant::now();
+    let (first, second) = match placement {
+        Placement::SameWorker => task::block_on(async {
+            let first = task::spawn_local(first.play());
+            let second = task::spawn_local(second.play());
+            (first.await, second.await)
+        }),
+        Placement::CrossWorker => {
+            let second = thread::spawn(move || task::block_on(second.play()));
+            let first = task::block_on(first.play());
+            (first, second.join().unwrap())
+        }
+    };
+    report(start, hops, first, second)
+}
+
+/// Makes `hops` hops on smol, with one thread per worker.
+pub fn smol(placement: Placement, hops: usize) -> LoadReport {
+    let (first, second) = players(hops);
+    let start = Instant::now();
+    let (first, second) = match placement {
+        Placement::SameWorker => {
+            let executor = smol::LocalExecutor::new();
+            smol::block_on(executor.run(async {
+                let first = executor.spawn(first.play());
+                let second = executor.spawn(second.play());
+                (first.await, second.await)
+            }))
+        }
+        Placement::CrossWorker => {
+            let second = thread::spawn(move || smol::block_on(second.play()));
+            let first = smol::block_on(first.play());
+            (first, second.join().unwrap())
+        }
+    };
+    report(start, hops, first, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_hop_is_recorded() {
+        for run in [tokio, async_std, smol] {
+            for placement in [Placement::SameWorker, Placement::CrossWorker] {
+                assert_eq!(run(placement, 101).latencies.len(), 101);
+            }
+        }
+    }
+}
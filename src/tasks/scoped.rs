@@ -0,0 +1,220 @@
+//! Parallel work over borrowed data in scoped tasks, against `'static` tasks
+//! sharing the data through an `Arc`, on tokio and async_std.
+//!
+//! Every round sums a slice of `items` numbers in `tasks` parts, one task per
+//! part, and adds the sums up. `'static` tasks, spawned one by one or into a
+//! tokio `JoinSet`, own what they use, so each gets a clone of the `Arc` and
+//! the range of its part. async-scoped's scopes spawn tasks onto the runtime
+//! that borrow their part and are awaited until all of them are done, with
+//! `scope_and_collect`: its safe `scope_and_block` blocks the worker of the
+//! caller instead, which on an async_std executor of one thread leaves none
+//! for the tasks. A moro scope borrows too, but
+//! its jobs are futures polled by the scope itself rather than tasks, so they
+//! run concurrently on the caller's task and never in parallel. The cost is
+//! per part, from spawning its task to adding up its sum.
+
+use super::CostReport;
+use crate::harness;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// How the parts are handed to their tasks.
+#[derive(Clone, Copy, Debug)]
+pub enum Scoping {
+    /// `'static` tasks spawned one by one with a clone of the `Arc`.
+    Static,
+    /// `'static` tasks in a tokio `JoinSet`, only on tokio.
+    JoinSet,
+    /// Tasks borrowing their part in an async-scoped scope.
+    AsyncScoped,
+    /// Jobs borrowing their part in a moro scope.
+    Moro,
+}
+
+/// The numbers to sum and how they are split.
+#[derive(Clone, Copy, Debug)]
+pub struct Split {
+    pub tasks: usize,
+    pub items: usize,
+    pub rounds: usize,
+}
+
+impl Split {
+    fn numbers(&self) -> Arc<[u64]> {
+        (0..self.items as u64).collect()
+    }
+
+    fn parts(&self) -> impl Iterator<Item = Range<usize>> {
+        let mut start = 0;
+        harness::split(self.items, self.tasks).map(move |len| {
+            start += len;
+            start - len..start
+        })
+    }
+
+    fn check(&self, sum: u64) {
+        let items = self.items as u64;
+        assert_eq!(sum, items * items.saturating_sub(1) / 2, "lost parts");
+    }
+}
+
+/// Sums the parts of `numbers` as jobs of a moro scope.
+///
+/// The scope is made with `scope_fn` rather than the `async_scope!` macro, as
+/// the body has to be an `async move` block for this edition to let it keep
+/// the scope it is given.
+async fn moro(numbers: &[u64], split: Split) -> u64 {
+    moro::scope_fn(|scope| {
+        Box::pin(async move {
+            let jobs: Vec<_> = split
+                .parts()
+                .map(|part| {
+                    let part = &numbers[part];
+                    scope.spawn(async move { part.iter().sum::<u64>() })
+                })
+                .collect();
+            let mut sum = 0;
+            for job in jobs {
+                sum += job.await;
+            }
+            sum
+        })
+    })
+    .await
+}
+
+/// Sums `split` from a tokio task, handing the parts out as `scoping` says.
+pub fn tokio(scoping: Scoping, split: Split) -> CostReport {
+    use async_scoped::TokioScope;
+    use tokio::task::JoinSet;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        tokio::spawn(async move {
+            let numbers = split.numbers();
+            let start = Instant::now();
+            for _ in 0..split.rounds {
+                let sum = match scoping {
+                    Scoping::Static => {
+                        let handles: Vec<_> = split
+                            .parts()
+                            .map(|part| {
+                                let numbers = numbers.clone();
+                                tokio::spawn(async move { numbers[part].iter().sum::<u64>() })
+                            })
+                            .collect();
+                        let mut sum = 0;
+                        for handle in handles {
+                            sum += handle.await.unwrap();
+                        }
+                        sum
+                    }
+                    Scoping::JoinSet => {
+                        let mut set = JoinSet::new();
+                        for part in split.parts() {
+                            let numbers = numbers.clone();
+                            set.spawn(async move { numbers[part].iter().sum::<u64>() });
+                        }
+                        let mut sum = 0;
+                        while let Some(part) = set.join_next().await {
+                            sum += part.unwrap();
+                        }
+                        sum
+                    }
+                    Scoping::AsyncScoped => {
+                        // Safety: the scope is awaited until all its tasks are
+                        // done and never dropped before.
+                        let ((), sums) = unsafe {
+                            TokioScope::scope_and_collect(|scope| {
+                                for part in split.parts() {
+                                    let part = &numbers[part];
+                                    scope.spawn(async move { part.iter().sum::<u64>() });
+                                }
+                            })
+                            .await
+                        };
+                        sums.into_iter().map(Result::unwrap).sum()
+                    }
+                    Scoping::Moro => moro(&numbers, split).await,
+                };
+                split.check(sum);
+            }
+            CostReport::new(split.rounds * split.tasks, start.elapsed())
+        })
+        .await
+        .unwrap()
+    })
+}
+
+/// Sums `split` from an async_std task, handing the parts out as `scoping`
+/// says.
+pub fn async_std(scoping: Scoping, split: Split) -> CostReport {
+    use async_scoped::AsyncStdScope;
+    use async_std::task;
+
+    task::block_on(task::spawn(async move {
+        let numbers = split.numbers();
+        let start = Instant::now();
+        for _ in 0..split.rounds {
+            let sum = match scoping {
+                Scoping::Static => {
+                    let handles: Vec<_> = split
+                        .parts()
+                        .map(|part| {
+                            let numbers = numbers.clone();
+                            task::spawn(async move { numbers[part].iter().sum::<u64>() })
+                        })
+                        .collect();
+                    let mut sum = 0;
+                    for handle in handles {
+                        sum += handle.await;
+                    }
+                    sum
+                }
+                Scoping::JoinSet => panic!("async_std has no JoinSet"),
+                Scoping::AsyncScoped => {
+                    // Safety: as on tokio.
+                    let ((), sums) = unsafe {
+                        AsyncStdScope::scope_and_collect(|scope| {
+                            for part in split.parts() {
+                                let part = &numbers[part];
+                                scope.spawn(async move { part.iter().sum::<u64>() });
+                            }
+                        })
+                        .await
+                    };
+                    sums.into_iter().sum()
+                }
+                Scoping::Moro => moro(&numbers, split).await,
+            };
+            split.check(sum);
+        }
+        CostReport::new(split.rounds * split.tasks, start.elapsed())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_scoping_sums_every_part() {
+        let split = Split {
+            tasks: 7,
+            items: 1_000,
+            rounds: 10,
+        };
+        for scoping in [
+            Scoping::Static,
+            Scoping::JoinSet,
+            Scoping::AsyncScoped,
+            Scoping::Moro,
+        ] {
+            assert_eq!(tokio(scoping, split).operations, 70);
+        }
+        for scoping in [Scoping::Static, Scoping::AsyncScoped, Scoping::Moro] {
+            assert_eq!(async_std(scoping, split).operations, 70);
+        }
+    }
+}
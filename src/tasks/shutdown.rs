@@ -0,0 +1,223 @@
+//! Shutting down thousands of busy tasks gracefully, on tokio and async_std,
+//! with a `CancellationToken`, by closing a channel and with a stop flag.
+//!
+//! Every task works in units, each a sleep, the way a task waits on its I/O.
+//! Their first units are of different lengths, so that the tasks are spread
+//! evenly over a unit when the shutdown is signalled. Tasks watching a
+//! `CancellationToken` from tokio-util or waiting for a channel to close race
+//! every unit against the signal and drop the unit they are in; tasks polling
+//! a stop flag only check it between units, so they finish the unit they are
+//! in. The token and the channel do not depend on a runtime, so both are
+//! measured on both runtimes.
+//!
+//! Quiescence is the time from the signal until every task has ended. Lost
+//! units are those that were dropped when they had begun but not finished.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// How the tasks are told to stop.
+#[derive(Clone, Copy, Debug)]
+pub enum Signal {
+    Token,
+    ChannelClose,
+    StopFlag,
+}
+
+/// What the tasks do before they are told to stop.
+#[derive(Clone, Copy, Debug)]
+pub struct Work {
+    pub tasks: usize,
+    /// Time every unit of work sleeps for.
+    pub unit: Duration,
+    /// Time from spawning the tasks to signalling them.
+    pub after: Duration,
+}
+
+/// How long the tasks took to stop and the work they dropped.
+pub struct ShutdownReport {
+    pub tasks: u64,
+    /// Time from the signal until every task had ended.
+    pub quiescence: Duration,
+    /// Units finished by all tasks.
+    pub completed: u64,
+    /// Units begun but dropped.
+    pub lost: u64,
+}
+
+impl fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "quiescent in {:>8.3}ms  completed {:>8}  lost {:>6}",
+            self.quiescence.as_secs_f64() * 1e3,
+            self.completed,
+            self.lost
+        )
+    }
+}
+
+/// What every task can be signalled with.
+#[derive(Clone)]
+struct Stop {
+    token: CancellationToken,
+    closed: async_channel::Receiver<()>,
+    flag: Arc<AtomicBool>,
+}
+
+/// Works in units of `unit`, the first one `first` long, until `signal`
+/// says to stop, and returns the units completed and whether one was lost.
+async fn working<F, Fut>(
+    signal: Signal,
+    stop: Stop,
+    unit: Duration,
+    first: Duration,
+    sleep: F,
+) -> (u64, bool)
+where
+    F: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    use futures::future::{select, Either};
+
+    let mut completed = 0;
+    let mut length = first;
+    loop {
+        let work = Box::pin(sleep(length));
+        match signal {
+            Signal::Token => {
+                let cancelled = Box::pin(stop.token.cancelled());
+                if let Either::Right(_) = select(work, cancelled).await {
+                    return (completed, true);
+                }
+            }
+            Signal::ChannelClose => {
+                let closed = Box::pin(stop.closed.recv());
+                if let Either::Right(_) = select(work, closed).await {
+                    return (completed, true);
+                }
+            }
+            Signal::StopFlag => {
+                work.await;
+                if stop.flag.load(Ordering::Relaxed) {
+                    return (completed + 1, false);
+                }
+            }
+        }
+        completed += 1;
+        length = unit;
+    }
+}
+
+/// Signals the tasks through `signal`.
+fn signal(signal: Signal, stop: &Stop, close: &mut Option<async_channel::Sender<()>>) {
+    match signal {
+        Signal::Token => stop.token.cancel(),
+        Signal::ChannelClose => drop(close.take()),
+        Signal::StopFlag => stop.flag.store(true, Ordering::Relaxed),
+    }
+}
+
+/// Signals of every kind, none given yet, and the end of the channel whose
+/// drop closes it.
+fn stop() -> (Stop, Option<async_channel::Sender<()>>) {
+    let (close, closed) = async_channel::bounded(1);
+    let stop = Stop {
+        token: CancellationToken::new(),
+        closed,
+        flag: Arc::new(AtomicBool::new(false)),
+    };
+    (stop, Some(close))
+}
+
+/// The length of the first unit of the `i`-th task.
+fn first(work: Work, i: usize) -> Duration {
+    work.unit.mul_f64((i + 1) as f64 / work.tasks as f64)
+}
+
+impl ShutdownReport {
+    fn new(tasks: usize, quiescence: Duration, outcomes: Vec<(u64, bool)>) -> Self {
+        ShutdownReport {
+            tasks: tasks as u64,
+            quiescence,
+            completed: outcomes.iter().map(|&(completed, _)| completed).sum(),
+            lost: outcomes.iter().filter(|&&(_, lost)| lost).count() as u64,
+        }
+    }
+}
+
+/// Shuts down `work.tasks` tokio tasks with `how`.
+pub fn tokio(how: Signal, work: Work) -> ShutdownReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (stop, mut close) = stop();
+        let handles: Vec<_> = (0..work.tasks)
+            .map(|i| {
+                let run = working(
+                    how,
+                    stop.clone(),
+                    work.unit,
+                    first(work, i),
+                    tokio::time::sleep,
+                );
+                tokio::spawn(run)
+            })
+            .collect();
+        tokio::time::sleep(work.after).await;
+        let signalled = Instant::now();
+        signal(how, &stop, &mut close);
+        let mut outcomes = Vec::with_capacity(work.tasks);
+        for handle in handles {
+            outcomes.push(handle.await.unwrap());
+        }
+        ShutdownReport::new(work.tasks, signalled.elapsed(), outcomes)
+    })
+}
+
+/// Shuts down `work.tasks` async_std tasks with `how`.
+pub fn async_std(how: Signal, work: Work) -> ShutdownReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (stop, mut close) = stop();
+        let handles: Vec<_> = (0..work.tasks)
+            .map(|i| {
+                let run = working(how, stop.clone(), work.unit, first(work, i), task::sleep);
+                task::spawn(run)
+            })
+            .collect();
+        task::sleep(work.after).await;
+        let signalled = Instant::now();
+        signal(how, &stop, &mut close);
+        let mut outcomes = Vec::with_capacity(work.tasks);
+        for handle in handles {
+            outcomes.push(handle.await);
+        }
+        ShutdownReport::new(work.tasks, signalled.elapsed(), outcomes)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_racing_signals_lose_work() {
+        let work = Work {
+            tasks: 100,
+            unit: Duration::from_millis(5),
+            after: Duration::from_millis(12),
+        };
+        for run in [tokio, async_std] {
+            assert!(run(Signal::Token, work).lost > 0);
+            assert!(run(Signal::ChannelClose, work).lost > 0);
+            let report = run(Signal::StopFlag, work);
+            assert_eq!(report.lost, 0);
+            assert!(report.completed >= 200);
+        }
+    }
+}
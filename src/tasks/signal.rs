@@ -0,0 +1,104 @@
+//! The time from raising SIGUSR1 to the task handling it running, with
+//! tokio's `signal`, async-signal on smol and signal-hook's iterator on a
+//! thread of its own.
+//!
+//! A thread outside the runtime raises the signal and waits for the handler
+//! to answer before raising the next, so signals never coalesce. All three
+//! register through signal-hook-registry, whose handler only writes to a pipe
+//! or socket that the runtime's reactor, or the signal-hook thread, is
+//! waiting on; the latency is that of the wakeup that follows. Latencies are
+//! from the raise to the handler running.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use futures::StreamExt;
+use hdrhistogram::Histogram;
+use signal_hook::consts::SIGUSR1;
+use std::thread;
+use std::time::Instant;
+
+/// Raises SIGUSR1 `rounds` times, each time waiting for the handler to send
+/// the time it ran.
+fn raising(rounds: usize, handled: async_channel::Receiver<Instant>) -> Histogram<u64> {
+    let mut latencies = latency_histogram();
+    for _ in 0..rounds {
+        let raised = Instant::now();
+        signal_hook::low_level::raise(SIGUSR1).unwrap();
+        let ran = handled.recv_blocking().unwrap();
+        record(&mut latencies, ran - raised);
+    }
+    latencies
+}
+
+/// Handles SIGUSR1 `rounds` times with `tokio::signal`.
+pub fn tokio(rounds: usize) -> LoadReport {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let mut signal = signal(SignalKind::user_defined1()).unwrap();
+        let (handle, handled) = async_channel::unbounded();
+        let handler = tokio::spawn(async move {
+            while signal.recv().await.is_some() {
+                if handle.send(Instant::now()).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let start = Instant::now();
+        let latencies = tokio::task::spawn_blocking(move || raising(rounds, handled))
+            .await
+            .unwrap();
+        handler.abort();
+        LoadReport::since(start, rounds, latencies)
+    })
+}
+
+/// Handles SIGUSR1 `rounds` times with async-signal on smol.
+pub fn smol(rounds: usize) -> LoadReport {
+    use async_signal::{Signal, Signals};
+
+    let mut signals = Signals::new([Signal::Usr1]).unwrap();
+    let (handle, handled) = async_channel::unbounded();
+    let handler = smol::spawn(async move {
+        while signals.next().await.is_some() {
+            if handle.send(Instant::now()).await.is_err() {
+                break;
+            }
+        }
+    });
+    let start = Instant::now();
+    let latencies = smol::block_on(smol::unblock(move || raising(rounds, handled)));
+    drop(handler);
+    LoadReport::since(start, rounds, latencies)
+}
+
+/// Handles SIGUSR1 `rounds` times with signal-hook's iterator on a thread.
+pub fn signal_hook_thread(rounds: usize) -> LoadReport {
+    let mut signals = signal_hook::iterator::Signals::new([SIGUSR1]).unwrap();
+    let closing = signals.handle();
+    let (handle, handled) = async_channel::unbounded();
+    let handler = thread::spawn(move || {
+        for _ in signals.forever() {
+            if handle.send_blocking(Instant::now()).is_err() {
+                break;
+            }
+        }
+    });
+    let start = Instant::now();
+    let latencies = raising(rounds, handled);
+    closing.close();
+    handler.join().unwrap();
+    LoadReport::since(start, rounds, latencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_signal_is_handled() {
+        for run in [tokio, smol, signal_hook_thread] {
+            assert_eq!(run(50).latencies.len(), 50);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! A small workload running next to a growing number of idle tasks asleep on
+//! long timers, on tokio and async_std.
+//!
+//! The sleepers never wake during the run; they only sit in the runtime's
+//! timer and task lists. The measured workload is one task sending pings one
+//! at a time to an echo task over async-channel, and its latencies are the
+//! round trips. Comparing them across sleeper counts shows what idle tasks
+//! cost the active ones.
+//!
+//! Once the workload is done the channel the sleepers also wait on is closed
+//! and the run waits for all of them to end, so no sleeper outlives its
+//! scenario on async_std's global executor.
+
+use crate::harness::{latency_histogram, record, Alive, Guard, LoadReport};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// How long the sleepers sleep, far longer than any run.
+const LONG: Duration = Duration::from_secs(3600);
+
+/// Runs `rounds` ping round trips next to `sleepers` sleeping tokio tasks.
+pub fn on_tokio(sleepers: usize, rounds: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (stop, stopped) = async_channel::bounded(1);
+        let alive = Alive::default();
+        for _ in 0..sleepers {
+            let sleeper = sleep(tokio::time::sleep(LONG), stopped.clone(), alive.guard());
+            tokio::spawn(sleeper);
+        }
+        let (pings, echoes) = (async_channel::bounded(1), async_channel::bounded(1));
+        tokio::spawn(echo(pings.1, echoes.0));
+        let report = tokio::spawn(ping(pings.0, echoes.1, rounds)).await.unwrap();
+        drop(stop);
+        alive.gone(tokio::task::yield_now).await;
+        report
+    })
+}
+
+/// Runs `rounds` ping round trips next to `sleepers` sleeping async_std tasks.
+pub fn on_async_std(sleepers: usize, rounds: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let (stop, stopped) = async_channel::bounded(1);
+        let alive = Alive::default();
+        for _ in 0..sleepers {
+            task::spawn(sleep(task::sleep(LONG), stopped.clone(), alive.guard()));
+        }
+        let (pings, echoes) = (async_channel::bounded(1), async_channel::bounded(1));
+        task::spawn(echo(pings.1, echoes.0));
+        let report = task::spawn(ping(pings.0, echoes.1, rounds)).await;
+        drop(stop);
+        alive.gone(task::yield_now).await;
+        report
+    })
+}
+
+/// Sleeps through `sleep` unless `stopped` closes first.
+async fn sleep<S: Future<Output = ()>>(
+    sleep: S,
+    stopped: async_channel::Receiver<()>,
+    _alive: Guard,
+) {
+    futures::pin_mut!(sleep);
+    let stopped = stopped.recv();
+    futures::pin_mut!(stopped);
+    futures::future::select(sleep, stopped).await;
+}
+
+/// Sends every ping back until the pings stop.
+async fn echo(pings: async_channel::Receiver<Instant>, echoes: async_channel::Sender<Instant>) {
+    while let Ok(sent) = pings.recv().await {
+        echoes.send(sent).await.unwrap();
+    }
+}
+
+/// Times `rounds` round trips of a ping through the echo task.
+async fn ping(
+    pings: async_channel::Sender<Instant>,
+    echoes: async_channel::Receiver<Instant>,
+    rounds: usize,
+) -> LoadReport {
+    let start = Instant::now();
+    let mut latencies = latency_histogram();
+    for _ in 0..rounds {
+        pings.send(Instant::now()).await.unwrap();
+        record(&mut latencies, echoes.recv().await.unwrap().elapsed());
+    }
+    LoadReport::since(start, rounds, latencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pings_come_back_and_sleepers_stop() {
+        for run in [on_tokio, on_async_std] {
+            assert_eq!(run(1_000, 100).latencies.len(), 100);
+        }
+    }
+}
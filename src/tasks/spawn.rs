@@ -0,0 +1,139 @@
+//! Spawning trivial tasks, either awaiting each one before spawning the next
+//! or spawning them all without keeping their handles, on tokio, async_std and
+//! smol, against spawning threads.
+//!
+//! Awaited spawns measure a spawn, one scheduling of the task and the wakeup
+//! of the spawner; detached ones are timed until the last task has run, which
+//! they find out by counting, and show how fast a runtime takes in new tasks.
+//! smol runs on its global executor, which has one thread unless
+//! `SMOL_THREADS` says otherwise.
+
+use super::tuning::Tuning;
+use super::{CostReport, Countdown};
+use std::time::Instant;
+
+/// Whether the spawner waits for every task.
+#[derive(Clone, Copy, Debug)]
+pub enum Mode {
+    Awaited,
+    Detached,
+}
+
+/// Spawns `tasks` trivial tokio tasks from a tokio task.
+pub fn tokio(mode: Mode, tasks: usize) -> CostReport {
+    tokio_tuned(mode, tasks, Tuning::default())
+}
+
+/// Spawns `tasks` trivial tokio tasks from a tokio task, on a runtime with
+/// the knobs of `tuning` set.
+pub fn tokio_tuned(mode: Mode, tasks: usize, tuning: Tuning) -> CostReport {
+    let rt = tuning.runtime();
+    rt.block_on(async {
+        tokio::spawn(async move {
+            let start = Instant::now();
+            match mode {
+                Mode::Awaited => {
+                    for i in 0..tasks {
+                        assert_eq!(tokio::spawn(async move { i }).await.unwrap(), i);
+                    }
+                }
+                Mode::Detached => {
+                    let (countdown, all_done) = Countdown::new(tasks);
+                    for _ in 0..tasks {
+                        let countdown = countdown.clone();
+                        tokio::spawn(async move { countdown.count() });
+                    }
+                    all_done.recv().await.unwrap();
+                }
+            }
+            CostReport::new(tasks, start.elapsed())
+        })
+        .await
+        .unwrap()
+    })
+}
+
+/// Spawns `tasks` trivial async_std tasks from an async_std task.
+pub fn async_std(mode: Mode, tasks: usize) -> CostReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(async move {
+        let start = Instant::now();
+        match mode {
+            Mode::Awaited => {
+                for i in 0..tasks {
+                    assert_eq!(task::spawn(async move { i }).await, i);
+                }
+            }
+            Mode::Detached => {
+                let (countdown, all_done) = Countdown::new(tasks);
+                for _ in 0..tasks {
+                    let countdown = countdown.clone();
+                    task::spawn(async move { countdown.count() });
+                }
+                all_done.recv().await.unwrap();
+            }
+        }
+        CostReport::new(tasks, start.elapsed())
+    }))
+}
+
+/// Spawns `tasks` trivial smol tasks from a smol task.
+pub fn smol(mode: Mode, tasks: usize) -> CostReport {
+    smol::block_on(smol::spawn(async move {
+        let start = Instant::now();
+        match mode {
+            Mode::Awaited => {
+                for i in 0..tasks {
+                    assert_eq!(smol::spawn(async move { i }).await, i);
+                }
+            }
+            Mode::Detached => {
+                let (countdown, all_done) = Countdown::new(tasks);
+                for _ in 0..tasks {
+                    let countdown = countdown.clone();
+                    smol::spawn(async move { countdown.count() }).detach();
+                }
+                all_done.recv().await.unwrap();
+            }
+        }
+        CostReport::new(tasks, start.elapsed())
+    }))
+}
+
+/// Spawns `tasks` threads that do nothing.
+pub fn threads(mode: Mode, tasks: usize) -> CostReport {
+    use std::thread;
+
+    let start = Instant::now();
+    match mode {
+        Mode::Awaited => {
+            for i in 0..tasks {
+                assert_eq!(thread::spawn(move || i).join().unwrap(), i);
+            }
+        }
+        Mode::Detached => {
+            let (countdown, all_done) = Countdown::new(tasks);
+            for _ in 0..tasks {
+                let countdown = countdown.clone();
+                thread::spawn(move || countdown.count());
+            }
+            all_done.recv_blocking().unwrap();
+        }
+    }
+    CostReport::new(tasks, start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_task_runs() {
+        for spawn in [tokio, async_std, smol, threads] {
+            for mode in [Mode::Awaited, Mode::Detached] {
+                assert_eq!(spawn(mode, 100).operations, 100);
+            }
+        }
+    }
+}
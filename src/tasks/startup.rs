@@ -0,0 +1,100 @@
+//! What it costs to start a runtime and run a first task on it: building a
+//! tokio runtime of either flavor and blocking on it, and the first
+//! `block_on` of a spawned task on async_std and smol, whose global runtimes
+//! start lazily on first use.
+//!
+//! The first start in the process also pays for whatever the runtime sets up
+//! once, so it is reported apart from the mean of the starts after it. tokio
+//! builds a new runtime every round; async_std and smol only start their
+//! global runtimes in the first round, so later rounds only cost a
+//! `block_on`. Their first round is only cold if nothing ran on them before
+//! in the process. The time to drop a runtime is not counted.
+
+use super::drivers::Flavor;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tokio::runtime::Builder;
+
+/// How long the first start took, and the ones after it on average.
+pub struct StartupReport {
+    pub first: Duration,
+    pub rest: Duration,
+    pub rounds: u64,
+}
+
+impl StartupReport {
+    /// Times `rounds` calls of `start`, which returns what it started so
+    /// that dropping it happens outside the timing.
+    fn measure<T>(rounds: usize, mut start: impl FnMut() -> T) -> Self {
+        let mut times = (0..rounds).map(|_| {
+            let started = Instant::now();
+            let started_runtime = start();
+            let elapsed = started.elapsed();
+            drop(started_runtime);
+            elapsed
+        });
+        let first = times.next().unwrap();
+        let rest: Duration = times.sum();
+        StartupReport {
+            first,
+            rest,
+            rounds: rounds as u64,
+        }
+    }
+}
+
+impl fmt::Display for StartupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let later = self.rest.as_secs_f64() / (self.rounds - 1).max(1) as f64;
+        write!(
+            f,
+            "first {:>9.1}us  then {:>9.1}us",
+            self.first.as_secs_f64() * 1e6,
+            later * 1e6
+        )
+    }
+}
+
+/// Builds a tokio runtime of `flavor` with every driver and blocks on an
+/// empty future, `rounds` times.
+pub fn tokio(flavor: Flavor, rounds: usize) -> StartupReport {
+    StartupReport::measure(rounds, || {
+        let mut builder = match flavor {
+            Flavor::CurrentThread => Builder::new_current_thread(),
+            Flavor::MultiThread => Builder::new_multi_thread(),
+        };
+        let rt = builder.enable_all().build().unwrap();
+        rt.block_on(async {});
+        rt
+    })
+}
+
+/// Blocks on a spawned async_std task, `rounds` times.
+pub fn async_std(rounds: usize) -> StartupReport {
+    use async_std::task;
+
+    StartupReport::measure(rounds, || task::block_on(task::spawn(async {})))
+}
+
+/// Blocks on a task spawned on smol's global executor, `rounds` times.
+pub fn smol(rounds: usize) -> StartupReport {
+    StartupReport::measure(rounds, || smol::block_on(smol::spawn(async {})))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_round_starts() {
+        for report in [
+            tokio(Flavor::CurrentThread, 3),
+            tokio(Flavor::MultiThread, 3),
+            async_std(3),
+            smol(3),
+        ] {
+            assert_eq!(report.rounds, 3);
+            assert!(report.first > Duration::ZERO);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Bursts of busy tasks all spawned from one task, which the runtime has to
+//! spread over its workers, on tokio, async_std and smol.
+//!
+//! Every burst spawns its tasks at once from the same task, so they all start
+//! out wherever the spawner runs, and the next burst is only spawned once all
+//! of the previous one has run. Every task spins for a fixed time. How soon
+//! the idle workers take their share decides how long a burst takes: with
+//! perfect balance it takes the tasks' total work divided by the number of
+//! workers.
+//!
+//! Every runtime has one worker per core: tokio and async_std by default,
+//! smol on an executor run by that many threads, as its global executor has a
+//! single thread by default. The utilization of a worker is the time it spent
+//! running the tasks over the whole run; workers that never ran one count as
+//! idle.
+
+use super::tuning::Tuning;
+use super::Countdown;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+/// The bursts of work.
+#[derive(Clone, Copy, Debug)]
+pub struct Bursts {
+    pub bursts: usize,
+    /// Tasks spawned per burst.
+    pub tasks: usize,
+    /// Time every task spins for.
+    pub work: Duration,
+}
+
+/// How long the bursts took and how busy every worker was.
+pub struct StealReport {
+    pub bursts: u64,
+    pub elapsed: Duration,
+    /// Time each worker spent running tasks, busiest first.
+    pub busy: Vec<Duration>,
+    /// Time a burst would take with the work spread evenly.
+    pub balanced: Duration,
+}
+
+impl fmt::Display for StealReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let burst = self.elapsed / self.bursts as u32;
+        write!(
+            f,
+            "{:>8.2}ms/burst  balanced {:>8.2}ms  utilization",
+            burst.as_secs_f64() * 1e3,
+            self.balanced.as_secs_f64() * 1e3
+        )?;
+        for busy in &self.busy {
+            write!(
+                f,
+                " {:>3.0}%",
+                busy.as_secs_f64() * 1e2 / self.elapsed.as_secs_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Time spent running tasks, by thread.
+#[derive(Clone, Default)]
+struct Busy(Arc<Mutex<HashMap<ThreadId, Duration>>>);
+
+impl Busy {
+    /// Spins for `work` and counts it for the current thread.
+    fn spin(&self, work: Duration) {
+        let start = Instant::now();
+        while start.elapsed() < work {
+            std::hint::spin_loop();
+        }
+        let mut busy = self.0.lock().unwrap();
+        *busy.entry(thread::current().id()).or_default() += start.elapsed();
+    }
+
+    fn report(&self, bursts: Bursts, elapsed: Duration) -> StealReport {
+        let workers = workers();
+        let mut busy: Vec<_> = self.0.lock().unwrap().values().copied().collect();
+        busy.resize(busy.len().max(workers), Duration::ZERO);
+        busy.sort_by(|a, b| b.cmp(a));
+        StealReport {
+            bursts: bursts.bursts as u64,
+            elapsed,
+            busy,
+            balanced: bursts.work * bursts.tasks as u32 / workers as u32,
+        }
+    }
+}
+
+/// Number of worker threads every runtime gets.
+fn workers() -> usize {
+    thread::available_parallelism().map_or(1, |cores| cores.get())
+}
+
+/// Spawns every burst with `spawn` and waits for it to run.
+async fn spawn_bursts<F>(bursts: Bursts, busy: Busy, mut spawn: F) -> StealReport
+where
+    F: FnMut(Box<dyn FnOnce() + Send>),
+{
+    let start = Instant::now();
+    for _ in 0..bursts.bursts {
+        let (countdown, all_done) = Countdown::new(bursts.tasks);
+        for _ in 0..bursts.tasks {
+            let (countdown, busy) = (countdown.clone(), busy.clone());
+            spawn(Box::new(move || {
+                busy.spin(bursts.work);
+                countdown.count();
+            }));
+        }
+        all_done.recv().await.unwrap();
+    }
+    busy.report(bursts, start.elapsed())
+}
+
+/// Runs `bursts` on tokio.
+pub fn tokio(bursts: Bursts) -> StealReport {
+    tokio_tuned(bursts, Tuning::default())
+}
+
+/// Runs `bursts` on tokio with the knobs of `tuning` set.
+pub fn tokio_tuned(bursts: Bursts, tuning: Tuning) -> StealReport {
+    let rt = tuning.runtime();
+    rt.block_on(async {
+        let run = spawn_bursts(bursts, Busy::default(), |task| {
+            tokio::spawn(async move { task() });
+        });
+        tokio::spawn(run).await.unwrap()
+    })
+}
+
+/// Runs `bursts` on async_std.
+pub fn async_std(bursts: Bursts) -> StealReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(spawn_bursts(bursts, Busy::default(), |task| {
+        task::spawn(async move { task() });
+    })))
+}
+
+/// Runs `bursts` on a smol executor with one thread per core.
+pub fn smol(bursts: Bursts) -> StealReport {
+    let executor = Arc::new(smol::Executor::new());
+    let (stop, stopped) = async_channel::bounded::<()>(1);
+    let threads: Vec<_> = (0..workers())
+        .map(|_| {
+            let (executor, stopped) = (executor.clone(), stopped.clone());
+            thread::spawn(move || smol::block_on(executor.run(stopped.recv())))
+        })
+        .collect();
+    let spawner = executor.clone();
+    let run = spawn_bursts(bursts, Busy::default(), move |task| {
+        spawner.spawn(async move { task() }).detach();
+    });
+    let report = smol::block_on(executor.spawn(run));
+    drop(stop);
+    for thread in threads {
+        let _ = thread.join().unwrap();
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_burst_runs() {
+        let bursts = Bursts {
+            bursts: 3,
+            tasks: 50,
+            work: Duration::from_micros(10),
+        };
+        for run in [tokio, async_std, smol] {
+            let report = run(bursts);
+            assert!(report.busy.len() >= workers());
+            let busy: Duration = report.busy.iter().sum();
+            assert!(busy >= Duration::from_micros(10 * 150));
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Threads outside the runtime submitting tasks to it, the way code that is
+//! not async hands work over: through a `tokio::runtime::Handle` on tokio and
+//! with the plain `spawn` on async_std, whose global executor takes tasks from
+//! any thread.
+//!
+//! Each of `threads` threads submits its share of the tasks as fast as it
+//! can, and every task reports how long after its submission it started
+//! running. Throughput counts until the last task has reported.
+
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Submits `tasks` tasks from `threads` threads through a tokio `Handle`.
+pub fn tokio(threads: usize, tasks: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    let (started, reports) = async_channel::unbounded();
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for share in split(tasks, threads) {
+            let (handle, started) = (rt.handle().clone(), started.clone());
+            scope.spawn(move || {
+                for _ in 0..share {
+                    handle.spawn(report_start(Instant::now(), started.clone()));
+                }
+            });
+        }
+    });
+    collect(start, tasks, reports)
+}
+
+/// Submits `tasks` tasks from `threads` threads with async_std's `spawn`.
+pub fn async_std(threads: usize, tasks: usize) -> LoadReport {
+    let (started, reports) = async_channel::unbounded();
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for share in split(tasks, threads) {
+            let started = started.clone();
+            scope.spawn(move || {
+                for _ in 0..share {
+                    async_std::task::spawn(report_start(Instant::now(), started.clone()));
+                }
+            });
+        }
+    });
+    collect(start, tasks, reports)
+}
+
+async fn report_start(submitted: Instant, started: async_channel::Sender<Duration>) {
+    started.try_send(submitted.elapsed()).unwrap();
+}
+
+fn collect(start: Instant, tasks: usize, reports: async_channel::Receiver<Duration>) -> LoadReport {
+    let mut latencies = latency_histogram();
+    for _ in 0..tasks {
+        record(&mut latencies, reports.recv_blocking().unwrap());
+    }
+    LoadReport::since(start, tasks, latencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_submitted_task_runs() {
+        for submit in [tokio, async_std] {
+            assert_eq!(submit(3, 1_000).latencies.len(), 1_000);
+        }
+    }
+}
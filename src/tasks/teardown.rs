@@ -0,0 +1,153 @@
+//! Dropping a runtime with work still outstanding: nothing, thousands of
+//! tasks that never finish, or blocking calls still running, on tokio and on
+//! a smol executor.
+//!
+//! tokio drops the tasks it still holds when the runtime is dropped, then
+//! waits for every blocking call to return; `shutdown_timeout` stops waiting
+//! for them after a deadline and leaves them running. A smol executor drops
+//! its tasks the same way, but its blocking calls run on the process-wide
+//! pool of the blocking crate, which it does not wait for. async_std only has
+//! its global runtime, which lives as long as the process, so it has nothing
+//! to drop.
+//!
+//! Each round builds a runtime, starts the work and drops the runtime once
+//! the work is under way; only the drop is timed.
+
+use std::fmt;
+use std::future;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The work left when the runtime is dropped.
+#[derive(Clone, Copy, Debug)]
+pub enum Outstanding {
+    Idle,
+    /// Tasks waiting on a future that never completes.
+    Pending(usize),
+    /// Blocking calls that each sleep for `each`.
+    Blocking {
+        calls: usize,
+        each: Duration,
+    },
+}
+
+/// How a tokio runtime is torn down.
+#[derive(Clone, Copy, Debug)]
+pub enum Teardown {
+    Drop,
+    ShutdownTimeout(Duration),
+}
+
+/// How long the drops took.
+pub struct TeardownReport {
+    pub rounds: u64,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for TeardownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.elapsed.as_secs_f64() / self.rounds as f64;
+        write!(f, "{:>10.3}ms/drop", seconds * 1e3)
+    }
+}
+
+/// Time for the spawned work to get under way before the drop.
+const SETTLE: Duration = Duration::from_millis(10);
+
+/// Tears down a tokio runtime with `outstanding` work `rounds` times.
+pub fn tokio(outstanding: Outstanding, teardown: Teardown, rounds: usize) -> TeardownReport {
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..rounds {
+        let rt = crate::metrics::runtime();
+        match outstanding {
+            Outstanding::Idle => {}
+            Outstanding::Pending(tasks) => {
+                for _ in 0..tasks {
+                    rt.spawn(future::pending::<()>());
+                }
+            }
+            Outstanding::Blocking { calls, each } => {
+                for _ in 0..calls {
+                    rt.spawn_blocking(move || thread::sleep(each));
+                }
+            }
+        }
+        thread::sleep(SETTLE);
+        let start = Instant::now();
+        match teardown {
+            Teardown::Drop => drop(rt),
+            Teardown::ShutdownTimeout(timeout) => rt.shutdown_timeout(timeout),
+        }
+        elapsed += start.elapsed();
+    }
+    TeardownReport {
+        rounds: rounds as u64,
+        elapsed,
+    }
+}
+
+/// Tears down a smol executor run by one thread per core, with
+/// `outstanding` work, `rounds` times.
+pub fn smol(outstanding: Outstanding, rounds: usize) -> TeardownReport {
+    let workers = thread::available_parallelism().map_or(1, |cores| cores.get());
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..rounds {
+        let executor = Arc::new(smol::Executor::new());
+        let (stop, stopped) = async_channel::bounded::<()>(1);
+        let threads: Vec<_> = (0..workers)
+            .map(|_| {
+                let (executor, stopped) = (executor.clone(), stopped.clone());
+                thread::spawn(move || smol::block_on(executor.run(stopped.recv())))
+            })
+            .collect();
+        match outstanding {
+            Outstanding::Idle => {}
+            Outstanding::Pending(tasks) => {
+                for _ in 0..tasks {
+                    executor.spawn(future::pending::<()>()).detach();
+                }
+            }
+            Outstanding::Blocking { calls, each } => {
+                for _ in 0..calls {
+                    executor
+                        .spawn(smol::unblock(move || thread::sleep(each)))
+                        .detach();
+                }
+            }
+        }
+        thread::sleep(SETTLE);
+        let start = Instant::now();
+        drop(stop);
+        for thread in threads {
+            let _ = thread.join().unwrap();
+        }
+        drop(executor);
+        elapsed += start.elapsed();
+    }
+    TeardownReport {
+        rounds: rounds as u64,
+        elapsed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokio_waits_for_blocking_calls_unless_timed_out() {
+        let blocking = Outstanding::Blocking {
+            calls: 2,
+            each: Duration::from_millis(200),
+        };
+        assert!(tokio(blocking, Teardown::Drop, 1).elapsed >= Duration::from_millis(100));
+        let timeout = Teardown::ShutdownTimeout(Duration::from_millis(1));
+        assert!(tokio(blocking, timeout, 1).elapsed < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn smol_drops_pending_tasks() {
+        assert_eq!(smol(Outstanding::Pending(100), 2).rounds, 2);
+    }
+}
@@ -0,0 +1,86 @@
+//! Settings of the knobs of tokio's scheduler, for runs of the task-heavy
+//! scenarios on tuned runtimes next to runs on the default one.
+//!
+//! `event_interval` is the number of tasks a worker polls between polls of
+//! the IO and timer drivers, 61 by default. `global_queue_interval` is the
+//! number of tasks it polls between checks of the queue shared by all
+//! workers, which tokio otherwise adapts to how long tasks take. The LIFO slot
+//! makes a task woken by the running one run next on the same worker; it can
+//! only be disabled with `--cfg tokio_unstable`, and is left alone otherwise.
+
+use crate::metrics;
+use std::fmt;
+use tokio::runtime::{Builder, Runtime};
+
+/// Knobs to set on a multi-threaded tokio runtime, each left at tokio's
+/// default when `None` or `false`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tuning {
+    pub event_interval: Option<u32>,
+    pub global_queue_interval: Option<u32>,
+    pub disable_lifo_slot: bool,
+}
+
+impl Tuning {
+    /// Whether the LIFO slot can be disabled in this build.
+    pub const LIFO_SLOT_KNOB: bool = cfg!(tokio_unstable);
+
+    /// Builds a multi-threaded runtime with every driver and these knobs,
+    /// pinned and registered for sampling like [`metrics::runtime`].
+    pub fn runtime(&self) -> Runtime {
+        let mut builder = Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(interval) = self.event_interval {
+            builder.event_interval(interval);
+        }
+        if let Some(interval) = self.global_queue_interval {
+            builder.global_queue_interval(interval);
+        }
+        #[cfg(tokio_unstable)]
+        if self.disable_lifo_slot {
+            builder.disable_lifo_slot();
+        }
+        metrics::build(&mut builder)
+    }
+}
+
+/// Names the knobs that are set, or `default` if none is.
+impl fmt::Display for Tuning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut knobs = Vec::new();
+        if let Some(interval) = self.event_interval {
+            knobs.push(format!("event_interval={}", interval));
+        }
+        if let Some(interval) = self.global_queue_interval {
+            knobs.push(format!("global_queue_interval={}", interval));
+        }
+        if self.disable_lifo_slot {
+            knobs.push("no_lifo_slot".to_owned());
+        }
+        if knobs.is_empty() {
+            return write!(f, "default");
+        }
+        write!(f, "{}", knobs.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_the_knobs_set() {
+        assert_eq!(Tuning::default().to_string(), "default");
+        let tuning = Tuning {
+            event_interval: Some(7),
+            global_queue_interval: Some(31),
+            ..Tuning::default()
+        };
+        assert_eq!(
+            tuning.to_string(),
+            "event_interval=7,global_queue_interval=31"
+        );
+        let rt = tuning.runtime();
+        assert_eq!(rt.block_on(tokio::spawn(async { 1 })).unwrap(), 1);
+    }
+}
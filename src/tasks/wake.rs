@@ -0,0 +1,151 @@
+//! The time from a thread outside the runtime waking a parked task to the task
+//! being polled, with the executor idle and with it busy.
+//!
+//! The task is woken either through its `Waker` directly, which a thread
+//! waits for on a `Condvar` until the task has parked, or by a message the thread sends over an
+//! async-channel with `send_blocking`, after which it waits for the task to
+//! answer. In the busy runs `busy` other tasks keep spinning for 20µs at a
+//! time and yielding in between, so the woken task has to wait for a worker
+//! to come free. Latencies are from the wakeup to the poll.
+
+use crate::harness::{latency_histogram, record, LoadReport};
+use hdrhistogram::Histogram;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time a busy task spins between yields.
+const SPIN: Duration = Duration::from_micros(20);
+
+/// How the thread wakes the task.
+#[derive(Clone, Copy, Debug)]
+pub enum Wake {
+    Waker,
+    Channel,
+}
+
+/// Wakes a task `rounds` times, with `busy` other tasks running, on tokio.
+pub fn on_tokio(wake: Wake, busy: usize, rounds: usize) -> LoadReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        for _ in 0..busy {
+            tokio::spawn(spinning(stop.clone(), tokio::task::yield_now));
+        }
+        let start = Instant::now();
+        let latencies = tokio::spawn(woken(wake, rounds)).await.unwrap();
+        stop.store(true, Ordering::Relaxed);
+        LoadReport::since(start, rounds, latencies)
+    })
+}
+
+/// Wakes a task `rounds` times, with `busy` other tasks running, on async_std.
+pub fn on_async_std(wake: Wake, busy: usize, rounds: usize) -> LoadReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        for _ in 0..busy {
+            task::spawn(spinning(stop.clone(), task::yield_now));
+        }
+        let start = Instant::now();
+        let latencies = task::spawn(woken(wake, rounds)).await;
+        stop.store(true, Ordering::Relaxed);
+        LoadReport::since(start, rounds, latencies)
+    })
+}
+
+async fn spinning<F, Fut>(stop: Arc<AtomicBool>, yield_now: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    while !stop.load(Ordering::Relaxed) {
+        let spun = Instant::now();
+        while spun.elapsed() < SPIN {}
+        yield_now().await;
+    }
+}
+
+/// A parked task's waker, or the time it was woken.
+#[derive(Default)]
+struct Parked {
+    waker: Option<Waker>,
+    woken: Option<Instant>,
+}
+
+/// Parks `rounds` times and has a thread wake the task every time.
+async fn woken(wake: Wake, rounds: usize) -> Histogram<u64> {
+    let mut latencies = latency_histogram();
+    match wake {
+        Wake::Waker => {
+            let parked = Arc::new((Mutex::new(Parked::default()), Condvar::new()));
+            let waking = parked.clone();
+            let waker = thread::spawn(move || {
+                let (state, registered) = &*waking;
+                for _ in 0..rounds {
+                    let state = state.lock().unwrap();
+                    let mut state = registered
+                        .wait_while(state, |state| state.waker.is_none())
+                        .unwrap();
+                    let waker = state.waker.take().unwrap();
+                    state.woken = Some(Instant::now());
+                    drop(state);
+                    waker.wake();
+                }
+            });
+            for _ in 0..rounds {
+                let woken = std::future::poll_fn(|cx| {
+                    let (state, registered) = &*parked;
+                    let mut state = state.lock().unwrap();
+                    match state.woken.take() {
+                        Some(woken) => Poll::Ready(woken),
+                        None => {
+                            state.waker = Some(cx.waker().clone());
+                            registered.notify_one();
+                            Poll::Pending
+                        }
+                    }
+                })
+                .await;
+                record(&mut latencies, woken.elapsed());
+            }
+            waker.join().unwrap();
+        }
+        Wake::Channel => {
+            let (wakeups, parked) = async_channel::bounded(1);
+            let (answers, answered) = async_channel::bounded(1);
+            let waker = thread::spawn(move || {
+                for _ in 0..rounds {
+                    wakeups.send_blocking(Instant::now()).unwrap();
+                    answered.recv_blocking().unwrap();
+                }
+            });
+            for _ in 0..rounds {
+                let woken = parked.recv().await.unwrap();
+                record(&mut latencies, woken.elapsed());
+                answers.send(()).await.unwrap();
+            }
+            waker.join().unwrap();
+        }
+    }
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_wakeup_reaches_the_task() {
+        for wake in [Wake::Waker, Wake::Channel] {
+            for busy in [0, 4] {
+                assert_eq!(on_tokio(wake, busy, 200).latencies.len(), 200);
+                assert_eq!(on_async_std(wake, busy, 200).latencies.len(), 200);
+            }
+        }
+    }
+}
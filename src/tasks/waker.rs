@@ -0,0 +1,156 @@
+//! Cloning and waking the `Waker` of a task, from inside the task and from a
+//! thread outside the runtime, on tokio, async_std and smol.
+//!
+//! Inside, the task clones and drops its waker in a single poll, or wakes
+//! itself once per poll, by reference or by a clone that the wake consumes,
+//! so each wake also costs the runtime rescheduling the task and polling it
+//! again. Outside, the task hands a clone of its waker to a thread and parks
+//! until the thread is done; the thread clones and drops it or wakes the task
+//! over and over, and only the thread's calls are timed. A wake of a task
+//! that is already scheduled should be close to free, so most of those wakes
+//! only cost finding that out, and the others the runtime's queue and the
+//! unparking of a worker. Every waker is a reference count on the task in
+//! all three runtimes, which is what a clone costs.
+
+use super::CostReport;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::task::{Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What is done with the waker.
+#[derive(Clone, Copy, Debug)]
+pub enum Op {
+    /// Cloned and dropped.
+    Clone,
+    /// Woken through `wake_by_ref`.
+    WakeByRef,
+    /// Cloned and woken through `wake`, which consumes the clone.
+    Wake,
+}
+
+/// Where the waker is used from.
+#[derive(Clone, Copy, Debug)]
+pub enum Side {
+    /// The task that the waker wakes.
+    Inside,
+    /// A thread outside the runtime.
+    Outside,
+}
+
+impl Op {
+    fn apply(self, waker: &Waker) {
+        match self {
+            Op::Clone => drop(black_box(waker.clone())),
+            Op::WakeByRef => waker.wake_by_ref(),
+            Op::Wake => black_box(waker.clone()).wake(),
+        }
+    }
+}
+
+/// Does `op` `ops` times from inside the task and returns how long it took.
+async fn inside(op: Op, ops: usize) -> Duration {
+    let start = Instant::now();
+    match op {
+        Op::Clone => {
+            std::future::poll_fn(|cx| {
+                for _ in 0..ops {
+                    op.apply(cx.waker());
+                }
+                Poll::Ready(())
+            })
+            .await
+        }
+        Op::WakeByRef | Op::Wake => {
+            let mut woken = 0;
+            std::future::poll_fn(|cx| {
+                if woken == ops {
+                    return Poll::Ready(());
+                }
+                woken += 1;
+                op.apply(cx.waker());
+                Poll::Pending
+            })
+            .await
+        }
+    }
+    start.elapsed()
+}
+
+/// Has a thread do `op` `ops` times on the task's waker and returns how long
+/// the thread took.
+async fn outside(op: Op, ops: usize) -> Duration {
+    let done = Arc::new(AtomicBool::new(false));
+    let (handing, handed) = mpsc::channel::<Waker>();
+    let thread = {
+        let done = done.clone();
+        thread::spawn(move || {
+            let waker = handed.recv().unwrap();
+            let start = Instant::now();
+            for _ in 0..ops {
+                op.apply(&waker);
+            }
+            let elapsed = start.elapsed();
+            done.store(true, Ordering::Release);
+            waker.wake();
+            elapsed
+        })
+    };
+    let mut handing = Some(handing);
+    std::future::poll_fn(|cx| {
+        if let Some(handing) = handing.take() {
+            handing.send(cx.waker().clone()).unwrap();
+        }
+        if done.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+    thread.join().unwrap()
+}
+
+async fn using(op: Op, side: Side, ops: usize) -> CostReport {
+    let elapsed = match side {
+        Side::Inside => inside(op, ops).await,
+        Side::Outside => outside(op, ops).await,
+    };
+    CostReport::new(ops, elapsed)
+}
+
+/// Uses the waker of a tokio task `ops` times.
+pub fn tokio(op: Op, side: Side, ops: usize) -> CostReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async { tokio::spawn(using(op, side, ops)).await.unwrap() })
+}
+
+/// Uses the waker of an async_std task `ops` times.
+pub fn async_std(op: Op, side: Side, ops: usize) -> CostReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(using(op, side, ops)))
+}
+
+/// Uses the waker of a smol task `ops` times.
+pub fn smol(op: Op, side: Side, ops: usize) -> CostReport {
+    smol::block_on(smol::spawn(using(op, side, ops)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_use_of_the_waker_returns() {
+        for run in [tokio, async_std, smol] {
+            for op in [Op::Clone, Op::WakeByRef, Op::Wake] {
+                for side in [Side::Inside, Side::Outside] {
+                    assert_eq!(run(op, side, 1_000).operations, 1_000);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! The cost of a task yielding to the scheduler, alone and with other tasks
+//! ready to run.
+//!
+//! The task yields with its runtime's `yield_now` or by waking itself and
+//! returning pending once, which is all that futures offers. tokio's
+//! `yield_now` defers the wakeup until the worker has checked its IO and
+//! timer drivers, which costs a poll of the driver on every yield even when
+//! the task is alone, where a task that wakes itself is simply queued again.
+//! The other tasks yield the same way in a loop until the measured one is
+//! done, so every yield can cost a turn of each.
+
+use super::CostReport;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Instant;
+
+/// How the tasks yield.
+#[derive(Clone, Copy, Debug)]
+pub enum Yield {
+    /// The runtime's `yield_now`.
+    Runtime,
+    /// Waking itself and returning pending once.
+    PendingOnce,
+}
+
+/// Yields `yields` times from a tokio task, with `others` yielding too.
+pub fn on_tokio(how: Yield, others: usize, yields: usize) -> CostReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        for _ in 0..others {
+            match how {
+                Yield::Runtime => tokio::spawn(looping(stop.clone(), tokio::task::yield_now)),
+                Yield::PendingOnce => tokio::spawn(looping(stop.clone(), pending_once)),
+            };
+        }
+        let report = match how {
+            Yield::Runtime => tokio::spawn(timed(yields, tokio::task::yield_now)).await,
+            Yield::PendingOnce => tokio::spawn(timed(yields, pending_once)).await,
+        };
+        stop.store(true, Ordering::Relaxed);
+        report.unwrap()
+    })
+}
+
+/// Yields `yields` times from an async_std task, with `others` yielding too.
+pub fn on_async_std(how: Yield, others: usize, yields: usize) -> CostReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        for _ in 0..others {
+            match how {
+                Yield::Runtime => task::spawn(looping(stop.clone(), task::yield_now)),
+                Yield::PendingOnce => task::spawn(looping(stop.clone(), pending_once)),
+            };
+        }
+        let report = match how {
+            Yield::Runtime => task::spawn(timed(yields, task::yield_now)).await,
+            Yield::PendingOnce => task::spawn(timed(yields, pending_once)).await,
+        };
+        stop.store(true, Ordering::Relaxed);
+        report
+    })
+}
+
+pub(crate) async fn timed<F, Fut>(yields: usize, yield_now: F) -> CostReport
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let start = Instant::now();
+    for _ in 0..yields {
+        yield_now().await;
+    }
+    CostReport::new(yields, start.elapsed())
+}
+
+async fn looping<F, Fut>(stop: Arc<AtomicBool>, yield_now: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    while !stop.load(Ordering::Relaxed) {
+        yield_now().await;
+    }
+}
+
+/// Wakes the task and returns pending once.
+async fn pending_once() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_yield_returns() {
+        for how in [Yield::Runtime, Yield::PendingOnce] {
+            for others in [0, 4] {
+                assert_eq!(on_tokio(how, others, 1_000).operations, 1_000);
+                assert_eq!(on_async_std(how, others, 1_000).operations, 1_000);
+            }
+        }
+    }
+}
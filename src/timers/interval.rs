@@ -0,0 +1,123 @@
+//! A fast interval ticking next to busy tasks, on tokio, async_std and smol.
+//!
+//! One task waits for the ticks of the runtime's interval stream:
+//! `tokio::time::interval`, `async_std::stream::interval` and
+//! `smol::Timer::interval`. Next to it busy tasks spin for 20 µs at a time
+//! and yield, keeping every worker occupied. Jitter is how far the time
+//! between two ticks was from the period, either way, which is what a
+//! heartbeat or a scheduler built on the interval sees.
+
+use super::busy;
+use crate::harness::{latency_histogram, record, Millis};
+use futures::{Stream, StreamExt};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long every busy task spins between yields.
+const SPIN: Duration = Duration::from_micros(20);
+
+/// How far the gaps between ticks were from the period.
+pub struct JitterReport {
+    pub period: Duration,
+    pub jitter: Histogram<u64>,
+}
+
+impl fmt::Display for JitterReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>8} ticks  jitter p50 {}  p99 {}  max {}",
+            self.jitter.len(),
+            Millis(self.jitter.value_at_quantile(0.5)),
+            Millis(self.jitter.value_at_quantile(0.99)),
+            Millis(self.jitter.max())
+        )
+    }
+}
+
+/// Waits for `ticks` ticks of `interval` after the first and measures the
+/// jitter of the ticks.
+async fn ticking<S: Stream + Unpin>(
+    mut interval: S,
+    period: Duration,
+    ticks: usize,
+) -> JitterReport {
+    let mut jitter = latency_histogram();
+    interval.next().await;
+    let mut last = Instant::now();
+    for _ in 0..ticks {
+        interval.next().await;
+        let gap = last.elapsed();
+        last += gap;
+        record(&mut jitter, gap.abs_diff(period));
+    }
+    JitterReport { period, jitter }
+}
+
+/// Waits for `ticks` ticks of a tokio interval of `period` next to
+/// `busy_tasks` busy tokio tasks.
+pub fn tokio(period: Duration, busy_tasks: usize, ticks: usize) -> JitterReport {
+    use tokio_stream::wrappers::IntervalStream;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        for _ in 0..busy_tasks {
+            tokio::spawn(busy(stop.clone(), SPIN, tokio::task::yield_now));
+        }
+        let interval = IntervalStream::new(tokio::time::interval(period));
+        let report = tokio::spawn(ticking(interval, period, ticks))
+            .await
+            .unwrap();
+        stop.store(true, Ordering::Relaxed);
+        report
+    })
+}
+
+/// Waits for `ticks` ticks of an async_std interval of `period` next to
+/// `busy_tasks` busy async_std tasks.
+pub fn async_std(period: Duration, busy_tasks: usize, ticks: usize) -> JitterReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        for _ in 0..busy_tasks {
+            task::spawn(busy(stop.clone(), SPIN, task::yield_now));
+        }
+        let interval = async_std::stream::interval(period);
+        let report = task::spawn(ticking(interval, period, ticks)).await;
+        stop.store(true, Ordering::Relaxed);
+        report
+    })
+}
+
+/// Waits for `ticks` ticks of a smol interval of `period` next to
+/// `busy_tasks` busy smol tasks.
+pub fn smol(period: Duration, busy_tasks: usize, ticks: usize) -> JitterReport {
+    smol::block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        for _ in 0..busy_tasks {
+            smol::spawn(busy(stop.clone(), SPIN, smol::future::yield_now)).detach();
+        }
+        let interval = smol::Timer::interval(period);
+        let report = smol::spawn(ticking(interval, period, ticks)).await;
+        stop.store(true, Ordering::Relaxed);
+        report
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_tick_is_measured() {
+        for run in [tokio, async_std, smol] {
+            let report = run(Duration::from_millis(1), 2, 20);
+            assert_eq!(report.jitter.len(), 20);
+        }
+    }
+}
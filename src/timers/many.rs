@@ -0,0 +1,163 @@
+//! Hundreds of thousands of timers pending at once and firing at staggered
+//! times, on tokio, async_std and smol.
+//!
+//! Every timer is a task sleeping until a deadline drawn at random within the
+//! spread, so the timers are created in no particular order of their
+//! deadlines and fire at a steady rate over the spread. Lateness is the time
+//! from a timer's deadline until its task ran, or from the time the timer was
+//! set if that was already past its deadline: spawning the tasks takes a
+//! while, and the first ones may only be set after their deadlines, which is
+//! no fault of the timer. The CPU time is that of the whole process over the
+//! run, mostly the timer driver's as the tasks do nothing else; it is counted
+//! in clock ticks, so it is only accurate to about ten milliseconds.
+
+use crate::harness::{cpu_time, latency_histogram, record, xorshift, Millis};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How late the timers fired and what firing them cost.
+pub struct TimersReport {
+    pub lateness: Histogram<u64>,
+    pub elapsed: Duration,
+    /// CPU time used during the run, or `None` if it is not available on
+    /// this platform.
+    pub cpu: Option<Duration>,
+}
+
+impl fmt::Display for TimersReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>8} timers  late p50 {}  p99 {}  max {}",
+            self.lateness.len(),
+            Millis(self.lateness.value_at_quantile(0.5)),
+            Millis(self.lateness.value_at_quantile(0.99)),
+            Millis(self.lateness.max())
+        )?;
+        match self.cpu {
+            Some(cpu) => write!(
+                f,
+                "  cpu {:>5.0}ms ({:>5.1}%)",
+                cpu.as_secs_f64() * 1e3,
+                cpu.as_secs_f64() * 1e2 / self.elapsed.as_secs_f64()
+            ),
+            None => write!(f, "  cpu n/a"),
+        }
+    }
+}
+
+impl TimersReport {
+    fn new(start: Instant, cpu_before: Option<Duration>, lateness: Vec<Duration>) -> Self {
+        let elapsed = start.elapsed();
+        let cpu = cpu_before
+            .zip(cpu_time())
+            .map(|(before, after)| after.saturating_sub(before));
+        let mut histogram = latency_histogram();
+        for late in lateness {
+            record(&mut histogram, late);
+        }
+        TimersReport {
+            lateness: histogram,
+            elapsed,
+            cpu,
+        }
+    }
+}
+
+/// Deadlines of `timers` timers spread at random over `spread` from `start`.
+pub(super) fn deadlines(
+    start: Instant,
+    timers: usize,
+    spread: Duration,
+) -> impl Iterator<Item = Instant> {
+    let mut rng = 0x9e37_79b9_7f4a_7c15;
+    let spread = spread.as_nanos() as u64;
+    (0..timers).map(move |_| start + Duration::from_nanos(xorshift(&mut rng) % spread))
+}
+
+/// Fires `timers` tokio timers over `spread`.
+pub fn tokio(timers: usize, spread: Duration) -> TimersReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let cpu_before = cpu_time();
+        let start = Instant::now();
+        let handles: Vec<_> = deadlines(start, timers, spread)
+            .map(|deadline| {
+                tokio::spawn(async move {
+                    let set = Instant::now();
+                    tokio::time::sleep_until(deadline.into()).await;
+                    deadline.max(set).elapsed()
+                })
+            })
+            .collect();
+        let mut lateness = Vec::with_capacity(timers);
+        for handle in handles {
+            lateness.push(handle.await.unwrap());
+        }
+        TimersReport::new(start, cpu_before, lateness)
+    })
+}
+
+/// Fires `timers` async_std timers over `spread`.
+///
+/// async_std can only sleep for a duration, so every task sleeps for the time
+/// left until its deadline when it is set.
+pub fn async_std(timers: usize, spread: Duration) -> TimersReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let cpu_before = cpu_time();
+        let start = Instant::now();
+        let handles: Vec<_> = deadlines(start, timers, spread)
+            .map(|deadline| {
+                task::spawn(async move {
+                    let set = Instant::now();
+                    task::sleep(deadline.saturating_duration_since(set)).await;
+                    deadline.max(set).elapsed()
+                })
+            })
+            .collect();
+        let mut lateness = Vec::with_capacity(timers);
+        for handle in handles {
+            lateness.push(handle.await);
+        }
+        TimersReport::new(start, cpu_before, lateness)
+    })
+}
+
+/// Fires `timers` smol timers over `spread`.
+pub fn smol(timers: usize, spread: Duration) -> TimersReport {
+    smol::block_on(async {
+        let cpu_before = cpu_time();
+        let start = Instant::now();
+        let handles: Vec<_> = deadlines(start, timers, spread)
+            .map(|deadline| {
+                smol::spawn(async move {
+                    let set = Instant::now();
+                    smol::Timer::at(deadline).await;
+                    deadline.max(set).elapsed()
+                })
+            })
+            .collect();
+        let mut lateness = Vec::with_capacity(timers);
+        for handle in handles {
+            lateness.push(handle.await);
+        }
+        TimersReport::new(start, cpu_before, lateness)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_timer_fires() {
+        for run in [tokio, async_std, smol] {
+            let report = run(1_000, Duration::from_millis(20));
+            assert_eq!(report.lateness.len(), 1_000);
+            assert!(report.elapsed >= Duration::from_millis(15));
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Timers: how precisely the runtimes sleep and how their timer drivers cope
+//! with many timers at once.
+
+pub mod interval;
+pub mod many;
+pub mod paused;
+pub mod saturated;
+pub mod sleep;
+pub mod timeout;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Spins for `spin` at a time and yields with `yield_now` until `stop` is set.
+pub(crate) async fn busy<F, Fut>(stop: Arc<AtomicBool>, spin: Duration, yield_now: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    while !stop.load(Ordering::Relaxed) {
+        let spun = Instant::now();
+        while spun.elapsed() < spin {
+            std::hint::spin_loop();
+        }
+        yield_now().await;
+    }
+}
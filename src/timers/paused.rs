@@ -0,0 +1,116 @@
+//! The staggered timers of [`super::many`] on a current-thread tokio runtime
+//! whose clock is paused and only moves when it is advanced, in fixed steps.
+//!
+//! With the clock paused, time stands still while tasks run, so the timers
+//! fire at the same simulated times and in the same order on any machine and
+//! under any load: only the wall-clock time of the run depends on either.
+//! That makes comparing scheduling behavior, such as how the timer wheel
+//! batches deadlines that fall in one step, free of wall-clock noise. Every
+//! step moves the clock on by `step` and lets every timer that expired run
+//! before the next.
+//!
+//! Lateness is in simulated time, and so is bounded by the step and the
+//! millisecond resolution of tokio's timers. Inversions count timers that
+//! fired right after one with a later deadline. Only tokio can pause its
+//! clock, so async_std and smol have no deterministic mode.
+
+use super::many::deadlines;
+use crate::harness::{latency_histogram, record, Millis};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How late the timers fired in simulated time, in what order, and how long
+/// the simulation took.
+pub struct PausedReport {
+    pub lateness: Histogram<u64>,
+    pub inversions: u64,
+    pub steps: u64,
+    /// Wall-clock time of the whole run.
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for PausedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>8} timers  simulated late p50 {}  p99 {}  max {}  inversions {:>6}  {:>6} steps in {:>8.1}ms",
+            self.lateness.len(),
+            Millis(self.lateness.value_at_quantile(0.5)),
+            Millis(self.lateness.value_at_quantile(0.99)),
+            Millis(self.lateness.max()),
+            self.inversions,
+            self.steps,
+            self.elapsed.as_secs_f64() * 1e3
+        )
+    }
+}
+
+/// Fires `timers` tokio timers over `spread` of simulated time, advancing
+/// the paused clock by `step` at a time.
+pub fn tokio(timers: usize, spread: Duration, step: Duration) -> PausedReport {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap();
+    let started = Instant::now();
+    rt.block_on(async {
+        let start = tokio::time::Instant::now();
+        let order = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = deadlines(start.into_std(), timers, spread)
+            .map(|deadline| {
+                let order = order.clone();
+                let deadline = tokio::time::Instant::from_std(deadline);
+                tokio::spawn(async move {
+                    let set = tokio::time::Instant::now();
+                    tokio::time::sleep_until(deadline).await;
+                    let late = deadline.max(set).elapsed();
+                    (order.fetch_add(1, Ordering::Relaxed), deadline, late)
+                })
+            })
+            .collect();
+        let mut steps = 0;
+        while start.elapsed() <= spread {
+            tokio::time::advance(step).await;
+            steps += 1;
+        }
+        let mut fired = Vec::with_capacity(timers);
+        for handle in handles {
+            fired.push(handle.await.unwrap());
+        }
+        fired.sort_by_key(|&(order, _, _)| order);
+        let inversions = fired
+            .windows(2)
+            .filter(|pair| pair[1].1 < pair[0].1)
+            .count();
+        let mut lateness = latency_histogram();
+        for &(_, _, late) in &fired {
+            record(&mut lateness, late);
+        }
+        PausedReport {
+            lateness,
+            inversions: inversions as u64,
+            steps,
+            elapsed: started.elapsed(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_are_identical() {
+        let run = || tokio(1_000, Duration::from_millis(200), Duration::from_millis(5));
+        let (first, second) = (run(), run());
+        assert_eq!(first.lateness.len(), 1_000);
+        assert_eq!(first.steps, second.steps);
+        assert_eq!(first.inversions, second.inversions);
+        assert_eq!(first.lateness.max(), second.lateness.max());
+        assert!(first.lateness.max() <= Duration::from_millis(6).as_nanos() as u64);
+    }
+}
@@ -0,0 +1,109 @@
+//! Sleeps on an executor saturated with CPU-bound tasks, on tokio, async_std
+//! and smol.
+//!
+//! Next to the sleeping task a number of tasks spin for a chunk of time,
+//! yield and spin again, so that every worker always has work queued. Once a
+//! sleep's timer has fired its task still waits behind the busy ones for a
+//! worker, and busy workers also look at their timers less often, so the
+//! overshoot grows with the number of busy tasks per worker and the length of
+//! their chunks. The report is that of the plain sleeps.
+
+use super::busy;
+use super::sleep::SleepReport;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The CPU-bound tasks filling the executor.
+#[derive(Clone, Copy, Debug)]
+pub struct Load {
+    pub tasks: usize,
+    /// Time every task spins for between yields.
+    pub chunk: Duration,
+}
+
+/// Sleeps `sleeps` times for `requested` in a tokio task next to `load`.
+pub fn tokio(load: Load, requested: Duration, sleeps: usize) -> SleepReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        for _ in 0..load.tasks {
+            tokio::spawn(busy(stop.clone(), load.chunk, tokio::task::yield_now));
+        }
+        let slept = tokio::spawn(async move {
+            let mut slept = Vec::with_capacity(sleeps);
+            for _ in 0..sleeps {
+                let start = Instant::now();
+                tokio::time::sleep(requested).await;
+                slept.push(start.elapsed());
+            }
+            slept
+        });
+        let slept = slept.await.unwrap();
+        stop.store(true, Ordering::Relaxed);
+        SleepReport::new(requested, slept)
+    })
+}
+
+/// Sleeps `sleeps` times for `requested` in an async_std task next to `load`.
+pub fn async_std(load: Load, requested: Duration, sleeps: usize) -> SleepReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        for _ in 0..load.tasks {
+            task::spawn(busy(stop.clone(), load.chunk, task::yield_now));
+        }
+        let slept = task::spawn(async move {
+            let mut slept = Vec::with_capacity(sleeps);
+            for _ in 0..sleeps {
+                let start = Instant::now();
+                task::sleep(requested).await;
+                slept.push(start.elapsed());
+            }
+            slept
+        })
+        .await;
+        stop.store(true, Ordering::Relaxed);
+        SleepReport::new(requested, slept)
+    })
+}
+
+/// Sleeps `sleeps` times for `requested` in a smol task next to `load`.
+pub fn smol(load: Load, requested: Duration, sleeps: usize) -> SleepReport {
+    smol::block_on(async {
+        let stop = Arc::new(AtomicBool::new(false));
+        for _ in 0..load.tasks {
+            smol::spawn(busy(stop.clone(), load.chunk, smol::future::yield_now)).detach();
+        }
+        let slept = smol::spawn(async move {
+            let mut slept = Vec::with_capacity(sleeps);
+            for _ in 0..sleeps {
+                let start = Instant::now();
+                smol::Timer::after(requested).await;
+                slept.push(start.elapsed());
+            }
+            slept
+        })
+        .await;
+        stop.store(true, Ordering::Relaxed);
+        SleepReport::new(requested, slept)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_sleep_is_recorded_under_load() {
+        let load = Load {
+            tasks: 8,
+            chunk: Duration::from_millis(1),
+        };
+        for run in [tokio, async_std, smol] {
+            let report = run(load, Duration::from_millis(2), 5);
+            assert_eq!(report.overshoot.len(), 5);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Sleeps of a requested length, one after another from one task, on tokio,
+//! async_std and smol, against `std::thread::sleep`.
+//!
+//! The report is the overshoot of every sleep, the time it took beyond the
+//! requested length. tokio's timer wheel has millisecond slots and rounds
+//! every sleep up to the next one, so it overshoots sub-millisecond sleeps by
+//! up to a millisecond; async-io, behind async_std and smol, sets the
+//! reactor's timeout to the exact deadline, and a thread sleep is as precise
+//! as the operating system lets it be, which on Linux adds a timer slack of
+//! 50 µs by default. The simulated computations of the `tests` bench are such
+//! sleeps, of 2 ms.
+
+use crate::harness::{latency_histogram, record, Millis};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How far past the requested length the sleeps ended.
+pub struct SleepReport {
+    pub requested: Duration,
+    pub overshoot: Histogram<u64>,
+}
+
+impl SleepReport {
+    pub(super) fn new(requested: Duration, slept: Vec<Duration>) -> Self {
+        let mut overshoot = latency_histogram();
+        for slept in slept {
+            record(&mut overshoot, slept.saturating_sub(requested));
+        }
+        SleepReport {
+            requested,
+            overshoot,
+        }
+    }
+}
+
+impl fmt::Display for SleepReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>8} sleeps  overshoot p50 {}  p99 {}  max {}",
+            self.overshoot.len(),
+            Millis(self.overshoot.value_at_quantile(0.5)),
+            Millis(self.overshoot.value_at_quantile(0.99)),
+            Millis(self.overshoot.max())
+        )
+    }
+}
+
+/// Sleeps `sleeps` times for `requested` in a tokio task.
+pub fn tokio(requested: Duration, sleeps: usize) -> SleepReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let slept = tokio::spawn(async move {
+            let mut slept = Vec::with_capacity(sleeps);
+            for _ in 0..sleeps {
+                let start = Instant::now();
+                tokio::time::sleep(requested).await;
+                slept.push(start.elapsed());
+            }
+            slept
+        });
+        SleepReport::new(requested, slept.await.unwrap())
+    })
+}
+
+/// Sleeps `sleeps` times for `requested` in an async_std task.
+pub fn async_std(requested: Duration, sleeps: usize) -> SleepReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(async move {
+        let mut slept = Vec::with_capacity(sleeps);
+        for _ in 0..sleeps {
+            let start = Instant::now();
+            task::sleep(requested).await;
+            slept.push(start.elapsed());
+        }
+        SleepReport::new(requested, slept)
+    }))
+}
+
+/// Sleeps `sleeps` times for `requested` in a smol task.
+pub fn smol(requested: Duration, sleeps: usize) -> SleepReport {
+    smol::block_on(smol::spawn(async move {
+        let mut slept = Vec::with_capacity(sleeps);
+        for _ in 0..sleeps {
+            let start = Instant::now();
+            smol::Timer::after(requested).await;
+            slept.push(start.elapsed());
+        }
+        SleepReport::new(requested, slept)
+    }))
+}
+
+/// Sleeps `sleeps` times for `requested` on the current thread.
+pub fn thread(requested: Duration, sleeps: usize) -> SleepReport {
+    let mut slept = Vec::with_capacity(sleeps);
+    for _ in 0..sleeps {
+        let start = Instant::now();
+        std::thread::sleep(requested);
+        slept.push(start.elapsed());
+    }
+    SleepReport::new(requested, slept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_sleep_is_recorded() {
+        for run in [tokio, async_std, smol, thread] {
+            let report = run(Duration::from_micros(100), 20);
+            assert_eq!(report.overshoot.len(), 20);
+        }
+    }
+}
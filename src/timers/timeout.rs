@@ -0,0 +1,154 @@
+//! The cost of wrapping a future in a timeout: `tokio::time::timeout`,
+//! `async_std::future::timeout` and futures-timer's `Delay` raced against the
+//! future, against awaiting it plainly.
+//!
+//! When the timeout never fires the wrapped future returns pending once,
+//! waking itself, and is ready on its second poll, well before a timeout of an
+//! hour; what is measured is setting up the timer, polling through the
+//! wrapper and dropping the timer again. When it fires the wrapped future
+//! never completes and the timeout is zero, so it could fire on its first poll
+//! without waiting: async-std's does, whereas tokio's still waits for the next
+//! millisecond tick of its timer and futures-timer's for its thread to fire
+//! it. Each runtime's own timeout only runs on that runtime; futures-timer
+//! does not depend on one and has a thread of its own.
+
+use futures::future::{self, Either};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Whether a timeout fires before the future it wraps completes.
+#[derive(Clone, Copy, Debug)]
+pub enum Outcome {
+    Completes,
+    Fires,
+}
+
+/// How every future is awaited.
+#[derive(Clone, Copy, Debug)]
+pub enum Wrap {
+    Plain,
+    /// In the runtime's own timeout.
+    Runtime(Outcome),
+    /// Raced against a futures-timer `Delay`.
+    FuturesTimer(Outcome),
+}
+
+/// How long the awaits took.
+pub struct TimeoutReport {
+    pub awaits: u64,
+    /// Awaits that ended by the timeout.
+    pub timed_out: u64,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for TimeoutReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>8.1} ns/await  {:>5.1}% timed out",
+            self.elapsed.as_nanos() as f64 / self.awaits as f64,
+            self.timed_out as f64 * 1e2 / self.awaits as f64
+        )
+    }
+}
+
+/// A future that is ready on its second poll.
+#[derive(Default)]
+struct Step(bool);
+
+impl Future for Step {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+/// The future to wrap and the timeout to give it for `outcome`.
+fn wrapped(outcome: Outcome) -> (Either<Step, future::Pending<()>>, Duration) {
+    match outcome {
+        Outcome::Completes => (Either::Left(Step::default()), Duration::from_secs(3600)),
+        Outcome::Fires => (Either::Right(future::pending()), Duration::ZERO),
+    }
+}
+
+/// Awaits `rounds` futures wrapped as `wrap` says, with `timeout` as the
+/// runtime's own timeout, and counts those that timed out.
+async fn awaiting<T, Fut>(wrap: Wrap, rounds: usize, timeout: T) -> TimeoutReport
+where
+    T: Fn(Duration, Either<Step, future::Pending<()>>) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let start = Instant::now();
+    let mut timed_out = 0;
+    for _ in 0..rounds {
+        let fired = match wrap {
+            Wrap::Plain => {
+                Step::default().await;
+                false
+            }
+            Wrap::Runtime(outcome) => {
+                let (inner, after) = wrapped(outcome);
+                timeout(after, inner).await
+            }
+            Wrap::FuturesTimer(outcome) => {
+                let (inner, after) = wrapped(outcome);
+                let delay = futures_timer::Delay::new(after);
+                matches!(future::select(inner, delay).await, Either::Right(_))
+            }
+        };
+        timed_out += u64::from(fired);
+    }
+    TimeoutReport {
+        awaits: rounds as u64,
+        timed_out,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Awaits `rounds` futures wrapped as `wrap` says in a tokio task.
+pub fn tokio(wrap: Wrap, rounds: usize) -> TimeoutReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let run = awaiting(wrap, rounds, |after, inner| async move {
+            tokio::time::timeout(after, inner).await.is_err()
+        });
+        tokio::spawn(run).await.unwrap()
+    })
+}
+
+/// Awaits `rounds` futures wrapped as `wrap` says in an async_std task.
+pub fn async_std(wrap: Wrap, rounds: usize) -> TimeoutReport {
+    use async_std::task;
+
+    task::block_on(task::spawn(awaiting(
+        wrap,
+        rounds,
+        |after, inner| async move { async_std::future::timeout(after, inner).await.is_err() },
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_firing_timeouts_time_out() {
+        for run in [tokio, async_std] {
+            assert_eq!(run(Wrap::Plain, 100).timed_out, 0);
+            for wrap in [Wrap::Runtime, Wrap::FuturesTimer] {
+                assert_eq!(run(wrap(Outcome::Completes), 100).timed_out, 0);
+                assert_eq!(run(wrap(Outcome::Fires), 100).timed_out, 100);
+            }
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Bulk transfer over established TLS sessions: tokio-rustls on tokio and
+//! futures-rustls on async_std.
+//!
+//! All handshakes complete before the clock starts, so only the record layer
+//! is measured. Each client announces how many bytes it is going to send, then
+//! streams them in fixed-size chunks and waits for the server to acknowledge
+//! that everything arrived.
+//!
+//! The transfer code is written against the `futures` IO traits; on tokio the
+//! TLS streams are adapted with `tokio_util::compat`.
+
+use super::{server_name, Identity};
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use futures::future::join_all;
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use hdrhistogram::Histogram;
+use std::time::Instant;
+
+/// Uploads `total` bytes in `chunk`-sized writes over `connections` sessions on tokio.
+///
+/// Latencies are the durations of individual chunk writes, which grow when the
+/// receiving side cannot keep up.
+pub fn tokio(connections: usize, total: usize, chunk: usize) -> LoadReport {
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::{TlsAcceptor, TlsConnector};
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+
+    let identity = Identity::generate();
+    let acceptor = TlsAcceptor::from(identity.server_config());
+    let connector = TlsConnector::from(identity.client_config());
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    if let Ok(stream) = acceptor.accept(stream).await {
+                        let _ = sink(stream.compat(), chunk).await;
+                    }
+                });
+            }
+        });
+
+        let sessions = join_all((0..connections).map(|_| async {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            connector
+                .connect(server_name(), stream)
+                .await
+                .unwrap()
+                .compat()
+        }))
+        .await;
+
+        let start = Instant::now();
+        let uploads: Vec<_> = sessions
+            .into_iter()
+            .zip(split(total, connections))
+            .map(|(session, share)| tokio::spawn(upload(session, share, chunk)))
+            .collect();
+        let mut latencies = latency_histogram();
+        for upload in uploads {
+            latencies.add(upload.await.unwrap()).unwrap();
+        }
+        let chunks = latencies.len() as usize;
+        LoadReport::since(start, chunks, latencies).with_bytes(total as u64)
+    })
+}
+
+/// Uploads `total` bytes in `chunk`-sized writes over `connections` sessions on async_std.
+///
+/// Latencies are the durations of individual chunk writes, which grow when the
+/// receiving side cannot keep up.
+pub fn async_std(connections: usize, total: usize, chunk: usize) -> LoadReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+    use futures_rustls::{TlsAcceptor, TlsConnector};
+
+    let identity = Identity::generate();
+    let acceptor = TlsAcceptor::from(identity.server_config());
+    let connector = TlsConnector::from(identity.client_config());
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept = task::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                task::spawn(async move {
+                    if let Ok(stream) = acceptor.accept(stream).await {
+                        let _ = sink(stream, chunk).await;
+                    }
+                });
+            }
+        });
+
+        let sessions = join_all((0..connections).map(|_| async {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            connector.connect(server_name(), stream).await.unwrap()
+        }))
+        .await;
+
+        let start = Instant::now();
+        let uploads: Vec<_> = sessions
+            .into_iter()
+            .zip(split(total, connections))
+            .map(|(session, share)| task::spawn(upload(session, share, chunk)))
+            .collect();
+        let mut latencies = latency_histogram();
+        for upload in uploads {
+            latencies.add(upload.await).unwrap();
+        }
+        let chunks = latencies.len() as usize;
+        let report = LoadReport::since(start, chunks, latencies);
+        accept.cancel().await;
+        report.with_bytes(total as u64)
+    })
+}
+
+/// Sends a length header followed by `bytes` bytes and waits for the acknowledgement.
+async fn upload<S>(mut session: S, bytes: usize, chunk: usize) -> Histogram<u64>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let buf = vec![0u8; chunk];
+    let mut latencies = latency_histogram();
+    session
+        .write_all(&(bytes as u64).to_be_bytes())
+        .await
+        .unwrap();
+    let mut remaining = bytes;
+    while remaining > 0 {
+        let len = remaining.min(chunk);
+        let start = Instant::now();
+        session.write_all(&buf[..len]).await.unwrap();
+        record(&mut latencies, start.elapsed());
+        remaining -= len;
+    }
+    session.flush().await.unwrap();
+    session.read_exact(&mut [0]).await.unwrap();
+    latencies
+}
+
+/// Reads and discards an upload, then acknowledges it with a single byte.
+async fn sink<S>(mut session: S, chunk: usize) -> std::io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut header = [0u8; 8];
+    session.read_exact(&mut header).await?;
+    let mut remaining = u64::from_be_bytes(header) as usize;
+    let mut buf = vec![0u8; chunk];
+    while remaining > 0 {
+        let len = remaining.min(chunk);
+        session.read_exact(&mut buf[..len]).await?;
+        remaining -= len;
+    }
+    session.write_all(b"!").await?;
+    session.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokio_transfers_everything() {
+        let report = tokio(2, 100_000, 4096);
+        assert_eq!(report.bytes, 100_000);
+        assert_eq!(report.requests, 26);
+    }
+
+    #[test]
+    fn async_std_transfers_everything() {
+        assert_eq!(async_std(2, 100_000, 4096).requests, 26);
+    }
+}
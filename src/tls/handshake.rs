@@ -0,0 +1,126 @@
+//! Full TLS handshakes per second: tokio-rustls on tokio and futures-rustls on async_std.
+//!
+//! Every connection completes a handshake and then reads a single byte written
+//! by the server, so both sides have finished their part before it is counted.
+
+use super::{server_name, Identity};
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use std::net::SocketAddr;
+use std::time::Instant;
+
+/// Performs `handshakes` handshakes from `concurrency` clients on tokio.
+pub fn tokio(concurrency: usize, handshakes: usize) -> LoadReport {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+    let identity = Identity::generate();
+    let acceptor = TlsAcceptor::from(identity.server_config());
+    let connector = TlsConnector::from(identity.client_config());
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    if let Ok(mut stream) = acceptor.accept(stream).await {
+                        let _ = stream.write_all(b"!").await;
+                        let _ = stream.flush().await;
+                    }
+                });
+            }
+        });
+
+        let client = |addr: SocketAddr, connector: TlsConnector, handshakes: usize| async move {
+            let mut latencies = latency_histogram();
+            for _ in 0..handshakes {
+                let start = Instant::now();
+                let stream = TcpStream::connect(addr).await.unwrap();
+                let mut stream = connector.connect(server_name(), stream).await.unwrap();
+                stream.read_exact(&mut [0]).await.unwrap();
+                record(&mut latencies, start.elapsed());
+            }
+            latencies
+        };
+        let start = Instant::now();
+        let clients: Vec<_> = split(handshakes, concurrency)
+            .map(|share| tokio::spawn(client(addr, connector.clone(), share)))
+            .collect();
+        let mut latencies = latency_histogram();
+        for client in clients {
+            latencies.add(client.await.unwrap()).unwrap();
+        }
+        LoadReport::since(start, handshakes, latencies)
+    })
+}
+
+/// Performs `handshakes` handshakes from `concurrency` clients on async_std.
+pub fn async_std(concurrency: usize, handshakes: usize) -> LoadReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+    use futures_rustls::{TlsAcceptor, TlsConnector};
+
+    let identity = Identity::generate();
+    let acceptor = TlsAcceptor::from(identity.server_config());
+    let connector = TlsConnector::from(identity.client_config());
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept = task::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                task::spawn(async move {
+                    if let Ok(mut stream) = acceptor.accept(stream).await {
+                        let _ = stream.write_all(b"!").await;
+                        let _ = stream.flush().await;
+                    }
+                });
+            }
+        });
+
+        let client = |addr: SocketAddr, connector: TlsConnector, handshakes: usize| async move {
+            let mut latencies = latency_histogram();
+            for _ in 0..handshakes {
+                let start = Instant::now();
+                let stream = TcpStream::connect(addr).await.unwrap();
+                let mut stream = connector.connect(server_name(), stream).await.unwrap();
+                stream.read_exact(&mut [0]).await.unwrap();
+                record(&mut latencies, start.elapsed());
+            }
+            latencies
+        };
+        let start = Instant::now();
+        let clients: Vec<_> = split(handshakes, concurrency)
+            .map(|share| task::spawn(client(addr, connector.clone(), share)))
+            .collect();
+        let mut latencies = latency_histogram();
+        for client in clients {
+            latencies.add(client.await).unwrap();
+        }
+        let report = LoadReport::since(start, handshakes, latencies);
+        accept.cancel().await;
+        report
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokio_handshakes() {
+        assert_eq!(tokio(2, 10).latencies.len(), 10);
+    }
+
+    #[test]
+    fn async_std_handshakes() {
+        assert_eq!(async_std(2, 10).latencies.len(), 10);
+    }
+}
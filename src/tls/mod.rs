@@ -0,0 +1,66 @@
+//! TLS over loopback with rustls, and the TLS material shared by the
+//! encrypted transports.
+
+pub mod bulk;
+pub mod handshake;
+
+use rustls::client::Resumption;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// A freshly generated self-signed certificate for `localhost` and its key.
+pub struct Identity {
+    pub cert: CertificateDer<'static>,
+    key: PrivatePkcs8KeyDer<'static>,
+}
+
+impl Identity {
+    /// Generates a new self-signed certificate for `localhost`.
+    pub fn generate() -> Self {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        Identity {
+            cert: certified.cert.der().clone(),
+            key: PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der()),
+        }
+    }
+
+    /// The private key of the certificate.
+    pub fn key(&self) -> PrivateKeyDer<'static> {
+        PrivateKeyDer::Pkcs8(self.key.clone_key())
+    }
+
+    /// A root store that trusts only this certificate.
+    pub fn roots(&self) -> RootCertStore {
+        let mut roots = RootCertStore::empty();
+        roots.add(self.cert.clone()).unwrap();
+        roots
+    }
+
+    /// A server configuration presenting this certificate.
+    pub fn server_config(&self) -> Arc<ServerConfig> {
+        let config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![self.cert.clone()], self.key())
+            .unwrap();
+        Arc::new(config)
+    }
+
+    /// A client configuration that trusts this certificate.
+    ///
+    /// Session resumption is disabled, so that every connection performs a
+    /// full handshake.
+    pub fn client_config(&self) -> Arc<ClientConfig> {
+        let mut config = ClientConfig::builder()
+            .with_root_certificates(self.roots())
+            .with_no_client_auth();
+        config.resumption = Resumption::disabled();
+        Arc::new(config)
+    }
+}
+
+/// Name the clients expect in the server certificate.
+fn server_name() -> ServerName<'static> {
+    ServerName::try_from("localhost").unwrap()
+}
@@ -0,0 +1,175 @@
+//! A simulated wide-area link between clients and a server, without tc/netem.
+//!
+//! [`DelayedLink`] is a TCP relay on its own tokio runtime, so its timers do
+//! not compete with the runtime being measured. It holds every chunk it reads
+//! for the link's delay plus a uniformly distributed jitter before passing it
+//! on, in both directions, and never reorders bytes: a chunk is not released
+//! before the one read ahead of it. Any scenario that takes a server address
+//! can be run through it by connecting to [`DelayedLink::addr`] instead.
+
+use crate::harness::{loopback_listener, xorshift, LoadReport};
+use crate::rpc::length_prefixed::{self, EchoServer};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// Delay and jitter added to every message crossing the link in either direction.
+#[derive(Clone, Copy, Debug)]
+pub struct Link {
+    pub delay: Duration,
+    /// Largest deviation from `delay`, in either direction.
+    pub jitter: Duration,
+}
+
+impl Link {
+    /// Time a message read now is held back, drawn with the xorshift state `rng`.
+    fn hold(&self, rng: &mut u64) -> Duration {
+        let spread = 2 * self.jitter.as_nanos() as u64;
+        if spread == 0 {
+            return self.delay;
+        }
+        let offset = Duration::from_nanos(xorshift(rng) % (spread + 1));
+        (self.delay + offset).saturating_sub(self.jitter)
+    }
+}
+
+/// A relay that delays everything passing through it according to a [`Link`].
+///
+/// The relay is stopped when the handle is dropped.
+pub struct DelayedLink {
+    addr: SocketAddr,
+    rt: Option<tokio::runtime::Runtime>,
+}
+
+impl DelayedLink {
+    /// Starts relaying connections made to [`addr`](Self::addr) to `target`.
+    pub fn start(target: SocketAddr, link: Link) -> Self {
+        use tokio::net::{TcpListener, TcpStream};
+
+        // Not `metrics::runtime()`, so that the relay's steals and parks are
+        // not added to those of the scenario, nor its workers pinned.
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let listener = rt.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr = listener.local_addr().unwrap();
+        rt.spawn(async move {
+            for seed in 1.. {
+                let (client, _) = listener.accept().await.unwrap();
+                client.set_nodelay(true).unwrap();
+                tokio::spawn(async move {
+                    let server = TcpStream::connect(target).await.unwrap();
+                    server.set_nodelay(true).unwrap();
+                    let (client_read, client_write) = client.into_split();
+                    let (server_read, server_write) = server.into_split();
+                    tokio::join!(
+                        delay(client_read, server_write, link, 2 * seed),
+                        delay(server_read, client_write, link, 2 * seed + 1),
+                    );
+                });
+            }
+        });
+        DelayedLink { addr, rt: Some(rt) }
+    }
+
+    /// Address to connect to instead of the target.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for DelayedLink {
+    fn drop(&mut self) {
+        if let Some(rt) = self.rt.take() {
+            rt.shutdown_background();
+        }
+    }
+}
+
+/// Copies everything from `from` to `to`, holding every chunk back as `link` says.
+async fn delay(
+    mut from: tokio::net::tcp::OwnedReadHalf,
+    mut to: tokio::net::tcp::OwnedWriteHalf,
+    link: Link,
+    seed: u64,
+) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::mpsc;
+
+    let (chunks, mut pending) = mpsc::unbounded_channel::<(Instant, Vec<u8>)>();
+    let forward = tokio::spawn(async move {
+        while let Some((due, chunk)) = pending.recv().await {
+            tokio::time::sleep_until(due.into()).await;
+            if to.write_all(&chunk).await.is_err() {
+                return;
+            }
+        }
+        let _ = to.shutdown().await;
+    });
+
+    let mut rng = seed;
+    let mut last = Instant::now();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let len = match from.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(len) => len,
+        };
+        last = last.max(Instant::now() + link.hold(&mut rng));
+        if chunks.send((last, buf[..len].to_vec())).is_err() {
+            break;
+        }
+    }
+    drop(chunks);
+    let _ = forward.await;
+}
+
+/// Starts a length-prefixed echo server on a thread of its own, behind a delayed link.
+///
+/// The server and the link are both stopped when the handles are dropped.
+fn echo_behind(link: Link) -> (DelayedLink, EchoServer) {
+    let listener = loopback_listener();
+    let addr = listener.local_addr().unwrap();
+    let server = EchoServer::start(listener);
+    (DelayedLink::start(addr, link), server)
+}
+
+/// Echoes `requests` length-prefixed messages over `concurrency` connections
+/// from tokio clients to a server behind `link`.
+pub fn tokio(link: Link, concurrency: usize, requests: usize, payload: usize) -> LoadReport {
+    let (link, _server) = echo_behind(link);
+    length_prefixed::tokio_client(link.addr(), concurrency, requests, payload)
+}
+
+/// Echoes `requests` length-prefixed messages over `concurrency` connections
+/// from async_std clients to a server behind `link`.
+pub fn async_std(link: Link, concurrency: usize, requests: usize, payload: usize) -> LoadReport {
+    let (link, _server) = echo_behind(link);
+    length_prefixed::async_std_client(link.addr(), concurrency, requests, payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hold_stays_within_jitter() {
+        let link = Link {
+            delay: Duration::from_millis(10),
+            jitter: Duration::from_millis(3),
+        };
+        let mut rng = 1;
+        for _ in 0..1000 {
+            let hold = link.hold(&mut rng);
+            assert!(hold >= Duration::from_millis(7) && hold <= Duration::from_millis(13));
+        }
+    }
+
+    #[test]
+    fn round_trips_pay_the_delay_twice() {
+        let link = Link {
+            delay: Duration::from_millis(10),
+            jitter: Duration::from_millis(0),
+        };
+        let report = tokio(link, 2, 4, 16);
+        assert!(report.latencies.min() >= Duration::from_millis(20).as_nanos() as u64);
+        assert_eq!(async_std(link, 2, 4, 16).latencies.len(), 4);
+    }
+}
@@ -0,0 +1,240 @@
+//! A key-value cache shared by many tasks doing a mix of reads and writes, on
+//! tokio and async_std.
+//!
+//! The cache is a `HashMap` behind a parking_lot readers-writer lock, a
+//! `DashMap`, which shards its map over many such locks, or a map owned by
+//! one actor task that the others send their requests to over a channel,
+//! answering each through a oneshot channel. The locks are only held for the
+//! lookup or the insert and never across an await. Every task picks keys
+//! uniformly at random among the prefilled ones, writes with probability
+//! `writes` and reads otherwise, and yields after every operation, as a
+//! request handler would go on to other work.
+//!
+//! Latencies are those of single operations, including the round trip
+//! through the actor; the actor serializes all of them on one worker, which
+//! the locks only do for writes.
+
+use crate::harness::{latency_histogram, record, split, xorshift, LoadReport};
+use futures::channel::oneshot;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A map from keys to values that tasks share.
+pub trait Cache: Send + Sync + 'static {
+    /// Creates the cache with the keys `0..keys` present, together with the
+    /// task that has to run next to it for it to work.
+    fn new(keys: u64) -> (Self, impl Future<Output = ()> + Send + 'static)
+    where
+        Self: Sized;
+
+    fn get(&self, key: u64) -> impl Future<Output = Option<u64>> + Send;
+
+    fn put(&self, key: u64, value: u64) -> impl Future<Output = ()> + Send;
+}
+
+impl Cache for parking_lot::RwLock<HashMap<u64, u64>> {
+    fn new(keys: u64) -> (Self, impl Future<Output = ()> + Send + 'static) {
+        let map = (0..keys).map(|key| (key, key)).collect();
+        (parking_lot::RwLock::new(map), async {})
+    }
+
+    async fn get(&self, key: u64) -> Option<u64> {
+        self.read().get(&key).copied()
+    }
+
+    async fn put(&self, key: u64, value: u64) {
+        self.write().insert(key, value);
+    }
+}
+
+impl Cache for dashmap::DashMap<u64, u64> {
+    fn new(keys: u64) -> (Self, impl Future<Output = ()> + Send + 'static) {
+        ((0..keys).map(|key| (key, key)).collect(), async {})
+    }
+
+    async fn get(&self, key: u64) -> Option<u64> {
+        dashmap::DashMap::get(self, &key).map(|value| *value)
+    }
+
+    async fn put(&self, key: u64, value: u64) {
+        self.insert(key, value);
+    }
+}
+
+/// A request to the actor owning the map.
+enum Request {
+    Get(u64, oneshot::Sender<Option<u64>>),
+    Put(u64, u64, oneshot::Sender<()>),
+}
+
+/// A map owned by an actor task, reached through its inbox.
+pub struct Actor {
+    inbox: async_channel::Sender<Request>,
+}
+
+impl Cache for Actor {
+    fn new(keys: u64) -> (Self, impl Future<Output = ()> + Send + 'static) {
+        let (inbox, requests) = async_channel::bounded(1024);
+        let owner = async move {
+            let mut map: HashMap<u64, u64> = (0..keys).map(|key| (key, key)).collect();
+            while let Ok(request) = requests.recv().await {
+                match request {
+                    Request::Get(key, reply) => {
+                        let _ = reply.send(map.get(&key).copied());
+                    }
+                    Request::Put(key, value, reply) => {
+                        map.insert(key, value);
+                        let _ = reply.send(());
+                    }
+                }
+            }
+        };
+        (Actor { inbox }, owner)
+    }
+
+    async fn get(&self, key: u64) -> Option<u64> {
+        let (reply, answer) = oneshot::channel();
+        self.inbox.send(Request::Get(key, reply)).await.unwrap();
+        answer.await.unwrap()
+    }
+
+    async fn put(&self, key: u64, value: u64) {
+        let (reply, answer) = oneshot::channel();
+        self.inbox
+            .send(Request::Put(key, value, reply))
+            .await
+            .unwrap();
+        answer.await.unwrap()
+    }
+}
+
+/// The operations the tasks do on the cache.
+#[derive(Clone, Copy, Debug)]
+pub struct Mix {
+    pub tasks: usize,
+    /// Number of keys in the cache.
+    pub keys: u64,
+    /// Probability of an operation being a write.
+    pub writes: f64,
+    pub operations: usize,
+}
+
+/// Does `operations` operations on `cache` as `mix` says, yielding with
+/// `yield_now` after each, and records how long each took.
+pub(crate) async fn operate<C, F, Fut>(
+    cache: Arc<C>,
+    mix: Mix,
+    operations: usize,
+    seed: u64,
+    yield_now: F,
+) -> Vec<Duration>
+where
+    C: Cache,
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut rng = seed;
+    let mut latencies = Vec::with_capacity(operations);
+    for _ in 0..operations {
+        let random = xorshift(&mut rng);
+        let key = random % mix.keys;
+        let start = Instant::now();
+        if (random >> 32) as f64 / u32::MAX as f64 >= mix.writes {
+            assert!(cache.get(key).await.is_some());
+        } else {
+            cache.put(key, random).await;
+        }
+        latencies.push(start.elapsed());
+        yield_now().await;
+    }
+    latencies
+}
+
+/// A load report over the operations of all tasks.
+fn report(start: Instant, tasks: Vec<Vec<Duration>>) -> LoadReport {
+    let mut latencies = latency_histogram();
+    let mut operations = 0;
+    for latency in tasks.into_iter().flatten() {
+        record(&mut latencies, latency);
+        operations += 1;
+    }
+    LoadReport::since(start, operations, latencies)
+}
+
+/// Runs `mix` on a `C` from tokio tasks.
+pub fn on_tokio<C: Cache>(mix: Mix) -> LoadReport {
+    use tokio::task::yield_now;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (cache, owner) = C::new(mix.keys);
+        let owner = tokio::spawn(owner);
+        let cache = Arc::new(cache);
+        let start = Instant::now();
+        let handles: Vec<_> = split(mix.operations, mix.tasks)
+            .zip(1..)
+            .map(|(operations, seed)| {
+                tokio::spawn(operate(cache.clone(), mix, operations, seed, yield_now))
+            })
+            .collect();
+        let mut latencies = Vec::with_capacity(mix.tasks);
+        for handle in handles {
+            latencies.push(handle.await.unwrap());
+        }
+        let report = report(start, latencies);
+        drop(cache);
+        owner.await.unwrap();
+        report
+    })
+}
+
+/// Runs `mix` on a `C` from async_std tasks.
+pub fn on_async_std<C: Cache>(mix: Mix) -> LoadReport {
+    use async_std::task::{self, yield_now};
+
+    task::block_on(async {
+        let (cache, owner) = C::new(mix.keys);
+        let owner = task::spawn(owner);
+        let cache = Arc::new(cache);
+        let start = Instant::now();
+        let handles: Vec<_> = split(mix.operations, mix.tasks)
+            .zip(1..)
+            .map(|(operations, seed)| {
+                task::spawn(operate(cache.clone(), mix, operations, seed, yield_now))
+            })
+            .collect();
+        let mut latencies = Vec::with_capacity(mix.tasks);
+        for handle in handles {
+            latencies.push(handle.await);
+        }
+        let report = report(start, latencies);
+        drop(cache);
+        owner.await;
+        report
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn every_operation_is_done<C: Cache>() {
+        let mix = Mix {
+            tasks: 8,
+            keys: 100,
+            writes: 0.2,
+            operations: 1_000,
+        };
+        assert_eq!(on_tokio::<C>(mix).requests, 1_000);
+        assert_eq!(on_async_std::<C>(mix).requests, 1_000);
+    }
+
+    #[test]
+    fn every_cache_does_every_operation() {
+        every_operation_is_done::<parking_lot::RwLock<HashMap<u64, u64>>>();
+        every_operation_is_done::<dashmap::DashMap<u64, u64>>();
+        every_operation_is_done::<Actor>();
+    }
+}
@@ -0,0 +1,292 @@
+//! The same ledger kept as shared state behind a mutex and by a single owner
+//! task receiving commands over a channel, on tokio and async_std.
+//!
+//! The ledger holds the balances of a number of accounts. Tasks move random
+//! amounts between random accounts, which is refused when the paying account
+//! cannot cover the amount, and yield after every transfer. With shared state
+//! a task locks a `std::sync::Mutex` around the whole ledger for the transfer;
+//! the lock is never held across an await. With an owner task the transfer is
+//! a command sent over a bounded async-channel and answered through a oneshot
+//! channel, so every transfer pays for two channel hops and a wakeup of the
+//! owner, but never contends for a lock. Both keep the total of the balances,
+//! which is checked after the run.
+
+use crate::harness::{latency_histogram, record, split, xorshift, LoadReport};
+use futures::channel::oneshot;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Balance every account starts with.
+const OPENING_BALANCE: u64 = 1_000;
+
+/// The balances of all accounts.
+struct Accounts(Vec<u64>);
+
+impl Accounts {
+    fn open(accounts: usize) -> Self {
+        Accounts(vec![OPENING_BALANCE; accounts])
+    }
+
+    /// Moves `amount` from `from` to `to` and tells whether it was covered.
+    fn transfer(&mut self, from: usize, to: usize, amount: u64) -> bool {
+        if self.0[from] < amount {
+            return false;
+        }
+        self.0[from] -= amount;
+        self.0[to] += amount;
+        true
+    }
+
+    fn total(&self) -> u64 {
+        self.0.iter().sum()
+    }
+}
+
+/// A ledger that tasks transfer money through.
+pub trait Ledger: Send + Sync + 'static {
+    /// Opens `accounts` accounts, together with the task that has to run next
+    /// to the ledger for it to work.
+    fn open(accounts: usize) -> (Self, impl Future<Output = ()> + Send + 'static)
+    where
+        Self: Sized;
+
+    /// Moves `amount` from `from` to `to` and tells whether it was covered.
+    fn transfer(&self, from: usize, to: usize, amount: u64) -> impl Future<Output = bool> + Send;
+
+    /// The total of all balances.
+    fn total(&self) -> impl Future<Output = u64> + Send;
+}
+
+/// The ledger as state shared behind a mutex.
+pub struct Shared(Mutex<Accounts>);
+
+impl Ledger for Shared {
+    fn open(accounts: usize) -> (Self, impl Future<Output = ()> + Send + 'static) {
+        (Shared(Mutex::new(Accounts::open(accounts))), async {})
+    }
+
+    async fn transfer(&self, from: usize, to: usize, amount: u64) -> bool {
+        self.0.lock().unwrap().transfer(from, to, amount)
+    }
+
+    async fn total(&self) -> u64 {
+        self.0.lock().unwrap().total()
+    }
+}
+
+/// A command to the task owning the ledger.
+enum Command {
+    Transfer {
+        from: usize,
+        to: usize,
+        amount: u64,
+        covered: oneshot::Sender<bool>,
+    },
+    Total(oneshot::Sender<u64>),
+}
+
+/// The ledger owned by one task, reached through its commands.
+pub struct Owned {
+    commands: async_channel::Sender<Command>,
+}
+
+impl Ledger for Owned {
+    fn open(accounts: usize) -> (Self, impl Future<Output = ()> + Send + 'static) {
+        let (commands, received) = async_channel::bounded(1024);
+        let owner = async move {
+            let mut accounts = Accounts::open(accounts);
+            while let Ok(command) = received.recv().await {
+                match command {
+                    Command::Transfer {
+                        from,
+                        to,
+                        amount,
+                        covered,
+                    } => {
+                        let _ = covered.send(accounts.transfer(from, to, amount));
+                    }
+                    Command::Total(total) => {
+                        let _ = total.send(accounts.total());
+                    }
+                }
+            }
+        };
+        (Owned { commands }, owner)
+    }
+
+    async fn transfer(&self, from: usize, to: usize, amount: u64) -> bool {
+        let (covered, answer) = oneshot::channel();
+        let command = Command::Transfer {
+            from,
+            to,
+            amount,
+            covered,
+        };
+        self.commands.send(command).await.unwrap();
+        answer.await.unwrap()
+    }
+
+    async fn total(&self) -> u64 {
+        let (total, answer) = oneshot::channel();
+        self.commands.send(Command::Total(total)).await.unwrap();
+        answer.await.unwrap()
+    }
+}
+
+/// The transfers the tasks make.
+#[derive(Clone, Copy, Debug)]
+pub struct Transfers {
+    pub tasks: usize,
+    pub accounts: usize,
+    pub transfers: usize,
+}
+
+/// A load report over the transfers, with how many were refused.
+pub struct LedgerReport {
+    pub load: LoadReport,
+    pub refused: u64,
+}
+
+impl fmt::Display for LedgerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  refused {:>4.1}%",
+            self.load,
+            self.refused as f64 * 1e2 / self.load.requests as f64
+        )
+    }
+}
+
+impl LedgerReport {
+    fn new(start: Instant, tasks: Vec<(Vec<Duration>, u64)>) -> Self {
+        let mut latencies = latency_histogram();
+        let mut refused = 0;
+        for (task, task_refused) in tasks {
+            for latency in task {
+                record(&mut latencies, latency);
+            }
+            refused += task_refused;
+        }
+        let transfers = latencies.len() as usize;
+        LedgerReport {
+            load: LoadReport::since(start, transfers, latencies),
+            refused,
+        }
+    }
+}
+
+/// Makes `transfers` random transfers between `accounts` accounts of
+/// `ledger`, yielding with `yield_now` after each, and records how long each
+/// took and how many were refused.
+pub(crate) async fn transferring<L, F, Fut>(
+    ledger: Arc<L>,
+    accounts: usize,
+    transfers: usize,
+    seed: u64,
+    yield_now: F,
+) -> (Vec<Duration>, u64)
+where
+    L: Ledger,
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut rng = seed;
+    let mut latencies = Vec::with_capacity(transfers);
+    let mut refused = 0;
+    for _ in 0..transfers {
+        let random = xorshift(&mut rng);
+        let from = random as usize % accounts;
+        let to = (random >> 20) as usize % accounts;
+        let amount = (random >> 40) % (OPENING_BALANCE / 2);
+        let start = Instant::now();
+        if !ledger.transfer(from, to, amount).await {
+            refused += 1;
+        }
+        latencies.push(start.elapsed());
+        yield_now().await;
+    }
+    (latencies, refused)
+}
+
+/// Makes `transfers` through an `L` ledger from tokio tasks.
+pub fn on_tokio<L: Ledger>(transfers: Transfers) -> LedgerReport {
+    use tokio::task::yield_now;
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let (ledger, owner) = L::open(transfers.accounts);
+        let owner = tokio::spawn(owner);
+        let ledger = Arc::new(ledger);
+        let start = Instant::now();
+        let handles: Vec<_> = split(transfers.transfers, transfers.tasks)
+            .zip(1..)
+            .map(|(share, seed)| {
+                let task = transferring(ledger.clone(), transfers.accounts, share, seed, yield_now);
+                tokio::spawn(task)
+            })
+            .collect();
+        let mut tasks = Vec::with_capacity(transfers.tasks);
+        for handle in handles {
+            tasks.push(handle.await.unwrap());
+        }
+        let report = LedgerReport::new(start, tasks);
+        let expected = OPENING_BALANCE * transfers.accounts as u64;
+        assert_eq!(ledger.total().await, expected);
+        drop(ledger);
+        owner.await.unwrap();
+        report
+    })
+}
+
+/// Makes `transfers` through an `L` ledger from async_std tasks.
+pub fn on_async_std<L: Ledger>(transfers: Transfers) -> LedgerReport {
+    use async_std::task::{self, yield_now};
+
+    task::block_on(async {
+        let (ledger, owner) = L::open(transfers.accounts);
+        let owner = task::spawn(owner);
+        let ledger = Arc::new(ledger);
+        let start = Instant::now();
+        let handles: Vec<_> = split(transfers.transfers, transfers.tasks)
+            .zip(1..)
+            .map(|(share, seed)| {
+                let task = transferring(ledger.clone(), transfers.accounts, share, seed, yield_now);
+                task::spawn(task)
+            })
+            .collect();
+        let mut tasks = Vec::with_capacity(transfers.tasks);
+        for handle in handles {
+            tasks.push(handle.await);
+        }
+        let report = LedgerReport::new(start, tasks);
+        let expected = OPENING_BALANCE * transfers.accounts as u64;
+        assert_eq!(ledger.total().await, expected);
+        drop(ledger);
+        owner.await;
+        report
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn every_transfer_is_made<L: Ledger>() {
+        let transfers = Transfers {
+            tasks: 8,
+            accounts: 4,
+            transfers: 1_000,
+        };
+        assert_eq!(on_tokio::<L>(transfers).load.requests, 1_000);
+        assert_eq!(on_async_std::<L>(transfers).load.requests, 1_000);
+    }
+
+    #[test]
+    fn both_ledgers_keep_the_total() {
+        every_transfer_is_made::<Shared>();
+        every_transfer_is_made::<Owned>();
+    }
+}
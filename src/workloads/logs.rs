@@ -0,0 +1,258 @@
+//! Processing a large log file: reading it line by line, parsing the lines,
+//! counting them per service and writing a summary of the counts, on tokio,
+//! async_std and smol and with blocking std IO.
+//!
+//! Every variant reads through a buffered reader of the same capacity into one
+//! reused line buffer, so they do the same parsing and counting and differ in
+//! how the reads reach the file: tokio and async_std hand every read of the
+//! buffer to their blocking pools, smol to the threads of the `blocking` crate
+//! under async-fs, and the baseline reads directly. The file is read
+//! sequentially and mostly comes from the page cache, so the runtimes'
+//! per-read overhead is all that separates them.
+//!
+//! Peak memory is the largest growth of the resident set during the run;
+//! only the counts are kept, so it stays small however large the file.
+
+use crate::harness::{xorshift, PeakMemory};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Capacity of the buffered readers and writers.
+const BUFFER: usize = 64 * 1024;
+
+/// Services the log lines are attributed to.
+const SERVICES: &[&str] = &[
+    "auth",
+    "billing",
+    "catalog",
+    "checkout",
+    "gateway",
+    "inventory",
+    "search",
+    "shipping",
+];
+
+/// Levels of the log lines.
+const LEVELS: &[&str] = &["DEBUG", "INFO", "INFO", "INFO", "WARN", "ERROR"];
+
+/// How long processing the log took and the memory it needed.
+pub struct LogsReport {
+    pub bytes: u64,
+    pub lines: u64,
+    pub elapsed: Duration,
+    /// Largest growth of the resident set in bytes, or `None` if the resident
+    /// set size is not available on this platform.
+    pub peak_memory: Option<u64>,
+}
+
+impl fmt::Display for LogsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.elapsed.as_secs_f64();
+        write!(
+            f,
+            "{:>8.1} MiB/s  {:>10.0} lines/s  in {:>8.1}ms",
+            self.bytes as f64 / (1 << 20) as f64 / secs,
+            self.lines as f64 / secs,
+            secs * 1e3
+        )?;
+        match self.peak_memory {
+            Some(bytes) => write!(f, "  peak {:>7.1} MiB", bytes as f64 / (1 << 20) as f64),
+            None => write!(f, "  memory n/a"),
+        }
+    }
+}
+
+/// Writes about `bytes` bytes of log lines to `path`, ending with a full line.
+pub fn write_log(path: &Path, bytes: u64) {
+    let mut file = std::io::BufWriter::with_capacity(BUFFER, std::fs::File::create(path).unwrap());
+    let mut rng = 0x2545_f491_4f6c_dd1d;
+    let mut written = 0;
+    let mut line = 0u64;
+    while written < bytes {
+        let random = xorshift(&mut rng);
+        let text = format!(
+            "{:012} {} service={} status={} latency_us={} request={:016x}\n",
+            line,
+            LEVELS[random as usize % LEVELS.len()],
+            SERVICES[(random >> 8) as usize % SERVICES.len()],
+            [200, 200, 200, 201, 304, 404, 500][(random >> 16) as usize % 7],
+            (random >> 24) % 100_000,
+            random
+        );
+        file.write_all(text.as_bytes()).unwrap();
+        written += text.len() as u64;
+        line += 1;
+    }
+    file.flush().unwrap();
+}
+
+/// Line counts per service, with the lines that did not name one.
+#[derive(Default)]
+struct Counts {
+    lines: u64,
+    per_service: HashMap<String, u64>,
+    unparsed: u64,
+}
+
+impl Counts {
+    fn add(&mut self, line: &str) {
+        self.lines += 1;
+        let service = line
+            .split_ascii_whitespace()
+            .find_map(|field| field.strip_prefix("service="));
+        match service {
+            Some(service) => match self.per_service.get_mut(service) {
+                Some(count) => *count += 1,
+                None => {
+                    self.per_service.insert(service.to_owned(), 1);
+                }
+            },
+            None => self.unparsed += 1,
+        }
+    }
+
+    /// The summary written at the end, one service per line by name.
+    fn summary(&self) -> String {
+        let mut services: Vec<_> = self.per_service.iter().collect();
+        services.sort();
+        let mut summary = String::new();
+        for (service, count) in services {
+            summary += &format!("{} {}\n", service, count);
+        }
+        summary += &format!("unparsed {}\n", self.unparsed);
+        summary
+    }
+}
+
+/// Reports on a run that started at `start` and counted `counts`.
+fn report(log: &Path, start: Instant, peak: PeakMemory, counts: &Counts) -> LogsReport {
+    let elapsed = start.elapsed();
+    LogsReport {
+        bytes: std::fs::metadata(log).unwrap().len(),
+        lines: counts.lines,
+        elapsed,
+        peak_memory: peak.stop(),
+    }
+}
+
+/// Processes the log at `log` in a tokio task and writes the summary to
+/// `summary`.
+pub fn tokio(log: &Path, summary: &Path) -> LogsReport {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (log, summary) = (log.to_owned(), summary.to_owned());
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        tokio::spawn(async move {
+            let peak = PeakMemory::start();
+            let start = Instant::now();
+            let file = tokio::fs::File::open(&log).await.unwrap();
+            let mut reader = BufReader::with_capacity(BUFFER, file);
+            let mut counts = Counts::default();
+            let mut line = String::new();
+            while reader.read_line(&mut line).await.unwrap() > 0 {
+                counts.add(&line);
+                line.clear();
+            }
+            let mut out = tokio::fs::File::create(&summary).await.unwrap();
+            out.write_all(counts.summary().as_bytes()).await.unwrap();
+            out.flush().await.unwrap();
+            report(&log, start, peak, &counts)
+        })
+        .await
+        .unwrap()
+    })
+}
+
+/// Processes the log at `log` in an async_std task and writes the summary to
+/// `summary`.
+pub fn async_std(log: &Path, summary: &Path) -> LogsReport {
+    use async_std::io::{prelude::BufReadExt, BufReader, WriteExt};
+    use async_std::task;
+
+    let (log, summary) = (log.to_owned(), summary.to_owned());
+    task::block_on(task::spawn(async move {
+        let peak = PeakMemory::start();
+        let start = Instant::now();
+        let file = async_std::fs::File::open(&log).await.unwrap();
+        let mut reader = BufReader::with_capacity(BUFFER, file);
+        let mut counts = Counts::default();
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            counts.add(&line);
+            line.clear();
+        }
+        let mut out = async_std::fs::File::create(&summary).await.unwrap();
+        out.write_all(counts.summary().as_bytes()).await.unwrap();
+        out.flush().await.unwrap();
+        report(&log, start, peak, &counts)
+    }))
+}
+
+/// Processes the log at `log` in a smol task and writes the summary to
+/// `summary`.
+pub fn smol(log: &Path, summary: &Path) -> LogsReport {
+    use smol::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (log, summary) = (log.to_owned(), summary.to_owned());
+    smol::block_on(smol::spawn(async move {
+        let peak = PeakMemory::start();
+        let start = Instant::now();
+        let file = smol::fs::File::open(&log).await.unwrap();
+        let mut reader = BufReader::with_capacity(BUFFER, file);
+        let mut counts = Counts::default();
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            counts.add(&line);
+            line.clear();
+        }
+        let mut out = smol::fs::File::create(&summary).await.unwrap();
+        out.write_all(counts.summary().as_bytes()).await.unwrap();
+        out.flush().await.unwrap();
+        report(&log, start, peak, &counts)
+    }))
+}
+
+/// Processes the log at `log` with blocking std IO on the current thread and
+/// writes the summary to `summary`.
+pub fn std_io(log: &Path, summary: &Path) -> LogsReport {
+    use std::io::{BufRead, BufReader};
+
+    let peak = PeakMemory::start();
+    let start = Instant::now();
+    let file = std::fs::File::open(log).unwrap();
+    let mut reader = BufReader::with_capacity(BUFFER, file);
+    let mut counts = Counts::default();
+    let mut line = String::new();
+    while reader.read_line(&mut line).unwrap() > 0 {
+        counts.add(&line);
+        line.clear();
+    }
+    std::fs::write(summary, counts.summary()).unwrap();
+    report(log, start, peak, &counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_variant_writes_the_same_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        write_log(&log, 1 << 20);
+        let summary = dir.path().join("summary");
+        let expected = std_io(&log, &summary);
+        let expected_summary = std::fs::read_to_string(&summary).unwrap();
+        assert!(expected_summary.ends_with("unparsed 0\n"));
+        for run in [tokio, async_std, smol] {
+            std::fs::remove_file(&summary).unwrap();
+            let report = run(&log, &summary);
+            assert_eq!(report.lines, expected.lines);
+            assert_eq!(std::fs::read_to_string(&summary).unwrap(), expected_summary);
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Workloads: small end-to-end applications on each runtime, built from the
+//! pieces the other benchmarks measure one at a time.
+
+pub mod cache;
+pub mod ledger;
+pub mod logs;
+pub mod priority;
+pub mod replay;
+pub mod sqlite;
+pub mod thumbnails;
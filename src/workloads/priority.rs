@@ -0,0 +1,262 @@
+//! A work queue with two priority classes, flooded with low-priority items
+//! while high-priority ones trickle in, on tokio and async_std.
+//!
+//! One producer task keeps `backlog` low-priority items queued at all times,
+//! sending another whenever a worker is done with one, another sends a
+//! high-priority item every `interval`, and worker tasks take items off the
+//! queue and spin for `work` on each, yielding after every item as a worker
+//! doing IO on it would. The channels are unbounded, the
+//! backlog being limited by a semaphore instead, so that the high-priority
+//! producer never waits for room behind the flood. With
+//! [`Queue::Fifo`] both classes share one channel, so every high-priority
+//! item waits behind a full queue of low-priority ones. With
+//! [`Queue::Prioritized`] each class has a channel of its own and the
+//! workers look at the high-priority one first, so such an item only waits
+//! for a worker to finish what it is doing. The latency of a high-priority
+//! item is from its send until a worker finished it.
+
+use crate::harness::{latency_histogram, record, Millis};
+use async_channel::{Receiver, Sender};
+use async_lock::{Semaphore, SemaphoreGuardArc};
+use futures::future::{self, Either};
+use hdrhistogram::Histogram;
+use std::fmt;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How the two classes of items are queued.
+#[derive(Clone, Copy, Debug)]
+pub enum Queue {
+    /// In one channel, in the order they were sent.
+    Fifo,
+    /// In a channel per class, the high-priority one taken from first.
+    Prioritized,
+}
+
+/// The workers and the items they are flooded with.
+#[derive(Clone, Copy, Debug)]
+pub struct Flood {
+    pub workers: usize,
+    /// Number of low-priority items queued at all times.
+    pub backlog: usize,
+    /// Time a worker spins for on every item.
+    pub work: Duration,
+    /// Number of high-priority items sent per run.
+    pub high: usize,
+    /// Time between two high-priority items.
+    pub interval: Duration,
+}
+
+/// An item of work, with the time it was sent if it has high priority or its
+/// place in the backlog otherwise.
+pub(crate) struct Item {
+    high: Option<Instant>,
+    _place: Option<SemaphoreGuardArc>,
+}
+
+/// How long the high-priority items took and how many low-priority ones were
+/// done meanwhile.
+pub struct PriorityReport {
+    pub high: Histogram<u64>,
+    pub low: u64,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for PriorityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "high p50 {}  p99 {}  max {}  low {:>9.0}/s",
+            Millis(self.high.value_at_quantile(0.5)),
+            Millis(self.high.value_at_quantile(0.99)),
+            Millis(self.high.max()),
+            self.low as f64 / self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+/// Takes the next item off the queues, from `high` if it has one, and
+/// returns `None` once both are closed and empty.
+async fn next(high: &Receiver<Item>, low: &Receiver<Item>) -> Option<Item> {
+    if let Ok(item) = high.try_recv() {
+        return Some(item);
+    }
+    if let Ok(item) = low.try_recv() {
+        return Some(item);
+    }
+    match future::select(pin!(high.recv()), pin!(low.recv())).await {
+        Either::Left((Ok(item), _)) | Either::Right((Ok(item), _)) => Some(item),
+        Either::Left((Err(_), low)) => low.await.ok(),
+        Either::Right((Err(_), high)) => high.await.ok(),
+    }
+}
+
+/// Works through the items of the queues until they are closed, yielding with
+/// `yield_now` after every item, and returns the latencies of the
+/// high-priority items and the number of low-priority ones.
+pub(crate) async fn working<F, Fut>(
+    high: Receiver<Item>,
+    low: Receiver<Item>,
+    work: Duration,
+    yield_now: F,
+) -> (Vec<Duration>, u64)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut latencies = Vec::new();
+    let mut done = 0;
+    while let Some(item) = next(&high, &low).await {
+        let spun = Instant::now();
+        while spun.elapsed() < work {
+            std::hint::spin_loop();
+        }
+        match item.high {
+            Some(sent) => latencies.push(sent.elapsed()),
+            None => done += 1,
+        }
+        drop(item);
+        yield_now().await;
+    }
+    (latencies, done)
+}
+
+/// Keeps `backlog` low-priority items queued in `low` until `stop` is set.
+async fn flooding(low: Sender<Item>, backlog: usize, stop: Arc<AtomicBool>) {
+    let places = Arc::new(Semaphore::new(backlog));
+    while !stop.load(Ordering::Relaxed) {
+        let item = Item {
+            high: None,
+            _place: Some(places.acquire_arc().await),
+        };
+        low.send(item).await.unwrap();
+    }
+}
+
+/// Sends the high-priority items of `flood` into `high`, sleeping with
+/// `sleep` between them, and sets `stop` afterwards.
+pub(crate) async fn trickling<S, Fut>(
+    high: Sender<Item>,
+    flood: Flood,
+    stop: Arc<AtomicBool>,
+    sleep: S,
+) where
+    S: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    for _ in 0..flood.high {
+        sleep(flood.interval).await;
+        let item = Item {
+            high: Some(Instant::now()),
+            _place: None,
+        };
+        high.send(item).await.unwrap();
+    }
+    stop.store(true, Ordering::Relaxed);
+}
+
+/// The senders and receivers of both classes as `queue` says.
+type Channels = (
+    (Sender<Item>, Receiver<Item>),
+    (Sender<Item>, Receiver<Item>),
+);
+
+fn channels(queue: Queue) -> Channels {
+    match queue {
+        Queue::Fifo => {
+            let shared = async_channel::unbounded();
+            (shared.clone(), shared)
+        }
+        Queue::Prioritized => (async_channel::unbounded(), async_channel::unbounded()),
+    }
+}
+
+impl PriorityReport {
+    fn new(start: Instant, workers: Vec<(Vec<Duration>, u64)>) -> Self {
+        let elapsed = start.elapsed();
+        let mut high = latency_histogram();
+        let mut low = 0;
+        for (latencies, done) in workers {
+            for latency in latencies {
+                record(&mut high, latency);
+            }
+            low += done;
+        }
+        PriorityReport { high, low, elapsed }
+    }
+}
+
+/// Floods tokio workers with items queued as `queue` says.
+pub fn tokio(queue: Queue, flood: Flood) -> PriorityReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let ((high, high_received), (low, low_received)) = channels(queue);
+        let stop = Arc::new(AtomicBool::new(false));
+        let start = Instant::now();
+        let workers: Vec<_> = (0..flood.workers)
+            .map(|_| {
+                let (high, low) = (high_received.clone(), low_received.clone());
+                tokio::spawn(working(high, low, flood.work, tokio::task::yield_now))
+            })
+            .collect();
+        drop((high_received, low_received));
+        tokio::spawn(flooding(low, flood.backlog, stop.clone()));
+        tokio::spawn(trickling(high, flood, stop, tokio::time::sleep));
+        let mut done = Vec::with_capacity(flood.workers);
+        for worker in workers {
+            done.push(worker.await.unwrap());
+        }
+        PriorityReport::new(start, done)
+    })
+}
+
+/// Floods async_std workers with items queued as `queue` says.
+pub fn async_std(queue: Queue, flood: Flood) -> PriorityReport {
+    use async_std::task;
+
+    task::block_on(async {
+        let ((high, high_received), (low, low_received)) = channels(queue);
+        let stop = Arc::new(AtomicBool::new(false));
+        let start = Instant::now();
+        let workers: Vec<_> = (0..flood.workers)
+            .map(|_| {
+                let (high, low) = (high_received.clone(), low_received.clone());
+                task::spawn(working(high, low, flood.work, task::yield_now))
+            })
+            .collect();
+        drop((high_received, low_received));
+        task::spawn(flooding(low, flood.backlog, stop.clone()));
+        task::spawn(trickling(high, flood, stop, task::sleep));
+        let mut done = Vec::with_capacity(flood.workers);
+        for worker in workers {
+            done.push(worker.await);
+        }
+        PriorityReport::new(start, done)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_high_priority_item_is_done() {
+        let flood = Flood {
+            workers: 2,
+            backlog: 64,
+            work: Duration::from_micros(5),
+            high: 20,
+            interval: Duration::from_millis(1),
+        };
+        for run in [tokio, async_std] {
+            for queue in [Queue::Fifo, Queue::Prioritized] {
+                let report = run(queue, flood);
+                assert_eq!(report.high.len(), 20);
+                assert!(report.low > 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Replaying a recorded trace of operations, exported from production logs
+//! for instance, on tokio, async_std and smol, so that runtimes are compared
+//! on the traffic shape of their users rather than on constant rates.
+//!
+//! A trace is JSON Lines, one operation per line, in the order they were
+//! recorded:
+//!
+//! ```text
+//! {"at_us": 0, "op": "read", "size": 4096}
+//! {"at_us": 150, "op": "write", "size": 512}
+//! {"at_us": 900, "op": "sleep", "us": 2000}
+//! {"at_us": 1000, "op": "compute", "us": 50}
+//! ```
+//!
+//! `at_us` is when the operation started, in microseconds from the start of
+//! the trace. `read` and `write` read and write `size` bytes of a file,
+//! `sleep` waits on the runtime's timer for `us` microseconds and `compute`
+//! spins on the CPU for as long. Fields an operation does not use may be
+//! left out, and fields the schema does not know are ignored.
+//!
+//! Every operation is spawned as a task of its own once it is due, sped up
+//! by a factor, whether the earlier ones finished or not, like the
+//! open-loop scenarios of [`crate::open_loop`], and its latency counts from
+//! when it was due, so a runtime that falls behind the trace is charged for
+//! it.
+
+use crate::harness::{latency_histogram, record, LoadReport, Millis};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use hdrhistogram::Histogram;
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Files written by the trace, in turn, so that a long trace does not leave
+/// a file per write behind.
+const WRITE_FILES: usize = 64;
+
+/// The kinds of operation a trace holds.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    Read,
+    Write,
+    Sleep,
+    Compute,
+}
+
+impl Op {
+    const ALL: [Op; 4] = [Op::Read, Op::Write, Op::Sleep, Op::Compute];
+
+    fn name(self) -> &'static str {
+        match self {
+            Op::Read => "read",
+            Op::Write => "write",
+            Op::Sleep => "sleep",
+            Op::Compute => "compute",
+        }
+    }
+}
+
+/// One line of a trace.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct Operation {
+    pub at_us: u64,
+    pub op: Op,
+    /// Bytes read or written.
+    #[serde(default)]
+    pub size: usize,
+    /// Microseconds slept or spent computing.
+    #[serde(default)]
+    pub us: u64,
+}
+
+/// The operations of a trace, in the order they are due.
+pub struct Trace {
+    pub operations: Vec<Operation>,
+}
+
+impl Trace {
+    /// Parses a trace, naming the first line that does not fit the schema.
+    pub fn parse(jsonl: &str) -> Result<Self, String> {
+        let mut operations = Vec::new();
+        for (i, line) in jsonl.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let operation: Operation =
+                serde_json::from_str(line).map_err(|err| format!("line {}: {}", i + 1, err))?;
+            operations.push(operation);
+        }
+        operations.sort_by_key(|operation| operation.at_us);
+        Ok(Trace { operations })
+    }
+
+    /// The largest read of the trace, which the file read from must hold.
+    pub fn largest_read(&self) -> usize {
+        self.operations
+            .iter()
+            .filter(|operation| operation.op == Op::Read)
+            .map(|operation| operation.size)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// How a replay kept up with the trace: throughput and latencies from when
+/// the operations were due, over all of them and per kind.
+pub struct ReplayReport {
+    pub load: LoadReport,
+    /// Time the trace spans, sped up.
+    pub span: Duration,
+    pub kinds: Vec<(Op, Histogram<u64>)>,
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  span {}",
+            self.load,
+            Millis(self.span.as_nanos() as u64)
+        )?;
+        for (op, latencies) in &self.kinds {
+            write!(
+                f,
+                "  {} p99 {}",
+                op.name(),
+                Millis(latencies.value_at_quantile(0.99))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// What a replay needs of a runtime.
+struct Runtime {
+    sleep: fn(Duration) -> BoxFuture<'static, ()>,
+    read: fn(PathBuf, usize) -> BoxFuture<'static, ()>,
+    write: fn(PathBuf, usize) -> BoxFuture<'static, ()>,
+    /// Spawns a task and waits for what it returns.
+    spawn: fn(BoxFuture<'static, Duration>) -> BoxFuture<'static, Duration>,
+}
+
+/// Writes the file the operations of `trace` read from, in `dir`.
+pub fn prepare(trace: &Trace, dir: &Path) {
+    std::fs::write(dir.join("read"), vec![1; trace.largest_read()]).unwrap();
+}
+
+async fn replay(trace: &Trace, dir: &Path, speed: f64, runtime: Runtime) -> ReplayReport {
+    let start = Instant::now();
+    let mut running = Vec::with_capacity(trace.operations.len());
+    for (i, &operation) in trace.operations.iter().enumerate() {
+        let due = start + Duration::from_micros(operation.at_us).div_f64(speed);
+        let now = Instant::now();
+        if due > now {
+            (runtime.sleep)(due - now).await;
+        }
+        let work = match operation.op {
+            Op::Read => (runtime.read)(dir.join("read"), operation.size),
+            Op::Write => {
+                let path = dir.join(format!("write-{}", i % WRITE_FILES));
+                (runtime.write)(path, operation.size)
+            }
+            Op::Sleep => (runtime.sleep)(Duration::from_micros(operation.us)),
+            Op::Compute => {
+                let spin = Duration::from_micros(operation.us);
+                async move {
+                    let spun = Instant::now();
+                    while spun.elapsed() < spin {
+                        std::hint::spin_loop();
+                    }
+                }
+                .boxed()
+            }
+        };
+        let timed = async move {
+            work.await;
+            due.elapsed()
+        };
+        running.push((operation.op, (runtime.spawn)(timed.boxed())));
+    }
+    let mut latencies = latency_histogram();
+    let mut kinds: Vec<_> = Op::ALL
+        .iter()
+        .map(|&op| (op, latency_histogram()))
+        .collect();
+    for (op, latency) in running {
+        let latency = latency.await;
+        record(&mut latencies, latency);
+        let (_, of_kind) = kinds.iter_mut().find(|(kind, _)| *kind == op).unwrap();
+        record(of_kind, latency);
+    }
+    kinds.retain(|(_, latencies)| !latencies.is_empty());
+    let span = trace.operations.last().map_or(Duration::ZERO, |last| {
+        Duration::from_micros(last.at_us).div_f64(speed)
+    });
+    ReplayReport {
+        load: LoadReport::since(start, trace.operations.len(), latencies),
+        span,
+        kinds,
+    }
+}
+
+/// Replays `trace` sped up by `speed` on tokio, with its files in `dir`.
+pub fn tokio(trace: &Trace, dir: &Path, speed: f64) -> ReplayReport {
+    let rt = crate::metrics::runtime();
+    rt.block_on(replay(
+        trace,
+        dir,
+        speed,
+        Runtime {
+            sleep: |duration| tokio::time::sleep(duration).boxed(),
+            read: |path, size| {
+                async move {
+                    use tokio::io::AsyncReadExt;
+                    let mut file = tokio::fs::File::open(path).await.unwrap();
+                    file.read_exact(&mut vec![0; size]).await.unwrap();
+                }
+                .boxed()
+            },
+            write: |path, size| {
+                async move {
+                    tokio::fs::write(path, vec![1; size]).await.unwrap();
+                }
+                .boxed()
+            },
+            spawn: |task| tokio::spawn(task).map(Result::unwrap).boxed(),
+        },
+    ))
+}
+
+/// Replays `trace` sped up by `speed` on async_std, with its files in `dir`.
+pub fn async_std(trace: &Trace, dir: &Path, speed: f64) -> ReplayReport {
+    async_std::task::block_on(replay(
+        trace,
+        dir,
+        speed,
+        Runtime {
+            sleep: |duration| async_std::task::sleep(duration).boxed(),
+            read: |path, size| {
+                async move {
+                    use async_std::io::ReadExt;
+                    let mut file = async_std::fs::File::open(path).await.unwrap();
+                    file.read_exact(&mut vec![0; size]).await.unwrap();
+                }
+                .boxed()
+            },
+            write: |path, size| {
+                async move {
+                    async_std::fs::write(path, vec![1; size]).await.unwrap();
+                }
+                .boxed()
+            },
+            spawn: |task| async_std::task::spawn(task).boxed(),
+        },
+    ))
+}
+
+/// Replays `trace` sped up by `speed` on smol, with its files in `dir`.
+pub fn smol(trace: &Trace, dir: &Path, speed: f64) -> ReplayReport {
+    smol::block_on(replay(
+        trace,
+        dir,
+        speed,
+        Runtime {
+            sleep: |duration| {
+                async move {
+                    smol::Timer::after(duration).await;
+                }
+                .boxed()
+            },
+            read: |path, size| {
+                async move {
+                    use smol::io::AsyncReadExt;
+                    let mut file = smol::fs::File::open(path).await.unwrap();
+                    file.read_exact(&mut vec![0; size]).await.unwrap();
+                }
+                .boxed()
+            },
+            write: |path, size| {
+                async move {
+                    smol::fs::write(path, vec![1; size]).await.unwrap();
+                }
+                .boxed()
+            },
+            spawn: |task| smol::spawn(task).boxed(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACE: &str = r#"{"at_us": 0, "op": "read", "size": 4096}
+{"at_us": 150, "op": "write", "size": 512, "service": "auth"}
+
+{"at_us": 100, "op": "sleep", "us": 2000}
+{"at_us": 1000, "op": "compute", "us": 50}
+"#;
+
+    #[test]
+    fn parses_the_schema_in_order() {
+        let trace = Trace::parse(TRACE).unwrap();
+        let ops: Vec<_> = trace
+            .operations
+            .iter()
+            .map(|operation| operation.op)
+            .collect();
+        assert_eq!(ops, [Op::Read, Op::Sleep, Op::Write, Op::Compute]);
+        assert_eq!(trace.operations[1].us, 2000);
+        assert_eq!(trace.largest_read(), 4096);
+        let err = Trace::parse("{\"at_us\": 0, \"op\": \"read\"}\n{\"op\": \"fly\"}").err();
+        assert!(err.unwrap().starts_with("line 2:"));
+    }
+
+    #[test]
+    fn every_runtime_replays_every_operation() {
+        let trace = Trace::parse(TRACE).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        prepare(&trace, dir.path());
+        for report in [
+            tokio(&trace, dir.path(), 1.0),
+            async_std(&trace, dir.path(), 1.0),
+            smol(&trace, dir.path(), 1.0),
+        ] {
+            assert_eq!(report.load.requests, 4);
+            assert_eq!(report.kinds.len(), 4);
+        }
+    }
+}
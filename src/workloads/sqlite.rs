@@ -0,0 +1,260 @@
+//! Inserts and selects against a SQLite database file, through sqlx on tokio
+//! and async_std and through rusqlite on threads.
+//!
+//! Before every run the database is created afresh with one indexed table of
+//! `rows` rows, in WAL mode with `synchronous = NORMAL`, as an application
+//! would set it up. Tasks on the runtimes share a sqlx pool with a connection
+//! per task; the baseline has a thread per task, each with its own rusqlite
+//! connection. Every transaction is a single statement: with probability
+//! `writes` an insert of a row with a random key, otherwise a select of one
+//! row by a random key. SQLite admits one writer at a time and the others
+//! wait for it in the busy handler, so writes serialize however many
+//! connections there are.
+//!
+//! sqlx runs every SQLite connection on a thread of its own and talks to it
+//! over channels, since SQLite itself only has a blocking API, so on the
+//! runtimes each statement also costs a round trip to that thread.
+
+use crate::harness::{latency_histogram, record, split, xorshift, LoadReport};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// The transactions to run against the database.
+#[derive(Clone, Copy, Debug)]
+pub struct Mix {
+    /// Tasks or threads running transactions, each with its own connection.
+    pub tasks: usize,
+    /// Rows in the table before the run, and the range of the keys.
+    pub rows: u64,
+    /// Probability of a transaction being an insert.
+    pub writes: f64,
+    pub transactions: usize,
+}
+
+/// A transaction of the mix, drawn with the xorshift state `rng`.
+enum Transaction {
+    Insert { key: i64, value: i64 },
+    Select { key: i64 },
+}
+
+impl Transaction {
+    fn draw(mix: Mix, rng: &mut u64) -> Self {
+        let random = xorshift(rng);
+        let key = (random % mix.rows) as i64;
+        if (random >> 32) as f64 / u32::MAX as f64 >= mix.writes {
+            Transaction::Select { key }
+        } else {
+            Transaction::Insert {
+                key,
+                value: (random >> 8) as i64,
+            }
+        }
+    }
+}
+
+const INSERT: &str = "INSERT INTO items (key, value) VALUES (?1, ?2)";
+
+const SELECT: &str = "SELECT value FROM items WHERE key = ?1 LIMIT 1";
+
+/// Creates the database at `path` afresh with `rows` rows.
+fn create(path: &Path, rows: u64) {
+    for suffix in &["", "-wal", "-shm"] {
+        let mut file = path.as_os_str().to_owned();
+        file.push(suffix);
+        let _ = std::fs::remove_file(file);
+    }
+    let mut db = rusqlite::Connection::open(path).unwrap();
+    db.pragma_update(None, "journal_mode", "WAL").unwrap();
+    db.execute_batch(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, key INTEGER NOT NULL, value INTEGER NOT NULL);
+         CREATE INDEX items_key ON items (key);",
+    )
+    .unwrap();
+    let fill = db.transaction().unwrap();
+    {
+        let mut insert = fill.prepare(INSERT).unwrap();
+        for key in 0..rows as i64 {
+            insert.execute((key, key)).unwrap();
+        }
+    }
+    fill.commit().unwrap();
+}
+
+/// A load report over the transactions of all tasks.
+fn report(start: Instant, tasks: Vec<Vec<Duration>>) -> LoadReport {
+    let mut latencies = latency_histogram();
+    let mut transactions = 0;
+    for latency in tasks.into_iter().flatten() {
+        record(&mut latencies, latency);
+        transactions += 1;
+    }
+    LoadReport::since(start, transactions, latencies)
+}
+
+/// Connects a sqlx pool with a connection per task of `mix` to the database
+/// at `path`.
+async fn pool(path: &Path, mix: Mix) -> sqlx::SqlitePool {
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqliteSynchronous};
+
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .synchronous(SqliteSynchronous::Normal);
+    SqlitePoolOptions::new()
+        .min_connections(mix.tasks as u32)
+        .max_connections(mix.tasks as u32)
+        .connect_with(options)
+        .await
+        .unwrap()
+}
+
+/// Runs `transactions` transactions of `mix` through `pool` and records how
+/// long each took.
+async fn transacting(
+    pool: sqlx::SqlitePool,
+    mix: Mix,
+    transactions: usize,
+    seed: u64,
+) -> Vec<Duration> {
+    let mut rng = seed;
+    let mut latencies = Vec::with_capacity(transactions);
+    for _ in 0..transactions {
+        let transaction = Transaction::draw(mix, &mut rng);
+        let start = Instant::now();
+        match transaction {
+            Transaction::Insert { key, value } => {
+                sqlx::query(INSERT)
+                    .bind(key)
+                    .bind(value)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+            }
+            Transaction::Select { key } => {
+                let row: Option<(i64,)> = sqlx::query_as(SELECT)
+                    .bind(key)
+                    .fetch_optional(&pool)
+                    .await
+                    .unwrap();
+                assert!(row.is_some());
+            }
+        }
+        latencies.push(start.elapsed());
+    }
+    latencies
+}
+
+/// Runs `mix` against a new database at `path` through sqlx from tokio tasks.
+pub fn tokio(path: &Path, mix: Mix) -> LoadReport {
+    create(path, mix.rows);
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let pool = pool(path, mix).await;
+        let start = Instant::now();
+        let handles: Vec<_> = split(mix.transactions, mix.tasks)
+            .zip(1..)
+            .map(|(share, seed)| tokio::spawn(transacting(pool.clone(), mix, share, seed)))
+            .collect();
+        let mut latencies = Vec::with_capacity(mix.tasks);
+        for handle in handles {
+            latencies.push(handle.await.unwrap());
+        }
+        let report = report(start, latencies);
+        pool.close().await;
+        report
+    })
+}
+
+/// Runs `mix` against a new database at `path` through sqlx from async_std
+/// tasks.
+pub fn async_std(path: &Path, mix: Mix) -> LoadReport {
+    use async_std::task;
+
+    create(path, mix.rows);
+    task::block_on(async {
+        let pool = pool(path, mix).await;
+        let start = Instant::now();
+        let handles: Vec<_> = split(mix.transactions, mix.tasks)
+            .zip(1..)
+            .map(|(share, seed)| task::spawn(transacting(pool.clone(), mix, share, seed)))
+            .collect();
+        let mut latencies = Vec::with_capacity(mix.tasks);
+        for handle in handles {
+            latencies.push(handle.await);
+        }
+        let report = report(start, latencies);
+        pool.close().await;
+        report
+    })
+}
+
+/// Runs `mix` against a new database at `path` through rusqlite from a
+/// thread per task.
+pub fn rusqlite_threads(path: &Path, mix: Mix) -> LoadReport {
+    use std::thread;
+
+    create(path, mix.rows);
+    let connections: Vec<_> = (0..mix.tasks)
+        .map(|_| {
+            let db = rusqlite::Connection::open(path).unwrap();
+            db.pragma_update(None, "synchronous", "NORMAL").unwrap();
+            db
+        })
+        .collect();
+    let start = Instant::now();
+    let latencies = thread::scope(|scope| {
+        let handles: Vec<_> = split(mix.transactions, mix.tasks)
+            .zip(connections)
+            .zip(1..)
+            .map(|((transactions, db), seed)| {
+                scope.spawn(move || {
+                    let mut insert = db.prepare_cached(INSERT).unwrap();
+                    let mut select = db.prepare_cached(SELECT).unwrap();
+                    let mut rng = seed;
+                    let mut latencies = Vec::with_capacity(transactions);
+                    for _ in 0..transactions {
+                        let transaction = Transaction::draw(mix, &mut rng);
+                        let start = Instant::now();
+                        match transaction {
+                            Transaction::Insert { key, value } => {
+                                insert.execute((key, value)).unwrap();
+                            }
+                            Transaction::Select { key } => {
+                                let value: i64 =
+                                    select.query_row((key,), |row| row.get(0)).unwrap();
+                                std::hint::black_box(value);
+                            }
+                        }
+                        latencies.push(start.elapsed());
+                    }
+                    latencies
+                })
+            })
+            .collect();
+        let latencies: Vec<_> = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        latencies
+    });
+    report(start, latencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_transaction_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db");
+        let mix = Mix {
+            tasks: 4,
+            rows: 100,
+            writes: 0.2,
+            transactions: 200,
+        };
+        for run in [tokio, async_std, rusqlite_threads] {
+            assert_eq!(run(&path, mix).requests, 200);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Generating thumbnails: reading image-sized blobs from files, scaling them
+//! down and blurring them with the image crate, and writing the results, on
+//! tokio and async_std with a bounded number of images in flight, and with
+//! rayon and std IO.
+//!
+//! The blobs are raw RGB pixels, so that decoding and encoding take no part
+//! and the transform is all of the CPU work: a triangle-filtered resize to
+//! [`THUMBNAIL_WIDTH`] pixels wide and a Gaussian blur. On the runtimes the
+//! files are read and written with the runtime's fs and the transform runs
+//! in its `spawn_blocking`, at most `concurrency` images at a time through
+//! [`drive`]; the baseline runs the same steps in a rayon pool of
+//! `concurrency` threads. Latencies are those of whole images, from the read
+//! until the thumbnail is written, and bandwidth is that of the blobs read.
+//!
+//! Every read, write and transform takes a thread of the runtime's blocking
+//! pool, so tokio takes the most threads its pool may have as
+//! `blocking_threads`. async_std's pool is the process-wide one of the
+//! blocking crate, which sizes itself once from the `BLOCKING_MAX_THREADS`
+//! environment variable, so it can only be swept across processes.
+
+use crate::harness::{drive, latency_histogram, record, xorshift, LoadReport};
+use image::imageops::{self, FilterType};
+use image::RgbImage;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Width of the thumbnails in pixels; their height keeps the aspect ratio.
+pub const THUMBNAIL_WIDTH: u32 = 256;
+
+/// Standard deviation of the blur of the thumbnails, in pixels.
+const BLUR_SIGMA: f32 = 1.5;
+
+/// The blobs to make thumbnails of.
+#[derive(Clone, Copy, Debug)]
+pub struct Images {
+    pub count: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Images {
+    fn bytes(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height) * 3
+    }
+}
+
+fn source(dir: &Path, image: usize) -> PathBuf {
+    dir.join(format!("image-{}.rgb", image))
+}
+
+fn thumbnail_path(dir: &Path, image: usize) -> PathBuf {
+    dir.join(format!("thumbnail-{}.rgb", image))
+}
+
+/// Writes `images.count` blobs of random-looking pixels to `dir`.
+pub fn write_images(dir: &Path, images: Images) {
+    let mut rng = 0x853c_49e6_748f_ea9b_u64;
+    for image in 0..images.count {
+        let pixels: Vec<u8> = (0..images.bytes())
+            .map(|_| xorshift(&mut rng) as u8)
+            .collect();
+        let mut file = std::fs::File::create(source(dir, image)).unwrap();
+        file.write_all(&pixels).unwrap();
+    }
+}
+
+/// Scales the raw RGB `pixels` of an image of `images` down to a thumbnail
+/// and blurs it, returning the raw pixels of the thumbnail.
+fn transform(images: Images, pixels: Vec<u8>) -> Vec<u8> {
+    let image = RgbImage::from_raw(images.width, images.height, pixels).unwrap();
+    let height = THUMBNAIL_WIDTH * images.height / images.width;
+    let small = imageops::resize(&image, THUMBNAIL_WIDTH, height, FilterType::Triangle);
+    imageops::blur(&small, BLUR_SIGMA).into_raw()
+}
+
+/// Makes thumbnails of the `images` in `dir` from tokio tasks, at most
+/// `concurrency` at a time, with up to `blocking_threads` blocking threads.
+pub fn tokio(
+    dir: &Path,
+    images: Images,
+    concurrency: usize,
+    blocking_threads: usize,
+) -> LoadReport {
+    let dir = dir.to_owned();
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .max_blocking_threads(blocking_threads)
+        .enable_all()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let run = async move {
+            let mut next = 0;
+            let report = drive(concurrency, images.count, || {
+                let image = next;
+                next += 1;
+                let dir = dir.clone();
+                async move {
+                    let pixels = tokio::fs::read(source(&dir, image)).await.unwrap();
+                    let thumbnail = tokio::task::spawn_blocking(move || transform(images, pixels))
+                        .await
+                        .unwrap();
+                    tokio::fs::write(thumbnail_path(&dir, image), thumbnail)
+                        .await
+                        .unwrap();
+                }
+            })
+            .await;
+            report.with_bytes(images.bytes() * images.count as u64)
+        };
+        tokio::spawn(run).await.unwrap()
+    })
+}
+
+/// Makes thumbnails of the `images` in `dir` from async_std tasks, at most
+/// `concurrency` at a time.
+pub fn async_std(dir: &Path, images: Images, concurrency: usize) -> LoadReport {
+    use async_std::task;
+
+    let dir = dir.to_owned();
+    task::block_on(task::spawn(async move {
+        let mut next = 0;
+        let report = drive(concurrency, images.count, || {
+            let image = next;
+            next += 1;
+            let dir = dir.clone();
+            async move {
+                let pixels = async_std::fs::read(source(&dir, image)).await.unwrap();
+                let thumbnail = task::spawn_blocking(move || transform(images, pixels)).await;
+                async_std::fs::write(thumbnail_path(&dir, image), thumbnail)
+                    .await
+                    .unwrap();
+            }
+        })
+        .await;
+        report.with_bytes(images.bytes() * images.count as u64)
+    }))
+}
+
+/// Makes thumbnails of the `images` in `dir` in a rayon pool of
+/// `concurrency` threads with std IO.
+pub fn rayon(dir: &Path, images: Images, concurrency: usize) -> LoadReport {
+    use rayon::prelude::*;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(concurrency)
+        .build()
+        .unwrap();
+    let start = Instant::now();
+    let timings: Vec<_> = pool.install(|| {
+        (0..images.count)
+            .into_par_iter()
+            .map(|image| {
+                let started = Instant::now();
+                let pixels = std::fs::read(source(dir, image)).unwrap();
+                let thumbnail = transform(images, pixels);
+                std::fs::write(thumbnail_path(dir, image), thumbnail).unwrap();
+                started.elapsed()
+            })
+            .collect()
+    });
+    let mut latencies = latency_histogram();
+    for latency in timings {
+        record(&mut latencies, latency);
+    }
+    LoadReport::since(start, images.count, latencies)
+        .with_bytes(images.bytes() * images.count as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_image_gets_a_thumbnail() {
+        let dir = tempfile::tempdir().unwrap();
+        let images = Images {
+            count: 4,
+            width: 512,
+            height: 384,
+        };
+        write_images(dir.path(), images);
+        let runs: [fn(&Path, Images, usize) -> LoadReport; 3] = [
+            |dir, images, concurrency| tokio(dir, images, concurrency, 1),
+            async_std,
+            rayon,
+        ];
+        for run in runs {
+            assert_eq!(run(dir.path(), images, 2).requests, 4);
+            for image in 0..images.count {
+                let path = thumbnail_path(dir.path(), image);
+                let thumbnail = std::fs::read(&path).unwrap();
+                assert_eq!(thumbnail.len(), 256 * 192 * 3);
+                std::fs::remove_file(path).unwrap();
+            }
+        }
+    }
+}
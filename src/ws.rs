@@ -0,0 +1,139 @@
+//! WebSocket echo over loopback: tokio-tungstenite on tokio and
+//! async-tungstenite on async_std.
+//!
+//! Both wrap the same tungstenite protocol implementation, so the echo and
+//! ping-pong loops are shared and only the socket plumbing differs.
+
+use crate::harness::{latency_histogram, record, split, LoadReport};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use hdrhistogram::Histogram;
+use std::time::Instant;
+use tokio_tungstenite::tungstenite::{Error, Message};
+
+/// URL the clients ask for during the handshake; the servers ignore it.
+const URL: &str = "ws://localhost/";
+
+/// Echoes `frames` binary frames of `frame_size` bytes over `connections` connections on tokio.
+pub fn tokio(connections: usize, frames: usize, frame_size: usize) -> LoadReport {
+    use tokio::net::{TcpListener, TcpStream};
+
+    let rt = crate::metrics::runtime();
+    rt.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                tokio::spawn(async move {
+                    if let Ok(ws) = tokio_tungstenite::accept_async(stream).await {
+                        echo(ws).await;
+                    }
+                });
+            }
+        });
+
+        let start = Instant::now();
+        let clients: Vec<_> = split(frames, connections)
+            .map(|share| {
+                tokio::spawn(async move {
+                    let stream = TcpStream::connect(addr).await.unwrap();
+                    stream.set_nodelay(true).unwrap();
+                    let (ws, _) = tokio_tungstenite::client_async(URL, stream).await.unwrap();
+                    ping_pong(ws, share, frame_size).await
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for client in clients {
+            latencies.add(client.await.unwrap()).unwrap();
+        }
+        LoadReport::since(start, frames, latencies).with_bytes((frames * frame_size) as u64)
+    })
+}
+
+/// Echoes `frames` binary frames of `frame_size` bytes over `connections` connections on async_std.
+pub fn async_std(connections: usize, frames: usize, frame_size: usize) -> LoadReport {
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept = task::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                task::spawn(async move {
+                    if let Ok(ws) = async_tungstenite::accept_async(stream).await {
+                        echo(ws).await;
+                    }
+                });
+            }
+        });
+
+        let start = Instant::now();
+        let clients: Vec<_> = split(frames, connections)
+            .map(|share| {
+                task::spawn(async move {
+                    let stream = TcpStream::connect(addr).await.unwrap();
+                    stream.set_nodelay(true).unwrap();
+                    let (ws, _) = async_tungstenite::client_async(URL, stream).await.unwrap();
+                    ping_pong(ws, share, frame_size).await
+                })
+            })
+            .collect();
+        let mut latencies = latency_histogram();
+        for client in clients {
+            latencies.add(client.await).unwrap();
+        }
+        let report = LoadReport::since(start, frames, latencies);
+        accept.cancel().await;
+        report.with_bytes((frames * frame_size) as u64)
+    })
+}
+
+/// Sends every binary frame back until the client closes the connection.
+async fn echo<S>(mut ws: S)
+where
+    S: Stream<Item = Result<Message, Error>> + Sink<Message, Error = Error> + Unpin,
+{
+    while let Some(Ok(message)) = ws.next().await {
+        if message.is_binary() && ws.send(message).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Sends `frames` binary frames one after another, waiting for each echo.
+async fn ping_pong<S>(mut ws: S, frames: usize, frame_size: usize) -> Histogram<u64>
+where
+    S: Stream<Item = Result<Message, Error>> + Sink<Message, Error = Error> + Unpin,
+{
+    let payload = bytes::Bytes::from(vec![0u8; frame_size]);
+    let mut latencies = latency_histogram();
+    for _ in 0..frames {
+        let start = Instant::now();
+        ws.send(Message::Binary(payload.clone())).await.unwrap();
+        let echoed = ws.next().await.unwrap().unwrap();
+        assert_eq!(echoed.len(), frame_size);
+        record(&mut latencies, start.elapsed());
+    }
+    let _ = ws.close().await;
+    latencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokio_echoes_frames() {
+        assert_eq!(tokio(2, 20, 256).latencies.len(), 20);
+    }
+
+    #[test]
+    fn async_std_echoes_frames() {
+        assert_eq!(async_std(2, 20, 256).latencies.len(), 20);
+    }
+}
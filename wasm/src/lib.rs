@@ -0,0 +1,230 @@
+//! Scenarios of the benchmarks that make sense in a browser, built for
+//! wasm32 and run on the executors a WASM backend has there:
+//! wasm-bindgen-futures, which runs tasks as microtasks of the JavaScript
+//! event loop, and a futures `LocalPool`, which polls them itself until
+//! they are done.
+//!
+//! There are no threads, no sockets and no files, so only spawning, a
+//! message bounced between two tasks, CPU-bound work split into tasks and
+//! sleeps are measured; sleeps only on wasm-bindgen-futures, since a
+//! `LocalPool` cannot wait for the event loop to fire a timer. The clock is
+//! `performance.now()`, which browsers coarsen, to 1 ms in Firefox unless
+//! the page is cross-origin isolated.
+//!
+//! [`run`] returns the reports as a result file, with a header naming the
+//! system as `browser-wasm32/js`, that `merge_results` in the main crate merges with
+//! the files of native runs; `wasm-pack test --headless --firefox wasm --
+//! --nocapture` prints it.
+
+use futures::channel::{mpsc, oneshot};
+use futures::executor::LocalPool;
+use futures::task::LocalSpawnExt;
+use futures::{SinkExt, StreamExt};
+use std::fmt::Write;
+use std::future::Future;
+use std::task::Poll;
+use wasm_bindgen::prelude::*;
+
+/// Number of tasks spawned one after another.
+const SPAWN_TASKS: usize = 10_000;
+
+/// Number of hops of the message between two tasks.
+const PING_PONG_HOPS: usize = 100_000;
+
+/// Number of tasks the CPU-bound work is split into.
+const COMPUTE_TASKS: usize = 64;
+
+/// Numbers summed by every task.
+const COMPUTE_ITEMS: u64 = 100_000;
+
+/// Number of sleeps, and how long each asks for.
+const SLEEPS: usize = 100;
+const SLEEP_MS: u32 = 1;
+
+/// Milliseconds since the page loaded.
+fn now() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .expect("no performance clock")
+        .now()
+}
+
+/// One of the executors, able to spawn tasks and to run a future until it is
+/// done.
+#[derive(Clone, Copy)]
+enum Executor {
+    WasmBindgenFutures,
+    LocalPool,
+}
+
+impl Executor {
+    fn name(self) -> &'static str {
+        match self {
+            Executor::WasmBindgenFutures => "wasm_bindgen_futures",
+            Executor::LocalPool => "local_pool",
+        }
+    }
+
+    /// Runs `scenario` with a spawner of tasks on this executor until it is
+    /// done.
+    async fn run<F, Fut>(self, scenario: F) -> String
+    where
+        F: FnOnce(Spawner) -> Fut,
+        Fut: Future<Output = String>,
+    {
+        match self {
+            Executor::WasmBindgenFutures => scenario(Spawner(None)).await,
+            Executor::LocalPool => {
+                let mut pool = LocalPool::new();
+                let spawner = Spawner(Some(pool.spawner()));
+                pool.run_until(scenario(spawner))
+            }
+        }
+    }
+}
+
+/// Spawns tasks on wasm-bindgen-futures, or on a `LocalPool`.
+#[derive(Clone)]
+struct Spawner(Option<futures::executor::LocalSpawner>);
+
+impl Spawner {
+    fn spawn<T: 'static>(&self, task: impl Future<Output = T> + 'static) -> oneshot::Receiver<T> {
+        let (done, output) = oneshot::channel();
+        let task = async move {
+            let _ = done.send(task.await);
+        };
+        match &self.0 {
+            None => wasm_bindgen_futures::spawn_local(task),
+            Some(pool) => pool.spawn_local(task).unwrap(),
+        }
+        output
+    }
+}
+
+/// Time per operation and operations per second, like the native reports.
+fn cost(operations: usize, millis: f64) -> String {
+    format!(
+        "{:>8.1} ns/op  {:>10.0} ops/s",
+        millis * 1e6 / operations as f64,
+        operations as f64 / millis * 1e3
+    )
+}
+
+/// Spawns trivial tasks, awaiting each before spawning the next.
+async fn spawn(spawner: Spawner) -> String {
+    let start = now();
+    for i in 0..SPAWN_TASKS {
+        assert_eq!(spawner.spawn(async move { i }).await.unwrap(), i);
+    }
+    cost(SPAWN_TASKS, now() - start)
+}
+
+/// Bounces a message between two tasks until it made every hop.
+async fn ping_pong(spawner: Spawner) -> String {
+    let (mut to_first, mut first_inbox) = mpsc::channel::<usize>(1);
+    let (mut to_second, mut second_inbox) = mpsc::channel::<usize>(1);
+    let start = now();
+    let second = spawner.spawn(async move {
+        while let Some(left) = second_inbox.next().await {
+            if left == 0 || to_first.send(left - 1).await.is_err() {
+                break;
+            }
+        }
+    });
+    to_second.send(PING_PONG_HOPS - 1).await.unwrap();
+    while let Some(left) = first_inbox.next().await {
+        if left == 0 || to_second.send(left - 1).await.is_err() {
+            break;
+        }
+    }
+    drop(to_second);
+    second.await.unwrap();
+    format!("{:>10.0} req/s", PING_PONG_HOPS as f64 / (now() - start) * 1e3)
+}
+
+/// Sums numbers in tasks that yield between chunks, so that they take
+/// turns.
+async fn compute(spawner: Spawner) -> String {
+    let start = now();
+    let tasks: Vec<_> = (0..COMPUTE_TASKS as u64)
+        .map(|task| {
+            spawner.spawn(async move {
+                let mut sum = 0u64;
+                for chunk in 0..COMPUTE_ITEMS / 1000 {
+                    for i in chunk * 1000..(chunk + 1) * 1000 {
+                        sum = sum.wrapping_add((task + i) * (task + i));
+                    }
+                    yield_now().await;
+                }
+                sum
+            })
+        })
+        .collect();
+    let mut total = 0u64;
+    for task in tasks {
+        total = total.wrapping_add(task.await.unwrap());
+    }
+    assert_ne!(total, 0);
+    cost(COMPUTE_TASKS * COMPUTE_ITEMS as usize, now() - start)
+}
+
+/// Returns pending once, having woken itself, so that other tasks get a
+/// turn.
+async fn yield_now() {
+    let mut yielded = false;
+    futures::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+/// Sleeps one after another and reports how far they overshoot.
+async fn sleep() -> String {
+    let mut overshoot = Vec::with_capacity(SLEEPS);
+    for _ in 0..SLEEPS {
+        let start = now();
+        gloo_timers::future::TimeoutFuture::new(SLEEP_MS).await;
+        overshoot.push((now() - start - f64::from(SLEEP_MS)).max(0.0));
+    }
+    overshoot.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let at = |quantile: f64| overshoot[((overshoot.len() - 1) as f64 * quantile) as usize];
+    format!(
+        "{:>8} sleeps  overshoot p50 {:.3}ms  p99 {:.3}ms  max {:.3}ms",
+        SLEEPS,
+        at(0.5),
+        at(0.99),
+        at(1.0)
+    )
+}
+
+/// Runs every scenario and returns the reports as a result file.
+#[wasm_bindgen]
+pub async fn run() -> String {
+    let mut results = String::new();
+    for line in [
+        "# os browser",
+        "# arch wasm32",
+        "# cores 1",
+        "# io js",
+        "# note one thread, timed with performance.now(), which browsers coarsen",
+    ] {
+        writeln!(results, "{}", line).unwrap();
+    }
+    for executor in [Executor::WasmBindgenFutures, Executor::LocalPool] {
+        let name = executor.name();
+        let report = executor.run(spawn).await;
+        writeln!(results, "{:<48} {}", format!("spawn_{}/awaited", name), report).unwrap();
+        let report = executor.run(ping_pong).await;
+        writeln!(results, "{:<48} {}", format!("ping_pong_{}/same_worker", name), report).unwrap();
+        let report = executor.run(compute).await;
+        writeln!(results, "{:<48} {}", format!("compute_{}", name), report).unwrap();
+    }
+    let name = format!("sleep_wasm_bindgen_futures/{}", u64::from(SLEEP_MS) * 1_000_000);
+    writeln!(results, "{:<48} {}", name, sleep().await).unwrap();
+    results
+}
@@ -0,0 +1,13 @@
+//! Runs the scenarios in a headless browser and prints their result file,
+//! e.g. `wasm-pack test --headless --firefox wasm -- --nocapture`.
+
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+async fn scenarios() {
+    let results = async_runtimes_cmp_wasm::run().await;
+    assert!(results.lines().any(|line| line.starts_with("spawn_local_pool/awaited")));
+    console_log!("{}", results);
+}